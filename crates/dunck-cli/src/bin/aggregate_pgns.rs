@@ -1,10 +1,7 @@
 const INPUT_DIRECTORY: &str = "data/lichess_elite_db_multi_pgn";
 
 use std::fs;
-use dunck::pgn::{tokenize_pgn, PgnToken};
-use dunck::r#move::Move;
-use dunck::state::State;
-use dunck::utils::Color;
+use dunck_core::pgn::{tokenize_pgn, PgnToken};
 
 fn extract_pgns(multi_pgn_file_content: &str, num_read: &mut usize) -> Vec<String> {
     let mut pgns = Vec::new();
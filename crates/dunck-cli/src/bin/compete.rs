@@ -1,7 +1,7 @@
-use dunck::engine::evaluators::neural::conv_net_evaluator::ConvNetEvaluator;
-use dunck::engine::mcts::mcts::{calc_puct_score, calc_uct_score, MCTS};
-use dunck::engine::evaluators::random_rollout::RolloutEvaluator;
-use dunck::state::State;
+use dunck_nn::conv_net_evaluator::ConvNetEvaluator;
+use dunck_engine::mcts::mcts::{calc_puct_score, calc_uct_score, MCTS};
+use dunck_engine::evaluators::random_rollout::RolloutEvaluator;
+use dunck_core::state::State;
 
 const MAX_GAME_DEPTH: usize = 400;
 
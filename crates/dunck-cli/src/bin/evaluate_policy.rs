@@ -0,0 +1,46 @@
+use std::env;
+use std::str::FromStr;
+use dunck_engine::evaluators::factory::{create_evaluator, EvaluatorConfig};
+use dunck_nn::training_utils::extract_pgns;
+use dunck_engine::policy_evaluation::PolicyMatchReport;
+use dunck_core::pgn::{extract_tags, tokenize_pgn, PgnStateTree};
+
+pub const DEFAULT_PGN_FILE: &str = "data/lichess_elite_db_multi_pgn/accepted.pgn";
+pub const ELO_BUCKET_WIDTH: u32 = 200;
+
+/// Usage: evaluate_policy [pgn file] [model file] [max games]
+/// Reports how often the model's top-1 and top-3 moves match the moves played in the games.
+fn main() {
+    let pgn_file = env::args().nth(1).unwrap_or(DEFAULT_PGN_FILE.to_string());
+    let mut config = EvaluatorConfig::default();
+    if let Some(model_path) = env::args().nth(2) {
+        config.model_path = model_path;
+    }
+    let max_games = env::args().nth(3).map(|max_games| max_games.parse::<usize>().expect("Invalid max games"));
+
+    dunck_nn::register();
+    let loaded = create_evaluator(&config);
+    println!("Using {} evaluator", loaded.active);
+
+    let multi_pgn_file_content = std::fs::read_to_string(&pgn_file).expect("Failed to read PGN file");
+    let pgns = extract_pgns(&multi_pgn_file_content);
+
+    let mut report = PolicyMatchReport::new(ELO_BUCKET_WIDTH);
+    let mut num_games = 0;
+    for pgn in pgns.iter().take(max_games.unwrap_or(usize::MAX)) {
+        let mut tree = match PgnStateTree::from_str(pgn) {
+            Ok(tree) => tree,
+            Err(_) => continue,
+        };
+        tree.tags = tokenize_pgn(pgn).and_then(|tokens| extract_tags(&tokens)).unwrap_or_default();
+
+        report.add_game(loaded.evaluator.as_ref(), &tree);
+        num_games += 1;
+        if num_games % 100 == 0 {
+            println!("Evaluated {} games", num_games);
+        }
+    }
+
+    println!("Evaluated {} games", num_games);
+    print!("{}", report);
+}
@@ -0,0 +1,34 @@
+use std::env;
+use dunck_engine::evaluators::factory::get_default_evaluator_kind;
+use dunck_engine::mcts::mcts::calc_puct_score;
+use dunck_engine::server::EngineServer;
+use dunck_engine::InitOptions;
+
+pub const DEFAULT_ADDRESS: &str = "127.0.0.1:7878";
+pub const EXPLORATION_PARAM: f64 = 2.0;
+/// The iterations of the search run before listening, so that the first request is not slowed down by the model.
+pub const WARMUP_ITERATIONS: usize = 16;
+
+/// Usage: server [address] [evaluator]
+/// The evaluator is given as e.g. `material`, `rollout:300` or `convnet:<path>`.
+/// By default, uses the conv net in model.safetensors if it can be loaded, else the material evaluator.
+/// Without the `neural` feature, uses the piece-square table evaluator.
+fn main() {
+    #[cfg(feature = "neural")]
+    dunck_nn::register();
+    let address = env::args().nth(1).unwrap_or(DEFAULT_ADDRESS.to_string());
+
+    let kind = match env::args().nth(2) {
+        Some(kind) => kind.parse().expect("Invalid evaluator"),
+        None => get_default_evaluator_kind()
+    };
+    let initialized = dunck_engine::init(&InitOptions::default().with_evaluator(kind).with_warmup_search(WARMUP_ITERATIONS))
+        .expect("Failed to create evaluator");
+    let loaded = initialized.evaluator.unwrap();
+    println!("Using {} evaluator, ready in {:?}", loaded.active, initialized.elapsed);
+
+    let mut server = EngineServer::new(EXPLORATION_PARAM, loaded.evaluator.as_ref(), &calc_puct_score)
+        .with_evaluator_name(&loaded.active.to_string());
+    println!("Listening on {}", address);
+    server.serve(address.as_str()).expect("Server failed");
+}
@@ -3,13 +3,13 @@
 // use tch::{nn, Tensor};
 // use rand::seq::SliceRandom;
 // use std::time::Instant;
-// use dunck::engine::conv_net_evaluator::constants::{NUM_OUTPUT_POLICY_MOVES, NUM_TARGET_SQUARE_POSSIBILITIES};
-// use dunck::engine::conv_net_evaluator::ConvNetEvaluator;
-// use dunck::engine::conv_net_evaluator::utils::{get_policy_index_for_move, state_to_tensor};
-// use dunck::engine::mcts::{calc_puct_score, MCTS};
-// use dunck::engine::evaluation::Evaluation;
-// use dunck::r#move::MoveFlag;
-// use dunck::state::State;
+// use dunck_engine::conv_net_evaluator::constants::{NUM_OUTPUT_POLICY_MOVES, NUM_TARGET_SQUARE_POSSIBILITIES};
+// use dunck_engine::conv_net_evaluator::ConvNetEvaluator;
+// use dunck_engine::conv_net_evaluator::utils::{get_policy_index_for_move, state_to_tensor};
+// use dunck_engine::mcts::{calc_puct_score, MCTS};
+// use dunck_engine::evaluation::Evaluation;
+// use dunck_core::r#move::MoveFlag;
+// use dunck_core::state::State;
 // 
 // pub const EXPLORATION_PARAM: f64 = 1.5;
 // pub const NUM_RESIDUAL_BLOCKS: usize = 4;
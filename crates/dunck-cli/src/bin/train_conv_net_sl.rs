@@ -1,9 +1,9 @@
-use dunck::engine::evaluators::neural::conv_net_evaluator::ConvNetEvaluator;
+use dunck_nn::conv_net_evaluator::ConvNetEvaluator;
 use std::fs::exists;
 use tch::nn::OptimizerConfig;
 use tch::{nn, Tensor};
-use dunck::engine::evaluators::neural::training::{compute_loss, train_batch};
-use dunck::engine::evaluators::neural::training_utils::{extract_pgns, get_labeled_random_batch_from_pgns};
+use dunck_nn::training::{compute_loss, train_batch};
+use dunck_nn::training_utils::{extract_pgns, get_labeled_random_batch_from_pgns};
 
 pub const MULTI_PGN_FILE: &str = "data/lichess_elite_db_multi_pgn/accepted.pgn";
 pub const MODEL_FILE: &str = "model.safetensors";
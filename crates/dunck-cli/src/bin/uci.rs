@@ -0,0 +1,27 @@
+use std::env;
+use std::io;
+use std::io::BufRead;
+use dunck_engine::evaluators::factory::get_default_evaluator_kind;
+use dunck_engine::mcts::mcts::calc_puct_score;
+use dunck_engine::uci::uci_engine::UciEngine;
+use dunck_engine::InitOptions;
+
+pub const EXPLORATION_PARAM: f64 = 2.0;
+
+/// Usage: uci [evaluator]
+/// Speaks UCI on stdin and stdout. The evaluator is given as e.g. `material`, `rollout:300` or `convnet:<path>`,
+/// and can be changed with the EvalBackend option. By default, uses the conv net in model.safetensors,
+/// or the piece-square table evaluator without the `neural` feature.
+fn main() {
+    #[cfg(feature = "neural")]
+    dunck_nn::register();
+    let evaluator_kind = match env::args().nth(1) {
+        Some(kind) => kind.parse().expect("Invalid evaluator"),
+        None => get_default_evaluator_kind()
+    };
+
+    // the search worker loads the evaluator itself
+    dunck_engine::init(&InitOptions::default()).unwrap();
+    let mut engine = UciEngine::new(evaluator_kind, EXPLORATION_PARAM, &calc_puct_score, Box::new(io::stdout()));
+    engine.run(io::stdin().lock().lines().map_while(Result::ok));
+}
@@ -0,0 +1,723 @@
+//! The dunck command line. Every subcommand except `play` runs without prompts, so it can be scripted.
+
+#[cfg(feature = "neural")]
+use std::fs::exists;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::PathBuf;
+use std::process;
+#[cfg(feature = "neural")]
+use std::sync::Arc;
+use clap::{Parser, Subcommand};
+use indexmap::IndexMap;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+#[cfg(feature = "neural")]
+use tch::nn;
+#[cfg(feature = "neural")]
+use tch::nn::OptimizerConfig;
+use dunck_engine::evaluators::factory::{create_evaluator_of_kind, get_default_evaluator_kind, ActiveEvaluator, EvaluatorConfig, EvaluatorKind, LoadedEvaluator};
+#[cfg(feature = "neural")]
+use dunck_nn::conv_net_evaluator::ConvNetEvaluator;
+#[cfg(feature = "neural")]
+use dunck_nn::training::{compute_loss, train_batch};
+#[cfg(feature = "neural")]
+use dunck_nn::training_utils::get_labeled_batch_from_pgn_reader;
+#[cfg(feature = "neural")]
+use dunck_engine::engine_context::{EngineContext, SharedEvaluator};
+#[cfg(feature = "neural")]
+use dunck_engine::evaluation::Evaluation;
+#[cfg(feature = "neural")]
+use dunck_engine::evaluators::neural::lr_schedule::LrSchedule;
+#[cfg(feature = "neural")]
+use dunck_engine::gating::{run_gating, GatingConfig};
+use dunck_engine::gating::{play_arena_game, ArenaConfig};
+use dunck_engine::manifest::EngineManifest;
+use dunck_engine::mcts::explanation::format_line;
+use dunck_engine::mcts::mcts::{calc_puct_score, MCTS};
+use dunck_engine::self_check::{SelfCheck, DEFAULT_SELF_CHECK_PERFT_NODES};
+#[cfg(feature = "neural")]
+use dunck_engine::selfplay::read_selfplay_games;
+use dunck_engine::uci::uci_engine::{value_to_centipawns, UciEngine};
+use dunck_core::game_record::write_game_records;
+use dunck_engine::handicap::Handicap;
+use dunck_core::perft::{perft, perft_divide, perft_divide_hashed, perft_hashed, perft_unique, PerftTable};
+use dunck_core::pgn::{PgnReader, PgnStats, DEFAULT_NUM_POPULARITY_PLIES};
+use dunck_core::r#move::Move;
+use dunck_engine::referee::{run_refereed_match, DrawRule, MatchPlayer, RefereeConfig, ResignRule, UciProcess};
+use dunck_core::state::{MoveHistory, State, INITIAL_FEN};
+use dunck_engine::time_control::TimeControl;
+use dunck_core::utils::PieceLocale;
+
+pub const EXPLORATION_PARAM: f64 = 2.0;
+/// Gating games that reach this many halfmoves are recorded as draws.
+#[cfg(feature = "neural")]
+const MAX_GATING_GAME_DEPTH: usize = 300;
+
+#[derive(Parser)]
+#[command(name = "dunck", version, about = "A chess engine based on Monte Carlo tree search")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Plays interactively against the engine, with moves entered in SAN.
+    Play {
+        /// The starting position, as a FEN or `startpos`.
+        #[arg(long, default_value = "startpos")]
+        fen: String,
+        /// The number of search iterations for each engine move.
+        #[arg(long, default_value_t = 800)]
+        iterations: usize,
+        /// The evaluator, e.g. `material`, `rollout:300` or `convnet:<path>`. Defaults to the conv net, if it loads,
+        /// or to the piece-square tables without the `neural` feature.
+        #[arg(long)]
+        evaluator: Option<EvaluatorKind>,
+        /// The piece letters of the board and moves, e.g. `de` or `figurine`. Moves can be entered in either
+        /// these letters or English ones.
+        #[arg(long)]
+        locale: Option<PieceLocale>,
+    },
+    /// Counts the leaf nodes of the move tree of a position.
+    /// With `--divide`, the output matches Stockfish's `go perft`, so the two can be diffed to find movegen bugs.
+    Perft {
+        /// The position, as a FEN or `startpos`.
+        fen: String,
+        depth: u32,
+        /// Moves in UCI notation to play from the position first, to narrow down a mismatch.
+        #[arg(long, num_args = 1..)]
+        moves: Vec<String>,
+        /// Also prints the count after each legal move, in UCI notation.
+        #[arg(long)]
+        divide: bool,
+        /// The size of the transposition table in megabytes, or 0 to search without one.
+        #[arg(long, default_value_t = 0)]
+        hash: usize,
+        /// Also prints the number of distinct positions at the last ply, to study transpositions.
+        #[arg(long, conflicts_with_all = ["divide", "hash"])]
+        unique: bool,
+    },
+    /// Searches a position and prints the best move, its evaluation and the principal variation.
+    Analyze {
+        /// The position, as a FEN or `startpos`.
+        fen: String,
+        #[arg(long, default_value_t = 800)]
+        iterations: usize,
+        #[arg(long)]
+        evaluator: Option<EvaluatorKind>,
+    },
+    /// Plays games of the engine against itself.
+    Selfplay {
+        #[arg(long, default_value_t = 1)]
+        games: usize,
+        /// The number of search iterations for each move, unless `--tc` is given.
+        #[arg(long, default_value_t = 200)]
+        iterations: usize,
+        /// Games that reach this many halfmoves are recorded as draws.
+        #[arg(long, default_value_t = 300)]
+        max_moves: usize,
+        /// The number of random halfmoves played before each game, so that games differ.
+        #[arg(long, default_value_t = 4)]
+        random_plies: usize,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+        /// Where to write the games, in the binary game record format unless `--pgn` is given.
+        /// PGN is written to stdout if no output is given.
+        #[arg(long)]
+        output: Option<PathBuf>,
+        #[arg(long)]
+        pgn: bool,
+        #[arg(long)]
+        evaluator: Option<EvaluatorKind>,
+        /// Odds given in every game, applied before the random halfmoves, e.g. `giver=black,material=knight,time=0.5`.
+        #[arg(long)]
+        handicap: Option<Handicap>,        /// Plays on simulated clocks with this time control, e.g. `1+0.1`, letting the time manager allocate
+        /// each move's thinking time instead of searching a fixed number of iterations.
+        #[arg(long)]
+        tc: Option<TimeControl>,
+    },
+    /// Trains the conv net for a number of epochs over a PGN file or a self-play replay buffer,
+    /// optionally gating checkpoints against the best network so far.
+    #[cfg(feature = "neural")]
+    Train {
+        /// A file with several PGN games, e.g. a database dump, or a replay buffer written by self-play with `--replay`.
+        /// PGN files are streamed, so they may be larger than memory.
+        data: PathBuf,
+        /// Reads `data` as a self-play replay buffer, training on every position with its search policy.
+        #[arg(long)]
+        replay: bool,
+        /// The model file, which is trained further if it exists and saved at every checkpoint,
+        /// along with a manifest of the training run in `<model>.manifest.json`.
+        #[arg(long, default_value = "model.safetensors")]
+        model: String,
+        #[arg(long, default_value_t = 1)]
+        epochs: usize,
+        #[arg(long, default_value_t = 256)]
+        batch_size: usize,
+        #[arg(long, default_value_t = 0.0005)]
+        learning_rate: f64,
+        /// How the learning rate changes with the number of steps: `constant`, `cosine:<steps>[:<min fraction>]` or `step:<steps>:<gamma>`.
+        #[arg(long, default_value_t = LrSchedule::Constant)]
+        lr_schedule: LrSchedule,
+        /// Saves the model and reports the validation loss every this many steps, as well as after every epoch.
+        #[arg(long, default_value_t = 1000)]
+        checkpoint_every: usize,
+        /// Plays a gating match between the model and the best network every this many steps, or never if 0.
+        #[arg(long, default_value_t = 0)]
+        gating_every: usize,
+        #[arg(long, default_value_t = 20)]
+        gating_games: usize,
+        /// The number of MCTS iterations per move in gating matches.
+        #[arg(long, default_value_t = 100)]
+        gating_iterations: usize,
+        /// The minimum score, as a fraction of points, the model needs against the best network to replace it.
+        #[arg(long, default_value_t = 0.55)]
+        promotion_threshold: f64,
+        /// The best network so far, which is initialized from the model if it does not exist.
+        #[arg(long, default_value = "best.safetensors")]
+        best_model: String,
+        /// The league history every gating match is appended to.
+        #[arg(long, default_value = "league.tsv")]
+        league: String,
+    },
+    /// Collects statistics over the games in a PGN file: results by opening, game length, material balance and move popularity.
+    Stats {
+        /// A file with several PGN games. It is streamed, so it may be larger than memory.
+        pgn: PathBuf,
+        /// The number of halfmoves whose moves are counted for move popularity.
+        #[arg(long, default_value_t = DEFAULT_NUM_POPULARITY_PLIES)]
+        plies: usize,
+        /// A directory to write `results_by_eco.csv`, `material_by_ply.csv` and `move_popularity.csv` to.
+        /// The statistics are written to stdout as JSON if none is given.
+        #[arg(long)]
+        csv: Option<PathBuf>,
+    },
+    /// Referees a match between two external UCI engines, writing the games as PGN.
+    Referee {
+        /// The command starting the first engine, e.g. `stockfish` or `./engine --threads 2`.
+        engine1: String,
+        /// The command starting the second engine.
+        engine2: String,
+        /// The number of games, with the engines alternating colors.
+        #[arg(long, default_value_t = 2)]
+        games: usize,
+        /// The time control, as minutes plus seconds per move, e.g. `5+3`, or in stages, e.g. `40/90+30:30+30`.
+        #[arg(long, default_value = "1+0.1")]
+        tc: TimeControl,
+        /// The starting position of every game, as a FEN or `startpos`.
+        #[arg(long, default_value = "startpos")]
+        fen: String,
+        /// Games that reach this many halfmoves are adjudicated as draws.
+        #[arg(long, default_value_t = 400)]
+        max_plies: usize,
+        /// Adjudicates games as won once both engines agree on a score of at least this many centipawns.
+        #[arg(long)]
+        resign_score: Option<i32>,
+        /// Adjudicates games as drawn once both engines agree on a score within this many centipawns of zero.
+        #[arg(long)]
+        draw_score: Option<i32>,
+        /// Where to write the games. PGN is written to stdout if no output is given.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Speaks UCI on stdin and stdout.
+    Uci {
+        #[arg(long)]
+        evaluator: Option<EvaluatorKind>,
+    },
+    /// Checks move generation, the attack tables, hashing and the evaluator, and prints a report to attach to bug reports.
+    SelfCheck {
+        #[arg(long)]
+        evaluator: Option<EvaluatorKind>,
+        /// Perft counts up to this many nodes are checked.
+        #[arg(long, default_value_t = DEFAULT_SELF_CHECK_PERFT_NODES)]
+        perft_nodes: u64,
+        /// Prints the report as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+fn main() {
+    #[cfg(feature = "neural")]
+    dunck_nn::register();
+    if let Err(e) = run(Cli::parse().command) {
+        eprintln!("Error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn run(command: Command) -> Result<(), String> {
+    match command {
+        Command::Play { fen, iterations, evaluator, locale } => play(parse_state(&fen)?, iterations, load_evaluator(evaluator)?, locale),
+        Command::Perft { fen, depth, moves, divide, hash, unique } => run_perft(parse_state(&fen)?, &moves, depth, divide, hash, unique),
+        Command::Analyze { fen, iterations, evaluator } => analyze(parse_state(&fen)?, iterations, load_evaluator(evaluator)?),
+        Command::Selfplay { games, iterations, max_moves, random_plies, seed, output, pgn, evaluator, handicap, tc } => {
+            if output.is_none() && !pgn {
+                return Err("Game records are binary, so give an output file or use --pgn".to_string());
+            }
+            let config = ArenaConfig {
+                num_games: games,
+                iterations_per_move: iterations,
+                max_game_depth: max_moves,
+                exploration_param: EXPLORATION_PARAM,
+                calc_node_score: &calc_puct_score,
+                tablebase: None,
+                handicap,
+                time_control: tc,
+            };
+            selfplay(&config, random_plies, seed, output, pgn, load_evaluator(evaluator)?)
+        },
+        #[cfg(feature = "neural")]
+        Command::Train {
+            data, replay, model, epochs, batch_size, learning_rate, lr_schedule, checkpoint_every,
+            gating_every, gating_games, gating_iterations, promotion_threshold, best_model, league
+        } => {
+            let gating = (gating_every > 0).then(|| GatingConfig {
+                arena: ArenaConfig {
+                    num_games: gating_games,
+                    iterations_per_move: gating_iterations,
+                    max_game_depth: MAX_GATING_GAME_DEPTH,
+                    exploration_param: EXPLORATION_PARAM,
+                    calc_node_score: &calc_puct_score,
+                    tablebase: None,
+                    handicap: None,
+                    time_control: None,
+                },
+                promotion_threshold,
+                best_model_path: best_model,
+                history_path: league,
+            });
+            let config = TrainConfig { epochs, batch_size, learning_rate, lr_schedule, checkpoint_every, gating_every, gating };
+            train(&data, replay, &model, &config)
+        },
+        Command::Stats { pgn, plies, csv } => stats(&pgn, plies, csv),
+        Command::Referee { engine1, engine2, games, tc, fen, max_plies, resign_score, draw_score, output } => {
+            let config = RefereeConfig {
+                time_control: tc,
+                max_plies,
+                resign_rule: resign_score.map(|score| ResignRule { score, ..Default::default() }),
+                draw_rule: draw_score.map(|score| DrawRule { score, ..Default::default() }),
+                ..Default::default()
+            };
+            referee(&engine1, &engine2, games, parse_state(&fen)?, &config, output)
+        },
+        Command::Uci { evaluator } => {
+            let evaluator_kind = evaluator.unwrap_or_else(get_default_evaluator_kind);
+            let mut engine = UciEngine::new(evaluator_kind, EXPLORATION_PARAM, &calc_puct_score, Box::new(io::stdout()));
+            engine.run(io::stdin().lock().lines().map_while(Result::ok));
+            Ok(())
+        },
+        Command::SelfCheck { evaluator, perft_nodes, json } => self_check(load_evaluator(evaluator)?, perft_nodes, json),
+    }
+}
+
+fn parse_state(fen: &str) -> Result<State, String> {
+    let fen = if fen == "startpos" { INITIAL_FEN } else { fen };
+    State::from_fen(fen).map_err(|e| format!("Invalid FEN {}: {}", fen, e))
+}
+
+fn run_perft(mut state: State, moves: &[String], depth: u32, divide: bool, hash: usize, unique: bool) -> Result<(), String> {
+    for uci in moves {
+        let mv = Move::from_uci(&state, uci).map_err(|e| e.to_string())?;
+        state.make_move(mv);
+    }
+    if unique {
+        let counts = perft_unique(&state, depth);
+        println!("Nodes: {}", counts.nodes);
+        println!("Unique positions: {}", counts.unique_positions);
+        return Ok(());
+    }
+    let mut table = (hash > 0).then(|| PerftTable::with_megabytes(hash));
+    if !divide {
+        let num_nodes = match &mut table {
+            Some(table) => perft_hashed(&state, depth, table),
+            None => perft(&state, depth)
+        };
+        println!("{}", num_nodes);
+        return Ok(());
+    }
+
+    let divided = match &mut table {
+        Some(table) => perft_divide_hashed(&state, depth, table),
+        None => perft_divide(&state, depth)
+    };
+    for (mv, num_nodes) in &divided {
+        println!("{}: {}", mv.to_uci(), num_nodes);
+    }
+    println!();
+    println!("Nodes searched: {}", divided.iter().map(|(_, num_nodes)| num_nodes).sum::<u64>());
+    Ok(())
+}
+
+fn load_evaluator(kind: Option<EvaluatorKind>) -> Result<LoadedEvaluator, String> {
+    let kind = kind.unwrap_or_else(get_default_evaluator_kind);
+    let loaded = create_evaluator_of_kind(&kind, &EvaluatorConfig::default())?;
+    eprintln!("Using {} evaluator", loaded.active);
+    Ok(loaded)
+}
+
+/// Plays interactively from `state`. Without a `locale`, the board is shown with Unicode symbols and moves in English SAN.
+fn play(state: State, iterations: usize, loaded: LoadedEvaluator, locale: Option<PieceLocale>) -> Result<(), String> {
+    let mut history = MoveHistory::new(state);
+    let stdin = io::stdin();
+    let read_line = || {
+        let mut input = String::new();
+        stdin.read_line(&mut input).map_err(|e| e.to_string())?;
+        Ok::<String, String>(input.trim().to_string())
+    };
+
+    loop {
+        let state = history.get_state().clone();
+        println!();
+        println!("{}", state.to_fen());
+        match locale {
+            Some(locale) => println!("{}", state.board.to_string_localized(locale)),
+            None => state.board.print()
+        }
+        let locale = locale.unwrap_or_default();
+        let moves = state.calc_legal_moves();
+        let move_sans = state.sans_for(&moves);
+        let localized_sans: Vec<String> = move_sans.iter().map(|san| locale.localize_san(san)).collect();
+        println!("Moves: {}", localized_sans.join(", "));
+        println!("Enter move (q|QUIT to quit, n|NEW for new position from fen, b|BEST for best move according to engine, u|UNDO to take back a move, r|REDO to play it again): ");
+        let input = read_line()?;
+        match input.as_str() {
+            "q" | "QUIT" | "" => return Ok(()),
+            "n" | "NEW" => {
+                loop {
+                    println!("Enter fen (q to cancel): ");
+                    let input = read_line()?;
+                    if input == "q" {
+                        break;
+                    }
+                    match parse_state(&input) {
+                        Ok(new_state) => {
+                            history = MoveHistory::new(new_state);
+                            break;
+                        },
+                        Err(e) => println!("{}", e)
+                    }
+                }
+            },
+            "b" | "BEST" => {
+                let mut mcts = MCTS::new(state.clone(), EXPLORATION_PARAM, loaded.evaluator.as_ref(), &calc_puct_score, false);
+                mcts.run(iterations);
+                if let Some(best_child) = mcts.get_best_child_by_visits() {
+                    let best_move = best_child.borrow().mv.unwrap();
+                    let new_state = best_child.borrow().state_after_move.clone();
+                    println!("Playing best move: {}", locale.localize_san(&best_move.to_san(&state, &new_state, &moves)));
+                    history.make_move(best_move)?;
+                }
+            },
+            "u" | "UNDO" => {
+                if history.undo().is_none() {
+                    println!("No move to take back");
+                }
+            },
+            "r" | "REDO" => {
+                if history.redo().is_none() {
+                    println!("No move to redo");
+                }
+            },
+            // the locale's letters take precedence, e.g. R is a king in French and Spanish
+            _ => match move_sans.iter().position(|san| *san == locale.delocalize_san(&input)).or_else(|| move_sans.iter().position(|san| *san == input)) {
+                Some(index) => history.make_move(moves[index])?,
+                None => println!("Invalid move")
+            }
+        }
+    }
+}
+
+fn analyze(state: State, iterations: usize, loaded: LoadedEvaluator) -> Result<(), String> {
+    if state.calc_legal_moves().is_empty() {
+        return Err("The position has no legal moves".to_string());
+    }
+    let mut mcts = MCTS::new(state.clone(), EXPLORATION_PARAM, loaded.evaluator.as_ref(), &calc_puct_score, false);
+    mcts.run(iterations);
+
+    let best_child = mcts.get_best_child_by_visits().ok_or("The search did not expand the position")?;
+    let best_child = best_child.borrow();
+    let mut pv = vec![best_child.mv.unwrap()];
+    pv.extend(best_child.get_principal_variation());
+    // the child's value is from the perspective of the side to move
+    let value = if best_child.visits > 0 { best_child.value / best_child.visits as f64 } else { 0. };
+    println!("bestmove {}", pv[0].to_uci());
+    println!("value {:.3} (cp {})", value, value_to_centipawns(value));
+    println!("visits {}/{}", best_child.visits, mcts.root.borrow().visits);
+    println!("pv {}", format_line(&state, &pv));
+    Ok(())
+}
+
+fn selfplay(
+    config: &ArenaConfig,
+    random_plies: usize,
+    seed: u64,
+    output: Option<PathBuf>,
+    pgn: bool,
+    loaded: LoadedEvaluator
+) -> Result<(), String> {
+    let start_state = match &config.handicap {
+        Some(handicap) => handicap.apply(&State::initial()).map_err(|e| e.to_string())?,
+        None => State::initial()
+    };
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut records = Vec::with_capacity(config.num_games);
+    for game_index in 0..config.num_games {
+        let mut initial_state = start_state.clone();
+        for _ in 0..random_plies {
+            match initial_state.calc_legal_moves().choose(&mut rng) {
+                Some(mv) => initial_state.make_move(*mv),
+                None => break
+            }
+        }
+        let record = play_arena_game(initial_state, loaded.evaluator.as_ref(), loaded.evaluator.as_ref(), config);
+        eprintln!("Game {}/{}: {} moves, result {}", game_index + 1, config.num_games, record.moves.len(), record.result);
+        records.push(record);
+    }
+
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?)),
+        None => Box::new(io::stdout())
+    };
+    if pgn {
+        let mut tags = IndexMap::new();
+        create_manifest(&loaded, config)?.add_to_pgn_tags(&mut tags);
+        for record in &records {
+            writeln!(writer, "{}\n", record.to_pgn_with_tags(&tags, None)?).map_err(|e| e.to_string())?;
+        }
+    } else {
+        write_game_records(&mut writer, &records).map_err(|e| e.to_string())?;
+    }
+    writer.flush().map_err(|e| e.to_string())
+}
+
+/// Returns the manifest of self-play games with `config`, including the model if the evaluator uses one.
+fn create_manifest(loaded: &LoadedEvaluator, config: &ArenaConfig) -> Result<EngineManifest, String> {
+    let mut manifest = EngineManifest::new()
+        .with_parameter("evaluator", &loaded.active)
+        .with_parameter("exploration_param", config.exploration_param)
+        .with_parameter("iterations_per_move", config.iterations_per_move)
+        .with_parameter("max_game_depth", config.max_game_depth);
+    if let Some(time_control) = &config.time_control {
+        manifest = manifest.with_parameter("time_control", time_control);
+    }
+    if let Some(handicap) = &config.handicap {
+        manifest = manifest.with_parameter("handicap", handicap);
+    }
+    if let ActiveEvaluator::ConvNet { model_path } = &loaded.active {
+        manifest = manifest.with_model(model_path).map_err(|e| format!("Failed to read {}: {}", model_path, e))?;
+    }
+    Ok(manifest)
+}
+
+fn referee(engine1: &str, engine2: &str, games: usize, initial_state: State, config: &RefereeConfig, output: Option<PathBuf>) -> Result<(), String> {
+    let mut first = UciProcess::start(engine1).map_err(|e| e.to_string())?;
+    let mut second = UciProcess::start(engine2).map_err(|e| e.to_string())?;
+    let event = format!("{} vs {}", first.get_name(), second.get_name());
+    eprintln!("{}, {} games at {}", event, games, config.time_control);
+
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(&path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?)),
+        None => Box::new(io::stdout())
+    };
+    // games are written as they finish, so that a long match can be followed and is not lost if interrupted
+    let mut write_result = Ok(());
+    let result = run_refereed_match(&mut first, &mut second, &[initial_state], games, config, |game_index, game| {
+        eprintln!("Game {}/{}: {} - {}, result {} ({})", game_index + 1, games, game.white, game.black, game.record.result, game.end);
+        if write_result.is_ok() {
+            write_result = game.to_pgn(&event, game_index + 1)
+                .and_then(|pgn| writeln!(writer, "{}\n", pgn).and_then(|_| writer.flush()).map_err(|e| e.to_string()));
+        }
+    });
+    write_result?;
+    eprintln!("Score of {}: +{} ={} -{} ({:.3})", event, result.wins, result.draws, result.losses, result.get_score());
+    Ok(())
+}
+
+fn stats(pgn: &PathBuf, plies: usize, csv: Option<PathBuf>) -> Result<(), String> {
+    let file = File::open(pgn).map_err(|e| format!("Failed to read {}: {}", pgn.display(), e))?;
+    let stats = PgnStats::from_reader(&mut PgnReader::new(BufReader::new(file)), plies)
+        .map_err(|e| format!("Failed to read {}: {}", pgn.display(), e))?;
+    eprintln!("Read {} games, skipped {} that failed to parse", stats.num_games, stats.num_unparsable_games);
+    match csv {
+        Some(directory) => {
+            std::fs::create_dir_all(&directory).map_err(|e| format!("Failed to create {}: {}", directory.display(), e))?;
+            for (file_name, table) in [
+                ("results_by_eco.csv", stats.results_by_eco_to_csv()),
+                ("material_by_ply.csv", stats.material_by_ply_to_csv()),
+                ("move_popularity.csv", stats.move_popularity_to_csv()),
+            ] {
+                let path = directory.join(file_name);
+                std::fs::write(&path, table).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            }
+        },
+        None => println!("{}", stats.to_json())
+    }
+    Ok(())
+}
+
+fn self_check(loaded: LoadedEvaluator, perft_nodes: u64, json: bool) -> Result<(), String> {
+    let manifest = EngineManifest::new().with_parameter("evaluator", &loaded.active);
+    let report = SelfCheck::default()
+        .with_evaluator(loaded.evaluator.as_ref())
+        .with_max_perft_nodes(perft_nodes)
+        .with_manifest(manifest)
+        .run();
+    if json {
+        println!("{}", report.to_json());
+    } else {
+        print!("{}", report);
+    }
+    if !report.is_ok() {
+        return Err(format!("{} checks failed", report.get_failures().count()));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "neural")]
+struct TrainConfig {
+    epochs: usize,
+    batch_size: usize,
+    learning_rate: f64,
+    lr_schedule: LrSchedule,
+    checkpoint_every: usize,
+    gating_every: usize,
+    gating: Option<GatingConfig>,
+}
+
+/// Yields the training batches of one epoch, either streamed from a PGN file or shuffled from a replay buffer.
+#[cfg(feature = "neural")]
+enum TrainingData {
+    Pgn(PgnReader<BufReader<File>>),
+    Replay(std::vec::IntoIter<Vec<(State, Evaluation)>>),
+}
+
+#[cfg(feature = "neural")]
+fn open_pgn(path: &PathBuf) -> Result<PgnReader<BufReader<File>>, String> {
+    File::open(path)
+        .map(|file| PgnReader::new(BufReader::new(file)))
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+#[cfg(feature = "neural")]
+fn read_replay_buffer(path: &PathBuf) -> Result<Vec<(State, Evaluation)>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let games = read_selfplay_games(&mut BufReader::new(file)).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut samples = Vec::new();
+    for (i, game) in games.iter().enumerate() {
+        samples.extend(game.to_training_samples().map_err(|e| format!("Invalid game {} in {}: {}", i + 1, path.display(), e))?);
+    }
+    Ok(samples)
+}
+
+#[cfg(feature = "neural")]
+fn train(data: &PathBuf, replay: bool, model: &str, config: &TrainConfig) -> Result<(), String> {
+    let net_config = EvaluatorConfig::default();
+    let mut rng = rand::thread_rng();
+
+    let mut evaluator = ConvNetEvaluator::new(net_config.num_residual_blocks, net_config.num_filters);
+    if exists(model).map_err(|e| e.to_string())? {
+        evaluator.model.load(model).map_err(|e| format!("Failed to load {}: {}", model, e))?;
+    }
+    if let Some(gating) = &config.gating {
+        if !exists(&gating.best_model_path).map_err(|e| e.to_string())? {
+            evaluator.model.save(&gating.best_model_path).map_err(|e| format!("Failed to save {}: {}", gating.best_model_path, e))?;
+        }
+    }
+    let mut optimizer = nn::Adam::default().build(&evaluator.model.vs, config.learning_rate).map_err(|e| e.to_string())?;
+
+    // the best network is loaded once, and swapped for the candidate whenever the candidate is promoted
+    let load_best = |path: &str| -> Result<SharedEvaluator, String> {
+        let mut best = ConvNetEvaluator::new(net_config.num_residual_blocks, net_config.num_filters);
+        best.model.load(path).map_err(|e| format!("Failed to load {}: {}", path, e))?;
+        Ok(Arc::new(best))
+    };
+    let best = config.gating.as_ref().map(|gating| load_best(&gating.best_model_path).map(EngineContext::new)).transpose()?;
+
+    // replay buffers fit in memory, so validation uses a random batch held out from training;
+    // PGN files are streamed rather than loaded, so validation uses the first games, which are skipped in every epoch
+    let mut replay_samples = if replay { read_replay_buffer(data)? } else { Vec::new() };
+    let validation_data: Vec<(State, Evaluation)> = if replay {
+        replay_samples.shuffle(&mut rng);
+        let num_validation_samples = config.batch_size.min(replay_samples.len() / 10);
+        replay_samples.drain(..num_validation_samples).collect()
+    } else {
+        get_labeled_batch_from_pgn_reader(&mut open_pgn(data)?, config.batch_size, &mut rng)
+    };
+    if validation_data.is_empty() {
+        return Err(format!("No training examples in {}", data.display()));
+    }
+
+    let save_checkpoint = |evaluator: &ConvNetEvaluator, epoch: usize, step: usize| -> Result<(), String> {
+        let loss = compute_loss(&evaluator.model, &validation_data);
+        println!(
+            "Epoch {}/{}, step {}: validation loss (Policy: {:.7}, Value: {:.7}, Total: {:.7})",
+            epoch + 1, config.epochs, step, loss.policy_loss, loss.value_loss, loss.total_loss
+        );
+        evaluator.model.save(model).map_err(|e| format!("Failed to save {}: {}", model, e))?;
+        EngineManifest::new()
+            .with_model(model).map_err(|e| format!("Failed to read {}: {}", model, e))?
+            .with_parameter("training_data", data.display())
+            .with_parameter("learning_rate", config.learning_rate)
+            .with_parameter("lr_schedule", config.lr_schedule)
+            .with_parameter("batch_size", config.batch_size)
+            .with_parameter("epochs", epoch + 1)
+            .with_parameter("steps", step)
+            .save(format!("{}.manifest.json", model))
+            .map_err(|e| format!("Failed to save the manifest of {}: {}", model, e))
+    };
+
+    let mut step = 0;
+    for epoch in 0..config.epochs {
+        let mut batches = if replay {
+            replay_samples.shuffle(&mut rng);
+            let batches: Vec<Vec<(State, Evaluation)>> = replay_samples.chunks(config.batch_size).map(|batch| batch.to_vec()).collect();
+            TrainingData::Replay(batches.into_iter())
+        } else {
+            let mut games = open_pgn(data)?;
+            get_labeled_batch_from_pgn_reader(&mut games, validation_data.len(), &mut rng);
+            TrainingData::Pgn(games)
+        };
+
+        loop {
+            let training_data = match &mut batches {
+                TrainingData::Pgn(games) => get_labeled_batch_from_pgn_reader(games, config.batch_size, &mut rng),
+                TrainingData::Replay(batches) => batches.next().unwrap_or_default(),
+            };
+            if training_data.is_empty() {
+                break;
+            }
+            optimizer.set_lr(config.lr_schedule.get_learning_rate(config.learning_rate, step));
+            train_batch(&evaluator.model, &mut optimizer, &training_data);
+            step += 1;
+
+            let is_gating_step = config.gating_every > 0 && step % config.gating_every == 0;
+            if step % config.checkpoint_every.max(1) == 0 || is_gating_step {
+                save_checkpoint(&evaluator, epoch, step)?;
+            }
+            if let (Some(gating), Some(best)) = (config.gating.as_ref().filter(|_| is_gating_step), &best) {
+                let entry = run_gating(model, &evaluator, best, gating)
+                    .map_err(|e| format!("Failed to record the gating match of {}: {}", model, e))?;
+                if entry.promoted {
+                    best.swap_evaluator(load_best(&gating.best_model_path)?);
+                }
+                println!(
+                    "Gating at step {}: +{} ={} -{}, score {:.3}, {}",
+                    step, entry.result.wins, entry.result.draws, entry.result.losses, entry.result.get_score(),
+                    if entry.promoted {
+                        format!("promoted to {} (best network generation {})", gating.best_model_path, best.get_generation())
+                    } else {
+                        "not promoted".to_string()
+                    }
+                );
+            }
+        }
+        save_checkpoint(&evaluator, epoch, step)?;
+    }
+    Ok(())
+}
@@ -1,6 +1,6 @@
 //! Magic bitboard generation and attack calculation for sliding pieces
 
-use crate::utils::{get_bit_combinations_iter, Bitboard};
+use crate::utils::{get_bit_combinations_iter, Bitboard, Checksum};
 use crate::utils::masks::{ANTIDIAGONALS, DIAGONALS, FILE_A, FILE_H, RANK_1, RANK_8};
 use crate::utils::{SlidingPieceType, Square};
 use static_init::dynamic;
@@ -121,6 +121,19 @@ impl MagicDict {
         self.attacks[magic_index]
     }
 
+    /// Feed the magic info of every square and the attack table into `checksum`
+    fn update_checksum(&self, checksum: &mut Checksum) {
+        for magic_info in self.magic_info_for_squares.iter() {
+            checksum.update_u64(magic_info.relevant_mask);
+            checksum.update_u64(magic_info.magic_number);
+            checksum.update_u64(magic_info.right_shift_amount as u64);
+            checksum.update_u64(magic_info.offset as u64);
+        }
+        for attack_mask in self.attacks.iter() {
+            checksum.update_u64(*attack_mask);
+        }
+    }
+
     /// Fill the magic numbers and attack tables for all squares
     pub fn fill_magic_numbers_and_attacks(&mut self, sliding_piece: SlidingPieceType) {
         let mut current_offset = 0;
@@ -214,6 +227,32 @@ pub fn calc_magic_index(magic_info: &MagicInfo, occupied_mask: Bitboard) -> usiz
     calc_magic_index_without_offset(magic_info, occupied_mask) + magic_info.offset as usize
 }
 
+/// Calculate a checksum of the rook and bishop magic dictionaries
+pub fn calc_magic_checksum() -> u64 {
+    let mut checksum = Checksum::default();
+    ROOK_MAGIC_DICT.update_checksum(&mut checksum);
+    BISHOP_MAGIC_DICT.update_checksum(&mut checksum);
+    checksum.get_value()
+}
+
+/// Find a square and occupied mask for which the magic attacks of a rook or bishop differ from the manual ones,
+/// trying every relevant occupied mask of every square
+pub fn find_magic_mismatch() -> Option<(Square, Bitboard)> {
+    for src_square in Square::iter_all() {
+        for occupied_mask in get_bit_combinations_iter(get_rook_relevant_mask(*src_square)) {
+            if magic_single_rook_attacks(*src_square, occupied_mask) != manual_single_rook_attacks(*src_square, occupied_mask) {
+                return Some((*src_square, occupied_mask));
+            }
+        }
+        for occupied_mask in get_bit_combinations_iter(get_bishop_relevant_mask(*src_square)) {
+            if magic_single_bishop_attacks(*src_square, occupied_mask) != manual_single_bishop_attacks(*src_square, occupied_mask) {
+                return Some((*src_square, occupied_mask));
+            }
+        }
+    }
+    None
+}
+
 /// Calculate the attack mask for a rook on a given square with a given occupied mask
 pub fn magic_single_rook_attacks(src_square: Square, occupied_mask: Bitboard) -> Bitboard {
     ROOK_MAGIC_DICT.calc_attack_mask(src_square, occupied_mask)
@@ -6,45 +6,65 @@ mod precomputed;
 
 use crate::utils::{Bitboard, Square};
 use crate::utils::Color;
+use crate::utils::{record_perf_counter, PerfCounter};
 
 /// Returns an attack mask encoding all squares attacked by a knight on `src_square`
 pub fn single_knight_attacks(src_square: Square) -> Bitboard {
+    record_perf_counter(PerfCounter::AttackLookup);
     precomputed::precomputed_single_knight_attacks(src_square)
 }
 
 /// Returns an attack mask encoding all squares attacked by a king on `src_square`
 pub fn single_king_attacks(src_square: Square) -> Bitboard {
+    record_perf_counter(PerfCounter::AttackLookup);
     precomputed::precomputed_single_king_attacks(src_square)
 }
 
 /// Returns an attack mask encoding all squares attacked by knight(s) on `knights_mask`
 pub fn multi_knight_attacks(knights_mask: Bitboard) -> Bitboard {
+    record_perf_counter(PerfCounter::AttackLookup);
     manual::multi_knight_attacks(knights_mask)
 }
 
 /// Returns an attack mask encoding all squares attacked by king(s) on `kings_mask`
 pub fn multi_king_attacks(kings_mask: Bitboard) -> Bitboard {
+    record_perf_counter(PerfCounter::AttackLookup);
     manual::multi_king_attacks(kings_mask)
 }
 
 /// Returns an attack mask encoding all squares attacked by pawn(s) on `pawns_mask`
 pub fn multi_pawn_attacks(pawns_mask: Bitboard, by_color: Color) -> Bitboard {
+    record_perf_counter(PerfCounter::AttackLookup);
     manual::multi_pawn_attacks(pawns_mask, by_color)
 }
 
 /// Returns a mask encoding all squares that pawn(s) on `pawns_mask` can move to
 pub fn multi_pawn_moves(pawns_mask: Bitboard, by_color: Color) -> Bitboard {
+    record_perf_counter(PerfCounter::AttackLookup);
     manual::multi_pawn_moves(pawns_mask, by_color)
 }
 
 /// Returns an attack mask encoding all squares attacked by a rook on `src_square`, 
 /// with `occupied_mask` as the mask of occupied squares
 pub fn single_rook_attacks(src_square: Square, occupied_mask: Bitboard) -> Bitboard {
+    record_perf_counter(PerfCounter::AttackLookup);
     magic::magic_single_rook_attacks(src_square, occupied_mask)
 }
 
 /// Returns an attack mask encoding all squares attacked by a bishop on `src_square`,
 /// with `occupied_mask` as the mask of occupied squares
 pub fn single_bishop_attacks(src_square: Square, occupied_mask: Bitboard) -> Bitboard {
+    record_perf_counter(PerfCounter::AttackLookup);
     magic::magic_single_bishop_attacks(src_square, occupied_mask)
-}
\ No newline at end of file
+}
+/// Returns a checksum of the magic numbers and attack tables of the sliding pieces, which are generated at startup,
+/// to record exactly which tables a result was computed with
+pub fn calc_magic_checksum() -> u64 {
+    magic::calc_magic_checksum()
+}
+
+/// Returns a square and mask of occupied squares for which the magic attack tables are wrong, or None if they are all correct,
+/// to check the tables generated at startup
+pub fn find_magic_mismatch() -> Option<(Square, Bitboard)> {
+    magic::find_magic_mismatch()
+}
@@ -0,0 +1,477 @@
+//! A compact binary format for complete games, used to store and transport self-play and arena games.
+//!
+//! Each record is laid out as follows, with all integers big-endian:
+//! - format version (u8), currently `GAME_RECORD_VERSION`
+//! - flags (u8), where bit 0 is set if per-move metadata is present
+//! - initial FEN length (u16) and bytes, with length 0 meaning the standard initial position
+//! - result from white's point of view (i8): 1, 0, or -1
+//! - number of moves (u16), followed by each move in its 16-bit encoding (see `Move::to_u16`)
+//! - if flagged, per move: search value (f32), visits of the move played (u32), nodes searched (u32),
+//!   depth (u16) and thinking time in milliseconds (u32). Version 1 records only have the value and visits.
+//!
+//! In PGN, the metadata of each move is written as a comment of command annotations, see `MoveMetadata::to_pgn_comment`.
+
+use std::io;
+use std::io::{Read, Write};
+use indexmap::IndexMap;
+use crate::pgn::PgnStateTree;
+use crate::r#move::Move;
+use crate::state::{State, INITIAL_FEN};
+use crate::utils::Color;
+
+pub const GAME_RECORD_VERSION: u8 = 2;
+
+const HAS_METADATA_FLAG: u8 = 0b1;
+
+/// Converts a value in [-1, 1] to a centipawn score for GUIs.
+pub fn value_to_centipawns(value: f64) -> i32 {
+    (111.714640912 * (1.5620688421 * value.clamp(-0.999, 0.999)).tan()).round() as i32
+}
+
+/// Converts a centipawn score back to a value in [-1, 1], the inverse of `value_to_centipawns` up to its rounding.
+pub fn centipawns_to_value(centipawns: i32) -> f64 {
+    (centipawns as f64 / 111.714640912).atan() / 1.5620688421
+}
+
+/// Search statistics recorded for a move.
+#[derive(Copy, Clone, Debug, PartialEq, Default)]
+pub struct MoveMetadata {
+    /// The search value of the move, from the point of view of the side that played it.
+    pub value: f32,
+    /// The visits of the move played.
+    pub visits: u32,
+    /// The number of nodes searched, over all moves.
+    pub nodes: u32,
+    /// The length of the principal variation.
+    pub depth: u16,
+    /// The time spent searching, in milliseconds.
+    pub elapsed_ms: u32,
+}
+
+impl MoveMetadata {
+    /// Returns the statistics as PGN command annotations, e.g. `[%eval 0.35] [%depth 12] [%nodes 800] [%visits 512] [%emt 0:00:01.250]`,
+    /// with the evaluation in pawns from white's point of view. `mover` is the side that played the move.
+    pub fn to_pgn_comment(&self, mover: Color) -> String {
+        let value = if mover == Color::White { self.value } else { -self.value };
+        let (hours, minutes) = (self.elapsed_ms / 3_600_000, self.elapsed_ms / 60_000 % 60);
+        let (seconds, millis) = (self.elapsed_ms / 1000 % 60, self.elapsed_ms % 1000);
+        format!(
+            "[%eval {:.2}] [%depth {}] [%nodes {}] [%visits {}] [%emt {}:{:02}:{:02}.{:03}]",
+            value_to_centipawns(value as f64) as f64 / 100., self.depth, self.nodes, self.visits, hours, minutes, seconds, millis
+        )
+    }
+
+    /// Reads the statistics written by `to_pgn_comment` for a move played by `mover`, ignoring any other text,
+    /// or returns None unless all of them are present. The value is only as precise as the evaluation in centipawns.
+    pub fn from_pgn_comment(comment: &str, mover: Color) -> Option<MoveMetadata> {
+        let mut metadata = MoveMetadata::default();
+        let mut num_found = 0;
+        for command in comment.split("[%").skip(1) {
+            let Some((name, argument)) = command.split_once(']').and_then(|(command, _)| command.split_once(' ')) else {
+                continue;
+            };
+            let argument = argument.trim();
+            match name {
+                "eval" => {
+                    let pawns: f64 = argument.parse().ok()?;
+                    let value = centipawns_to_value((pawns * 100.).round() as i32) as f32;
+                    metadata.value = if mover == Color::White { value } else { -value };
+                },
+                "depth" => metadata.depth = argument.parse().ok()?,
+                "nodes" => metadata.nodes = argument.parse().ok()?,
+                "visits" => metadata.visits = argument.parse().ok()?,
+                "emt" => metadata.elapsed_ms = parse_emt(argument)?,
+                _ => continue
+            }
+            num_found += 1;
+        }
+        (num_found == 5).then_some(metadata)
+    }
+}
+
+/// Parses an elapsed move time as `h:mm:ss` with optional fractional seconds, in milliseconds.
+fn parse_emt(emt: &str) -> Option<u32> {
+    let fields: Vec<&str> = emt.split(':').collect();
+    let [hours, minutes, seconds] = fields.as_slice() else {
+        return None;
+    };
+    let seconds: f64 = seconds.parse().ok().filter(|seconds: &f64| *seconds >= 0.)?;
+    let minutes = hours.parse::<u32>().ok()? * 60 + minutes.parse::<u32>().ok()?;
+    Some(minutes * 60_000 + (seconds * 1000.).round() as u32)
+}
+
+/// A complete game: where it started, the moves played, and how it ended.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GameRecord {
+    pub initial_fen: String,
+    pub moves: Vec<Move>,
+    /// The result from white's point of view: 1 for a win, -1 for a loss, 0 for a draw.
+    pub result: i8,
+    /// Per-move metadata, with one entry per move if present.
+    pub metadata: Option<Vec<MoveMetadata>>,
+}
+
+impl GameRecord {
+    /// Creates a record of a game from the standard initial position, without metadata.
+    pub fn new(moves: Vec<Move>, result: i8) -> GameRecord {
+        GameRecord {
+            initial_fen: INITIAL_FEN.to_string(),
+            moves,
+            result,
+            metadata: None,
+        }
+    }
+
+    /// Writes the record in the binary format.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.moves.len() > u16::MAX as usize {
+            return Err(invalid_data(format!("Too many moves: {}", self.moves.len())));
+        }
+        if let Some(metadata) = &self.metadata {
+            if metadata.len() != self.moves.len() {
+                return Err(invalid_data(format!("{} metadata entries for {} moves", metadata.len(), self.moves.len())));
+            }
+        }
+
+        let flags = if self.metadata.is_some() { HAS_METADATA_FLAG } else { 0 };
+        let fen = if self.initial_fen == INITIAL_FEN { "" } else { self.initial_fen.as_str() };
+        writer.write_all(&[GAME_RECORD_VERSION, flags])?;
+        writer.write_all(&(fen.len() as u16).to_be_bytes())?;
+        writer.write_all(fen.as_bytes())?;
+        writer.write_all(&self.result.to_be_bytes())?;
+        writer.write_all(&(self.moves.len() as u16).to_be_bytes())?;
+        for mv in self.moves.iter() {
+            writer.write_all(&mv.to_u16().to_be_bytes())?;
+        }
+        if let Some(metadata) = &self.metadata {
+            for entry in metadata {
+                writer.write_all(&entry.value.to_be_bytes())?;
+                writer.write_all(&entry.visits.to_be_bytes())?;
+                writer.write_all(&entry.nodes.to_be_bytes())?;
+                writer.write_all(&entry.depth.to_be_bytes())?;
+                writer.write_all(&entry.elapsed_ms.to_be_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a record in the binary format, or returns None if `reader` is already at its end.
+    /// Moves are not checked for legality; see `replay`.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<GameRecord>> {
+        let mut header = [0; 2];
+        match reader.read(&mut header[..1])? {
+            0 => return Ok(None),
+            _ => reader.read_exact(&mut header[1..])?
+        }
+        let [version, flags] = header;
+        if version != 1 && version != GAME_RECORD_VERSION {
+            return Err(invalid_data(format!("Unsupported game record version: {}", version)));
+        }
+
+        let mut fen = vec![0; u16::from_be_bytes(read_array(reader)?) as usize];
+        reader.read_exact(&mut fen)?;
+        let fen = String::from_utf8(fen).map_err(|e| invalid_data(e.to_string()))?;
+        let result = i8::from_be_bytes(read_array(reader)?);
+
+        let num_moves = u16::from_be_bytes(read_array(reader)?) as usize;
+        let mut moves = Vec::with_capacity(num_moves);
+        for _ in 0..num_moves {
+            let mv = Move::from_u16(u16::from_be_bytes(read_array(reader)?)).map_err(|e| invalid_data(e.to_string()))?;
+            moves.push(mv);
+        }
+
+        let metadata = if flags & HAS_METADATA_FLAG != 0 {
+            let mut metadata = Vec::with_capacity(num_moves);
+            for _ in 0..num_moves {
+                let mut entry = MoveMetadata {
+                    value: f32::from_be_bytes(read_array(reader)?),
+                    visits: u32::from_be_bytes(read_array(reader)?),
+                    ..MoveMetadata::default()
+                };
+                if version >= 2 {
+                    entry.nodes = u32::from_be_bytes(read_array(reader)?);
+                    entry.depth = u16::from_be_bytes(read_array(reader)?);
+                    entry.elapsed_ms = u32::from_be_bytes(read_array(reader)?);
+                }
+                metadata.push(entry);
+            }
+            Some(metadata)
+        } else {
+            None
+        };
+
+        Ok(Some(GameRecord {
+            initial_fen: if fen.is_empty() { INITIAL_FEN.to_string() } else { fen },
+            moves,
+            result,
+            metadata,
+        }))
+    }
+
+    /// Returns the record in the binary format.
+    pub fn to_bytes(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Plays the moves from the initial position, checking that each is legal, and returns the final state.
+    pub fn replay(&self) -> Result<State, String> {
+        let mut state = State::from_fen(&self.initial_fen).map_err(|e| format!("Invalid initial FEN: {}", e))?;
+        for (i, mv) in self.moves.iter().enumerate() {
+            if !state.calc_legal_moves().contains(mv) {
+                return Err(format!("Illegal move {} at halfmove {}", mv.to_uci(), i));
+            }
+            state.make_move(*mv);
+        }
+        Ok(state)
+    }
+
+    /// Reads the main line of `tree`, with the result from its `Result` tag, a draw if it has none.
+    /// The record has metadata if every move has a comment written by `MoveMetadata::to_pgn_comment`.
+    pub fn from_pgn_tree(tree: &PgnStateTree) -> GameRecord {
+        let initial_fen = tree.head.borrow().state_after_move.to_fen();
+        let mut moves = Vec::new();
+        let mut metadata = Vec::new();
+        let mut node = tree.head.clone();
+        loop {
+            let Some(next_node) = node.borrow().next_main_node() else {
+                break;
+            };
+            if let Some((mv, _, previous_node)) = &next_node.borrow().move_and_san_and_previous_node {
+                moves.push(*mv);
+                let mover = previous_node.borrow().state_after_move.side_to_move;
+                metadata.push(next_node.borrow().comments.iter().find_map(|comment| MoveMetadata::from_pgn_comment(comment, mover)));
+            }
+            node = next_node;
+        }
+        let result = match tree.get_tag("Result") {
+            Some("1-0") => 1,
+            Some("0-1") => -1,
+            _ => 0
+        };
+        GameRecord {
+            initial_fen,
+            moves,
+            result,
+            metadata: metadata.into_iter().collect(),
+        }
+    }
+
+    /// Renders the game as PGN, with the result tag and, if given, `result_comment` just before the result.
+    /// Games not starting from the standard initial position get `SetUp` and `FEN` tags.
+    /// Moves with metadata are followed by a comment written by `MoveMetadata::to_pgn_comment`.
+    pub fn to_pgn(&self, result_comment: Option<&str>) -> Result<String, String> {
+        self.to_pgn_with_tags(&IndexMap::new(), result_comment)
+    }
+
+    /// Like `to_pgn`, with `tags` written first, e.g. the players or an `EngineManifest`.
+    pub fn to_pgn_with_tags(&self, tags: &IndexMap<String, String>, result_comment: Option<&str>) -> Result<String, String> {
+        let result = match self.result {
+            1 => "1-0",
+            -1 => "0-1",
+            _ => "1/2-1/2"
+        };
+        let mut pgn = String::new();
+        for (name, value) in tags {
+            pgn += &format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""));
+        }
+        if self.initial_fen != INITIAL_FEN {
+            pgn += &format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", self.initial_fen);
+        }
+        pgn += &format!("[Result \"{}\"]\n\n", result);
+
+        let mut state = State::from_fen(&self.initial_fen).map_err(|e| format!("Invalid initial FEN: {}", e))?;
+        let mut tokens = Vec::with_capacity(self.moves.len() * 3 / 2 + 2);
+        for (i, mv) in self.moves.iter().enumerate() {
+            let legal_moves = state.calc_legal_moves();
+            if !legal_moves.contains(mv) {
+                return Err(format!("Illegal move {} at halfmove {}", mv.to_uci(), i));
+            }
+            let mut next_state = state.clone();
+            next_state.make_move(*mv);
+            next_state.check_and_update_termination();
+
+            if state.side_to_move == Color::White {
+                tokens.push(format!("{}.", state.get_fullmove()));
+            } else if i == 0 {
+                tokens.push(format!("{}...", state.get_fullmove()));
+            }
+            tokens.push(mv.to_san(&state, &next_state, &legal_moves));
+            if let Some(entry) = self.metadata.as_ref().and_then(|metadata| metadata.get(i)) {
+                tokens.push(format!("{{{}}}", entry.to_pgn_comment(state.side_to_move)));
+            }
+            state = next_state;
+        }
+        if let Some(comment) = result_comment {
+            tokens.push(format!("{{{}}}", comment));
+        }
+        tokens.push(result.to_string());
+        pgn += &tokens.join(" ");
+        Ok(pgn)
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn read_array<R: Read, const N: usize>(reader: &mut R) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Writes records back to back.
+pub fn write_game_records<W: Write>(writer: &mut W, records: &[GameRecord]) -> io::Result<()> {
+    records.iter().try_for_each(|record| record.write_to(writer))
+}
+
+/// Reads records written by `write_game_records` until the end of `reader`.
+pub fn read_game_records<R: Read>(reader: &mut R) -> io::Result<Vec<GameRecord>> {
+    let mut records = Vec::new();
+    while let Some(record) = GameRecord::read_from(reader)? {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::PgnReader;
+    use crate::r#move::MoveFlag;
+    use crate::utils::{PieceType, Square};
+
+    fn make_scholars_mate() -> GameRecord {
+        GameRecord::new(vec![
+            Move::new_non_promotion(Square::E4, Square::E2, MoveFlag::NormalMove),
+            Move::new_non_promotion(Square::E5, Square::E7, MoveFlag::NormalMove),
+            Move::new_non_promotion(Square::C4, Square::F1, MoveFlag::NormalMove),
+            Move::new_non_promotion(Square::C6, Square::B8, MoveFlag::NormalMove),
+            Move::new_non_promotion(Square::H5, Square::D1, MoveFlag::NormalMove),
+            Move::new_non_promotion(Square::F6, Square::G8, MoveFlag::NormalMove),
+            Move::new_non_promotion(Square::F7, Square::H5, MoveFlag::NormalMove),
+        ], 1)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut with_metadata = make_scholars_mate();
+        with_metadata.metadata = Some((0..7).map(|i| MoveMetadata {
+            value: i as f32 / 10.,
+            visits: i * 100,
+            nodes: i * 150,
+            depth: i as u16,
+            elapsed_ms: i * 1000,
+        }).collect());
+        let promotion = GameRecord {
+            initial_fen: "8/P6k/8/8/8/8/8/K7 w - - 0 1".to_string(),
+            moves: vec![Move::new(Square::A8, Square::A7, PieceType::Queen, MoveFlag::Promotion)],
+            result: 0,
+            metadata: None,
+        };
+        let records = vec![make_scholars_mate(), with_metadata, promotion];
+
+        let mut bytes = Vec::new();
+        write_game_records(&mut bytes, &records).unwrap();
+        assert_eq!(read_game_records(&mut bytes.as_slice()).unwrap(), records);
+
+        // 2 header bytes, 2 FEN length bytes, 1 result byte, 2 move count bytes, and 2 bytes per move
+        assert_eq!(make_scholars_mate().to_bytes().unwrap().len(), 7 + 2 * 7);
+    }
+
+    #[test]
+    fn test_read_version_1() {
+        let mut bytes = make_scholars_mate().to_bytes().unwrap();
+        bytes[0] = 1;
+        bytes[1] = HAS_METADATA_FLAG;
+        for i in 0..7 {
+            bytes.extend((i as f32 / 10.).to_be_bytes());
+            bytes.extend((i * 100u32).to_be_bytes());
+        }
+        let record = GameRecord::read_from(&mut bytes.as_slice()).unwrap().unwrap();
+        assert_eq!(record.moves, make_scholars_mate().moves);
+        assert_eq!(record.metadata.unwrap()[3], MoveMetadata { value: 0.3, visits: 300, ..MoveMetadata::default() });
+    }
+
+    #[test]
+    fn test_pgn_metadata() {
+        let entry = MoveMetadata { value: 0.25, visits: 512, nodes: 800, depth: 12, elapsed_ms: 3_723_045 };
+        let comment = entry.to_pgn_comment(Color::Black);
+        assert_eq!(comment, format!("[%eval {:.2}] [%depth 12] [%nodes 800] [%visits 512] [%emt 1:02:03.045]", value_to_centipawns(-0.25) as f64 / 100.));
+        let parsed = MoveMetadata::from_pgn_comment(&format!("Good move {}", comment), Color::Black).unwrap();
+        assert!((parsed.value - entry.value).abs() < 1e-2);
+        assert_eq!(MoveMetadata { value: entry.value, ..parsed }, entry);
+        assert_eq!(MoveMetadata::from_pgn_comment("[%eval 0.35] [%emt 0:00:05]", Color::White), None);
+        assert_eq!(parse_emt("0:00:05"), Some(5000));
+        assert_eq!(parse_emt("5"), None);
+
+        let mut record = make_scholars_mate();
+        record.metadata = Some((0..7).map(|i| MoveMetadata { value: 0.5, visits: i, nodes: 10 * i, depth: 2, elapsed_ms: 250 }).collect());
+        let pgn = record.to_pgn(None).unwrap();
+        assert!(pgn.contains("1. e4 {[%eval "));
+        let tree = PgnReader::new(pgn.as_bytes()).next_with_tags().unwrap().unwrap();
+        let parsed = GameRecord::from_pgn_tree(&tree);
+        assert_eq!(parsed.moves, record.moves);
+        assert_eq!(parsed.result, 1);
+        for (parsed_entry, entry) in parsed.metadata.unwrap().iter().zip(record.metadata.unwrap()) {
+            assert!((parsed_entry.value - entry.value).abs() < 1e-2);
+            assert_eq!(MoveMetadata { value: entry.value, ..*parsed_entry }, entry);
+        }
+        let pgn = make_scholars_mate().to_pgn(None).unwrap();
+        let tree = PgnReader::new(pgn.as_bytes()).next_with_tags().unwrap().unwrap();
+        assert_eq!(GameRecord::from_pgn_tree(&tree).metadata, None);
+    }
+
+    #[test]
+    fn test_replay() {
+        let state = make_scholars_mate().replay().unwrap();
+        assert!(state.calc_legal_moves().is_empty());
+        assert!(state.board.is_color_in_check(state.side_to_move));
+
+        let mut illegal = make_scholars_mate();
+        illegal.moves.swap(0, 1);
+        assert!(illegal.replay().is_err());
+    }
+
+    #[test]
+    fn test_invalid_data() {
+        let bytes = make_scholars_mate().to_bytes().unwrap();
+        assert!(GameRecord::read_from(&mut &bytes[..bytes.len() - 1]).is_err());
+
+        let mut wrong_version = bytes.clone();
+        wrong_version[0] = GAME_RECORD_VERSION + 1;
+        assert!(GameRecord::read_from(&mut wrong_version.as_slice()).is_err());
+
+        let mut mismatched_metadata = make_scholars_mate();
+        mismatched_metadata.metadata = Some(vec![]);
+        assert!(mismatched_metadata.to_bytes().is_err());
+    }
+
+    #[test]
+    fn test_to_pgn() {
+        assert_eq!(
+            make_scholars_mate().to_pgn(None).unwrap(),
+            "[Result \"1-0\"]\n\n1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0"
+        );
+
+        let from_setup = GameRecord {
+            initial_fen: "4k3/8/8/8/8/8/8/R3K3 b Q - 0 30".to_string(),
+            moves: vec![Move::new_non_promotion(Square::D8, Square::E8, MoveFlag::NormalMove)],
+            result: 0,
+            metadata: None,
+        };
+        assert_eq!(
+            from_setup.to_pgn(Some("Adjourned")).unwrap(),
+            "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b Q - 0 30\"]\n[Result \"1/2-1/2\"]\n\n30... Kd8 {Adjourned} 1/2-1/2"
+        );
+
+        let mut tags = IndexMap::new();
+        tags.insert("White".to_string(), "dunck \"v1\"".to_string());
+        assert_eq!(
+            make_scholars_mate().to_pgn_with_tags(&tags, None).unwrap(),
+            "[White \"dunck \\\"v1\\\"\"]\n[Result \"1-0\"]\n\n1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7# 1-0"
+        );
+    }
+}
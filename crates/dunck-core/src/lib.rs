@@ -0,0 +1,10 @@
+//! Boards, moves, FEN and PGN, without any search or evaluation.
+
+pub mod attacks;
+pub mod game_record;
+pub mod r#move;
+pub mod perft;
+pub mod pgn;
+pub mod state;
+pub mod utils;
+pub mod prelude;
@@ -1,7 +1,8 @@
 //! This module contains the implementation of the `Move` struct and its associated functions.
 
 mod move_flag;
-mod san;
+pub mod san;
+pub mod uci;
 mod r#move;
 
 pub use r#move::*;
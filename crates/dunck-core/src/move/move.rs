@@ -0,0 +1,268 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use crate::r#move::MoveFlag;
+use crate::state::State;
+use crate::utils::{Color, ColoredPiece, PieceType, Square};
+
+/// Represents a move in the game.
+/// Internally, it is stored as a 16-bit unsigned integer, from most to least significant bit:
+///
+/// | bits  | field       | encoding                                                  |
+/// |-------|-------------|-----------------------------------------------------------|
+/// | 15-10 | destination | `Square` value (A8 = 0, ..., H1 = 63)                     |
+/// | 9-4   | source      | `Square` value                                            |
+/// | 3-2   | promotion   | `PieceType` value minus 2 (knight = 0, ..., queen = 3)    |
+/// | 1-0   | flag        | `MoveFlag` value                                          |
+///
+/// Moves that are not promotions always use `Move::DEFAULT_PROMOTION_VALUE` in the promotion bits,
+/// so each move has exactly one encoding. See `Move::to_u16` and `Move::from_u16`.
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Move {
+    /// format: {6 bit dest}{6 bit src}{2 bit promotion PieceType value minus 2}{2 bit MoveFlag value}
+    pub value: u16,
+}
+
+/// The reason a 16-bit value is not a valid move encoding.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MoveDecodeError {
+    SameSquare(u16),
+    NonCanonicalPromotion(u16),
+    InvalidPromotionRank(u16),
+    InvalidEnPassantRank(u16),
+}
+
+impl Display for MoveDecodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveDecodeError::SameSquare(value) => write!(f, "Source and destination are the same: {:#06x}", value),
+            MoveDecodeError::NonCanonicalPromotion(value) => write!(f, "Promotion bits set on a non-promotion: {:#06x}", value),
+            MoveDecodeError::InvalidPromotionRank(value) => write!(f, "Promotion not onto the last rank: {:#06x}", value),
+            MoveDecodeError::InvalidEnPassantRank(value) => write!(f, "En passant not onto the third or sixth rank: {:#06x}", value),
+        }
+    }
+}
+
+impl Error for MoveDecodeError {}
+
+impl Move {
+    /// The default promotion value for a move.
+    pub const DEFAULT_PROMOTION_VALUE: PieceType = PieceType::Rook;
+
+    /// Creates a new move.
+    pub fn new(dst: Square, src: Square, promotion: PieceType, flag: MoveFlag) -> Move {
+        assert!(promotion != PieceType::King && promotion != PieceType::Pawn, "Invalid promotion piece type");
+        Move {
+            value: ((dst as u16) << 10) | ((src as u16) << 4) | ((promotion as u16 - 2) << 2) | flag as u16
+        }
+    }
+
+    /// Creates a new move with the default promotion value.
+    pub fn new_non_promotion(dst: Square, src: Square, flag: MoveFlag) -> Move {
+        Move::new(dst, src, Move::DEFAULT_PROMOTION_VALUE, flag)
+    }
+
+    /// Gets the destination square of the move.
+    pub const fn get_destination(&self) -> Square {
+        let dst_int = (self.value >> 10) as u8;
+        unsafe { Square::from(dst_int) }
+    }
+
+    /// Gets the source square of the move.
+    pub const fn get_source(&self) -> Square {
+        let src_int = ((self.value & 0b0000001111110000) >> 4) as u8;
+        unsafe { Square::from(src_int) }
+    }
+
+    /// Gets the promotion piece type of the move.
+    pub const fn get_promotion(&self) -> PieceType {
+        let promotion_int = ((self.value & 0b0000000000001100) >> 2) as u8;
+        unsafe { PieceType::from(promotion_int + 2) }
+    }
+
+    /// Gets the flag of the move.
+    pub const fn get_flag(&self) -> MoveFlag {
+        let flag_int = (self.value & 0b0000000000000011) as u8;
+        unsafe { MoveFlag::from(flag_int) }
+    }
+
+    /// Unpacks the move into its components.
+    pub const fn unpack(&self) -> (Square, Square, PieceType, MoveFlag) {
+        (self.get_destination(), self.get_source(), self.get_promotion(), self.get_flag())
+    }
+
+    /// Returns the packed 16-bit encoding of the move.
+    pub const fn to_u16(&self) -> u16 {
+        self.value
+    }
+
+    /// Creates a move from its packed 16-bit encoding, checking that it is structurally valid:
+    /// distinct squares, canonical promotion bits, and a plausible destination rank for
+    /// promotions and en passant captures. Legality in a position is not checked.
+    pub fn from_u16(value: u16) -> Result<Move, MoveDecodeError> {
+        let mv = Move { value };
+        let (dst, src, promotion, flag) = mv.unpack();
+        if dst == src {
+            return Err(MoveDecodeError::SameSquare(value));
+        }
+        match flag {
+            MoveFlag::Promotion => {
+                if dst.get_rank() != 0 && dst.get_rank() != 7 {
+                    return Err(MoveDecodeError::InvalidPromotionRank(value));
+                }
+            },
+            _ => {
+                if promotion != Move::DEFAULT_PROMOTION_VALUE {
+                    return Err(MoveDecodeError::NonCanonicalPromotion(value));
+                }
+                if flag == MoveFlag::EnPassant && dst.get_rank() != 2 && dst.get_rank() != 5 {
+                    return Err(MoveDecodeError::InvalidEnPassantRank(value));
+                }
+            }
+        }
+        Ok(mv)
+    }
+
+    /// If this is a castling move in `state`, returns the destination and source squares of the rook,
+    /// in the same order as `unpack`, e.g. for a GUI to animate the rook along with the king.
+    /// Returns None for any other move, or if `state` does not have the king and rook on their squares.
+    pub fn castling_rook_move(&self, state: &State) -> Option<(Square, Square)> {
+        let (dst, src, _, flag) = self.unpack();
+        if flag != MoveFlag::Castling {
+            return None;
+        }
+        let (rook_dst, rook_src) = if dst.get_file() > src.get_file() {
+            (src.offset(1, 0)?, src.offset(3, 0)?)
+        } else {
+            (src.offset(-1, 0)?, src.offset(-4, 0)?)
+        };
+        let color = state.side_to_move;
+        let is_in_place = state.board.get_colored_piece_at(src) == ColoredPiece::from(color, PieceType::King)
+            && state.board.get_colored_piece_at(rook_src) == ColoredPiece::from(color, PieceType::Rook);
+        is_in_place.then_some((rook_dst, rook_src))
+    }
+
+    /// If this is an en passant capture in `state`, returns the square of the captured pawn,
+    /// which unlike for other captures is not the destination.
+    /// Returns None for any other move, or if `state` has no pawn of the opponent on that square.
+    pub fn is_en_passant_capture_square(&self, state: &State) -> Option<Square> {
+        if self.get_flag() != MoveFlag::EnPassant {
+            return None;
+        }
+        let opponent = state.side_to_move.flip();
+        let captured_square = match opponent {
+            Color::White => self.get_destination().up()?,
+            Color::Black => self.get_destination().down()?
+        };
+        (state.board.get_colored_piece_at(captured_square) == ColoredPiece::from(opponent, PieceType::Pawn)).then_some(captured_square)
+    }
+
+    /// Returns a readable representation of the move.
+    pub fn readable(&self) -> String {
+        let (dst, src, promotion, flag) = self.unpack();
+        let (dst_str, src_str, promotion_char, flag_str) = (src.readable(), dst.readable(), promotion.to_char(), flag.to_readable());
+        format!("{}{}{}", dst_str, src_str, flag_str.replace('?', &promotion_char.to_string()))
+    }
+}
+
+impl std::fmt::Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.readable())
+    }
+}
+
+impl std::fmt::Debug for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Move, MoveDecodeError, MoveFlag};
+    use crate::state::State;
+    use crate::utils::{PieceType, Square};
+
+    #[test]
+    fn test_move() {
+        for dst_square in Square::iter_all() {
+            for src_square in Square::iter_all() {
+                for promotion_piece in PieceType::iter_promotion_pieces() {
+                    for flag_int in 0..4 {
+                        let flag = unsafe { MoveFlag::from(flag_int) };
+
+                        let mv = Move::new(*dst_square, *src_square, *promotion_piece, flag);
+                        assert_eq!(mv.get_destination(), *dst_square);
+                        assert_eq!(mv.get_source(), *src_square);
+                        assert_eq!(mv.get_promotion(), *promotion_piece);
+                        assert_eq!(mv.get_flag(), flag);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_u16_round_trip() {
+        let mv = Move::new_non_promotion(Square::E4, Square::E2, MoveFlag::NormalMove);
+        assert_eq!(Move::from_u16(mv.to_u16()), Ok(mv));
+
+        let mv = Move::new(Square::A8, Square::A7, PieceType::Knight, MoveFlag::Promotion);
+        assert_eq!(Move::from_u16(mv.to_u16()), Ok(mv));
+
+        let mv = Move::new_non_promotion(Square::D6, Square::E5, MoveFlag::EnPassant);
+        assert_eq!(Move::from_u16(mv.to_u16()), Ok(mv));
+
+        let mv = Move::new_non_promotion(Square::G1, Square::E1, MoveFlag::Castling);
+        assert_eq!(Move::from_u16(mv.to_u16()), Ok(mv));
+
+        // every valid encoding decodes to itself, and there is at least one valid move per square pair
+        let num_valid = (0..=u16::MAX).filter_map(|value| Move::from_u16(value).ok()).inspect(|mv| {
+            assert_eq!(Move::from_u16(mv.to_u16()), Ok(*mv));
+        }).count();
+        assert!(num_valid >= 64 * 63);
+    }
+
+    #[test]
+    fn test_castling_rook_move() {
+        let state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let short = Move::from_uci(&state, "e1g1").unwrap();
+        let long = Move::from_uci(&state, "e1c1").unwrap();
+        assert_eq!(short.castling_rook_move(&state), Some((Square::F1, Square::H1)));
+        assert_eq!(long.castling_rook_move(&state), Some((Square::D1, Square::A1)));
+        assert_eq!(Move::from_uci(&state, "h1h2").unwrap().castling_rook_move(&state), None);
+
+        let state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1").unwrap();
+        assert_eq!(Move::from_uci(&state, "e8g8").unwrap().castling_rook_move(&state), Some((Square::F8, Square::H8)));
+        assert_eq!(Move::from_uci(&state, "e8c8").unwrap().castling_rook_move(&state), Some((Square::D8, Square::A8)));
+        // the white castling move does not fit black's turn
+        assert_eq!(short.castling_rook_move(&state), None);
+    }
+
+    #[test]
+    fn test_en_passant_capture_square() {
+        let state = State::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        let en_passant = Move::from_uci(&state, "e5d6").unwrap();
+        assert_eq!(en_passant.get_flag(), MoveFlag::EnPassant);
+        assert_eq!(en_passant.is_en_passant_capture_square(&state), Some(Square::D5));
+        assert_eq!(Move::from_uci(&state, "e5e6").unwrap().is_en_passant_capture_square(&state), None);
+
+        let state = State::from_fen("4k3/8/8/8/4pP2/8/8/4K3 b - f3 0 1").unwrap();
+        let en_passant = Move::from_uci(&state, "e4f3").unwrap();
+        assert_eq!(en_passant.is_en_passant_capture_square(&state), Some(Square::F4));
+    }
+
+    #[test]
+    fn test_from_u16_rejects_invalid() {
+        let same_square = Move::new_non_promotion(Square::E4, Square::E4, MoveFlag::NormalMove);
+        assert_eq!(Move::from_u16(same_square.to_u16()), Err(MoveDecodeError::SameSquare(same_square.to_u16())));
+
+        let non_canonical = Move::new(Square::E4, Square::E2, PieceType::Queen, MoveFlag::NormalMove);
+        assert!(matches!(Move::from_u16(non_canonical.to_u16()), Err(MoveDecodeError::NonCanonicalPromotion(_))));
+
+        let bad_promotion = Move::new(Square::E4, Square::E2, PieceType::Queen, MoveFlag::Promotion);
+        assert!(matches!(Move::from_u16(bad_promotion.to_u16()), Err(MoveDecodeError::InvalidPromotionRank(_))));
+
+        let bad_en_passant = Move::new_non_promotion(Square::E4, Square::D5, MoveFlag::EnPassant);
+        assert!(matches!(Move::from_u16(bad_en_passant.to_u16()), Err(MoveDecodeError::InvalidEnPassantRank(_))));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,359 @@
+//! SAN (Standard Algebraic Notation) rendering, plus strict and lenient SAN parsing.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use crate::utils::{Bitboard, PieceType, Square};
+use crate::r#move::{Move};
+use crate::r#move::move_flag::MoveFlag;
+use crate::state::{Board, State, Termination};
+
+/// The reason a SAN string could not be matched to a legal move.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SanError {
+    Malformed(String),
+    Illegal(String),
+    Ambiguous(String),
+    NonCanonical { found: String, expected: String },
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SanError::Malformed(san) => write!(f, "Malformed SAN: {}", san),
+            SanError::Illegal(san) => write!(f, "No legal move matches SAN: {}", san),
+            SanError::Ambiguous(san) => write!(f, "More than one legal move matches SAN: {}", san),
+            SanError::NonCanonical { found, expected } => write!(f, "Non-canonical SAN: {} (expected {})", found, expected),
+        }
+    }
+}
+
+impl Error for SanError {}
+
+impl Move {
+    /// Returns the SAN (Standard Algebraic Notation) representation of the move.
+    /// Assumes that `final_state` has an updated termination
+    pub fn to_san(&self, initial_state: &State, final_state: &State, initial_state_moves: &[Move]) -> String {
+        self.format_san(initial_state, final_state, |moved_piece| {
+            get_clashes(moved_piece, self.get_source(), self.get_destination(), initial_state_moves, &initial_state.board)
+        })
+    }
+
+    /// Like `to_san`, with `calc_clashes` returning the sources of the other moves of the moved piece type
+    /// to the same destination, which the move has to be disambiguated from.
+    fn format_san(&self, initial_state: &State, final_state: &State, calc_clashes: impl FnOnce(PieceType) -> Bitboard) -> String {
+        let dst_square = self.get_destination();
+        let src_square = self.get_source();
+        let promotion = self.get_promotion();
+        let flag = self.get_flag();
+
+        let src_file = src_square.get_file_char();
+
+        let mut promotion_str = String::new();
+        let is_capture;
+        let moved_piece;
+
+        let annotation_str = match final_state.termination {
+            Some(Termination::Checkmate) => "#",
+            _ => if final_state.board.is_color_in_check(final_state.side_to_move) { "+" } else { "" },
+        };
+
+        match flag {
+            MoveFlag::Castling => {
+                return if dst_square.get_file() == 6 {
+                    format!("O-O{}", annotation_str)
+                } else {
+                    format!("O-O-O{}", annotation_str)
+                }
+            },
+            MoveFlag::EnPassant => {
+                is_capture = true;
+                moved_piece = PieceType::Pawn;
+            },
+            MoveFlag::NormalMove | MoveFlag::Promotion => {
+                is_capture = initial_state.board.color_masks[final_state.side_to_move as usize] != final_state.board.color_masks[final_state.side_to_move as usize];
+
+                if flag == MoveFlag::Promotion {
+                    promotion_str = format!("={}", promotion.to_char());
+                    moved_piece = PieceType::Pawn;
+                }
+                else {
+                    moved_piece = initial_state.board.get_piece_type_at(src_square);
+                }
+            }
+        }
+
+        let capture_str = if is_capture { "x" } else { "" };
+
+        let piece_str = match moved_piece {
+            PieceType::Pawn => {
+                if is_capture {
+                    src_file.to_string()
+                }
+                else {
+                    "".to_string()
+                }
+            },
+            _ => moved_piece.to_char().to_string()
+        };
+        
+        let disambiguation_str = match moved_piece {
+            PieceType::Pawn | PieceType::King => String::new(),
+            _ => get_disambiguation(src_square, calc_clashes(moved_piece))
+        };
+
+        format!("{}{}{}{}{}{}", piece_str, disambiguation_str, capture_str, dst_square.to_string(), promotion_str, annotation_str)
+    }
+}
+
+impl State {
+    /// Returns the SAN of each of `moves`, which must be all the legal moves of the position, in one pass:
+    /// the moves that need disambiguating are found by grouping them by piece type and destination,
+    /// instead of comparing every move with every other one as repeated calls to `Move::to_san` do.
+    pub fn sans_for(&self, moves: &[Move]) -> Vec<String> {
+        // the sources of the moves of each piece type to each square
+        let mut sources = [[0 as Bitboard; 64]; 7];
+        for mv in moves {
+            let src_square = mv.get_source();
+            let moved_piece = self.board.get_piece_type_at(src_square);
+            sources[moved_piece as usize][mv.get_destination() as usize] |= src_square.get_mask();
+        }
+
+        moves.iter().map(|mv| {
+            let mut final_state = self.clone();
+            final_state.make_move(*mv);
+            // only a move giving check can be mate
+            if final_state.board.is_color_in_check(final_state.side_to_move) {
+                final_state.check_and_update_termination();
+            }
+            mv.format_san(self, &final_state, |moved_piece| {
+                sources[moved_piece as usize][mv.get_destination() as usize] & !mv.get_source().get_mask()
+            })
+        }).collect()
+    }
+}
+
+/// Returns the sources of the other moves of `moved_piece` to `dst_square` among `initial_state_moves`.
+fn get_clashes(moved_piece: PieceType, src_square: Square, dst_square: Square, initial_state_moves: &[Move], initial_state_board: &Board) -> Bitboard {
+    let mut clashes = 0;
+    for other_move in initial_state_moves.iter() {
+        let other_src_square = other_move.get_source();
+        if src_square == other_src_square { // same move
+            continue;
+        }
+        if dst_square == other_move.get_destination() && moved_piece == initial_state_board.get_piece_type_at(other_src_square) {
+            clashes |= other_src_square.get_mask();
+        }
+    }
+    clashes
+}
+
+/// Returns the shortest part of `src_square` that tells it apart from the sources in `clashes`:
+/// the file if possible, else the rank, else the whole square.
+fn get_disambiguation(src_square: Square, clashes: Bitboard) -> String {
+    if clashes == 0 {
+        String::new()
+    } else if clashes & src_square.get_file_mask() == 0 {
+        src_square.get_file_char().to_string()
+    } else if clashes & src_square.get_rank_mask() == 0 {
+        (src_square.get_rank() + 1).to_string()
+    } else {
+        src_square.to_string()
+    }
+}
+
+/// The components of a SAN string, as written, before being matched against legal moves.
+struct SanComponents {
+    castling_file: Option<u8>,
+    moved_piece: PieceType,
+    src_file: Option<u8>,
+    src_rank: Option<u8>,
+    dst_square: Square,
+    promotion: Option<PieceType>,
+}
+
+fn parse_piece_char(c: char) -> Option<PieceType> {
+    match c {
+        'N' => Some(PieceType::Knight),
+        'B' => Some(PieceType::Bishop),
+        'R' => Some(PieceType::Rook),
+        'Q' => Some(PieceType::Queen),
+        'K' => Some(PieceType::King),
+        _ => None
+    }
+}
+
+fn parse_components(san: &str) -> Option<SanComponents> {
+    let body = san.trim_end_matches(['+', '#', '!', '?']);
+
+    let castling_file = match body {
+        "O-O" | "0-0" => Some(6),
+        "O-O-O" | "0-0-0" => Some(2),
+        _ => None
+    };
+    if castling_file.is_some() {
+        return Some(SanComponents {
+            castling_file,
+            moved_piece: PieceType::King,
+            src_file: None,
+            src_rank: None,
+            dst_square: Square::A1,
+            promotion: None,
+        });
+    }
+
+    let mut chars: Vec<char> = body.chars().filter(|c| !matches!(c, 'x' | ':' | '-' | '=')).collect();
+
+    let moved_piece = match chars.first().copied().and_then(parse_piece_char) {
+        Some(piece) => {
+            chars.remove(0);
+            piece
+        },
+        None => PieceType::Pawn
+    };
+
+    let promotion = match chars.last().copied().and_then(parse_piece_char) {
+        Some(piece) if moved_piece == PieceType::Pawn && piece != PieceType::King => {
+            chars.pop();
+            Some(piece)
+        },
+        Some(_) => return None,
+        None => None
+    };
+
+    if chars.len() < 2 || chars.len() > 4 {
+        return None;
+    }
+    let (hint, dst) = chars.split_at(chars.len() - 2);
+    let dst_square = dst.iter().collect::<String>().parse::<Square>().ok()?;
+
+    let mut src_file = None;
+    let mut src_rank = None;
+    for c in hint {
+        match c {
+            'a'..='h' if src_file.is_none() && src_rank.is_none() => src_file = Some(*c as u8 - b'a'),
+            '1'..='8' if src_rank.is_none() => src_rank = Some(*c as u8 - b'1'),
+            _ => return None
+        }
+    }
+
+    Some(SanComponents { castling_file, moved_piece, src_file, src_rank, dst_square, promotion })
+}
+
+fn matches_components(mv: &Move, components: &SanComponents, board: &Board) -> bool {
+    let (dst_square, src_square, promotion, flag) = mv.unpack();
+
+    if let Some(castling_file) = components.castling_file {
+        return flag == MoveFlag::Castling && dst_square.get_file() == castling_file;
+    }
+
+    flag != MoveFlag::Castling &&
+        dst_square == components.dst_square &&
+        board.get_piece_type_at(src_square) == components.moved_piece &&
+        components.src_file.map_or(true, |file| file == src_square.get_file()) &&
+        components.src_rank.map_or(true, |rank| rank == src_square.get_rank()) &&
+        match flag {
+            MoveFlag::Promotion => components.promotion == Some(promotion),
+            _ => components.promotion.is_none()
+        }
+}
+
+/// Leniently parses `san` into a legal move in `state`.
+/// Accepts redundant disambiguation, missing or extra capture marks, missing or wrong check marks,
+/// promotions without '=', zeros in castling, and trailing annotations such as "!?".
+pub fn parse(state: &State, san: &str) -> Result<Move, SanError> {
+    let components = parse_components(san).ok_or_else(|| SanError::Malformed(san.to_string()))?;
+
+    let legal_moves = state.calc_legal_moves();
+    let mut matching_moves = legal_moves.iter().filter(|mv| matches_components(mv, &components, &state.board));
+
+    match (matching_moves.next(), matching_moves.next()) {
+        (Some(mv), None) => Ok(*mv),
+        (Some(_), Some(_)) => Err(SanError::Ambiguous(san.to_string())),
+        (None, _) => Err(SanError::Illegal(san.to_string()))
+    }
+}
+
+/// Strictly parses `san` into a legal move in `state`, rejecting anything that is not exactly
+/// the canonical SAN produced by `Move::to_san`, including check and mate marks.
+pub fn validate(state: &State, san: &str) -> Result<Move, SanError> {
+    let mv = parse(state, san)?;
+
+    let mut final_state = state.clone();
+    final_state.make_move(mv);
+    final_state.check_and_update_termination();
+    let expected = mv.to_san(state, &final_state, &state.calc_legal_moves());
+
+    if expected == san {
+        Ok(mv)
+    } else {
+        Err(SanError::NonCanonical { found: san.to_string(), expected })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::str::FromStr;
+    use super::*;
+    use crate::pgn::PgnStateTree;
+
+    fn assert_round_trips(state: &State) {
+        let legal_moves = state.calc_legal_moves();
+        let sans = state.sans_for(&legal_moves);
+        for (mv, batch_san) in legal_moves.iter().zip(sans) {
+            let mut final_state = state.clone();
+            final_state.make_move(*mv);
+            final_state.check_and_update_termination();
+            let san = mv.to_san(state, &final_state, &legal_moves);
+            assert_eq!(validate(state, &san), Ok(*mv), "{} in {}", san, state.to_fen());
+            assert_eq!(batch_san, san, "{}", state.to_fen());
+        }
+    }
+
+    #[test]
+    fn test_lichess_round_trip() {
+        for file_name in ["amirkhafan_vs_trickortreat", "blitzstream-twitch_vs_amirkhafan", "pinhead-larry_vs_orlando_gloom", "rosen1", "complex"] {
+            let pgn = fs::read_to_string(format!("data/pgn_test_files/{}.pgn", file_name)).expect("Could not read file");
+            let pgn_tree = PgnStateTree::from_str(&pgn).unwrap();
+            let mut nodes = vec![pgn_tree.head.clone()];
+            while let Some(node) = nodes.pop() {
+                assert_round_trips(&node.borrow().state_after_move);
+                nodes.extend(node.borrow().next_nodes.iter().cloned());
+            }
+        }
+    }
+
+    #[test]
+    fn test_killer_san_corpus() {
+        let corpus = fs::read_to_string("data/san_test_files/killer_san.txt").expect("Could not read file");
+        for line in corpus.lines().filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            let fields: Vec<&str> = line.split(';').map(str::trim).collect();
+            let state = State::from_fen(fields[0]).unwrap();
+            let canonical = fields[1];
+
+            let mv = validate(&state, canonical).unwrap_or_else(|e| panic!("{}: {}", line, e));
+            for variant in &fields[2..] {
+                assert_eq!(parse(&state, variant), Ok(mv), "{}", line);
+                assert_eq!(
+                    validate(&state, variant),
+                    Err(SanError::NonCanonical { found: variant.to_string(), expected: canonical.to_string() }),
+                    "{}", line
+                );
+            }
+            assert_round_trips(&state);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let state = State::initial();
+        assert_eq!(parse(&state, "e5"), Err(SanError::Illegal("e5".to_string())));
+        assert_eq!(parse(&state, "Ke2"), Err(SanError::Illegal("Ke2".to_string())));
+        assert_eq!(parse(&state, "e9"), Err(SanError::Malformed("e9".to_string())));
+        assert_eq!(parse(&state, "Nf3=Q"), Err(SanError::Malformed("Nf3=Q".to_string())));
+        assert_eq!(parse(&state, ""), Err(SanError::Malformed("".to_string())));
+
+        let state = State::from_fen("7k/8/8/8/8/8/8/N1N4K w - - 0 1").unwrap();
+        assert_eq!(parse(&state, "Nb3"), Err(SanError::Ambiguous("Nb3".to_string())));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,110 @@
+//! UCI long algebraic notation: the source and destination squares, plus a lowercase promotion letter, e.g. "e7e8q".
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use crate::r#move::{Move, MoveFlag};
+use crate::state::State;
+use crate::utils::{PieceType, Square};
+
+/// The reason a UCI string could not be matched to a legal move.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum UciMoveError {
+    Malformed(String),
+    Illegal(String),
+}
+
+impl Display for UciMoveError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UciMoveError::Malformed(uci) => write!(f, "Malformed UCI move: {}", uci),
+            UciMoveError::Illegal(uci) => write!(f, "Illegal move: {}", uci),
+        }
+    }
+}
+
+impl Error for UciMoveError {}
+
+impl Move {
+    /// Returns the UCI (Universal Chess Interface) representation of the move.
+    /// Castling is written as the king's move, e.g. "e1g1".
+    pub fn to_uci(&self) -> String {
+        let (dst, src, promotion, flag) = self.unpack();
+        match flag {
+            MoveFlag::Promotion => format!("{}{}{}", src, dst, promotion.to_char().to_ascii_lowercase()),
+            _ => format!("{}{}", src, dst)
+        }
+    }
+
+    /// Parses `uci` into a legal move in `state`, which resolves whether it is a normal move,
+    /// an en passant capture, castling, or a promotion.
+    /// Promotion letters are accepted in either case; the null move "0000" is malformed.
+    pub fn from_uci(state: &State, uci: &str) -> Result<Move, UciMoveError> {
+        let malformed = || UciMoveError::Malformed(uci.to_string());
+        if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+            return Err(malformed());
+        }
+        let src: Square = uci[0..2].parse().map_err(|_| malformed())?;
+        let dst: Square = uci[2..4].parse().map_err(|_| malformed())?;
+        let promotion = match &uci[4..] {
+            "" => None,
+            letter => match letter.parse::<PieceType>() {
+                Ok(piece_type @ (PieceType::Knight | PieceType::Bishop | PieceType::Rook | PieceType::Queen)) => Some(piece_type),
+                _ => return Err(malformed())
+            }
+        };
+
+        state.calc_legal_moves().into_iter()
+            .find(|mv| {
+                let is_promotion = mv.get_flag() == MoveFlag::Promotion;
+                mv.get_source() == src && mv.get_destination() == dst
+                    && promotion == is_promotion.then(|| mv.get_promotion())
+            })
+            .ok_or_else(|| UciMoveError::Illegal(uci.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N b - - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+        ] {
+            let state = State::from_fen(fen).unwrap();
+            for mv in state.calc_legal_moves() {
+                assert_eq!(Move::from_uci(&state, &mv.to_uci()), Ok(mv), "{} in {}", mv.to_uci(), fen);
+            }
+        }
+    }
+
+    #[test]
+    fn test_flags_are_resolved() {
+        let state = State::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 2").unwrap();
+        let castle = Move::from_uci(&state, "e1g1").unwrap();
+        assert_eq!(castle.get_flag(), MoveFlag::Castling);
+        assert_eq!(Move::from_uci(&state, "e1c1").unwrap().get_flag(), MoveFlag::Castling);
+        assert_eq!(Move::from_uci(&state, "e5d6").unwrap().get_flag(), MoveFlag::EnPassant);
+        assert_eq!(Move::from_uci(&state, "e1e2").unwrap().get_flag(), MoveFlag::NormalMove);
+
+        let promotion = Move::from_uci(&state, "b7a8n").unwrap();
+        assert_eq!((promotion.get_flag(), promotion.get_promotion()), (MoveFlag::Promotion, PieceType::Knight));
+        assert_eq!(promotion.to_uci(), "b7a8n");
+        assert_eq!(Move::from_uci(&state, "b7b8Q").unwrap().to_uci(), "b7b8q");
+    }
+
+    #[test]
+    fn test_invalid_moves() {
+        let state = State::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 2").unwrap();
+        for uci in ["", "e2", "e2e4e", "i1a1", "0000", "b7b8k", "b7b8p", "é1g1"] {
+            assert_eq!(Move::from_uci(&state, uci), Err(UciMoveError::Malformed(uci.to_string())));
+        }
+        // a promotion needs its piece, and other moves must not have one
+        for uci in ["b7b8", "e1e2q", "e1g1q", "e2e4", "e8g8", "a1a8q"] {
+            assert_eq!(Move::from_uci(&state, uci), Err(UciMoveError::Illegal(uci.to_string())));
+        }
+    }
+}
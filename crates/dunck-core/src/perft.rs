@@ -0,0 +1,594 @@
+//! Perft: counting the leaf nodes of the legal move tree, to test move generation.
+
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use crate::r#move::Move;
+use crate::state::State;
+use crate::utils::Bitboard;
+
+/// Returns the number of move sequences of exactly `depth` legal moves from `state`.
+/// Draws by rule, such as insufficient material, do not end a sequence, as in the standard definition of perft.
+pub fn perft(state: &State, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let legal_moves = state.calc_legal_moves();
+    if depth == 1 {
+        return legal_moves.len() as u64;
+    }
+    legal_moves.iter().map(|mv| {
+        perft(&make_move_ignoring_draws(state, *mv), depth - 1)
+    }).sum()
+}
+
+/// Returns the perft count after each legal move of `state`, which sum to `perft(state, depth)`.
+pub fn perft_divide(state: &State, depth: u32) -> Vec<(Move, u64)> {
+    state.calc_legal_moves().into_iter().map(|mv| {
+        (mv, perft(&make_move_ignoring_draws(state, mv), depth.saturating_sub(1)))
+    }).collect()
+}
+
+/// Returns the same count as `perft`, but with moves generated by `State::calc_legal_moves_legacy`.
+pub fn perft_legacy(state: &State, depth: u32) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let legal_moves = state.calc_legal_moves_legacy();
+    if depth == 1 {
+        return legal_moves.len() as u64;
+    }
+    legal_moves.iter().map(|mv| {
+        perft_legacy(&make_move_ignoring_draws(state, *mv), depth - 1)
+    }).sum()
+}
+
+/// A transposition table of perft counts, keyed by the Zobrist hash of the position and the depth.
+/// Each slot keeps the latest count stored in it.
+pub struct PerftTable {
+    entries: Vec<PerftEntry>,
+}
+
+#[derive(Clone, Copy, Default)]
+struct PerftEntry {
+    hash: Bitboard,
+    /// 0 for an empty slot, since counts at depths 0 and 1 are never stored.
+    depth: u32,
+    count: u64,
+}
+
+impl PerftTable {
+    /// Returns a table with `num_entries` slots, rounded up to a power of two.
+    pub fn new(num_entries: usize) -> PerftTable {
+        PerftTable { entries: vec![PerftEntry::default(); num_entries.max(1).next_power_of_two()] }
+    }
+
+    /// Returns a table that takes up about `megabytes` of memory.
+    pub fn with_megabytes(megabytes: usize) -> PerftTable {
+        let num_entries = ((megabytes << 20) / size_of::<PerftEntry>()).max(1);
+        // rounded down, to stay within the requested size
+        PerftTable::new(1 << num_entries.ilog2())
+    }
+
+    pub fn get_num_entries(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn get_index(&self, hash: Bitboard) -> usize {
+        (hash as usize) & (self.entries.len() - 1)
+    }
+
+    fn probe(&self, hash: Bitboard, depth: u32) -> Option<u64> {
+        let entry = &self.entries[self.get_index(hash)];
+        (entry.hash == hash && entry.depth == depth).then_some(entry.count)
+    }
+
+    fn store(&mut self, hash: Bitboard, depth: u32, count: u64) {
+        let index = self.get_index(hash);
+        self.entries[index] = PerftEntry { hash, depth, count };
+    }
+}
+
+/// Returns the same count as `perft`, reusing the counts of transpositions stored in `table`.
+/// The table can be reused across calls, even for other positions.
+pub fn perft_hashed(state: &State, depth: u32, table: &mut PerftTable) -> u64 {
+    if depth <= 1 {
+        return perft(state, depth);
+    }
+    let hash = state.get_zobrist_hash();
+    if let Some(count) = table.probe(hash, depth) {
+        return count;
+    }
+    let count = state.calc_legal_moves().into_iter().map(|mv| {
+        perft_hashed(&make_move_ignoring_draws(state, mv), depth - 1, table)
+    }).sum();
+    table.store(hash, depth, count);
+    count
+}
+
+/// Like `perft_divide`, but with the counts after each move computed by `perft_hashed`.
+pub fn perft_divide_hashed(state: &State, depth: u32, table: &mut PerftTable) -> Vec<(Move, u64)> {
+    state.calc_legal_moves().into_iter().map(|mv| {
+        (mv, perft_hashed(&make_move_ignoring_draws(state, mv), depth.saturating_sub(1), table))
+    }).collect()
+}
+
+/// The number of move sequences of a perft and the number of distinct positions they end in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerftUniqueCounts {
+    pub nodes: u64,
+    pub unique_positions: u64,
+}
+
+impl PerftUniqueCounts {
+    /// Returns the average number of move sequences reaching each position, which is 1 without transpositions.
+    pub fn get_transposition_ratio(&self) -> f64 {
+        self.nodes as f64 / self.unique_positions.max(1) as f64
+    }
+}
+
+/// Returns `perft(state, depth)` along with the number of distinct positions at exactly `depth` plies,
+/// identified by their Polyglot hash, which ignores move counters and an en passant square without a capturer.
+/// Positions are expanded one ply at a time, once per distinct position, so memory grows with the number of
+/// positions at the last ply.
+pub fn perft_unique(state: &State, depth: u32) -> PerftUniqueCounts {
+    // each distinct position with the number of move sequences reaching it
+    let mut layer: HashMap<u64, (State, u64)> = HashMap::from([(state.polyglot_hash(), (state.clone(), 1))]);
+    for _ in 0..depth {
+        let mut next_layer: HashMap<u64, (State, u64)> = HashMap::with_capacity(layer.len() * 2);
+        for (state, num_paths) in layer.into_values() {
+            for mv in state.calc_legal_moves() {
+                let next_state = make_move_ignoring_draws(&state, mv);
+                next_layer.entry(next_state.polyglot_hash()).or_insert((next_state, 0)).1 += num_paths;
+            }
+        }
+        layer = next_layer;
+    }
+    PerftUniqueCounts {
+        nodes: layer.values().map(|(_, num_paths)| num_paths).sum(),
+        unique_positions: layer.len() as u64,
+    }
+}
+
+/// Returns `state` after `mv`, without the termination of a draw by rule, so that its moves are still generated.
+/// Checkmate and stalemate are not terminations set by `make_move`, so they still have no moves.
+fn make_move_ignoring_draws(state: &State, mv: Move) -> State {
+    let mut next_state = state.clone();
+    next_state.make_move(mv);
+    next_state.termination = None;
+    next_state
+}
+
+/// A position with its known perft counts, for validating move generation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerftPosition {
+    pub name: &'static str,
+    pub fen: &'static str,
+    /// The perft counts at depths 1, 2, and so on.
+    pub counts: &'static [u64],
+}
+
+/// Well-known perft positions with their counts: the initial position and positions 2 to 6 from the
+/// Chess Programming Wiki, and smaller positions that each target a rule that is easy to get wrong.
+pub const PERFT_POSITIONS: [PerftPosition; 21] = [
+    PerftPosition { name: "initial", fen: "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", counts: &[20, 400, 8902, 197281, 4865609] },
+    PerftPosition { name: "kiwipete", fen: "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", counts: &[48, 2039, 97862, 4085603] },
+    PerftPosition { name: "position 3", fen: "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", counts: &[14, 191, 2812, 43238, 674624] },
+    PerftPosition { name: "position 4", fen: "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", counts: &[6, 264, 9467, 422333] },
+    PerftPosition { name: "position 4 mirrored", fen: "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", counts: &[6, 264, 9467, 422333] },
+    PerftPosition { name: "position 5", fen: "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", counts: &[44, 1486, 62379, 2103487] },
+    PerftPosition { name: "position 6", fen: "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", counts: &[46, 2079, 89890, 3894594] },
+    PerftPosition { name: "illegal en passant, pinned along the rank", fen: "3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", counts: &[18, 92, 1670, 10138, 185429, 1134888] },
+    PerftPosition { name: "illegal en passant, pinned along the diagonal", fen: "8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", counts: &[13, 102, 1266, 10276, 135655, 1015133] },
+    PerftPosition { name: "en passant capture gives check", fen: "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", counts: &[15, 126, 1928, 13931, 206379, 1440467] },
+    PerftPosition { name: "short castling gives check", fen: "5k2/8/8/8/8/8/8/4K2R w K - 0 1", counts: &[15, 66, 1198, 6399, 120330, 661072] },
+    PerftPosition { name: "long castling gives check", fen: "3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", counts: &[16, 71, 1286, 7418, 141077, 803711] },
+    PerftPosition { name: "castling rights lost by captures", fen: "r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", counts: &[26, 1141, 27826, 1274206] },
+    PerftPosition { name: "castling prevented by attacks", fen: "r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", counts: &[44, 1494, 50509, 1720476] },
+    PerftPosition { name: "promotion out of check", fen: "2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", counts: &[11, 133, 1442, 19174, 266199, 3821001] },
+    PerftPosition { name: "discovered check", fen: "8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", counts: &[29, 165, 5160, 31961, 1004658] },
+    PerftPosition { name: "promotion gives check", fen: "4k3/1P6/8/8/8/8/K7/8 w - - 0 1", counts: &[9, 40, 472, 2661, 38983, 217342] },
+    PerftPosition { name: "underpromotion gives check", fen: "8/P1k5/K7/8/8/8/8/8 w - - 0 1", counts: &[6, 27, 273, 1329, 18135, 92683] },
+    PerftPosition { name: "self stalemate", fen: "K1k5/8/P7/8/8/8/8/8 w - - 0 1", counts: &[2, 6, 13, 63, 382, 2217] },
+    PerftPosition { name: "stalemate and checkmate after promotion", fen: "8/k1P5/8/1K6/8/8/8/8 w - - 0 1", counts: &[10, 25, 268, 926, 10857, 43261, 567584] },
+    PerftPosition { name: "stalemate and checkmate with few pieces", fen: "8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", counts: &[37, 183, 6559, 23527] },
+];
+
+/// A perft count that differs from the known count of a position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PerftMismatch {
+    pub name: &'static str,
+    pub fen: &'static str,
+    pub depth: u32,
+    pub expected: u64,
+    pub actual: u64,
+}
+
+impl Display for PerftMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Perft mismatch for {} ({}) at depth {}: expected {}, got {}", self.name, self.fen, self.depth, self.expected, self.actual)
+    }
+}
+
+impl PerftPosition {
+    /// Checks the perft counts of the position up to `max_depth`, shallowest first.
+    pub fn verify(&self, max_depth: u32) -> Result<(), PerftMismatch> {
+        let state = State::from_fen(self.fen).expect("perft positions are valid");
+        for (depth, expected) in (1..=max_depth).zip(self.counts) {
+            let actual = perft(&state, depth);
+            if actual != *expected {
+                return Err(PerftMismatch { name: self.name, fen: self.fen, depth, expected: *expected, actual });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Checks every position in `PERFT_POSITIONS` at all of its known depths, returning the first mismatch.
+/// This visits tens of millions of nodes, so it is meant for release builds; see `verify_all_to_depth`.
+pub fn verify_all() -> Result<(), PerftMismatch> {
+    verify_all_to_depth(u32::MAX)
+}
+
+/// Like `verify_all`, but only checks depths up to `max_depth`.
+pub fn verify_all_to_depth(max_depth: u32) -> Result<(), PerftMismatch> {
+    PERFT_POSITIONS.iter().try_for_each(|position| position.verify(max_depth))
+}
+
+/// A position where `State::calc_legal_moves` disagrees with `State::calc_legal_moves_legacy`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MovegenMismatch {
+    pub fen: String,
+    /// Moves only generated by `calc_legal_moves`, in UCI notation.
+    pub extra_moves: Vec<String>,
+    /// Moves only generated by `calc_legal_moves_legacy`, in UCI notation.
+    pub missing_moves: Vec<String>,
+    /// The perft counts of both generators, if they differ.
+    pub perft_counts: Option<(u64, u64)>,
+}
+
+impl Display for MovegenMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Move generation mismatch at {}", self.fen)?;
+        if !self.extra_moves.is_empty() {
+            write!(f, ", extra moves: {}", self.extra_moves.join(" "))?;
+        }
+        if !self.missing_moves.is_empty() {
+            write!(f, ", missing moves: {}", self.missing_moves.join(" "))?;
+        }
+        if let Some((perft_count, legacy_perft_count)) = self.perft_counts {
+            write!(f, ", perft {} vs legacy {}", perft_count, legacy_perft_count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks that both legal move generators produce the same moves at `state`,
+/// and the same perft counts up to `perft_depth`.
+pub fn compare_movegen_with_legacy(state: &State, perft_depth: u32) -> Result<(), MovegenMismatch> {
+    let moves: Vec<String> = state.calc_legal_moves().iter().map(Move::to_uci).collect();
+    let legacy_moves: Vec<String> = state.calc_legal_moves_legacy().iter().map(Move::to_uci).collect();
+    let extra_moves: Vec<String> = moves.iter().filter(|mv| !legacy_moves.contains(mv)).cloned().collect();
+    let missing_moves: Vec<String> = legacy_moves.iter().filter(|mv| !moves.contains(mv)).cloned().collect();
+
+    let perft_count = perft(state, perft_depth);
+    let legacy_perft_count = perft_legacy(state, perft_depth);
+    let perft_counts = (perft_count != legacy_perft_count).then_some((perft_count, legacy_perft_count));
+
+    if extra_moves.is_empty() && missing_moves.is_empty() && moves.len() == legacy_moves.len() && perft_counts.is_none() {
+        return Ok(());
+    }
+    Err(MovegenMismatch {
+        fen: state.to_fen(),
+        extra_moves,
+        missing_moves,
+        perft_counts,
+    })
+}
+
+/// Plays `num_playouts` random games of up to `max_plies` plies from `state`, and compares both legal move generators
+/// at every position reached with `compare_movegen_with_legacy`. The games are determined by `seed`.
+/// Returns the number of positions compared, or the first mismatch found.
+pub fn fuzz_movegen(state: &State, seed: u64, num_playouts: usize, max_plies: usize, perft_depth: u32) -> Result<usize, MovegenMismatch> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut num_positions = 0;
+    for _ in 0..num_playouts {
+        let mut state = state.clone();
+        for _ in 0..max_plies {
+            compare_movegen_with_legacy(&state, perft_depth)?;
+            num_positions += 1;
+            match state.calc_legal_moves().choose(&mut rng) {
+                Some(mv) => state.make_move(*mv),
+                None => break
+            }
+        }
+    }
+    Ok(num_positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::iter::zip;
+    use std::str::FromStr;
+    use chess;
+    use crate::utils::{PieceType, Square};
+    use crate::r#move::{Move, MoveFlag};
+    use crate::state::State;
+    use super::*;
+
+    #[test]
+    fn test_perft() {
+        let state = State::initial();
+        assert_eq!(perft(&state, 0), 1);
+        assert_eq!(perft(&state, 3), 8902);
+        let divided = perft_divide(&state, 3);
+        assert_eq!(divided.len(), 20);
+        assert_eq!(divided.iter().map(|(_, num_nodes)| num_nodes).sum::<u64>(), 8902);
+    }
+
+    #[test]
+    fn test_perft_hashed() {
+        // a small table, so that slots are overwritten
+        let mut table = PerftTable::new(1000);
+        assert_eq!(table.get_num_entries(), 1024);
+        for position in &PERFT_POSITIONS[..7] {
+            let state = State::from_fen(position.fen).unwrap();
+            for depth in 0..=3 {
+                assert_eq!(perft_hashed(&state, depth, &mut table), perft(&state, depth), "{} at depth {}", position.name, depth);
+            }
+            assert_eq!(perft_divide_hashed(&state, 3, &mut table), perft_divide(&state, 3));
+        }
+        assert_eq!(perft_hashed(&State::initial(), 4, &mut PerftTable::with_megabytes(1)), PERFT_POSITIONS[0].counts[3]);
+        assert!(PerftTable::with_megabytes(1).get_num_entries() * size_of::<PerftEntry>() <= 1 << 20);
+    }
+
+    #[test]
+    fn test_perft_unique() {
+        let state = State::initial();
+        let counts: Vec<PerftUniqueCounts> = (0..=3).map(|depth| perft_unique(&state, depth)).collect();
+        let unique_positions: Vec<u64> = counts.iter().map(|counts| counts.unique_positions).collect();
+        assert_eq!(unique_positions, [1, 20, 400, 5362]);
+        for (depth, counts) in counts.iter().enumerate() {
+            assert_eq!(counts.nodes, perft(&state, depth as u32));
+        }
+        assert!(counts[3].get_transposition_ratio() > 1.);
+
+        let state = State::from_fen(PERFT_POSITIONS[1].fen).unwrap();
+        let counts = perft_unique(&state, 3);
+        assert_eq!(counts.nodes, PERFT_POSITIONS[1].counts[2]);
+        assert!(counts.unique_positions < counts.nodes);
+    }
+
+    #[test]
+    fn test_verify_all_to_depth() {
+        if let Err(mismatch) = verify_all_to_depth(3) {
+            panic!("{}", mismatch);
+        }
+        let position = PerftPosition { counts: &[20, 401], ..PERFT_POSITIONS[0] };
+        let mismatch = position.verify(3).unwrap_err();
+        assert_eq!((mismatch.depth, mismatch.expected, mismatch.actual), (2, 401, 400));
+    }
+
+    /// Run with `cargo test --release --features long-tests`.
+    #[cfg(feature = "long-tests")]
+    #[test]
+    fn test_verify_all() {
+        if let Err(mismatch) = verify_all() {
+            panic!("{}", mismatch);
+        }
+    }
+
+    const FUZZ_FENS: [&str; 5] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    ];
+
+    #[test]
+    fn test_fuzz_movegen() {
+        for fen in FUZZ_FENS {
+            let state = State::from_fen(fen).unwrap();
+            if let Err(mismatch) = fuzz_movegen(&state, 0, 2, 60, 1) {
+                panic!("{}", mismatch);
+            }
+        }
+    }
+
+    /// Run with `cargo test --release --features long-tests`.
+    #[cfg(feature = "long-tests")]
+    #[test]
+    fn test_fuzz_movegen_long() {
+        for fen in FUZZ_FENS {
+            let state = State::from_fen(fen).unwrap();
+            for seed in 0..32 {
+                if let Err(mismatch) = fuzz_movegen(&state, seed, 8, 300, 2) {
+                    panic!("seed {}: {}", seed, mismatch);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_chess() {
+        let board = chess::Board::default();
+        let movegen = chess::MoveGen::new_legal(&board);
+        assert_eq!(movegen.len(), 20);
+    }
+
+    fn are_squares_equal(square: Square, chess_square: chess::Square) -> bool {
+        let file = square.get_file();
+        let rank = square.get_rank();
+        let chess_square_number = rank * 8 + file;
+        let created_chess_square = unsafe { chess::Square::new(chess_square_number) };
+        created_chess_square == chess_square
+    }
+    
+    fn are_promotions_equal(promotion: PieceType, chess_promotion: Option<chess::Piece>) -> bool {
+        match chess_promotion {
+            None => promotion == PieceType::Rook,
+            Some(chess_promotion) => {
+                let promotion_piece = match chess_promotion {
+                    chess::Piece::Queen => PieceType::Queen,
+                    chess::Piece::Rook => PieceType::Rook,
+                    chess::Piece::Bishop => PieceType::Bishop,
+                    chess::Piece::Knight => PieceType::Knight,
+                    _ => panic!()
+                };
+                promotion == promotion_piece
+            }
+        }
+    }
+
+    fn are_moves_equal(mv: Move, chess_mv: chess::ChessMove) -> bool {
+        let (dst_square, src_square, promotion, _) = mv.unpack();
+        let (chess_dst_square, chess_src_square, chess_promotion) = (chess_mv.get_dest(), chess_mv.get_source(), chess_mv.get_promotion());
+        are_squares_equal(dst_square, chess_dst_square) &&
+            are_squares_equal(src_square, chess_src_square) &&
+            are_promotions_equal(promotion, chess_promotion)
+    }
+
+    #[test]
+    fn test_are_moves_equal() {
+        let mv = Move::new(Square::E4, Square::E2, Move::DEFAULT_PROMOTION_VALUE, MoveFlag::NormalMove);
+        let chess_mv = chess::ChessMove::from_str("e2e4").unwrap();
+        assert!(are_moves_equal(mv, chess_mv));
+
+        let mv = Move::new(Square::H8, Square::G7, PieceType::Knight, MoveFlag::Promotion);
+        let chess_mv = chess::ChessMove::from_str("g7h8n").unwrap();
+        assert!(are_moves_equal(mv, chess_mv));
+
+        let mv = Move::new(Square::F1, Square::F2, PieceType::Bishop, MoveFlag::Promotion);
+        let chess_mv = chess::ChessMove::from_str("f2f1b").unwrap();
+        assert!(are_moves_equal(mv, chess_mv));
+    }
+
+    fn count_moves_and_test(state: &State, validation_board: chess::Board, depth: u32) -> (u64, u64) {
+        if depth == 0 {
+            return (1, 1);
+        }
+
+        let found_fen = state.to_fen(); // for debugging
+        let expected_fen = validation_board.to_string(); // for debugging
+        // assert_eq!(found_fen, expected_fen);
+        let found_fen_split = found_fen.split_ascii_whitespace().collect::<Vec<_>>();
+        let expected_fen_split = expected_fen.split_ascii_whitespace().collect::<Vec<_>>();
+        let (found_fen_board, found_fen_side_to_move, found_fen_castling_rights) = (found_fen_split[0], found_fen_split[1], found_fen_split[2]);
+        let (expected_fen_board, expected_fen_side_to_move, expected_fen_castling_rights) = (expected_fen_split[0], expected_fen_split[1], expected_fen_split[2]);
+        assert_eq!(found_fen_split[0..3], expected_fen_split[0..3]); // ensure board, side to move, and castling rights are the same
+
+        let found_moves_unordered = state.calc_legal_moves();
+        let expected_moves = chess::MoveGen::new_legal(&validation_board);
+        let mut found_moves_ordered;
+
+        let (mut found_count, mut known_count) = (found_moves_unordered.len() as u64, expected_moves.len() as u64);
+
+        found_moves_ordered = Vec::with_capacity(found_count as usize);
+        for expected_move in expected_moves {
+            let expected_move_uci = expected_move.to_string(); // for debugging
+            let mut corresponding_move = None;
+            for found_move in &found_moves_unordered {
+                if are_moves_equal(*found_move, expected_move) {
+                    assert!(corresponding_move.is_none());
+                    corresponding_move = Some(*found_move);
+                    break;
+                }
+            }
+            match corresponding_move {
+                None => {
+                    let moves_uci = found_moves_unordered.iter().map(|mv| mv.to_uci()).collect::<Vec<_>>(); // for debugging
+                    panic!()
+                },
+                Some(found_move) => {
+                    assert!(!found_moves_ordered.contains(&found_move));
+                    found_moves_ordered.push(found_move);
+                }
+            }
+        }
+        if found_moves_ordered.len() != found_moves_unordered.len() {
+            let found_moves_set = found_moves_unordered.iter().collect::<HashSet<_>>();
+            let expected_moves_set = found_moves_ordered.iter().collect::<HashSet<_>>();
+            let missing_moves = found_moves_set.difference(&expected_moves_set).collect::<Vec<_>>();
+            let missing_moves_uci = missing_moves.iter().map(|mv| mv.to_uci()).collect::<Vec<_>>(); // for debugging
+            assert!(missing_moves.is_empty());
+            panic!();
+        }
+
+        for (found_move, expected_move) in zip(found_moves_ordered, chess::MoveGen::new_legal(&validation_board)) {
+            let mut new_state = state.clone();
+            new_state.make_move(found_move);
+            let mut new_validation_board = validation_board.clone();
+            validation_board.make_move(expected_move, &mut new_validation_board);
+            let (found_count_inc, known_count_inc) = count_moves_and_test(&new_state, new_validation_board, depth - 1);
+            found_count += found_count_inc;
+            known_count += known_count_inc;
+        }
+
+        assert_eq!(found_count, known_count);
+        (found_count, known_count)
+    }
+    
+    fn generic_depth_test(fen: Option<&str>, depth: u32) {
+        let (state, validation_board) = match fen {
+            Some(fen) => {
+                let state = State::from_fen(fen).unwrap();
+                let validation_board = chess::Board::from_str(fen).unwrap();
+                (state, validation_board)
+            }
+            None => {
+                let state = State::initial();
+                let validation_board = chess::Board::default();
+                (state, validation_board)
+            }
+        };
+        let possible_moves = state.calc_legal_moves();
+        let (found_count, known_count) = count_moves_and_test(&state, validation_board, depth);
+        assert_eq!(found_count, known_count);
+        println!("{} moves", found_count);
+    }
+
+    #[test]
+    fn test_initial_depth_4() {
+        generic_depth_test(None, 4);
+    }
+    
+    #[test]
+    fn test_arb_depth_5() {
+        let fen = "1k6/1P6/3P1N2/2K3R1/8/3p3B/8/8 w - - 0 1";
+        generic_depth_test(Some(fen), 5);
+    }
+    
+    #[test]
+    fn test_kiwipete_depth_4() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        generic_depth_test(Some(fen), 4);
+    }
+
+    #[test]
+    fn test_p3_depth_5() {
+        let fen = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+        generic_depth_test(Some(fen), 5);
+    }
+
+    #[test]
+    fn test_p4_depth_4() {
+        let fen = "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1";
+        generic_depth_test(Some(fen), 4);
+    }
+
+    #[test]
+    fn test_p5_depth_4() {
+        let fen = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+        generic_depth_test(Some(fen), 4);
+    }
+
+    #[test]
+    fn test_p6_depth_4() {
+        let fen = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+        generic_depth_test(Some(fen), 4);
+    }
+}
\ No newline at end of file
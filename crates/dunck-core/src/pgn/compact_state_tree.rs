@@ -0,0 +1,222 @@
+//! A compact form of `PgnStateTree` for large game databases. Nodes store only their move and a few fields;
+//! full states are only kept every few plies and reconstructed on demand by replaying moves from the nearest one.
+
+use std::collections::HashMap;
+use indexmap::IndexMap;
+use crate::pgn::state_tree::PgnStateTree;
+use crate::pgn::state_tree_traverser::PgnStateTreeTraverseError;
+use crate::r#move::Move;
+use crate::state::{State, Termination};
+use crate::utils::Bitboard;
+
+/// The default number of plies between stored states.
+pub const DEFAULT_SNAPSHOT_INTERVAL: u16 = 16;
+
+struct CompactNode {
+    mv: Option<Move>,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    ply: u16,
+    zobrist_hash: Bitboard,
+    termination: Option<Termination>,
+}
+
+pub struct CompactPgnStateTree {
+    pub tags: IndexMap<String, String>,
+    nodes: Vec<CompactNode>,
+    snapshots: HashMap<usize, State>,
+}
+
+impl CompactPgnStateTree {
+    /// The id of the root node, before any move.
+    pub const ROOT: usize = 0;
+
+    /// Converts `tree`, storing a state for the root and every node `snapshot_interval` plies deep.
+    /// Node ids follow the order of `PgnStateTree::get_all_nodes`.
+    pub fn from_tree(tree: &PgnStateTree, snapshot_interval: u16) -> CompactPgnStateTree {
+        assert!(snapshot_interval > 0, "Snapshot interval must be positive");
+        let mut compact_tree = CompactPgnStateTree {
+            tags: tree.tags.clone(),
+            nodes: Vec::new(),
+            snapshots: HashMap::new(),
+        };
+
+        let mut stack = vec![(tree.head.clone(), None)];
+        while let Some((node, parent)) = stack.pop() {
+            let node = node.borrow();
+            let id = compact_tree.nodes.len();
+            let ply = parent.map_or(0, |parent: usize| compact_tree.nodes[parent].ply + 1);
+            compact_tree.nodes.push(CompactNode {
+                mv: node.move_and_san_and_previous_node.as_ref().map(|(mv, _, _)| *mv),
+                parent,
+                children: Vec::new(),
+                ply,
+                zobrist_hash: node.zobrist_hash,
+                termination: node.state_after_move.termination,
+            });
+            if let Some(parent) = parent {
+                compact_tree.nodes[parent].children.push(id);
+            }
+            if ply % snapshot_interval == 0 {
+                compact_tree.snapshots.insert(id, node.state_after_move.clone());
+            }
+            stack.extend(node.next_nodes.iter().rev().map(|next_node| (next_node.clone(), Some(id))));
+        }
+        compact_tree
+    }
+
+    pub fn get_num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn get_num_snapshots(&self) -> usize {
+        self.snapshots.len()
+    }
+
+    /// Returns the move leading to `node`, or `None` for the root.
+    pub fn get_move(&self, node: usize) -> Option<Move> {
+        self.nodes[node].mv
+    }
+
+    pub fn get_parent(&self, node: usize) -> Option<usize> {
+        self.nodes[node].parent
+    }
+
+    /// Returns the children of `node`, main line first.
+    pub fn get_children(&self, node: usize) -> &[usize] {
+        &self.nodes[node].children
+    }
+
+    pub fn get_zobrist_hash(&self, node: usize) -> Bitboard {
+        self.nodes[node].zobrist_hash
+    }
+
+    /// Reconstructs the state after the move leading to `node`, replaying moves from the nearest stored state.
+    pub fn get_state(&self, node: usize) -> State {
+        let mut moves = Vec::new();
+        let mut current = node;
+        while !self.snapshots.contains_key(&current) {
+            moves.push(self.nodes[current].mv.unwrap());
+            current = self.nodes[current].parent.unwrap();
+        }
+
+        let mut state = self.snapshots[&current].clone();
+        for mv in moves.into_iter().rev() {
+            state.make_move(mv);
+        }
+        state.termination = self.nodes[node].termination;
+        state
+    }
+}
+
+/// Walks a `CompactPgnStateTree` like `PgnStateTreeTraverser`, keeping the current state up to date
+/// by making and unmaking moves instead of reconstructing it.
+pub struct CompactPgnStateTreeTraverser<'a> {
+    pub tree: &'a CompactPgnStateTree,
+    pub current_node: usize,
+    current_state: State,
+}
+
+impl<'a> CompactPgnStateTreeTraverser<'a> {
+    pub fn new(tree: &'a CompactPgnStateTree) -> CompactPgnStateTreeTraverser<'a> {
+        CompactPgnStateTreeTraverser {
+            tree,
+            current_node: CompactPgnStateTree::ROOT,
+            current_state: tree.get_state(CompactPgnStateTree::ROOT),
+        }
+    }
+
+    pub fn get_current_state(&self) -> State {
+        self.current_state.clone()
+    }
+
+    /// Returns the move leading to the current node and its SAN.
+    pub fn get_played_move(&self) -> Result<(Move, String), PgnStateTreeTraverseError> {
+        let mv = self.tree.get_move(self.current_node).ok_or(PgnStateTreeTraverseError::NoMovePlayed)?;
+        let mut initial_state = self.current_state.clone();
+        initial_state.termination = None;
+        initial_state.unmake_move(mv);
+        let san = mv.to_san(&initial_state, &self.current_state, &initial_state.calc_legal_moves());
+        Ok((mv, san))
+    }
+
+    pub fn has_next(&self) -> bool {
+        !self.tree.get_children(self.current_node).is_empty()
+    }
+
+    pub fn get_all_next(&self) -> Vec<Move> {
+        self.tree.get_children(self.current_node).iter().map(|child| self.tree.get_move(*child).unwrap()).collect()
+    }
+
+    pub fn step_forward_with_main_line(&mut self) -> Result<(), PgnStateTreeTraverseError> {
+        if !self.has_next() {
+            return Err(PgnStateTreeTraverseError::NoNextNode);
+        }
+        self.step_forward_with_variation_by_index(0)
+    }
+
+    /// Steps forward to the child at `variation_index`, where 0 is the main line.
+    pub fn step_forward_with_variation_by_index(&mut self, variation_index: usize) -> Result<(), PgnStateTreeTraverseError> {
+        let child = *self.tree.get_children(self.current_node).get(variation_index)
+            .ok_or(PgnStateTreeTraverseError::VariationDoesNotExist)?;
+        self.current_state.make_move(self.tree.get_move(child).unwrap());
+        self.current_state.termination = self.tree.nodes[child].termination;
+        self.current_node = child;
+        Ok(())
+    }
+
+    pub fn step_backward(&mut self) -> Result<(), PgnStateTreeTraverseError> {
+        let parent = self.tree.get_parent(self.current_node).ok_or(PgnStateTreeTraverseError::NoPreviousNode)?;
+        self.current_state.unmake_move(self.tree.get_move(self.current_node).unwrap());
+        self.current_state.termination = self.tree.nodes[parent].termination;
+        self.current_node = parent;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::str::FromStr;
+    use super::*;
+
+    #[test]
+    fn test_compact_tree_matches_tree() {
+        let pgn = fs::read_to_string("data/pgn_test_files/complex.pgn").unwrap();
+        let tree = PgnStateTree::from_str(&pgn).unwrap();
+        let compact_tree = CompactPgnStateTree::from_tree(&tree, 8);
+
+        let nodes = tree.get_all_nodes();
+        assert_eq!(compact_tree.get_num_nodes(), nodes.len());
+        assert!(compact_tree.get_num_snapshots() * 4 < nodes.len());
+        for (id, node) in nodes.iter().enumerate() {
+            let state = compact_tree.get_state(id);
+            assert_eq!(state.to_fen(), node.borrow().state_after_move.to_fen());
+            assert_eq!(state.termination, node.borrow().state_after_move.termination);
+            assert_eq!(compact_tree.get_zobrist_hash(id), node.borrow().zobrist_hash);
+        }
+    }
+
+    #[test]
+    fn test_compact_tree_traverser() {
+        let tree = PgnStateTree::from_str("1. e4 e5 2. Nf3 ( 2. f4 exf4 ) 2... Nc6").unwrap();
+        let compact_tree = CompactPgnStateTree::from_tree(&tree, DEFAULT_SNAPSHOT_INTERVAL);
+        let mut traverser = CompactPgnStateTreeTraverser::new(&compact_tree);
+        assert_eq!(traverser.get_played_move(), Err(PgnStateTreeTraverseError::NoMovePlayed));
+
+        traverser.step_forward_with_main_line().unwrap();
+        traverser.step_forward_with_main_line().unwrap();
+        assert_eq!(traverser.get_all_next().len(), 2);
+        traverser.step_forward_with_variation_by_index(1).unwrap();
+        traverser.step_forward_with_main_line().unwrap();
+        assert_eq!(traverser.get_played_move().unwrap().1, "exf4");
+        assert_eq!(traverser.get_current_state().to_fen(), compact_tree.get_state(traverser.current_node).to_fen());
+        assert_eq!(traverser.step_forward_with_main_line(), Err(PgnStateTreeTraverseError::NoNextNode));
+
+        traverser.step_backward().unwrap();
+        traverser.step_backward().unwrap();
+        traverser.step_forward_with_main_line().unwrap();
+        assert_eq!(traverser.get_played_move().unwrap().1, "Nf3");
+        assert_eq!(traverser.step_forward_with_variation_by_index(1), Err(PgnStateTreeTraverseError::VariationDoesNotExist));
+    }
+}
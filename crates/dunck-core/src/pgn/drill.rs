@@ -0,0 +1,324 @@
+//! Drilling an opening repertoire: the drill plays the opponent's moves from the repertoire,
+//! and checks that the user answers with a repertoire move, keeping success statistics for every line.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use crate::pgn::query::get_position_key;
+use crate::pgn::state_tree::PgnStateTree;
+use crate::r#move::Move;
+use crate::state::State;
+use crate::utils::Color;
+
+/// How often a line was drilled, and how often it was completed without mistakes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DrillStats {
+    pub num_attempts: u32,
+    pub num_successes: u32,
+    /// The number of wrong moves over all attempts.
+    pub num_mistakes: u32,
+}
+
+impl DrillStats {
+    pub fn get_success_rate(&self) -> Option<f64> {
+        if self.num_attempts == 0 {
+            None
+        } else {
+            Some(self.num_successes as f64 / self.num_attempts as f64)
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrillMoveResult {
+    /// The move is in the repertoire and was played, followed by the opponent's `reply`, if there is one.
+    /// Once the repertoire has no more moves for the user, the line is complete.
+    Correct { reply: Option<(Move, String)>, is_line_complete: bool },
+    /// The move is not in the repertoire and was not played. `expected` are the repertoire moves, in SAN.
+    Incorrect { expected: Vec<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DrillError {
+    NoSuchLine(usize),
+    NoLineStarted,
+    LineComplete,
+    IllegalMove(String),
+}
+
+impl Display for DrillError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrillError::NoSuchLine(index) => write!(f, "No line with index {}", index),
+            DrillError::NoLineStarted => write!(f, "No line was started"),
+            DrillError::LineComplete => write!(f, "The line is already complete"),
+            DrillError::IllegalMove(mv) => write!(f, "Illegal move: {}", mv),
+        }
+    }
+}
+
+impl Error for DrillError {}
+
+struct DrillAttempt {
+    line_index: usize,
+    state: State,
+    /// The number of moves played that follow the line. Once a move leaves it, the opponent's replies
+    /// are the first repertoire moves instead.
+    num_line_moves_played: usize,
+    is_on_line: bool,
+    num_mistakes: u32,
+    is_complete: bool,
+}
+
+/// Drills the lines of a repertoire for `side`. Moves are looked up by position rather than by tree node,
+/// so a move that reaches a repertoire position by another move order is accepted, with the repertoire's
+/// moves from every node reaching that position.
+pub struct RepertoireDrill {
+    pub side: Color,
+    initial_state: State,
+    moves_by_position: HashMap<String, Vec<(Move, String)>>,
+    /// The moves from the root to each leaf of the tree.
+    lines: Vec<Vec<(Move, String)>>,
+    stats: Vec<DrillStats>,
+    attempt: Option<DrillAttempt>,
+}
+
+impl RepertoireDrill {
+    pub fn new(tree: &PgnStateTree, side: Color) -> RepertoireDrill {
+        let mut moves_by_position: HashMap<String, Vec<(Move, String)>> = HashMap::new();
+        for node in tree.get_all_nodes() {
+            let node = node.borrow();
+            let moves = moves_by_position.entry(get_position_key(&node.state_after_move)).or_default();
+            for next_node in node.next_nodes.iter() {
+                if let Some((mv, san, _)) = &next_node.borrow().move_and_san_and_previous_node {
+                    if !moves.iter().any(|(existing_move, _)| existing_move == mv) {
+                        moves.push((*mv, san.clone()));
+                    }
+                }
+            }
+        }
+
+        let mut lines = Vec::new();
+        let mut stack = vec![(tree.head.clone(), Vec::new())];
+        while let Some((node, line)) = stack.pop() {
+            let node = node.borrow();
+            if !node.has_next() {
+                if !line.is_empty() {
+                    lines.push(line);
+                }
+                continue;
+            }
+            for next_node in node.next_nodes.iter().rev() {
+                let (mv, san, _) = next_node.borrow().move_and_san_and_previous_node.clone().unwrap();
+                let mut next_line = line.clone();
+                next_line.push((mv, san));
+                stack.push((next_node.clone(), next_line));
+            }
+        }
+
+        let num_lines = lines.len();
+        RepertoireDrill {
+            side,
+            initial_state: tree.head.borrow().state_after_move.clone(),
+            moves_by_position,
+            lines,
+            stats: vec![DrillStats::default(); num_lines],
+            attempt: None,
+        }
+    }
+
+    /// Returns the lines of the repertoire, in the order they appear in the tree.
+    pub fn get_lines(&self) -> &[Vec<(Move, String)>] {
+        &self.lines
+    }
+
+    pub fn get_stats(&self, line_index: usize) -> Option<&DrillStats> {
+        self.stats.get(line_index)
+    }
+
+    /// Returns the line to drill next: the one drilled least, then the one with the lowest success rate.
+    pub fn select_line(&self) -> Option<usize> {
+        (0..self.lines.len()).min_by(|a, b| {
+            let (a, b) = (&self.stats[*a], &self.stats[*b]);
+            a.num_attempts.cmp(&b.num_attempts)
+                .then(a.get_success_rate().unwrap_or(0.).total_cmp(&b.get_success_rate().unwrap_or(0.)))
+        })
+    }
+
+    /// Starts drilling the line with index `line_index`, abandoning the current attempt, if any, without recording it.
+    /// Plays the opponent's moves until it is the user's turn, and returns the moves played.
+    pub fn start_line(&mut self, line_index: usize) -> Result<Vec<(Move, String)>, DrillError> {
+        if line_index >= self.lines.len() {
+            return Err(DrillError::NoSuchLine(line_index));
+        }
+        self.attempt = Some(DrillAttempt {
+            line_index,
+            state: self.initial_state.clone(),
+            num_line_moves_played: 0,
+            is_on_line: true,
+            num_mistakes: 0,
+            is_complete: false,
+        });
+        let mut replies = Vec::new();
+        while let Some(reply) = self.play_reply() {
+            replies.push(reply);
+        }
+        self.complete_if_out_of_moves();
+        Ok(replies)
+    }
+
+    /// Returns the position the user has to move in, or None if no line was started.
+    pub fn get_current_state(&self) -> Option<&State> {
+        self.attempt.as_ref().map(|attempt| &attempt.state)
+    }
+
+    /// Returns the repertoire moves in the current position, the first of which is the line's move if still on it.
+    pub fn get_expected_moves(&self) -> Vec<(Move, String)> {
+        let attempt = match &self.attempt {
+            Some(attempt) => attempt,
+            None => return Vec::new()
+        };
+        let mut moves = self.get_repertoire_moves(&attempt.state).to_vec();
+        if let Some(line_move) = self.get_line_move(attempt) {
+            moves.retain(|(mv, _)| *mv != line_move.0);
+            moves.insert(0, line_move);
+        }
+        moves
+    }
+
+    /// Checks the user's move against the repertoire. A correct move is played, followed by the opponent's reply.
+    /// Illegal moves are an error rather than a mistake.
+    pub fn submit_move(&mut self, mv: Move) -> Result<DrillMoveResult, DrillError> {
+        let attempt = self.attempt.as_ref().ok_or(DrillError::NoLineStarted)?;
+        if attempt.is_complete {
+            return Err(DrillError::LineComplete);
+        }
+        if !attempt.state.calc_legal_moves().contains(&mv) {
+            return Err(DrillError::IllegalMove(mv.to_uci()));
+        }
+
+        if !self.get_repertoire_moves(&attempt.state).iter().any(|(repertoire_move, _)| *repertoire_move == mv) {
+            let expected = self.get_expected_moves().into_iter().map(|(_, san)| san).collect();
+            self.attempt.as_mut().unwrap().num_mistakes += 1;
+            return Ok(DrillMoveResult::Incorrect { expected });
+        }
+
+        self.play(mv);
+        let reply = self.play_reply();
+        let is_line_complete = self.complete_if_out_of_moves();
+        Ok(DrillMoveResult::Correct { reply, is_line_complete })
+    }
+
+    /// Like `submit_move`, with the move given in SAN.
+    pub fn submit_san(&mut self, san: &str) -> Result<DrillMoveResult, DrillError> {
+        let state = self.get_current_state().ok_or(DrillError::NoLineStarted)?;
+        let legal_moves = state.calc_legal_moves();
+        let mv = legal_moves.iter().find(|mv| {
+            let mut next_state = state.clone();
+            next_state.make_move(**mv);
+            mv.to_san(state, &next_state, &legal_moves) == san
+        });
+        match mv {
+            Some(mv) => self.submit_move(*mv),
+            None => Err(DrillError::IllegalMove(san.to_string()))
+        }
+    }
+
+    fn get_repertoire_moves(&self, state: &State) -> &[(Move, String)] {
+        self.moves_by_position.get(&get_position_key(state)).map(|moves| moves.as_slice()).unwrap_or(&[])
+    }
+
+    fn get_line_move(&self, attempt: &DrillAttempt) -> Option<(Move, String)> {
+        if !attempt.is_on_line {
+            return None;
+        }
+        self.lines[attempt.line_index].get(attempt.num_line_moves_played).cloned()
+    }
+
+    fn play(&mut self, mv: Move) {
+        let line_move = self.get_line_move(self.attempt.as_ref().unwrap());
+        let attempt = self.attempt.as_mut().unwrap();
+        attempt.state.make_move(mv);
+        if line_move.is_some_and(|(line_move, _)| line_move == mv) {
+            attempt.num_line_moves_played += 1;
+        } else {
+            attempt.is_on_line = false;
+        }
+    }
+
+    /// Plays the opponent's move, if it is their turn and the repertoire has one: the line's move if still on it,
+    /// else the first repertoire move.
+    fn play_reply(&mut self) -> Option<(Move, String)> {
+        let attempt = self.attempt.as_ref()?;
+        if attempt.state.side_to_move == self.side {
+            return None;
+        }
+        let reply = self.get_line_move(attempt).or_else(|| self.get_repertoire_moves(&attempt.state).first().cloned())?;
+        self.play(reply.0);
+        Some(reply)
+    }
+
+    /// Completes the attempt and records it if the user has no more moves to find. Returns whether it is complete.
+    fn complete_if_out_of_moves(&mut self) -> bool {
+        let attempt = self.attempt.as_ref().unwrap();
+        let is_out_of_moves = attempt.state.side_to_move != self.side || self.get_repertoire_moves(&attempt.state).is_empty();
+        if is_out_of_moves && !attempt.is_complete {
+            let stats = &mut self.stats[attempt.line_index];
+            stats.num_attempts += 1;
+            stats.num_mistakes += attempt.num_mistakes;
+            if attempt.num_mistakes == 0 {
+                stats.num_successes += 1;
+            }
+            self.attempt.as_mut().unwrap().is_complete = true;
+        }
+        is_out_of_moves
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::*;
+
+    #[test]
+    fn test_drill() {
+        let tree = PgnStateTree::from_str("1. e4 e5 ( 1... c5 2. Nf3 d6 ) 2. Nf3 Nc6 3. Bb5").unwrap();
+        let mut drill = RepertoireDrill::new(&tree, Color::White);
+        assert_eq!(drill.get_lines().len(), 2);
+        assert_eq!(drill.submit_san("e4"), Err(DrillError::NoLineStarted));
+
+        assert_eq!(drill.start_line(1), Ok(Vec::new()));
+        assert_eq!(drill.submit_san("d4"), Ok(DrillMoveResult::Incorrect { expected: vec!["e4".to_string()] }));
+        assert!(matches!(drill.submit_san("e4"), Ok(DrillMoveResult::Correct { reply: Some((_, ref san)), is_line_complete: false }) if san == "c5"));
+        assert!(matches!(drill.submit_san("Nf3"), Ok(DrillMoveResult::Correct { reply: Some((_, ref san)), is_line_complete: true }) if san == "d6"));
+        assert_eq!(drill.submit_san("Nc3"), Err(DrillError::LineComplete));
+        assert_eq!(drill.get_stats(1), Some(&DrillStats { num_attempts: 1, num_successes: 0, num_mistakes: 1 }));
+
+        assert_eq!(drill.select_line(), Some(0));
+        drill.start_line(0).unwrap();
+        for san in ["e4", "Nf3"] {
+            assert!(matches!(drill.submit_san(san), Ok(DrillMoveResult::Correct { is_line_complete: false, .. })));
+        }
+        assert_eq!(drill.submit_san("Bb5"), Ok(DrillMoveResult::Correct { reply: None, is_line_complete: true }));
+        assert_eq!(drill.get_stats(0).unwrap().get_success_rate(), Some(1.));
+        assert_eq!(drill.select_line(), Some(1));
+        assert_eq!(drill.submit_san("a6"), Err(DrillError::LineComplete));
+    }
+
+    #[test]
+    fn test_drill_transpositions() {
+        // the repertoire answers 1. Nf3 d5 2. d4 and 1. d4 d5 2. Nf3 with the same 2... Nf6
+        let tree = PgnStateTree::from_str("1. d4 ( 1. Nf3 d5 2. d4 ) 1... d5 2. Nf3 Nf6 3. c4").unwrap();
+        let mut drill = RepertoireDrill::new(&tree, Color::Black);
+        assert_eq!(drill.get_lines().len(), 2);
+
+        let replies = drill.start_line(1).unwrap();
+        assert_eq!(replies.iter().map(|(_, san)| san.as_str()).collect::<Vec<_>>(), vec!["Nf3"]);
+        assert!(matches!(drill.submit_san("d5"), Ok(DrillMoveResult::Correct { reply: Some((_, ref san)), is_line_complete: false }) if san == "d4"));
+        // the line ends here, but the position transposes into the other line
+        assert_eq!(drill.get_expected_moves().iter().map(|(_, san)| san.as_str()).collect::<Vec<_>>(), vec!["Nf6"]);
+        assert!(matches!(drill.submit_san("Nf6"), Ok(DrillMoveResult::Correct { reply: Some((_, ref san)), is_line_complete: true }) if san == "c4"));
+        assert_eq!(drill.get_stats(1).unwrap().num_successes, 1);
+        assert!(matches!(drill.start_line(2), Err(DrillError::NoSuchLine(2))));
+    }
+}
@@ -13,6 +13,8 @@ pub enum PgnParseError {
     InvalidResult(String),
     InvalidTagPlacement(String),
     InvalidResultPlacement(String),
+    InvalidFen(String),
+    Io(String),
 }
 
 impl Display for PgnParseError {
@@ -28,6 +30,8 @@ impl Display for PgnParseError {
             PgnParseError::InvalidResult(result) => write!(f, "Invalid result: {}", result),
             PgnParseError::InvalidResultPlacement(result) => write!(f, "Invalid result placement: {}", result),
             PgnParseError::InvalidTagPlacement(tag) => write!(f, "Invalid tag placement: {}", tag),
+            PgnParseError::InvalidFen(error) => write!(f, "Invalid FEN tag: {}", error),
+            PgnParseError::Io(error) => write!(f, "Failed to read PGN: {}", error),
         }
     }
 }
@@ -0,0 +1,123 @@
+//! Merging of several games or trees into one tree, e.g. to build an opening repertoire.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::pgn::state_tree::PgnStateTree;
+use crate::pgn::query::get_position_key;
+use crate::pgn::state_tree_node::PgnStateTreeNode;
+
+type PositionIndex = HashMap<String, Rc<RefCell<PgnStateTreeNode>>>;
+
+impl PgnStateTree {
+    /// Adds every line of `other` to this tree. Lines already in this tree are not duplicated,
+    /// and new lines become variations. Tags of this tree are kept.
+    pub fn merge(&mut self, other: &PgnStateTree) -> Result<(), String> {
+        self.merge_with_options(other, false)
+    }
+
+    /// Like `merge`, but when a line of `other` reaches a position already in this tree through a different
+    /// move order, the line ends there and its continuation is merged into the existing position.
+    pub fn merge_transpositions(&mut self, other: &PgnStateTree) -> Result<(), String> {
+        self.merge_with_options(other, true)
+    }
+
+    /// Merges `trees` into a single tree, with the tags of the first one.
+    pub fn merge_all(trees: &[PgnStateTree], transposition_aware: bool) -> Result<PgnStateTree, String> {
+        let mut merged = PgnStateTree::new();
+        if let Some(first) = trees.first() {
+            merged.tags = first.tags.clone();
+        }
+        for tree in trees {
+            merged.merge_with_options(tree, transposition_aware)?;
+        }
+        Ok(merged)
+    }
+
+    fn merge_with_options(&mut self, other: &PgnStateTree, transposition_aware: bool) -> Result<(), String> {
+        let head_key = get_position_key(&self.head.borrow().state_after_move);
+        if head_key != get_position_key(&other.head.borrow().state_after_move) {
+            return Err("Cannot merge trees with different initial positions".to_string());
+        }
+
+        let mut index = PositionIndex::new();
+        if transposition_aware {
+            let mut stack = vec![self.head.clone()];
+            while let Some(node) = stack.pop() {
+                let key = get_position_key(&node.borrow().state_after_move);
+                index.entry(key).or_insert_with(|| node.clone());
+                stack.extend(node.borrow().next_nodes());
+            }
+        }
+        merge_nodes(&self.head, &other.head, transposition_aware.then_some(&mut index));
+        Ok(())
+    }
+}
+
+/// Merges the continuations of `other` into `node`, which must be the same position.
+fn merge_nodes(node: &Rc<RefCell<PgnStateTreeNode>>, other: &Rc<RefCell<PgnStateTreeNode>>, mut index: Option<&mut PositionIndex>) {
+    for other_child in other.borrow().next_nodes() {
+        let (mv, san) = match &other_child.borrow().move_and_san_and_previous_node {
+            Some((mv, san, _)) => (*mv, san.clone()),
+            None => continue
+        };
+
+        let existing_child = node.borrow().next_nodes.iter()
+            .find(|child| matches!(&child.borrow().move_and_san_and_previous_node, Some((child_move, _, _)) if *child_move == mv))
+            .cloned();
+        if let Some(existing_child) = existing_child {
+            merge_nodes(&existing_child, &other_child, index.as_deref_mut());
+            continue;
+        }
+
+        let mut new_state = node.borrow().state_after_move.clone();
+        new_state.make_move(mv);
+        new_state.termination = other_child.borrow().state_after_move.termination;
+        let key = get_position_key(&new_state);
+        let new_child = PgnStateTreeNode::new_linked_to_previous(mv, san, node.clone(), new_state);
+        {
+            let other_child = other_child.borrow();
+            let mut new_child = new_child.borrow_mut();
+            new_child.annotations = other_child.annotations.clone();
+            new_child.comments = other_child.comments.clone();
+            new_child.comments_before = other_child.comments_before.clone();
+        }
+
+        match index.as_deref_mut() {
+            Some(index) => {
+                let continuation = index.entry(key).or_insert_with(|| new_child.clone()).clone();
+                merge_nodes(&continuation, &other_child, Some(index));
+            },
+            None => merge_nodes(&new_child, &other_child, None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut tree = PgnStateTree::from_str("1. e4 e5 2. Nf3 Nc6").unwrap();
+        tree.merge(&PgnStateTree::from_str("1. e4 e5 2. Nf3 Nf6").unwrap()).unwrap();
+        tree.merge(&PgnStateTree::from_str("1. e4 c5! {The Sicilian}").unwrap()).unwrap();
+        tree.merge(&PgnStateTree::from_str("1. e4 e5").unwrap()).unwrap();
+        assert_eq!(tree.to_string(), "1.e4 e5\n    ( 1...c5! {The Sicilian} )\n2.Nf3 Nc6\n    ( 2...Nf6 )");
+    }
+
+    #[test]
+    fn test_merge_transpositions() {
+        let games = [
+            PgnStateTree::from_str("1. e4 e5 2. Nf3 Nc6").unwrap(),
+            PgnStateTree::from_str("1. Nf3 Nc6 2. e4 e5 3. Bb5").unwrap(),
+        ];
+
+        let merged = PgnStateTree::merge_all(&games, false).unwrap();
+        assert_eq!(merged.to_string(), "1.e4\n    ( 1.Nf3 Nc6 2.e4 e5 3.Bb5 )\n1...e5 2.Nf3 Nc6");
+
+        let merged = PgnStateTree::merge_all(&games, true).unwrap();
+        assert_eq!(merged.to_string(), "1.e4\n    ( 1.Nf3 Nc6 2.e4 e5 )\n1...e5 2.Nf3 Nc6 3.Bb5");
+    }
+}
@@ -0,0 +1,30 @@
+mod state_tree_node;
+mod state_tree_traverser;
+mod render;
+mod parse;
+mod tokenize;
+mod error;
+mod state_tree;
+mod merge;
+mod query;
+mod position_index;
+mod compact_state_tree;
+mod drill;
+mod reader;
+mod writer;
+mod stats;
+
+pub use render::*;
+pub use parse::*;
+pub use tokenize::*;
+pub use error::*;
+pub use state_tree::*;
+pub use state_tree_node::*;
+pub use query::*;
+pub use position_index::*;
+pub use compact_state_tree::*;
+pub use state_tree_traverser::*;
+pub use drill::*;
+pub use reader::*;
+pub use writer::*;
+pub use stats::*;
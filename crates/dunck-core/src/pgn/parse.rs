@@ -1,3 +1,4 @@
+use indexmap::IndexMap;
 use crate::pgn::error::PgnParseError;
 use crate::pgn::state_tree::PgnStateTree;
 use crate::pgn::state_tree_node::PgnStateTreeNode;
@@ -6,6 +7,26 @@ use crate::r#move::Move;
 use crate::state::{State, Termination};
 use crate::utils::Color;
 
+/// Parses the contents of a tag token, e.g. `White "Fischer, Robert J."`, into its name and value.
+pub fn parse_tag(tag: &str) -> Result<(String, String), PgnParseError> {
+    let (name, value) = tag.trim().split_once(' ').ok_or_else(|| PgnParseError::InvalidTag(tag.to_string()))?;
+    let value = value.trim().strip_prefix('"').and_then(|value| value.strip_suffix('"'))
+        .ok_or_else(|| PgnParseError::InvalidTag(tag.to_string()))?;
+    Ok((name.to_string(), value.replace("\\\"", "\"")))
+}
+
+/// Returns the tags among `tokens`, in order.
+pub fn extract_tags(tokens: &[PgnToken]) -> Result<IndexMap<String, String>, PgnParseError> {
+    let mut tags = IndexMap::new();
+    for token in tokens {
+        if let PgnToken::Tag(tag) = token {
+            let (name, value) = parse_tag(tag)?;
+            tags.insert(name, value);
+        }
+    }
+    Ok(tags)
+}
+
 fn validate_tag_placement(tokens: &[PgnToken]) -> Result<(), PgnParseError> {
     let mut can_tag_be_placed = true;
     
@@ -114,14 +135,14 @@ fn validate_variation_closure(tokens: &[PgnToken]) -> Result<(), PgnParseError>
     Ok(())
 }
 
-fn validate_move_numbers(tokens: &[PgnToken]) -> Result<(), PgnParseError> {
+fn validate_move_numbers(tokens: &[PgnToken], initial_state: &State) -> Result<(), PgnParseError> {
     let mut stack = Vec::new();
-    let mut halfmove = 1;
+    let mut halfmove = initial_state.halfmove + 1;
     
     for token in tokens {
         match token {
             PgnToken::MoveNumberAndPeriods(found_fullmove, _) => {
-                let expected_fullmove = (halfmove + 1) / 2;
+                let expected_fullmove = halfmove.div_ceil(2);
                 if found_fullmove != &expected_fullmove {
                     return Err(PgnParseError::IncorrectMoveNumber(found_fullmove.to_string()));
                 }
@@ -146,44 +167,65 @@ fn validate_move_numbers(tokens: &[PgnToken]) -> Result<(), PgnParseError> {
     Ok(())
 }
 
-fn validate(tokens: &[PgnToken]) -> Result<(), PgnParseError> {
+fn validate(tokens: &[PgnToken], initial_state: &State) -> Result<(), PgnParseError> {
     validate_tag_placement(tokens)?;
     validate_result_placement(tokens)?;
     validate_variation_start_placement(tokens)?;
     validate_variation_end_placement(tokens)?;
     validate_variation_closure(tokens)?;
-    validate_move_numbers(tokens)?;
+    validate_move_numbers(tokens, initial_state)?;
     
     Ok(())
 }
 
-fn find_san_match(initial_state: &State, legal_moves: &[Move], expected_san: &str) -> Option<(Move, String, State)> {
-    let update_termination = expected_san.ends_with("#");
-    
-    for legal_move in legal_moves {
-        let mut new_state = initial_state.clone();
-        new_state.make_move(*legal_move);
-        if update_termination {
-            new_state.check_and_update_termination();
-        }
-        
-        let san = legal_move.to_san(&initial_state, &new_state, legal_moves);
-        if san == expected_san {
-            return Some((*legal_move, san, new_state));
+/// Returns the position the game starts from: the one given by the `FEN` tag, unless `SetUp` is "0",
+/// or the standard initial position.
+fn get_initial_state(tokens: &[PgnToken]) -> Result<State, PgnParseError> {
+    let mut fen = None;
+    let mut is_set_up = true;
+    for token in tokens {
+        let PgnToken::Tag(tag) = token else {
+            continue;
+        };
+        // malformed tags are not needed to parse the moves, so they are ignored
+        match parse_tag(tag) {
+            Ok((name, value)) if name == "FEN" => fen = Some(value),
+            Ok((name, value)) if name == "SetUp" => is_set_up = value != "0",
+            _ => {}
         }
     }
-    
-    None
+    match fen {
+        Some(fen) if is_set_up => State::from_fen(&fen).map_err(|e| PgnParseError::InvalidFen(format!("{} ({})", fen, e))),
+        _ => Ok(State::initial())
+    }
+}
+
+fn find_san_match(initial_state: &State, legal_moves: &[Move], expected_san: &str) -> Option<(Move, String, State)> {
+    // mates written with a check mark are accepted too
+    let index = initial_state.sans_for(legal_moves).iter()
+        .position(|san| san == expected_san || (expected_san.ends_with('+') && san.strip_suffix('#') == expected_san.strip_suffix('+')))?;
+    let legal_move = legal_moves[index];
+
+    let mut new_state = initial_state.clone();
+    new_state.make_move(legal_move);
+    if expected_san.ends_with('#') {
+        new_state.check_and_update_termination();
+    }
+    Some((legal_move, expected_san.to_string(), new_state))
 }
 
 impl PgnStateTree {
     pub fn from_tokens(tokens: &[PgnToken]) -> Result<PgnStateTree, PgnParseError> {
-        validate(tokens)?;
+        let initial_state = get_initial_state(tokens)?;
+        validate(tokens, &initial_state)?;
 
-        let pgn_move_tree = PgnStateTree::new();
+        let pgn_move_tree = PgnStateTree::from_initial_state(initial_state);
 
         let mut current_node = pgn_move_tree.head.clone();
         let mut node_stack = Vec::new();
+        // comments at the start of a variation belong to its first move
+        let mut at_variation_start = false;
+        let mut comments_before = Vec::new();
         
         let mut tokens = tokens.iter().peekable();
         
@@ -203,6 +245,8 @@ impl PgnStateTree {
                     match find_san_match(&initial_state, &legal_moves, mv) {
                         Some((found_move, _, new_state)) => {
                             current_node = PgnStateTreeNode::new_linked_to_previous(found_move, mv.to_string(), current_node, new_state);
+                            current_node.borrow_mut().comments_before.append(&mut comments_before);
+                            at_variation_start = false;
                         }
                         None => return Err(PgnParseError::IllegalMove(mv.to_string()))
                     }
@@ -214,18 +258,28 @@ impl PgnStateTree {
                         Some((_, _, previous_node)) => previous_node.clone(), // Clone the Rc to get a new reference
                         None => return Err(PgnParseError::InvalidVariationStart("Variation does not start after a move".to_string())),
                     };
+                    at_variation_start = true;
                 }
                 PgnToken::EndVariation => {
                     current_node = match node_stack.pop() {
                         Some(node) => node,
                         None => return Err(PgnParseError::InvalidVariationClosure("There is no open variation".to_string()))
-                    }
+                    };
+                    at_variation_start = false;
                 }
-                PgnToken::Comment(_) => {
-                    // todo!()
+                PgnToken::Comment(comment) => {
+                    // line breaks and indentation within comments are not kept
+                    let comment = comment.split_whitespace().collect::<Vec<_>>().join(" ");
+                    if comment.is_empty() {
+                        continue;
+                    }
+                    match at_variation_start {
+                        true => comments_before.push(comment),
+                        false => current_node.borrow_mut().comments.push(comment)
+                    }
                 }
-                PgnToken::Annotation(_) => {
-                    // todo!()
+                PgnToken::Annotation(annotation) => {
+                    current_node.borrow_mut().annotations.push(annotation.clone());
                 }
                 PgnToken::Result(result) => {
                     match result.as_str() {
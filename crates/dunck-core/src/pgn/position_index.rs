@@ -0,0 +1,87 @@
+//! An index from Zobrist hashes to the nodes of one or more trees, for fast position lookups.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use crate::pgn::state_tree::PgnStateTree;
+use crate::pgn::state_tree_node::PgnStateTreeNode;
+use crate::utils::Bitboard;
+
+/// Maps the Zobrist hash of each board to the nodes reaching it, with the index of the tree each node is in.
+/// The hash only covers piece placement, so nodes with the same board but e.g. a different side to move
+/// share an entry.
+#[derive(Default)]
+pub struct PgnPositionIndex {
+    nodes_by_hash: HashMap<Bitboard, Vec<(usize, Rc<RefCell<PgnStateTreeNode>>)>>,
+}
+
+impl PgnPositionIndex {
+    pub fn new() -> PgnPositionIndex {
+        PgnPositionIndex::default()
+    }
+
+    /// Builds an index over all nodes of `trees`.
+    pub fn from_trees(trees: &[PgnStateTree]) -> PgnPositionIndex {
+        let mut index = PgnPositionIndex::new();
+        for (tree_index, tree) in trees.iter().enumerate() {
+            index.add_tree(tree_index, tree);
+        }
+        index
+    }
+
+    /// Adds all nodes of `tree` to the index, under `tree_index`.
+    pub fn add_tree(&mut self, tree_index: usize, tree: &PgnStateTree) {
+        for node in tree.get_all_nodes() {
+            let zobrist_hash = node.borrow().zobrist_hash;
+            self.nodes_by_hash.entry(zobrist_hash).or_default().push((tree_index, node));
+        }
+    }
+
+    /// Returns the nodes whose board has Zobrist hash `zobrist_hash`, with the indices of their trees.
+    pub fn get(&self, zobrist_hash: Bitboard) -> &[(usize, Rc<RefCell<PgnStateTreeNode>>)] {
+        self.nodes_by_hash.get(&zobrist_hash).map(|nodes| nodes.as_slice()).unwrap_or(&[])
+    }
+
+    /// Returns the number of distinct boards in the index.
+    pub fn get_num_positions(&self) -> usize {
+        self.nodes_by_hash.len()
+    }
+}
+
+impl PgnStateTree {
+    /// Builds an index over the nodes of this tree.
+    pub fn build_position_index(&self) -> PgnPositionIndex {
+        let mut index = PgnPositionIndex::new();
+        index.add_tree(0, self);
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::*;
+    use crate::state::State;
+
+    #[test]
+    fn test_position_index() {
+        let trees = [
+            PgnStateTree::from_str("1. e4 e5 2. Nf3 Nc6").unwrap(),
+            PgnStateTree::from_str("1. Nf3 Nc6 2. e4 e5 3. Bb5").unwrap(),
+        ];
+        let index = PgnPositionIndex::from_trees(&trees);
+
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        let zobrist_hash = State::from_fen(fen).unwrap().board.zobrist_hash;
+        let tree_indices: Vec<usize> = index.get(zobrist_hash).iter().map(|(tree_index, _)| *tree_index).collect();
+        assert_eq!(tree_indices, vec![0, 1]);
+        for (_, node) in index.get(zobrist_hash) {
+            assert_eq!(node.borrow().state_after_move.board.zobrist_hash, zobrist_hash);
+        }
+
+        assert_eq!(index.get(0).len(), 0);
+        // the initial position, 3 positions only in the first tree, 4 only in the second and the shared one
+        assert_eq!(index.get_num_positions(), 1 + 3 + 4 + 1);
+        assert_eq!(trees[1].build_position_index().get_num_positions(), 6);
+    }
+}
@@ -0,0 +1,137 @@
+//! Queries over parsed trees, e.g. to find where a position occurs and what was played from it.
+
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::rc::Rc;
+use indexmap::IndexMap;
+use crate::pgn::state_tree::PgnStateTree;
+use crate::pgn::state_tree_node::PgnStateTreeNode;
+use crate::r#move::{Move, MoveFlag};
+use crate::state::{FenParseError, State};
+use crate::utils::Bitboard;
+
+/// A move played from a queried position, with the number of trees it was played in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Continuation {
+    pub mv: Move,
+    pub san: String,
+    pub frequency: usize,
+}
+
+/// Returns the FEN without move counters, and without the en passant square unless en passant is legal,
+/// which identifies a position regardless of how it was reached.
+pub(crate) fn get_position_key(state: &State) -> String {
+    let has_en_passant = state.calc_legal_moves().iter().any(|mv| mv.get_flag() == MoveFlag::EnPassant);
+    let num_fields = if has_en_passant { 4 } else { 3 };
+    state.to_fen().split(' ').take(num_fields).collect::<Vec<_>>().join(" ")
+}
+
+impl PgnStateTree {
+    /// Returns the value of the tag called `name`.
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags.get(name).map(|value| value.as_str())
+    }
+
+    /// Returns every node of the tree, each before its children.
+    pub fn get_all_nodes(&self) -> Vec<Rc<RefCell<PgnStateTreeNode>>> {
+        let mut nodes = Vec::new();
+        let mut stack = vec![self.head.clone()];
+        while let Some(node) = stack.pop() {
+            stack.extend(node.borrow().next_nodes.iter().rev().cloned());
+            nodes.push(node);
+        }
+        nodes
+    }
+
+    /// Returns the nodes whose board has Zobrist hash `zobrist_hash`. The hash only covers piece placement.
+    pub fn find_nodes_by_zobrist_hash(&self, zobrist_hash: Bitboard) -> Vec<Rc<RefCell<PgnStateTreeNode>>> {
+        self.get_all_nodes().into_iter().filter(|node| node.borrow().zobrist_hash == zobrist_hash).collect()
+    }
+
+    /// Returns the nodes reaching the position described by `fen`, ignoring move counters.
+    pub fn find_nodes_by_fen(&self, fen: &str) -> Result<Vec<Rc<RefCell<PgnStateTreeNode>>>, FenParseError> {
+        let key = get_position_key(&State::from_fen(fen)?);
+        Ok(self.get_all_nodes().into_iter().filter(|node| get_position_key(&node.borrow().state_after_move) == key).collect())
+    }
+}
+
+/// Returns the trees whose tags satisfy `predicate`.
+pub fn filter_trees_by_tags(trees: &[PgnStateTree], predicate: impl Fn(&IndexMap<String, String>) -> bool) -> Vec<&PgnStateTree> {
+    trees.iter().filter(|tree| predicate(&tree.tags)).collect()
+}
+
+/// Returns the indices of the trees reaching the position described by `fen`.
+pub fn find_trees_by_fen(trees: &[PgnStateTree], fen: &str) -> Result<Vec<usize>, FenParseError> {
+    let mut indices = Vec::new();
+    for (i, tree) in trees.iter().enumerate() {
+        if !tree.find_nodes_by_fen(fen)?.is_empty() {
+            indices.push(i);
+        }
+    }
+    Ok(indices)
+}
+
+/// Returns the moves played from the position described by `fen`, most frequent first.
+/// A move is counted once per tree, however many times it occurs in that tree.
+pub fn get_continuations(trees: &[PgnStateTree], fen: &str) -> Result<Vec<Continuation>, FenParseError> {
+    let mut continuations: Vec<Continuation> = Vec::new();
+    for tree in trees {
+        let mut moves_in_tree: Vec<(Move, String)> = Vec::new();
+        for node in tree.find_nodes_by_fen(fen)? {
+            for next_node in node.borrow().next_nodes.iter() {
+                if let Some((mv, san, _)) = &next_node.borrow().move_and_san_and_previous_node {
+                    if !moves_in_tree.iter().any(|(existing_move, _)| existing_move == mv) {
+                        moves_in_tree.push((*mv, san.clone()));
+                    }
+                }
+            }
+        }
+
+        for (mv, san) in moves_in_tree {
+            match continuations.iter_mut().find(|continuation| continuation.mv == mv) {
+                Some(continuation) => continuation.frequency += 1,
+                None => continuations.push(Continuation { mv, san, frequency: 1 })
+            }
+        }
+    }
+    continuations.sort_by_key(|continuation| Reverse(continuation.frequency));
+    Ok(continuations)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::*;
+    use crate::pgn::{extract_tags, tokenize_pgn};
+    use crate::state::INITIAL_FEN;
+
+    fn parse_game(pgn: &str) -> PgnStateTree {
+        let mut tree = PgnStateTree::from_str(pgn).unwrap();
+        tree.tags = extract_tags(&tokenize_pgn(pgn).unwrap()).unwrap();
+        tree
+    }
+
+    #[test]
+    fn test_queries() {
+        let trees = [
+            parse_game("[White \"Carlsen\"]\n[ECO \"C60\"]\n1. e4 e5 2. Nf3 Nc6 3. Bb5"),
+            parse_game("[White \"Nakamura\"]\n[ECO \"C50\"]\n1. e4 e5 2. Nf3 Nc6 3. Bc4 ( 3. Bb5 )"),
+            parse_game("[White \"Carlsen\"]\n[ECO \"A04\"]\n1. Nf3 Nc6 2. e4 e5 3. d4"),
+        ];
+        assert_eq!(trees[0].get_tag("White"), Some("Carlsen"));
+
+        let carlsen_games = filter_trees_by_tags(&trees, |tags| tags.get("White").map(|value| value.as_str()) == Some("Carlsen"));
+        assert_eq!(carlsen_games.len(), 2);
+
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3";
+        assert_eq!(find_trees_by_fen(&trees, fen).unwrap(), vec![0, 1, 2]);
+        let sans: Vec<(String, usize)> = get_continuations(&trees, fen).unwrap().into_iter()
+            .map(|continuation| (continuation.san, continuation.frequency))
+            .collect();
+        assert_eq!(sans, vec![("Bb5".to_string(), 2), ("Bc4".to_string(), 1), ("d4".to_string(), 1)]);
+
+        let initial_hash = State::from_fen(INITIAL_FEN).unwrap().board.zobrist_hash;
+        assert_eq!(trees[1].find_nodes_by_zobrist_hash(initial_hash).len(), 1);
+        assert_eq!(trees[1].get_all_nodes().len(), 7);
+    }
+}
@@ -0,0 +1,150 @@
+//! Streaming games out of multi-game PGN files, e.g. database dumps too large to load into memory.
+
+use std::io;
+use std::io::BufRead;
+use std::str::FromStr;
+use crate::pgn::{extract_tags, tokenize_pgn, PgnParseError, PgnStateTree};
+
+/// Reads the games of a multi-game PGN one at a time, holding only the game being read in memory.
+///
+/// A game ends at a blank line after its movetext, at a tag line after its movetext, or at the end of the input,
+/// so games separated by blank lines and games run together without them are both split correctly.
+/// Lines starting with `%` are escaped and skipped. As an iterator, it yields each game parsed as a tree;
+/// a game that fails to parse yields an error without stopping the games after it.
+pub struct PgnReader<R: BufRead> {
+    reader: R,
+    line: String,
+    next_tag_line: Option<String>,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> PgnReader<R> {
+        PgnReader { reader, line: String::new(), next_tag_line: None }
+    }
+
+    /// Returns the text of the next game, or None at the end of the input.
+    pub fn next_pgn(&mut self) -> io::Result<Option<String>> {
+        let mut pgn = self.next_tag_line.take().unwrap_or_default();
+        let mut has_movetext = false;
+        let mut in_comment = false;
+
+        loop {
+            self.line.clear();
+            if self.reader.read_line(&mut self.line)? == 0 {
+                break;
+            }
+            let line = self.line.trim();
+
+            if !in_comment {
+                if line.starts_with('%') {
+                    continue;
+                }
+                if line.is_empty() && has_movetext {
+                    break;
+                }
+                if line.starts_with('[') && has_movetext {
+                    self.next_tag_line = Some(self.line.clone());
+                    break;
+                }
+                if !line.is_empty() && !line.starts_with('[') {
+                    has_movetext = true;
+                }
+            }
+            for ch in line.chars() {
+                match ch {
+                    '{' => in_comment = true,
+                    '}' => in_comment = false,
+                    _ => {}
+                }
+            }
+            pgn.push_str(&self.line);
+        }
+
+        match pgn.trim().is_empty() {
+            true => Ok(None),
+            false => Ok(Some(pgn))
+        }
+    }
+
+    /// Like `next`, with the tags of the game kept on the tree, e.g. to group games by their `ECO` tag.
+    pub fn next_with_tags(&mut self) -> Option<Result<PgnStateTree, PgnParseError>> {
+        let pgn = match self.next_pgn() {
+            Ok(pgn) => pgn?,
+            Err(e) => return Some(Err(PgnParseError::Io(e.to_string())))
+        };
+        Some(tokenize_pgn(&pgn).and_then(|tokens| {
+            let mut tree = PgnStateTree::from_tokens(&tokens)?;
+            tree.tags = extract_tags(&tokens)?;
+            Ok(tree)
+        }))
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnStateTree, PgnParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.next_pgn() {
+            Ok(pgn) => pgn.map(|pgn| PgnStateTree::from_str(&pgn)),
+            Err(e) => Some(Err(PgnParseError::Io(e.to_string())))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::io::{BufReader, Cursor};
+    use super::*;
+
+    #[test]
+    fn test_splitting_games() {
+        let pgns = "\
+[Event \"First\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+
+[Event \"Second\"]
+
+1. d4 {A comment
+
+spanning a blank line} 1... d5 *
+[Event \"Third\"]
+% an escaped line
+1. c4 (1. Nf3 {[%clk 0:03:00]}) 1... e5
+2. Nc3 *";
+        let mut reader = PgnReader::new(Cursor::new(pgns));
+        let first = reader.next_pgn().unwrap().unwrap();
+        assert!(first.starts_with("[Event \"First\"]") && first.contains("Qxf7#"));
+        assert!(reader.next_pgn().unwrap().unwrap().contains("spanning a blank line} 1... d5 *"));
+        let third = reader.next_pgn().unwrap().unwrap();
+        assert!(third.starts_with("[Event \"Third\"]") && !third.contains("escaped"));
+        assert_eq!(reader.next_pgn().unwrap(), None);
+
+        let trees: Vec<PgnStateTree> = PgnReader::new(Cursor::new(pgns)).collect::<Result<_, _>>().unwrap();
+        let num_nodes: Vec<usize> = trees.iter().map(|tree| tree.get_all_nodes().len()).collect();
+        assert_eq!(num_nodes, [8, 3, 5]);
+    }
+
+    #[test]
+    fn test_reading_files() {
+        let mut database = String::new();
+        for file_name in ["complex", "rosen1", "amirkhafan_vs_trickortreat"] {
+            database += &fs::read_to_string(format!("data/pgn_test_files/{}.pgn", file_name)).unwrap();
+            database += "\n\n";
+        }
+        let trees: Vec<PgnStateTree> = PgnReader::new(BufReader::new(database.as_bytes())).collect::<Result<_, _>>().unwrap();
+        assert_eq!(trees.len(), 3);
+        assert_eq!(trees[0].to_string(), fs::read_to_string("data/pgn_test_files/complex_formatted.pgn").unwrap());
+    }
+
+    #[test]
+    fn test_invalid_game_does_not_stop_reading() {
+        let pgns = "1. e4 e5 2. Ke3 *\n\n1. e4 e5 *\n";
+        let results: Vec<Result<PgnStateTree, PgnParseError>> = PgnReader::new(Cursor::new(pgns)).collect();
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], Err(PgnParseError::IllegalMove(_))));
+        assert!(results[1].is_ok());
+    }
+}
@@ -27,8 +27,13 @@ pub fn render_tokens(tokens: Vec<PgnToken>) -> String {
             }
             PgnToken::Move(m) => write!(result, "{} ", m).unwrap(),
             PgnToken::Tag(tag) => writeln!(result, "{}", tag).unwrap(),
-            PgnToken::Comment(c) => write!(result, "{}", c).unwrap(),
-            PgnToken::Annotation(a) => write!(result, "{}", a).unwrap(),
+            PgnToken::Comment(c) => write!(result, "{{{}}} ", c).unwrap(),
+            PgnToken::Annotation(a) if a.starts_with('$') => write!(result, "{} ", a).unwrap(),
+            PgnToken::Annotation(a) => {
+                // glyphs like "!?" are written attached to the move
+                result.truncate(result.trim_end_matches(' ').len());
+                write!(result, "{} ", a).unwrap();
+            }
             PgnToken::Result(r) => write!(result, "{}", r).unwrap(),
         }
     }
@@ -56,6 +61,14 @@ impl PgnStateTreeNode {
         }
     }
     
+    /// Returns the tokens of the move followed by its annotations and comments.
+    fn move_tokens(&self) -> Vec<PgnToken> {
+        let mut res = vec![PgnToken::Move(self.get_san())];
+        res.extend(self.annotations.iter().cloned().map(PgnToken::Annotation));
+        res.extend(self.comments.iter().cloned().map(PgnToken::Comment));
+        res
+    }
+
     pub(crate) fn to_tokens(&self, render_own_move: bool) -> Vec<PgnToken> {
         let mut res = Vec::new();
         let side_to_move_after_move = self.state_after_move.side_to_move;
@@ -63,8 +76,7 @@ impl PgnStateTreeNode {
         
         if render_own_move {
             // add the current node's move
-            let san = self.get_san();
-            res.push(PgnToken::Move(san));
+            res.append(&mut self.move_tokens());
         }
 
         // check for next node
@@ -77,15 +89,18 @@ impl PgnStateTreeNode {
         if side_to_move_after_move == Color::White {
             // add next node's fullmove number
             res.push(PgnToken::MoveNumberAndPeriods(fullmove_after_move, 1));
+        } else if !self.comments.is_empty() || self.move_and_san_and_previous_node.is_none() {
+            // black's first move, and black's move after a comment, are numbered too
+            res.push(PgnToken::MoveNumberAndPeriods(fullmove_after_move, 3));
         }
         
         // add next node's move
-        let san = next_node.borrow().get_san();
-        res.push(PgnToken::Move(san));
+        res.append(&mut next_node.borrow().move_tokens());
         
         // recurse into next variation nodes
         for variation in self.next_variation_nodes() {
             res.push(PgnToken::StartVariation); // add '('
+            res.extend(variation.borrow().comments_before.iter().cloned().map(PgnToken::Comment));
             let num_periods = match side_to_move_after_move {
                 Color::White => 1,
                 Color::Black => 3
@@ -95,7 +110,7 @@ impl PgnStateTreeNode {
             res.push(PgnToken::EndVariation); // add ')'
         }
         
-        if self.has_variation() && side_to_move_after_move == Color::White {
+        if self.has_variation() && side_to_move_after_move == Color::White && next_node.borrow().comments.is_empty() {
             // add fullmove number
             res.push(PgnToken::MoveNumberAndPeriods(next_node.borrow().state_after_move.get_fullmove(), 3));
         }
@@ -114,7 +129,12 @@ impl PgnStateTree {
         for tag in self.tags.iter() {
             res.push(PgnToken::Tag(format!("[{} \"{}\"]", tag.0, tag.1)));
         }
+        if !self.starts_from_initial_position() && !self.tags.contains_key("FEN") {
+            res.push(PgnToken::Tag("[SetUp \"1\"]".to_string()));
+            res.push(PgnToken::Tag(format!("[FEN \"{}\"]", self.head.borrow().state_after_move.to_fen())));
+        }
         
+        res.extend(self.head.borrow().comments.iter().cloned().map(PgnToken::Comment));
         res.append(&mut (*self.head).borrow().to_tokens(false));
         
         let mut last_node = self.head.clone();
@@ -132,7 +152,8 @@ impl PgnStateTree {
                             Color::Black => "1-0"
                         }
                     },
-                    Termination::Stalemate | Termination::ThreefoldRepetition | Termination::InsufficientMaterial | Termination::FiftyMoveRule => "1/2-1/2",
+                    Termination::Stalemate | Termination::ThreefoldRepetition | Termination::InsufficientMaterial | Termination::FiftyMoveRule |
+                    Termination::FivefoldRepetition | Termination::SeventyFiveMoveRule => "1/2-1/2",
                 };
                 res.push(PgnToken::Result(result_string.to_string()));
             }
@@ -0,0 +1,149 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::str::FromStr;
+use indexmap::IndexMap;
+use crate::pgn::state_tree_node::{PgnStateTreeNode};
+use crate::pgn::{tokenize_pgn, PgnParseError};
+use crate::state::State;
+
+pub struct PgnStateTree {
+    pub tags: IndexMap<String, String>,
+    pub head: Rc<RefCell<PgnStateTreeNode>>,
+}
+
+impl PgnStateTree {
+    pub fn new() -> PgnStateTree {
+        PgnStateTree {
+            tags: IndexMap::new(),
+            head: PgnStateTreeNode::new_root()
+        }
+    }
+
+    /// Returns an empty tree for a game starting from `initial_state`.
+    pub fn from_initial_state(initial_state: State) -> PgnStateTree {
+        PgnStateTree {
+            tags: IndexMap::new(),
+            head: PgnStateTreeNode::new_root_at(initial_state)
+        }
+    }
+
+    /// Returns whether the game starts from the standard initial position.
+    pub fn starts_from_initial_position(&self) -> bool {
+        self.head.borrow().state_after_move.to_fen() == State::initial().to_fen()
+    }
+}
+
+impl FromStr for PgnStateTree {
+    type Err = PgnParseError;
+
+    fn from_str(pgn: &str) -> Result<PgnStateTree, PgnParseError> {
+        let tokens = tokenize_pgn(pgn)?;
+        PgnStateTree::from_tokens(&tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::str::FromStr;
+    use super::*;
+
+    fn load_input_and_expected_pgn(file_name: &str) -> (String, String) {
+        let input_pgn = fs::read_to_string(format!("data/pgn_test_files/{}.pgn", file_name)).expect("Could not read file");
+        let expected_pgn = fs::read_to_string(format!("data/pgn_test_files/{}_formatted.pgn", file_name)).expect("Could not read file");
+        (input_pgn, expected_pgn)
+    }
+
+    fn test_pgn(input_pgn: &str, expected_pgn: &str) {
+        let pgn_tree = PgnStateTree::from_str(input_pgn).unwrap();
+        assert_eq!(pgn_tree.to_string(), expected_pgn);
+    }
+
+    fn generic_pgn_test(file_name: &str) {
+        let (input_pgn, expected_pgn) = load_input_and_expected_pgn(file_name);
+        test_pgn(&input_pgn, &expected_pgn);
+    }
+
+    #[test]
+    fn empty_pgn_test() {
+        let input_pgn = "";
+        let pgn_tree = PgnStateTree::from_str(input_pgn).unwrap();
+        assert!(pgn_tree.tags.is_empty());
+        assert!((*pgn_tree.head).borrow().move_and_san_and_previous_node.is_none());
+        assert_eq!(pgn_tree.to_string(), "");
+    }
+
+    #[test]
+    fn complex_pgn_test() {
+        generic_pgn_test("complex");
+    }
+
+    #[test]
+    fn rosen1_pgn_test() {
+        generic_pgn_test("rosen1");
+    }
+    
+    #[test]
+    fn amirkhafan_vs_trickortreat_pgn_test() {
+        generic_pgn_test("amirkhafan_vs_trickortreat");
+    }
+
+    #[test]
+    fn blitzstream_twitch_vs_amirkhafan_test() {
+        generic_pgn_test("blitzstream-twitch_vs_amirkhafan");
+    }
+    
+    #[test]
+    fn pinhead_larry_vs_orlando_gloom_test() {
+        generic_pgn_test("pinhead-larry_vs_orlando_gloom");
+    }
+
+    #[test]
+    fn annotations_pgn_test() {
+        let input_pgn = "{An open game} 1. e4 e5!? {A
+            classical reply} 2. Nf3 $1 (2. Qh5?! {Premature} 2... Nc6 ({Or} 2... g6)) 2... Nc6 *";
+        let pgn_tree = PgnStateTree::from_str(input_pgn).unwrap();
+        let e5 = pgn_tree.get_all_nodes()[2].clone();
+        assert_eq!(e5.borrow().annotations, ["!?"]);
+        assert_eq!(e5.borrow().comments, ["A classical reply"]);
+
+        let expected_pgn = "{An open game} 1.e4 e5!? {A classical reply} 2.Nf3 $1
+    ( 2.Qh5?! {Premature} 2...Nc6
+        ( {Or} 2...g6 )
+    )
+2...Nc6";
+        assert_eq!(pgn_tree.to_string(), expected_pgn);
+        test_pgn(expected_pgn, expected_pgn);
+    }
+
+    #[test]
+    fn mate_with_check_mark_pgn_test() {
+        let pgn_tree = PgnStateTree::from_str("1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7+").unwrap();
+        assert_eq!(pgn_tree.get_all_nodes().len(), 8);
+    }
+
+    #[test]
+    fn set_up_pgn_test() {
+        let fen = "6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 30";
+        let input_pgn = format!("[SetUp \"1\"]\n[FEN \"{}\"]\n\n30... h6 (30... g6 31. Ra8+) 31. Ra8+ Kh7 *", fen);
+        let pgn_tree = PgnStateTree::from_str(&input_pgn).unwrap();
+        assert!(!pgn_tree.starts_from_initial_position());
+        assert_eq!(pgn_tree.head.borrow().state_after_move.to_fen(), fen);
+
+        let expected_pgn = format!("[SetUp \"1\"]\n[FEN \"{}\"]\n30...h6\n    ( 30...g6 31.Ra8+ )\n31.Ra8+ Kh7", fen);
+        assert_eq!(pgn_tree.to_string(), expected_pgn);
+        test_pgn(&expected_pgn, &expected_pgn);
+
+        // move numbers are checked against the position
+        assert!(matches!(PgnStateTree::from_str(&format!("[FEN \"{}\"] 1... h6", fen)), Err(PgnParseError::IncorrectMoveNumber(_))));
+        assert!(matches!(PgnStateTree::from_str("[SetUp \"1\"] [FEN \"8/8/8\"] 1. e4"), Err(PgnParseError::InvalidFen(_))));
+        // the FEN is ignored if the game is explicitly not set up
+        assert!(PgnStateTree::from_str(&format!("[SetUp \"0\"] [FEN \"{}\"] 1. e4", fen)).unwrap().starts_from_initial_position());
+    }
+
+    #[test]
+    fn complex_pgn_round_trip_test() {
+        let (_, expected_pgn) = load_input_and_expected_pgn("complex");
+        test_pgn(&expected_pgn, &expected_pgn);
+    }
+}
\ No newline at end of file
@@ -2,19 +2,37 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use crate::r#move::Move;
 use crate::state::State;
+use crate::utils::Bitboard;
 
 pub struct PgnStateTreeNode {
     pub move_and_san_and_previous_node: Option<(Move, String, Rc<RefCell<PgnStateTreeNode>>)>,
     pub state_after_move: State,
+    /// The Zobrist hash of the board after the move, kept for position lookups.
+    pub zobrist_hash: Bitboard,
     pub next_nodes: Vec<Rc<RefCell<PgnStateTreeNode>>>,
+    /// Annotations of the move, e.g. "!?" or "$14", in the order they were given.
+    pub annotations: Vec<String>,
+    /// Comments following the move. The root's comments precede the first move of the game.
+    pub comments: Vec<String>,
+    /// Comments preceding the move, given at the start of a variation.
+    pub comments_before: Vec<String>,
 }
 
 impl PgnStateTreeNode {
     pub fn new_root() -> Rc<RefCell<PgnStateTreeNode>> {
+        PgnStateTreeNode::new_root_at(State::initial())
+    }
+
+    /// Returns a root for games starting from `state_after_move`, e.g. set up with a FEN tag.
+    pub fn new_root_at(state_after_move: State) -> Rc<RefCell<PgnStateTreeNode>> {
         Rc::new(RefCell::new(PgnStateTreeNode {
             move_and_san_and_previous_node: None,
-            state_after_move: State::initial(),
+            zobrist_hash: state_after_move.board.zobrist_hash,
+            state_after_move,
             next_nodes: Vec::new(),
+            annotations: Vec::new(),
+            comments: Vec::new(),
+            comments_before: Vec::new(),
         }))
     }
 
@@ -26,8 +44,12 @@ impl PgnStateTreeNode {
     ) -> Rc<RefCell<PgnStateTreeNode>> {
         let new_node = Rc::new(RefCell::new(PgnStateTreeNode {
             move_and_san_and_previous_node: Some((move_, san, Rc::clone(&previous_node))),
+            zobrist_hash: state_after_move.board.zobrist_hash,
             state_after_move,
             next_nodes: Vec::new(),
+            annotations: Vec::new(),
+            comments: Vec::new(),
+            comments_before: Vec::new(),
         }));
 
         // Add the new node to the previous node's children
@@ -0,0 +1,286 @@
+//! Statistics over game collections, e.g. results by opening or the most popular moves, exported as CSV or JSON.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io::BufRead;
+use serde::Serialize;
+use crate::pgn::{PgnParseError, PgnReader, PgnStateTree};
+use crate::state::get_see_value;
+use crate::state::State;
+use crate::utils::{Color, PieceType};
+
+/// The number of halfmoves whose moves are counted for move popularity, by default.
+pub const DEFAULT_NUM_POPULARITY_PLIES: usize = 10;
+
+/// The number of games with each result.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResultCounts {
+    pub white_wins: usize,
+    pub black_wins: usize,
+    pub draws: usize,
+    /// Games with no result or `*`.
+    pub unfinished: usize,
+}
+
+impl ResultCounts {
+    pub fn get_num_games(&self) -> usize {
+        self.white_wins + self.black_wins + self.draws + self.unfinished
+    }
+
+    /// Returns white's score over the finished games, from 0 to 1, or None if no game finished.
+    pub fn get_white_score(&self) -> Option<f64> {
+        let num_finished = self.white_wins + self.black_wins + self.draws;
+        match num_finished {
+            0 => None,
+            _ => Some((self.white_wins as f64 + self.draws as f64 / 2.) / num_finished as f64)
+        }
+    }
+
+    fn add(&mut self, result: Option<&str>) {
+        match result {
+            Some("1-0") => self.white_wins += 1,
+            Some("0-1") => self.black_wins += 1,
+            Some("1/2-1/2") => self.draws += 1,
+            _ => self.unfinished += 1
+        }
+    }
+}
+
+/// The material balance of the games still going after a number of halfmoves.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MaterialAtPly {
+    pub num_games: usize,
+    /// The sum over the games of white's material minus black's, in pawns.
+    pub total_balance: i64,
+    /// The sum over the games of the absolute difference in material, in pawns.
+    pub total_imbalance: i64,
+}
+
+impl MaterialAtPly {
+    pub fn get_average_balance(&self) -> f64 {
+        self.total_balance as f64 / self.num_games.max(1) as f64
+    }
+
+    pub fn get_average_imbalance(&self) -> f64 {
+        self.total_imbalance as f64 / self.num_games.max(1) as f64
+    }
+}
+
+/// Statistics over the main lines of a collection of games, collected one game at a time,
+/// so that databases too large to load into memory can be streamed through `from_reader`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct PgnStats {
+    pub num_games: usize,
+    /// Games that failed to parse, which are not counted otherwise.
+    pub num_unparsable_games: usize,
+    /// The total number of halfmoves in the main lines.
+    pub total_plies: usize,
+    /// Results by the `ECO` tag, with `?` for games without one.
+    pub results_by_eco: BTreeMap<String, ResultCounts>,
+    /// The material balance after each number of halfmoves, from the starting position on.
+    pub material_by_ply: Vec<MaterialAtPly>,
+    /// The number of games playing each move at each of the first halfmoves, in SAN.
+    pub move_counts_by_ply: Vec<BTreeMap<String, usize>>,
+}
+
+impl Default for PgnStats {
+    fn default() -> PgnStats {
+        PgnStats::new(DEFAULT_NUM_POPULARITY_PLIES)
+    }
+}
+
+/// Returns white's material minus black's, in pawns.
+fn calc_material_balance(state: &State) -> i64 {
+    let signature = state.get_material_signature();
+    PieceType::iter_non_king_pieces()
+        .map(|piece_type| {
+            let balance = signature.get_count(Color::White, *piece_type) as i64 - signature.get_count(Color::Black, *piece_type) as i64;
+            get_see_value(*piece_type) as i64 * balance
+        })
+        .sum()
+}
+
+impl PgnStats {
+    /// Returns empty statistics, counting move popularity for the first `num_popularity_plies` halfmoves.
+    pub fn new(num_popularity_plies: usize) -> PgnStats {
+        PgnStats {
+            num_games: 0,
+            num_unparsable_games: 0,
+            total_plies: 0,
+            results_by_eco: BTreeMap::new(),
+            material_by_ply: Vec::new(),
+            move_counts_by_ply: vec![BTreeMap::new(); num_popularity_plies],
+        }
+    }
+
+    /// Collects statistics over the games of `reader`. Games that fail to parse are counted and skipped,
+    /// but an error reading the input stops the collection.
+    pub fn from_reader<R: BufRead>(reader: &mut PgnReader<R>, num_popularity_plies: usize) -> Result<PgnStats, PgnParseError> {
+        let mut stats = PgnStats::new(num_popularity_plies);
+        while let Some(result) = reader.next_with_tags() {
+            match result {
+                Ok(tree) => stats.add_game(&tree),
+                Err(PgnParseError::Io(e)) => return Err(PgnParseError::Io(e)),
+                Err(_) => stats.num_unparsable_games += 1
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Adds the main line of `tree` to the statistics. The result and opening are taken from its tags.
+    pub fn add_game(&mut self, tree: &PgnStateTree) {
+        self.num_games += 1;
+        let eco = tree.get_tag("ECO").unwrap_or("?").to_string();
+        self.results_by_eco.entry(eco).or_default().add(tree.get_tag("Result"));
+
+        let mut node = tree.head.clone();
+        let mut ply = 0;
+        loop {
+            let balance = calc_material_balance(&node.borrow().state_after_move);
+            if self.material_by_ply.len() == ply {
+                self.material_by_ply.push(MaterialAtPly::default());
+            }
+            let material = &mut self.material_by_ply[ply];
+            material.num_games += 1;
+            material.total_balance += balance;
+            material.total_imbalance += balance.abs();
+
+            let Some(next_node) = node.borrow().next_main_node() else {
+                break;
+            };
+            if let (Some(move_counts), Some((_, san, _))) = (self.move_counts_by_ply.get_mut(ply), &next_node.borrow().move_and_san_and_previous_node) {
+                *move_counts.entry(san.clone()).or_default() += 1;
+            }
+            node = next_node;
+            ply += 1;
+        }
+        self.total_plies += ply;
+    }
+
+    /// Returns the average number of halfmoves in the main lines, or 0 without games.
+    pub fn get_average_plies(&self) -> f64 {
+        self.total_plies as f64 / self.num_games.max(1) as f64
+    }
+
+    /// Returns the moves played at halfmove `ply`, counting from 0, most popular first, with the number of games playing them.
+    /// Moves played equally often are in alphabetical order.
+    pub fn get_popular_moves(&self, ply: usize) -> Vec<(&str, usize)> {
+        let mut moves: Vec<(&str, usize)> = match self.move_counts_by_ply.get(ply) {
+            Some(move_counts) => move_counts.iter().map(|(san, count)| (san.as_str(), *count)).collect(),
+            None => Vec::new()
+        };
+        moves.sort_by_key(|&(_, count)| Reverse(count));
+        moves
+    }
+
+    /// Returns a CSV table with a row per opening: `eco,games,white_wins,black_wins,draws,unfinished,white_score`.
+    pub fn results_by_eco_to_csv(&self) -> String {
+        let mut csv = String::from("eco,games,white_wins,black_wins,draws,unfinished,white_score\n");
+        for (eco, counts) in self.results_by_eco.iter() {
+            let white_score = counts.get_white_score().map(|score| format!("{:.4}", score)).unwrap_or_default();
+            writeln!(csv, "{},{},{},{},{},{},{}", eco, counts.get_num_games(), counts.white_wins, counts.black_wins, counts.draws, counts.unfinished, white_score).unwrap();
+        }
+        csv
+    }
+
+    /// Returns a CSV table with a row per halfmove: `ply,games,average_balance,average_imbalance`, in pawns from white's perspective.
+    pub fn material_by_ply_to_csv(&self) -> String {
+        let mut csv = String::from("ply,games,average_balance,average_imbalance\n");
+        for (ply, material) in self.material_by_ply.iter().enumerate() {
+            writeln!(csv, "{},{},{:.4},{:.4}", ply, material.num_games, material.get_average_balance(), material.get_average_imbalance()).unwrap();
+        }
+        csv
+    }
+
+    /// Returns a CSV table with a row per move played at each of the first halfmoves: `ply,san,games,share`,
+    /// most popular first.
+    pub fn move_popularity_to_csv(&self) -> String {
+        let mut csv = String::from("ply,san,games,share\n");
+        for ply in 0..self.move_counts_by_ply.len() {
+            let moves = self.get_popular_moves(ply);
+            let num_games: usize = moves.iter().map(|(_, count)| count).sum();
+            for (san, count) in moves {
+                writeln!(csv, "{},{},{},{:.4}", ply, san, count, count as f64 / num_games as f64).unwrap();
+            }
+        }
+        csv
+    }
+
+    /// Returns the statistics as JSON, with the averages alongside the totals they are computed from.
+    pub fn to_json(&self) -> String {
+        let json = serde_json::json!({
+            "average_plies": self.get_average_plies(),
+            "average_material_by_ply": self.material_by_ply.iter()
+                .map(|material| [material.get_average_balance(), material.get_average_imbalance()])
+                .collect::<Vec<_>>(),
+            "stats": self,
+        });
+        serde_json::to_string_pretty(&json).expect("statistics are always serializable")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use super::*;
+
+    const PGNS: &str = "\
+[ECO \"C20\"]
+[Result \"1-0\"]
+
+1. e4 e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+
+[ECO \"C20\"]
+[Result \"1/2-1/2\"]
+
+1. e4 e5 2. Nf3 1/2-1/2
+
+[Result \"0-1\"]
+
+1. d4 d5 2. Ke3 0-1
+
+1. d4 d5 2. c4 dxc4 *
+";
+
+    #[test]
+    fn test_stats() {
+        let stats = PgnStats::from_reader(&mut PgnReader::new(Cursor::new(PGNS)), 2).unwrap();
+        assert_eq!(stats.num_games, 3);
+        assert_eq!(stats.num_unparsable_games, 1);
+        assert_eq!(stats.total_plies, 7 + 3 + 4);
+        assert!((stats.get_average_plies() - 14. / 3.).abs() < 1e-9);
+
+        assert_eq!(stats.results_by_eco["C20"], ResultCounts { white_wins: 1, black_wins: 0, draws: 1, unfinished: 0 });
+        assert_eq!(stats.results_by_eco["?"], ResultCounts { unfinished: 1, ..ResultCounts::default() });
+        assert_eq!(stats.results_by_eco["C20"].get_white_score(), Some(0.75));
+        assert_eq!(stats.results_by_eco["?"].get_white_score(), None);
+
+        assert_eq!(stats.material_by_ply.len(), 8);
+        assert_eq!(stats.material_by_ply[0], MaterialAtPly { num_games: 3, total_balance: 0, total_imbalance: 0 });
+        // after 4. Qxf7# in the first game and 2... dxc4 in the last
+        assert_eq!(stats.material_by_ply[4], MaterialAtPly { num_games: 2, total_balance: -1, total_imbalance: 1 });
+        assert_eq!(stats.material_by_ply[7], MaterialAtPly { num_games: 1, total_balance: 1, total_imbalance: 1 });
+
+        assert_eq!(stats.get_popular_moves(0), vec![("e4", 2), ("d4", 1)]);
+        assert_eq!(stats.get_popular_moves(1), vec![("e5", 2), ("d5", 1)]);
+        assert!(stats.get_popular_moves(2).is_empty());
+    }
+
+    #[test]
+    fn test_export() {
+        let stats = PgnStats::from_reader(&mut PgnReader::new(Cursor::new(PGNS)), 1).unwrap();
+        assert_eq!(stats.results_by_eco_to_csv(), "\
+eco,games,white_wins,black_wins,draws,unfinished,white_score
+?,1,0,0,0,1,
+C20,2,1,0,1,0,0.7500
+");
+        assert_eq!(stats.move_popularity_to_csv(), "ply,san,games,share\n0,e4,2,0.6667\n0,d4,1,0.3333\n");
+        assert!(stats.material_by_ply_to_csv().starts_with("ply,games,average_balance,average_imbalance\n0,3,0.0000,0.0000\n"));
+
+        let json: serde_json::Value = serde_json::from_str(&stats.to_json()).unwrap();
+        assert_eq!(json["stats"]["num_games"], 3);
+        assert_eq!(json["stats"]["results_by_eco"]["C20"]["draws"], 1);
+        assert_eq!(json["stats"]["move_counts_by_ply"][0]["e4"], 2);
+    }
+}
@@ -58,7 +58,7 @@ pub fn tokenize_pgn(pgn: &str) -> Result<Vec<PgnToken>, PgnParseError> {
             }
             '!' | '?' | '$' => {
                 // Annotation (like "!", "!?", "$19" etc.)
-                let annotation = collect_until(&mut chars, |c| c.is_ascii_whitespace());
+                let annotation = collect_until(&mut chars, |c| c.is_ascii_whitespace() || c == ')');
                 tokens.push(PgnToken::Annotation(annotation));
             }
             '*' => {
@@ -82,8 +82,15 @@ pub fn tokenize_pgn(pgn: &str) -> Result<Vec<PgnToken>, PgnParseError> {
             }
             _ if ch.is_alphabetic() => {
                 // Assume it's a move (e.g., "e4", "Nf3", "O-O", etc.)
-                let mv = collect_until(&mut chars, |c| c.is_ascii_whitespace());
-                tokens.push(PgnToken::Move(mv));
+                // A variation can end right after a move, e.g. "(1. d4 d5)"
+                let mv = collect_until(&mut chars, |c| c.is_ascii_whitespace() || c == ')');
+                // A move can be suffixed with an annotation, e.g. "Nf3!?"
+                let san = mv.trim_end_matches(['!', '?']);
+                let annotation = &mv[san.len()..];
+                tokens.push(PgnToken::Move(san.to_string()));
+                if !annotation.is_empty() {
+                    tokens.push(PgnToken::Annotation(annotation.to_string()));
+                }
             }
             _ => {
                 // Invalid token
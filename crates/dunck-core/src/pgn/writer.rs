@@ -0,0 +1,383 @@
+//! Exporting games as PGN in the export format: the seven tag roster first, then the other tags,
+//! and movetext wrapped into lines, ending with the game's result.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use indexmap::IndexMap;
+use crate::pgn::state_tree::PgnStateTree;
+use crate::pgn::tokenize::PgnToken;
+use crate::r#move::Move;
+use crate::state::{State, Termination};
+use crate::utils::Color;
+
+/// The tags every exported game has, in this order, with their values when unknown.
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+/// A move of a `PgnGame`, with the annotations written after it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PgnMove {
+    pub mv: Move,
+    /// Numeric annotation glyphs, e.g. 1 for a good move and 4 for a blunder.
+    pub nags: Vec<u8>,
+    pub comment: Option<String>,
+}
+
+impl PgnMove {
+    pub fn new(mv: Move) -> PgnMove {
+        PgnMove { mv, nags: Vec::new(), comment: None }
+    }
+
+    pub fn with_nag(mut self, nag: u8) -> Self {
+        self.nags.push(nag);
+        self
+    }
+
+    pub fn with_comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+}
+
+impl From<Move> for PgnMove {
+    fn from(mv: Move) -> PgnMove {
+        PgnMove::new(mv)
+    }
+}
+
+/// A game built move by move for export, rather than parsed.
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    pub tags: IndexMap<String, String>,
+    pub initial_state: State,
+    pub moves: Vec<PgnMove>,
+    /// The result from white's point of view: 1, 0, or -1. If None, the result is taken from the final position
+    /// if the game ended there, and the game is written as unfinished otherwise.
+    pub result: Option<i8>,
+}
+
+impl PgnGame {
+    pub fn new(initial_state: State) -> PgnGame {
+        PgnGame { tags: IndexMap::new(), initial_state, moves: Vec::new(), result: None }
+    }
+
+    pub fn with_tag(mut self, name: &str, value: &str) -> Self {
+        self.tags.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_result(mut self, result: i8) -> Self {
+        self.result = Some(result);
+        self
+    }
+
+    pub fn add_move(&mut self, mv: impl Into<PgnMove>) {
+        self.moves.push(mv.into());
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PgnWriteError {
+    /// The move, in UCI notation, at the halfmove with this index is illegal.
+    IllegalMove { halfmove: usize, uci: String },
+}
+
+impl Display for PgnWriteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnWriteError::IllegalMove { halfmove, uci } => write!(f, "Illegal move {} at halfmove {}", uci, halfmove),
+        }
+    }
+}
+
+impl Error for PgnWriteError {}
+
+/// Writes games as PGN. Movetext lines are wrapped at `max_line_length` characters, 79 by default, or not at all
+/// if it is None. Comments and NAGs are written unless turned off, e.g. for data that only needs the moves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PgnWriter {
+    pub max_line_length: Option<usize>,
+    pub include_annotations: bool,
+}
+
+impl Default for PgnWriter {
+    fn default() -> Self {
+        PgnWriter { max_line_length: Some(79), include_annotations: true }
+    }
+}
+
+impl PgnWriter {
+    pub fn with_max_line_length(mut self, max_line_length: Option<usize>) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    pub fn with_annotations(mut self, include_annotations: bool) -> Self {
+        self.include_annotations = include_annotations;
+        self
+    }
+
+    /// Writes `game`, checking that its moves are legal. Games not starting from the standard initial position
+    /// get `SetUp` and `FEN` tags, and the `Result` tag is always set to the game's result.
+    pub fn write_game(&self, game: &PgnGame) -> Result<String, PgnWriteError> {
+        let mut state = game.initial_state.clone();
+        let mut words = Vec::with_capacity(game.moves.len() * 3 / 2 + 1);
+        let mut needs_move_number = true;
+        for (halfmove, pgn_move) in game.moves.iter().enumerate() {
+            let legal_moves = state.calc_legal_moves();
+            if !legal_moves.contains(&pgn_move.mv) {
+                return Err(PgnWriteError::IllegalMove { halfmove, uci: pgn_move.mv.to_uci() });
+            }
+            let mut next_state = state.clone();
+            next_state.make_move(pgn_move.mv);
+            next_state.check_and_update_termination();
+
+            if state.side_to_move == Color::White {
+                words.push(format!("{}.", state.get_fullmove()));
+            } else if needs_move_number {
+                words.push(format!("{}...", state.get_fullmove()));
+            }
+            words.push(pgn_move.mv.to_san(&state, &next_state, &legal_moves));
+            needs_move_number = false;
+            if self.include_annotations {
+                words.extend(pgn_move.nags.iter().map(|nag| format!("${}", nag)));
+                if let Some(comment) = &pgn_move.comment {
+                    words.push(format_comment(comment));
+                    // black's move after a comment is numbered again
+                    needs_move_number = true;
+                }
+            }
+            state = next_state;
+        }
+
+        let result = match game.result {
+            Some(result) => format_result(result),
+            None if state.termination == Some(Termination::Checkmate) => match state.side_to_move {
+                Color::White => format_result(-1),
+                Color::Black => format_result(1)
+            },
+            None if state.termination.is_some() => format_result(0),
+            None => "*"
+        };
+        let mut tags = game.tags.clone();
+        if game.initial_state.to_fen() != State::initial().to_fen() {
+            tags.insert("SetUp".to_string(), "1".to_string());
+            tags.insert("FEN".to_string(), game.initial_state.to_fen());
+        }
+        Ok(self.format(&tags, words, result))
+    }
+
+    /// Writes the tree with its variations, and its tags. The result is taken from the final position of the main line
+    /// if the game ended there, and from the `Result` tag otherwise.
+    pub fn write_tree(&self, tree: &PgnStateTree) -> String {
+        let mut words: Vec<String> = Vec::new();
+        let mut attach_to_previous = false;
+        let mut result = None;
+        for token in tree.to_tokens() {
+            let word = match token {
+                PgnToken::StartVariation => {
+                    words.push("(".to_string());
+                    attach_to_previous = true;
+                    continue;
+                },
+                PgnToken::EndVariation => {
+                    if let Some(previous) = words.last_mut() {
+                        previous.push(')');
+                    }
+                    continue;
+                },
+                PgnToken::MoveNumberAndPeriods(fullmove, num_periods) => format!("{}{}", fullmove, ".".repeat(num_periods)),
+                PgnToken::Move(san) => san,
+                PgnToken::Comment(comment) if self.include_annotations => format_comment(&comment),
+                PgnToken::Annotation(annotation) if self.include_annotations => {
+                    // glyphs like "!?" are attached to the move, NAGs are separate words
+                    attach_to_previous = !annotation.starts_with('$');
+                    annotation
+                },
+                PgnToken::Result(token) => {
+                    result = Some(token);
+                    continue;
+                },
+                PgnToken::Tag(_) | PgnToken::Comment(_) | PgnToken::Annotation(_) => continue
+            };
+            match words.last_mut() {
+                Some(previous) if attach_to_previous => previous.push_str(&word),
+                _ => words.push(word)
+            }
+            attach_to_previous = false;
+        }
+
+        let result = result.as_deref()
+            .or_else(|| tree.get_tag("Result").filter(|result| ["1-0", "0-1", "1/2-1/2"].contains(result)))
+            .unwrap_or("*");
+        let mut tags = tree.tags.clone();
+        if !tree.starts_from_initial_position() {
+            tags.insert("SetUp".to_string(), "1".to_string());
+            tags.insert("FEN".to_string(), tree.head.borrow().state_after_move.to_fen());
+        }
+        self.format(&tags, words, result)
+    }
+
+    /// Formats the tags, with the seven tag roster first, and the movetext `words` followed by the result.
+    fn format(&self, tags: &IndexMap<String, String>, mut words: Vec<String>, result: &str) -> String {
+        let mut pgn = String::new();
+        for (name, unknown) in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => result,
+                _ => tags.get(name).map_or(unknown, |value| value.as_str())
+            };
+            pgn += &format_tag(name, value);
+        }
+        for (name, value) in tags {
+            if !SEVEN_TAG_ROSTER.iter().any(|(roster_name, _)| roster_name == name) {
+                pgn += &format_tag(name, value);
+            }
+        }
+        pgn.push('\n');
+
+        words.push(result.to_string());
+        let mut line_length = 0;
+        // comments are split into words, so that they can be wrapped too
+        for word in words.iter().flat_map(|word| word.split_whitespace()) {
+            if line_length > 0 {
+                match self.max_line_length {
+                    Some(max_line_length) if line_length + 1 + word.len() > max_line_length => {
+                        pgn.push('\n');
+                        line_length = 0;
+                    },
+                    _ => {
+                        pgn.push(' ');
+                        line_length += 1;
+                    }
+                }
+            }
+            pgn += word;
+            line_length += word.len();
+        }
+        pgn.push('\n');
+        pgn
+    }
+}
+
+fn format_tag(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Formats a comment in braces, dropping any closing braces in it, which would end it early.
+fn format_comment(comment: &str) -> String {
+    format!("{{{}}}", comment.replace('}', ""))
+}
+
+fn format_result(result: i8) -> &'static str {
+    match result {
+        1 => "1-0",
+        -1 => "0-1",
+        _ => "1/2-1/2"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::*;
+
+    fn play(game: &mut PgnGame, san_moves: &[&str]) {
+        let mut state = game.moves.iter().fold(game.initial_state.clone(), |mut state, pgn_move| {
+            state.make_move(pgn_move.mv);
+            state
+        });
+        for san in san_moves {
+            let legal_moves = state.calc_legal_moves();
+            let mv = *legal_moves.iter().find(|mv| {
+                let mut next_state = state.clone();
+                next_state.make_move(**mv);
+                next_state.check_and_update_termination();
+                mv.to_san(&state, &next_state, &legal_moves) == *san
+            }).unwrap();
+            state.make_move(mv);
+            game.add_move(mv);
+        }
+    }
+
+    #[test]
+    fn test_write_game() {
+        let mut game = PgnGame::new(State::initial()).with_tag("White", "Morphy, \"Paul\"").with_tag("Opening", "Philidor");
+        play(&mut game, &["e4", "e5", "Nf3", "d6"]);
+        let philidor = game.moves.pop().unwrap().with_nag(1).with_comment("The Philidor {Defence}");
+        game.add_move(philidor);
+        play(&mut game, &["d4"]);
+
+        let pgn = PgnWriter::default().write_game(&game).unwrap();
+        assert_eq!(pgn, "\
+[Event \"?\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"Morphy, \\\"Paul\\\"\"]
+[Black \"?\"]
+[Result \"*\"]
+[Opening \"Philidor\"]
+
+1. e4 e5 2. Nf3 d6 $1 {The Philidor {Defence} 3. d4 *
+");
+        let plain = PgnWriter::default().with_annotations(false).write_game(&game.clone().with_result(0)).unwrap();
+        assert!(plain.contains("[Result \"1/2-1/2\"]") && plain.ends_with("\n1. e4 e5 2. Nf3 d6 3. d4 1/2-1/2\n"));
+        let tree = PgnStateTree::from_str(&pgn).unwrap();
+        assert_eq!(tree.get_all_nodes().len(), 6);
+    }
+
+    #[test]
+    fn test_write_game_from_position() {
+        let mut game = PgnGame::new(State::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1").unwrap());
+        play(&mut game, &["Kh8", "Ra8#"]);
+        let pgn = PgnWriter::default().write_game(&game).unwrap();
+        assert!(pgn.contains("[Result \"1-0\"]\n[SetUp \"1\"]\n[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1\"]\n"));
+        assert!(pgn.ends_with("\n\n1... Kh8 2. Ra8# 1-0\n"));
+
+        game.add_move(Move::from_uci(&State::initial(), "e2e4").unwrap());
+        assert_eq!(PgnWriter::default().write_game(&game), Err(PgnWriteError::IllegalMove { halfmove: 2, uci: "e2e4".to_string() }));
+    }
+
+    #[test]
+    fn test_line_wrapping() {
+        let mut game = PgnGame::new(State::initial());
+        play(&mut game, &["e4", "e5", "Nf3", "Nc6", "Bb5", "a6", "Ba4", "Nf6", "O-O", "Be7", "Re1", "b5", "Bb3", "d6", "c3", "O-O", "h3", "Nb8", "d4", "Nbd7"]);
+        let pgn = PgnWriter::default().with_max_line_length(Some(30)).write_game(&game).unwrap();
+        let movetext: Vec<&str> = pgn.split("\n\n").nth(1).unwrap().lines().collect();
+        assert!(movetext.len() > 1 && movetext.iter().all(|line| line.len() <= 30));
+        assert_eq!(movetext.join(" "), PgnWriter::default().with_max_line_length(None).write_game(&game).unwrap().split("\n\n").nth(1).unwrap().trim());
+    }
+
+    #[test]
+    fn test_write_tree() {
+        let mut tree = PgnStateTree::from_str("1. e4 (1. d4 d5) 1... e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7#").unwrap();
+        tree.tags.insert("Event".to_string(), "Casual game".to_string());
+        assert_eq!(PgnWriter::default().write_tree(&tree), "\
+[Event \"Casual game\"]
+[Site \"?\"]
+[Date \"????.??.??\"]
+[Round \"?\"]
+[White \"?\"]
+[Black \"?\"]
+[Result \"1-0\"]
+
+1. e4 (1. d4 d5) 1... e5 2. Qh5 Nc6 3. Bc4 Nf6 4. Qxf7# 1-0
+");
+        let mut unfinished = PgnStateTree::from_str("1. e4 e5").unwrap();
+        assert!(PgnWriter::default().write_tree(&unfinished).ends_with("\n1. e4 e5 *\n"));
+        unfinished.tags.insert("Result".to_string(), "0-1".to_string());
+        assert!(PgnWriter::default().write_tree(&unfinished).ends_with("\n1. e4 e5 0-1\n"));
+
+        let set_up = PgnStateTree::from_str("[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1\"] 1... h6 2. Ra8+").unwrap();
+        let pgn = PgnWriter::default().write_tree(&set_up);
+        assert!(pgn.ends_with("[SetUp \"1\"]\n[FEN \"6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1\"]\n\n1... h6 2. Ra8+ *\n"));
+        assert_eq!(PgnStateTree::from_str(&pgn).unwrap().to_string(), set_up.to_string());
+    }
+}
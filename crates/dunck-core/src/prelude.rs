@@ -0,0 +1,23 @@
+//! The stable public surface of dunck, for glob importing with `use dunck_core::prelude::*`.
+//! Items outside the prelude are still public, but may change between minor versions.
+
+pub use crate::game_record::{GameRecord, MoveMetadata};
+pub use crate::pgn::{PgnParseError, PgnStateTree, PgnStateTreeNode, PgnToken};
+pub use crate::r#move::san::SanError;
+pub use crate::r#move::{Move, MoveFlag};
+pub use crate::state::{Board, FenParseError, State, Termination, INITIAL_FEN};
+pub use crate::utils::{Bitboard, Color, ColoredPiece, EnumParseError, PieceType, Square};
+
+#[cfg(test)]
+mod tests {
+    use crate::prelude::*;
+
+    #[test]
+    fn test_prelude() {
+        let state = State::from_fen(INITIAL_FEN).unwrap();
+        let mv = crate::r#move::san::parse(&state, "e4").unwrap();
+        assert_eq!(mv.get_destination(), Square::E4);
+        assert_eq!(state.board.get_piece_type_at(Square::E2), PieceType::Pawn);
+        assert_eq!(state.side_to_move, Color::White);
+    }
+}
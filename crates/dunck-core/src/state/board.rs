@@ -99,12 +99,11 @@ impl Board {
         true
     }
     
-    /// Returns true if `mask` is attacked by any piece of the given color.
-    /// Else, returns false.
-    pub fn is_mask_in_check(&self, mask: Bitboard, by_color: Color) -> bool {
+    /// Returns the mask of all squares attacked by pieces of the given color,
+    /// with `occupied_mask` as the mask of occupied squares.
+    pub fn calc_attack_mask(&self, by_color: Color, occupied_mask: Bitboard) -> Bitboard {
         let attacking_color_mask = self.color_masks[by_color as usize];
-        let occupied_mask = self.piece_type_masks[PieceType::AllPieceTypes as usize];
-        
+
         let pawns_mask = self.piece_type_masks[PieceType::Pawn as usize];
         let knights_mask = self.piece_type_masks[PieceType::Knight as usize];
         let bishops_mask = self.piece_type_masks[PieceType::Bishop as usize];
@@ -126,7 +125,13 @@ impl Board {
         
         attacks |= multi_king_attacks(kings_mask & attacking_color_mask);
         
-        attacks & mask != 0
+        attacks
+    }
+    
+    /// Returns true if `mask` is attacked by any piece of the given color.
+    /// Else, returns false.
+    pub fn is_mask_in_check(&self, mask: Bitboard, by_color: Color) -> bool {
+        self.calc_attack_mask(by_color, self.piece_type_masks[PieceType::AllPieceTypes as usize]) & mask != 0
     }
 
     /// Returns true if the given color's king is in check.
@@ -136,7 +141,49 @@ impl Board {
             color.flip()
         )
     }
-    
+
+    /// Returns the attack mask of the piece at `square`, with `occupied_mask` as the mask of occupied squares.
+    /// Returns an empty mask if `square` is empty.
+    pub fn calc_attacks_from_with_occupancy(&self, square: Square, occupied_mask: Bitboard) -> Bitboard {
+        match self.get_piece_type_at(square) {
+            PieceType::NoPieceType => 0,
+            PieceType::Pawn => multi_pawn_attacks(square.get_mask(), self.get_color_at(square)),
+            PieceType::Knight => single_knight_attacks(square),
+            PieceType::Bishop => single_bishop_attacks(square, occupied_mask),
+            PieceType::Rook => single_rook_attacks(square, occupied_mask),
+            PieceType::Queen => single_bishop_attacks(square, occupied_mask) | single_rook_attacks(square, occupied_mask),
+            PieceType::King => single_king_attacks(square)
+        }
+    }
+
+    /// Returns a mask of all pieces of either color that attack `square`,
+    /// with `occupied_mask` as the mask of occupied squares.
+    pub fn calc_attackers_to(&self, square: Square, occupied_mask: Bitboard) -> Bitboard {
+        let mask = square.get_mask();
+        let white_mask = self.color_masks[Color::White as usize];
+        let black_mask = self.color_masks[Color::Black as usize];
+
+        let pawns_mask = self.piece_type_masks[PieceType::Pawn as usize];
+        let knights_mask = self.piece_type_masks[PieceType::Knight as usize];
+        let bishops_mask = self.piece_type_masks[PieceType::Bishop as usize];
+        let rooks_mask = self.piece_type_masks[PieceType::Rook as usize];
+        let queens_mask = self.piece_type_masks[PieceType::Queen as usize];
+        let kings_mask = self.piece_type_masks[PieceType::King as usize];
+
+        (multi_pawn_attacks(mask, Color::Black) & pawns_mask & white_mask) |
+            (multi_pawn_attacks(mask, Color::White) & pawns_mask & black_mask) |
+            (single_knight_attacks(square) & knights_mask) |
+            (single_bishop_attacks(square, occupied_mask) & (bishops_mask | queens_mask)) |
+            (single_rook_attacks(square, occupied_mask) & (rooks_mask | queens_mask)) |
+            (single_king_attacks(square) & kings_mask)
+    }
+
+    /// Returns the attack mask of the piece at `square` on the current board.
+    /// Returns an empty mask if `square` is empty.
+    pub fn calc_attacks_from(&self, square: Square) -> Bitboard {
+        self.calc_attacks_from_with_occupancy(square, self.piece_type_masks[PieceType::AllPieceTypes as usize])
+    }
+
     /// Populates a square with `color`, but no piece type.
     /// Does not update the zobrist hash.
     pub fn put_color_at(&mut self, color: Color, square: Square) {
@@ -293,6 +340,22 @@ impl Board {
         kings_bb.count_ones() == 2 && (white_bb & kings_bb).count_ones() == 1
     }
     
+    /// Checks if a pawn of `color` is next to the square that a pawn pushed two squares on `file` landed on,
+    /// i.e. if `color` could capture it en passant, disregarding pins.
+    pub fn has_en_passant_capturer(&self, color: Color, file: u8) -> bool {
+        let capturing_rank = match color {
+            Color::White => 4,
+            Color::Black => 3
+        };
+        let capturing_pawns_mask = self.piece_type_masks[PieceType::Pawn as usize] & self.color_masks[color as usize];
+        [file as i8 - 1, file as i8 + 1].iter()
+            .filter(|adjacent_file| (0..8).contains(*adjacent_file))
+            .any(|adjacent_file| {
+                let square = unsafe { Square::from_rank_file(capturing_rank, *adjacent_file as u8) };
+                capturing_pawns_mask & square.get_mask() != 0
+            })
+    }
+
     /// Checks if the zobrist hash is correctly calculated.
     pub fn is_zobrist_valid(&self) -> bool {
         self.zobrist_hash == self.calc_zobrist_hash()
@@ -2,6 +2,8 @@
 
 use std::cell::RefCell;
 use std::rc::Rc;
+use crate::r#move::Move;
+use crate::state::MoveGenCache;
 use crate::utils::Bitboard;
 use crate::utils::masks::{STARTING_KING_SIDE_ROOK, STARTING_QUEEN_SIDE_ROOK};
 use crate::utils::{Color, ColoredPiece, PieceType, Square};
@@ -17,7 +19,12 @@ pub struct Context {
     // updated after every move
     pub captured_piece: PieceType,
     pub previous: Option<Rc<RefCell<Context>>>,
-    pub zobrist_hash: Bitboard
+    pub last_move: Option<Move>, // move that led to this context, if any
+    pub zobrist_hash: Bitboard,
+    pub repetition_count: u8, // number of earlier occurrences of the position since the last halfmove clock reset
+
+    // filled lazily by move generation
+    pub movegen_cache: MoveGenCache
 }
 
 impl Context {
@@ -30,7 +37,10 @@ impl Context {
             castling_rights: previous.castling_rights,
             captured_piece: PieceType::NoPieceType,
             previous: Some(previous_context.clone()),
-            zobrist_hash
+            last_move: None,
+            zobrist_hash,
+            repetition_count: 0,
+            movegen_cache: MoveGenCache::default()
         }
     }
 
@@ -44,7 +54,10 @@ impl Context {
             castling_rights: 0b00001111,
             captured_piece: PieceType::NoPieceType,
             previous: None,
-            zobrist_hash
+            last_move: None,
+            zobrist_hash,
+            repetition_count: 0,
+            movegen_cache: MoveGenCache::default()
         }
     }
 
@@ -57,13 +70,16 @@ impl Context {
             castling_rights: 0b00000000,
             captured_piece: PieceType::NoPieceType,
             previous: None,
-            zobrist_hash
+            last_move: None,
+            zobrist_hash,
+            repetition_count: 0,
+            movegen_cache: MoveGenCache::default()
         }
     }
 
-    /// Checks if the halfmove clock is valid (less than or equal to 100).
+    /// Checks if the halfmove clock is valid (at most 150, when the seventy-five-move rule ends the game).
     pub fn has_valid_halfmove_clock(&self) -> bool {
-        self.halfmove_clock <= 100
+        self.halfmove_clock <= 150
     }
     
     /// Gets the last context belonging to a position that could be the same as the current position
@@ -86,17 +102,15 @@ impl Context {
         }
     }
     
-    /// Checks if threefold repetition has occurred by checking if the zobrist hash of the current
-    /// position has occurred three times, searching backward until the halfmove clock indicates
-    /// that no more possible repetitions could have occurred, or until there are no more previous
-    /// contexts.
-    pub fn has_threefold_repetition_occurred(&self) -> bool {
+    /// Calculates the repetition count of the current position from the previous contexts, by searching
+    /// backward for the last occurrence of the zobrist hash of the current position, until the halfmove clock
+    /// indicates that no more possible repetitions could have occurred, or until there are no more previous
+    /// contexts. The count of that occurrence already includes the ones before it, so the search stops there.
+    pub fn calc_repetition_count(&self) -> u8 {
         if self.halfmove_clock < 4 {
-            return false;
+            return 0;
         }
 
-        let mut count = 1;
-        
         let mut current_context = self.get_previous_possible_repetition();
         let mut expected_halfmove_clock = self.halfmove_clock - 2;
         
@@ -108,16 +122,18 @@ impl Context {
             }
             
             if context.zobrist_hash == self.zobrist_hash {
-                count += 1;
-                if count == 3 {
-                    return true;
-                }
+                return context.repetition_count + 1;
             }
             
             expected_halfmove_clock = expected_halfmove_clock.wrapping_sub(2);
             current_context = context.get_previous_possible_repetition();
         }
         
-        false
+        0
+    }
+
+    /// Checks if threefold repetition has occurred, i.e. if the current position has occurred twice before.
+    pub fn has_threefold_repetition_occurred(&self) -> bool {
+        self.repetition_count >= 2
     }
 }
\ No newline at end of file
@@ -0,0 +1,177 @@
+//! Rendering of positions as ASCII or SVG diagrams, with optional arrows and highlighted squares.
+
+use std::fmt::Write;
+use crate::r#move::Move;
+use crate::state::State;
+use crate::utils::{ColoredPiece, Square};
+
+const SVG_SQUARE_SIZE: u32 = 45;
+const SVG_LIGHT_SQUARE_COLOR: &str = "#f0d9b5";
+const SVG_DARK_SQUARE_COLOR: &str = "#b58863";
+const SVG_HIGHLIGHT_COLOR: &str = "#ffff3f";
+const SVG_ARROW_COLOR: &str = "#15781b";
+
+/// Marks to draw on top of a position, e.g. from analysis data.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiagramAnnotations {
+    /// Arrows from the first square to the second.
+    pub arrows: Vec<(Square, Square)>,
+    pub highlights: Vec<Square>,
+    /// If set, the board is drawn from black's side.
+    pub flipped: bool,
+}
+
+impl DiagramAnnotations {
+    /// Creates annotations with an arrow for each move, e.g. the principal variation or candidate moves.
+    pub fn from_moves(moves: &[Move]) -> DiagramAnnotations {
+        DiagramAnnotations {
+            arrows: moves.iter().map(|mv| (mv.get_source(), mv.get_destination())).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Returns the column and row of `square` in the diagram, counted from the top left.
+    fn get_position(&self, square: Square) -> (u32, u32) {
+        let (file, rank) = (square.get_file() as u32, square.get_rank() as u32);
+        if self.flipped { (7 - file, rank) } else { (file, 7 - rank) }
+    }
+
+    /// Returns the square drawn at `column` and `row`, counted from the top left.
+    fn get_square(&self, column: u32, row: u32) -> Square {
+        let (file, rank) = if self.flipped { (7 - column, row) } else { (column, 7 - row) };
+        unsafe { Square::from_rank_file(rank as u8, file as u8) }
+    }
+}
+
+impl State {
+    /// Renders the position as text, with highlighted squares in brackets and arrows listed below the board.
+    pub fn to_ascii_diagram(&self, annotations: &DiagramAnnotations) -> String {
+        let mut res = String::new();
+        for row in 0..8 {
+            let rank_char = annotations.get_square(0, row).get_rank_char();
+            res.push(rank_char);
+            res.push(' ');
+            for column in 0..8 {
+                let square = annotations.get_square(column, row);
+                let piece_char = match self.board.get_colored_piece_at(square) {
+                    ColoredPiece::NoPiece => '.',
+                    colored_piece => colored_piece.to_char()
+                };
+                if annotations.highlights.contains(&square) {
+                    write!(res, "[{}]", piece_char).unwrap();
+                } else {
+                    write!(res, " {} ", piece_char).unwrap();
+                }
+            }
+            res.push('\n');
+        }
+        res.push_str("  ");
+        for column in 0..8 {
+            write!(res, " {} ", annotations.get_square(column, 0).get_file_char()).unwrap();
+        }
+        for (src, dst) in annotations.arrows.iter() {
+            write!(res, "\n{} -> {}", src.readable(), dst.readable()).unwrap();
+        }
+        res
+    }
+
+    /// Renders the position as a standalone SVG image, with pieces drawn as Unicode chess symbols.
+    pub fn to_svg_diagram(&self, annotations: &DiagramAnnotations) -> String {
+        let board_size = 8 * SVG_SQUARE_SIZE;
+        let mut res = String::new();
+        writeln!(
+            res,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{0}" height="{0}" viewBox="0 0 {0} {0}">"#,
+            board_size
+        ).unwrap();
+        writeln!(
+            res,
+            r#"<defs><marker id="arrowhead" markerWidth="4" markerHeight="4" refX="2" refY="2" orient="auto"><path d="M0,0 L4,2 L0,4 Z" fill="{}"/></marker></defs>"#,
+            SVG_ARROW_COLOR
+        ).unwrap();
+
+        for square in Square::iter_all() {
+            let (column, row) = annotations.get_position(*square);
+            let (x, y) = (column * SVG_SQUARE_SIZE, row * SVG_SQUARE_SIZE);
+            let is_light = (square.get_file() + square.get_rank()) % 2 == 1;
+            let color = if is_light { SVG_LIGHT_SQUARE_COLOR } else { SVG_DARK_SQUARE_COLOR };
+            writeln!(res, r#"<rect x="{}" y="{}" width="{2}" height="{2}" fill="{3}"/>"#, x, y, SVG_SQUARE_SIZE, color).unwrap();
+            if annotations.highlights.contains(square) {
+                writeln!(
+                    res,
+                    r#"<rect class="highlight" x="{}" y="{}" width="{2}" height="{2}" fill="{3}" fill-opacity="0.5"/>"#,
+                    x, y, SVG_SQUARE_SIZE, SVG_HIGHLIGHT_COLOR
+                ).unwrap();
+            }
+
+            let colored_piece = self.board.get_colored_piece_at(*square);
+            if colored_piece != ColoredPiece::NoPiece {
+                writeln!(
+                    res,
+                    r#"<text x="{}" y="{}" font-size="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                    x + SVG_SQUARE_SIZE / 2, y + SVG_SQUARE_SIZE / 2, SVG_SQUARE_SIZE * 4 / 5, colored_piece.to_char_pretty()
+                ).unwrap();
+            }
+        }
+
+        let get_center = |square: Square| {
+            let (column, row) = annotations.get_position(square);
+            (column * SVG_SQUARE_SIZE + SVG_SQUARE_SIZE / 2, row * SVG_SQUARE_SIZE + SVG_SQUARE_SIZE / 2)
+        };
+        for (src, dst) in annotations.arrows.iter() {
+            let ((x1, y1), (x2, y2)) = (get_center(*src), get_center(*dst));
+            writeln!(
+                res,
+                r#"<line class="arrow" x1="{}" y1="{}" x2="{}" y2="{}" stroke="{}" stroke-width="{}" stroke-opacity="0.8" marker-end="url(#arrowhead)"/>"#,
+                x1, y1, x2, y2, SVG_ARROW_COLOR, SVG_SQUARE_SIZE / 5
+            ).unwrap();
+        }
+
+        res.push_str("</svg>");
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r#move::MoveFlag;
+
+    #[test]
+    fn test_ascii_diagram() {
+        let state = State::initial();
+        let annotations = DiagramAnnotations {
+            arrows: vec![(Square::E2, Square::E4)],
+            highlights: vec![Square::E2],
+            flipped: false,
+        };
+        let diagram = state.to_ascii_diagram(&annotations);
+        let lines: Vec<&str> = diagram.lines().collect();
+        assert_eq!(lines[0], "8  r  n  b  q  k  b  n  r ");
+        assert_eq!(lines[6], "2  P  P  P  P [P] P  P  P ");
+        assert_eq!(lines[8], "   a  b  c  d  e  f  g  h ");
+        assert_eq!(lines[9], "e2 -> e4");
+
+        let flipped = state.to_ascii_diagram(&DiagramAnnotations { flipped: true, ..Default::default() });
+        let lines: Vec<&str> = flipped.lines().collect();
+        assert_eq!(lines[0], "1  R  N  B  K  Q  B  N  R ");
+        assert_eq!(lines[8], "   h  g  f  e  d  c  b  a ");
+    }
+
+    #[test]
+    fn test_svg_diagram() {
+        let state = State::initial();
+        let mv = Move::new_non_promotion(Square::F3, Square::G1, MoveFlag::NormalMove);
+        let mut annotations = DiagramAnnotations::from_moves(&[mv]);
+        annotations.highlights.push(Square::F3);
+        let svg = state.to_svg_diagram(&annotations);
+
+        assert!(svg.starts_with("<svg") && svg.ends_with("</svg>"));
+        assert_eq!(svg.matches("<text").count(), 32);
+        assert_eq!(svg.matches(r#"class="highlight""#).count(), 1);
+        // g1 is at column 6, row 7 and f3 at column 5, row 5, both centered
+        assert!(svg.contains(r#"x1="292" y1="337" x2="247" y2="247""#));
+        // a1 is dark
+        assert!(svg.contains(&format!(r#"<rect x="0" y="315" width="45" height="45" fill="{}"/>"#, SVG_DARK_SQUARE_COLOR)));
+    }
+}
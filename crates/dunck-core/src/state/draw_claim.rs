@@ -0,0 +1,131 @@
+//! Draws that a player may claim, as opposed to the terminations that end the game by themselves.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use crate::state::{State, Termination};
+
+/// A draw that the side to move may claim, but that does not end the game unless claimed.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum DrawClaim {
+    /// The position has occurred at least three times.
+    ThreefoldRepetition,
+    /// Each side has made fifty moves without a capture or a pawn move.
+    FiftyMoveRule,
+}
+
+impl Display for DrawClaim {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DrawClaim::ThreefoldRepetition => write!(f, "threefold repetition"),
+            DrawClaim::FiftyMoveRule => write!(f, "the fifty-move rule"),
+        }
+    }
+}
+
+impl From<DrawClaim> for Termination {
+    fn from(claim: DrawClaim) -> Termination {
+        match claim {
+            DrawClaim::ThreefoldRepetition => Termination::ThreefoldRepetition,
+            DrawClaim::FiftyMoveRule => Termination::FiftyMoveRule,
+        }
+    }
+}
+
+impl State {
+    /// Returns the draws that can be claimed in the current position, which are none once the game is over.
+    /// Fivefold repetition, the seventy-five-move rule and insufficient material are set as the termination
+    /// by `make_move` instead, and stalemate by `check_and_update_termination`.
+    pub fn claimable_draws(&self) -> Vec<DrawClaim> {
+        if self.termination.is_some() {
+            return Vec::new();
+        }
+        let mut claims = Vec::new();
+        if self.is_threefold_repetition() {
+            claims.push(DrawClaim::ThreefoldRepetition);
+        }
+        if self.context.borrow().halfmove_clock >= 100 {
+            claims.push(DrawClaim::FiftyMoveRule);
+        }
+        // checkmate and stalemate end the game before a draw can be claimed
+        if !claims.is_empty() && self.calc_legal_moves().is_empty() {
+            claims.clear();
+        }
+        claims
+    }
+
+    /// Ends the game in a draw by `claim`, if it can be claimed in the current position.
+    pub fn claim_draw(&mut self, claim: DrawClaim) -> Result<(), String> {
+        if !self.claimable_draws().contains(&claim) {
+            return Err(format!("Cannot claim a draw by {} in this position", claim));
+        }
+        self.termination = Some(claim.into());
+        Ok(())
+    }
+
+    /// Claims the first draw that can be claimed, if any, and returns it.
+    /// Engines and arbiters of engine games use this to treat claimable draws like automatic ones.
+    pub fn claim_any_draw(&mut self) -> Option<DrawClaim> {
+        let claim = *self.claimable_draws().first()?;
+        self.termination = Some(claim.into());
+        Some(claim)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::r#move::Move;
+    use super::*;
+
+    fn play(state: &mut State, ucis: &[&str]) {
+        for uci in ucis {
+            state.make_move(Move::from_uci(state, uci).unwrap());
+        }
+    }
+
+    #[test]
+    fn test_repetition_claims() {
+        let mut state = State::initial();
+        let knight_moves = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        play(&mut state, &knight_moves);
+        assert!(state.claimable_draws().is_empty());
+        assert!(state.claim_draw(DrawClaim::ThreefoldRepetition).is_err());
+
+        // threefold repetition can be claimed, but the game goes on until the fifth occurrence
+        play(&mut state, &knight_moves);
+        assert_eq!(state.termination, None);
+        assert_eq!(state.claimable_draws(), [DrawClaim::ThreefoldRepetition]);
+        let mut claimed = state.clone();
+        claimed.claim_draw(DrawClaim::ThreefoldRepetition).unwrap();
+        assert_eq!(claimed.termination, Some(Termination::ThreefoldRepetition));
+        assert!(claimed.claimable_draws().is_empty());
+
+        play(&mut state, &knight_moves);
+        assert_eq!(state.termination, None);
+        play(&mut state, &knight_moves);
+        assert_eq!(state.termination, Some(Termination::FivefoldRepetition));
+        assert!(state.claimable_draws().is_empty());
+    }
+
+    #[test]
+    fn test_move_rule_claims() {
+        let mut state = State::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        assert!(state.claimable_draws().is_empty());
+        play(&mut state, &["a1a2"]);
+        assert_eq!(state.claimable_draws(), [DrawClaim::FiftyMoveRule]);
+        assert!(state.claim_draw(DrawClaim::ThreefoldRepetition).is_err());
+        assert_eq!(state.claim_any_draw(), Some(DrawClaim::FiftyMoveRule));
+        assert_eq!(state.termination, Some(Termination::FiftyMoveRule));
+
+        let mut state = State::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 149 105").unwrap();
+        play(&mut state, &["a1a2"]);
+        assert_eq!(state.termination, Some(Termination::SeventyFiveMoveRule));
+
+        // checkmate on the last move takes precedence over the seventy-five-move rule
+        let mut state = State::from_fen("4k3/R7/4K3/8/8/8/8/8 w - - 149 105").unwrap();
+        play(&mut state, &["a7a8"]);
+        assert_eq!(state.termination, None);
+        assert!(state.claimable_draws().is_empty());
+        state.check_and_update_termination();
+        assert_eq!(state.termination, Some(Termination::Checkmate));
+    }
+}
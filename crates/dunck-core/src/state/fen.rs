@@ -1,5 +1,7 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
 use crate::utils::{Color, ColoredPiece, PieceType, Square};
-use crate::state::State;
+use crate::state::{State, ValidationProfile};
 
 pub const INITIAL_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
 
@@ -13,9 +15,32 @@ pub enum FenParseError {
     InvalidEnPassantTarget(String),
     InvalidHalfmoveClock(String),
     InvalidFullmoveCounter(String),
+    /// The halfmove clock exceeds the number of halfmoves played, which the strict validation profile rejects.
+    ImplausibleHalfmoveClock(u8, u16),
     InvalidState(String)
 }
 
+impl Display for FenParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenParseError::InvalidFieldCount(count) => write!(f, "Invalid field count: {}", count),
+            FenParseError::InvalidRankCount(count) => write!(f, "Invalid rank count: {}", count),
+            FenParseError::InvalidRow(row) => write!(f, "Invalid row: {}", row),
+            FenParseError::InvalidSideToMove(side_to_move) => write!(f, "Invalid side to move: {}", side_to_move),
+            FenParseError::InvalidCastle(castle) => write!(f, "Invalid castling rights: {}", castle),
+            FenParseError::InvalidEnPassantTarget(target) => write!(f, "Invalid en passant target: {}", target),
+            FenParseError::InvalidHalfmoveClock(clock) => write!(f, "Invalid halfmove clock: {}", clock),
+            FenParseError::InvalidFullmoveCounter(counter) => write!(f, "Invalid fullmove counter: {}", counter),
+            FenParseError::ImplausibleHalfmoveClock(clock, halfmove) => {
+                write!(f, "Halfmove clock {} exceeds the {} halfmoves played", clock, halfmove)
+            },
+            FenParseError::InvalidState(fen) => write!(f, "Invalid state: {}", fen),
+        }
+    }
+}
+
+impl Error for FenParseError {}
+
 fn process_fen_side_to_move(state: &mut State, fen_side_to_move: &str) -> bool {
     if fen_side_to_move == "w" {
         state.side_to_move = Color::White;
@@ -94,7 +119,7 @@ fn process_fen_halfmove_clock(state: &mut State, fen_halfmove_clock: &str) -> bo
     let halfmove_clock_parsed = fen_halfmove_clock.parse::<u8>();
     match halfmove_clock_parsed {
         Ok(halfmove_clock) => {
-            if halfmove_clock > 100 {
+            if halfmove_clock > 150 {
                 return false;
             }
             state.context.borrow_mut().halfmove_clock = halfmove_clock;
@@ -169,68 +194,82 @@ fn process_fen_board(state: &mut State, fen_board: &str) -> Result<State, FenPar
     Ok(State::blank())
 }
 
+/// Parses `fen` without checking that the resulting state is valid.
+pub(crate) fn parse_fen(fen: &str) -> Result<State, FenParseError> {
+    let mut state = State::blank();
+    
+    let fen_parts: Vec<&str> = fen.split_ascii_whitespace().collect();
+    if fen_parts.len() != 6 {
+        return Err(FenParseError::InvalidFieldCount(fen_parts.len()));
+    }
+    
+    let [
+        fen_board, 
+        fen_side_to_move, 
+        fen_castle, 
+        fen_double_pawn_push, 
+        fen_halfmove_clock, 
+        fen_fullmove
+    ] = match &fen_parts[..] {
+        [
+            board, 
+            side_to_move, 
+            castle, 
+            double_pawn_push, 
+            halfmove_clock, 
+            fullmove
+        ] => [board, side_to_move, castle, double_pawn_push, halfmove_clock, fullmove],
+        _ => return Err(FenParseError::InvalidFieldCount(fen_parts.len())),
+    };
+    
+    let is_fen_side_to_move_valid = process_fen_side_to_move(&mut state, fen_side_to_move);
+    if !is_fen_side_to_move_valid {
+        return Err(FenParseError::InvalidSideToMove(fen_side_to_move.to_string()));
+    }
+    
+    let is_fen_castle_valid = process_fen_castle(&mut state, fen_castle);
+    if !is_fen_castle_valid {
+        return Err(FenParseError::InvalidCastle(fen_castle.to_string()));
+    }
+    
+    let is_fen_double_pawn_push_valid = process_en_passant_target_square(&mut state, fen_double_pawn_push);
+    if !is_fen_double_pawn_push_valid {
+        return Err(FenParseError::InvalidEnPassantTarget(fen_double_pawn_push.to_string()));
+    }
+    
+    let is_fen_halfmove_clock_valid = process_fen_halfmove_clock(&mut state, fen_halfmove_clock);
+    if !is_fen_halfmove_clock_valid {
+        return Err(FenParseError::InvalidHalfmoveClock(fen_halfmove_clock.to_string()));
+    }
+    
+    let is_fen_fullmove_valid = process_fen_fullmove(&mut state, fen_fullmove);
+    if !is_fen_fullmove_valid {
+        return Err(FenParseError::InvalidFullmoveCounter(fen_fullmove.to_string()));
+    }
+    
+    let fen_board_result = process_fen_board(&mut state, fen_board);
+    if fen_board_result.is_err() {
+        return fen_board_result;
+    }
+
+    state.board.zobrist_hash = state.board.calc_zobrist_hash();
+    let zobrist_hash = state.calc_zobrist_hash();
+    state.context.borrow_mut().zobrist_hash = zobrist_hash;
+
+    Ok(state)
+}
+
 impl State {
+    /// Parses `fen`, checking the state with the strict validation profile.
     pub fn from_fen(fen: &str) -> Result<State, FenParseError> {
-        let mut state = State::blank();
-        
-        let fen_parts: Vec<&str> = fen.split_ascii_whitespace().collect();
-        if fen_parts.len() != 6 {
-            return Err(FenParseError::InvalidFieldCount(fen_parts.len()));
-        }
-        
-        let [
-            fen_board, 
-            fen_side_to_move, 
-            fen_castle, 
-            fen_double_pawn_push, 
-            fen_halfmove_clock, 
-            fen_fullmove
-        ] = match &fen_parts[..] {
-            [
-                board, 
-                side_to_move, 
-                castle, 
-                double_pawn_push, 
-                halfmove_clock, 
-                fullmove
-            ] => [board, side_to_move, castle, double_pawn_push, halfmove_clock, fullmove],
-            _ => return Err(FenParseError::InvalidFieldCount(fen_parts.len())),
-        };
-        
-        let is_fen_side_to_move_valid = process_fen_side_to_move(&mut state, fen_side_to_move);
-        if !is_fen_side_to_move_valid {
-            return Err(FenParseError::InvalidSideToMove(fen_side_to_move.to_string()));
-        }
-        
-        let is_fen_castle_valid = process_fen_castle(&mut state, fen_castle);
-        if !is_fen_castle_valid {
-            return Err(FenParseError::InvalidCastle(fen_castle.to_string()));
-        }
-        
-        let is_fen_double_pawn_push_valid = process_en_passant_target_square(&mut state, fen_double_pawn_push);
-        if !is_fen_double_pawn_push_valid {
-            return Err(FenParseError::InvalidEnPassantTarget(fen_double_pawn_push.to_string()));
-        }
-        
-        let is_fen_halfmove_clock_valid = process_fen_halfmove_clock(&mut state, fen_halfmove_clock);
-        if !is_fen_halfmove_clock_valid {
-            return Err(FenParseError::InvalidHalfmoveClock(fen_halfmove_clock.to_string()));
-        }
-        
-        let is_fen_fullmove_valid = process_fen_fullmove(&mut state, fen_fullmove);
-        if !is_fen_fullmove_valid {
-            return Err(FenParseError::InvalidFullmoveCounter(fen_fullmove.to_string()));
-        }
-        
-        let fen_board_result = process_fen_board(&mut state, fen_board);
-        if fen_board_result.is_err() {
-            return fen_board_result;
-        }
+        State::from_fen_with_profile(fen, ValidationProfile::Strict)
+    }
 
-        let zobrist_hash = state.board.calc_zobrist_hash();
-        state.board.zobrist_hash = zobrist_hash;
-        state.context.borrow_mut().zobrist_hash = zobrist_hash;
-        
+    /// Like `from_fen`, but clears castling rights that are inconsistent with the position of the
+    /// kings and rooks instead of rejecting the FEN, as some sources do not update them.
+    pub fn from_fen_with_castling_repair(fen: &str) -> Result<State, FenParseError> {
+        let mut state = parse_fen(fen)?;
+        state.repair_castling_rights();
         if state.is_unequivocally_valid() {
             Ok(state)
         } else {
@@ -415,10 +454,10 @@ mod tests {
         let is_valid = process_fen_halfmove_clock(&mut state, "0");
         assert!(is_valid);
         assert_eq!(state.context.borrow().halfmove_clock, 0);
-        let is_valid = process_fen_halfmove_clock(&mut state, "100");
+        let is_valid = process_fen_halfmove_clock(&mut state, "150");
         assert!(is_valid);
-        assert_eq!(state.context.borrow().halfmove_clock, 100);
-        let is_valid = process_fen_halfmove_clock(&mut state, "101");
+        assert_eq!(state.context.borrow().halfmove_clock, 150);
+        let is_valid = process_fen_halfmove_clock(&mut state, "151");
         assert!(!is_valid);
         let is_valid = process_fen_halfmove_clock(&mut state, "101a");
         assert!(!is_valid);
@@ -497,7 +536,8 @@ mod tests {
         assert!(is_valid);
         assert!(state.board.is_unequivocally_valid());
         state.context.borrow_mut().castling_rights = 0b00001111;
-        state.context.borrow_mut().zobrist_hash = state.board.zobrist_hash;
+        let zobrist_hash = state.calc_zobrist_hash();
+        state.context.borrow_mut().zobrist_hash = zobrist_hash;
         assert_eq!(state, State::initial());
     }
     
@@ -509,7 +549,8 @@ mod tests {
         assert!(result.is_ok());
         assert!(state.board.is_unequivocally_valid());
         state.context.borrow_mut().castling_rights = 0b00001111;
-        state.context.borrow_mut().zobrist_hash = state.board.zobrist_hash;
+        let zobrist_hash = state.calc_zobrist_hash();
+        state.context.borrow_mut().zobrist_hash = zobrist_hash;
         assert_eq!(state, State::initial());
         
         let mut state = State::blank();
@@ -563,7 +604,8 @@ mod tests {
         expected_state.halfmove = 175;
         expected_state.side_to_move = Color::Black;
         expected_state.context.borrow_mut().halfmove_clock = 99;
-        expected_state.context.borrow_mut().zobrist_hash = expected_state.board.zobrist_hash;
+        let zobrist_hash = expected_state.calc_zobrist_hash();
+        expected_state.context.borrow_mut().zobrist_hash = zobrist_hash;
         assert_eq!(state, expected_state);
         
         let fen = "r2qk2r/8/8/7p/8/8/8/R2QK2R w KQkq h6 0 6";
@@ -587,7 +629,8 @@ mod tests {
         expected_state.board.put_colored_piece_at(ColoredPiece::BlackPawn, Square::H5);
         expected_state.halfmove = 10;
         expected_state.context.borrow_mut().double_pawn_push = 7;
-        expected_state.context.borrow_mut().zobrist_hash = expected_state.board.zobrist_hash;
+        let zobrist_hash = expected_state.calc_zobrist_hash();
+        expected_state.context.borrow_mut().zobrist_hash = zobrist_hash;
         assert_eq!(state, expected_state);
     }
     
@@ -619,7 +662,9 @@ mod tests {
         let fen = "1k2N1K1/4Q3/6p1/2B2B2/p1PPb3/2P2Nb1/2r5/n7 b - - 36 18";
         let state_result = State::from_fen(fen);
         assert!(state_result.is_err());
-        assert_eq!(state_result.err().unwrap(), FenParseError::InvalidState(fen.to_string()));
+        assert_eq!(state_result.err().unwrap(), FenParseError::ImplausibleHalfmoveClock(36, 35));
+        let state_result = State::from_fen_with_profile(fen, ValidationProfile::Lenient);
+        assert_eq!(state_result.unwrap().to_fen(), fen);
 
         let fen = "1k2N1K1/4Q3/6p1/2B2B2/p1PPb3/2P2Nb1/2r5/n7 b - - 35 18";
         let state_result = State::from_fen(fen);
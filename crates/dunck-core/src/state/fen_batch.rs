@@ -0,0 +1,138 @@
+//! Batch FEN parsing and validation, for EPD suites and dataset ingestion.
+
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use std::thread;
+use crate::state::{Board, Context, FenParseError, State};
+use crate::utils::{Bitboard, Color};
+
+/// A FEN parse error, along with the (1-based) line number it occurred on.
+#[derive(Eq, PartialEq, Debug)]
+pub struct FenBatchError {
+    pub line_number: usize,
+    pub error: FenParseError
+}
+
+impl Display for FenBatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Line {}: {}", self.line_number, self.error)
+    }
+}
+
+/// The fields of a parsed and validated FEN. Unlike a `State`, which holds an `Rc`,
+/// it can be sent back from the thread that parsed it.
+struct ParsedFen {
+    board: Board,
+    side_to_move: Color,
+    halfmove: u16,
+    halfmove_clock: u8,
+    double_pawn_push: i8,
+    castling_rights: u8,
+    zobrist_hash: Bitboard
+}
+
+impl ParsedFen {
+    fn from_state(state: &State) -> ParsedFen {
+        let context = state.context.borrow();
+        ParsedFen {
+            board: state.board.clone(),
+            side_to_move: state.side_to_move,
+            halfmove: state.halfmove,
+            halfmove_clock: context.halfmove_clock,
+            double_pawn_push: context.double_pawn_push,
+            castling_rights: context.castling_rights,
+            zobrist_hash: context.zobrist_hash
+        }
+    }
+
+    fn into_state(self) -> State {
+        let mut context = Context::initial_no_castling(self.zobrist_hash);
+        context.halfmove_clock = self.halfmove_clock;
+        context.double_pawn_push = self.double_pawn_push;
+        context.castling_rights = self.castling_rights;
+        State {
+            board: self.board,
+            side_to_move: self.side_to_move,
+            halfmove: self.halfmove,
+            termination: None,
+            context: Rc::new(RefCell::new(context))
+        }
+    }
+}
+
+/// Parses every line as a FEN using up to `num_threads` threads, returning one result per line, in order.
+fn parse_fen_batch(lines: &[&str], num_threads: usize) -> Vec<Result<ParsedFen, FenBatchError>> {
+    if lines.is_empty() {
+        return Vec::new();
+    }
+    let chunk_size = lines.len().div_ceil(num_threads.max(1));
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = lines.chunks(chunk_size).enumerate().map(|(chunk_index, chunk)| {
+            scope.spawn(move || {
+                chunk.iter().enumerate().map(|(i, line)| {
+                    State::from_fen(line.trim())
+                        .map(|state| ParsedFen::from_state(&state))
+                        .map_err(|error| FenBatchError { line_number: chunk_index * chunk_size + i + 1, error })
+                }).collect::<Vec<_>>()
+            })
+        }).collect();
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+impl State {
+    /// Parses each line as a FEN using up to `num_threads` threads, returning one result per line, in order.
+    /// Lines are parsed and validated on the worker threads, but the states are built on the calling thread.
+    pub fn from_fen_batch(lines: &[&str], num_threads: usize) -> Vec<Result<State, FenBatchError>> {
+        parse_fen_batch(lines, num_threads).into_iter().map(|result| result.map(ParsedFen::into_state)).collect()
+    }
+
+    /// Parses every line as a FEN using up to `num_threads` threads, discarding the parsed states,
+    /// and returns the errors found, ordered by line number.
+    pub fn validate_fen_batch(lines: &[&str], num_threads: usize) -> Vec<FenBatchError> {
+        parse_fen_batch(lines, num_threads).into_iter().filter_map(Result::err).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::INITIAL_FEN;
+
+    const LINES: [&str; 5] = [
+        INITIAL_FEN,
+        "8/8/8/8/8/8/k7/7K b - - 99 88",
+        "8/8/8/8/8/8/k7/7K x - - 0 1",
+        "r2qk2r/8/8/7p/8/8/8/R2QK2R w KQkq h6 0 6",
+        "8/8/8/8/8/8/8/8 w - - 0"
+    ];
+
+    #[test]
+    fn test_from_fen_batch() {
+        for num_threads in 1..=6 {
+            let results = State::from_fen_batch(&LINES, num_threads);
+            assert_eq!(results.len(), 5);
+            assert_eq!(results[0], Ok(State::initial()));
+            assert_eq!(results[1], State::from_fen(LINES[1]).map_err(|error| FenBatchError { line_number: 2, error }));
+            assert_eq!(results[3], State::from_fen(LINES[3]).map_err(|error| FenBatchError { line_number: 4, error }));
+
+            let errors: Vec<FenBatchError> = results.into_iter().filter_map(Result::err).collect();
+            assert_eq!(errors, vec![
+                FenBatchError { line_number: 3, error: FenParseError::InvalidSideToMove("x".to_string()) },
+                FenBatchError { line_number: 5, error: FenParseError::InvalidFieldCount(5) }
+            ]);
+            assert_eq!(errors[0].to_string(), "Line 3: Invalid side to move: x");
+        }
+    }
+
+    #[test]
+    fn test_validate_fen_batch() {
+        for num_threads in 1..=6 {
+            let errors = State::validate_fen_batch(&LINES, num_threads);
+            assert_eq!(errors.iter().map(|error| error.line_number).collect::<Vec<_>>(), vec![3, 5]);
+        }
+        assert!(State::validate_fen_batch(&[], 4).is_empty());
+    }
+}
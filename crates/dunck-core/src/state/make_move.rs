@@ -3,11 +3,11 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 use crate::utils::masks::{STARTING_KING_ROOK_GAP_SHORT, STARTING_KING_SIDE_ROOK, STARTING_QUEEN_SIDE_ROOK};
-use crate::utils::{Bitboard, Color, ColoredPiece, PieceType, Square};
+use crate::utils::{record_perf_counter, Bitboard, Color, ColoredPiece, PerfCounter, PieceType, Square};
 use crate::r#move::{Move, MoveFlag};
 use crate::state::context::Context;
 use crate::state::termination::Termination;
-use crate::state::zobrist::get_piece_zobrist_hash;
+use crate::state::zobrist::get_state_zobrist_hash;
 use crate::state::State;
 
 impl State {
@@ -83,9 +83,11 @@ impl State {
     /// All make_move calls with valid (not malformed) moves
     /// should be fully able to be undone by unmake_move.
     pub fn make_move(&mut self, mv: Move) {
+        record_perf_counter(PerfCounter::MakeMove);
         let (dst_square, src_square, promotion, flag) = mv.unpack();
 
         let mut new_context = Context::new_from(Rc::clone(&self.context), 0);
+        new_context.last_move = Some(mv);
 
         self.board.move_color(self.side_to_move, dst_square, src_square);
 
@@ -96,22 +98,26 @@ impl State {
             MoveFlag::Castling => self.process_castling(dst_square, src_square, &mut new_context)
         }
 
-        new_context.zobrist_hash = self.board.zobrist_hash;
-        
         // update data members
         self.halfmove += 1;
         self.side_to_move = self.side_to_move.flip();
+
+        // the board's hash was updated piece by piece, the rest only depends on a few fields
+        new_context.zobrist_hash = self.board.zobrist_hash ^
+            get_state_zobrist_hash(&self.board, self.side_to_move, new_context.castling_rights, new_context.double_pawn_push);
+        new_context.repetition_count = new_context.calc_repetition_count();
         self.context = Rc::new(RefCell::new(new_context));
 
+        let halfmove_clock = self.context.borrow().halfmove_clock;
         if self.board.are_both_sides_insufficient_material(true) {
             self.termination = Some(Termination::InsufficientMaterial);
         }
-        else if self.context.borrow().halfmove_clock == 100 { // fifty move rule
-            self.termination = Some(Termination::FiftyMoveRule);
+        // seventy-five move rule, unless the last move was checkmate
+        else if halfmove_clock == 150 && !(self.is_in_check() && self.calc_legal_moves().is_empty()) {
+            self.termination = Some(Termination::SeventyFiveMoveRule);
         }
-        else if self.context.borrow().has_threefold_repetition_occurred() {
-            // check for repetition
-            self.termination = Some(Termination::ThreefoldRepetition);
+        else if self.is_fivefold_repetition() {
+            self.termination = Some(Termination::FivefoldRepetition);
         }
     }
 }
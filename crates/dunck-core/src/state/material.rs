@@ -0,0 +1,143 @@
+//! Material signatures, which describe a position by the pieces left on the board, e.g. `KRPvKR`.
+
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::state::State;
+use crate::utils::{Color, PieceType};
+
+/// The number of non-king pieces of each type and color, indexed by `Color` and then by piece type, from pawn to queen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct MaterialSignature {
+    pub counts: [[u8; 5]; 2],
+}
+
+impl MaterialSignature {
+    pub fn from_state(state: &State) -> MaterialSignature {
+        let mut counts = [[0; 5]; 2];
+        for color in [Color::White, Color::Black] {
+            let color_mask = state.board.color_masks[color as usize];
+            for piece_type in PieceType::iter_non_king_pieces() {
+                let mask = color_mask & state.board.piece_type_masks[*piece_type as usize];
+                counts[color as usize][*piece_type as usize - 1] = mask.count_ones() as u8;
+            }
+        }
+        MaterialSignature { counts }
+    }
+
+    pub fn get_count(&self, color: Color, piece_type: PieceType) -> u8 {
+        self.counts[color as usize][piece_type as usize - 1]
+    }
+
+    /// Returns the number of pieces on the board, including both kings.
+    pub fn get_num_pieces(&self) -> u32 {
+        2 + self.counts.iter().flatten().map(|count| *count as u32).sum::<u32>()
+    }
+
+    /// Returns the signature with the colors swapped.
+    pub fn flip(&self) -> MaterialSignature {
+        MaterialSignature { counts: [self.counts[1], self.counts[0]] }
+    }
+
+    /// Returns whether `other` has the same material, possibly with the colors swapped.
+    pub fn matches_either_color(&self, other: &MaterialSignature) -> bool {
+        self == other || self.flip() == *other
+    }
+
+    /// Returns whether a position with this material could lead to one with `other`'s, i.e. whether every piece
+    /// of `other` either is already there or can come from promoting a pawn that `other` no longer has.
+    pub fn can_reach(&self, other: &MaterialSignature) -> bool {
+        self.counts.iter().zip(other.counts.iter()).all(|(from, to)| {
+            let num_promotions: u32 = (1..5).map(|i| to[i].saturating_sub(from[i]) as u32).sum();
+            to[0] <= from[0] && num_promotions <= (from[0] - to[0]) as u32
+        })
+    }
+}
+
+impl Display for MaterialSignature {
+    /// Writes white's pieces and then black's, from the king down to pawns, separated by a `v`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, counts) in self.counts.iter().enumerate() {
+            if i == 1 {
+                write!(f, "v")?;
+            }
+            write!(f, "K")?;
+            for piece_type in [PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight, PieceType::Pawn] {
+                for _ in 0..counts[piece_type as usize - 1] {
+                    write!(f, "{}", piece_type.to_char())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MaterialSignature {
+    type Err = String;
+
+    /// Parses a signature like `KRPvKR`, with the pieces of each side in any order.
+    fn from_str(s: &str) -> Result<MaterialSignature, String> {
+        let sides: Vec<&str> = s.trim().split(['v', 'V']).collect();
+        if sides.len() != 2 {
+            return Err(format!("Invalid material signature: {}", s));
+        }
+
+        let mut counts = [[0; 5]; 2];
+        for (side, counts) in sides.iter().zip(counts.iter_mut()) {
+            let mut num_kings = 0;
+            for c in side.chars() {
+                match c.to_ascii_uppercase() {
+                    'K' => num_kings += 1,
+                    'P' => counts[0] += 1,
+                    'N' => counts[1] += 1,
+                    'B' => counts[2] += 1,
+                    'R' => counts[3] += 1,
+                    'Q' => counts[4] += 1,
+                    _ => return Err(format!("Invalid piece {} in material signature: {}", c, s))
+                }
+            }
+            if num_kings != 1 {
+                return Err(format!("Each side needs exactly one king in material signature: {}", s));
+            }
+        }
+        Ok(MaterialSignature { counts })
+    }
+}
+
+impl State {
+    pub fn get_material_signature(&self) -> MaterialSignature {
+        MaterialSignature::from_state(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_material_signature() {
+        let state = State::from_fen("8/5pk1/6p1/8/3R4/6P1/5PK1/8 w - - 0 40").unwrap();
+        let signature = state.get_material_signature();
+        assert_eq!(signature.to_string(), "KRPPvKPP");
+        assert_eq!(signature.get_count(Color::White, PieceType::Rook), 1);
+        assert_eq!(signature.get_count(Color::Black, PieceType::Rook), 0);
+        assert_eq!(signature.get_num_pieces(), 7);
+        assert_eq!(signature.flip().to_string(), "KPPvKRPP");
+
+        assert_eq!("KPPRvKPP".parse(), Ok(signature));
+        assert!("KPPvKRPP".parse::<MaterialSignature>().unwrap().matches_either_color(&signature));
+        assert!(!"KRPvKPP".parse::<MaterialSignature>().unwrap().matches_either_color(&signature));
+        assert_eq!(State::initial().get_material_signature().to_string(), "KQRRBBNNPPPPPPPPvKQRRBBNNPPPPPPPP");
+
+        let initial = State::initial().get_material_signature();
+        assert!(initial.can_reach(&signature));
+        assert!(!signature.can_reach(&initial));
+        assert!(signature.can_reach(&signature));
+        // a pawn promoted to a queen, and another captured
+        assert!(signature.can_reach(&"KRQvKPP".parse().unwrap()));
+        assert!(!signature.can_reach(&"KRQQPvKPP".parse().unwrap()));
+
+        assert!("KRP".parse::<MaterialSignature>().is_err());
+        assert!("KRvR".parse::<MaterialSignature>().is_err());
+        assert!("KXvK".parse::<MaterialSignature>().is_err());
+    }
+}
@@ -0,0 +1,113 @@
+//! Per-piece mobility and trapped piece detection for the state struct
+
+use crate::attacks::multi_pawn_moves;
+use crate::r#move::{Move, MoveFlag};
+use crate::state::State;
+use crate::utils::masks::{RANK_1, RANK_2, RANK_7, RANK_8};
+use crate::utils::{get_squares_from_mask_iter, Bitboard, Color, PieceType, Square};
+
+impl State {
+    /// Returns a mask of the squares the piece on `square` could move to, ignoring pins and checks.
+    /// Works for pieces of either color, regardless of the side to move.
+    fn calc_piece_destinations(&self, square: Square) -> Bitboard {
+        let board = &self.board;
+        let color = board.get_color_at(square);
+        let own_mask = board.color_masks[color as usize];
+        let enemy_mask = board.color_masks[color.flip() as usize];
+        let occupied_mask = board.piece_type_masks[PieceType::AllPieceTypes as usize];
+
+        match board.get_piece_type_at(square) {
+            PieceType::NoPieceType => 0,
+            PieceType::Pawn => {
+                let single_pushes = multi_pawn_moves(square.get_mask(), color) & !occupied_mask;
+                let start_rank = match color {
+                    Color::White => RANK_2,
+                    Color::Black => RANK_7
+                };
+                let double_pushes = if square.get_mask() & start_rank != 0 {
+                    multi_pawn_moves(single_pushes, color) & !occupied_mask
+                } else {
+                    0
+                };
+                single_pushes | double_pushes | (board.calc_attacks_from(square) & enemy_mask)
+            },
+            _ => board.calc_attacks_from(square) & !own_mask
+        }
+    }
+
+    /// Returns true if moving the piece on `src_square` to `dst_square` does not lose material.
+    fn is_destination_safe(&self, dst_square: Square, src_square: Square) -> bool {
+        let board = &self.board;
+        let color = board.get_color_at(src_square);
+        match board.get_piece_type_at(src_square) {
+            PieceType::King => {
+                let mut board_without_king = board.clone();
+                board_without_king.remove_colored_piece_at(board.get_colored_piece_at(src_square), src_square);
+                !board_without_king.is_mask_in_check(dst_square.get_mask(), color.flip())
+            },
+            piece_type => {
+                let mv = if piece_type == PieceType::Pawn && dst_square.get_mask() & (RANK_1 | RANK_8) != 0 {
+                    Move::new(dst_square, src_square, PieceType::Queen, MoveFlag::Promotion)
+                } else {
+                    Move::new_non_promotion(dst_square, src_square, MoveFlag::NormalMove)
+                };
+                self.calc_see(mv) >= 0
+            }
+        }
+    }
+
+    /// Returns the number of squares the piece on `square` can safely move to,
+    /// where a move is safe if its static exchange evaluation is not negative.
+    /// Pins and checks are ignored. Returns 0 if `square` is empty.
+    pub fn mobility(&self, square: Square) -> u32 {
+        get_squares_from_mask_iter(self.calc_piece_destinations(square))
+            .filter(|dst_square| self.is_destination_safe(*dst_square, square))
+            .count() as u32
+    }
+
+    /// Returns true if the piece on `square` is trapped: it is neither a pawn nor a king,
+    /// it has no safe moves, and it either has unsafe moves or is attacked.
+    /// A piece that is merely boxed in by its own pieces is not considered trapped.
+    pub fn is_trapped(&self, square: Square) -> bool {
+        let board = &self.board;
+        if matches!(board.get_piece_type_at(square), PieceType::NoPieceType | PieceType::Pawn | PieceType::King) {
+            return false;
+        }
+        let is_attacked = board.is_mask_in_check(square.get_mask(), board.get_color_at(square).flip());
+        let has_destinations = self.calc_piece_destinations(square) != 0;
+        (has_destinations || is_attacked) && self.mobility(square) == 0
+    }
+
+    /// Returns a mask of all trapped pieces of `color`.
+    pub fn calc_trapped_pieces(&self, color: Color) -> Bitboard {
+        get_squares_from_mask_iter(self.board.color_masks[color as usize])
+            .filter(|square| self.is_trapped(*square))
+            .fold(0, |mask, square| mask | square.get_mask())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mobility() {
+        let state = State::initial();
+        assert_eq!(state.mobility(Square::G1), 2);
+        assert_eq!(state.mobility(Square::C1), 0);
+        assert_eq!(state.mobility(Square::E2), 2);
+        assert_eq!(state.mobility(Square::B8), 2);
+        assert_eq!(state.mobility(Square::E4), 0);
+        assert_eq!(state.calc_trapped_pieces(Color::White), 0);
+        assert_eq!(state.calc_trapped_pieces(Color::Black), 0);
+    }
+
+    #[test]
+    fn test_trapped_piece() {
+        let state = State::from_fen("8/B1k5/1p6/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(state.mobility(Square::A7), 0);
+        assert!(state.is_trapped(Square::A7));
+        assert_eq!(state.calc_trapped_pieces(Color::White), Square::A7.get_mask());
+        assert_eq!(state.calc_trapped_pieces(Color::Black), 0);
+    }
+}
@@ -0,0 +1,47 @@
+//! This module contains game state related code.
+
+mod board;
+mod context;
+mod termination;
+mod draw_claim;
+mod make_move;
+mod movegen;
+mod movegen_cache;
+mod movegen_stats;
+mod unmake_move;
+mod zobrist;
+mod polyglot;
+mod fen;
+mod fen_batch;
+mod state;
+mod motifs;
+mod see;
+mod mobility;
+mod material;
+mod diagram;
+mod validation;
+mod move_history;
+#[cfg(feature = "shakmaty-interop")]
+mod shakmaty_interop;
+
+pub use state::*;
+pub use board::*;
+pub use context::*;
+pub use termination::*;
+pub use draw_claim::*;
+pub use make_move::*;
+pub use movegen::*;
+pub use movegen_cache::*;
+pub use movegen_stats::*;
+pub use unmake_move::*;
+pub use zobrist::*;
+pub use fen::*;
+pub use fen_batch::*;
+pub use motifs::*;
+pub use see::*;
+pub use material::*;
+pub use diagram::*;
+pub use validation::*;
+pub use move_history::*;
+#[cfg(feature = "shakmaty-interop")]
+pub use shakmaty_interop::*;
@@ -0,0 +1,157 @@
+//! Detection of basic tactical motifs created by a move.
+
+use std::collections::HashSet;
+use crate::r#move::Move;
+use crate::state::{get_see_value, Board, State};
+use crate::utils::{get_squares_from_mask_iter, Bitboard, Color, PieceType, Square};
+
+/// A basic tactical motif, used for puzzle tagging and annotation.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Hash)]
+pub enum Motif {
+    /// The moved piece attacks two or more valuable targets at once.
+    Fork,
+    /// The moved piece pins an enemy piece to its king.
+    AbsolutePin,
+    /// The moved piece pins an enemy piece to a more valuable piece.
+    RelativePin,
+    /// The moved piece attacks a valuable piece with a less valuable piece behind it.
+    Skewer,
+    /// Moving the piece uncovers an attack by another friendly sliding piece.
+    DiscoveredAttack
+}
+
+impl State {
+    /// Returns the set of tactical motifs created by playing `mv` in this state.
+    /// Assumes that `mv` is legal.
+    pub fn calc_motifs(&self, mv: Move) -> HashSet<Motif> {
+        let mut final_state = self.clone();
+        final_state.make_move(mv);
+
+        let mut motifs = HashSet::new();
+        let moved_square = mv.get_destination();
+
+        if is_fork(&final_state.board, moved_square) {
+            motifs.insert(Motif::Fork);
+        }
+        add_xray_motifs(&final_state.board, moved_square, &mut motifs);
+        if is_discovered_attack(&self.board, &final_state.board, moved_square, self.side_to_move) {
+            motifs.insert(Motif::DiscoveredAttack);
+        }
+
+        motifs
+    }
+}
+
+/// Returns true if the piece on `square` attacks at least two enemy pieces that are
+/// either the king, worth more than the attacker, or undefended.
+fn is_fork(board: &Board, square: Square) -> bool {
+    let attacker_color = board.get_color_at(square);
+    let defender_color = attacker_color.flip();
+    let attacker_value = get_see_value(board.get_piece_type_at(square));
+
+    let targets = board.calc_attacks_from(square) & board.color_masks[defender_color as usize];
+    let valuable_targets = get_squares_from_mask_iter(targets).filter(|target| {
+        let target_piece_type = board.get_piece_type_at(*target);
+        target_piece_type == PieceType::King ||
+            get_see_value(target_piece_type) > attacker_value ||
+            !board.is_mask_in_check(target.get_mask(), defender_color)
+    });
+
+    valuable_targets.count() >= 2
+}
+
+/// Adds pins and skewers made by the sliding piece on `square`, found by looking through
+/// each attacked enemy piece to the next piece on the same line.
+fn add_xray_motifs(board: &Board, square: Square, motifs: &mut HashSet<Motif>) {
+    let piece_type = board.get_piece_type_at(square);
+    if !matches!(piece_type, PieceType::Bishop | PieceType::Rook | PieceType::Queen) {
+        return;
+    }
+
+    let defender_mask = board.color_masks[board.get_color_at(square).flip() as usize];
+    let occupied_mask = board.piece_type_masks[PieceType::AllPieceTypes as usize];
+    let attacks = board.calc_attacks_from_with_occupancy(square, occupied_mask);
+
+    for front_square in get_squares_from_mask_iter(attacks & defender_mask) {
+        let xray_attacks = board.calc_attacks_from_with_occupancy(square, occupied_mask & !front_square.get_mask());
+        let behind_mask = xray_attacks & !attacks & occupied_mask & defender_mask;
+        let behind_square = match get_squares_from_mask_iter(behind_mask).next() {
+            Some(behind_square) => behind_square,
+            None => continue
+        };
+
+        let front_piece_type = board.get_piece_type_at(front_square);
+        let behind_piece_type = board.get_piece_type_at(behind_square);
+        if behind_piece_type == PieceType::King {
+            motifs.insert(Motif::AbsolutePin);
+        } else if front_piece_type == PieceType::King || get_see_value(front_piece_type) > get_see_value(behind_piece_type) {
+            motifs.insert(Motif::Skewer);
+        } else if get_see_value(front_piece_type) < get_see_value(behind_piece_type) {
+            motifs.insert(Motif::RelativePin);
+        }
+    }
+}
+
+/// Returns true if a friendly sliding piece, other than the one on `moved_square`,
+/// attacks a non-pawn enemy piece on `final_board` that it did not attack on `initial_board`.
+fn is_discovered_attack(initial_board: &Board, final_board: &Board, moved_square: Square, mover_color: Color) -> bool {
+    let sliders_mask = |board: &Board| -> Bitboard {
+        (board.piece_type_masks[PieceType::Bishop as usize] |
+            board.piece_type_masks[PieceType::Rook as usize] |
+            board.piece_type_masks[PieceType::Queen as usize]) & board.color_masks[mover_color as usize]
+    };
+    let unmoved_sliders_mask = sliders_mask(initial_board) & sliders_mask(final_board) & !moved_square.get_mask();
+
+    let defender_mask = final_board.color_masks[mover_color.flip() as usize];
+    let targets_mask = defender_mask & !final_board.piece_type_masks[PieceType::Pawn as usize];
+
+    get_squares_from_mask_iter(unmoved_sliders_mask).any(|slider_square| {
+        let initial_attacks = initial_board.calc_attacks_from(slider_square);
+        let final_attacks = final_board.calc_attacks_from(slider_square);
+        final_attacks & !initial_attacks & targets_mask != 0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r#move::MoveFlag;
+
+    fn calc_motifs_for(fen: &str, dst: Square, src: Square) -> HashSet<Motif> {
+        let state = State::from_fen(fen).unwrap();
+        state.calc_motifs(Move::new_non_promotion(dst, src, MoveFlag::NormalMove))
+    }
+
+    #[test]
+    fn test_fork() {
+        let motifs = calc_motifs_for("r3k3/8/8/3N4/8/8/8/4K3 w - - 0 1", Square::C7, Square::D5);
+        assert_eq!(motifs, HashSet::from([Motif::Fork]));
+    }
+
+    #[test]
+    fn test_pins() {
+        let motifs = calc_motifs_for("4k3/8/2n5/8/8/8/8/3BK3 w - - 0 1", Square::A4, Square::D1);
+        assert_eq!(motifs, HashSet::from([Motif::AbsolutePin]));
+
+        let motifs = calc_motifs_for("4q1k1/8/2n5/8/8/8/8/3BK3 w - - 0 1", Square::A4, Square::D1);
+        assert_eq!(motifs, HashSet::from([Motif::RelativePin]));
+    }
+
+    #[test]
+    fn test_skewer() {
+        let motifs = calc_motifs_for("4q3/8/2k5/8/8/3B4/8/7K w - - 0 1", Square::B5, Square::D3);
+        assert_eq!(motifs, HashSet::from([Motif::Skewer]));
+    }
+
+    #[test]
+    fn test_discovered_attack() {
+        let motifs = calc_motifs_for("4q1k1/8/8/8/4N3/8/8/4R1K1 w - - 0 1", Square::C5, Square::E4);
+        assert_eq!(motifs, HashSet::from([Motif::DiscoveredAttack]));
+    }
+
+    #[test]
+    fn test_quiet_move() {
+        let motifs = calc_motifs_for(crate::state::INITIAL_FEN, Square::E4, Square::E2);
+        assert!(motifs.is_empty());
+    }
+}
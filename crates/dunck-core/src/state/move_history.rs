@@ -0,0 +1,217 @@
+//! Navigation through the moves played from a position, with undo, redo and branching,
+//! for interactive use such as the play REPL and analysis sessions.
+
+use crate::r#move::Move;
+use crate::state::State;
+
+struct HistoryNode {
+    mv: Option<Move>,
+    state: State,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    /// The child that redo goes to, i.e. the one most recently visited.
+    redo_child: Option<usize>,
+}
+
+/// A tree of the moves played from an initial position, with a current position in it.
+///
+/// Undoing a move keeps it, so it can be redone. Playing a different move than the one that would be redone
+/// forks a new branch at the current position instead of discarding the old line, and playing a move that
+/// is already in the tree follows it. Unlike `PgnStateTree`, nodes are kept in a flat list without SAN,
+/// comments or shared ownership, so the history is cheap to update after every move.
+pub struct MoveHistory {
+    nodes: Vec<HistoryNode>,
+    current: usize,
+}
+
+impl MoveHistory {
+    pub fn new(initial_state: State) -> MoveHistory {
+        MoveHistory {
+            nodes: vec![HistoryNode { mv: None, state: initial_state, parent: None, children: Vec::new(), redo_child: None }],
+            current: 0,
+        }
+    }
+
+    pub fn get_initial_state(&self) -> &State {
+        &self.nodes[0].state
+    }
+
+    /// Returns the current position.
+    pub fn get_state(&self) -> &State {
+        &self.nodes[self.current].state
+    }
+
+    /// Returns the number of moves played from the initial position to the current one.
+    pub fn get_ply(&self) -> usize {
+        let mut ply = 0;
+        let mut node = self.current;
+        while let Some(parent) = self.nodes[node].parent {
+            ply += 1;
+            node = parent;
+        }
+        ply
+    }
+
+    /// Returns the moves played from the initial position to the current one.
+    pub fn get_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::new();
+        let mut node = self.current;
+        while let Some(mv) = self.nodes[node].mv {
+            moves.push(mv);
+            node = self.nodes[node].parent.unwrap();
+        }
+        moves.reverse();
+        moves
+    }
+
+    /// Returns the moves of the current line, i.e. the moves played to the current position
+    /// followed by the moves that would be redone.
+    pub fn get_line(&self) -> Vec<Move> {
+        let mut line = self.get_moves();
+        let mut node = self.current;
+        while let Some(child) = self.nodes[node].redo_child {
+            line.push(self.nodes[child].mv.unwrap());
+            node = child;
+        }
+        line
+    }
+
+    /// Returns the moves played so far from the current position, the one that would be redone first.
+    pub fn get_next_moves(&self) -> Vec<Move> {
+        let node = &self.nodes[self.current];
+        let mut next_moves: Vec<Move> = node.redo_child.into_iter().map(|child| self.nodes[child].mv.unwrap()).collect();
+        next_moves.extend(node.children.iter()
+            .filter(|child| Some(**child) != node.redo_child)
+            .map(|child| self.nodes[*child].mv.unwrap()));
+        next_moves
+    }
+
+    /// Returns whether more than one move has been played from the current position.
+    pub fn is_branch_point(&self) -> bool {
+        self.nodes[self.current].children.len() > 1
+    }
+
+    pub fn can_undo(&self) -> bool {
+        self.nodes[self.current].parent.is_some()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        self.nodes[self.current].redo_child.is_some()
+    }
+
+    /// Plays `mv` in the current position. If it was played there before, its branch is followed,
+    /// otherwise a new branch is started, keeping the moves that could have been redone as another branch.
+    pub fn make_move(&mut self, mv: Move) -> Result<(), String> {
+        let existing_child = self.nodes[self.current].children.iter()
+            .find(|child| self.nodes[**child].mv == Some(mv))
+            .copied();
+        let child = match existing_child {
+            Some(child) => child,
+            None => {
+                let state = self.get_state();
+                if !state.calc_legal_moves().contains(&mv) {
+                    return Err(format!("Illegal move: {}", mv.to_uci()));
+                }
+                let mut state_after_move = state.clone();
+                state_after_move.make_move(mv);
+                self.nodes.push(HistoryNode { mv: Some(mv), state: state_after_move, parent: Some(self.current), children: Vec::new(), redo_child: None });
+                let child = self.nodes.len() - 1;
+                self.nodes[self.current].children.push(child);
+                child
+            }
+        };
+        self.nodes[self.current].redo_child = Some(child);
+        self.current = child;
+        Ok(())
+    }
+
+    /// Takes back the last move, returning it, or None in the initial position.
+    pub fn undo(&mut self) -> Option<Move> {
+        let parent = self.nodes[self.current].parent?;
+        let mv = self.nodes[self.current].mv;
+        self.nodes[parent].redo_child = Some(self.current);
+        self.current = parent;
+        mv
+    }
+
+    /// Plays the last move taken back from the current position again, returning it, or None if there is none.
+    pub fn redo(&mut self) -> Option<Move> {
+        let child = self.nodes[self.current].redo_child?;
+        self.current = child;
+        self.nodes[child].mv
+    }
+
+    /// Goes to the position after `ply` moves of the current line, undoing or redoing moves as needed.
+    pub fn jump_to_ply(&mut self, ply: usize) -> Result<(), String> {
+        let line_length = self.get_line().len();
+        if ply > line_length {
+            return Err(format!("Ply {} is beyond the end of the line, at ply {}", ply, line_length));
+        }
+        let current_ply = self.get_ply();
+        for _ in ply..current_ply {
+            self.undo();
+        }
+        for _ in current_ply..ply {
+            self.redo();
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(history: &mut MoveHistory, ucis: &[&str]) {
+        for uci in ucis {
+            let mv = Move::from_uci(history.get_state(), uci).unwrap();
+            history.make_move(mv).unwrap();
+        }
+    }
+
+    fn to_ucis(moves: &[Move]) -> Vec<String> {
+        moves.iter().map(|mv| mv.to_uci()).collect()
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let mut history = MoveHistory::new(State::initial());
+        assert!(!history.can_undo() && history.undo().is_none());
+        play(&mut history, &["e2e4", "e7e5", "g1f3"]);
+        assert_eq!(history.get_ply(), 3);
+
+        assert_eq!(history.undo().map(|mv| mv.to_uci()).as_deref(), Some("g1f3"));
+        assert_eq!(history.undo().map(|mv| mv.to_uci()).as_deref(), Some("e7e5"));
+        assert_eq!(history.get_state().to_fen(), "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+        assert_eq!(to_ucis(&history.get_moves()), ["e2e4"]);
+        assert_eq!(to_ucis(&history.get_line()), ["e2e4", "e7e5", "g1f3"]);
+
+        assert_eq!(history.redo().map(|mv| mv.to_uci()).as_deref(), Some("e7e5"));
+        assert_eq!(history.redo().map(|mv| mv.to_uci()).as_deref(), Some("g1f3"));
+        assert!(!history.can_redo() && history.redo().is_none());
+        assert!(history.make_move(Move::from_uci(&State::initial(), "d2d4").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_fork_on_divergence() {
+        let mut history = MoveHistory::new(State::initial());
+        play(&mut history, &["e2e4", "e7e5", "g1f3"]);
+        history.jump_to_ply(1).unwrap();
+        play(&mut history, &["c7c5"]);
+        assert!(!history.can_redo());
+        history.undo();
+        assert!(history.is_branch_point());
+        // the new branch is redone first, and the old one is kept
+        assert_eq!(to_ucis(&history.get_next_moves()), ["c7c5", "e7e5"]);
+        assert_eq!(to_ucis(&history.get_line()), ["e2e4", "c7c5"]);
+
+        // playing a move already in the tree follows its branch, with its continuation
+        play(&mut history, &["e7e5"]);
+        assert_eq!(to_ucis(&history.get_line()), ["e2e4", "e7e5", "g1f3"]);
+        history.jump_to_ply(3).unwrap();
+        assert_eq!(history.get_ply(), 3);
+        assert!(history.jump_to_ply(4).is_err());
+        history.jump_to_ply(0).unwrap();
+        assert_eq!(history.get_state().to_fen(), history.get_initial_state().to_fen());
+    }
+}
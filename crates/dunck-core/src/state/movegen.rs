@@ -0,0 +1,546 @@
+//! Move generation functions for the state struct
+
+use crate::attacks::{multi_pawn_attacks, multi_pawn_moves, single_bishop_attacks, single_king_attacks, single_knight_attacks, single_rook_attacks};
+use crate::utils::{get_squares_from_mask_iter, get_set_bit_mask_iter, SetBitMaskIterator};
+use crate::utils::masks::{FILE_A, RANK_1, RANK_3, RANK_4, RANK_5, RANK_6, RANK_8};
+use crate::utils::{record_perf_counter, Bitboard, Color, PerfCounter, PieceType, Square};
+use crate::r#move::{Move, MoveFlag};
+use crate::state::{State, Termination};
+
+/// Options controlling which moves are generated and in what order.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct MoveGenOptions {
+    /// If false, only queen promotions are generated, except when promoting to a queen would stalemate
+    /// (only applies to legal move generation; pseudolegal generation skips underpromotions unconditionally).
+    pub include_underpromotions: bool,
+    /// If true, promotions are generated queen first (queen, rook, bishop, knight) instead of knight first.
+    pub queen_first_promotions: bool
+}
+
+impl MoveGenOptions {
+    /// Generates every move, in the default order.
+    pub const ALL: MoveGenOptions = MoveGenOptions {
+        include_underpromotions: true,
+        queen_first_promotions: false
+    };
+
+    /// Reduces branching for search: queen-only promotions (unless they stalemate), generated first.
+    pub const SEARCH: MoveGenOptions = MoveGenOptions {
+        include_underpromotions: false,
+        queen_first_promotions: true
+    };
+}
+
+impl Default for MoveGenOptions {
+    fn default() -> Self {
+        MoveGenOptions::ALL
+    }
+}
+
+fn add_pawn_promotion_moves(moves: &mut Vec<Move>, src: Square, dst: Square, options: &MoveGenOptions) {
+    if !options.include_underpromotions {
+        moves.push(Move::new(dst, src, PieceType::Queen, MoveFlag::Promotion));
+    }
+    else if options.queen_first_promotions {
+        for promotion_piece in PieceType::iter_promotion_pieces().rev() {
+            moves.push(Move::new(dst, src, *promotion_piece, MoveFlag::Promotion));
+        }
+    }
+    else {
+        for promotion_piece in PieceType::iter_promotion_pieces() {
+            moves.push(Move::new(dst, src, *promotion_piece, MoveFlag::Promotion));
+        }
+    }
+}
+
+/// Restrictions on the destinations of the side to move's pieces, derived from checks and pins.
+/// `MoveFilter::NONE` allows every destination, for pseudolegal generation.
+struct MoveFilter {
+    /// Squares a non-king piece may move to: anywhere when not in check,
+    /// the checker or a square between it and the king in single check, and nowhere in double check.
+    target_mask: Bitboard,
+    /// Squares the king may step to without being attacked.
+    king_target_mask: Bitboard,
+    /// Pieces of the side to move that are pinned to their king.
+    pinned_mask: Bitboard,
+    /// For each pinned piece, the squares between the king and the pinner, including the pinner.
+    pin_rays: [Bitboard; 64],
+    /// The king of the side to move, if en passant captures must be checked for uncovering an attack on it.
+    /// Both pawns leave the same rank, so the usual pin detection misses this.
+    en_passant_king_square: Option<Square>,
+}
+
+impl MoveFilter {
+    const NONE: MoveFilter = MoveFilter {
+        target_mask: !0,
+        king_target_mask: !0,
+        pinned_mask: 0,
+        pin_rays: [!0; 64],
+        en_passant_king_square: None,
+    };
+
+    /// Returns the squares a non-king piece on `src_square` may move to.
+    fn get_allowed_destinations(&self, src_square: Square) -> Bitboard {
+        if self.pinned_mask & src_square.get_mask() != 0 {
+            self.target_mask & self.pin_rays[src_square as usize]
+        }
+        else {
+            self.target_mask
+        }
+    }
+}
+
+/// Returns the squares strictly between `first` and `last`,
+/// or an empty mask if they do not share a rank, file or diagonal.
+fn calc_squares_between(first: Square, last: Square) -> Bitboard {
+    let first_mask = first.get_mask();
+    let last_mask = last.get_mask();
+    if single_rook_attacks(first, 0) & last_mask != 0 {
+        single_rook_attacks(first, last_mask) & single_rook_attacks(last, first_mask)
+    }
+    else if single_bishop_attacks(first, 0) & last_mask != 0 {
+        single_bishop_attacks(first, last_mask) & single_bishop_attacks(last, first_mask)
+    }
+    else {
+        0
+    }
+}
+
+impl State {
+    /// Computes the checks and pins against the side to move's king, which restrict its legal moves.
+    fn calc_move_filter(&self) -> MoveFilter {
+        let same_color_bb = self.board.color_masks[self.side_to_move as usize];
+        let opposite_color = self.side_to_move.flip();
+        let opposite_color_bb = self.board.color_masks[opposite_color as usize];
+        let all_occupancy_bb = self.board.piece_type_masks[PieceType::AllPieceTypes as usize];
+        let queens_bb = self.board.piece_type_masks[PieceType::Queen as usize];
+
+        let king_bb = self.board.piece_type_masks[PieceType::King as usize] & same_color_bb;
+        let king_square = unsafe { Square::from(king_bb.leading_zeros() as u8) };
+
+        let checkers_bb = self.board.calc_attackers_to(king_square, all_occupancy_bb) & opposite_color_bb;
+        let target_mask = match checkers_bb.count_ones() {
+            0 => !0,
+            1 => {
+                let checker_square = unsafe { Square::from(checkers_bb.leading_zeros() as u8) };
+                checkers_bb | calc_squares_between(king_square, checker_square)
+            },
+            _ => 0
+        };
+
+        // the king must not block the attacks it steps away from
+        let king_target_mask = !self.board.calc_attack_mask(opposite_color, all_occupancy_bb & !king_bb);
+
+        // sliders that would attack the king if it weren't for the side to move's own pieces
+        let orthogonal_sliders_bb = (self.board.piece_type_masks[PieceType::Rook as usize] | queens_bb) & opposite_color_bb;
+        let diagonal_sliders_bb = (self.board.piece_type_masks[PieceType::Bishop as usize] | queens_bb) & opposite_color_bb;
+        let pinners_bb = (single_rook_attacks(king_square, opposite_color_bb) & orthogonal_sliders_bb) |
+            (single_bishop_attacks(king_square, opposite_color_bb) & diagonal_sliders_bb);
+
+        let mut pinned_mask = 0;
+        let mut pin_rays = [!0; 64];
+        for pinner_square in get_squares_from_mask_iter(pinners_bb) {
+            let between_bb = calc_squares_between(king_square, pinner_square);
+            let blockers_bb = between_bb & all_occupancy_bb;
+            if blockers_bb.count_ones() == 1 && blockers_bb & same_color_bb != 0 {
+                pinned_mask |= blockers_bb;
+                pin_rays[blockers_bb.leading_zeros() as usize] = between_bb | pinner_square.get_mask();
+            }
+        }
+
+        MoveFilter {
+            target_mask,
+            king_target_mask,
+            pinned_mask,
+            pin_rays,
+            en_passant_king_square: Some(king_square),
+        }
+    }
+
+    /// Returns true if capturing en passant from `src_square` to `dst_square` leaves the king on `king_square` unattacked.
+    fn is_en_passant_safe(&self, dst_square: Square, src_square: Square, king_square: Square) -> bool {
+        let opposite_color = self.side_to_move.flip();
+        let captured_bb = multi_pawn_moves(dst_square.get_mask(), opposite_color);
+        let occupancy_after_bb = (self.board.piece_type_masks[PieceType::AllPieceTypes as usize] & !src_square.get_mask() & !captured_bb) | dst_square.get_mask();
+        let attackers_bb = self.board.calc_attackers_to(king_square, occupancy_after_bb) & self.board.color_masks[opposite_color as usize];
+        attackers_bb & !captured_bb == 0
+    }
+
+    fn add_normal_pawn_captures_pseudolegal(&self, moves: &mut Vec<Move>, pawn_srcs: SetBitMaskIterator, options: &MoveGenOptions, filter: &MoveFilter) {
+        let opposite_color = self.side_to_move.flip();
+        let opposite_color_bb = self.board.color_masks[opposite_color as usize];
+
+        let promotion_rank = match self.side_to_move {
+            Color::White => RANK_8,
+            Color::Black => RANK_1
+        };
+
+        for src in pawn_srcs.clone() {
+            let move_src = unsafe { Square::from(src.leading_zeros() as u8) };
+            let captures = multi_pawn_attacks(src, self.side_to_move) & opposite_color_bb & filter.get_allowed_destinations(move_src);
+            for dst in get_set_bit_mask_iter(captures) {
+                let move_dst = unsafe { Square::from(dst.leading_zeros() as u8) };
+                if dst & promotion_rank != 0 {
+                    add_pawn_promotion_moves(moves, move_src, move_dst, options);
+                }
+                else {
+                    moves.push(Move::new_non_promotion(move_dst, move_src, MoveFlag::NormalMove));
+                }
+            }
+        }
+    }
+
+    fn add_en_passant_pseudolegal(&self, moves: &mut Vec<Move>, filter: &MoveFilter) {
+        let context = self.context.borrow();
+        let same_color_bb = self.board.color_masks[self.side_to_move as usize];
+        let pawns_bb = self.board.piece_type_masks[PieceType::Pawn as usize] & same_color_bb;
+
+        let (src_rank_bb, dst_rank_bb) = match self.side_to_move {
+            Color::White => (RANK_5, RANK_6),
+            Color::Black => (RANK_4, RANK_3),
+        };
+
+        if context.double_pawn_push != -1 { // if en passant is possible
+            for &direction in [-1, 1].iter() { // left and right
+                let double_pawn_push_file = context.double_pawn_push as i32 + direction;
+                if double_pawn_push_file >= 0 && double_pawn_push_file <= 7 { // if within bounds
+                    let double_pawn_push_file_mask = FILE_A >> double_pawn_push_file;
+                    if pawns_bb & double_pawn_push_file_mask & src_rank_bb != 0 {
+                        let move_src = unsafe { Square::from(src_rank_bb.leading_zeros() as u8 + double_pawn_push_file as u8) };
+                        let move_dst = unsafe { Square::from(dst_rank_bb.leading_zeros() as u8 + context.double_pawn_push as u8) };
+                        let is_safe = match filter.en_passant_king_square {
+                            Some(king_square) => self.is_en_passant_safe(move_dst, move_src, king_square),
+                            None => true
+                        };
+                        if is_safe {
+                            moves.push(Move::new_non_promotion(move_dst, move_src, MoveFlag::EnPassant));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    
+    fn add_pawn_push_pseudolegal(&self, moves: &mut Vec<Move>, pawn_srcs: SetBitMaskIterator, options: &MoveGenOptions, filter: &MoveFilter) {
+        let all_occupancy_bb = self.board.piece_type_masks[PieceType::AllPieceTypes as usize];
+
+        let promotion_rank = RANK_8 >> (self.side_to_move as u8 * 7 * 8); // RANK_8 for white, RANK_1 for black
+
+        // pawn pushes
+        let single_push_rank = match self.side_to_move {
+            Color::White => RANK_3,
+            Color::Black => RANK_6
+        };
+        for src_bb in pawn_srcs {
+            let src_square = unsafe { Square::from(src_bb.leading_zeros() as u8) };
+            let allowed_dsts = filter.get_allowed_destinations(src_square);
+
+            // single moves
+            let single_move_dst = multi_pawn_moves(src_bb, self.side_to_move) & !all_occupancy_bb;
+            if single_move_dst == 0 { // if no single moves
+                continue;
+            }
+
+            let single_move_dst_square = unsafe { Square::from(single_move_dst.leading_zeros() as u8) };
+
+            // double push
+            if single_move_dst & single_push_rank != 0 {
+                let double_move_dst = multi_pawn_moves(single_move_dst, self.side_to_move) & !all_occupancy_bb;
+                if double_move_dst & allowed_dsts != 0 {
+                    unsafe {
+                        let double_move_dst_square = Square::from(double_move_dst.leading_zeros() as u8);
+                        moves.push(Move::new_non_promotion(double_move_dst_square, src_square, MoveFlag::NormalMove));
+                    }
+                }
+            }
+            else if single_move_dst & promotion_rank != 0 { // promotion
+                if single_move_dst & allowed_dsts != 0 {
+                    add_pawn_promotion_moves(moves, src_square, single_move_dst_square, options);
+                }
+                continue;
+            }
+
+            // single push (non-promotion)
+            if single_move_dst & allowed_dsts != 0 {
+                moves.push(Move::new_non_promotion(single_move_dst_square, src_square, MoveFlag::NormalMove));
+            }
+        }
+    }
+    
+    fn add_all_pawn_pseudolegal(&self, moves: &mut Vec<Move>, options: &MoveGenOptions, filter: &MoveFilter) {
+        let same_color_bb = self.board.color_masks[self.side_to_move as usize];
+        let pawns_bb = self.board.piece_type_masks[PieceType::Pawn as usize] & same_color_bb;
+        let pawn_srcs = get_set_bit_mask_iter(pawns_bb);
+
+        self.add_normal_pawn_captures_pseudolegal(moves, pawn_srcs.clone(), options, filter);
+        self.add_en_passant_pseudolegal(moves, filter);
+        self.add_pawn_push_pseudolegal(moves, pawn_srcs, options, filter);
+    }
+
+    fn add_knight_pseudolegal(&self, moves: &mut Vec<Move>, filter: &MoveFilter) {
+        let same_color_bb = self.board.color_masks[self.side_to_move as usize];
+
+        let knights_bb = self.board.piece_type_masks[PieceType::Knight as usize] & same_color_bb;
+        for src_square in get_squares_from_mask_iter(knights_bb) {
+            let knight_moves = single_knight_attacks(src_square) & !same_color_bb & filter.get_allowed_destinations(src_square);
+            for dst_square in get_squares_from_mask_iter(knight_moves) {
+                moves.push(Move::new_non_promotion(dst_square, src_square, MoveFlag::NormalMove));
+            }
+        }
+    }
+
+    fn add_bishop_pseudolegal(&self, moves: &mut Vec<Move>, filter: &MoveFilter) {
+        let same_color_bb = self.board.color_masks[self.side_to_move as usize];
+        let all_occupancy_bb = self.board.piece_type_masks[PieceType::AllPieceTypes as usize];
+
+        let bishops_bb = self.board.piece_type_masks[PieceType::Bishop as usize] & same_color_bb;
+        for src_square in get_squares_from_mask_iter(bishops_bb) {
+            let bishop_moves = single_bishop_attacks(src_square, all_occupancy_bb) & !same_color_bb & filter.get_allowed_destinations(src_square);
+            for dst_square in get_squares_from_mask_iter(bishop_moves) {
+                moves.push(Move::new_non_promotion(dst_square, src_square, MoveFlag::NormalMove));
+            }
+        }
+    }
+
+    fn add_rook_pseudolegal(&self, moves: &mut Vec<Move>, filter: &MoveFilter) {
+        let same_color_bb = self.board.color_masks[self.side_to_move as usize];
+        let all_occupancy_bb = self.board.piece_type_masks[PieceType::AllPieceTypes as usize];
+
+        let rooks_bb = self.board.piece_type_masks[PieceType::Rook as usize] & same_color_bb;
+        for src_square in get_squares_from_mask_iter(rooks_bb) {
+            let rook_moves = single_rook_attacks(src_square, all_occupancy_bb) & !same_color_bb & filter.get_allowed_destinations(src_square);
+            for dst_square in get_squares_from_mask_iter(rook_moves) {
+                moves.push(Move::new_non_promotion(dst_square, src_square, MoveFlag::NormalMove));
+            }
+        }
+    }
+
+    fn add_queen_pseudolegal(&self, moves: &mut Vec<Move>, filter: &MoveFilter) {
+        let same_color_bb = self.board.color_masks[self.side_to_move as usize];
+        let all_occupancy_bb = self.board.piece_type_masks[PieceType::AllPieceTypes as usize];
+
+        let queens_bb = self.board.piece_type_masks[PieceType::Queen as usize] & same_color_bb;
+        for src_square in get_squares_from_mask_iter(queens_bb) {
+            let queen_moves = (single_rook_attacks(src_square, all_occupancy_bb) | single_bishop_attacks(src_square, all_occupancy_bb)) & !same_color_bb & filter.get_allowed_destinations(src_square);
+            for dst_square in get_squares_from_mask_iter(queen_moves) {
+                moves.push(Move::new_non_promotion(dst_square, src_square, MoveFlag::NormalMove));
+            }
+        }
+    }
+
+    fn add_king_pseudolegal(&self, moves: &mut Vec<Move>, filter: &MoveFilter) {
+        let same_color_bb = self.board.color_masks[self.side_to_move as usize];
+        self.board.piece_type_masks[PieceType::AllPieceTypes as usize];
+
+        // king moves
+        let king_src_bb = self.board.piece_type_masks[PieceType::King as usize] & same_color_bb;
+        let king_src_square = unsafe { Square::from(king_src_bb.leading_zeros() as u8) };
+        let king_moves = single_king_attacks(king_src_square) & !same_color_bb & filter.king_target_mask;
+        for dst_square in get_squares_from_mask_iter(king_moves) {
+            moves.push(Move::new_non_promotion(dst_square, king_src_square, MoveFlag::NormalMove));
+        }
+    }
+    
+    fn add_castling_pseudolegal(&self, moves: &mut Vec<Move>) {
+        let king_src_square = match self.side_to_move {
+            Color::White => Square::E1,
+            Color::Black => Square::E8
+        };
+
+        if self.can_legally_castle_short(self.side_to_move) {
+            let king_dst_square = unsafe { Square::from(king_src_square as u8 + 2) };
+            moves.push(Move::new_non_promotion(king_dst_square, king_src_square, MoveFlag::Castling));
+        }
+        if self.can_legally_castle_long(self.side_to_move) {
+            let king_dst_square = unsafe { Square::from(king_src_square as u8 - 2) };
+            moves.push(Move::new_non_promotion(king_dst_square, king_src_square, MoveFlag::Castling));
+        }
+    }
+
+    /// Returns a vector of pseudolegal moves.
+    pub fn calc_pseudolegal_moves(&self) -> Vec<Move> {
+        self.calc_pseudolegal_moves_with_options(&MoveGenOptions::default())
+    }
+
+    /// Returns a vector of pseudolegal moves, generated according to `options`.
+    pub fn calc_pseudolegal_moves_with_options(&self, options: &MoveGenOptions) -> Vec<Move> {
+        record_perf_counter(PerfCounter::PseudolegalMovegen);
+        let mut moves: Vec<Move> = Vec::new();
+        self.add_moves(&mut moves, options, &MoveFilter::NONE);
+        moves
+    }
+
+    /// Appends the moves allowed by `filter` to `moves`, in the same order for pseudolegal and legal generation.
+    /// Castling moves are only generated when legal.
+    fn add_moves(&self, moves: &mut Vec<Move>, options: &MoveGenOptions, filter: &MoveFilter) {
+        self.add_all_pawn_pseudolegal(moves, options, filter);
+        self.add_knight_pseudolegal(moves, filter);
+        self.add_bishop_pseudolegal(moves, filter);
+        self.add_rook_pseudolegal(moves, filter);
+        self.add_queen_pseudolegal(moves, filter);
+        self.add_king_pseudolegal(moves, filter);
+        self.add_castling_pseudolegal(moves);
+    }
+
+    /// Returns a vector of legal moves.
+    /// For each pseudolegal move, it clones the state,
+    /// makes the move, checks if the state is unequivocally valid, 
+    /// and if so, adds the move to the vector.
+    /// This is the legacy version of `calc_legal_moves`, which is far more efficient.
+    pub fn calc_legal_moves_legacy(&self) -> Vec<Move> {
+        if self.termination.is_some() {
+            return Vec::new();
+        }
+        let pseudolegal_moves = self.calc_pseudolegal_moves();
+        let mut filtered_moves = Vec::new();
+        for move_ in pseudolegal_moves {
+            let mut new_state = self.clone();
+            new_state.make_move(move_);
+            if new_state.is_unequivocally_valid() && !new_state.board.is_color_in_check(self.side_to_move) {
+                filtered_moves.push(move_);
+            }
+        }
+        filtered_moves
+    }
+
+    /// Returns a vector of legal moves, generated according to `options`.
+    /// Checks and pins are computed once up front, so each move is generated legal without being made.
+    /// This is the more efficient version of `calc_legal_moves_legacy`.
+    /// Unlike `calc_legal_moves`, the result is not cached.
+    /// When underpromotions are excluded, they are still generated for any queen promotion that would stalemate.
+    pub fn calc_legal_moves_with_options(&self, options: &MoveGenOptions) -> Vec<Move> {
+        let mut moves = Vec::new();
+        self.add_legal_moves(&mut moves, options);
+        moves
+    }
+
+    /// Replaces the contents of `moves` with the legal moves, generated with the default options.
+    /// Unlike `calc_legal_moves`, the result is not cached, so a buffer reused across positions
+    /// (e.g. during rollouts) avoids allocating for every move.
+    pub fn calc_legal_moves_into(&self, moves: &mut Vec<Move>) {
+        moves.clear();
+        self.add_legal_moves(moves, &MoveGenOptions::default());
+    }
+
+    /// Appends the legal moves to `moves`, which is expected to be empty.
+    fn add_legal_moves(&self, moves: &mut Vec<Move>, options: &MoveGenOptions) {
+        record_perf_counter(PerfCounter::LegalMovegen);
+        if self.termination.is_some() {
+            return;
+        }
+
+        self.add_moves(moves, options, &self.calc_move_filter());
+
+        if !options.include_underpromotions {
+            self.add_underpromotions_if_queen_stalemates(moves);
+        }
+    }
+
+    /// Inserts the underpromotions after each queen promotion in `moves` that would stalemate the opponent.
+    fn add_underpromotions_if_queen_stalemates(&self, moves: &mut Vec<Move>) {
+        let mut i = 0;
+        while i < moves.len() {
+            let mv = moves[i];
+            i += 1;
+            if mv.get_flag() != MoveFlag::Promotion {
+                continue;
+            }
+
+            let mut state = self.clone();
+            state.make_move(mv);
+            let is_stalemate = !state.board.is_color_in_check(state.side_to_move) && state.calc_legal_moves().is_empty();
+            if is_stalemate {
+                for promotion_piece in PieceType::iter_promotion_pieces().rev().skip(1) {
+                    moves.insert(i, Move::new(mv.get_destination(), mv.get_source(), *promotion_piece, MoveFlag::Promotion));
+                    i += 1;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_promotions(moves: &[Move]) -> Vec<PieceType> {
+        moves.iter().filter(|mv| mv.get_flag() == MoveFlag::Promotion).map(|mv| mv.get_promotion()).collect()
+    }
+
+    #[test]
+    fn test_promotion_options() {
+        let state = State::from_fen("8/P7/8/8/8/8/8/k6K w - - 0 1").unwrap();
+
+        let moves = state.calc_legal_moves_with_options(&MoveGenOptions::ALL);
+        assert_eq!(get_promotions(&moves), vec![PieceType::Knight, PieceType::Bishop, PieceType::Rook, PieceType::Queen]);
+
+        let options = MoveGenOptions { include_underpromotions: true, queen_first_promotions: true };
+        let moves = state.calc_legal_moves_with_options(&options);
+        assert_eq!(get_promotions(&moves), vec![PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight]);
+
+        let moves = state.calc_legal_moves_with_options(&MoveGenOptions::SEARCH);
+        assert_eq!(get_promotions(&moves), vec![PieceType::Queen]);
+        assert_eq!(moves.len(), state.calc_legal_moves().len() - 3);
+    }
+
+    #[test]
+    fn test_underpromotions_kept_when_queen_stalemates() {
+        // c8=Q stalemates the king on a7, so the underpromotions must still be generated
+        let state = State::from_fen("8/k1P5/8/1K6/8/8/8/8 w - - 0 1").unwrap();
+        let moves = state.calc_legal_moves_with_options(&MoveGenOptions::SEARCH);
+        assert_eq!(get_promotions(&moves), vec![PieceType::Queen, PieceType::Rook, PieceType::Bishop, PieceType::Knight]);
+    }
+
+    /// Checks the direct legal move generator against the make-and-validate one,
+    /// in `state` and in every position reachable from it within `depth` plies.
+    fn assert_matches_legacy(state: &State, depth: u8) {
+        let mut moves = state.calc_legal_moves_with_options(&MoveGenOptions::ALL);
+        let mut legacy_moves = state.calc_legal_moves_legacy();
+        moves.sort_by_key(|mv| mv.to_uci());
+        legacy_moves.sort_by_key(|mv| mv.to_uci());
+        assert_eq!(moves, legacy_moves, "{}", state.to_fen());
+
+        if depth > 0 {
+            for mv in legacy_moves {
+                let mut next_state = state.clone();
+                next_state.make_move(mv);
+                assert_matches_legacy(&next_state, depth - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_legal_moves_match_legacy() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", // kiwipete
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/8/8/K2pP2r/8/8/8/7k w - d6 0 2", // en passant would uncover a rook check
+            "4k3/8/8/8/1b6/8/3N4/r3K3 w - - 0 1", // double check, only king moves
+            "4k3/8/8/2Pp4/8/8/8/1K6 w - d6 0 2", // en passant that captures the checking pawn
+        ];
+        for fen in fens {
+            assert_matches_legacy(&State::from_fen(fen).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn test_pinned_pieces() {
+        // the knight on e2 is pinned and the bishop on d2 can only move along the pin
+        let state = State::from_fen("4r2k/8/8/b7/8/8/3BN3/4K3 w - - 0 1").unwrap();
+        let moves = state.calc_legal_moves();
+        assert!(moves.iter().all(|mv| mv.get_source() != Square::E2));
+        let bishop_dsts: Vec<Square> = moves.iter().filter(|mv| mv.get_source() == Square::D2).map(|mv| mv.get_destination()).collect();
+        assert_eq!(bishop_dsts, vec![Square::C3, Square::B4, Square::A5]);
+    }
+
+    #[test]
+    fn test_calc_legal_moves_into() {
+        let state = State::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mut moves = vec![Move::new_non_promotion(Square::A1, Square::A2, MoveFlag::NormalMove)];
+        state.calc_legal_moves_into(&mut moves);
+        assert_eq!(moves, state.calc_legal_moves());
+        assert_eq!(moves.len(), 48);
+    }
+}
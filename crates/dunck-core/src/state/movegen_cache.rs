@@ -0,0 +1,189 @@
+//! Per-position caching of legal moves and check status.
+
+use crate::r#move::Move;
+use crate::state::{Context, MoveGenOptions, State};
+use crate::utils::{Bitboard, Color, PieceType, Square};
+
+/// Everything legal moves depend on: the board's Zobrist hash, the side to move, the castling rights
+/// and the double pawn push. Read from the fields themselves rather than the context's Zobrist hash,
+/// which direct edits of the board or the context do not update.
+type MoveGenCacheKey = (Bitboard, Color, u8, i8);
+
+/// Move generation results for the position a `Context` belongs to.
+/// Since `make_move` creates a fresh context and `unmake_move` restores the previous one,
+/// results stay valid until the next move. They are also keyed by the position they were computed for,
+/// so states whose board, castling rights or double pawn push were edited directly are not served stale results.
+/// Ignored when comparing contexts.
+#[derive(Clone, Debug, Default)]
+pub struct MoveGenCache {
+    key: Option<MoveGenCacheKey>,
+    legal_moves: Option<Vec<Move>>,
+    is_in_check: Option<bool>,
+}
+
+impl MoveGenCache {
+    /// Empties the cache if it was filled for a different position.
+    fn validate(&mut self, key: MoveGenCacheKey) {
+        if self.key != Some(key) {
+            *self = MoveGenCache {
+                key: Some(key),
+                legal_moves: None,
+                is_in_check: None,
+            };
+        }
+    }
+
+    /// Empties the cache.
+    pub fn clear(&mut self) {
+        *self = MoveGenCache::default();
+    }
+}
+
+impl PartialEq for MoveGenCache {
+    fn eq(&self, _other: &MoveGenCache) -> bool {
+        true
+    }
+}
+
+impl Eq for MoveGenCache {}
+
+impl State {
+    /// Empties the move generation cache of `context`, which must be this state's, if it was filled for a different position.
+    fn validate_movegen_cache(&self, context: &mut Context) {
+        let key = (self.board.zobrist_hash, self.side_to_move, context.castling_rights, context.double_pawn_push);
+        context.movegen_cache.validate(key);
+    }
+
+    /// Returns a vector of legal moves, generated with the default options.
+    /// The result is cached on the context, so repeated calls for the same position are cheap.
+    pub fn calc_legal_moves(&self) -> Vec<Move> {
+        self.with_legal_moves(|legal_moves| legal_moves.to_vec())
+    }
+
+    /// Calls `f` with the cached legal moves, generating and caching them first if needed.
+    fn with_legal_moves<R>(&self, f: impl FnOnce(&[Move]) -> R) -> R {
+        if self.termination.is_some() {
+            return f(&[]);
+        }
+
+        {
+            let mut context = self.context.borrow_mut();
+            self.validate_movegen_cache(&mut context);
+            if let Some(legal_moves) = &context.movegen_cache.legal_moves {
+                return f(legal_moves);
+            }
+        }
+
+        let legal_moves = self.calc_legal_moves_with_options(&MoveGenOptions::default());
+        let result = f(&legal_moves);
+        self.context.borrow_mut().movegen_cache.legal_moves = Some(legal_moves);
+        result
+    }
+
+    /// Returns a mask of the squares the piece on `square` can legally move to.
+    /// A castling king's destination is the square the king lands on.
+    /// Empty if `square` holds no piece of the side to move. Reuses the cached legal moves.
+    pub fn legal_destinations(&self, square: Square) -> Bitboard {
+        self.with_legal_moves(|legal_moves| {
+            legal_moves.iter()
+                .filter(|mv| mv.get_source() == square)
+                .fold(0, |mask, mv| mask | mv.get_destination().get_mask())
+        })
+    }
+
+    /// Returns a mask of the squares holding a piece of type `piece_type` of the side to move
+    /// that has at least one legal move. Reuses the cached legal moves.
+    pub fn legal_sources(&self, piece_type: PieceType) -> Bitboard {
+        self.with_legal_moves(|legal_moves| {
+            legal_moves.iter()
+                .map(|mv| mv.get_source())
+                .filter(|&src| self.board.get_piece_type_at(src) == piece_type)
+                .fold(0, |mask, src| mask | src.get_mask())
+        })
+    }
+
+    /// Returns whether the side to move is in check.
+    /// The result is cached on the context, like `calc_legal_moves`.
+    pub fn is_in_check(&self) -> bool {
+        let mut context = self.context.borrow_mut();
+        self.validate_movegen_cache(&mut context);
+        *context.movegen_cache.is_in_check.get_or_insert_with(|| self.board.is_color_in_check(self.side_to_move))
+    }
+
+    #[deprecated(since = "0.1.0", note = "use `calc_legal_moves` instead")]
+    pub fn get_moves(&self) -> Vec<Move> {
+        self.calc_legal_moves()
+    }
+
+    #[deprecated(since = "0.1.0", note = "use `calc_legal_moves` instead")]
+    pub fn get_legal_moves(&self) -> Vec<Move> {
+        self.calc_legal_moves()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r#move::MoveFlag;
+
+    #[test]
+    fn test_legal_moves_cache() {
+        let mut state = State::initial();
+        let initial_moves = state.calc_legal_moves();
+        assert_eq!(state.calc_legal_moves(), initial_moves);
+        assert!(!state.is_in_check());
+
+        let e4 = Move::new_non_promotion(Square::E4, Square::E2, MoveFlag::NormalMove);
+        state.make_move(e4);
+        let moves_after_e4 = state.calc_legal_moves();
+        assert_eq!(moves_after_e4.len(), 20);
+        assert_ne!(moves_after_e4, initial_moves);
+
+        state.unmake_move(e4);
+        assert_eq!(state.calc_legal_moves(), initial_moves);
+        assert_eq!(state, State::initial());
+
+        // a direct board edit must not be served the cached moves
+        state.board.remove_colored_piece_at(crate::utils::ColoredPiece::from(Color::White, PieceType::Knight), Square::G1);
+        assert_eq!(state.calc_legal_moves().len(), initial_moves.len() - 2 + 1);
+    }
+
+    #[test]
+    fn test_legal_moves_cache_keyed_on_castling_and_en_passant() {
+        let state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let moves_with_castling = state.calc_legal_moves();
+        state.context.borrow_mut().castling_rights = 0;
+        assert_eq!(state.calc_legal_moves().len(), moves_with_castling.len() - 2);
+
+        let state = State::from_fen("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2").unwrap();
+        let moves_with_en_passant = state.calc_legal_moves();
+        state.context.borrow_mut().double_pawn_push = -1;
+        assert_eq!(state.calc_legal_moves().len(), moves_with_en_passant.len() - 1);
+    }
+
+    #[test]
+    fn test_check_cache() {
+        let state = State::from_fen("4k3/8/8/8/8/8/8/4K2R b K - 0 1").unwrap();
+        assert!(!state.is_in_check());
+        let state = State::from_fen("4k3/8/8/8/8/8/8/4R1K1 b - - 0 1").unwrap();
+        assert!(state.is_in_check());
+        assert!(state.is_in_check());
+    }
+
+    #[test]
+    fn test_legal_destinations_and_sources() {
+        let state = State::initial();
+        assert_eq!(state.legal_destinations(Square::G1), Square::F3.get_mask() | Square::H3.get_mask());
+        assert_eq!(state.legal_destinations(Square::E2), Square::E3.get_mask() | Square::E4.get_mask());
+        assert_eq!(state.legal_destinations(Square::D1), 0);
+        assert_eq!(state.legal_destinations(Square::E7), 0);
+        assert_eq!(state.legal_sources(PieceType::Knight), Square::B1.get_mask() | Square::G1.get_mask());
+        assert_eq!(state.legal_sources(PieceType::Bishop), 0);
+
+        let state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let king_destinations = state.legal_destinations(Square::E1);
+        assert_ne!(king_destinations & Square::G1.get_mask(), 0);
+        assert_ne!(king_destinations & Square::C1.get_mask(), 0);
+        assert_eq!(state.legal_sources(PieceType::King), Square::E1.get_mask());
+    }
+}
@@ -0,0 +1,213 @@
+//! Move generation statistics over a corpus of positions, for sizing move buffers and tuning move ordering.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use crate::r#move::MoveFlag;
+use crate::state::State;
+use crate::utils::PieceType;
+
+/// Accumulated legal move statistics over a set of positions.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MoveGenStats {
+    /// Number of positions added.
+    pub num_positions: usize,
+    /// Maps a number of legal moves to the number of positions with exactly that many.
+    pub branching_factors: BTreeMap<usize, usize>,
+    /// Total number of legal moves over all positions.
+    pub num_moves: usize,
+    /// Number of legal moves that capture a piece, including en passant.
+    pub num_captures: usize,
+    /// Number of legal moves that are promotions, capturing or not.
+    pub num_promotions: usize,
+    /// Number of legal promotions to a piece other than a queen.
+    pub num_underpromotions: usize,
+    /// Number of legal castling moves.
+    pub num_castling_moves: usize,
+    /// Number of legal moves that are not captures, promotions, or castling.
+    pub num_quiet_moves: usize,
+    /// Number of positions in which the side to move has at least one legal promotion.
+    pub num_positions_with_promotions: usize,
+    /// Number of positions in which the side to move is in check.
+    pub num_positions_in_check: usize,
+}
+
+impl MoveGenStats {
+    /// Creates empty statistics.
+    pub fn new() -> MoveGenStats {
+        MoveGenStats::default()
+    }
+
+    /// Creates statistics over all of `states`.
+    pub fn from_states<'a>(states: impl IntoIterator<Item = &'a State>) -> MoveGenStats {
+        let mut stats = MoveGenStats::new();
+        for state in states {
+            stats.add_state(state);
+        }
+        stats
+    }
+
+    /// Adds the legal moves of `state` to the statistics.
+    pub fn add_state(&mut self, state: &State) {
+        let moves = state.calc_legal_moves();
+        self.num_positions += 1;
+        *self.branching_factors.entry(moves.len()).or_insert(0) += 1;
+        self.num_moves += moves.len();
+        if state.board.is_color_in_check(state.side_to_move) {
+            self.num_positions_in_check += 1;
+        }
+
+        let mut has_promotion = false;
+        for mv in moves {
+            let (dst, _, promotion, flag) = mv.unpack();
+            let is_capture = flag == MoveFlag::EnPassant || state.board.get_piece_type_at(dst) != PieceType::NoPieceType;
+            if is_capture {
+                self.num_captures += 1;
+            }
+            match flag {
+                MoveFlag::Castling => self.num_castling_moves += 1,
+                MoveFlag::Promotion => {
+                    has_promotion = true;
+                    self.num_promotions += 1;
+                    if promotion != PieceType::Queen {
+                        self.num_underpromotions += 1;
+                    }
+                },
+                MoveFlag::NormalMove if !is_capture => self.num_quiet_moves += 1,
+                _ => {}
+            }
+        }
+        if has_promotion {
+            self.num_positions_with_promotions += 1;
+        }
+    }
+
+    /// Merges `other` into these statistics.
+    pub fn merge(&mut self, other: &MoveGenStats) {
+        self.num_positions += other.num_positions;
+        for (&branching_factor, &count) in &other.branching_factors {
+            *self.branching_factors.entry(branching_factor).or_insert(0) += count;
+        }
+        self.num_moves += other.num_moves;
+        self.num_captures += other.num_captures;
+        self.num_promotions += other.num_promotions;
+        self.num_underpromotions += other.num_underpromotions;
+        self.num_castling_moves += other.num_castling_moves;
+        self.num_quiet_moves += other.num_quiet_moves;
+        self.num_positions_with_promotions += other.num_positions_with_promotions;
+        self.num_positions_in_check += other.num_positions_in_check;
+    }
+
+    /// Returns the mean number of legal moves per position, or 0 if there are no positions.
+    pub fn get_mean_branching_factor(&self) -> f64 {
+        ratio(self.num_moves, self.num_positions)
+    }
+
+    /// Returns the largest number of legal moves in any position, or 0 if there are no positions.
+    pub fn get_max_branching_factor(&self) -> usize {
+        self.branching_factors.keys().next_back().copied().unwrap_or(0)
+    }
+
+    /// Returns the smallest number of legal moves that at least `fraction` of the positions do not exceed,
+    /// e.g. 0.99 gives a move buffer capacity that suffices for 99% of positions.
+    /// Returns 0 if there are no positions.
+    pub fn get_branching_factor_percentile(&self, fraction: f64) -> usize {
+        let target = (fraction.clamp(0., 1.) * self.num_positions as f64).ceil() as usize;
+        let mut cumulative = 0;
+        for (&branching_factor, &count) in &self.branching_factors {
+            cumulative += count;
+            if cumulative >= target {
+                return branching_factor;
+            }
+        }
+        0
+    }
+
+    /// Returns the fraction of legal moves that are captures.
+    pub fn get_capture_rate(&self) -> f64 {
+        ratio(self.num_captures, self.num_moves)
+    }
+
+    /// Returns the number of captures per quiet move.
+    pub fn get_capture_to_quiet_ratio(&self) -> f64 {
+        ratio(self.num_captures, self.num_quiet_moves)
+    }
+
+    /// Returns the fraction of legal moves that are promotions.
+    pub fn get_promotion_rate(&self) -> f64 {
+        ratio(self.num_promotions, self.num_moves)
+    }
+
+    /// Returns the fraction of positions in which the side to move has at least one legal promotion.
+    pub fn get_positions_with_promotions_rate(&self) -> f64 {
+        ratio(self.num_positions_with_promotions, self.num_positions)
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+impl Display for MoveGenStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Positions: {} ({} in check)", self.num_positions, self.num_positions_in_check)?;
+        writeln!(
+            f, "Branching factor: mean {:.2}, median {}, p99 {}, max {}",
+            self.get_mean_branching_factor(),
+            self.get_branching_factor_percentile(0.5),
+            self.get_branching_factor_percentile(0.99),
+            self.get_max_branching_factor()
+        )?;
+        writeln!(
+            f, "Moves: {} ({} captures, {} quiet, {} castling; capture/quiet ratio {:.3})",
+            self.num_moves, self.num_captures, self.num_quiet_moves, self.num_castling_moves, self.get_capture_to_quiet_ratio()
+        )?;
+        write!(
+            f, "Promotions: {} ({} underpromotions; {:.2}% of moves, available in {:.2}% of positions)",
+            self.num_promotions, self.num_underpromotions,
+            100. * self.get_promotion_rate(), 100. * self.get_positions_with_promotions_rate()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_initial_state() {
+        let stats = MoveGenStats::from_states([&State::initial()]);
+        assert_eq!(stats.num_positions, 1);
+        assert_eq!(stats.num_moves, 20);
+        assert_eq!(stats.num_quiet_moves, 20);
+        assert_eq!(stats.num_captures, 0);
+        assert_eq!(stats.get_mean_branching_factor(), 20.);
+        assert_eq!(stats.get_branching_factor_percentile(0.99), 20);
+    }
+
+    #[test]
+    fn test_move_categories() {
+        // kiwipete: 48 moves, 8 captures, 2 castling moves
+        let kiwipete = State::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        // b7 can push to b8 or capture on a8 and c8, each with four promotions
+        let promotions = State::from_fen("r1n1k3/1P6/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut stats = MoveGenStats::from_states([&kiwipete]);
+        assert_eq!(stats.num_moves, 48);
+        assert_eq!(stats.num_captures, 8);
+        assert_eq!(stats.num_castling_moves, 2);
+        assert_eq!(stats.num_quiet_moves, 38);
+
+        stats.merge(&MoveGenStats::from_states([&promotions]));
+        assert_eq!(stats.num_positions, 2);
+        assert_eq!(stats.num_promotions, 12);
+        assert_eq!(stats.num_underpromotions, 9);
+        assert_eq!(stats.num_positions_with_promotions, 1);
+        assert_eq!(stats.num_captures, 16);
+        assert_eq!(stats.num_moves, stats.num_captures + stats.num_quiet_moves + stats.num_castling_moves + 4);
+        assert_eq!(stats.get_max_branching_factor(), 48);
+        assert_eq!(stats.get_branching_factor_percentile(0.5), stats.num_moves - 48);
+    }
+}
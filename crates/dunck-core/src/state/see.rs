@@ -0,0 +1,95 @@
+//! Static exchange evaluation (SEE) for the state struct
+
+use crate::r#move::{Move, MoveFlag};
+use crate::state::State;
+use crate::utils::{get_squares_from_mask_iter, PieceType, Square};
+
+/// Piece values used by static exchange evaluation, indexed by piece type.
+const SEE_PIECE_VALUES: [i32; PieceType::LIMIT as usize] = [
+    0,   // NoPieceType
+    1,   // Pawn
+    3,   // Knight
+    3,   // Bishop
+    5,   // Rook
+    9,   // Queen
+    100  // King
+];
+
+/// Returns the value of `piece_type` as used by static exchange evaluation.
+pub const fn get_see_value(piece_type: PieceType) -> i32 {
+    SEE_PIECE_VALUES[piece_type as usize]
+}
+
+impl State {
+    /// Returns the expected material gain, in pawns, of playing `mv` and then letting both sides
+    /// recapture on the destination square with their least valuable attacker for as long as it pays.
+    /// The moving side is the color of the piece on the source square, not necessarily the side to move.
+    pub fn calc_see(&self, mv: Move) -> i32 {
+        let (dst_square, src_square, promotion, flag) = mv.unpack();
+        let board = &self.board;
+
+        let mut occupied_mask = board.piece_type_masks[PieceType::AllPieceTypes as usize] & !src_square.get_mask();
+        let mut side = board.get_color_at(src_square);
+
+        let mut gains = vec![match flag {
+            MoveFlag::EnPassant => {
+                let captured_square = unsafe { Square::from_rank_file(src_square.get_rank(), dst_square.get_file()) };
+                occupied_mask &= !captured_square.get_mask();
+                get_see_value(PieceType::Pawn)
+            },
+            MoveFlag::Castling => return 0,
+            _ => get_see_value(board.get_piece_type_at(dst_square))
+        }];
+        let mut attacker_value = get_see_value(board.get_piece_type_at(src_square));
+        if flag == MoveFlag::Promotion {
+            gains[0] += get_see_value(promotion) - get_see_value(PieceType::Pawn);
+            attacker_value = get_see_value(promotion);
+        }
+
+        loop {
+            side = side.flip();
+            let attackers_mask = board.calc_attackers_to(dst_square, occupied_mask) & occupied_mask & board.color_masks[side as usize];
+            let least_valuable_attacker = PieceType::iter_pieces().find_map(|piece_type| {
+                let mask = attackers_mask & board.piece_type_masks[*piece_type as usize];
+                get_squares_from_mask_iter(mask).next().map(|square| (*piece_type, square))
+            });
+            let (piece_type, square) = match least_valuable_attacker {
+                Some(attacker) => attacker,
+                None => break
+            };
+
+            gains.push(attacker_value - gains[gains.len() - 1]);
+            attacker_value = get_see_value(piece_type);
+            occupied_mask &= !square.get_mask();
+        }
+
+        for i in (1..gains.len()).rev() {
+            gains[i - 1] = -(-gains[i - 1]).max(gains[i]);
+        }
+        gains[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_see() {
+        // pawn takes undefended knight
+        let state = State::from_fen("4k3/8/8/3n4/4P3/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(state.calc_see(Move::new_non_promotion(Square::D5, Square::E4, MoveFlag::NormalMove)), 3);
+
+        // rook takes pawn defended by pawn
+        let state = State::from_fen("4k3/8/2p5/3p4/8/8/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(state.calc_see(Move::new_non_promotion(Square::D5, Square::D1, MoveFlag::NormalMove)), -4);
+
+        // knight takes pawn defended by rook, backed up by a rook behind a rook
+        let state = State::from_fen("3rk3/8/8/3p4/8/4N3/8/3RK3 w - - 0 1").unwrap();
+        assert_eq!(state.calc_see(Move::new_non_promotion(Square::D5, Square::E3, MoveFlag::NormalMove)), 1);
+
+        // moving to an empty square attacked by a pawn
+        let state = State::from_fen("4k3/8/2p5/8/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert_eq!(state.calc_see(Move::new_non_promotion(Square::D5, Square::D1, MoveFlag::NormalMove)), -9);
+    }
+}
@@ -0,0 +1,207 @@
+//! Conversions between dunck types and their `shakmaty` counterparts, behind the `shakmaty-interop` feature.
+//! Positions are converted through FEN, so move history is not carried over.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess, EnPassantMode, Role};
+use crate::r#move::{Move, MoveFlag};
+use crate::state::{FenParseError, State};
+use crate::utils::{Color, PieceType, Square};
+
+/// The reason a value could not be converted to or from its `shakmaty` counterpart.
+#[derive(Debug)]
+pub enum ShakmatyConversionError {
+    InvalidFen(FenParseError),
+    InvalidPosition(String),
+    NoPieceType,
+    UnsupportedMove(shakmaty::Move),
+}
+
+impl Display for ShakmatyConversionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShakmatyConversionError::InvalidFen(error) => write!(f, "Invalid FEN: {}", error),
+            ShakmatyConversionError::InvalidPosition(error) => write!(f, "Invalid position: {}", error),
+            ShakmatyConversionError::NoPieceType => write!(f, "NoPieceType has no shakmaty role"),
+            ShakmatyConversionError::UnsupportedMove(mv) => write!(f, "Unsupported move: {}", mv),
+        }
+    }
+}
+
+impl Error for ShakmatyConversionError {}
+
+impl From<Square> for shakmaty::Square {
+    fn from(square: Square) -> shakmaty::Square {
+        shakmaty::Square::new(square.to_a1_index() as u32)
+    }
+}
+
+impl From<shakmaty::Square> for Square {
+    fn from(square: shakmaty::Square) -> Square {
+        Square::from_a1_index(square as u8).unwrap()
+    }
+}
+
+impl From<Color> for shakmaty::Color {
+    fn from(color: Color) -> shakmaty::Color {
+        match color {
+            Color::White => shakmaty::Color::White,
+            Color::Black => shakmaty::Color::Black
+        }
+    }
+}
+
+impl From<shakmaty::Color> for Color {
+    fn from(color: shakmaty::Color) -> Color {
+        match color {
+            shakmaty::Color::White => Color::White,
+            shakmaty::Color::Black => Color::Black
+        }
+    }
+}
+
+impl TryFrom<PieceType> for Role {
+    type Error = ShakmatyConversionError;
+
+    fn try_from(piece_type: PieceType) -> Result<Role, ShakmatyConversionError> {
+        match piece_type {
+            PieceType::NoPieceType => Err(ShakmatyConversionError::NoPieceType),
+            PieceType::Pawn => Ok(Role::Pawn),
+            PieceType::Knight => Ok(Role::Knight),
+            PieceType::Bishop => Ok(Role::Bishop),
+            PieceType::Rook => Ok(Role::Rook),
+            PieceType::Queen => Ok(Role::Queen),
+            PieceType::King => Ok(Role::King)
+        }
+    }
+}
+
+impl From<Role> for PieceType {
+    fn from(role: Role) -> PieceType {
+        match role {
+            Role::Pawn => PieceType::Pawn,
+            Role::Knight => PieceType::Knight,
+            Role::Bishop => PieceType::Bishop,
+            Role::Rook => PieceType::Rook,
+            Role::Queen => PieceType::Queen,
+            Role::King => PieceType::King
+        }
+    }
+}
+
+impl TryFrom<&State> for Chess {
+    type Error = ShakmatyConversionError;
+
+    fn try_from(state: &State) -> Result<Chess, ShakmatyConversionError> {
+        let fen: Fen = state.to_fen().parse().map_err(|error: shakmaty::fen::ParseFenError| {
+            ShakmatyConversionError::InvalidPosition(error.to_string())
+        })?;
+        fen.into_position(CastlingMode::Standard).map_err(|error| ShakmatyConversionError::InvalidPosition(error.to_string()))
+    }
+}
+
+impl TryFrom<&Chess> for State {
+    type Error = ShakmatyConversionError;
+
+    fn try_from(position: &Chess) -> Result<State, ShakmatyConversionError> {
+        let fen = Fen::from_position(position, EnPassantMode::Always);
+        State::from_fen(&fen.to_string()).map_err(ShakmatyConversionError::InvalidFen)
+    }
+}
+
+impl TryFrom<shakmaty::Move> for Move {
+    type Error = ShakmatyConversionError;
+
+    fn try_from(mv: shakmaty::Move) -> Result<Move, ShakmatyConversionError> {
+        match mv {
+            shakmaty::Move::Normal { from, to, promotion: Some(promotion), .. } => {
+                Ok(Move::new(to.into(), from.into(), promotion.into(), MoveFlag::Promotion))
+            },
+            shakmaty::Move::Normal { from, to, promotion: None, .. } => {
+                Ok(Move::new_non_promotion(to.into(), from.into(), MoveFlag::NormalMove))
+            },
+            shakmaty::Move::EnPassant { from, to } => {
+                Ok(Move::new_non_promotion(to.into(), from.into(), MoveFlag::EnPassant))
+            },
+            shakmaty::Move::Castle { king, rook } => {
+                let (king, rook): (Square, Square) = (king.into(), rook.into());
+                let file_delta = if rook.get_file() > king.get_file() { 2 } else { -2 };
+                let dst = king.offset(file_delta, 0).ok_or(ShakmatyConversionError::UnsupportedMove(mv))?;
+                Ok(Move::new_non_promotion(dst, king, MoveFlag::Castling))
+            },
+            shakmaty::Move::Put { .. } => Err(ShakmatyConversionError::UnsupportedMove(mv))
+        }
+    }
+}
+
+impl Move {
+    /// Converts the move to a `shakmaty::Move`, looking up the moved and captured pieces in `state`,
+    /// the position the move is played from.
+    pub fn to_shakmaty(&self, state: &State) -> shakmaty::Move {
+        let (dst, src, promotion, flag) = self.unpack();
+        match flag {
+            MoveFlag::EnPassant => shakmaty::Move::EnPassant { from: src.into(), to: dst.into() },
+            MoveFlag::Castling => {
+                let rook_file_delta = if dst.get_file() > src.get_file() { 7 - src.get_file() as i8 } else { -(src.get_file() as i8) };
+                shakmaty::Move::Castle { king: src.into(), rook: src.offset(rook_file_delta, 0).unwrap().into() }
+            },
+            MoveFlag::NormalMove | MoveFlag::Promotion => shakmaty::Move::Normal {
+                role: Role::try_from(state.board.get_piece_type_at(src)).expect("No piece on the source square"),
+                from: src.into(),
+                capture: Role::try_from(state.board.get_piece_type_at(dst)).ok(),
+                to: dst.into(),
+                promotion: if flag == MoveFlag::Promotion { Some(Role::try_from(promotion).unwrap()) } else { None }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use shakmaty::Position;
+    use super::*;
+
+    const FENS: [&str; 4] = [
+        crate::state::INITIAL_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+    ];
+
+    fn assert_movegen_matches(state: &State, position: &Chess, depth: u32) {
+        let moves: HashSet<Move> = state.calc_legal_moves().into_iter().collect();
+        let shakmaty_moves: HashSet<Move> = position.legal_moves().into_iter().map(|mv| Move::try_from(mv).unwrap()).collect();
+        assert_eq!(moves, shakmaty_moves, "{}", state.to_fen());
+
+        if depth > 1 {
+            for mv in moves {
+                let mut next_state = state.clone();
+                next_state.make_move(mv);
+                let next_position = position.clone().play(mv.to_shakmaty(state)).unwrap();
+                assert_movegen_matches(&next_state, &next_position, depth - 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_movegen_matches_shakmaty() {
+        for fen in FENS {
+            let state = State::from_fen(fen).unwrap();
+            let position = Chess::try_from(&state).unwrap();
+            assert_eq!(State::try_from(&position).unwrap().to_fen(), state.to_fen());
+            assert_movegen_matches(&state, &position, 2);
+        }
+    }
+
+    #[test]
+    fn test_square_conversions() {
+        assert_eq!(shakmaty::Square::from(Square::A1), shakmaty::Square::A1);
+        assert_eq!(shakmaty::Square::from(Square::E4), shakmaty::Square::E4);
+        for square in Square::ALL {
+            let converted: Square = shakmaty::Square::from(square).into();
+            assert_eq!(converted, square);
+        }
+    }
+}
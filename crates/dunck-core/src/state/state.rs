@@ -0,0 +1,410 @@
+//! Contains the State struct, which is the main struct for representing a position in a chess game.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::state::{Board, Context, Termination};
+use crate::utils::{Bitboard, Color, PieceType};
+use crate::utils::masks::{CASTLING_CHECK_MASK_LONG, CASTLING_CHECK_MASK_SHORT, FILES, RANK_4, STARTING_BK, STARTING_KING_ROOK_GAP_LONG, STARTING_KING_ROOK_GAP_SHORT, STARTING_KING_SIDE_BR, STARTING_KING_SIDE_WR, STARTING_QUEEN_SIDE_BR, STARTING_QUEEN_SIDE_WR, STARTING_WK};
+
+/// A struct containing all the information needed to represent a position in a chess game.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct State {
+    pub board: Board,
+    pub side_to_move: Color,
+    pub halfmove: u16,
+    pub termination: Option<Termination>,
+    pub context: Rc<RefCell<Context>>,
+}
+
+impl State {
+    /// Creates a blank state with no pieces on the board.
+    pub fn blank() -> State {
+        let state = State {
+            board: Board::blank(),
+            side_to_move: Color::White,
+            halfmove: 0,
+            termination: None,
+            context: Rc::new(RefCell::new(Context::initial_no_castling(0))),
+        };
+        state.context.borrow_mut().zobrist_hash = state.calc_zobrist_hash();
+        state
+    }
+
+    /// Creates an initial state with the standard starting position.
+    pub fn initial() -> State {
+        let state = State {
+            board: Board::initial(),
+            side_to_move: Color::White,
+            halfmove: 0,
+            termination: None,
+            context: Rc::new(RefCell::new(Context::initial(0))),
+        };
+        state.context.borrow_mut().zobrist_hash = state.calc_zobrist_hash();
+        state
+    }
+
+    /// Gets the fullmove number of the position.
+    pub const fn get_fullmove(&self) -> u16 {
+        self.halfmove / 2 + 1
+    }
+
+    /// Gets up to `n` boards that preceded the current one, most recent first.
+    /// Fewer are returned if the game history is shorter, e.g. for positions loaded from FEN.
+    pub fn get_recent_boards(&self, n: usize) -> Vec<Board> {
+        // boards are not stored per move, so rebuild them by unmaking the recorded moves on a copy
+        let mut boards = Vec::with_capacity(n);
+        let mut state = self.clone();
+        while boards.len() < n {
+            let last_move = state.context.borrow().last_move;
+            match last_move {
+                Some(mv) => state.unmake_move(mv),
+                None => break
+            }
+            boards.push(state.board.clone());
+        }
+        boards
+    }
+
+    /// Gets the number of times the position occurred before in the game, since the last capture or pawn move:
+    /// 0 for a new position, 1 for a first repetition, and 2 once threefold repetition has occurred.
+    /// Positions loaded from FEN have no known history, so they start at 0.
+    pub fn repetition_count(&self) -> u8 {
+        self.context.borrow().repetition_count
+    }
+
+    /// Checks if the position has occurred at least twice before, so the game is drawn by threefold repetition.
+    pub fn is_threefold_repetition(&self) -> bool {
+        self.context.borrow().has_threefold_repetition_occurred()
+    }
+
+    /// Checks if the position has occurred at least four times before.
+    pub fn is_fivefold_repetition(&self) -> bool {
+        self.repetition_count() >= 4
+    }
+
+    /// Gets the Zobrist hashes of the positions since the last capture or pawn move, oldest first,
+    /// ending with the current position. Only these positions can be repeated.
+    /// Unmaking a move pops its position, since the history is kept in the contexts.
+    pub fn get_repetition_history(&self) -> Vec<Bitboard> {
+        let mut hashes = Vec::new();
+        let mut current_context = Some(self.context.clone());
+        while let Some(context) = current_context {
+            let context = context.borrow();
+            hashes.push(context.zobrist_hash);
+            current_context = match context.halfmove_clock {
+                0 => None,
+                _ => context.previous.clone()
+            };
+        }
+        hashes.reverse();
+        hashes
+    }
+
+    /// Assumes the game has ended and updates the termination as threefold repetition if the position
+    /// has been repeated, else as checkmate or stalemate.
+    pub fn assume_and_update_termination(&mut self) {
+        self.termination = Some(
+            match self.termination {
+                Some(termination) => termination,
+                None if self.is_threefold_repetition() => Termination::ThreefoldRepetition,
+                None => match self.board.is_color_in_check(self.side_to_move) {
+                    true => Termination::Checkmate,
+                    false => Termination::Stalemate,
+                }
+            }
+        );
+    }
+    
+    /// Checks if the game has ended and updates the termination as checkmate or stalemate.
+    pub fn check_and_update_termination(&mut self) {
+        if self.calc_legal_moves().is_empty() {
+            self.assume_and_update_termination();
+        }
+    }
+
+    /// Returns whether the current side to move has short castling rights.
+    pub fn has_castling_rights_short(&self, color: Color) -> bool {
+        self.context.borrow().castling_rights & (0b00001000 >> (color as u8 * 2)) != 0
+    }
+
+    /// Returns whether the current side to move has long castling rights.
+    pub fn has_castling_rights_long(&self, color: Color) -> bool {
+        self.context.borrow().castling_rights & (0b00000100 >> (color as u8 * 2)) != 0
+    }
+
+    /// Returns true if the current side to move has no pieces between the king and the rook for short castling.
+    /// Else, returns false.
+    const fn has_castling_space_short(&self, color: Color) -> bool {
+        STARTING_KING_ROOK_GAP_SHORT[color as usize] & self.board.piece_type_masks[PieceType::AllPieceTypes as usize] == 0
+    }
+
+    /// Returns true if the current side to move has no pieces between the king and the rook for long castling.
+    /// Else, returns false.
+    const fn has_castling_space_long(&self, color: Color) -> bool {
+        STARTING_KING_ROOK_GAP_LONG[color as usize] & self.board.piece_type_masks[PieceType::AllPieceTypes as usize] == 0
+    }
+
+    /// Returns true if the opponent has no pieces that can attack the squares the king moves through for short castling.
+    /// Else, returns false.
+    fn can_castle_short_without_check(&self, color: Color) -> bool {
+        !self.board.is_mask_in_check(CASTLING_CHECK_MASK_SHORT[color as usize], color.flip())
+    }
+
+    /// Returns true if the opponent has no pieces that can attack the squares the king moves through for long castling.
+    /// Else, returns false.
+    fn can_castle_long_without_check(&self, color: Color) -> bool {
+        !self.board.is_mask_in_check(CASTLING_CHECK_MASK_LONG[color as usize], color.flip())
+    }
+
+    /// Returns true if the current side to move can legally castle short.
+    /// Else, returns false.
+    pub fn can_legally_castle_short(&self, color: Color) -> bool {
+        self.has_castling_rights_short(color) && self.has_castling_space_short(color) && self.can_castle_short_without_check(color)
+    }
+
+    /// Returns true if the current side to move can legally castle long.
+    /// Else, returns false.
+    pub fn can_legally_castle_long(&self, color: Color) -> bool {
+        self.has_castling_rights_long(color) && self.has_castling_space_long(color) && self.can_castle_long_without_check(color)
+    }
+    
+    /// Rigorous check for whether the current positional information is consistent and valid.
+    pub fn is_unequivocally_valid(&self) -> bool {
+        self.board.is_unequivocally_valid() &&
+            self.has_valid_side_to_move() &&
+            self.has_valid_castling_rights() &&
+            self.has_valid_double_pawn_push() &&
+            self.has_valid_halfmove_clock() &&
+            self.is_not_in_illegal_check() &&
+            self.is_zobrist_consistent()
+    }
+
+    /// Quick check for whether the state is probably valid, should be used after making pseudo-legal moves.
+    pub fn is_probably_valid(&self) -> bool {
+        self.board.has_valid_kings() && self.is_not_in_illegal_check()
+    }
+
+    /// Checks if the zobrist hash in the context is the zobrist hash in the board
+    /// combined with the side to move, castling rights and en passant file.
+    pub fn is_zobrist_consistent(&self) -> bool {
+        self.context.borrow().zobrist_hash == self.board.zobrist_hash ^ self.calc_state_zobrist_hash()
+    }
+
+    /// Returns true if the opponent king is not in check.
+    /// Else, returns false.
+    pub fn is_not_in_illegal_check(&self) -> bool {
+        !self.board.is_color_in_check(self.side_to_move.flip())
+    }
+
+    /// Checks if the halfmove clock is valid.
+    pub fn has_valid_halfmove_clock(&self) -> bool {
+        self.context.borrow().has_valid_halfmove_clock()
+    }
+
+    /// Checks if the halfmove clock is at most the halfmove counter, as in games played from the initial position.
+    /// Games started from a setup position can legitimately fail this check.
+    pub fn has_plausible_halfmove_clock(&self) -> bool {
+        self.context.borrow().halfmove_clock as u16 <= self.halfmove
+    }
+
+    /// Checks if the side to move is consistent with the halfmove counter.
+    pub fn has_valid_side_to_move(&self) -> bool {
+        self.halfmove % 2 == self.side_to_move as u16
+    }
+
+    /// Checks if the castling rights are consistent with the position of the rooks and kings.
+    pub fn has_valid_castling_rights(&self) -> bool {
+        self.calc_inconsistent_castling_rights() == 0
+    }
+
+    /// Returns the castling rights, in the same encoding as `Context::castling_rights`, that are held
+    /// although the king or the rook involved is not on its starting square.
+    pub fn calc_inconsistent_castling_rights(&self) -> u8 {
+        let castling_rights = self.context.borrow().castling_rights;
+
+        let kings_bb = self.board.piece_type_masks[PieceType::King as usize];
+        let rooks_bb = self.board.piece_type_masks[PieceType::Rook as usize];
+
+        let white_bb = self.board.color_masks[Color::White as usize];
+        let black_bb = self.board.color_masks[Color::Black as usize];
+
+        let mut consistent_rights = 0b00001111;
+        if (kings_bb & white_bb & STARTING_WK) == 0 {
+            consistent_rights &= !0b00001100;
+        }
+        if (kings_bb & black_bb & STARTING_BK) == 0 {
+            consistent_rights &= !0b00000011;
+        }
+        if (rooks_bb & white_bb & STARTING_KING_SIDE_WR) == 0 {
+            consistent_rights &= !0b00001000;
+        }
+        if (rooks_bb & white_bb & STARTING_QUEEN_SIDE_WR) == 0 {
+            consistent_rights &= !0b00000100;
+        }
+        if (rooks_bb & black_bb & STARTING_KING_SIDE_BR) == 0 {
+            consistent_rights &= !0b00000010;
+        }
+        if (rooks_bb & black_bb & STARTING_QUEEN_SIDE_BR) == 0 {
+            consistent_rights &= !0b00000001;
+        }
+
+        castling_rights & !consistent_rights
+    }
+
+    /// Clears the castling rights that are inconsistent with the position of the rooks and kings,
+    /// e.g. after editing the board or importing a FEN from a source that does not update them.
+    /// Returns the rights that were cleared. Edits the context with `edit_context`.
+    pub fn repair_castling_rights(&mut self) -> u8 {
+        let inconsistent_rights = self.calc_inconsistent_castling_rights();
+        self.edit_context(|context| context.castling_rights &= !inconsistent_rights);
+        inconsistent_rights
+    }
+
+    /// Edits the current context with `edit`, e.g. its castling rights or double pawn push,
+    /// then updates its Zobrist hash and discards its cached legal moves.
+    /// The context is copied first if other states share it, e.g. clones, so that they are left alone.
+    pub fn edit_context(&mut self, edit: impl FnOnce(&mut Context)) {
+        let state_zobrist_hash_before = self.calc_state_zobrist_hash();
+        let context = Rc::make_mut(&mut self.context).get_mut();
+        edit(context);
+        context.movegen_cache.clear();
+        let state_zobrist_hash_after = self.calc_state_zobrist_hash();
+        self.context.borrow_mut().zobrist_hash ^= state_zobrist_hash_before ^ state_zobrist_hash_after;
+    }
+
+    /// Checks if the double pawn push is consistent with the position of the pawns.
+    pub fn has_valid_double_pawn_push(&self) -> bool {
+        match self.context.borrow().double_pawn_push {
+            -1 => true,
+            file if file > 7 || file < -1 => false,
+            file => {
+                if self.halfmove < 1 {
+                    return false;
+                }
+                let color_just_moved = self.side_to_move.flip();
+                let pawns_bb = self.board.piece_type_masks[PieceType::Pawn as usize];
+                let colored_pawns_bb = pawns_bb & self.board.color_masks[color_just_moved as usize];
+                let file_mask = FILES[file as usize];
+                let rank_mask = RANK_4 << (color_just_moved as Bitboard * 8); // 4 for white, 5 for black
+                colored_pawns_bb & file_mask & rank_mask != 0
+            }
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::r#move::{Move, MoveFlag};
+    use crate::utils::{ColoredPiece, Square};
+
+    #[test]
+    fn test_get_recent_boards() {
+        let mut state = State::initial();
+        assert!(state.get_recent_boards(2).is_empty());
+
+        state.make_move(Move::new_non_promotion(Square::E4, Square::E2, MoveFlag::NormalMove));
+        let board_after_e4 = state.board.clone();
+        state.make_move(Move::new_non_promotion(Square::E5, Square::E7, MoveFlag::NormalMove));
+
+        assert_eq!(state.get_recent_boards(0), vec![]);
+        assert_eq!(state.get_recent_boards(1), vec![board_after_e4.clone()]);
+        assert_eq!(state.get_recent_boards(3), vec![board_after_e4, Board::initial()]);
+
+        state.unmake_move(Move::new_non_promotion(Square::E5, Square::E7, MoveFlag::NormalMove));
+        assert_eq!(state.get_recent_boards(3), vec![Board::initial()]);
+    }
+
+    #[test]
+    fn test_repetition_count() {
+        let mut state = State::initial();
+        let knight_moves = ["g1f3", "g8f6", "f3g1", "f6g8"];
+        let mut counts = Vec::new();
+        for uci in knight_moves.iter().chain(knight_moves.iter()) {
+            state.make_move(Move::from_uci(&state, uci).unwrap());
+            counts.push(state.repetition_count());
+        }
+        assert_eq!(counts, [0, 0, 0, 1, 1, 1, 1, 2]);
+        assert_eq!(state.termination, None);
+
+        // a pawn move makes earlier positions unreachable
+        let e4 = Move::new_non_promotion(Square::E4, Square::E2, MoveFlag::NormalMove);
+        state.make_move(e4);
+        assert_eq!(state.repetition_count(), 0);
+        state.unmake_move(e4);
+        assert_eq!(state.repetition_count(), 2);
+    }
+
+    #[test]
+    fn test_threefold_repetition() {
+        let mut state = State::from_fen("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1").unwrap();
+        let shuffle = ["e8d8", "e1d1", "d8e8", "d1e1"];
+        state.make_move(Move::from_uci(&state, "e2e3").unwrap());
+        assert_eq!(state.get_repetition_history(), [state.get_zobrist_hash()]);
+        for uci in shuffle.iter().cycle().take(8) {
+            assert!(!state.is_threefold_repetition());
+            state.make_move(Move::from_uci(&state, uci).unwrap());
+        }
+        assert!(state.is_threefold_repetition() && !state.is_fivefold_repetition());
+        let history = state.get_repetition_history();
+        assert_eq!(history.len(), 9);
+        assert_eq!(history.iter().filter(|hash| **hash == state.get_zobrist_hash()).count(), 3);
+
+        state.assume_and_update_termination();
+        assert_eq!(state.termination, Some(Termination::ThreefoldRepetition));
+    }
+
+    #[test]
+    fn test_has_valid_castling_rights() {
+        assert!(State::initial().has_valid_castling_rights());
+
+        // each right depends on its own rook and the king
+        let cases = [
+            ("r3k2r/8/8/8/8/8/8/R3K1R1 w KQkq - 0 1", 0b1000),
+            ("r3k2r/8/8/8/8/8/8/1R2K2R w KQkq - 0 1", 0b0100),
+            ("r3k1r1/8/8/8/8/8/8/R3K2R w KQkq - 0 1", 0b0010),
+            ("1r2k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", 0b0001),
+            ("r3k2r/8/8/8/8/8/8/R4K1R w KQkq - 0 1", 0b1100),
+            ("r4k1r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", 0b0011),
+            // a rook of the wrong color on the starting square does not count
+            ("r3k2r/8/8/8/8/8/8/R3K2r w KQkq - 0 1", 0b1000),
+        ];
+        for (fen, inconsistent_rights) in cases {
+            assert!(State::from_fen(fen).is_err(), "{}", fen);
+            let mut state = State::from_fen_with_castling_repair(fen).unwrap();
+            assert_eq!(state.calc_inconsistent_castling_rights(), 0, "{}", fen);
+            assert_eq!(state.context.borrow().castling_rights, 0b1111 & !inconsistent_rights, "{}", fen);
+            assert_eq!(state.repair_castling_rights(), 0);
+        }
+    }
+
+    #[test]
+    fn test_repair_castling_rights() {
+        let mut state = State::initial();
+        state.board.remove_colored_piece_at(ColoredPiece::WhiteRook, Square::H1);
+        state.board.put_colored_piece_at(ColoredPiece::WhiteRook, Square::H3);
+        assert!(!state.has_valid_castling_rights());
+        assert_eq!(state.calc_inconsistent_castling_rights(), 0b1000);
+
+        assert_eq!(state.repair_castling_rights(), 0b1000);
+        assert!(state.has_valid_castling_rights());
+        assert!(!state.has_castling_rights_short(Color::White));
+        assert!(state.has_castling_rights_long(Color::White));
+    }
+
+    #[test]
+    fn test_repair_castling_rights_after_generating_moves() {
+        let mut state = State::from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        let short_castling = Move::new_non_promotion(Square::G1, Square::E1, MoveFlag::Castling);
+        assert!(state.calc_legal_moves().contains(&short_castling));
+        let original = state.clone();
+
+        state.board.remove_colored_piece_at(ColoredPiece::WhiteRook, Square::H1);
+        state.board.put_colored_piece_at(ColoredPiece::WhiteRook, Square::H3);
+        state.calc_legal_moves();
+        assert_eq!(state.repair_castling_rights(), 0b1000);
+        assert!(!state.calc_legal_moves().contains(&short_castling));
+
+        assert!(original.has_castling_rights_short(Color::White));
+        assert!(original.calc_legal_moves().contains(&short_castling));
+    }
+}
@@ -1,13 +1,16 @@
 //! Contains the Termination enum and its implementation.
 
 /// Represents the different ways a game can end.
+/// Threefold repetition and the fifty-move rule only end the game when claimed, see `DrawClaim`.
 #[derive(Eq, PartialEq, Copy, Clone, Debug)]
 pub enum Termination {
     Checkmate,
     Stalemate,
     InsufficientMaterial,
     ThreefoldRepetition,
-    FiftyMoveRule
+    FiftyMoveRule,
+    FivefoldRepetition,
+    SeventyFiveMoveRule
 }
 
 impl Termination {
@@ -4,7 +4,7 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use crate::r#move::{Move, MoveFlag};
 use crate::state::{Context, State, Termination};
-use crate::utils::{Bitboard, Color, ColoredPiece, PieceType, Square};
+use crate::utils::{record_perf_counter, Color, ColoredPiece, PerfCounter, PieceType, Square};
 use crate::utils::masks::{STARTING_KING_ROOK_GAP_SHORT, STARTING_KING_SIDE_ROOK, STARTING_QUEEN_SIDE_ROOK};
 
 impl State {
@@ -66,6 +66,7 @@ impl State {
     /// This method is used to undo a move that was previously made with `State::make_move`, regardless of
     /// whether the move was legal. However, the move must have been valid (not malformed).
     pub fn unmake_move(&mut self, mv: Move) {
+        record_perf_counter(PerfCounter::UnmakeMove);
         let (dst_square, src_square, promotion, flag) = mv.unpack();
 
         self.board.move_color(self.side_to_move.flip(), src_square, dst_square);
@@ -0,0 +1,119 @@
+//! Validation profiles, choosing how strictly states built from outside input are checked.
+
+use crate::state::{Board, FenParseError, State};
+use crate::state::fen::parse_fen;
+use crate::utils::{Color, ColoredPiece, PieceType};
+use crate::utils::masks::{RANK_1, RANK_8};
+
+/// How thoroughly a state is checked when it is created from outside input, such as a FEN.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ValidationProfile {
+    /// Checks every rule, including that the halfmove clock is plausible and that the material
+    /// could have arisen in a game.
+    #[default]
+    Strict,
+    /// Only checks what the engine needs to work with the position: a consistent board with one king
+    /// of each color, and the side not to move not in check. Castling rights and en passant targets
+    /// that are inconsistent with the board are cleared instead of rejected.
+    Lenient,
+}
+
+impl Board {
+    /// Checks that each side's material could have arisen in a game: at most 8 pawns, none on the
+    /// first or last rank, and no more promoted pieces than missing pawns.
+    pub fn has_possible_material(&self) -> bool {
+        if self.piece_type_masks[PieceType::Pawn as usize] & (RANK_1 | RANK_8) != 0 {
+            return false;
+        }
+        Color::iter().all(|color| {
+            let count = |piece_type: PieceType| self.count_colored_piece(ColoredPiece::from(color, piece_type));
+            let num_pawns = count(PieceType::Pawn);
+            let num_promoted = count(PieceType::Knight).saturating_sub(2)
+                + count(PieceType::Bishop).saturating_sub(2)
+                + count(PieceType::Rook).saturating_sub(2)
+                + count(PieceType::Queen).saturating_sub(1);
+            num_pawns <= 8 && num_promoted <= 8 - num_pawns
+        })
+    }
+}
+
+impl State {
+    /// Checks the state according to `profile`.
+    pub fn is_valid_with_profile(&self, profile: ValidationProfile) -> bool {
+        match profile {
+            ValidationProfile::Strict => {
+                self.is_unequivocally_valid() && self.has_plausible_halfmove_clock() && self.board.has_possible_material()
+            },
+            ValidationProfile::Lenient => {
+                self.board.is_unequivocally_valid() &&
+                    self.has_valid_side_to_move() &&
+                    self.has_valid_castling_rights() &&
+                    self.has_valid_double_pawn_push() &&
+                    self.is_not_in_illegal_check() &&
+                    self.is_zobrist_consistent()
+            }
+        }
+    }
+
+    /// Like `from_fen`, but checks the state according to `profile`.
+    pub fn from_fen_with_profile(fen: &str, profile: ValidationProfile) -> Result<State, FenParseError> {
+        let mut state = parse_fen(fen)?;
+        if profile == ValidationProfile::Strict && !state.has_plausible_halfmove_clock() {
+            return Err(FenParseError::ImplausibleHalfmoveClock(state.context.borrow().halfmove_clock, state.halfmove));
+        }
+        if profile == ValidationProfile::Lenient {
+            state.repair_castling_rights();
+            if !state.has_valid_double_pawn_push() {
+                state.edit_context(|context| context.double_pawn_push = -1);
+            }
+        }
+        if state.is_valid_with_profile(profile) {
+            Ok(state)
+        } else {
+            Err(FenParseError::InvalidState(fen.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_has_possible_material() {
+        let has_possible_material = |fen: &str| State::from_fen_with_profile(fen, ValidationProfile::Lenient).unwrap().board.has_possible_material();
+        assert!(Board::initial().has_possible_material());
+        assert!(has_possible_material("4k3/8/8/8/8/8/8/QQQQKQQQ w - - 0 1"));
+        assert!(!has_possible_material("4k3/P7/8/8/8/8/QQQQQQQQ/3QK3 b - - 0 1"));
+        assert!(!has_possible_material("P3k3/8/8/8/8/8/8/4K3 w - - 0 1"));
+        assert!(!has_possible_material("4k3/pppppppp/p7/8/8/8/8/4K3 w - - 0 1"));
+    }
+
+    #[test]
+    fn test_validation_profiles() {
+        for fen in [
+            "4k3/P7/8/8/8/8/QQQQQQQQ/3QK3 b - - 0 1", // too many promoted pieces
+            "r3k2r/8/8/8/8/8/8/R3K1R1 w KQkq - 0 1", // displaced rook
+            "4k3/8/8/8/8/8/8/4K3 w - e6 0 1", // no pawn to capture en passant
+            "4k3/8/8/8/8/8/8/4K3 w - - 30 1", // halfmove clock exceeding the moves played
+        ] {
+            assert!(State::from_fen_with_profile(fen, ValidationProfile::Strict).is_err(), "{}", fen);
+            assert!(State::from_fen_with_profile(fen, ValidationProfile::Lenient).is_ok(), "{}", fen);
+        }
+
+        let state = State::from_fen_with_profile("r3k2r/8/8/8/8/8/8/R3K1R1 w KQkq - 0 1", ValidationProfile::Lenient).unwrap();
+        assert_eq!(state.to_fen(), "r3k2r/8/8/8/8/8/8/R3K1R1 w Qkq - 0 1");
+        assert_eq!(
+            State::from_fen_with_profile("4k3/8/8/8/8/8/8/4K3 w - - 30 1", ValidationProfile::Strict).err(),
+            Some(FenParseError::ImplausibleHalfmoveClock(30, 0))
+        );
+        let state = State::from_fen_with_profile("4k3/8/8/8/8/8/8/4K3 w - e6 0 1", ValidationProfile::Lenient).unwrap();
+        assert_eq!(state.to_fen(), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+
+        // structural problems are rejected by both profiles
+        for fen in ["8/8/8/8/8/8/8/4K3 w - - 0 1", "4k3/8/8/8/8/8/8/4R1K1 w - - 0 1"] {
+            assert!(State::from_fen_with_profile(fen, ValidationProfile::Lenient).is_err(), "{}", fen);
+        }
+        assert!(State::from_fen_with_profile(crate::state::INITIAL_FEN, ValidationProfile::Strict).is_ok());
+    }
+}
@@ -0,0 +1,371 @@
+//! All Zobrist hashing-related code.
+
+#[cfg(test)]
+use std::cell::RefCell;
+use static_init::dynamic;
+use crate::utils::{get_squares_from_mask_iter, Bitboard};
+use crate::utils::{Color, PieceType, Square};
+use crate::state::board::Board;
+use crate::state::State;
+
+/// Version of the internal Zobrist key set.
+/// The keys are generated deterministically, so hashes are stable across runs and builds;
+/// this must be bumped whenever the keys or the way they are combined change,
+/// so that caches keyed by Zobrist hashes can be invalidated.
+/// For a hash that is compatible with other software, see `State::polyglot_hash`.
+pub const ZOBRIST_KEYS_VERSION: u32 = 2;
+
+/// Seed of the generator used to produce the Zobrist keys.
+const ZOBRIST_SEED: u64 = 0x64_75_6E_63_6B_5A_4F_42;
+
+/// The keys used to hash boards.
+#[dynamic]
+static ZOBRIST_TABLE: ZobristKeyTable = ZobristKeyTable::from_seed(ZOBRIST_SEED);
+
+#[cfg(test)]
+thread_local! {
+    /// Keys that replace `ZOBRIST_TABLE` on this thread, see `with_test_zobrist_keys`.
+    static TEST_ZOBRIST_TABLE: RefCell<Option<ZobristKeyTable>> = const { RefCell::new(None) };
+}
+
+/// A set of Zobrist keys, with one key for each piece type on each square,
+/// and keys for the side to move, each castling right and each en passant file.
+/// A board's hash is the xor of the keys of all its pieces.
+/// A state's hash is its board's hash xor the keys of the rest of the position.
+pub trait ZobristKeys {
+    /// Returns the key for `piece_type` on `square`. `piece_type` must not be `PieceType::NoPieceType`.
+    fn get_piece_key(&self, square: Square, piece_type: PieceType) -> Bitboard;
+
+    /// Returns the key that is applied when black is to move.
+    fn get_side_to_move_key(&self) -> Bitboard;
+
+    /// Returns the key for a castling right, indexed in the order wk, wq, bk, bq.
+    fn get_castling_key(&self, index: usize) -> Bitboard;
+
+    /// Returns the key for an en passant capture on `file`.
+    fn get_en_passant_key(&self, file: u8) -> Bitboard;
+}
+
+/// A table of Zobrist keys for each piece type on each square and for the rest of the position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZobristKeyTable {
+    keys: [[Bitboard; 12]; 64],
+    side_to_move_key: Bitboard,
+    castling_keys: [Bitboard; 4],
+    en_passant_keys: [Bitboard; 8],
+}
+
+impl ZobristKeyTable {
+    /// Returns the keys that boards are hashed with.
+    pub fn get_default() -> &'static ZobristKeyTable {
+        &ZOBRIST_TABLE
+    }
+
+    /// Generates pseudorandom keys from `seed`. The same seed always gives the same keys.
+    pub fn from_seed(seed: u64) -> ZobristKeyTable {
+        let mut rng_state = seed;
+        let mut keys = [[0; 12]; 64];
+        for square_keys in keys.iter_mut() {
+            for key in square_keys.iter_mut() {
+                *key = next_splitmix64(&mut rng_state);
+            }
+        }
+        // generated after the piece keys, so that board hashes are the same as before these keys existed
+        let side_to_move_key = next_splitmix64(&mut rng_state);
+        let castling_keys = std::array::from_fn(|_| next_splitmix64(&mut rng_state));
+        let en_passant_keys = std::array::from_fn(|_| next_splitmix64(&mut rng_state));
+        ZobristKeyTable { keys, side_to_move_key, castling_keys, en_passant_keys }
+    }
+
+    /// Builds a table by calling `f` for each square and piece type, in that order.
+    /// The keys for the rest of the position are zero, see `with_state_keys`.
+    /// Useful for small, readable keys in tests.
+    pub fn from_fn(mut f: impl FnMut(Square, PieceType) -> Bitboard) -> ZobristKeyTable {
+        let mut keys = [[0; 12]; 64];
+        for square in Square::iter_all() {
+            for piece_type in PieceType::iter_pieces() {
+                keys[*square as usize][*piece_type as usize - 1] = f(*square, *piece_type);
+            }
+        }
+        ZobristKeyTable { keys, side_to_move_key: 0, castling_keys: [0; 4], en_passant_keys: [0; 8] }
+    }
+
+    /// Sets the keys for the side to move, the castling rights (wk, wq, bk, bq) and the en passant files.
+    pub fn with_state_keys(mut self, side_to_move_key: Bitboard, castling_keys: [Bitboard; 4], en_passant_keys: [Bitboard; 8]) -> Self {
+        self.side_to_move_key = side_to_move_key;
+        self.castling_keys = castling_keys;
+        self.en_passant_keys = en_passant_keys;
+        self
+    }
+}
+
+impl ZobristKeys for ZobristKeyTable {
+    fn get_piece_key(&self, square: Square, piece_type: PieceType) -> Bitboard {
+        self.keys[square as usize][piece_type as usize - 1]
+    }
+
+    fn get_side_to_move_key(&self) -> Bitboard {
+        self.side_to_move_key
+    }
+
+    fn get_castling_key(&self, index: usize) -> Bitboard {
+        self.castling_keys[index]
+    }
+
+    fn get_en_passant_key(&self, file: u8) -> Bitboard {
+        self.en_passant_keys[file as usize]
+    }
+}
+
+/// Runs `f` with boards on this thread hashed by `keys` instead of the default keys.
+/// States must be created inside `f` for their hashes to use `keys`.
+#[cfg(test)]
+pub(crate) fn with_test_zobrist_keys<R>(keys: ZobristKeyTable, f: impl FnOnce() -> R) -> R {
+    let previous_keys = TEST_ZOBRIST_TABLE.with(|table| table.replace(Some(keys)));
+    let result = f();
+    TEST_ZOBRIST_TABLE.with(|table| table.replace(previous_keys));
+    result
+}
+
+/// Returns the next output of a SplitMix64 generator with state `state`.
+/// Used instead of `rand` so that the keys do not depend on an external crate's algorithm.
+fn next_splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+/// Gets the Zobrist hash for a piece on a square.
+pub(crate) fn get_piece_zobrist_hash(square: Square, piece_type: PieceType) -> Bitboard {
+    #[cfg(test)]
+    if let Some(key) = TEST_ZOBRIST_TABLE.with(|table| table.borrow().as_ref().map(|keys| keys.get_piece_key(square, piece_type))) {
+        return key;
+    }
+    ZOBRIST_TABLE.get_piece_key(square, piece_type)
+}
+
+/// Gets the part of a state's Zobrist hash that does not come from the pieces on the board.
+/// The en passant file only counts if a pawn of the side to move is next to the pawn that was pushed,
+/// so that positions where en passant is impossible are not told apart for repetitions.
+pub(crate) fn get_state_zobrist_hash(board: &Board, side_to_move: Color, castling_rights: u8, double_pawn_push: i8) -> Bitboard {
+    #[cfg(test)]
+    if let Some(hash) = TEST_ZOBRIST_TABLE.with(|table| table.borrow().as_ref().map(|keys| {
+        calc_state_zobrist_hash_with_keys(keys, board, side_to_move, castling_rights, double_pawn_push)
+    })) {
+        return hash;
+    }
+    calc_state_zobrist_hash_with_keys(&*ZOBRIST_TABLE, board, side_to_move, castling_rights, double_pawn_push)
+}
+
+fn calc_state_zobrist_hash_with_keys(keys: &impl ZobristKeys, board: &Board, side_to_move: Color, castling_rights: u8, double_pawn_push: i8) -> Bitboard {
+    let mut hash = 0;
+    if side_to_move == Color::Black {
+        hash ^= keys.get_side_to_move_key();
+    }
+    for i in 0..4 {
+        if castling_rights & (0b1000 >> i) != 0 {
+            hash ^= keys.get_castling_key(i);
+        }
+    }
+    if double_pawn_push != -1 && board.has_en_passant_capturer(side_to_move, double_pawn_push as u8) {
+        hash ^= keys.get_en_passant_key(double_pawn_push as u8);
+    }
+    hash
+}
+
+impl Board {
+    /// Calculates the Zobrist hash scratch.
+    pub fn calc_zobrist_hash(&self) -> Bitboard {
+        self.calc_zobrist_hash_with(get_piece_zobrist_hash)
+    }
+
+    /// Calculates the Zobrist hash from scratch with the given keys.
+    pub fn calc_zobrist_hash_with_keys(&self, keys: &impl ZobristKeys) -> Bitboard {
+        self.calc_zobrist_hash_with(|square, piece_type| keys.get_piece_key(square, piece_type))
+    }
+
+    fn calc_zobrist_hash_with(&self, get_key: impl Fn(Square, PieceType) -> Bitboard) -> Bitboard {
+        let mut hash: Bitboard = 0;
+        for piece_type in PieceType::iter_pieces() { // skip PieceType::NoPieceType
+            let pieces_mask = self.piece_type_masks[*piece_type as usize];
+            for square in get_squares_from_mask_iter(pieces_mask) {
+                hash ^= get_key(square, *piece_type);
+            }
+        }
+        hash
+    }
+    
+    /// Applies the xor of the Zobrist hash of a piece on a square
+    pub fn xor_piece_zobrist_hash(&mut self, square: Square, piece_type: PieceType) {
+        self.zobrist_hash ^= get_piece_zobrist_hash(square, piece_type)
+    }
+}
+
+impl State {
+    /// Gets the Zobrist hash of the whole position, covering the board, the side to move,
+    /// the castling rights and the en passant file. It is updated incrementally by `make_move` and `unmake_move`.
+    pub fn get_zobrist_hash(&self) -> Bitboard {
+        self.context.borrow().zobrist_hash
+    }
+
+    /// Calculates the Zobrist hash of the whole position from scratch.
+    pub fn calc_zobrist_hash(&self) -> Bitboard {
+        self.board.calc_zobrist_hash() ^ self.calc_state_zobrist_hash()
+    }
+
+    /// Calculates the Zobrist hash of the whole position from scratch with the given keys.
+    pub fn calc_zobrist_hash_with_keys(&self, keys: &impl ZobristKeys) -> Bitboard {
+        let context = self.context.borrow();
+        self.board.calc_zobrist_hash_with_keys(keys) ^
+            calc_state_zobrist_hash_with_keys(keys, &self.board, self.side_to_move, context.castling_rights, context.double_pawn_push)
+    }
+
+    /// Calculates the part of the Zobrist hash that does not come from the pieces on the board.
+    pub(crate) fn calc_state_zobrist_hash(&self) -> Bitboard {
+        let context = self.context.borrow();
+        get_state_zobrist_hash(&self.board, self.side_to_move, context.castling_rights, context.double_pawn_push)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::{Rng, SeedableRng};
+    use crate::state::State;
+    use super::*;
+
+    const INITIAL_ZOBRIST_HASH: Bitboard = 0x91FE2EA053C57DDE;
+    const INITIAL_STATE_ZOBRIST_HASH: Bitboard = 0x78D3F98E3C09099D;
+
+    const FENS: [&str; 4] = [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    ];
+
+    /// Keys with a single bit set per piece type and square color, so hashes are easy to read when a test fails.
+    /// The other keys each have a bit of their own, above those of the pieces.
+    fn get_small_keys() -> ZobristKeyTable {
+        ZobristKeyTable::from_fn(|square, piece_type| {
+            let square_color = (square as u8 / 8 + square as u8 % 8) % 2;
+            1 << ((piece_type as u8 - 1) * 2 + square_color)
+        }).with_state_keys(1 << 12, std::array::from_fn(|i| 1 << (13 + i)), std::array::from_fn(|file| 1 << (17 + file)))
+    }
+
+    /// Plays random moves from each position in `FENS`, occasionally unmaking some,
+    /// and checks after every step that the incremental hash equals the hash recomputed from scratch.
+    fn assert_incremental_hashes_match(seed: u64) {
+        let mut rng = StdRng::seed_from_u64(seed);
+        for fen in FENS {
+            let mut state = State::from_fen(fen).unwrap();
+            let mut history = Vec::new();
+            for _ in 0..200 {
+                let moves = state.calc_legal_moves();
+                let should_unmake = !history.is_empty() && (moves.is_empty() || rng.gen_bool(0.3));
+                if should_unmake {
+                    let (mv, hashes_before) = history.pop().unwrap();
+                    state.unmake_move(mv);
+                    assert_eq!((state.board.zobrist_hash, state.get_zobrist_hash()), hashes_before);
+                }
+                else if let Some(mv) = moves.choose(&mut rng) {
+                    history.push((*mv, (state.board.zobrist_hash, state.get_zobrist_hash())));
+                    state.make_move(*mv);
+                }
+                else {
+                    break;
+                }
+                assert_eq!(state.board.zobrist_hash, state.board.calc_zobrist_hash(), "{}", state.to_fen());
+                assert_eq!(state.get_zobrist_hash(), state.calc_zobrist_hash(), "{}", state.to_fen());
+                assert!(state.is_zobrist_consistent());
+            }
+        }
+    }
+
+    #[test]
+    fn test_zobrist_hash() {
+        let state = State::from_fen(FENS[1]).unwrap();
+        assert_eq!(state.board.zobrist_hash, state.board.calc_zobrist_hash());
+        assert_eq!(state.board.calc_zobrist_hash_with_keys(ZobristKeyTable::get_default()), state.board.calc_zobrist_hash());
+        assert_ne!(state.board.calc_zobrist_hash_with_keys(&ZobristKeyTable::from_seed(1)), state.board.calc_zobrist_hash());
+
+        // the pawns are all on light squares, so two of their keys cancel out
+        let board = State::from_fen("4k3/8/8/8/8/8/P1P1P3/4K3 w - - 0 1").unwrap().board;
+        let king_keys = (1 << 10) | (1 << 11);
+        assert_eq!(board.calc_zobrist_hash_with_keys(&get_small_keys()) ^ king_keys, 1);
+    }
+
+    #[test]
+    fn test_state_zobrist_hash() {
+        let get_hash = |fen: &str| State::from_fen(fen).unwrap().get_zobrist_hash();
+        let hash = get_hash("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq d6 0 2");
+        assert_ne!(hash, get_hash("r3k2r/8/8/3pP3/8/8/8/R3K2R b KQkq - 0 2"));
+        assert_ne!(hash, get_hash("r3k2r/8/8/3pP3/8/8/8/R3K2R w Kkq d6 0 2"));
+        assert_ne!(hash, get_hash("r3k2r/8/8/3pP3/8/8/8/R3K2R w KQkq - 0 2"));
+        // en passant is impossible, so the file makes no difference
+        assert_eq!(get_hash("4k3/8/8/3p4/8/8/8/4K3 w - d6 0 2"), get_hash("4k3/8/8/3p4/8/8/8/4K3 w - - 0 2"));
+
+        with_test_zobrist_keys(get_small_keys(), || {
+            let state = State::from_fen("4k3/8/8/3pP3/8/8/8/4K2R w K d6 0 2").unwrap();
+            assert_eq!(state.get_zobrist_hash() ^ state.board.zobrist_hash, (1 << 13) | (1 << (17 + 3)));
+            assert_eq!(state.get_zobrist_hash(), state.calc_zobrist_hash_with_keys(&get_small_keys()));
+        });
+    }
+
+    #[test]
+    fn test_transpositions_have_same_hash() {
+        let play = |ucis: &[&str]| {
+            let mut state = State::initial();
+            for uci in ucis {
+                state.make_move(crate::r#move::Move::from_uci(&state, uci).unwrap());
+            }
+            state.get_zobrist_hash()
+        };
+        assert_eq!(play(&["g1f3", "g8f6", "b1c3"]), play(&["b1c3", "g8f6", "g1f3"]));
+        // the same pieces, but a different side to move
+        assert_ne!(play(&["g1f3", "g8f6", "f3g1"]), play(&["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6"]));
+        // the same pieces, but white has lost the right to castle
+        assert_ne!(play(&["e2e4", "e7e5", "e1e2", "e8e7", "e2e1", "e7e8"]), play(&["e2e4", "e7e5"]));
+    }
+
+    #[test]
+    fn test_incremental_hash_matches_recomputation() {
+        for seed in 0..8 {
+            assert_incremental_hashes_match(seed);
+        }
+    }
+
+    #[test]
+    fn test_incremental_hash_with_small_keys() {
+        with_test_zobrist_keys(get_small_keys(), || {
+            assert_eq!(State::initial().board.zobrist_hash, State::initial().board.calc_zobrist_hash_with_keys(&get_small_keys()));
+            for seed in 0..8 {
+                assert_incremental_hashes_match(seed);
+            }
+        });
+        assert_eq!(State::initial().board.zobrist_hash, INITIAL_ZOBRIST_HASH);
+    }
+
+    #[test]
+    fn test_zobrist_keys_are_stable() {
+        assert_eq!(ZobristKeyTable::from_seed(ZOBRIST_SEED), ZobristKeyTable::from_seed(ZOBRIST_SEED));
+        assert_eq!(ZobristKeyTable::get_default(), &ZobristKeyTable::from_seed(ZOBRIST_SEED));
+        // changing the keys requires bumping ZOBRIST_KEYS_VERSION and updating these values
+        assert_eq!(ZOBRIST_KEYS_VERSION, 2);
+        assert_eq!(Board::initial().calc_zobrist_hash(), INITIAL_ZOBRIST_HASH);
+        assert_eq!(State::initial().get_zobrist_hash(), INITIAL_STATE_ZOBRIST_HASH);
+    }
+
+    #[test]
+    fn test_increment_position_count() {
+        // todo
+    }
+
+    #[test]
+    fn test_decrement_position_count() {
+        // todo
+    }
+}
\ No newline at end of file
@@ -0,0 +1,267 @@
+use crate::utils::bitboard::Bitboard;
+use crate::state::Board;
+use crate::utils::{ColoredPiece, PieceLocale, Square};
+
+pub type Charboard = [[char; 8]; 8];
+
+pub const EMPTY_CHARBOARD: Charboard = [[' '; 8]; 8];
+
+pub const INITIAL_CHARBOARD: Charboard = [
+    ['r', 'n', 'b', 'q', 'k', 'b', 'n', 'r'],
+    ['p', 'p', 'p', 'p', 'p', 'p', 'p', 'p'],
+    [' '; 8],
+    [' '; 8],
+    [' '; 8],
+    [' '; 8],
+    ['P', 'P', 'P', 'P', 'P', 'P', 'P', 'P'],
+    ['R', 'N', 'B', 'Q', 'K', 'B', 'N', 'R']
+];
+
+pub const INITIAL_CHARBOARD_PRETTY: Charboard = [
+    ['♜', '♞', '♝', '♛', '♚', '♝', '♞', '♜'],
+    ['♟', '♟', '♟', '♟', '♟', '♟', '♟', '♟'],
+    [' '; 8],
+    [' '; 8],
+    [' '; 8],
+    [' '; 8],
+    ['♙', '♙', '♙', '♙', '♙', '♙', '♙', '♙'],
+    ['♖', '♘', '♗', '♕', '♔', '♗', '♘', '♖']
+];
+
+pub const SQUARE_NAMES: [&str; 64] = [
+    "a8", "b8", "c8", "d8", "e8", "f8", "g8", "h8",
+    "a7", "b7", "c7", "d7", "e7", "f7", "g7", "h7",
+    "a6", "b6", "c6", "d6", "e6", "f6", "g6", "h6",
+    "a5", "b5", "c5", "d5", "e5", "f5", "g5", "h5",
+    "a4", "b4", "c4", "d4", "e4", "f4", "g4", "h4",
+    "a3", "b3", "c3", "d3", "e3", "f3", "g3", "h3",
+    "a2", "b2", "c2", "d2", "e2", "f2", "g2", "h2",
+    "a1", "b1", "c1", "d1", "e1", "f1", "g1", "h1"
+];
+
+pub const COLORED_PIECE_CHARS: [char; 12] = [
+    'P', 'N', 'B', 'R', 'Q', 'K',
+    'p', 'n', 'b', 'r', 'q', 'k'
+];
+
+pub const COLORED_PIECE_CHARS_PRETTY: [char; 12] = [
+    '♙', '♘', '♗', '♖', '♕', '♔',
+    '♟', '♞', '♝', '♜', '♛', '♚'
+];
+
+pub fn cb_to_bb(cb: &Charboard) -> Bitboard {
+    let mut bb: Bitboard = 0;
+    for i in 0..8 {
+        for j in 0..8 {
+            if cb[i][j] != ' ' {
+                bb |= 1 << (63 - (i * 8 + j));
+            }
+        }
+    }
+    bb
+}
+
+pub fn bb_to_cb(mut bb: Bitboard) -> Charboard {
+    let mut cb: Charboard = [[' '; 8]; 8];
+    for i in 0..8 {
+        for j in 0..8 {
+            if bb & 1 != 0 {
+                cb[7 - i][7 - j] = 'X';
+            }
+            bb >>= 1;
+        }
+    }
+    cb
+}
+
+pub fn print_bb(bb: Bitboard) {
+    for i in 0..8 {
+        let shift_amt = 8 * (7 - i);
+        println!("{:08b}", (bb & (0xFF << shift_amt)) >> shift_amt);
+    }
+}
+
+pub fn print_bb_pretty(bb: Bitboard) {
+    print_cb(&bb_to_cb(bb));
+}
+
+pub fn cb_to_string(cb: &Charboard) -> String {
+    let mut res = String::new();
+    for i in 0..8u8 {
+        res += &*format!("{} ", 8 - i);
+        for j in 0..8u8 {
+            if cb[i as usize][j as usize] == ' ' {
+                res.push('.');
+            }
+            else {
+                res.push(cb[i as usize][j as usize])
+            }
+            res.push(' ');
+        }
+        res.push('\n')
+    }
+    res + "  a b c d e f g h"
+}
+
+pub fn print_cb(cb: &Charboard) {
+    println!("{}", cb_to_string(cb));
+}
+
+/// Returns the ASCII piece letter for `c`, which may be a piece letter or a Unicode chess symbol.
+fn normalize_piece_char(c: char) -> Option<char> {
+    if COLORED_PIECE_CHARS.contains(&c) {
+        return Some(c);
+    }
+    COLORED_PIECE_CHARS_PRETTY.iter().position(|pretty_char| *pretty_char == c).map(|i| COLORED_PIECE_CHARS[i])
+}
+
+/// Parses a diagram with one line per rank, from the 8th rank down, into a charboard of ASCII piece letters.
+/// Accepts the output of `cb_to_string` and `Board`'s `Display`, as well as lichess-style diagrams
+/// with `+---+` borders and `|` separators. Pieces may be letters or Unicode symbols and empty squares are `.`.
+/// Rank labels, file labels and lines without squares are ignored.
+pub fn cb_from_string(s: &str) -> Result<Charboard, String> {
+    let mut cb = EMPTY_CHARBOARD;
+    let mut num_ranks = 0;
+    for line in s.lines() {
+        let is_file_labels = line.chars().filter(|c| !c.is_whitespace()).eq("abcdefgh".chars());
+        if is_file_labels {
+            continue;
+        }
+
+        let row: Vec<char> = line.chars()
+            .filter_map(|c| if c == '.' { Some(' ') } else { normalize_piece_char(c) })
+            .collect();
+        if row.is_empty() {
+            continue;
+        }
+        if row.len() != 8 {
+            return Err(format!("Expected 8 squares, found {}: {}", row.len(), line));
+        }
+        if num_ranks == 8 {
+            return Err(format!("Too many ranks: {}", line));
+        }
+        cb[num_ranks].copy_from_slice(&row);
+        num_ranks += 1;
+    }
+
+    if num_ranks != 8 {
+        return Err(format!("Expected 8 ranks, found {}", num_ranks));
+    }
+    Ok(cb)
+}
+
+impl Board {
+    /// Creates a board from a charboard of ASCII piece letters, with anything else treated as an empty square.
+    pub fn from_cb(cb: &Charboard) -> Board {
+        let mut board = Board::blank();
+        for (i, square) in Square::iter_all().enumerate() {
+            let colored_piece = ColoredPiece::from_char(cb[i / 8][i % 8]);
+            if colored_piece != ColoredPiece::NoPiece {
+                board.put_colored_piece_at(colored_piece, *square);
+            }
+        }
+        board
+    }
+
+    /// Parses a board from a diagram, in any format accepted by `cb_from_string`,
+    /// e.g. the output of `print`.
+    pub fn from_ascii(diagram: &str) -> Result<Board, String> {
+        cb_from_string(diagram).map(|cb| Board::from_cb(&cb))
+    }
+
+    pub fn to_cb(&self) -> Charboard {
+        let mut cb: Charboard = [[' '; 8]; 8];
+        for (i, square) in Square::iter_all().enumerate() {
+            let colored_piece = self.get_colored_piece_at(*square);
+            cb[i / 8][i % 8] = colored_piece.to_char();
+        }
+        cb
+    }
+
+    pub fn to_cb_pretty(&self) -> Charboard {
+        let mut cb: Charboard = [[' '; 8]; 8];
+        for (i, square) in Square::iter_all().enumerate() {
+            let colored_piece = self.get_colored_piece_at(*square);
+            cb[i / 8][i % 8] = colored_piece.to_char_pretty();
+        }
+        cb
+    }
+
+    /// Like `to_cb`, with the piece letters of `locale`. The result can only be parsed back for `English` and `Figurine`.
+    pub fn to_cb_localized(&self, locale: PieceLocale) -> Charboard {
+        let mut cb: Charboard = [[' '; 8]; 8];
+        for (i, square) in Square::iter_all().enumerate() {
+            let colored_piece = self.get_colored_piece_at(*square);
+            cb[i / 8][i % 8] = locale.get_colored_piece_char(colored_piece);
+        }
+        cb
+    }
+
+    /// Returns the board as a diagram like `Display`, with the piece letters of `locale`.
+    pub fn to_string_localized(&self, locale: PieceLocale) -> String {
+        cb_to_string(&self.to_cb_localized(locale))
+    }
+}
+
+impl std::fmt::Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", cb_to_string(&self.to_cb_pretty()).as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+
+    #[test]
+    fn test_board_from_ascii_round_trip() {
+        let board = Board::initial();
+        assert_eq!(Board::from_ascii(&board.to_string()), Ok(board.clone()));
+        assert_eq!(cb_from_string(&cb_to_string(&INITIAL_CHARBOARD)), Ok(INITIAL_CHARBOARD));
+
+        let board = State::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap().board;
+        let parsed_board = Board::from_ascii(&board.to_string()).unwrap();
+        assert_eq!(parsed_board, board);
+        assert!(parsed_board.is_unequivocally_valid());
+    }
+
+    #[test]
+    fn test_board_from_lichess_ascii() {
+        let diagram = "
+   +------------------------+
+ 8 | r  n  b  q  k  b  n  r |
+ 7 | p  p  p  p  .  p  p  p |
+ 6 | .  .  .  .  .  .  .  . |
+ 5 | .  .  .  .  p  .  .  . |
+ 4 | .  .  .  .  P  .  .  . |
+ 3 | .  .  .  .  .  .  .  . |
+ 2 | P  P  P  P  .  P  P  P |
+ 1 | R  N  B  Q  K  B  N  R |
+   +------------------------+
+     a  b  c  d  e  f  g  h";
+        let expected = State::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2").unwrap().board;
+        assert_eq!(Board::from_ascii(diagram), Ok(expected));
+    }
+
+    #[test]
+    fn test_board_from_invalid_ascii() {
+        assert!(Board::from_ascii("").is_err());
+        assert!(Board::from_ascii("r n b q k b n r").is_err());
+        let missing_square = cb_to_string(&INITIAL_CHARBOARD).replacen(". ", "", 1);
+        assert!(Board::from_ascii(&missing_square).is_err());
+        let extra_rank = format!("p p p p p p p p\n{}", cb_to_string(&INITIAL_CHARBOARD));
+        assert!(Board::from_ascii(&extra_rank).is_err());
+    }
+
+    #[test]
+    fn test_board_to_string_localized() {
+        let board = Board::initial();
+        assert_eq!(board.to_cb_localized(PieceLocale::English), INITIAL_CHARBOARD);
+        assert_eq!(board.to_cb_localized(PieceLocale::Figurine), INITIAL_CHARBOARD_PRETTY);
+        assert_eq!(board.to_string_localized(PieceLocale::Figurine), board.to_string());
+        let diagram = board.to_string_localized(PieceLocale::German);
+        assert!(diagram.starts_with("8 t s l d k l s t \n7 b b b b b b b b \n"), "{}", diagram);
+        assert!(diagram.contains("1 T S L D K L S T \n"), "{}", diagram);
+    }
+}
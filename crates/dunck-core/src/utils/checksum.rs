@@ -0,0 +1,64 @@
+//! A checksum for fingerprinting data such as model files and lookup tables.
+//! Unlike `DefaultHasher`, its output is fixed by its definition, so it can be compared across builds and machines.
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+/// The 64-bit FNV-1a hash of everything fed into it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checksum {
+    hash: u64,
+}
+
+impl Default for Checksum {
+    fn default() -> Self {
+        Checksum { hash: FNV_OFFSET_BASIS }
+    }
+}
+
+impl Checksum {
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    /// Feeds `value` in little-endian byte order, so that the checksum is the same on every platform.
+    pub fn update_u64(&mut self, value: u64) {
+        self.update(&value.to_le_bytes());
+    }
+
+    pub fn get_value(&self) -> u64 {
+        self.hash
+    }
+
+    /// Returns the checksum as 16 lowercase hexadecimal digits.
+    pub fn to_hex(&self) -> String {
+        format!("{:016x}", self.hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        let checksum_of = |bytes: &[u8]| {
+            let mut checksum = Checksum::default();
+            checksum.update(bytes);
+            checksum
+        };
+        assert_eq!(checksum_of(b"").get_value(), 0xcbf29ce484222325);
+        assert_eq!(checksum_of(b"a").get_value(), 0xaf63dc4c8601ec8c);
+        assert_eq!(checksum_of(b"foobar").to_hex(), "85944171f73967e8");
+
+        let mut checksum = checksum_of(b"foo");
+        checksum.update(b"bar");
+        assert_eq!(checksum, checksum_of(b"foobar"));
+        let mut checksum = Checksum::default();
+        checksum.update_u64(1);
+        assert_eq!(checksum, checksum_of(&[1, 0, 0, 0, 0, 0, 0, 0]));
+    }
+}
@@ -0,0 +1,76 @@
+use std::fmt::Display;
+use std::str::FromStr;
+use crate::utils::EnumParseError;
+
+#[repr(u8)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Color {
+    White=0, Black=1
+}
+
+impl Color {
+    pub const ALL: [Color; 2] = [Color::White, Color::Black];
+
+    pub const fn from(is_black: bool) -> Color {
+        unsafe { std::mem::transmute::<bool, Color>(is_black) }
+    }
+
+    pub const fn flip(&self) -> Color {
+        unsafe { std::mem::transmute::<u8, Color>(*self as u8 ^ 1) }
+    }
+
+    pub fn iter() -> impl Iterator<Item = Color> {
+        Color::ALL.iter().copied()
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Color::White => "white",
+            Color::Black => "black"
+        }
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Color {
+    type Err = EnumParseError;
+
+    /// Parses "white"/"black" (any case) or the FEN side to move "w"/"b".
+    fn from_str(s: &str) -> Result<Color, EnumParseError> {
+        match s.to_ascii_lowercase().as_str() {
+            "white" | "w" => Ok(Color::White),
+            "black" | "b" => Ok(Color::Black),
+            _ => Err(EnumParseError::new("color", s))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_color() {
+        assert_eq!(Color::White as u8, 0);
+        assert_eq!(Color::Black as u8, 1);
+        assert_eq!(Color::White.flip(), Color::Black);
+        assert_eq!(Color::Black.flip(), Color::White);
+        assert_eq!(Color::from(false), Color::White);
+        assert_eq!(Color::from(true), Color::Black);
+    }
+
+    #[test]
+    fn test_color_strings() {
+        for color in Color::ALL {
+            assert_eq!(color.to_string().parse::<Color>(), Ok(color));
+        }
+        assert_eq!("White".parse::<Color>(), Ok(Color::White));
+        assert_eq!("b".parse::<Color>(), Ok(Color::Black));
+        assert_eq!("red".parse::<Color>(), Err(EnumParseError::new("color", "red")));
+    }
+}
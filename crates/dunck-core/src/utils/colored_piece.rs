@@ -45,6 +45,25 @@ impl ColoredPiece {
         }
     }
 
+    /// Like `from_char`, for the Unicode chess symbols returned by `to_char_pretty`.
+    pub const fn from_char_pretty(c: char) -> ColoredPiece {
+        match c {
+            '♙' => ColoredPiece::WhitePawn,
+            '♘' => ColoredPiece::WhiteKnight,
+            '♗' => ColoredPiece::WhiteBishop,
+            '♖' => ColoredPiece::WhiteRook,
+            '♕' => ColoredPiece::WhiteQueen,
+            '♔' => ColoredPiece::WhiteKing,
+            '♟' => ColoredPiece::BlackPawn,
+            '♞' => ColoredPiece::BlackKnight,
+            '♝' => ColoredPiece::BlackBishop,
+            '♜' => ColoredPiece::BlackRook,
+            '♛' => ColoredPiece::BlackQueen,
+            '♚' => ColoredPiece::BlackKing,
+            _ => ColoredPiece::NoPiece
+        }
+    }
+
     pub const fn to_char(&self) -> char {
         match self {
             ColoredPiece::NoPiece => ' ',
@@ -115,5 +134,7 @@ mod tests {
         assert_eq!(ColoredPiece::WhitePawn.to_char_pretty(), '♙');
         assert_eq!(ColoredPiece::BlackPawn.to_char_pretty(), '♟');
         assert_eq!(ColoredPiece::NoPiece.to_char_pretty(), ' ');
+        assert_eq!(ColoredPiece::from_char_pretty('♞'), ColoredPiece::BlackKnight);
+        assert_eq!(ColoredPiece::from_char_pretty('N'), ColoredPiece::NoPiece);
     }
 }
\ No newline at end of file
@@ -0,0 +1,23 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// The error returned when a string does not name a `Color`, `PieceType` or `Square`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct EnumParseError {
+    pub type_name: &'static str,
+    pub input: String,
+}
+
+impl EnumParseError {
+    pub fn new(type_name: &'static str, input: &str) -> EnumParseError {
+        EnumParseError { type_name, input: input.to_string() }
+    }
+}
+
+impl Display for EnumParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid {}: {}", self.type_name, self.input)
+    }
+}
+
+impl Error for EnumParseError {}
@@ -6,10 +6,18 @@ mod bitboard;
 pub mod charboard;
 pub mod masks;
 mod move_direction;
+mod perf_counters;
+mod enum_parse_error;
+mod checksum;
+mod piece_locale;
 
 pub use square::*;
 pub use color::*;
 pub use piece_type::*;
 pub use colored_piece::*;
 pub use bitboard::*;
-pub use move_direction::*;
\ No newline at end of file
+pub use move_direction::*;
+pub use perf_counters::*;
+pub use enum_parse_error::*;
+pub use checksum::*;
+pub use piece_locale::*;
\ No newline at end of file
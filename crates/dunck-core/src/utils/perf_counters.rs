@@ -0,0 +1,124 @@
+//! Global counters for board operations, compiled in only with the `perf-counters` feature.
+//! Without the feature, recording is a no-op and snapshots are always zero.
+
+#[cfg(feature = "perf-counters")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// The board operations that are counted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum PerfCounter {
+    MakeMove,
+    UnmakeMove,
+    PseudolegalMovegen,
+    LegalMovegen,
+    AttackLookup,
+}
+
+#[cfg(feature = "perf-counters")]
+static COUNTS: [AtomicU64; 5] = [AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0), AtomicU64::new(0)];
+
+/// Records one occurrence of `counter`. Compiles to nothing without the `perf-counters` feature.
+#[inline(always)]
+pub(crate) fn record_perf_counter(counter: PerfCounter) {
+    #[cfg(feature = "perf-counters")]
+    COUNTS[counter as usize].fetch_add(1, Ordering::Relaxed);
+    #[cfg(not(feature = "perf-counters"))]
+    let _ = counter;
+}
+
+/// A snapshot of the global board operation counters, summed over all threads.
+/// Legal moves are generated directly, so `calc_legal_moves` counts towards `legal_movegen` and
+/// `attack_lookups` only, not towards `pseudolegal_movegen`, `make_move`, or `unmake_move`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct PerfCounters {
+    /// Calls to `State::make_move`.
+    pub make_move: u64,
+    /// Calls to `State::unmake_move`.
+    pub unmake_move: u64,
+    /// Pseudolegal move generations, e.g. by `State::calc_pseudolegal_moves`.
+    pub pseudolegal_movegen: u64,
+    /// Legal move generations, e.g. by `State::calc_legal_moves`.
+    pub legal_movegen: u64,
+    /// Attack table lookups, for move generation and check detection.
+    pub attack_lookups: u64,
+}
+
+impl PerfCounters {
+    /// Whether the counters are compiled in.
+    pub const ENABLED: bool = cfg!(feature = "perf-counters");
+
+    /// Reads the current counter values.
+    pub fn snapshot() -> PerfCounters {
+        #[cfg(feature = "perf-counters")]
+        {
+            let get = |counter: PerfCounter| COUNTS[counter as usize].load(Ordering::Relaxed);
+            PerfCounters {
+                make_move: get(PerfCounter::MakeMove),
+                unmake_move: get(PerfCounter::UnmakeMove),
+                pseudolegal_movegen: get(PerfCounter::PseudolegalMovegen),
+                legal_movegen: get(PerfCounter::LegalMovegen),
+                attack_lookups: get(PerfCounter::AttackLookup),
+            }
+        }
+        #[cfg(not(feature = "perf-counters"))]
+        PerfCounters::default()
+    }
+
+    /// Resets all counters to zero.
+    pub fn reset() {
+        #[cfg(feature = "perf-counters")]
+        for count in COUNTS.iter() {
+            count.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the counts accumulated between `earlier` and this snapshot.
+    pub fn since(&self, earlier: &PerfCounters) -> PerfCounters {
+        PerfCounters {
+            make_move: self.make_move.saturating_sub(earlier.make_move),
+            unmake_move: self.unmake_move.saturating_sub(earlier.unmake_move),
+            pseudolegal_movegen: self.pseudolegal_movegen.saturating_sub(earlier.pseudolegal_movegen),
+            legal_movegen: self.legal_movegen.saturating_sub(earlier.legal_movegen),
+            attack_lookups: self.attack_lookups.saturating_sub(earlier.attack_lookups),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::State;
+
+    #[test]
+    fn test_perf_counters() {
+        // other tests run concurrently, so only lower bounds can be checked
+        let before = PerfCounters::snapshot();
+        let mut state = State::initial();
+        state.calc_legal_moves();
+        let counted = PerfCounters::snapshot().since(&before);
+
+        if PerfCounters::ENABLED {
+            assert!(counted.legal_movegen >= 1);
+            assert!(counted.attack_lookups > 0);
+        } else {
+            assert_eq!(counted, PerfCounters::default());
+        }
+
+        let before = PerfCounters::snapshot();
+        let pseudolegal_moves = state.calc_pseudolegal_moves();
+        for mv in pseudolegal_moves.iter() {
+            state.make_move(*mv);
+            state.unmake_move(*mv);
+        }
+        let counted = PerfCounters::snapshot().since(&before);
+        let num_moves = pseudolegal_moves.len() as u64;
+
+        if PerfCounters::ENABLED {
+            assert!(counted.pseudolegal_movegen >= 1);
+            assert!(counted.make_move >= num_moves);
+            assert!(counted.unmake_move >= num_moves);
+        } else {
+            assert_eq!(counted, PerfCounters::default());
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::str::FromStr;
+use crate::utils::{Color, ColoredPiece, EnumParseError, PieceType};
+
+/// The piece letters used to display moves and boards to people.
+/// FEN, PGN and UCI always use the English letters, so localized SAN is only for display,
+/// and has to be converted back with `delocalize_san` before being parsed.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PieceLocale {
+    /// K, Q, R, B, N and P.
+    #[default]
+    English,
+    /// K (König), D (Dame), T (Turm), L (Läufer), S (Springer) and B (Bauer).
+    German,
+    /// R (Roi), D (Dame), T (Tour), F (Fou), C (Cavalier) and P (Pion).
+    French,
+    /// R (Rey), D (Dama), T (Torre), A (Alfil), C (Caballo) and P (Peón).
+    Spanish,
+    /// R (Re), D (Donna), T (Torre), A (Alfiere), C (Cavallo) and P (Pedone).
+    Italian,
+    /// Unicode chess symbols, as in figurine algebraic notation.
+    Figurine,
+}
+
+impl PieceLocale {
+    pub const ALL: [PieceLocale; 6] = [
+        PieceLocale::English, PieceLocale::German, PieceLocale::French,
+        PieceLocale::Spanish, PieceLocale::Italian, PieceLocale::Figurine
+    ];
+
+    /// Returns the letters of the pawn, knight, bishop, rook, queen and king, in that order.
+    const fn get_piece_chars(&self) -> [char; 6] {
+        match self {
+            PieceLocale::English => ['P', 'N', 'B', 'R', 'Q', 'K'],
+            PieceLocale::German => ['B', 'S', 'L', 'T', 'D', 'K'],
+            PieceLocale::French => ['P', 'C', 'F', 'T', 'D', 'R'],
+            PieceLocale::Spanish | PieceLocale::Italian => ['P', 'C', 'A', 'T', 'D', 'R'],
+            PieceLocale::Figurine => ['♙', '♘', '♗', '♖', '♕', '♔']
+        }
+    }
+
+    /// Returns the letter of `piece_type`, or a space for `PieceType::NoPieceType`.
+    pub const fn get_piece_char(&self, piece_type: PieceType) -> char {
+        match piece_type {
+            PieceType::NoPieceType => ' ',
+            _ => self.get_piece_chars()[piece_type as usize - 1]
+        }
+    }
+
+    /// Returns the character of `colored_piece` on a board: the letter in upper case for white
+    /// and lower case for black, or the symbol of its color for `Figurine`.
+    pub fn get_colored_piece_char(&self, colored_piece: ColoredPiece) -> char {
+        match (self, colored_piece.get_color()) {
+            (PieceLocale::Figurine, _) => colored_piece.to_char_pretty(),
+            (_, Color::White) => self.get_piece_char(colored_piece.get_piece_type()),
+            (_, Color::Black) => self.get_piece_char(colored_piece.get_piece_type()).to_ascii_lowercase()
+        }
+    }
+
+    /// Returns `san` with the English piece letters replaced by those of the locale.
+    pub fn localize_san(&self, san: &str) -> String {
+        san.chars().map(|c| match PieceType::iter_non_pawn_pieces().find(|piece_type| piece_type.to_char() == c) {
+            Some(piece_type) => self.get_piece_char(*piece_type),
+            None => c
+        }).collect()
+    }
+
+    /// Returns `san`, written with the piece letters of the locale, with English piece letters instead,
+    /// so that it can be parsed. Pawn letters are left alone, since SAN never has them.
+    /// For `Figurine`, symbols of either color are accepted.
+    pub fn delocalize_san(&self, san: &str) -> String {
+        san.chars().map(|c| match ColoredPiece::from_char_pretty(c) {
+            colored_piece if *self == PieceLocale::Figurine && colored_piece != ColoredPiece::NoPiece => {
+                colored_piece.get_piece_type().to_char()
+            },
+            _ => match PieceType::iter_non_pawn_pieces().find(|piece_type| self.get_piece_char(**piece_type) == c) {
+                Some(piece_type) => piece_type.to_char(),
+                None => c
+            }
+        }).collect()
+    }
+}
+
+impl FromStr for PieceLocale {
+    type Err = EnumParseError;
+
+    /// Parses a language name or code (any case), e.g. "german" or "de", or "figurine".
+    fn from_str(s: &str) -> Result<PieceLocale, EnumParseError> {
+        match s.to_ascii_lowercase().as_str() {
+            "english" | "en" => Ok(PieceLocale::English),
+            "german" | "de" => Ok(PieceLocale::German),
+            "french" | "fr" => Ok(PieceLocale::French),
+            "spanish" | "es" => Ok(PieceLocale::Spanish),
+            "italian" | "it" => Ok(PieceLocale::Italian),
+            "figurine" => Ok(PieceLocale::Figurine),
+            _ => Err(EnumParseError::new("piece locale", s))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_piece_chars() {
+        assert_eq!(PieceLocale::German.get_piece_char(PieceType::Knight), 'S');
+        assert_eq!(PieceLocale::French.get_piece_char(PieceType::King), 'R');
+        assert_eq!(PieceLocale::English.get_piece_char(PieceType::NoPieceType), ' ');
+        assert_eq!(PieceLocale::German.get_colored_piece_char(ColoredPiece::BlackBishop), 'l');
+        assert_eq!(PieceLocale::Figurine.get_colored_piece_char(ColoredPiece::BlackQueen), '♛');
+        for locale in PieceLocale::ALL {
+            assert_eq!(locale.get_colored_piece_char(ColoredPiece::NoPiece), ' ');
+            // every piece can be told apart on a board
+            let mut chars: Vec<char> = Color::ALL.iter()
+                .flat_map(|color| PieceType::iter_pieces().map(|piece_type| locale.get_colored_piece_char(ColoredPiece::from(*color, *piece_type))))
+                .collect();
+            chars.sort();
+            chars.dedup();
+            assert_eq!(chars.len(), 12, "{:?}", locale);
+        }
+    }
+
+    #[test]
+    fn test_localize_san() {
+        assert_eq!(PieceLocale::German.localize_san("Nbd7"), "Sbd7");
+        assert_eq!(PieceLocale::German.localize_san("exd8=Q+"), "exd8=D+");
+        assert_eq!(PieceLocale::French.localize_san("Bxe5"), "Fxe5");
+        assert_eq!(PieceLocale::Spanish.localize_san("Rxe1#"), "Txe1#");
+        assert_eq!(PieceLocale::Figurine.localize_san("Qh5"), "♕h5");
+        // castling and pawn moves have no piece letters
+        assert_eq!(PieceLocale::German.localize_san("O-O-O"), "O-O-O");
+        assert_eq!(PieceLocale::German.localize_san("bxc3"), "bxc3");
+
+        for san in ["Nbd7", "exd8=Q+", "Bxe5", "Rxe1#", "Kf1", "Qh5", "O-O", "b4"] {
+            for locale in PieceLocale::ALL {
+                assert_eq!(locale.delocalize_san(&locale.localize_san(san)), san, "{:?}", locale);
+            }
+        }
+        assert_eq!(PieceLocale::Spanish.delocalize_san("Re2"), "Ke2");
+        assert_eq!(PieceLocale::Figurine.delocalize_san("♞f6"), "Nf6");
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(PieceLocale::from_str("DE"), Ok(PieceLocale::German));
+        assert_eq!(PieceLocale::from_str("figurine"), Ok(PieceLocale::Figurine));
+        assert_eq!(PieceLocale::from_str("klingon"), Err(EnumParseError::new("piece locale", "klingon")));
+    }
+}
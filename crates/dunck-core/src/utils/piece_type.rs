@@ -1,5 +1,7 @@
+use std::fmt::Display;
+use std::str::FromStr;
 use subenum::subenum;
-use crate::utils::{Color, ColoredPiece};
+use crate::utils::{Color, ColoredPiece, EnumParseError};
 
 #[subenum(SlidingPieceType)]
 #[repr(u8)]
@@ -73,6 +75,10 @@ const ALL_PROMOTION_PIECES: [PieceType; 4] = [
 impl PieceType {
     pub const LIMIT: u8 = 7;
     pub const AllPieceTypes: PieceType = PieceType::NoPieceType;
+    /// Every variant, including `NoPieceType`, in value order.
+    pub const ALL: [PieceType; 7] = ALL;
+    /// Every actual piece type, in value order.
+    pub const PIECES: [PieceType; 6] = ALL_PIECES;
 
     pub const unsafe fn from(piece_type_number: u8) -> PieceType {
         assert!(piece_type_number < PieceType::LIMIT, "Piece type number out of bounds");
@@ -107,7 +113,7 @@ impl PieceType {
         ALL_NON_PAWN_PIECES.iter()
     }
     
-    pub fn iter_promotion_pieces() -> impl Iterator<Item = &'static PieceType> {
+    pub fn iter_promotion_pieces() -> impl DoubleEndedIterator<Item = &'static PieceType> {
         ALL_PROMOTION_PIECES.iter()
     }
     
@@ -116,6 +122,31 @@ impl PieceType {
     }
 }
 
+impl Display for PieceType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.to_char())
+    }
+}
+
+impl FromStr for PieceType {
+    type Err = EnumParseError;
+
+    /// Parses a piece letter ("N", "n") or an English piece name ("knight"), in any case.
+    fn from_str(s: &str) -> Result<PieceType, EnumParseError> {
+        let lowercase = s.to_ascii_lowercase();
+        let piece_type = match lowercase.as_str() {
+            "p" | "pawn" => PieceType::Pawn,
+            "n" | "knight" => PieceType::Knight,
+            "b" | "bishop" => PieceType::Bishop,
+            "r" | "rook" => PieceType::Rook,
+            "q" | "queen" => PieceType::Queen,
+            "k" | "king" => PieceType::King,
+            _ => return Err(EnumParseError::new("piece type", s))
+        };
+        Ok(piece_type)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -132,4 +163,16 @@ mod tests {
             assert_eq!(PieceType::from(6), PieceType::King);
         }
     }
+
+    #[test]
+    fn test_piece_type_strings() {
+        for piece_type in PieceType::PIECES {
+            assert_eq!(piece_type.to_string().parse::<PieceType>(), Ok(piece_type));
+            assert_eq!(piece_type.to_string().to_lowercase().parse::<PieceType>(), Ok(piece_type));
+        }
+        assert_eq!(PieceType::Knight.to_string(), "N");
+        assert_eq!("Queen".parse::<PieceType>(), Ok(PieceType::Queen));
+        assert!("x".parse::<PieceType>().is_err());
+        assert!("".parse::<PieceType>().is_err());
+    }
 }
\ No newline at end of file
@@ -1,5 +1,6 @@
 use std::fmt::Display;
-use crate::utils::{Bitboard, Color};
+use std::str::FromStr;
+use crate::utils::{Bitboard, Color, EnumParseError, QueenLikeMoveDirection};
 use crate::utils::charboard::SQUARE_NAMES;
 use crate::utils::masks::{FILES, RANKS};
 
@@ -28,6 +29,9 @@ const ALL: [Square; 64] = [
 ];
 
 impl Square {
+    /// Every square, in value order (A8 first, H1 last).
+    pub const ALL: [Square; 64] = ALL;
+
     pub const unsafe fn from(square_number: u8) -> Square {
         assert!(square_number < 64, "Square number out of bounds");
         std::mem::transmute::<u8, Square>(square_number)
@@ -144,6 +148,53 @@ impl Square {
         }
     }
 
+    /// Returns the square `file_delta` files right and `rank_delta` ranks up from this one,
+    /// or `None` if that is off the board.
+    pub const fn offset(&self, file_delta: i8, rank_delta: i8) -> Option<Square> {
+        let file = self.get_file() as i8 + file_delta;
+        let rank = self.get_rank() as i8 + rank_delta;
+        if file < 0 || file > 7 || rank < 0 || rank > 7 {
+            None
+        } else {
+            Some(unsafe { Square::from_rank_file(rank as u8, file as u8) })
+        }
+    }
+
+    /// Returns the adjacent square in `direction`, or `None` if that is off the board.
+    pub const fn step(&self, direction: QueenLikeMoveDirection) -> Option<Square> {
+        match direction {
+            QueenLikeMoveDirection::Up => self.up(),
+            QueenLikeMoveDirection::Down => self.down(),
+            QueenLikeMoveDirection::Left => self.left(),
+            QueenLikeMoveDirection::Right => self.right(),
+            QueenLikeMoveDirection::UpLeft => self.up_left(),
+            QueenLikeMoveDirection::UpRight => self.up_right(),
+            QueenLikeMoveDirection::DownLeft => self.down_left(),
+            QueenLikeMoveDirection::DownRight => self.down_right()
+        }
+    }
+
+    /// Returns the number of king moves between this square and `other`.
+    pub const fn distance(&self, other: Square) -> u8 {
+        let file_distance = self.get_file().abs_diff(other.get_file());
+        let rank_distance = self.get_rank().abs_diff(other.get_rank());
+        if file_distance > rank_distance { file_distance } else { rank_distance }
+    }
+
+    /// Returns the little-endian rank-file index used by most other chess libraries (A1 = 0, B1 = 1, ..., H8 = 63).
+    pub const fn to_a1_index(&self) -> u8 {
+        self.get_rank() * 8 + self.get_file()
+    }
+
+    /// Creates a square from a little-endian rank-file index (A1 = 0, B1 = 1, ..., H8 = 63).
+    pub const fn from_a1_index(index: u8) -> Option<Square> {
+        if index < 64 {
+            Some(unsafe { Square::from_rank_file(index / 8, index % 8) })
+        } else {
+            None
+        }
+    }
+
     pub const fn get_file_char(&self) -> char {
         (b'a' + self.get_file()) as char
     }
@@ -171,6 +222,18 @@ impl Display for Square {
     }
 }
 
+impl FromStr for Square {
+    type Err = EnumParseError;
+
+    /// Parses a lowercase square name, e.g. "e4".
+    fn from_str(s: &str) -> Result<Square, EnumParseError> {
+        match s.as_bytes() {
+            [file @ b'a'..=b'h', rank @ b'1'..=b'8'] => Ok(unsafe { Square::from_rank_file(rank - b'1', file - b'a') }),
+            _ => Err(EnumParseError::new("square", s))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -182,4 +245,39 @@ mod tests {
         assert_eq!(Square::A1 as u8, 56);
         assert_eq!(Square::H1 as u8, 63);
     }
+
+    #[test]
+    fn test_square_strings() {
+        for square in Square::ALL {
+            assert_eq!(square.to_string().parse::<Square>(), Ok(square));
+        }
+        assert_eq!("e4".parse::<Square>(), Ok(Square::E4));
+        assert!("E4".parse::<Square>().is_err());
+        assert!("i1".parse::<Square>().is_err());
+        assert!("e44".parse::<Square>().is_err());
+    }
+
+    #[test]
+    fn test_square_navigation() {
+        assert_eq!(Square::E4.offset(1, 2), Some(Square::F6));
+        assert_eq!(Square::E4.offset(-4, -3), Some(Square::A1));
+        assert_eq!(Square::A1.offset(-1, 0), None);
+        assert_eq!(Square::H8.offset(0, 1), None);
+        assert_eq!(Square::E4.step(QueenLikeMoveDirection::UpLeft), Some(Square::D5));
+        assert_eq!(Square::A4.step(QueenLikeMoveDirection::Left), None);
+        assert_eq!(Square::A1.distance(Square::H8), 7);
+        assert_eq!(Square::E4.distance(Square::F6), 2);
+    }
+
+    #[test]
+    fn test_a1_index() {
+        assert_eq!(Square::A1.to_a1_index(), 0);
+        assert_eq!(Square::H1.to_a1_index(), 7);
+        assert_eq!(Square::A8.to_a1_index(), 56);
+        assert_eq!(Square::H8.to_a1_index(), 63);
+        for square in Square::ALL {
+            assert_eq!(Square::from_a1_index(square.to_a1_index()), Some(square));
+        }
+        assert_eq!(Square::from_a1_index(64), None);
+    }
 }
\ No newline at end of file
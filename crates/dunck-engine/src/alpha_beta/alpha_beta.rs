@@ -0,0 +1,375 @@
+//! Iterative deepening principal variation search, in centipawns, over material or an NNUE network.
+
+use crate::alpha_beta::pruning::PruningConfig;
+use crate::alpha_beta::search_stats::SearchStats;
+use crate::evaluators::nnue::{AccumulatorStack, NnueNetwork};
+use dunck_core::r#move::{Move, MoveFlag};
+use dunck_core::state::{get_see_value, State};
+use dunck_core::utils::{Color, PieceType};
+
+/// The score of delivering checkmate immediately. Mates further away score one less per ply.
+pub const MATE_SCORE: i32 = 100_000;
+
+const INFINITY: i32 = MATE_SCORE + 1;
+
+/// Aspiration windows wider than this are replaced by a full window.
+const MAX_ASPIRATION_DELTA: i32 = 1_000;
+
+/// Scores beyond this are mate scores, which futility pruning must not cut off.
+const MAX_NON_MATE_SCORE: i32 = MATE_SCORE - 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlphaBetaConfig {
+    /// The initial half-width of the window around the previous iteration's score, doubled on every failure.
+    /// If `None`, every iteration is searched with a full window.
+    pub aspiration_delta: Option<i32>,
+    /// Whether moves after the first are searched with a null window, and only searched again on a fail high.
+    pub use_null_window: bool,
+    /// If set, late move reductions and futility pruning are used.
+    pub pruning: Option<PruningConfig>,
+}
+
+impl Default for AlphaBetaConfig {
+    fn default() -> Self {
+        AlphaBetaConfig {
+            aspiration_delta: Some(25),
+            use_null_window: true,
+            pruning: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    /// The score for the side to move.
+    pub score: i32,
+    pub depth: u32,
+}
+
+pub struct AlphaBetaSearch<'a> {
+    pub config: AlphaBetaConfig,
+    pub stats: SearchStats,
+    /// The network evaluating leaves instead of material, if any.
+    nnue: Option<&'a NnueNetwork>,
+    /// The accumulators of the line being searched, updated as moves are made and unmade.
+    accumulators: Option<AccumulatorStack>,
+}
+
+impl<'a> AlphaBetaSearch<'a> {
+    pub fn new(config: AlphaBetaConfig) -> Self {
+        AlphaBetaSearch {
+            config,
+            stats: SearchStats::default(),
+            nnue: None,
+            accumulators: None,
+        }
+    }
+
+    /// Evaluates leaves with `network`, whose accumulators are updated incrementally along the searched line.
+    pub fn with_nnue(mut self, network: &'a NnueNetwork) -> Self {
+        self.nnue = Some(network);
+        self
+    }
+
+    /// Searches `state` with iterative deepening up to `max_depth` plies.
+    pub fn search(&mut self, state: &State, max_depth: u32) -> SearchResult {
+        let mut state = state.clone();
+        self.accumulators = self.nnue.map(|network| AccumulatorStack::new(network, &state));
+        let mut result = SearchResult {
+            best_move: None,
+            score: self.negamax(&mut state, 0, 0, -INFINITY, INFINITY),
+            depth: 0,
+        };
+
+        for depth in 1..=max_depth {
+            let mut delta = match self.config.aspiration_delta {
+                Some(delta) if depth > 1 => {
+                    self.stats.aspiration_searches += 1;
+                    delta
+                },
+                _ => INFINITY
+            };
+
+            loop {
+                let (alpha, beta) = if delta > MAX_ASPIRATION_DELTA {
+                    (-INFINITY, INFINITY)
+                } else {
+                    (result.score - delta, result.score + delta)
+                };
+                let (score, best_move) = self.search_root(&mut state, depth, alpha, beta, result.best_move);
+                if (score <= alpha && alpha > -INFINITY) || (score >= beta && beta < INFINITY) {
+                    self.stats.aspiration_re_searches += 1;
+                    delta *= 2;
+                } else {
+                    result = SearchResult { best_move, score, depth };
+                    break;
+                }
+            }
+        }
+
+        result
+    }
+
+    fn search_root(&mut self, state: &mut State, depth: u32, mut alpha: i32, beta: i32, previous_best_move: Option<Move>) -> (i32, Option<Move>) {
+        self.stats.nodes += 1;
+        let mut moves = order_moves(state, state.calc_legal_moves());
+        if let Some(index) = previous_best_move.and_then(|best_move| moves.iter().position(|mv| *mv == best_move)) {
+            let best_move = moves.remove(index);
+            moves.insert(0, best_move);
+        }
+
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for (i, mv) in moves.into_iter().enumerate() {
+            self.make_move(state, mv);
+            let score = self.search_child(state, depth - 1, 1, alpha, beta, i == 0);
+            self.unmake_move(state, mv);
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        if best_move.is_none() {
+            best_score = self.negamax(state, depth, 0, alpha, beta);
+        }
+        (best_score, best_move)
+    }
+
+    /// Searches a child of a node with window `(alpha, beta)`, returning its score for the parent.
+    /// Unless `is_first_move`, the child is first searched with a null window.
+    fn search_child(&mut self, state: &mut State, depth: u32, ply: u32, alpha: i32, beta: i32, is_first_move: bool) -> i32 {
+        if is_first_move || !self.config.use_null_window {
+            return -self.negamax(state, depth, ply, -beta, -alpha);
+        }
+
+        self.stats.null_window_searches += 1;
+        let score = -self.negamax(state, depth, ply, -alpha - 1, -alpha);
+        if score > alpha && score < beta {
+            self.stats.null_window_re_searches += 1;
+            -self.negamax(state, depth, ply, -beta, -alpha)
+        } else {
+            score
+        }
+    }
+
+    fn negamax(&mut self, state: &mut State, depth: u32, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        self.stats.nodes += 1;
+        if state.termination.is_some() || !state.claimable_draws().is_empty() {
+            return 0;
+        }
+
+        let moves = state.calc_legal_moves();
+        if moves.is_empty() {
+            return if state.is_in_check() { -MATE_SCORE + ply as i32 } else { 0 };
+        }
+        let static_score = self.calc_static_score(state);
+        if depth == 0 {
+            return static_score;
+        }
+
+        let is_in_check = state.is_in_check();
+        let is_null_window = beta - alpha == 1;
+        let mut is_futile = false;
+        if let Some(pruning) = self.config.pruning.filter(|_| !is_in_check && beta.abs() < MAX_NON_MATE_SCORE) {
+            if is_null_window && depth <= pruning.reverse_futility_max_depth
+                && static_score - pruning.reverse_futility_margin * depth as i32 >= beta {
+                self.stats.reverse_futility_prunes += 1;
+                return static_score;
+            }
+            is_futile = depth <= pruning.futility_max_depth && alpha.abs() < MAX_NON_MATE_SCORE
+                && static_score + pruning.futility_margin * (depth as i32) <= alpha;
+        }
+
+        let mut best_score = -INFINITY;
+        for (i, mv) in order_moves(state, moves).into_iter().enumerate() {
+            let is_quiet = !is_tactical(state, mv);
+            if is_futile && is_quiet && i > 0 {
+                self.stats.futility_prunes += 1;
+                continue;
+            }
+
+            self.make_move(state, mv);
+            let reduction = match self.config.pruning {
+                Some(pruning) if is_quiet && !is_in_check && depth >= pruning.lmr_min_depth
+                    && i as u32 >= pruning.lmr_min_move_index => pruning.lmr_reduction.min(depth - 1),
+                _ => 0
+            };
+            let score = if reduction > 0 {
+                self.stats.lmr_reductions += 1;
+                let reduced_score = -self.negamax(state, depth - 1 - reduction, ply + 1, -alpha - 1, -alpha);
+                if reduced_score > alpha {
+                    self.stats.lmr_re_searches += 1;
+                    self.search_child(state, depth - 1, ply + 1, alpha, beta, false)
+                } else {
+                    reduced_score
+                }
+            } else {
+                self.search_child(state, depth - 1, ply + 1, alpha, beta, i == 0)
+            };
+            self.unmake_move(state, mv);
+
+            if score > best_score {
+                best_score = score;
+            }
+            if score > alpha {
+                alpha = score;
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+        best_score
+    }
+
+    /// Makes `mv` on `state`, pushing the accumulator of the new position if searching with a network.
+    fn make_move(&mut self, state: &mut State, mv: Move) {
+        match (self.nnue, self.accumulators.as_mut()) {
+            (Some(network), Some(accumulators)) => accumulators.make_move(network, state, mv),
+            _ => state.make_move(mv)
+        }
+    }
+
+    /// Unmakes `mv` on `state`, popping the accumulator of the position it led to if searching with a network.
+    fn unmake_move(&mut self, state: &mut State, mv: Move) {
+        match self.accumulators.as_mut() {
+            Some(accumulators) => accumulators.unmake_move(state, mv),
+            None => state.unmake_move(mv)
+        }
+    }
+
+    /// Returns the network's evaluation of the current accumulator, short of mate scores, or the material score.
+    fn calc_static_score(&self, state: &State) -> i32 {
+        match (self.nnue, self.accumulators.as_ref()) {
+            (Some(network), Some(accumulators)) => accumulators.evaluate(network, state.side_to_move)
+                .clamp(-MAX_NON_MATE_SCORE + 1, MAX_NON_MATE_SCORE - 1),
+            _ => calc_material_score(state)
+        }
+    }
+}
+
+/// Returns the material balance in centipawns for the side to move.
+pub fn calc_material_score(state: &State) -> i32 {
+    let mut score = 0;
+    for piece_type in PieceType::iter_between(PieceType::Pawn, PieceType::Queen) {
+        let pieces_mask = state.board.piece_type_masks[*piece_type as usize];
+        let balance = (pieces_mask & state.board.color_masks[Color::White as usize]).count_ones() as i32
+            - (pieces_mask & state.board.color_masks[Color::Black as usize]).count_ones() as i32;
+        score += 100 * get_see_value(*piece_type) * balance;
+    }
+    if state.side_to_move == Color::White { score } else { -score }
+}
+
+/// Returns whether `mv` is a capture or a promotion.
+fn is_tactical(state: &State, mv: Move) -> bool {
+    mv.get_flag() == MoveFlag::Promotion || mv.get_flag() == MoveFlag::EnPassant
+        || state.board.get_piece_type_at(mv.get_destination()) != PieceType::NoPieceType
+}
+
+/// Orders captures and promotions first, by static exchange evaluation.
+fn order_moves(state: &State, mut moves: Vec<Move>) -> Vec<Move> {
+    moves.sort_by_cached_key(|mv| if is_tactical(state, *mv) { -state.calc_see(*mv) - 1_000 } else { 0 });
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::nnue::{get_feature_index, Accumulator, NnueEvaluator, NNUE_QB};
+    use dunck_core::utils::{ColoredPiece, Square};
+
+    const FENS: [&str; 3] = [
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r1bqkbnr/pppp1ppp/2n5/4p3/2B1P3/5Q2/PPPP1PPP/RNB1K1NR w KQkq - 2 3",
+    ];
+
+    #[test]
+    fn test_finds_mate_in_one() {
+        let state = State::from_fen("6k1/5ppp/8/8/8/8/8/R6K w - - 0 1").unwrap();
+        let result = AlphaBetaSearch::new(AlphaBetaConfig::default()).search(&state, 3);
+        assert_eq!(result.best_move, Some(Move::new_non_promotion(Square::A8, Square::A1, MoveFlag::NormalMove)));
+        assert_eq!(result.score, MATE_SCORE - 1);
+    }
+
+    #[test]
+    fn test_windows_do_not_change_scores() {
+        let full_window = AlphaBetaConfig { aspiration_delta: None, use_null_window: false, pruning: None };
+        for fen in FENS {
+            let state = State::from_fen(fen).unwrap();
+            let expected = AlphaBetaSearch::new(full_window).search(&state, 3).score;
+
+            let mut search = AlphaBetaSearch::new(AlphaBetaConfig::default());
+            assert_eq!(search.search(&state, 3).score, expected, "{}", fen);
+            assert!(search.stats.null_window_searches > 0);
+            assert!(search.stats.null_window_re_searches <= search.stats.null_window_searches);
+            assert_eq!(search.stats.aspiration_searches, 2);
+        }
+    }
+
+    #[test]
+    fn test_pruning() {
+        let config = AlphaBetaConfig { pruning: Some(PruningConfig::default()), ..Default::default() };
+        let state = State::from_fen("6k1/5ppp/8/8/8/8/8/R6K w - - 0 1").unwrap();
+        assert_eq!(AlphaBetaSearch::new(config).search(&state, 4).score, MATE_SCORE - 1);
+
+        let state = State::from_fen(FENS[0]).unwrap();
+        let mut unpruned_search = AlphaBetaSearch::new(AlphaBetaConfig::default());
+        unpruned_search.search(&state, 4);
+        let mut pruned_search = AlphaBetaSearch::new(config);
+        pruned_search.search(&state, 4);
+
+        let stats = pruned_search.stats;
+        assert!(stats.lmr_reductions > 0 && stats.lmr_re_searches <= stats.lmr_reductions);
+        assert!(stats.futility_prunes > 0);
+        assert!(stats.reverse_futility_prunes > 0);
+        assert!(stats.nodes < unpruned_search.stats.nodes);
+        assert_eq!(unpruned_search.stats.futility_prunes + unpruned_search.stats.lmr_reductions, 0);
+    }
+
+    /// Returns a network whose hidden units sum each side's material in sixteenths of centipawns,
+    /// and whose output is the side to move's material minus the opponent's.
+    fn material_network() -> NnueNetwork {
+        let mut network = NnueNetwork::zeroed(2);
+        let piece_values = [100, 300, 300, 500, 900, 0];
+        for color in Color::ALL {
+            for piece_type in PieceType::iter_pieces() {
+                for square in Square::iter_all() {
+                    let feature_index = get_feature_index(Color::White, ColoredPiece::from(color, *piece_type), *square);
+                    network.feature_weights[feature_index * 2 + color as usize] = piece_values[*piece_type as usize - 1] / 16;
+                }
+            }
+        }
+        network.output_weights = vec![4 * NNUE_QB as i16, -4 * NNUE_QB as i16, 0, 0];
+        network
+    }
+
+    #[test]
+    fn test_nnue_search() {
+        let network = material_network();
+        for fen in FENS {
+            let state = State::from_fen(fen).unwrap();
+            let mut search = AlphaBetaSearch::new(AlphaBetaConfig::default()).with_nnue(&network);
+            assert_eq!(search.search(&state, 0).score, NnueEvaluator::new(network.clone()).calc_centipawns(&state));
+            search.search(&state, 3);
+            // every move made during the search was unmade, leaving the root's accumulator
+            assert_eq!(*search.accumulators.as_ref().unwrap().get_current(), Accumulator::from_board(&network, &state.board));
+        }
+
+        let state = State::from_fen("6k1/5ppp/8/8/8/8/8/R6K w - - 0 1").unwrap();
+        assert_eq!(AlphaBetaSearch::new(AlphaBetaConfig::default()).with_nnue(&network).search(&state, 3).score, MATE_SCORE - 1);
+
+        let state = State::from_fen("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1").unwrap();
+        let result = AlphaBetaSearch::new(AlphaBetaConfig::default()).with_nnue(&network).search(&state, 2);
+        assert_eq!(result.best_move, Some(Move::new_non_promotion(Square::D5, Square::D2, MoveFlag::NormalMove)));
+        assert!(result.score > 0);
+    }
+}
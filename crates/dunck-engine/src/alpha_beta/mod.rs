@@ -0,0 +1,3 @@
+pub mod alpha_beta;
+pub mod pruning;
+pub mod search_stats;
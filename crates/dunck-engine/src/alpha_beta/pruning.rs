@@ -0,0 +1,79 @@
+use crate::tuning::{check_tunable_parameter, Tunable, TunableParameter};
+
+/// Settings for late move reductions, futility pruning and reverse futility pruning.
+/// Margins are in centipawns per ply of remaining depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PruningConfig {
+    /// Quiet moves at least this far down the move list are searched with reduced depth.
+    pub lmr_min_move_index: u32,
+    pub lmr_min_depth: u32,
+    pub lmr_reduction: u32,
+    /// Quiet moves are skipped if the static evaluation plus the margin cannot raise alpha.
+    pub futility_max_depth: u32,
+    pub futility_margin: i32,
+    /// Nodes are cut off if the static evaluation minus the margin still beats beta.
+    pub reverse_futility_max_depth: u32,
+    pub reverse_futility_margin: i32,
+}
+
+impl Default for PruningConfig {
+    fn default() -> Self {
+        PruningConfig {
+            lmr_min_move_index: 3,
+            lmr_min_depth: 3,
+            lmr_reduction: 1,
+            futility_max_depth: 2,
+            futility_margin: 200,
+            reverse_futility_max_depth: 3,
+            reverse_futility_margin: 150,
+        }
+    }
+}
+
+impl Tunable for PruningConfig {
+    fn get_tunable_parameters(&self) -> Vec<TunableParameter> {
+        vec![
+            TunableParameter { name: "lmr_min_move_index", value: self.lmr_min_move_index as i32, min: 1, max: 64 },
+            TunableParameter { name: "lmr_min_depth", value: self.lmr_min_depth as i32, min: 2, max: 16 },
+            TunableParameter { name: "lmr_reduction", value: self.lmr_reduction as i32, min: 0, max: 4 },
+            TunableParameter { name: "futility_max_depth", value: self.futility_max_depth as i32, min: 0, max: 8 },
+            TunableParameter { name: "futility_margin", value: self.futility_margin, min: 0, max: 1_000 },
+            TunableParameter { name: "reverse_futility_max_depth", value: self.reverse_futility_max_depth as i32, min: 0, max: 8 },
+            TunableParameter { name: "reverse_futility_margin", value: self.reverse_futility_margin, min: 0, max: 1_000 },
+        ]
+    }
+
+    fn set_tunable_parameter(&mut self, name: &str, value: i32) -> Result<(), String> {
+        check_tunable_parameter(&self.get_tunable_parameters(), name, value)?;
+        match name {
+            "lmr_min_move_index" => self.lmr_min_move_index = value as u32,
+            "lmr_min_depth" => self.lmr_min_depth = value as u32,
+            "lmr_reduction" => self.lmr_reduction = value as u32,
+            "futility_max_depth" => self.futility_max_depth = value as u32,
+            "futility_margin" => self.futility_margin = value,
+            "reverse_futility_max_depth" => self.reverse_futility_max_depth = value as u32,
+            _ => self.reverse_futility_margin = value
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tunable_parameters() {
+        let mut config = PruningConfig::default();
+        for parameter in config.get_tunable_parameters() {
+            assert!(parameter.min <= parameter.value && parameter.value <= parameter.max, "{}", parameter.name);
+            config.set_tunable_parameter(parameter.name, parameter.min).unwrap();
+        }
+        assert_eq!(config.lmr_min_move_index, 1);
+        assert_eq!(config.reverse_futility_margin, 0);
+
+        assert!(config.set_tunable_parameter("lmr_reduction", 5).is_err());
+        assert!(config.set_tunable_parameter("null_move_reduction", 2).is_err());
+        assert_eq!(config.lmr_reduction, 0);
+    }
+}
@@ -0,0 +1,41 @@
+/// Counters collected by `AlphaBetaSearch`, used to check that windowed searches pay off.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchStats {
+    pub nodes: u64,
+    /// Moves searched with a null window after the first move of a node.
+    pub null_window_searches: u64,
+    /// Null-window searches that failed high and had to be searched again with a full window.
+    pub null_window_re_searches: u64,
+    /// Iterations searched with an aspiration window around the previous iteration's score.
+    pub aspiration_searches: u64,
+    /// Times an aspiration window failed and was widened.
+    pub aspiration_re_searches: u64,
+    /// Moves searched with reduced depth by late move reductions.
+    pub lmr_reductions: u64,
+    /// Reduced searches that raised alpha and had to be searched again at full depth.
+    pub lmr_re_searches: u64,
+    /// Quiet moves skipped by futility pruning.
+    pub futility_prunes: u64,
+    /// Nodes cut off by reverse futility pruning.
+    pub reverse_futility_prunes: u64,
+}
+
+impl SearchStats {
+    /// The fraction of null-window searches that were searched again.
+    pub fn get_null_window_re_search_rate(&self) -> f64 {
+        calc_rate(self.null_window_re_searches, self.null_window_searches)
+    }
+
+    /// The average number of times an aspiration window had to be widened per iteration.
+    pub fn get_aspiration_re_search_rate(&self) -> f64 {
+        calc_rate(self.aspiration_re_searches, self.aspiration_searches)
+    }
+}
+
+fn calc_rate(count: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.
+    } else {
+        count as f64 / total as f64
+    }
+}
@@ -0,0 +1,74 @@
+//! The on-disk format of a paused `AnalysisSession`, stored as JSON.
+//! Only the root of the search tree and its children are kept, which is enough to resume the search
+//! with the accumulated statistics guiding it. Move history before the analyzed position is not kept.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use serde::{Deserialize, Serialize};
+use crate::manifest::EngineManifest;
+use dunck_core::r#move::MoveDecodeError;
+use dunck_core::state::FenParseError;
+
+pub const ANALYSIS_CHECKPOINT_VERSION: u32 = 1;
+
+/// The search statistics of a child of the root, with its move in the 16-bit encoding of `Move::to_u16`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ChildSummary {
+    pub mv: u16,
+    pub visits: u32,
+    pub value: f64,
+    pub prior: f64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AnalysisCheckpoint {
+    pub version: u32,
+    pub fen: String,
+    pub exploration_param: f64,
+    pub analysis_mode: bool,
+    pub is_root_filtered: bool,
+    pub root_visits: u32,
+    pub root_value: f64,
+    pub children: Vec<ChildSummary>,
+    pub notes: Vec<String>,
+    /// The build, model and settings the analysis was produced with, if known.
+    /// Checkpoints saved before manifests existed have none.
+    #[serde(default)]
+    pub manifest: Option<EngineManifest>,
+}
+
+#[derive(Debug)]
+pub enum AnalysisCheckpointError {
+    Io(io::Error),
+    Json(serde_json::Error),
+    UnsupportedVersion(u32),
+    InvalidFen(FenParseError),
+    InvalidMove(MoveDecodeError),
+}
+
+impl Display for AnalysisCheckpointError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnalysisCheckpointError::Io(error) => write!(f, "I/O error: {}", error),
+            AnalysisCheckpointError::Json(error) => write!(f, "Invalid checkpoint: {}", error),
+            AnalysisCheckpointError::UnsupportedVersion(version) => write!(f, "Unsupported checkpoint version: {}", version),
+            AnalysisCheckpointError::InvalidFen(error) => write!(f, "Invalid FEN: {}", error),
+            AnalysisCheckpointError::InvalidMove(error) => write!(f, "Invalid move: {}", error),
+        }
+    }
+}
+
+impl Error for AnalysisCheckpointError {}
+
+impl From<io::Error> for AnalysisCheckpointError {
+    fn from(error: io::Error) -> Self {
+        AnalysisCheckpointError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for AnalysisCheckpointError {
+    fn from(error: serde_json::Error) -> Self {
+        AnalysisCheckpointError::Json(error)
+    }
+}
@@ -0,0 +1,237 @@
+//! A persistent store of the best known analysis of each position, so that positions that were already
+//! analyzed deeply enough do not have to be searched again.
+//! The store itself is left to implementors of `AnalysisStore`; an on-disk store backed by sled
+//! is available with the `analysis-db` feature.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use serde::{Deserialize, Serialize};
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+
+/// The best known analysis of a position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnalysisRecord {
+    /// How much effort the analysis took. For MCTS analysis, this is the number of root visits.
+    pub depth: u32,
+    /// The principal variation, starting with the best move.
+    pub pv: Vec<Move>,
+    /// The score of the position, from the side to move's perspective, in [-1, 1].
+    pub score: f64,
+    /// Where the analysis came from, e.g. the engine or evaluator that produced it.
+    pub source: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredAnalysisRecord {
+    depth: u32,
+    pv: Vec<u16>,
+    score: f64,
+    source: String,
+}
+
+impl AnalysisRecord {
+    /// Returns whether this analysis should replace `other`, i.e. whether it is at least as deep.
+    pub fn supersedes(&self, other: &AnalysisRecord) -> bool {
+        self.depth >= other.depth
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let stored = StoredAnalysisRecord {
+            depth: self.depth,
+            pv: self.pv.iter().map(|mv| mv.to_u16()).collect(),
+            score: self.score,
+            source: self.source.clone(),
+        };
+        bincode::serialize(&stored).expect("Failed to serialize analysis record")
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<AnalysisRecord, AnalysisStoreError> {
+        let stored: StoredAnalysisRecord = bincode::deserialize(bytes).map_err(|e| AnalysisStoreError::Corrupt(e.to_string()))?;
+        let pv = stored.pv.into_iter()
+            .map(|value| Move::from_u16(value).map_err(|e| AnalysisStoreError::Corrupt(e.to_string())))
+            .collect::<Result<Vec<Move>, AnalysisStoreError>>()?;
+        Ok(AnalysisRecord { depth: stored.depth, pv, score: stored.score, source: stored.source })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalysisStoreError {
+    /// The underlying storage failed.
+    Storage(String),
+    /// A stored record could not be decoded.
+    Corrupt(String),
+}
+
+impl Display for AnalysisStoreError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnalysisStoreError::Storage(error) => write!(f, "Analysis storage error: {}", error),
+            AnalysisStoreError::Corrupt(error) => write!(f, "Corrupt analysis record: {}", error),
+        }
+    }
+}
+
+impl Error for AnalysisStoreError {}
+
+/// Returns the key a position is stored under: the board, side to move, castling rights and en passant fields of its FEN.
+/// Unlike Zobrist hashes, these keys stay the same across runs and versions.
+pub fn get_analysis_key(state: &State) -> String {
+    state.to_fen().split_ascii_whitespace().take(4).collect::<Vec<&str>>().join(" ")
+}
+
+pub trait AnalysisStore {
+    /// Returns the record stored under `key`, if any.
+    fn load(&self, key: &str) -> Result<Option<AnalysisRecord>, AnalysisStoreError>;
+
+    /// Stores `record` under `key`, replacing any previous record.
+    fn store(&self, key: &str, record: &AnalysisRecord) -> Result<(), AnalysisStoreError>;
+
+    /// Returns the best known analysis of `state`, if any.
+    fn get(&self, state: &State) -> Result<Option<AnalysisRecord>, AnalysisStoreError> {
+        self.load(&get_analysis_key(state))
+    }
+
+    /// Stores `record` as the analysis of `state` if it supersedes the stored one.
+    /// Returns whether it was stored.
+    fn update(&self, state: &State, record: &AnalysisRecord) -> Result<bool, AnalysisStoreError> {
+        let key = get_analysis_key(state);
+        if let Some(existing) = self.load(&key)? {
+            if !record.supersedes(&existing) {
+                return Ok(false);
+            }
+        }
+        self.store(&key, record)?;
+        Ok(true)
+    }
+}
+
+/// An analysis store that only lives as long as the process.
+#[derive(Debug, Default)]
+pub struct MemoryAnalysisStore {
+    records: RefCell<HashMap<String, AnalysisRecord>>,
+}
+
+impl MemoryAnalysisStore {
+    pub fn new() -> MemoryAnalysisStore {
+        MemoryAnalysisStore::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.borrow().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.borrow().is_empty()
+    }
+}
+
+impl AnalysisStore for MemoryAnalysisStore {
+    fn load(&self, key: &str) -> Result<Option<AnalysisRecord>, AnalysisStoreError> {
+        Ok(self.records.borrow().get(key).cloned())
+    }
+
+    fn store(&self, key: &str, record: &AnalysisRecord) -> Result<(), AnalysisStoreError> {
+        self.records.borrow_mut().insert(key.to_string(), record.clone());
+        Ok(())
+    }
+}
+
+/// An analysis store in an embedded sled database on disk.
+#[cfg(feature = "analysis-db")]
+pub struct SledAnalysisStore {
+    db: sled::Db,
+}
+
+#[cfg(feature = "analysis-db")]
+impl SledAnalysisStore {
+    /// Opens the database at `path`, creating it if it does not exist.
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<SledAnalysisStore, AnalysisStoreError> {
+        let db = sled::open(path).map_err(|e| AnalysisStoreError::Storage(e.to_string()))?;
+        Ok(SledAnalysisStore { db })
+    }
+
+    /// Writes all pending changes to disk.
+    pub fn flush(&self) -> Result<(), AnalysisStoreError> {
+        self.db.flush().map(|_| ()).map_err(|e| AnalysisStoreError::Storage(e.to_string()))
+    }
+}
+
+#[cfg(feature = "analysis-db")]
+impl AnalysisStore for SledAnalysisStore {
+    fn load(&self, key: &str) -> Result<Option<AnalysisRecord>, AnalysisStoreError> {
+        match self.db.get(key).map_err(|e| AnalysisStoreError::Storage(e.to_string()))? {
+            Some(bytes) => AnalysisRecord::from_bytes(&bytes).map(Some),
+            None => Ok(None)
+        }
+    }
+
+    fn store(&self, key: &str, record: &AnalysisRecord) -> Result<(), AnalysisStoreError> {
+        self.db.insert(key, record.to_bytes()).map(|_| ()).map_err(|e| AnalysisStoreError::Storage(e.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dunck_core::r#move::MoveFlag;
+    use dunck_core::utils::Square;
+
+    fn make_record(depth: u32) -> AnalysisRecord {
+        AnalysisRecord {
+            depth,
+            pv: vec![
+                Move::new_non_promotion(Square::E4, Square::E2, MoveFlag::NormalMove),
+                Move::new_non_promotion(Square::E5, Square::E7, MoveFlag::NormalMove),
+            ],
+            score: 0.1,
+            source: "test".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_record_bytes_roundtrip() {
+        let record = make_record(100);
+        assert_eq!(AnalysisRecord::from_bytes(&record.to_bytes()).unwrap(), record);
+        assert!(matches!(AnalysisRecord::from_bytes(&[1, 2, 3]), Err(AnalysisStoreError::Corrupt(_))));
+    }
+
+    #[test]
+    fn test_analysis_key_ignores_move_counters() {
+        let state = State::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        let later_state = State::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 12 40").unwrap();
+        assert_eq!(get_analysis_key(&state), "4k3/8/8/8/8/8/8/4K2R w K -");
+        assert_eq!(get_analysis_key(&state), get_analysis_key(&later_state));
+    }
+
+    #[test]
+    fn test_update_keeps_deepest_analysis() {
+        let store = MemoryAnalysisStore::new();
+        let state = State::initial();
+        assert_eq!(store.get(&state).unwrap(), None);
+        assert!(store.update(&state, &make_record(100)).unwrap());
+        assert!(!store.update(&state, &make_record(50)).unwrap());
+        assert_eq!(store.get(&state).unwrap().unwrap().depth, 100);
+        assert!(store.update(&state, &make_record(200)).unwrap());
+        assert_eq!(store.get(&state).unwrap().unwrap().depth, 200);
+        assert_eq!(store.len(), 1);
+    }
+
+    #[cfg(feature = "analysis-db")]
+    #[test]
+    fn test_sled_store_persists() {
+        let path = std::env::temp_dir().join(format!("dunck_analysis_db_{}", std::process::id()));
+        let state = State::initial();
+        {
+            let store = SledAnalysisStore::open(&path).unwrap();
+            assert!(store.update(&state, &make_record(100)).unwrap());
+            store.flush().unwrap();
+        }
+        let store = SledAnalysisStore::open(&path).unwrap();
+        assert_eq!(store.get(&state).unwrap(), Some(make_record(100)));
+        drop(store);
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
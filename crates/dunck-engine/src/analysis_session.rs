@@ -0,0 +1,619 @@
+//! A long-lived analysis session over a single game, reusing the search tree as moves are played.
+
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+use std::rc::Rc;
+use crate::analysis_db::{AnalysisRecord, AnalysisStore, AnalysisStoreError};
+use crate::analysis_checkpoint::{AnalysisCheckpoint, AnalysisCheckpointError, ChildSummary, ANALYSIS_CHECKPOINT_VERSION};
+use crate::engine_options::EngineOptions;
+use crate::evaluation::Evaluator;
+use crate::fortress::FortressDetector;
+use crate::manifest::EngineManifest;
+use crate::mcts::mcts::MCTS;
+use crate::mcts::mcts_node::MCTSNode;
+use crate::tablebase::{apply_tablebase_root_filter, TablebaseProber};
+use dunck_core::r#move::Move;
+use dunck_core::state::{MoveHistory, State};
+use dunck_core::utils::{Bitboard, Color, ColoredPiece, Square};
+
+/// The source recorded for analysis produced by a session.
+pub const ANALYSIS_SOURCE: &str = "dunck mcts";
+/// How many search trees of positions edited away from are kept, so that memory stays bounded while editing.
+/// The least recently kept tree is dropped first.
+pub const MAX_EDITED_TREES: usize = 4;
+
+/// Describes what happened to the search tree when a move was played in an `AnalysisSession`.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum TreeReuse {
+    /// The engine's expected move was played, and its subtree became the new root.
+    Expected,
+    /// A different move was played, but it had already been searched, so its subtree was salvaged.
+    Salvaged,
+    /// The move had not been searched, so the tree was discarded.
+    Discarded
+}
+
+pub struct AnalysisSession<'a> {
+    pub mcts: MCTS<'a>,
+    pub options: EngineOptions,
+    /// Free-form notes about the analysis, kept in checkpoints.
+    pub notes: Vec<String>,
+    /// The build, model and settings of the analysis, kept in checkpoints.
+    pub manifest: Option<EngineManifest>,
+    /// Watches the analyzed positions for fortresses, and adjusts reported scores if configured to.
+    pub fortress_detector: FortressDetector,
+    /// The moves played in the session, which can be taken back and redone.
+    history: MoveHistory,
+    tablebase: Option<&'a dyn TablebaseProber>,
+    analysis_store: Option<&'a dyn AnalysisStore>,
+    is_root_filtered: bool,
+    /// The search trees of positions edited away from, with their Zobrist hashes, so that an edit leading back to one
+    /// of them resumes its search. Least recently kept first, and at most `MAX_EDITED_TREES` of them.
+    /// Trees of positions that cannot arise from the edited position are dropped on every edit.
+    edited_trees: Vec<(Bitboard, Rc<RefCell<MCTSNode>>)>
+}
+
+impl<'a> AnalysisSession<'a> {
+    pub fn new(
+        state: State,
+        exploration_param: f64,
+        evaluator: &'a dyn Evaluator,
+        calc_node_score: &'static dyn Fn(&MCTSNode, u32, f64) -> f64
+    ) -> Self {
+        Self {
+            mcts: MCTS::new(state.clone(), exploration_param, evaluator, calc_node_score, false),
+            options: EngineOptions::default(),
+            notes: Vec::new(),
+            manifest: None,
+            fortress_detector: FortressDetector::default(),
+            history: MoveHistory::new(state),
+            tablebase: None,
+            analysis_store: None,
+            is_root_filtered: false,
+            edited_trees: Vec::new()
+        }
+    }
+
+    /// Writes the position, settings, root statistics and notes to `path`, so that analysis can be resumed
+    /// with `restore`.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AnalysisCheckpointError> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, &self.to_checkpoint())?;
+        Ok(())
+    }
+
+    /// Resumes a session saved with `save`. The evaluator, node scoring function and tablebase are not saved,
+    /// so they have to be provided again.
+    pub fn restore(
+        path: impl AsRef<Path>,
+        evaluator: &'a dyn Evaluator,
+        calc_node_score: &'static dyn Fn(&MCTSNode, u32, f64) -> f64
+    ) -> Result<Self, AnalysisCheckpointError> {
+        let reader = BufReader::new(File::open(path)?);
+        let checkpoint: AnalysisCheckpoint = serde_json::from_reader(reader)?;
+        Self::from_checkpoint(&checkpoint, evaluator, calc_node_score)
+    }
+
+    pub fn to_checkpoint(&self) -> AnalysisCheckpoint {
+        let root = self.mcts.root.borrow();
+        AnalysisCheckpoint {
+            version: ANALYSIS_CHECKPOINT_VERSION,
+            fen: root.state_after_move.to_fen(),
+            exploration_param: self.mcts.exploration_param,
+            analysis_mode: self.options.analysis_mode,
+            is_root_filtered: self.is_root_filtered,
+            root_visits: root.visits,
+            root_value: root.value,
+            children: root.children.iter().map(|child| {
+                let child = child.borrow();
+                ChildSummary { mv: child.mv.unwrap().to_u16(), visits: child.visits, value: child.value, prior: child.prior }
+            }).collect(),
+            notes: self.notes.clone(),
+            manifest: self.manifest.clone(),
+        }
+    }
+
+    pub fn from_checkpoint(
+        checkpoint: &AnalysisCheckpoint,
+        evaluator: &'a dyn Evaluator,
+        calc_node_score: &'static dyn Fn(&MCTSNode, u32, f64) -> f64
+    ) -> Result<Self, AnalysisCheckpointError> {
+        if checkpoint.version != ANALYSIS_CHECKPOINT_VERSION {
+            return Err(AnalysisCheckpointError::UnsupportedVersion(checkpoint.version));
+        }
+        let state = State::from_fen(&checkpoint.fen).map_err(AnalysisCheckpointError::InvalidFen)?;
+        let mut session = Self::new(state.clone(), checkpoint.exploration_param, evaluator, calc_node_score);
+        session.options.analysis_mode = checkpoint.analysis_mode;
+        session.is_root_filtered = checkpoint.is_root_filtered;
+        session.notes = checkpoint.notes.clone();
+        session.manifest = checkpoint.manifest.clone();
+
+        let root = session.mcts.root.clone();
+        let mut children = Vec::with_capacity(checkpoint.children.len());
+        for summary in checkpoint.children.iter() {
+            let mv = Move::from_u16(summary.mv).map_err(AnalysisCheckpointError::InvalidMove)?;
+            let mut state_after_move = state.clone();
+            state_after_move.make_move(mv);
+            let mut child = MCTSNode::new(Some(mv), Some(root.clone()), state_after_move);
+            child.visits = summary.visits;
+            child.value = summary.value;
+            child.prior = summary.prior;
+            children.push(Rc::new(RefCell::new(child)));
+        }
+
+        let mut root = root.borrow_mut();
+        root.visits = checkpoint.root_visits;
+        root.value = checkpoint.root_value;
+        root.is_expanded = !children.is_empty();
+        root.children = children;
+        drop(root);
+        Ok(session)
+    }
+
+    /// Uses `tablebase` to restrict the root moves before searching, unless in analysis mode.
+    pub fn with_tablebase(mut self, tablebase: &'a dyn TablebaseProber) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    /// Consults `analysis_store` in `analyze_with_store`, and records the results there.
+    pub fn with_analysis_store(mut self, analysis_store: &'a dyn AnalysisStore) -> Self {
+        self.analysis_store = Some(analysis_store);
+        self
+    }
+
+    /// Turns analysis mode on or off. In analysis mode, the search is never cut off by a tablebase.
+    /// Turning it on discards a root that was already restricted, so that every move is searched again.
+    pub fn set_analysis_mode(&mut self, analysis_mode: bool) {
+        if analysis_mode && self.is_root_filtered {
+            let state = self.get_state();
+            self.mcts.root = Rc::new(RefCell::new(MCTSNode::new(None, None, state)));
+            self.is_root_filtered = false;
+        }
+        self.options.analysis_mode = analysis_mode;
+    }
+
+    /// Returns the position currently being analyzed.
+    pub fn get_state(&self) -> State {
+        self.mcts.root.borrow().state_after_move.clone()
+    }
+
+    /// Continues deepening the search from the current position.
+    pub fn analyze(&mut self, iterations: usize) {
+        if let Some(tablebase) = self.tablebase {
+            if self.options.uses_tablebase_cutoffs() && !self.is_root_filtered {
+                self.is_root_filtered = apply_tablebase_root_filter(&mut self.mcts, tablebase);
+            }
+        }
+        self.mcts.run(iterations);
+        self.observe_fortress();
+    }
+
+    /// Feeds the static evaluation and search score of the current position to the fortress detector.
+    fn observe_fortress(&mut self) {
+        let Some(best_child) = self.mcts.get_best_child_by_visits() else {
+            return;
+        };
+        let best_child = best_child.borrow();
+        if best_child.visits == 0 {
+            return;
+        }
+        let state = self.get_state();
+        let static_value = self.mcts.evaluator.evaluate(&state).value;
+        self.fortress_detector.observe(&state, static_value, best_child.value / best_child.visits as f64);
+    }
+
+    /// Returns whether the game seems stuck in a fortress, with an advantage that neither the moves played
+    /// nor the search manage to convert.
+    pub fn is_fortress_suspected(&self) -> bool {
+        self.fortress_detector.is_fortress_suspected()
+    }
+
+    /// Like `analyze`, but returns the stored analysis of the position instead of searching if it is
+    /// at least as deep as the search would be. Otherwise, searches and records the result in the analysis store.
+    /// Returns None if no move has been searched, e.g. in a terminal position.
+    pub fn analyze_with_store(&mut self, iterations: usize) -> Result<Option<AnalysisRecord>, AnalysisStoreError> {
+        let Some(analysis_store) = self.analysis_store else {
+            self.analyze(iterations);
+            return Ok(self.get_analysis_record());
+        };
+
+        let state = self.get_state();
+        let target_depth = self.mcts.root.borrow().visits.saturating_add(iterations as u32);
+        if let Some(record) = analysis_store.get(&state)? {
+            if record.depth >= target_depth {
+                return Ok(Some(record));
+            }
+        }
+
+        self.analyze(iterations);
+        let record = self.get_analysis_record();
+        if let Some(record) = &record {
+            analysis_store.update(&state, record)?;
+        }
+        Ok(record)
+    }
+
+    /// Returns the current analysis of the position, or None if no move has been searched.
+    pub fn get_analysis_record(&self) -> Option<AnalysisRecord> {
+        let best_child = self.mcts.get_best_child_by_visits()?;
+        let best_child = best_child.borrow();
+        if best_child.visits == 0 {
+            return None;
+        }
+        let mut pv = vec![best_child.mv.unwrap()];
+        pv.extend(best_child.get_principal_variation());
+        Some(AnalysisRecord {
+            depth: self.mcts.root.borrow().visits,
+            pv,
+            // the child's value is from the perspective of the side to move at the root
+            score: self.fortress_detector.adjust_score(best_child.value / best_child.visits as f64, self.mcts.outcome_scores.draw),
+            source: ANALYSIS_SOURCE.to_string(),
+        })
+    }
+
+    /// Returns the move the engine currently expects to be played, i.e. the most visited child of the root.
+    pub fn get_expected_move(&self) -> Option<Move> {
+        self.mcts.get_best_child_by_visits().and_then(|child| child.borrow().mv)
+    }
+
+    /// Plays `mv` in the analyzed position.
+    /// If `mv` has already been searched, its subtree becomes the new root so that its statistics are kept;
+    /// otherwise, the search starts over from the new position.
+    pub fn make_move(&mut self, mv: Move) -> Result<TreeReuse, String> {
+        self.history.make_move(mv)?;
+        Ok(self.advance_root(mv))
+    }
+
+    /// Returns the moves played in the session, which can be taken back and redone.
+    pub fn get_history(&self) -> &MoveHistory {
+        &self.history
+    }
+
+    /// Takes back the last move, returning it, or None if no move has been played.
+    /// The search starts over from the previous position.
+    pub fn undo(&mut self) -> Option<Move> {
+        let mv = self.history.undo()?;
+        self.reset_root();
+        Some(mv)
+    }
+
+    /// Plays the last move taken back again, reusing the search tree like `make_move`.
+    /// Returns None if there is no move to redo.
+    pub fn redo(&mut self) -> Option<(Move, TreeReuse)> {
+        let mv = self.history.redo()?;
+        Some((mv, self.advance_root(mv)))
+    }
+
+    /// Goes to the position after `ply` moves of the current line of the history. The search starts over
+    /// from that position, unless it is the current one.
+    pub fn jump_to_ply(&mut self, ply: usize) -> Result<(), String> {
+        if ply == self.history.get_ply() {
+            return Ok(());
+        }
+        self.history.jump_to_ply(ply)?;
+        self.reset_root();
+        Ok(())
+    }
+
+    /// Puts `colored_piece` on `square`, replacing whatever was there, or empties it with `ColoredPiece::NoPiece`.
+    /// Castling rights the edit invalidates are dropped. See `set_position` for what happens to the search.
+    pub fn set_piece(&mut self, square: Square, colored_piece: ColoredPiece) -> Result<(), String> {
+        let mut state = self.get_state();
+        let existing_piece = state.board.get_colored_piece_at(square);
+        if existing_piece != ColoredPiece::NoPiece {
+            state.board.remove_colored_piece_at(existing_piece, square);
+        }
+        if colored_piece != ColoredPiece::NoPiece {
+            state.board.put_colored_piece_at(colored_piece, square);
+        }
+        self.set_edited_position(&state, state.side_to_move)
+    }
+
+    /// Gives the move to `color`. See `set_position` for what happens to the search.
+    pub fn set_side_to_move(&mut self, color: Color) -> Result<(), String> {
+        let state = self.get_state();
+        self.set_edited_position(&state, color)
+    }
+
+    /// Rebuilds `state` with `side_to_move` from its FEN, so that the hashes and castling rights match the edited board.
+    /// The en passant square is dropped, since the edit may have removed the pawn or the capturer.
+    fn set_edited_position(&mut self, state: &State, side_to_move: Color) -> Result<(), String> {
+        let fen = state.to_fen();
+        let mut fields: Vec<&str> = fen.split(' ').collect();
+        fields[1] = if side_to_move == Color::White { "w" } else { "b" };
+        fields[3] = "-";
+        let edited_fen = fields.join(" ");
+        let edited_state = State::from_fen_with_castling_repair(&edited_fen)
+            .map_err(|e| format!("Invalid position after the edit: {}", e))?;
+        self.set_position(edited_state);
+        Ok(())
+    }
+
+    /// Starts analyzing `state` with a new history, e.g. after editing the board.
+    /// The current search tree is kept aside, and trees kept aside for positions that cannot arise from `state`,
+    /// by material or castling rights, are dropped, as is the least recently kept one beyond `MAX_EDITED_TREES`.
+    /// If a tree was kept aside for `state` itself, the search resumes from it.
+    pub fn set_position(&mut self, state: State) {
+        let old_root = self.mcts.root.clone();
+        if old_root.borrow().visits > 0 {
+            let hash = old_root.borrow().state_after_move.get_zobrist_hash();
+            self.edited_trees.retain(|(kept_hash, _)| *kept_hash != hash);
+            self.edited_trees.push((hash, old_root));
+            if self.edited_trees.len() > MAX_EDITED_TREES {
+                self.edited_trees.remove(0);
+            }
+        }
+
+        let material = state.get_material_signature();
+        let castling_rights = state.context.borrow().castling_rights;
+        self.edited_trees.retain(|(_, root)| {
+            let root = root.borrow();
+            let root_castling_rights = root.state_after_move.context.borrow().castling_rights;
+            material.can_reach(&root.state_after_move.get_material_signature()) && root_castling_rights & !castling_rights == 0
+        });
+
+        let hash = state.get_zobrist_hash();
+        let kept_tree = self.edited_trees.iter().position(|(kept_hash, _)| *kept_hash == hash)
+            .map(|index| self.edited_trees.remove(index).1)
+            .filter(|root| root.borrow().state_after_move.board == state.board);
+        self.mcts.root = kept_tree.unwrap_or_else(|| Rc::new(RefCell::new(MCTSNode::new(None, None, state.clone()))));
+        self.history = MoveHistory::new(state);
+        self.is_root_filtered = false;
+        self.fortress_detector.reset();
+    }
+
+    fn reset_root(&mut self) {
+        self.mcts.root = Rc::new(RefCell::new(MCTSNode::new(None, None, self.history.get_state().clone())));
+        self.is_root_filtered = false;
+    }
+
+    /// Makes the search tree follow `mv`, which must be legal in the current position.
+    fn advance_root(&mut self, mv: Move) -> TreeReuse {
+        let state = self.get_state();
+        self.is_root_filtered = false;
+        let expected_move = self.get_expected_move();
+        if self.mcts.root.borrow().is_expanded && self.mcts.take_child_with_move(mv, false).is_ok() {
+            return if expected_move == Some(mv) { TreeReuse::Expected } else { TreeReuse::Salvaged };
+        }
+
+        let mut new_state = state;
+        new_state.make_move(mv);
+        self.mcts.root = Rc::new(RefCell::new(MCTSNode::new(None, None, new_state)));
+        TreeReuse::Discarded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis_db::MemoryAnalysisStore;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::mcts::mcts::calc_uct_score;
+    use crate::tablebase::Wdl;
+    use dunck_core::r#move::MoveFlag;
+    use dunck_core::utils::{Color, PieceType, Square};
+
+    #[test]
+    fn test_make_move_reuses_tree() {
+        let evaluator = MaterialEvaluator {};
+        let mut session = AnalysisSession::new(State::initial(), 1.5, &evaluator, &calc_uct_score);
+        session.analyze(200);
+
+        let expected_move = session.get_expected_move().unwrap();
+        let expected_visits = session.mcts.get_best_child_by_visits().unwrap().borrow().visits;
+        assert_eq!(session.make_move(expected_move), Ok(TreeReuse::Expected));
+        assert_eq!(session.mcts.root.borrow().visits, expected_visits);
+        assert!(session.mcts.root.borrow().previous_node.is_none());
+
+        session.analyze(200);
+        let expected_move = session.get_expected_move();
+        let salvaged_move = session.mcts.root.borrow().children.iter()
+            .map(|child| child.borrow().mv.unwrap())
+            .find(|mv| Some(*mv) != expected_move)
+            .unwrap();
+        assert_eq!(session.make_move(salvaged_move), Ok(TreeReuse::Salvaged));
+
+        assert_eq!(session.get_state().halfmove, 2);
+    }
+
+    #[test]
+    fn test_make_move_discards_unsearched_tree() {
+        let evaluator = MaterialEvaluator {};
+        let mut session = AnalysisSession::new(State::initial(), 1.5, &evaluator, &calc_uct_score);
+        let mv = Move::new_non_promotion(Square::E4, Square::E2, MoveFlag::NormalMove);
+        assert_eq!(session.make_move(mv), Ok(TreeReuse::Discarded));
+        assert_eq!(session.mcts.root.borrow().visits, 0);
+        assert_eq!(session.get_state().halfmove, 1);
+    }
+
+    #[test]
+    fn test_undo_and_redo() {
+        let evaluator = MaterialEvaluator {};
+        let mut session = AnalysisSession::new(State::initial(), 1.5, &evaluator, &calc_uct_score);
+        assert_eq!(session.undo(), None);
+        session.analyze(200);
+        let expected_move = session.get_expected_move().unwrap();
+        session.make_move(expected_move).unwrap();
+        session.analyze(200);
+
+        assert_eq!(session.undo(), Some(expected_move));
+        assert_eq!(session.get_state().to_fen(), State::initial().to_fen());
+        assert_eq!(session.mcts.root.borrow().visits, 0);
+
+        session.analyze(200);
+        let expected_move = session.get_expected_move().unwrap();
+        assert_eq!(session.redo(), Some((expected_move, TreeReuse::Expected)));
+        assert_eq!(session.redo(), None);
+
+        let reply = session.get_state().calc_legal_moves()[0];
+        session.make_move(reply).unwrap();
+        session.jump_to_ply(0).unwrap();
+        assert_eq!(session.get_history().get_line(), [expected_move, reply]);
+        assert!(session.jump_to_ply(3).is_err());
+        session.jump_to_ply(2).unwrap();
+        assert_eq!(session.get_state().halfmove, 2);
+    }
+
+    #[test]
+    fn test_edits_keep_reachable_trees() {
+        let evaluator = MaterialEvaluator {};
+        let mut session = AnalysisSession::new(State::initial(), 1.5, &evaluator, &calc_uct_score);
+        session.analyze(100);
+
+        session.set_side_to_move(Color::Black).unwrap();
+        assert_eq!(session.get_state().side_to_move, Color::Black);
+        assert_eq!(session.mcts.root.borrow().visits, 0);
+        assert_eq!(session.get_history().get_ply(), 0);
+        session.analyze(50);
+
+        // the initial position can arise from the edited one, so its search resumes
+        session.set_side_to_move(Color::White).unwrap();
+        assert_eq!(session.get_state().to_fen(), State::initial().to_fen());
+        assert_eq!(session.mcts.root.borrow().visits, 100);
+
+        // removing a pawn makes both earlier positions unreachable, so their trees are dropped
+        session.set_piece(Square::E2, ColoredPiece::NoPiece).unwrap();
+        assert_eq!(session.mcts.root.borrow().visits, 0);
+        session.set_piece(Square::E2, ColoredPiece::WhitePawn).unwrap();
+        assert_eq!(session.mcts.root.borrow().visits, 0);
+        assert_eq!(session.get_state().to_fen(), State::initial().to_fen());
+
+        // removing a rook drops its castling right, and leaving the side not to move in check is rejected
+        session.set_piece(Square::H1, ColoredPiece::NoPiece).unwrap();
+        assert_eq!(session.get_state().to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w Qkq - 0 1");
+        assert!(session.set_piece(Square::F7, ColoredPiece::WhiteQueen).is_err());
+        assert_eq!(session.get_state().to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBN1 w Qkq - 0 1");
+        session.set_side_to_move(Color::Black).unwrap();
+        session.set_piece(Square::F7, ColoredPiece::WhiteQueen).unwrap();
+        assert!(session.set_side_to_move(Color::White).is_err());
+    }
+
+    #[test]
+    fn test_edits_keep_bounded_trees() {
+        let evaluator = MaterialEvaluator {};
+        let state = State::from_fen("4k3/8/8/8/8/R7/8/4K3 w - - 0 1").unwrap();
+        let mut session = AnalysisSession::new(state, 1.5, &evaluator, &calc_uct_score);
+        let squares = [Square::A3, Square::B3, Square::C3, Square::D3, Square::F3, Square::G3, Square::H3, Square::A4, Square::B4];
+        for (from, to) in squares.iter().zip(squares.iter().skip(1)) {
+            session.analyze(20);
+            // moving the rook keeps every earlier position reachable, so only the cap drops trees
+            session.set_piece(*to, ColoredPiece::WhiteRook).unwrap();
+            session.set_piece(*from, ColoredPiece::NoPiece).unwrap();
+            assert!(session.edited_trees.len() <= MAX_EDITED_TREES);
+        }
+        assert_eq!(session.edited_trees.len(), MAX_EDITED_TREES);
+
+        // the most recent trees are kept, and the oldest ones were dropped
+        session.set_piece(Square::H3, ColoredPiece::WhiteRook).unwrap();
+        session.set_piece(Square::B4, ColoredPiece::NoPiece).unwrap();
+        assert_eq!(session.mcts.root.borrow().visits, 20);
+        session.set_piece(Square::A3, ColoredPiece::WhiteRook).unwrap();
+        session.set_piece(Square::H3, ColoredPiece::NoPiece).unwrap();
+        assert_eq!(session.mcts.root.borrow().visits, 0);
+    }
+
+    /// Only allows king moves towards the h-file, as if everything else lost.
+    struct KingSideTablebase {}
+
+    impl TablebaseProber for KingSideTablebase {
+        fn probe_wdl(&self, state: &State) -> Option<Wdl> {
+            let kings = state.board.piece_type_masks[PieceType::King as usize] & state.board.color_masks[Color::White as usize];
+            Some(if state.side_to_move == Color::White { Wdl::Win } else if kings & Square::G1.get_mask() != 0 { Wdl::Loss } else { Wdl::Win })
+        }
+
+        fn probe_dtz(&self, _state: &State) -> Option<i32> {
+            Some(1)
+        }
+    }
+
+    #[test]
+    fn test_analysis_mode_disables_tablebase_cutoffs() {
+        let evaluator = MaterialEvaluator {};
+        let tablebase = KingSideTablebase {};
+        let state = State::from_fen("7k/8/8/8/8/8/8/5K2 w - - 0 1").unwrap();
+
+        let mut session = AnalysisSession::new(state.clone(), 1.5, &evaluator, &calc_uct_score).with_tablebase(&tablebase);
+        session.analyze(50);
+        assert_eq!(session.mcts.root.borrow().children.len(), 1);
+
+        session.set_analysis_mode(true);
+        session.analyze(50);
+        assert_eq!(session.mcts.root.borrow().children.len(), state.calc_legal_moves().len());
+    }
+
+    #[test]
+    fn test_save_and_restore() {
+        let evaluator = MaterialEvaluator {};
+        let mut session = AnalysisSession::new(State::initial(), 1.5, &evaluator, &calc_uct_score);
+        session.set_analysis_mode(true);
+        session.notes.push("main line".to_string());
+        session.manifest = Some(EngineManifest::new().with_parameter("exploration_param", 1.5));
+        session.analyze(200);
+
+        let path = std::env::temp_dir().join(format!("dunck_analysis_checkpoint_{}.json", std::process::id()));
+        session.save(&path).unwrap();
+        let mut restored = AnalysisSession::restore(&path, &evaluator, &calc_uct_score).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(restored.to_checkpoint(), session.to_checkpoint());
+        assert_eq!(restored.get_expected_move(), session.get_expected_move());
+        assert!(restored.options.analysis_mode);
+        assert_eq!(restored.manifest, session.manifest);
+
+        restored.analyze(100);
+        assert_eq!(restored.mcts.root.borrow().visits, session.mcts.root.borrow().visits + 100);
+    }
+
+    #[test]
+    fn test_analyze_with_store() {
+        let evaluator = MaterialEvaluator {};
+        let store = MemoryAnalysisStore::new();
+        let mut session = AnalysisSession::new(State::initial(), 1.5, &evaluator, &calc_uct_score).with_analysis_store(&store);
+        let record = session.analyze_with_store(200).unwrap().unwrap();
+        assert_eq!(record.depth, session.mcts.root.borrow().visits);
+        assert_eq!(record.pv.first().copied(), session.get_expected_move());
+        assert_eq!(store.get(&State::initial()).unwrap(), Some(record.clone()));
+
+        // a fresh session on the same position is answered from the store without searching
+        let mut session = AnalysisSession::new(State::initial(), 1.5, &evaluator, &calc_uct_score).with_analysis_store(&store);
+        assert_eq!(session.analyze_with_store(100).unwrap(), Some(record.clone()));
+        assert_eq!(session.mcts.root.borrow().visits, 0);
+
+        // asking for a deeper search than stored searches and replaces the record
+        let deeper_record = session.analyze_with_store(500).unwrap().unwrap();
+        assert!(deeper_record.depth > record.depth);
+        assert_eq!(store.get(&State::initial()).unwrap(), Some(deeper_record));
+    }
+
+    #[test]
+    fn test_fortress_suspected_while_shuffling() {
+        // a rook pawn with the wrong bishop, where white stays a piece and a pawn up without getting anywhere
+        let evaluator = MaterialEvaluator {};
+        let state = State::from_fen("7k/8/5K2/7P/8/8/8/B7 w - - 30 60").unwrap();
+        let mut session = AnalysisSession::new(state, 1.5, &evaluator, &calc_uct_score);
+        session.fortress_detector.min_plies = 40;
+        session.fortress_detector.max_score_change = 2.;
+        session.fortress_detector.draw_blend = 1.;
+        for uci in ["a1b2", "h8g8", "b2c1", "g8h8", "c1d2", "h8g8", "d2e1", "g8h8", "e1f2", "h8g8"] {
+            session.analyze(20);
+            let mv = Move::from_uci(&session.get_state(), uci).unwrap();
+            session.make_move(mv).unwrap();
+        }
+        assert!(!session.is_fortress_suspected());
+
+        session.analyze(20);
+        assert!(session.is_fortress_suspected());
+        assert_eq!(session.get_analysis_record().unwrap().score, 0.);
+    }
+
+    #[test]
+    fn test_make_move_rejects_illegal_move() {
+        let evaluator = MaterialEvaluator {};
+        let mut session = AnalysisSession::new(State::initial(), 1.5, &evaluator, &calc_uct_score);
+        let illegal_move = Move::new_non_promotion(Square::E5, Square::E2, MoveFlag::NormalMove);
+        assert!(session.make_move(illegal_move).is_err());
+    }
+}
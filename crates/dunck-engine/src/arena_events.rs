@@ -0,0 +1,103 @@
+//! Per-move events emitted while arena games are played, so matches can be spectated as they happen.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::time::Duration;
+use crate::gating::Adjudication;
+use dunck_core::r#move::Move;
+
+/// A move played in an arena game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArenaMoveEvent {
+    pub game_index: usize,
+    /// The number of halfmoves played in the game, including this one.
+    pub ply: usize,
+    pub mv: Move,
+    /// The move in standard algebraic notation.
+    pub san: String,
+    /// The position after the move.
+    pub fen: String,
+    /// The search's estimate of the position after the move, from white's perspective, if the move was visited.
+    pub eval: Option<f64>,
+    /// The number of visits the move received.
+    pub visits: u32,
+    /// The time spent searching this move.
+    pub move_time: Duration,
+    /// The total time spent searching by white and black so far, indexed by `Color`.
+    pub clocks: [Duration; 2],
+}
+
+/// Something that happened in an arena match.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArenaEvent {
+    GameStarted { game_index: usize, initial_fen: String },
+    MovePlayed(ArenaMoveEvent),
+    /// The game ended with `result` from white's perspective: 1, 0, or -1,
+    /// either by being played out or by `adjudication`.
+    GameFinished { game_index: usize, result: i8, num_moves: usize, adjudication: Option<Adjudication> },
+}
+
+/// Receives arena events. Called on the thread playing the games, so implementations should return quickly.
+pub trait ArenaObserver {
+    fn on_event(&self, event: ArenaEvent);
+}
+
+/// An observer that ignores every event.
+pub struct NoArenaObserver;
+
+impl ArenaObserver for NoArenaObserver {
+    fn on_event(&self, _event: ArenaEvent) {}
+}
+
+/// Forwards events to a bounded channel without ever blocking the games.
+/// Events that do not fit because the receiver is falling behind are dropped and counted.
+pub struct ChannelArenaObserver {
+    sender: SyncSender<ArenaEvent>,
+    num_dropped: AtomicUsize,
+}
+
+impl ChannelArenaObserver {
+    /// Creates an observer and the receiving end of its channel, which buffers up to `capacity` events.
+    pub fn new(capacity: usize) -> (ChannelArenaObserver, Receiver<ArenaEvent>) {
+        let (sender, receiver) = sync_channel(capacity);
+        (ChannelArenaObserver { sender, num_dropped: AtomicUsize::new(0) }, receiver)
+    }
+
+    /// Returns the number of events dropped because the channel was full.
+    pub fn get_num_dropped(&self) -> usize {
+        self.num_dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl ArenaObserver for ChannelArenaObserver {
+    fn on_event(&self, event: ArenaEvent) {
+        match self.sender.try_send(event) {
+            Ok(()) | Err(TrySendError::Disconnected(_)) => {},
+            Err(TrySendError::Full(_)) => {
+                self.num_dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channel_observer_drops_when_full() {
+        let (observer, receiver) = ChannelArenaObserver::new(2);
+        for game_index in 0..3 {
+            observer.on_event(ArenaEvent::GameStarted { game_index, initial_fen: String::new() });
+        }
+        assert_eq!(observer.get_num_dropped(), 1);
+        assert_eq!(receiver.try_iter().count(), 2);
+
+        observer.on_event(ArenaEvent::GameFinished { game_index: 0, result: 0, num_moves: 0, adjudication: None });
+        assert_eq!(receiver.try_recv().unwrap(), ArenaEvent::GameFinished { game_index: 0, result: 0, num_moves: 0, adjudication: None });
+
+        drop(receiver);
+        observer.on_event(ArenaEvent::GameStarted { game_index: 3, initial_fen: String::new() });
+        assert_eq!(observer.get_num_dropped(), 1);
+    }
+}
@@ -0,0 +1,660 @@
+//! Building training datasets from labeled positions, merging duplicates of the same position
+//! and weighting them by frequency, so that common opening positions do not dominate training batches.
+//! Datasets can be split into train, validation and test sets by game, without positions shared between them.
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+use rand::Rng;
+use crate::evaluation::Evaluation;
+use dunck_core::pgn::PgnStateTree;
+use dunck_core::state::{MaterialSignature, State};
+use dunck_core::state::get_see_value;
+use dunck_core::utils::{Bitboard, Checksum, Color, PieceType};
+
+/// Identifies a position by its board, side to move, castling rights and double pawn push file.
+pub type PositionKey = (Bitboard, Color, u8, i8);
+
+pub fn get_position_key(state: &State) -> PositionKey {
+    let context = state.context.borrow();
+    (state.board.zobrist_hash, state.side_to_move, context.castling_rights, context.double_pawn_push)
+}
+
+/// A unique position with its labels averaged over all of its occurrences.
+#[derive(Debug, Clone)]
+pub struct TrainingSample {
+    pub state: State,
+    pub evaluation: Evaluation,
+    /// The number of times the position was added.
+    pub count: u32,
+    /// The relative probability of sampling the position, with a mean of 1 over the dataset.
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DedupStats {
+    pub num_added: usize,
+    pub num_unique: usize,
+}
+
+impl DedupStats {
+    /// The fraction of added samples that were duplicates.
+    pub fn get_dedup_rate(&self) -> f64 {
+        if self.num_added == 0 {
+            0.
+        } else {
+            1. - self.num_unique as f64 / self.num_added as f64
+        }
+    }
+}
+
+/// The kind of endgame a position is in, by which piece types other than kings and pawns are left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EndgameClass {
+    /// Only kings and pawns.
+    Pawn,
+    /// Knights and bishops only.
+    Minor,
+    /// Rooks only.
+    Rook,
+    /// Queens only.
+    Queen,
+    /// Any other material, including positions that are not endgames at all.
+    Mixed,
+}
+
+impl EndgameClass {
+    pub const ALL: [EndgameClass; 5] = [EndgameClass::Pawn, EndgameClass::Minor, EndgameClass::Rook, EndgameClass::Queen, EndgameClass::Mixed];
+
+    pub fn from_signature(signature: &MaterialSignature) -> EndgameClass {
+        let has_piece_type = |piece_type: PieceType| {
+            signature.get_count(Color::White, piece_type) > 0 || signature.get_count(Color::Black, piece_type) > 0
+        };
+        let has_minors = has_piece_type(PieceType::Knight) || has_piece_type(PieceType::Bishop);
+        match (has_minors, has_piece_type(PieceType::Rook), has_piece_type(PieceType::Queen)) {
+            (false, false, false) => EndgameClass::Pawn,
+            (true, false, false) => EndgameClass::Minor,
+            (false, true, false) => EndgameClass::Rook,
+            (false, false, true) => EndgameClass::Queen,
+            _ => EndgameClass::Mixed
+        }
+    }
+}
+
+impl Display for EndgameClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            EndgameClass::Pawn => "pawn",
+            EndgameClass::Minor => "minor",
+            EndgameClass::Rook => "rook",
+            EndgameClass::Queen => "queen",
+            EndgameClass::Mixed => "mixed",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Selects positions by their material, e.g. to build a dataset or test suite of rook endings only.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MaterialFilter {
+    /// Positions with exactly this material, with either color having either side's pieces.
+    Signature(MaterialSignature),
+    Class(EndgameClass),
+    /// Positions with at most this many pieces, including kings.
+    MaxPieces(u32),
+}
+
+impl MaterialFilter {
+    pub fn matches(&self, state: &State) -> bool {
+        let signature = state.get_material_signature();
+        match self {
+            MaterialFilter::Signature(expected) => expected.matches_either_color(&signature),
+            MaterialFilter::Class(class) => EndgameClass::from_signature(&signature) == *class,
+            MaterialFilter::MaxPieces(max_pieces) => signature.get_num_pieces() <= *max_pieces,
+        }
+    }
+}
+
+impl FromStr for MaterialFilter {
+    type Err = String;
+
+    /// Parses an endgame class name such as `rook`, a piece limit such as `pieces<=5`,
+    /// or a material signature such as `KRPvKR`.
+    fn from_str(s: &str) -> Result<MaterialFilter, String> {
+        let s = s.trim();
+        if let Some(class) = EndgameClass::ALL.iter().find(|class| class.to_string().eq_ignore_ascii_case(s)) {
+            return Ok(MaterialFilter::Class(*class));
+        }
+        if let Some(max_pieces) = s.strip_prefix("pieces<=") {
+            return max_pieces.trim().parse()
+                .map(MaterialFilter::MaxPieces)
+                .map_err(|e| format!("Invalid piece limit {}: {}", max_pieces, e));
+        }
+        s.parse().map(MaterialFilter::Signature)
+    }
+}
+
+pub struct DatasetBuilder {
+    /// Positions are weighted by `count.powf(frequency_exponent)`: 0 weights every unique position equally,
+    /// 1 weights them as if they had not been merged.
+    pub frequency_exponent: f64,
+    /// If not empty, only positions matching at least one of these filters are added.
+    pub material_filters: Vec<MaterialFilter>,
+    samples: Vec<TrainingSample>,
+    indices: HashMap<PositionKey, usize>,
+    num_added: usize,
+    num_filtered_out: usize,
+}
+
+impl DatasetBuilder {
+    pub fn new(frequency_exponent: f64) -> DatasetBuilder {
+        DatasetBuilder {
+            frequency_exponent,
+            material_filters: Vec::new(),
+            samples: Vec::new(),
+            indices: HashMap::new(),
+            num_added: 0,
+            num_filtered_out: 0,
+        }
+    }
+
+    /// Only adds positions matching `filter`, or any of the other filters added this way.
+    pub fn with_material_filter(mut self, filter: MaterialFilter) -> DatasetBuilder {
+        self.material_filters.push(filter);
+        self
+    }
+
+    /// Adds a labeled position, unless it is rejected by the material filters.
+    /// If it was already added, the labels are averaged with the existing ones.
+    /// Returns whether the position was added.
+    pub fn add(&mut self, state: State, evaluation: Evaluation) -> bool {
+        if !self.material_filters.is_empty() && !self.material_filters.iter().any(|filter| filter.matches(&state)) {
+            self.num_filtered_out += 1;
+            return false;
+        }
+
+        self.num_added += 1;
+        let key = get_position_key(&state);
+        let index = match self.indices.get(&key) {
+            Some(index) => *index,
+            None => {
+                self.indices.insert(key, self.samples.len());
+                self.samples.push(TrainingSample { state, evaluation, count: 1, weight: 1. });
+                return true;
+            }
+        };
+
+        let sample = &mut self.samples[index];
+        sample.count += 1;
+        let old_fraction = (sample.count - 1) as f64 / sample.count as f64;
+        let new_fraction = 1. / sample.count as f64;
+        sample.evaluation.value = sample.evaluation.value * old_fraction + evaluation.value * new_fraction;
+        for (mv, probability) in sample.evaluation.policy.iter_mut() {
+            let new_probability = evaluation.policy.iter().find(|(other_move, _)| other_move == mv).map_or(0., |(_, p)| *p);
+            *probability = *probability * old_fraction + new_probability * new_fraction;
+        }
+        true
+    }
+
+    /// Returns the number of positions rejected by the material filters, which are not counted as added.
+    pub fn get_num_filtered_out(&self) -> usize {
+        self.num_filtered_out
+    }
+
+    pub fn get_stats(&self) -> DedupStats {
+        DedupStats {
+            num_added: self.num_added,
+            num_unique: self.samples.len(),
+        }
+    }
+
+    /// Returns the unique samples with their weights.
+    pub fn build(mut self) -> Vec<TrainingSample> {
+        for sample in self.samples.iter_mut() {
+            sample.weight = (sample.count as f64).powf(self.frequency_exponent);
+        }
+        let mean_weight = self.samples.iter().map(|sample| sample.weight).sum::<f64>() / self.samples.len().max(1) as f64;
+        for sample in self.samples.iter_mut() {
+            sample.weight /= mean_weight;
+        }
+        self.samples
+    }
+}
+
+/// Draws `num_samples` samples with replacement, with probabilities proportional to their weights.
+pub fn sample_weighted_batch<R: Rng>(samples: &[TrainingSample], num_samples: usize, rng: &mut R) -> Vec<(State, Evaluation)> {
+    let distribution = WeightedIndex::new(samples.iter().map(|sample| sample.weight)).expect("No samples with positive weight");
+    (0..num_samples).map(|_| {
+        let sample = &samples[distribution.sample(rng)];
+        (sample.state.clone(), sample.evaluation.clone())
+    }).collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DatasetSplit {
+    Train,
+    Validation,
+    Test,
+}
+
+impl DatasetSplit {
+    pub const ALL: [DatasetSplit; 3] = [DatasetSplit::Train, DatasetSplit::Validation, DatasetSplit::Test];
+}
+
+impl Display for DatasetSplit {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DatasetSplit::Train => "train",
+            DatasetSplit::Validation => "validation",
+            DatasetSplit::Test => "test",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Returns a key identifying a game by its seven tag roster and main line, so that the same game
+/// appearing twice in a corpus, e.g. in two databases, is always assigned to the same split.
+pub fn get_game_key(tree: &PgnStateTree) -> String {
+    let mut key: Vec<String> = ["Event", "Site", "Date", "Round", "White", "Black", "Result"].iter()
+        .map(|name| tree.get_tag(name).unwrap_or("?").to_string())
+        .collect();
+    let mut node = tree.head.clone();
+    loop {
+        let Some(next_node) = node.borrow().next_main_node() else {
+            break;
+        };
+        if let Some((_, san, _)) = &next_node.borrow().move_and_san_and_previous_node {
+            key.push(san.clone());
+        }
+        node = next_node;
+    }
+    key.join(" ")
+}
+
+/// Assigns games to splits by a hash of their key, so that the assignment is deterministic,
+/// does not depend on the order games are added in, and can be checked later.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplitConfig {
+    pub validation_fraction: f64,
+    pub test_fraction: f64,
+    /// Changes which games go to each split, for cross-validation with the same fractions.
+    pub seed: u64,
+}
+
+impl Default for SplitConfig {
+    fn default() -> Self {
+        SplitConfig {
+            validation_fraction: 0.05,
+            test_fraction: 0.05,
+            seed: 0,
+        }
+    }
+}
+
+impl SplitConfig {
+    pub fn assign(&self, game_key: &str) -> DatasetSplit {
+        let mut checksum = Checksum::default();
+        checksum.update_u64(self.seed);
+        checksum.update(game_key.as_bytes());
+        // FNV-1a barely changes the high bits for keys differing in their last bytes, such as `game 1` and `game 2`,
+        // so they are mixed with the MurmurHash3 finalizer before taking the top 53 bits as a fraction in [0, 1)
+        let mut hash = checksum.get_value();
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        hash ^= hash >> 33;
+        let fraction = (hash >> 11) as f64 / (1u64 << 53) as f64;
+        if fraction < self.validation_fraction {
+            DatasetSplit::Validation
+        } else if fraction < self.validation_fraction + self.test_fraction {
+            DatasetSplit::Test
+        } else {
+            DatasetSplit::Train
+        }
+    }
+}
+
+/// Builds a dataset per split, assigning every position of a game to that game's split.
+/// A position already added to another split is left out, and counted as a prevented leak,
+/// since a position shared by games of two splits, e.g. from a common opening, would leak labels between them.
+pub struct SplitDatasetBuilder {
+    pub config: SplitConfig,
+    builders: [DatasetBuilder; 3],
+    position_splits: HashMap<PositionKey, DatasetSplit>,
+    game_splits: BTreeMap<String, DatasetSplit>,
+    num_leaks_prevented: usize,
+}
+
+impl SplitDatasetBuilder {
+    /// Returns a builder whose datasets are built with `frequency_exponent` and `material_filters`, as in `DatasetBuilder`.
+    pub fn new(config: SplitConfig, frequency_exponent: f64, material_filters: Vec<MaterialFilter>) -> SplitDatasetBuilder {
+        let new_builder = || {
+            let mut builder = DatasetBuilder::new(frequency_exponent);
+            builder.material_filters = material_filters.clone();
+            builder
+        };
+        SplitDatasetBuilder {
+            config,
+            builders: [new_builder(), new_builder(), new_builder()],
+            position_splits: HashMap::new(),
+            game_splits: BTreeMap::new(),
+            num_leaks_prevented: 0,
+        }
+    }
+
+    /// Adds the labeled positions of the game identified by `game_key` to its split, and returns the split.
+    pub fn add_game(&mut self, game_key: &str, positions: impl IntoIterator<Item = (State, Evaluation)>) -> DatasetSplit {
+        let split = self.config.assign(game_key);
+        self.game_splits.insert(game_key.to_string(), split);
+        for (state, evaluation) in positions {
+            let key = get_position_key(&state);
+            match self.position_splits.get(&key) {
+                Some(existing_split) if *existing_split != split => self.num_leaks_prevented += 1,
+                _ => {
+                    if self.builders[split as usize].add(state, evaluation) {
+                        self.position_splits.insert(key, split);
+                    }
+                }
+            }
+        }
+        split
+    }
+
+    /// Returns the number of positions left out because they were already in another split.
+    pub fn get_num_leaks_prevented(&self) -> usize {
+        self.num_leaks_prevented
+    }
+
+    pub fn get_stats(&self, split: DatasetSplit) -> DedupStats {
+        self.builders[split as usize].get_stats()
+    }
+
+    pub fn build(self) -> SplitDataset {
+        let [train, validation, test] = self.builders.map(DatasetBuilder::build);
+        SplitDataset { config: self.config, train, validation, test, game_splits: self.game_splits }
+    }
+}
+
+/// Why a split dataset failed `SplitDataset::verify`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SplitIntegrityError {
+    /// A game is recorded in a different split than its key is assigned to, e.g. after changing the fractions or seed.
+    MisassignedGame { game_key: String, expected: DatasetSplit, actual: DatasetSplit },
+    /// A position is in two splits.
+    SharedPosition { fen: String, splits: (DatasetSplit, DatasetSplit) },
+}
+
+impl Display for SplitIntegrityError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SplitIntegrityError::MisassignedGame { game_key, expected, actual } => {
+                write!(f, "Game {} is in the {} split, but should be in the {} split", game_key, actual, expected)
+            },
+            SplitIntegrityError::SharedPosition { fen, splits } => {
+                write!(f, "Position {} is in both the {} and {} splits", fen, splits.0, splits.1)
+            },
+        }
+    }
+}
+
+impl Error for SplitIntegrityError {}
+
+/// Datasets split by game, with the split of every game they were built from.
+#[derive(Debug, Clone)]
+pub struct SplitDataset {
+    pub config: SplitConfig,
+    pub train: Vec<TrainingSample>,
+    pub validation: Vec<TrainingSample>,
+    pub test: Vec<TrainingSample>,
+    pub game_splits: BTreeMap<String, DatasetSplit>,
+}
+
+impl SplitDataset {
+    pub fn get(&self, split: DatasetSplit) -> &[TrainingSample] {
+        match split {
+            DatasetSplit::Train => &self.train,
+            DatasetSplit::Validation => &self.validation,
+            DatasetSplit::Test => &self.test,
+        }
+    }
+
+    /// Checks that every game is in the split `config` assigns it to, and that no position is in two splits.
+    pub fn verify(&self) -> Result<(), SplitIntegrityError> {
+        for (game_key, actual) in self.game_splits.iter() {
+            let expected = self.config.assign(game_key);
+            if expected != *actual {
+                return Err(SplitIntegrityError::MisassignedGame { game_key: game_key.clone(), expected, actual: *actual });
+            }
+        }
+
+        let mut position_splits: HashMap<PositionKey, DatasetSplit> = HashMap::new();
+        for split in DatasetSplit::ALL {
+            for sample in self.get(split) {
+                match position_splits.insert(get_position_key(&sample.state), split) {
+                    Some(other_split) if other_split != split => {
+                        return Err(SplitIntegrityError::SharedPosition { fen: sample.state.to_fen(), splits: (other_split, split) });
+                    },
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum GamePhase {
+    Opening,
+    Middlegame,
+    Endgame,
+}
+
+impl GamePhase {
+    pub const ALL: [GamePhase; 3] = [GamePhase::Opening, GamePhase::Middlegame, GamePhase::Endgame];
+}
+
+/// How batches are split between game phases.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CurriculumConfig {
+    /// Positions before this ply are in the opening.
+    pub opening_max_ply: u16,
+    /// Positions with at most this much non-pawn material, in pawns and for both sides together, are in the endgame.
+    pub endgame_max_material: i32,
+    /// The fractions of each batch drawn from the opening, middlegame and endgame.
+    pub quotas: [f64; 3],
+}
+
+impl Default for CurriculumConfig {
+    fn default() -> Self {
+        CurriculumConfig {
+            opening_max_ply: 20,
+            endgame_max_material: 26,
+            quotas: [0.2, 0.5, 0.3],
+        }
+    }
+}
+
+impl CurriculumConfig {
+    pub fn get_game_phase(&self, state: &State) -> GamePhase {
+        let non_pawn_material: i32 = PieceType::iter_between(PieceType::Knight, PieceType::Queen)
+            .map(|piece_type| get_see_value(*piece_type) * state.board.piece_type_masks[*piece_type as usize].count_ones() as i32)
+            .sum();
+        if non_pawn_material <= self.endgame_max_material {
+            GamePhase::Endgame
+        } else if state.halfmove < self.opening_max_ply {
+            GamePhase::Opening
+        } else {
+            GamePhase::Middlegame
+        }
+    }
+}
+
+/// Like `sample_weighted_batch`, but draws from each game phase according to the quotas of `config`.
+/// The quotas of phases without any samples are shared among the other phases.
+pub fn sample_curriculum_batch<R: Rng>(samples: &[TrainingSample], num_samples: usize, config: &CurriculumConfig, rng: &mut R) -> Vec<(State, Evaluation)> {
+    let mut samples_by_phase: [Vec<TrainingSample>; 3] = Default::default();
+    for sample in samples {
+        samples_by_phase[config.get_game_phase(&sample.state) as usize].push(sample.clone());
+    }
+
+    let total_quota: f64 = GamePhase::ALL.iter()
+        .filter(|phase| !samples_by_phase[**phase as usize].is_empty())
+        .map(|phase| config.quotas[*phase as usize])
+        .sum();
+    let mut batch = Vec::with_capacity(num_samples);
+    let mut quota_so_far = 0.;
+    for phase in GamePhase::ALL {
+        let phase_samples = &samples_by_phase[phase as usize];
+        if phase_samples.is_empty() {
+            continue;
+        }
+        // rounding cumulative quotas makes the phase sizes add up to exactly num_samples
+        let start = (quota_so_far / total_quota * num_samples as f64).round() as usize;
+        quota_so_far += config.quotas[phase as usize];
+        let end = (quota_so_far / total_quota * num_samples as f64).round() as usize;
+        batch.append(&mut sample_weighted_batch(phase_samples, end - start, rng));
+    }
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dunck_core::state::State;
+
+    fn label(state: &State, value: f64) -> Evaluation {
+        let moves = state.calc_legal_moves();
+        let policy = moves.iter().enumerate().map(|(i, mv)| (*mv, if i == 0 { 1. } else { 0. })).collect();
+        Evaluation { policy, value }
+    }
+
+    #[test]
+    fn test_dataset_builder() {
+        let initial = State::initial();
+        let mut after_e4 = State::initial();
+        after_e4.make_move(initial.calc_legal_moves()[0]);
+
+        let mut builder = DatasetBuilder::new(0.5);
+        builder.add(initial.clone(), label(&initial, 1.));
+        builder.add(initial.clone(), label(&initial, 0.));
+        builder.add(after_e4.clone(), label(&after_e4, -1.));
+        let mut uniform_initial = label(&initial, 0.);
+        uniform_initial.policy[0].1 = 0.;
+        builder.add(initial.clone(), uniform_initial);
+
+        let stats = builder.get_stats();
+        assert_eq!(stats, DedupStats { num_added: 4, num_unique: 2 });
+        assert_eq!(stats.get_dedup_rate(), 0.5);
+
+        let samples = builder.build();
+        assert_eq!(samples[0].count, 3);
+        assert!((samples[0].evaluation.value - 1. / 3.).abs() < 1e-9);
+        assert!((samples[0].evaluation.policy[0].1 - 2. / 3.).abs() < 1e-9);
+        assert!((samples[0].weight / samples[1].weight - 3f64.sqrt()).abs() < 1e-9);
+        assert!((samples[0].weight + samples[1].weight - 2.).abs() < 1e-9);
+
+        let batch = sample_weighted_batch(&samples, 10, &mut rand::thread_rng());
+        assert_eq!(batch.len(), 10);
+    }
+
+    #[test]
+    fn test_curriculum_batch() {
+        let config = CurriculumConfig::default();
+        let positions = [
+            (State::initial(), GamePhase::Opening),
+            (State::from_fen("r1bq1rk1/pp2bppp/2n1pn2/3p4/3P4/2NBPN2/PP3PPP/R2QK2R w KQ - 4 12").unwrap(), GamePhase::Middlegame),
+            (State::from_fen("8/5pk1/6p1/8/3R4/6P1/5PK1/8 w - - 0 40").unwrap(), GamePhase::Endgame),
+        ];
+        let mut builder = DatasetBuilder::new(0.);
+        for (state, phase) in positions.iter() {
+            assert_eq!(config.get_game_phase(state), *phase);
+            builder.add(state.clone(), label(state, 0.));
+        }
+        let samples = builder.build();
+
+        let batch = sample_curriculum_batch(&samples, 10, &config, &mut rand::thread_rng());
+        let count_phase = |phase: GamePhase| batch.iter().filter(|(state, _)| config.get_game_phase(state) == phase).count();
+        assert_eq!((count_phase(GamePhase::Opening), count_phase(GamePhase::Middlegame), count_phase(GamePhase::Endgame)), (2, 5, 3));
+
+        let batch = sample_curriculum_batch(&samples[1..], 10, &config, &mut rand::thread_rng());
+        assert_eq!(batch.len(), 10);
+    }
+
+    #[test]
+    fn test_split_dataset() {
+        let config = SplitConfig { validation_fraction: 0.25, test_fraction: 0.25, seed: 7 };
+        assert_eq!(config.assign("a game"), config.assign("a game"));
+        let num_games = 400;
+        let mut counts = [0i32; 3];
+        for i in 0..num_games {
+            counts[config.assign(&format!("game {}", i)) as usize] += 1;
+        }
+        for (count, expected) in counts.iter().zip([200, 100, 100]) {
+            assert!((*count - expected).abs() < 40, "{:?}", counts);
+        }
+
+        // games sharing their first position, whatever splits they are assigned to
+        let initial = State::initial();
+        let mut builder = SplitDatasetBuilder::new(config, 1., Vec::new());
+        let mut game_keys_by_split: [Vec<String>; 3] = Default::default();
+        for i in 0..20 {
+            let mut state = initial.clone();
+            let mut positions = vec![(state.clone(), label(&state, 0.))];
+            state.make_move(initial.calc_legal_moves()[i]);
+            positions.push((state.clone(), label(&state, 0.)));
+            let game_key = format!("game {}", i);
+            let split = builder.add_game(&game_key, positions);
+            game_keys_by_split[split as usize].push(game_key);
+        }
+        assert!(game_keys_by_split.iter().all(|game_keys| !game_keys.is_empty()));
+        // the initial position is only kept in the split of the first game
+        assert_eq!(builder.get_num_leaks_prevented(), 20 - game_keys_by_split[config.assign("game 0") as usize].len());
+
+        let mut dataset = builder.build();
+        assert_eq!(dataset.train.len() + dataset.validation.len() + dataset.test.len(), 21);
+        assert_eq!(dataset.verify(), Ok(()));
+
+        let moved_sample = dataset.train.pop().unwrap();
+        dataset.test.push(moved_sample.clone());
+        dataset.train.push(moved_sample);
+        assert!(matches!(dataset.verify(), Err(SplitIntegrityError::SharedPosition { .. })));
+
+        dataset.config.seed += 1;
+        assert!(matches!(dataset.verify(), Err(SplitIntegrityError::MisassignedGame { .. })));
+    }
+
+    #[test]
+    fn test_material_filter() {
+        let rook_ending = State::from_fen("8/5pk1/6p1/8/3R4/6P1/5PK1/8 w - - 0 40").unwrap();
+        let double_rook_ending = State::from_fen("8/5pk1/6p1/3r4/3R4/6P1/5PK1/8 w - - 0 40").unwrap();
+        let pawn_ending = State::from_fen("8/5pk1/6p1/8/8/6P1/5PK1/8 w - - 0 40").unwrap();
+        let knight_ending = State::from_fen("8/5pk1/6p1/8/3N4/6P1/5PK1/8 w - - 0 40").unwrap();
+        let classes = [EndgameClass::Rook, EndgameClass::Rook, EndgameClass::Pawn, EndgameClass::Minor, EndgameClass::Mixed];
+        for (state, class) in [&rook_ending, &double_rook_ending, &pawn_ending, &knight_ending, &State::initial()].into_iter().zip(classes) {
+            assert_eq!(EndgameClass::from_signature(&state.get_material_signature()), class);
+        }
+
+        assert_eq!("Rook".parse(), Ok(MaterialFilter::Class(EndgameClass::Rook)));
+        assert_eq!("pieces<=6".parse(), Ok(MaterialFilter::MaxPieces(6)));
+        assert!("pieces<=six".parse::<MaterialFilter>().is_err());
+        let signature_filter: MaterialFilter = "KPPvKRPP".parse().unwrap();
+        assert!(signature_filter.matches(&rook_ending));
+        assert!(!signature_filter.matches(&double_rook_ending));
+        assert!(MaterialFilter::MaxPieces(6).matches(&pawn_ending));
+        assert!(!MaterialFilter::MaxPieces(6).matches(&rook_ending));
+
+        let mut builder = DatasetBuilder::new(1.)
+            .with_material_filter(MaterialFilter::Class(EndgameClass::Rook))
+            .with_material_filter(MaterialFilter::Class(EndgameClass::Pawn));
+        for state in [&rook_ending, &double_rook_ending, &pawn_ending, &knight_ending, &State::initial()] {
+            builder.add(state.clone(), label(state, 0.));
+        }
+        assert!(builder.add(rook_ending.clone(), label(&rook_ending, 0.)));
+        assert_eq!(builder.get_num_filtered_out(), 2);
+        assert_eq!(builder.get_stats(), DedupStats { num_added: 4, num_unique: 3 });
+    }
+}
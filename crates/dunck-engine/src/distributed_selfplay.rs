@@ -0,0 +1,505 @@
+//! A simple protocol for running self-play on several machines.
+//!
+//! A coordinator hands out jobs (a model version, a seed, and a number of games) and the current model weights,
+//! and workers send back the games they played, compressed.
+//! Every message is framed as a 4-byte big-endian length, followed by a 1-byte tag and the payload.
+//!
+//! A worker connects and sends `Hello` with the model version it already has, then repeatedly sends `RequestJob`.
+//! The coordinator answers with `Model` first if the worker's model is out of date, then with `Job`, or with
+//! `Shutdown` once enough games have been collected. Finished games are sent as `Games` and acknowledged with `Ack`.
+//! If the connection drops, the worker reconnects and resends any games that were not acknowledged.
+
+use std::collections::HashSet;
+use std::io;
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use crate::worker::{catch_worker_panic, EngineError};
+use dunck_core::game_record::{read_game_records, write_game_records, GameRecord};
+
+/// The largest message that will be read, in bytes. Messages carrying model weights are the largest.
+pub const MAX_MESSAGE_LENGTH: usize = 1 << 28;
+
+/// The number of bytes a message buffer grows by at a time while it is read,
+/// so that a length prefix alone cannot make the reader allocate `MAX_MESSAGE_LENGTH` bytes.
+const READ_CHUNK_LENGTH: usize = 1 << 16;
+
+/// A message exchanged between the coordinator and a worker.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Message {
+    Hello { worker_name: String, model_version: u32 },
+    RequestJob,
+    Model { version: u32, weights: Vec<u8> },
+    Job { job_id: u64, model_version: u32, seed: u64, num_games: u32 },
+    Games { job_id: u64, payload: Vec<u8> },
+    Ack { job_id: u64 },
+    Shutdown,
+}
+
+impl Message {
+    const fn get_tag(&self) -> u8 {
+        match self {
+            Message::Hello { .. } => 0,
+            Message::RequestJob => 1,
+            Message::Model { .. } => 2,
+            Message::Job { .. } => 3,
+            Message::Games { .. } => 4,
+            Message::Ack { .. } => 5,
+            Message::Shutdown => 6,
+        }
+    }
+
+    fn encode_payload(&self) -> Vec<u8> {
+        let mut payload = Vec::new();
+        match self {
+            Message::Hello { worker_name, model_version } => {
+                write_bytes(&mut payload, worker_name.as_bytes());
+                payload.extend_from_slice(&model_version.to_be_bytes());
+            },
+            Message::RequestJob | Message::Shutdown => {},
+            Message::Model { version, weights } => {
+                payload.extend_from_slice(&version.to_be_bytes());
+                write_bytes(&mut payload, weights);
+            },
+            Message::Job { job_id, model_version, seed, num_games } => {
+                payload.extend_from_slice(&job_id.to_be_bytes());
+                payload.extend_from_slice(&model_version.to_be_bytes());
+                payload.extend_from_slice(&seed.to_be_bytes());
+                payload.extend_from_slice(&num_games.to_be_bytes());
+            },
+            Message::Games { job_id, payload: games_payload } => {
+                payload.extend_from_slice(&job_id.to_be_bytes());
+                write_bytes(&mut payload, games_payload);
+            },
+            Message::Ack { job_id } => payload.extend_from_slice(&job_id.to_be_bytes()),
+        }
+        payload
+    }
+
+    fn decode(tag: u8, payload: &[u8]) -> io::Result<Message> {
+        let mut reader = payload;
+        let message = match tag {
+            0 => Message::Hello {
+                worker_name: String::from_utf8(read_bytes(&mut reader)?).map_err(|e| invalid_data(e.to_string()))?,
+                model_version: read_u32(&mut reader)?,
+            },
+            1 => Message::RequestJob,
+            2 => Message::Model { version: read_u32(&mut reader)?, weights: read_bytes(&mut reader)? },
+            3 => Message::Job {
+                job_id: read_u64(&mut reader)?,
+                model_version: read_u32(&mut reader)?,
+                seed: read_u64(&mut reader)?,
+                num_games: read_u32(&mut reader)?,
+            },
+            4 => Message::Games { job_id: read_u64(&mut reader)?, payload: read_bytes(&mut reader)? },
+            5 => Message::Ack { job_id: read_u64(&mut reader)? },
+            6 => Message::Shutdown,
+            _ => return Err(invalid_data(format!("Unknown message tag: {}", tag)))
+        };
+        if !reader.is_empty() {
+            return Err(invalid_data(format!("{} trailing bytes in message", reader.len())));
+        }
+        Ok(message)
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_bytes(buffer: &mut Vec<u8>, bytes: &[u8]) {
+    buffer.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    buffer.extend_from_slice(bytes);
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_be_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_be_bytes(bytes))
+}
+
+fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let length = read_u32(reader)? as usize;
+    if length > MAX_MESSAGE_LENGTH {
+        return Err(invalid_data(format!("Field too long: {} bytes", length)));
+    }
+    read_exact_chunked(reader, length)
+}
+
+/// Reads exactly `length` bytes, growing the buffer by at most `READ_CHUNK_LENGTH` bytes at a time.
+fn read_exact_chunked<R: Read>(reader: &mut R, length: usize) -> io::Result<Vec<u8>> {
+    let mut bytes = Vec::with_capacity(length.min(READ_CHUNK_LENGTH));
+    while bytes.len() < length {
+        let chunk_length = (length - bytes.len()).min(READ_CHUNK_LENGTH);
+        let start = bytes.len();
+        bytes.resize(start + chunk_length, 0);
+        reader.read_exact(&mut bytes[start..])?;
+    }
+    Ok(bytes)
+}
+
+/// Writes a framed message.
+pub fn write_message<W: Write>(writer: &mut W, message: &Message) -> io::Result<()> {
+    let payload = message.encode_payload();
+    writer.write_all(&(payload.len() as u32 + 1).to_be_bytes())?;
+    writer.write_all(&[message.get_tag()])?;
+    writer.write_all(&payload)?;
+    writer.flush()
+}
+
+/// Reads a framed message.
+pub fn read_message<R: Read>(reader: &mut R) -> io::Result<Message> {
+    let length = read_u32(reader)? as usize;
+    if length == 0 || length > MAX_MESSAGE_LENGTH {
+        return Err(invalid_data(format!("Invalid message length: {}", length)));
+    }
+    let frame = read_exact_chunked(reader, length)?;
+    Message::decode(frame[0], &frame[1..])
+}
+
+/// Encodes games as binary game records, compressed with zlib.
+pub fn compress_games(games: &[GameRecord]) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    write_game_records(&mut encoder, games)?;
+    encoder.finish()
+}
+
+/// Decodes games compressed by `compress_games`, checking that every move is legal.
+pub fn decompress_games(payload: &[u8]) -> io::Result<Vec<GameRecord>> {
+    let games = read_game_records(&mut ZlibDecoder::new(payload))?;
+    for game in games.iter() {
+        game.replay().map_err(invalid_data)?;
+    }
+    Ok(games)
+}
+
+/// A model version and its weights.
+type VersionedWeights = (u32, Arc<Vec<u8>>);
+
+struct CoordinatorState {
+    model_version: u32,
+    weights: Arc<Vec<u8>>,
+    games_per_job: u32,
+    target_num_games: usize,
+    next_job_id: u64,
+    next_seed: u64,
+    completed_job_ids: HashSet<u64>,
+    games: Vec<GameRecord>,
+}
+
+/// Hands out self-play jobs and collects the games played by workers.
+/// Cloning gives another handle to the same coordinator, e.g. for a thread per connection.
+#[derive(Clone)]
+pub struct SelfPlayCoordinator {
+    state: Arc<Mutex<CoordinatorState>>,
+}
+
+impl SelfPlayCoordinator {
+    /// Creates a coordinator that distributes `weights` as model version 1,
+    /// and stops handing out jobs once `target_num_games` games have been collected.
+    pub fn new(weights: Vec<u8>, games_per_job: u32, target_num_games: usize, first_seed: u64) -> SelfPlayCoordinator {
+        assert!(games_per_job > 0);
+        SelfPlayCoordinator {
+            state: Arc::new(Mutex::new(CoordinatorState {
+                model_version: 1,
+                weights: Arc::new(weights),
+                games_per_job,
+                target_num_games,
+                next_job_id: 0,
+                next_seed: first_seed,
+                completed_job_ids: HashSet::new(),
+                games: Vec::new(),
+            })),
+        }
+    }
+
+    /// Publishes new weights, which workers pick up with their next job. Returns the new model version.
+    pub fn set_model(&self, weights: Vec<u8>) -> u32 {
+        let mut state = self.state.lock().unwrap();
+        state.model_version += 1;
+        state.weights = Arc::new(weights);
+        state.model_version
+    }
+
+    /// Returns the number of games collected so far.
+    pub fn get_num_games(&self) -> usize {
+        self.state.lock().unwrap().games.len()
+    }
+
+    /// Removes and returns the games collected so far.
+    pub fn take_games(&self) -> Vec<GameRecord> {
+        std::mem::take(&mut self.state.lock().unwrap().games)
+    }
+
+    /// Serves a single worker connection until the worker disconnects or is shut down.
+    pub fn handle_connection<S: Read + Write>(&self, stream: &mut S) -> io::Result<()> {
+        let mut worker_model_version = match read_message(stream)? {
+            Message::Hello { model_version, .. } => model_version,
+            message => return Err(invalid_data(format!("Expected Hello, got {:?}", message)))
+        };
+
+        loop {
+            let message = match read_message(stream) {
+                Ok(message) => message,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(e)
+            };
+            match message {
+                Message::RequestJob => {
+                    let (model, job) = self.next_job(worker_model_version);
+                    if let Some((version, weights)) = model {
+                        write_message(stream, &Message::Model { version, weights: weights.as_ref().clone() })?;
+                        worker_model_version = version;
+                    }
+                    match job {
+                        Some(job) => write_message(stream, &job)?,
+                        None => {
+                            write_message(stream, &Message::Shutdown)?;
+                            return Ok(());
+                        }
+                    }
+                },
+                Message::Games { job_id, payload } => {
+                    let games = decompress_games(&payload)?;
+                    let mut state = self.state.lock().unwrap();
+                    if state.completed_job_ids.insert(job_id) {
+                        state.games.extend(games);
+                    }
+                    drop(state);
+                    write_message(stream, &Message::Ack { job_id })?;
+                },
+                message => return Err(invalid_data(format!("Unexpected message from worker: {:?}", message)))
+            }
+        }
+    }
+
+    /// Returns the weights to send first, if the worker's model is out of date, and the job to send,
+    /// or no job if enough games have been collected.
+    fn next_job(&self, worker_model_version: u32) -> (Option<VersionedWeights>, Option<Message>) {
+        let mut state = self.state.lock().unwrap();
+        if state.games.len() >= state.target_num_games {
+            return (None, None);
+        }
+        let model = if worker_model_version != state.model_version {
+            Some((state.model_version, Arc::clone(&state.weights)))
+        } else {
+            None
+        };
+        let job = Message::Job {
+            job_id: state.next_job_id,
+            model_version: state.model_version,
+            seed: state.next_seed,
+            num_games: state.games_per_job,
+        };
+        state.next_job_id += 1;
+        state.next_seed = state.next_seed.wrapping_add(1);
+        (model, Some(job))
+    }
+}
+
+/// Settings for a self-play worker.
+#[derive(Clone, Debug)]
+pub struct WorkerConfig {
+    pub worker_name: String,
+    pub max_reconnect_attempts: u32,
+    pub reconnect_delay: Duration,
+}
+
+/// Connects to the coordinator at `addr` and plays jobs until told to shut down.
+/// `play_games` receives the model weights, the seed, and the number of games to play.
+/// On connection errors, reconnects up to `config.max_reconnect_attempts` times in a row,
+/// resending finished games that were not acknowledged. Returns the number of games sent.
+/// If `play_games` panics, the worker stops without reconnecting and returns an error wrapping
+/// `EngineError::WorkerPanicked`, since `play_games` may have been left in an inconsistent state.
+pub fn run_worker<A, F>(addr: A, config: &WorkerConfig, mut play_games: F) -> io::Result<usize>
+where
+    A: ToSocketAddrs,
+    F: FnMut(&[u8], u64, u32) -> Vec<GameRecord>
+{
+    let mut model: Option<(u32, Vec<u8>)> = None;
+    let mut unacknowledged: Option<(u64, Vec<u8>)> = None;
+    let mut num_games_sent = 0;
+    let mut failed_attempts = 0;
+
+    loop {
+        let result = TcpStream::connect(&addr).and_then(|mut stream| {
+            failed_attempts = 0;
+            run_worker_session(&mut stream, config, &mut model, &mut unacknowledged, &mut num_games_sent, &mut play_games)
+        });
+        match result {
+            Ok(()) => return Ok(num_games_sent),
+            Err(e) if e.get_ref().is_some_and(|inner| inner.is::<EngineError>()) => return Err(e),
+            Err(e) => {
+                failed_attempts += 1;
+                if failed_attempts > config.max_reconnect_attempts {
+                    return Err(e);
+                }
+                eprintln!("Connection to coordinator lost ({}), reconnecting...", e);
+                thread::sleep(config.reconnect_delay);
+            }
+        }
+    }
+}
+
+/// Runs one connection of a worker, returning Ok once the coordinator shuts the worker down.
+fn run_worker_session<S, F>(
+    stream: &mut S,
+    config: &WorkerConfig,
+    model: &mut Option<(u32, Vec<u8>)>,
+    unacknowledged: &mut Option<(u64, Vec<u8>)>,
+    num_games_sent: &mut usize,
+    play_games: &mut F
+) -> io::Result<()>
+where
+    S: Read + Write,
+    F: FnMut(&[u8], u64, u32) -> Vec<GameRecord>
+{
+    let model_version = model.as_ref().map_or(0, |(version, _)| *version);
+    write_message(stream, &Message::Hello { worker_name: config.worker_name.clone(), model_version })?;
+
+    loop {
+        if let Some((job_id, payload)) = unacknowledged.clone() {
+            write_message(stream, &Message::Games { job_id, payload })?;
+            match read_message(stream)? {
+                Message::Ack { job_id: acknowledged_job_id } if acknowledged_job_id == job_id => *unacknowledged = None,
+                message => return Err(invalid_data(format!("Expected Ack, got {:?}", message)))
+            }
+        }
+
+        write_message(stream, &Message::RequestJob)?;
+        let (job_id, seed, num_games) = loop {
+            match read_message(stream)? {
+                Message::Model { version, weights } => *model = Some((version, weights)),
+                Message::Job { job_id, seed, num_games, .. } => break (job_id, seed, num_games),
+                Message::Shutdown => return Ok(()),
+                message => return Err(invalid_data(format!("Unexpected message from coordinator: {:?}", message)))
+            }
+        };
+
+        let weights = model.as_ref().map_or(&[][..], |(_, weights)| weights.as_slice());
+        let games = catch_worker_panic(|| play_games(weights, seed, num_games)).map_err(io::Error::other)?;
+        *num_games_sent += games.len();
+        *unacknowledged = Some((job_id, compress_games(&games)?));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+    use super::*;
+    use dunck_core::r#move::{Move, MoveFlag};
+    use dunck_core::utils::Square;
+
+    fn make_game(result: i8) -> GameRecord {
+        GameRecord::new(vec![
+            Move::new_non_promotion(Square::E4, Square::E2, MoveFlag::NormalMove),
+            Move::new_non_promotion(Square::E5, Square::E7, MoveFlag::NormalMove),
+        ], result)
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let messages = [
+            Message::Hello { worker_name: "worker".to_string(), model_version: 3 },
+            Message::RequestJob,
+            Message::Model { version: 2, weights: vec![1, 2, 3] },
+            Message::Model { version: 3, weights: vec![5; 3 * READ_CHUNK_LENGTH + 1] },
+            Message::Job { job_id: 7, model_version: 2, seed: 42, num_games: 8 },
+            Message::Games { job_id: 7, payload: vec![9; 100] },
+            Message::Ack { job_id: 7 },
+            Message::Shutdown,
+        ];
+        let mut buffer = Vec::new();
+        for message in messages.iter() {
+            write_message(&mut buffer, message).unwrap();
+        }
+        let mut reader = buffer.as_slice();
+        for message in messages.iter() {
+            assert_eq!(&read_message(&mut reader).unwrap(), message);
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_read_message_rejects_bad_lengths() {
+        let too_long = ((MAX_MESSAGE_LENGTH + 1) as u32).to_be_bytes();
+        assert_eq!(read_message(&mut too_long.as_slice()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // a length prefix within the limit, followed by far fewer bytes
+        let mut truncated = (MAX_MESSAGE_LENGTH as u32).to_be_bytes().to_vec();
+        truncated.extend_from_slice(&[6, 0, 0]);
+        assert_eq!(read_message(&mut truncated.as_slice()).unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_games_round_trip() {
+        let games = vec![make_game(1), make_game(0)];
+        assert_eq!(decompress_games(&compress_games(&games).unwrap()).unwrap(), games);
+    }
+
+    #[test]
+    fn test_worker_and_coordinator() {
+        let coordinator = SelfPlayCoordinator::new(vec![1, 2, 3], 2, 4, 100);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server_coordinator = coordinator.clone();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            server_coordinator.handle_connection(&mut stream).unwrap();
+        });
+
+        let config = WorkerConfig {
+            worker_name: "test".to_string(),
+            max_reconnect_attempts: 0,
+            reconnect_delay: Duration::from_millis(10),
+        };
+        let mut seeds = Vec::new();
+        let num_games_sent = run_worker(addr, &config, |weights, seed, num_games| {
+            assert_eq!(weights, &[1, 2, 3]);
+            seeds.push(seed);
+            (0..num_games).map(|_| make_game(0)).collect()
+        }).unwrap();
+        server.join().unwrap();
+
+        assert_eq!(num_games_sent, 4);
+        assert_eq!(seeds, vec![100, 101]);
+        assert_eq!(coordinator.get_num_games(), 4);
+        assert_eq!(coordinator.take_games()[0], make_game(0));
+    }
+
+    #[test]
+    fn test_worker_stops_on_panic() {
+        let coordinator = SelfPlayCoordinator::new(vec![1, 2, 3], 2, 4, 100);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            // the worker hangs up mid-job, without reconnecting
+            coordinator.handle_connection(&mut stream).unwrap();
+        });
+
+        let config = WorkerConfig {
+            worker_name: "test".to_string(),
+            max_reconnect_attempts: 3,
+            reconnect_delay: Duration::from_millis(10),
+        };
+        let error = run_worker(addr, &config, |_, _, _| -> Vec<GameRecord> { panic!("out of memory") }).unwrap_err();
+        server.join().unwrap();
+
+        let error = error.into_inner().unwrap().downcast::<EngineError>().unwrap();
+        assert_eq!(*error, EngineError::WorkerPanicked("out of memory".to_string()));
+    }
+}
@@ -0,0 +1,140 @@
+//! Shared engine state whose evaluator can be swapped while the engine is running,
+//! e.g. to pick up a new checkpoint after a training cycle.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use crate::evaluation::{Evaluation, Evaluator};
+use crate::self_check::{SelfCheck, SelfCheckReport};
+use dunck_core::state::State;
+
+pub type SharedEvaluator = Arc<dyn Evaluator + Send + Sync>;
+
+/// Holds the active evaluator behind a lock so that it can be replaced atomically.
+/// Every swap increments the generation, which caches of evaluations should be keyed on
+/// so that results from a previous evaluator are not reused.
+pub struct EngineContext {
+    evaluator: RwLock<SharedEvaluator>,
+    generation: AtomicU64,
+}
+
+impl EngineContext {
+    pub fn new(evaluator: SharedEvaluator) -> EngineContext {
+        EngineContext {
+            evaluator: RwLock::new(evaluator),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the active evaluator.
+    /// The returned handle keeps working after a swap, but keeps using the old evaluator.
+    pub fn get_evaluator(&self) -> SharedEvaluator {
+        self.evaluator.read().unwrap().clone()
+    }
+
+    /// Returns the number of times the evaluator has been swapped.
+    pub fn get_generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Runs `f` with the active evaluator, blocking any swap until `f` returns.
+    pub fn with_evaluator<R>(&self, f: impl FnOnce(&dyn Evaluator) -> R) -> R {
+        let evaluator = self.evaluator.read().unwrap();
+        f(evaluator.as_ref())
+    }
+
+    /// Replaces the evaluator, returning the previous one.
+    /// Waits for in-flight `with_evaluator` and `evaluate` calls to drain before switching,
+    /// then bumps the generation so that cached evaluations are invalidated.
+    pub fn swap_evaluator(&self, evaluator: SharedEvaluator) -> SharedEvaluator {
+        let mut active = self.evaluator.write().unwrap();
+        let previous = std::mem::replace(&mut *active, evaluator);
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        previous
+    }
+
+    /// Runs the self-check diagnostics with the active evaluator, e.g. to attach the report to a bug report.
+    pub fn self_check(&self) -> SelfCheckReport {
+        self.with_evaluator(|evaluator| SelfCheck::default().with_evaluator(evaluator).run())
+    }
+}
+
+impl Evaluator for EngineContext {
+    /// Evaluates with whichever evaluator is active, so a search running on the context
+    /// uses a swapped-in evaluator from its next evaluation onward.
+    fn evaluate(&self, state: &State) -> Evaluation {
+        self.with_evaluator(|evaluator| evaluator.evaluate(state))
+    }
+
+    fn evaluate_batch(&self, states: &[State]) -> Vec<Evaluation> {
+        self.with_evaluator(|evaluator| evaluator.evaluate_batch(states))
+    }
+
+    fn estimate_moves_left(&self, state: &State) -> Option<f64> {
+        self.with_evaluator(|evaluator| evaluator.estimate_moves_left(state))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use super::*;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::mcts::mcts::{calc_uct_score, MCTS};
+
+    struct ConstantEvaluator {
+        value: f64,
+    }
+
+    impl Evaluator for ConstantEvaluator {
+        fn evaluate(&self, state: &State) -> Evaluation {
+            let mut evaluation = MaterialEvaluator {}.evaluate(state);
+            evaluation.value = self.value;
+            evaluation
+        }
+    }
+
+    #[test]
+    fn test_swap_evaluator() {
+        let context = EngineContext::new(Arc::new(ConstantEvaluator { value: 0.25 }));
+        let old_handle = context.get_evaluator();
+        assert_eq!(context.get_generation(), 0);
+        assert_eq!(context.evaluate(&State::initial()).value, 0.25);
+
+        let previous = context.swap_evaluator(Arc::new(ConstantEvaluator { value: -0.5 }));
+        assert_eq!(context.get_generation(), 1);
+        assert_eq!(previous.evaluate(&State::initial()).value, 0.25);
+        assert_eq!(old_handle.evaluate(&State::initial()).value, 0.25);
+        assert_eq!(context.evaluate(&State::initial()).value, -0.5);
+    }
+
+    #[test]
+    fn test_self_check() {
+        let context = EngineContext::new(Arc::new(ConstantEvaluator { value: 2. }));
+        let report = context.self_check();
+        assert_eq!(report.get_failures().map(|check| check.name).collect::<Vec<_>>(), ["evaluator"]);
+
+        context.swap_evaluator(Arc::new(MaterialEvaluator {}));
+        assert!(context.self_check().is_ok());
+    }
+
+    #[test]
+    fn test_swap_during_search() {
+        let context = Arc::new(EngineContext::new(Arc::new(MaterialEvaluator {})));
+
+        let searcher_context = Arc::clone(&context);
+        let searcher = thread::spawn(move || {
+            for _ in 0..5 {
+                let mut mcts = MCTS::new(State::initial(), 1.5, searcher_context.as_ref(), &calc_uct_score, false);
+                mcts.run(50);
+                assert!(mcts.get_best_child_by_visits().is_some());
+            }
+        });
+        for value in [0.1, 0.2, 0.3] {
+            context.swap_evaluator(Arc::new(ConstantEvaluator { value }));
+        }
+        searcher.join().unwrap();
+
+        assert_eq!(context.get_generation(), 3);
+        assert_eq!(context.evaluate(&State::initial()).value, 0.3);
+    }
+}
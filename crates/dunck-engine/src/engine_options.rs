@@ -0,0 +1,89 @@
+//! Engine-wide settings that control what the engine may do besides searching.
+
+/// The name of the UCI option toggling `EngineOptions::analysis_mode`.
+pub const UCI_ANALYSE_MODE: &str = "UCI_AnalyseMode";
+/// The name of the UCI option setting `EngineOptions::multi_pv`.
+pub const UCI_MULTI_PV: &str = "MultiPV";
+/// The most lines a search may report.
+pub const MAX_MULTI_PV: usize = 64;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct EngineOptions {
+    /// When set, opening books, tablebase cutoffs and adjudication are disabled,
+    /// so that analysis output reflects pure search.
+    pub analysis_mode: bool,
+    /// The number of best root moves a search reports, each with its own principal variation.
+    pub multi_pv: usize,
+}
+
+impl Default for EngineOptions {
+    fn default() -> Self {
+        EngineOptions {
+            analysis_mode: false,
+            multi_pv: 1,
+        }
+    }
+}
+
+impl EngineOptions {
+    /// Whether the root may be restricted by a tablebase before searching.
+    pub const fn uses_tablebase_cutoffs(&self) -> bool {
+        !self.analysis_mode
+    }
+
+    /// Whether moves may be played from an opening book instead of being searched.
+    pub const fn uses_book(&self) -> bool {
+        !self.analysis_mode
+    }
+
+    /// Whether games may be ended early by adjudication.
+    pub const fn allows_adjudication(&self) -> bool {
+        !self.analysis_mode
+    }
+
+    /// Sets the option called `name` (case-insensitive, as in UCI) to `value`.
+    pub fn set_uci_option(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if name.eq_ignore_ascii_case(UCI_ANALYSE_MODE) {
+            self.analysis_mode = match value.to_ascii_lowercase().as_str() {
+                "true" => true,
+                "false" => false,
+                _ => return Err(format!("Invalid value for {}: {}", UCI_ANALYSE_MODE, value))
+            };
+            Ok(())
+        } else if name.eq_ignore_ascii_case(UCI_MULTI_PV) {
+            self.multi_pv = match value.parse::<usize>() {
+                Ok(multi_pv) if (1..=MAX_MULTI_PV).contains(&multi_pv) => multi_pv,
+                _ => return Err(format!("Invalid value for {}: {}", UCI_MULTI_PV, value))
+            };
+            Ok(())
+        } else {
+            Err(format!("Unknown option: {}", name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_uci_option() {
+        let mut options = EngineOptions::default();
+        assert!(options.uses_tablebase_cutoffs());
+
+        assert_eq!(options.set_uci_option("uci_analysemode", "true"), Ok(()));
+        assert!(options.analysis_mode);
+        assert!(!options.uses_tablebase_cutoffs() && !options.uses_book() && !options.allows_adjudication());
+
+        assert!(options.set_uci_option(UCI_ANALYSE_MODE, "yes").is_err());
+        assert!(options.analysis_mode);
+        assert!(options.set_uci_option("Hash", "16").is_err());
+
+        assert_eq!(options.multi_pv, 1);
+        assert_eq!(options.set_uci_option("multipv", "3"), Ok(()));
+        assert_eq!(options.multi_pv, 3);
+        assert!(options.set_uci_option(UCI_MULTI_PV, "0").is_err());
+        assert!(options.set_uci_option(UCI_MULTI_PV, "many").is_err());
+        assert_eq!(options.multi_pv, 3);
+    }
+}
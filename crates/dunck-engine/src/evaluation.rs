@@ -0,0 +1,148 @@
+use dunck_core::r#move::Move;
+use dunck_core::state::{State, Termination};
+use dunck_core::utils::Color;
+
+/// The values assigned to game outcomes. Wins and losses are always 1 and -1; the other scores are
+/// for the side to move in the final position and negated for its opponent, keeping scores zero-sum.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutcomeScores {
+    /// The value of any draw other than stalemate.
+    pub draw: f64,
+    /// The value of a stalemate, for the stalemated side.
+    pub stalemate: f64,
+    /// The value of a game or rollout cut off at its maximum depth.
+    pub max_depth_reached: f64,
+}
+
+impl Default for OutcomeScores {
+    fn default() -> Self {
+        OutcomeScores {
+            draw: 0.,
+            stalemate: 0.,
+            max_depth_reached: 0.,
+        }
+    }
+}
+
+impl OutcomeScores {
+    /// Scores every draw, including stalemate, as `draw`.
+    pub fn with_draw_score(draw: f64) -> Self {
+        OutcomeScores {
+            draw,
+            stalemate: draw,
+            ..Default::default()
+        }
+    }
+
+    pub fn get_value_at_terminal_state(&self, state: &State, for_color: Color) -> f64 {
+        match state.termination.unwrap() {
+            Termination::Checkmate => {
+                let checkmated_side = state.side_to_move;
+                if checkmated_side == for_color {
+                    -1.
+                } else {
+                    1.
+                }
+            }
+            termination => {
+                let value = if termination == Termination::Stalemate { self.stalemate } else { self.draw };
+                if state.side_to_move == for_color {
+                    value
+                } else {
+                    -value
+                }
+            }
+        }
+    }
+}
+
+/// Scores a terminal state with the default `OutcomeScores`.
+pub fn get_value_at_terminal_state(state: &State, for_color: Color) -> f64 {
+    OutcomeScores::default().get_value_at_terminal_state(state, for_color)
+}
+
+#[derive(Debug, Clone)]
+pub struct Evaluation {
+    pub policy: Vec<(Move, f64)>,
+    pub value: f64,
+}
+
+pub trait Evaluator {
+    fn evaluate(&self, state: &State) -> Evaluation;
+
+    /// Evaluates several states at once, returning their evaluations in the same order.
+    /// Evaluators that can share work between states, like a network's forward pass, should override this.
+    fn evaluate_batch(&self, states: &[State]) -> Vec<Evaluation> {
+        states.iter().map(|state| self.evaluate(state)).collect()
+    }
+
+    /// Estimates how many plies are left in the game, if the evaluator can predict game length.
+    fn estimate_moves_left(&self, _state: &State) -> Option<f64> {
+        None
+    }
+}
+
+/// Shifts winning and losing values by the predicted game length, so that search prefers
+/// faster wins and slower losses.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MovesLeftUtility {
+    /// How much each ply left shrinks the magnitude of a decisive value.
+    pub slope: f64,
+    /// The largest adjustment made to any value.
+    pub max_effect: f64,
+    /// Values closer to zero than this are not adjusted, since the game is not yet decided.
+    pub threshold: f64,
+}
+
+impl Default for MovesLeftUtility {
+    fn default() -> Self {
+        MovesLeftUtility {
+            slope: 0.002,
+            max_effect: 0.1,
+            threshold: 0.8,
+        }
+    }
+}
+
+impl MovesLeftUtility {
+    pub fn adjust_value(&self, value: f64, moves_left: f64) -> f64 {
+        if value.abs() < self.threshold {
+            return value;
+        }
+        let effect = (self.slope * moves_left.max(0.)).min(self.max_effect);
+        value - value.signum() * effect
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_moves_left_utility() {
+        let utility = MovesLeftUtility::default();
+        assert_eq!(utility.adjust_value(0.5, 100.), 0.5);
+        assert!(utility.adjust_value(0.9, 10.) > utility.adjust_value(0.9, 20.));
+        assert!(utility.adjust_value(-0.9, 10.) < utility.adjust_value(-0.9, 20.));
+        assert_eq!(utility.adjust_value(1., 1000.), 1. - utility.max_effect);
+        assert_eq!(utility.adjust_value(-1., 0.), -1.);
+    }
+
+    #[test]
+    fn test_outcome_scores() {
+        let mut stalemate = State::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        stalemate.check_and_update_termination();
+        assert_eq!(stalemate.termination, Some(Termination::Stalemate));
+        let mut insufficient_material = State::from_fen("7k/8/6K1/8/8/8/8/8 w - - 0 1").unwrap();
+        insufficient_material.termination = Some(Termination::InsufficientMaterial);
+
+        assert_eq!(get_value_at_terminal_state(&stalemate, Color::Black), 0.);
+        assert_eq!(get_value_at_terminal_state(&insufficient_material, Color::White), 0.);
+
+        let scores = OutcomeScores { draw: -0.1, stalemate: 0.5, max_depth_reached: 0. };
+        assert_eq!(scores.get_value_at_terminal_state(&stalemate, Color::Black), 0.5);
+        assert_eq!(scores.get_value_at_terminal_state(&stalemate, Color::White), -0.5);
+        assert_eq!(scores.get_value_at_terminal_state(&insufficient_material, Color::White), -0.1);
+        assert_eq!(scores.get_value_at_terminal_state(&insufficient_material, Color::Black), 0.1);
+        assert_eq!(OutcomeScores::with_draw_score(0.2).get_value_at_terminal_state(&stalemate, Color::Black), 0.2);
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+use crate::evaluation::{Evaluation, Evaluator};
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+
+/// Averages the values and policies of several evaluators.
+pub struct EnsembleEvaluator {
+    pub members: Vec<Box<dyn Evaluator>>,
+}
+
+impl EnsembleEvaluator {
+    pub fn new(members: Vec<Box<dyn Evaluator>>) -> EnsembleEvaluator {
+        assert!(!members.is_empty(), "An ensemble needs at least one evaluator");
+        EnsembleEvaluator { members }
+    }
+}
+
+/// Averages the evaluations of a state by every member, in the order of the members.
+fn average_evaluations(evaluations: impl ExactSizeIterator<Item = Evaluation>) -> Evaluation {
+    let num_members = evaluations.len() as f64;
+    let mut value = 0.;
+    let mut moves: Vec<Move> = Vec::new();
+    let mut priors: HashMap<Move, f64> = HashMap::new();
+    for evaluation in evaluations {
+        value += evaluation.value / num_members;
+        for (mv, prior) in evaluation.policy {
+            // keep the move order of the first evaluator
+            let total = priors.entry(mv).or_insert_with(|| {
+                moves.push(mv);
+                0.
+            });
+            *total += prior / num_members;
+        }
+    }
+
+    Evaluation {
+        policy: moves.into_iter().map(|mv| (mv, priors[&mv])).collect(),
+        value,
+    }
+}
+
+impl Evaluator for EnsembleEvaluator {
+    fn evaluate(&self, state: &State) -> Evaluation {
+        average_evaluations(self.members.iter().map(|member| member.evaluate(state)))
+    }
+
+    /// Lets every member evaluate the whole batch at once, then averages the evaluations of each state.
+    fn evaluate_batch(&self, states: &[State]) -> Vec<Evaluation> {
+        let mut member_evaluations: Vec<std::vec::IntoIter<Evaluation>> = self.members.iter()
+            .map(|member| member.evaluate_batch(states).into_iter())
+            .collect();
+        (0..states.len())
+            .map(|_| average_evaluations(member_evaluations.iter_mut().map(|evaluations| evaluations.next().unwrap())))
+            .collect()
+    }
+
+    /// Averages the estimates of the members that make one.
+    fn estimate_moves_left(&self, state: &State) -> Option<f64> {
+        let estimates: Vec<f64> = self.members.iter().filter_map(|member| member.estimate_moves_left(state)).collect();
+        if estimates.is_empty() {
+            None
+        } else {
+            Some(estimates.iter().sum::<f64>() / estimates.len() as f64)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::evaluators::pst::PstEvaluator;
+
+    #[test]
+    fn test_ensemble_averages() {
+        let state = State::from_fen("4k3/8/8/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+        let ensemble = EnsembleEvaluator::new(vec![Box::new(MaterialEvaluator {}), Box::new(PstEvaluator::default())]);
+        let evaluation = ensemble.evaluate(&state);
+        let expected_value = (MaterialEvaluator {}.evaluate(&state).value + PstEvaluator::default().evaluate(&state).value) / 2.;
+        assert!((evaluation.value - expected_value).abs() < 1e-9);
+
+        let num_moves = state.calc_legal_moves().len();
+        assert_eq!(evaluation.policy.len(), num_moves);
+        assert!(evaluation.policy.iter().all(|(_, prior)| (prior - 1. / num_moves as f64).abs() < 1e-9));
+        assert_eq!(ensemble.estimate_moves_left(&state), None);
+    }
+}
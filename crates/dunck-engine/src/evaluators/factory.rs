@@ -0,0 +1,309 @@
+//! Builds the evaluator used at engine startup, falling back to a simpler evaluator when the model is unavailable.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use crate::evaluation::Evaluator;
+use crate::evaluators::ensemble::EnsembleEvaluator;
+use crate::evaluators::material_simple::MaterialEvaluator;
+use crate::evaluators::nnue::NnueEvaluator;
+use crate::evaluators::pst::{EvalStyle, PstEvaluator};
+use crate::evaluators::random_rollout::RolloutEvaluator;
+
+/// The name of the UCI option selecting the evaluator, whose value is parsed as an `EvaluatorKind`.
+pub const UCI_EVAL_BACKEND: &str = "EvalBackend";
+
+/// The rollout depth used when a rollout evaluator is selected by name without one.
+pub const DEFAULT_MAX_ROLLOUT_DEPTH: u32 = 300;
+
+/// Loads a conv net with the given number of residual blocks and filters from a model file.
+pub type ConvNetLoader = fn(model_path: &str, num_residual_blocks: usize, num_filters: i64) -> Result<Box<dyn Evaluator>, String>;
+
+static CONV_NET_LOADER: OnceLock<ConvNetLoader> = OnceLock::new();
+
+/// Makes `create_evaluator` load conv nets with `loader`, returning false if a loader was already registered.
+/// `dunck_nn::register` registers the `tch` one; until then, conv nets are replaced by the fallback evaluator.
+pub fn register_conv_net_loader(loader: ConvNetLoader) -> bool {
+    CONV_NET_LOADER.set(loader).is_ok()
+}
+
+/// The evaluator to use when the model cannot be loaded.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum FallbackEvaluator {
+    Material,
+    Rollout { max_rollout_depth: u32 }
+}
+
+/// Describes where to look for the model, and what to use if it is missing.
+#[derive(Clone, Debug)]
+pub struct EvaluatorConfig {
+    pub model_path: String,
+    pub num_residual_blocks: usize,
+    pub num_filters: i64,
+    pub fallback: FallbackEvaluator,
+}
+
+impl Default for EvaluatorConfig {
+    fn default() -> Self {
+        EvaluatorConfig {
+            model_path: "model.safetensors".to_string(),
+            num_residual_blocks: 10,
+            num_filters: 256,
+            fallback: FallbackEvaluator::Material,
+        }
+    }
+}
+
+/// An evaluator selected by name, e.g. from a config file, a command line argument or the `EvalBackend` UCI option.
+/// Parsed from and displayed as `material`, `pst[:<style>]`, `rollout[:<depth>]`, `convnet[:<path>]`,
+/// `ensemble:<kind>+<kind>+...` or `nnue:<path>`, case-insensitively.
+#[derive(Clone, Debug, PartialEq)]
+pub enum EvaluatorKind {
+    Material,
+    Pst { style: EvalStyle },
+    Rollout { max_rollout_depth: u32 },
+    ConvNet { path: String },
+    Ensemble(Vec<EvaluatorKind>),
+    Nnue { path: String }
+}
+
+impl FromStr for EvaluatorKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<EvaluatorKind, String> {
+        let s = s.trim();
+        let (name, argument) = match s.split_once(':') {
+            Some((name, argument)) => (name, Some(argument.trim())),
+            None => (s, None)
+        };
+        match (name.to_ascii_lowercase().as_str(), argument) {
+            ("material", None) => Ok(EvaluatorKind::Material),
+            ("pst", None) => Ok(EvaluatorKind::Pst { style: EvalStyle::default() }),
+            ("pst", Some(style)) => style.parse().map(|style| EvaluatorKind::Pst { style }),
+            ("rollout", None) => Ok(EvaluatorKind::Rollout { max_rollout_depth: DEFAULT_MAX_ROLLOUT_DEPTH }),
+            ("rollout", Some(depth)) => depth.parse()
+                .map(|max_rollout_depth| EvaluatorKind::Rollout { max_rollout_depth })
+                .map_err(|e| format!("Invalid rollout depth {}: {}", depth, e)),
+            ("convnet", None) => Ok(EvaluatorKind::ConvNet { path: EvaluatorConfig::default().model_path }),
+            ("convnet", Some(path)) if !path.is_empty() => Ok(EvaluatorKind::ConvNet { path: path.to_string() }),
+            ("nnue", Some(path)) if !path.is_empty() => Ok(EvaluatorKind::Nnue { path: path.to_string() }),
+            ("ensemble", Some(members)) => {
+                let members = members.split('+').map(EvaluatorKind::from_str).collect::<Result<Vec<_>, _>>()?;
+                Ok(EvaluatorKind::Ensemble(members))
+            },
+            _ => Err(format!("Unknown evaluator: {}", s))
+        }
+    }
+}
+
+impl Display for EvaluatorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            EvaluatorKind::Material => write!(f, "material"),
+            EvaluatorKind::Pst { style } if *style == EvalStyle::default() => write!(f, "pst"),
+            EvaluatorKind::Pst { style } => write!(f, "pst:{}", style),
+            EvaluatorKind::Rollout { max_rollout_depth } => write!(f, "rollout:{}", max_rollout_depth),
+            EvaluatorKind::ConvNet { path } => write!(f, "convnet:{}", path),
+            EvaluatorKind::Ensemble(members) => {
+                let members: Vec<String> = members.iter().map(|member| member.to_string()).collect();
+                write!(f, "ensemble:{}", members.join("+"))
+            },
+            EvaluatorKind::Nnue { path } => write!(f, "nnue:{}", path),
+        }
+    }
+}
+
+/// Returns the evaluator used when none is selected: the conv net at the default model path,
+/// or the piece-square table evaluator if no conv net loader is registered.
+pub fn get_default_evaluator_kind() -> EvaluatorKind {
+    match CONV_NET_LOADER.get().is_some() {
+        true => EvaluatorKind::ConvNet { path: EvaluatorConfig::default().model_path },
+        false => EvaluatorKind::Pst { style: EvalStyle::default() }
+    }
+}
+
+/// Returns the UCI `option` line declaring the `EvalBackend` option.
+pub fn get_eval_backend_uci_option(default: &EvaluatorKind) -> String {
+    format!("option name {} type string default {}", UCI_EVAL_BACKEND, default)
+}
+
+/// Which kind of evaluator ended up being used.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ActiveEvaluator {
+    ConvNet { model_path: String },
+    Material,
+    Pst,
+    Rollout { max_rollout_depth: u32 },
+    Ensemble(Vec<ActiveEvaluator>),
+    Nnue { path: String }
+}
+
+impl Display for ActiveEvaluator {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ActiveEvaluator::ConvNet { model_path } => write!(f, "conv net ({})", model_path),
+            ActiveEvaluator::Material => write!(f, "material"),
+            ActiveEvaluator::Pst => write!(f, "piece-square tables"),
+            ActiveEvaluator::Rollout { max_rollout_depth } => write!(f, "rollout (depth {})", max_rollout_depth),
+            ActiveEvaluator::Ensemble(members) => {
+                let members: Vec<String> = members.iter().map(|member| member.to_string()).collect();
+                write!(f, "ensemble ({})", members.join(", "))
+            },
+            ActiveEvaluator::Nnue { path } => write!(f, "NNUE ({})", path),
+        }
+    }
+}
+
+/// An evaluator built by `create_evaluator`, along with a description of what it is.
+pub struct LoadedEvaluator {
+    pub evaluator: Box<dyn Evaluator>,
+    pub active: ActiveEvaluator,
+    /// Why the fallback was used, if it was.
+    pub warning: Option<String>,
+}
+
+impl LoadedEvaluator {
+    /// Returns a UCI `info string` line naming the active evaluator.
+    pub fn get_uci_info_string(&self) -> String {
+        format!("info string evaluator {}", self.active)
+    }
+}
+
+/// Loads the conv net from `config.model_path` if it exists and is loadable.
+/// Otherwise, prints a warning and returns the fallback evaluator instead of panicking.
+/// Without a registered conv net loader, always uses the fallback.
+pub fn create_evaluator(config: &EvaluatorConfig) -> LoadedEvaluator {
+    let warning = match CONV_NET_LOADER.get() {
+        None => format!("cannot load model {} without a conv net loader", config.model_path),
+        Some(_) if !Path::new(&config.model_path).exists() => format!("model file {} not found", config.model_path),
+        Some(loader) => match loader(&config.model_path, config.num_residual_blocks, config.num_filters) {
+            Ok(evaluator) => return LoadedEvaluator {
+                evaluator,
+                active: ActiveEvaluator::ConvNet { model_path: config.model_path.clone() },
+                warning: None,
+            },
+            Err(e) => format!("failed to load model {}: {}", config.model_path, e)
+        }
+    };
+
+    let loaded = create_fallback_evaluator(config.fallback, warning);
+    eprintln!("Warning: {}, using {} evaluator", loaded.warning.as_ref().unwrap(), loaded.active);
+    loaded
+}
+
+/// Builds the evaluator selected by `kind`. A conv net that cannot be loaded is replaced by `config.fallback`
+/// as in `create_evaluator`, using `config` for its architecture.
+/// Fails for NNUE networks that cannot be loaded, since there is no network to fall back to.
+pub fn create_evaluator_of_kind(kind: &EvaluatorKind, config: &EvaluatorConfig) -> Result<LoadedEvaluator, String> {
+    let loaded = match kind {
+        EvaluatorKind::Material => LoadedEvaluator { evaluator: Box::new(MaterialEvaluator {}), active: ActiveEvaluator::Material, warning: None },
+        EvaluatorKind::Pst { style } => LoadedEvaluator { evaluator: Box::new(PstEvaluator::new(*style)), active: ActiveEvaluator::Pst, warning: None },
+        EvaluatorKind::Rollout { max_rollout_depth } => LoadedEvaluator {
+            evaluator: Box::new(RolloutEvaluator::new(*max_rollout_depth)),
+            active: ActiveEvaluator::Rollout { max_rollout_depth: *max_rollout_depth },
+            warning: None,
+        },
+        EvaluatorKind::ConvNet { path } => create_evaluator(&EvaluatorConfig { model_path: path.clone(), ..config.clone() }),
+        EvaluatorKind::Ensemble(members) => {
+            if members.is_empty() {
+                return Err("An ensemble needs at least one evaluator".to_string());
+            }
+            let mut evaluators = Vec::with_capacity(members.len());
+            let mut actives = Vec::with_capacity(members.len());
+            let mut warnings = Vec::new();
+            for member in members {
+                let loaded = create_evaluator_of_kind(member, config)?;
+                evaluators.push(loaded.evaluator);
+                actives.push(loaded.active);
+                warnings.extend(loaded.warning);
+            }
+            LoadedEvaluator {
+                evaluator: Box::new(EnsembleEvaluator::new(evaluators)),
+                active: ActiveEvaluator::Ensemble(actives),
+                warning: if warnings.is_empty() { None } else { Some(warnings.join("; ")) },
+            }
+        },
+        EvaluatorKind::Nnue { path } => LoadedEvaluator {
+            evaluator: Box::new(NnueEvaluator::load(path).map_err(|e| format!("Failed to load NNUE network {}: {}", path, e))?),
+            active: ActiveEvaluator::Nnue { path: path.clone() },
+            warning: None,
+        },
+    };
+    Ok(loaded)
+}
+
+fn create_fallback_evaluator(fallback: FallbackEvaluator, warning: String) -> LoadedEvaluator {
+    let (evaluator, active): (Box<dyn Evaluator>, ActiveEvaluator) = match fallback {
+        FallbackEvaluator::Material => (Box::new(MaterialEvaluator {}), ActiveEvaluator::Material),
+        FallbackEvaluator::Rollout { max_rollout_depth } => (
+            Box::new(RolloutEvaluator::new(max_rollout_depth)),
+            ActiveEvaluator::Rollout { max_rollout_depth }
+        )
+    };
+    LoadedEvaluator { evaluator, active, warning: Some(warning) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::pst::StyleWeights;
+    use dunck_core::utils::Color;
+
+    #[test]
+    fn test_missing_model_falls_back() {
+        let config = EvaluatorConfig {
+            model_path: "does/not/exist.safetensors".to_string(),
+            fallback: FallbackEvaluator::Rollout { max_rollout_depth: 50 },
+            ..Default::default()
+        };
+        let loaded = create_evaluator(&config);
+        assert_eq!(loaded.active, ActiveEvaluator::Rollout { max_rollout_depth: 50 });
+        assert!(loaded.warning.is_some());
+        assert_eq!(loaded.get_uci_info_string(), "info string evaluator rollout (depth 50)");
+
+        let config = EvaluatorConfig { model_path: "does/not/exist.safetensors".to_string(), ..Default::default() };
+        assert_eq!(create_evaluator(&config).active, ActiveEvaluator::Material);
+
+        assert_eq!(get_default_evaluator_kind().to_string(), "pst");
+    }
+
+    #[test]
+    fn test_evaluator_kind_parsing() {
+        assert_eq!("material".parse::<EvaluatorKind>(), Ok(EvaluatorKind::Material));
+        assert_eq!("PST".parse::<EvaluatorKind>(), Ok(EvaluatorKind::Pst { style: EvalStyle::default() }));
+        assert_eq!("rollout".parse::<EvaluatorKind>(), Ok(EvaluatorKind::Rollout { max_rollout_depth: DEFAULT_MAX_ROLLOUT_DEPTH }));
+        assert_eq!("convnet:models/best.safetensors".parse::<EvaluatorKind>(), Ok(EvaluatorKind::ConvNet { path: "models/best.safetensors".to_string() }));
+        assert!("rollout:deep".parse::<EvaluatorKind>().is_err());
+        assert!("nnue".parse::<EvaluatorKind>().is_err());
+        assert!("alphazero".parse::<EvaluatorKind>().is_err());
+
+        let kind = EvaluatorKind::Ensemble(vec![EvaluatorKind::Material, EvaluatorKind::Rollout { max_rollout_depth: 20 }]);
+        assert_eq!(kind.to_string(), "ensemble:material+rollout:20");
+        assert_eq!(kind.to_string().parse::<EvaluatorKind>(), Ok(kind));
+        assert_eq!(get_eval_backend_uci_option(&EvaluatorKind::Pst { style: EvalStyle::default() }), "option name EvalBackend type string default pst");
+
+        let kind: EvaluatorKind = "ensemble:material+pst:tempo=0.1,white.aggressiveness=2".parse().unwrap();
+        let style = EvalStyle { tempo: 0.1, ..Default::default() }
+            .with_weights(Color::White, StyleWeights { aggressiveness: 2., king_safety: 0. });
+        assert_eq!(kind, EvaluatorKind::Ensemble(vec![EvaluatorKind::Material, EvaluatorKind::Pst { style }]));
+        assert_eq!(kind.to_string().parse::<EvaluatorKind>(), Ok(kind));
+        assert!("pst:tempo=fast".parse::<EvaluatorKind>().is_err());
+    }
+
+    #[test]
+    fn test_create_evaluator_of_kind() {
+        let config = EvaluatorConfig::default();
+        let loaded = create_evaluator_of_kind(&"ensemble:material+pst".parse().unwrap(), &config).unwrap();
+        assert_eq!(loaded.active, ActiveEvaluator::Ensemble(vec![ActiveEvaluator::Material, ActiveEvaluator::Pst]));
+        assert_eq!(loaded.warning, None);
+        assert_eq!(loaded.evaluator.evaluate(&dunck_core::state::State::initial()).policy.len(), 20);
+
+        let loaded = create_evaluator_of_kind(&"convnet:does/not/exist.safetensors".parse().unwrap(), &config).unwrap();
+        assert_eq!(loaded.active, ActiveEvaluator::Material);
+        assert!(loaded.warning.is_some());
+
+        assert!(create_evaluator_of_kind(&"nnue:net.nnue".parse().unwrap(), &config).is_err());
+    }
+}
@@ -1,7 +1,7 @@
-use crate::engine::evaluation::{Evaluation, Evaluator};
-use crate::r#move::Move;
-use crate::state::State;
-use crate::utils::{Color, PieceType};
+use crate::evaluation::{Evaluation, Evaluator};
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+use dunck_core::utils::{Color, PieceType};
 
 #[derive(Clone)]
 pub struct MaterialEvaluator {
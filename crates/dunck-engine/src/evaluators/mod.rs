@@ -0,0 +1,8 @@
+pub mod material_simple;
+pub mod random_rollout;
+pub mod pst;
+pub mod ensemble;
+pub mod neural;
+pub mod factory;
+pub mod tablebase;
+pub mod nnue;
\ No newline at end of file
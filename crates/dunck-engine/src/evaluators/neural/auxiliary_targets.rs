@@ -0,0 +1,111 @@
+//! Targets for the auxiliary heads, which are trained alongside the policy and value heads
+//! to give the network extra signal per position.
+
+use dunck_core::attacks::{multi_king_attacks, multi_knight_attacks, multi_pawn_attacks, single_bishop_attacks, single_rook_attacks};
+use crate::evaluators::neural::constants::NUM_ATTACKED_SQUARES_PLANES;
+use dunck_core::pgn::PgnStateTree;
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+use dunck_core::utils::{get_squares_from_mask_iter, Bitboard, Color, PieceType};
+
+/// Returns the mask of squares attacked by `color`, including squares occupied by its own pieces.
+pub fn calc_attacked_squares_mask(state: &State, color: Color) -> Bitboard {
+    let board = &state.board;
+    let occupied_mask = board.piece_type_masks[PieceType::AllPieceTypes as usize];
+    let color_mask = board.color_masks[color as usize];
+    let get_mask = |piece_type: PieceType| board.piece_type_masks[piece_type as usize] & color_mask;
+
+    let mut attacked_mask = multi_pawn_attacks(get_mask(PieceType::Pawn), color)
+        | multi_knight_attacks(get_mask(PieceType::Knight))
+        | multi_king_attacks(get_mask(PieceType::King));
+    for square in get_squares_from_mask_iter(get_mask(PieceType::Bishop) | get_mask(PieceType::Queen)) {
+        attacked_mask |= single_bishop_attacks(square, occupied_mask);
+    }
+    for square in get_squares_from_mask_iter(get_mask(PieceType::Rook) | get_mask(PieceType::Queen)) {
+        attacked_mask |= single_rook_attacks(square, occupied_mask);
+    }
+    attacked_mask
+}
+
+/// Returns the squares attacked by the side to move and by its opponent as two planes,
+/// indexed by rank and file from the perspective of the side to move, like the input planes.
+pub fn get_attacked_squares_target(state: &State) -> [[[f32; 8]; 8]; NUM_ATTACKED_SQUARES_PLANES as usize] {
+    let mut target = [[[0.; 8]; 8]; NUM_ATTACKED_SQUARES_PLANES as usize];
+    for (plane, color) in [state.side_to_move, state.side_to_move.flip()].into_iter().enumerate() {
+        for square in get_squares_from_mask_iter(calc_attacked_squares_mask(state, color)) {
+            let square = square.to_perspective_from_white(state.side_to_move);
+            target[plane][square.get_rank() as usize][square.get_file() as usize] = 1.;
+        }
+    }
+    target
+}
+
+/// A position from a game, with the move the opponent replied with after the move played from it.
+/// The reply is `None` if the game ended before the opponent moved.
+#[derive(Debug, Clone)]
+pub struct AuxiliaryExample {
+    pub state: State,
+    pub opponent_reply: Option<Move>,
+}
+
+/// Returns an example for every position on the main line of a game that still has a move to play.
+pub fn get_auxiliary_examples_from_state_tree(state_tree: &PgnStateTree) -> Vec<AuxiliaryExample> {
+    let mut states = Vec::new();
+    let mut moves = Vec::new();
+    let mut current_node = state_tree.head.clone();
+    loop {
+        states.push(current_node.borrow().state_after_move.clone());
+        let next_node = current_node.borrow().next_main_node();
+        match next_node {
+            Some(next_node) => {
+                moves.push(next_node.borrow().move_and_san_and_previous_node.as_ref().unwrap().0);
+                current_node = next_node;
+            },
+            None => break,
+        }
+    }
+
+    // the final position has no move played from it, so it gets no example
+    states.into_iter()
+        .take(moves.len())
+        .enumerate()
+        .map(|(i, state)| AuxiliaryExample { state, opponent_reply: moves.get(i + 1).copied() })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dunck_core::utils::Square;
+
+    #[test]
+    fn test_attacked_squares() {
+        let state = State::initial();
+        let white_attacks = calc_attacked_squares_mask(&state, Color::White);
+        assert_eq!(white_attacks.count_ones(), 22);
+        assert_ne!(white_attacks & Square::E3.get_mask(), 0);
+        assert_eq!(white_attacks & Square::E4.get_mask(), 0);
+
+        let target = get_attacked_squares_target(&state);
+        assert_eq!(target[0][2], [1.; 8]);
+        assert_eq!(target[0][3], [0.; 8]);
+        assert_eq!(target[1][5], [1.; 8]);
+
+        // planes are from the perspective of the side to move
+        let state = State::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        let target = get_attacked_squares_target(&state);
+        assert_eq!(target[1][0][7], 1.);
+        assert_eq!(target[1][7][7], 0.);
+    }
+
+    #[test]
+    fn test_auxiliary_examples() {
+        let state_tree: PgnStateTree = "1. e4 e5 2. Nf3".parse().unwrap();
+        let examples = get_auxiliary_examples_from_state_tree(&state_tree);
+        assert_eq!(examples.len(), 3);
+        assert_eq!(examples[0].state, State::initial());
+        assert_eq!(examples[0].opponent_reply.unwrap().get_destination(), Square::E5);
+        assert_eq!(examples[1].opponent_reply.unwrap().get_destination(), Square::F3);
+        assert!(examples[2].opponent_reply.is_none());
+    }
+}
@@ -3,7 +3,7 @@ pub const NUM_PIECE_TYPE_BITS: u8 = 6; // 6 piece types
 pub const NUM_COLOR_BITS: u8 = 2; // 2 colors
 pub const NUM_BITS_PER_BOARD: u8 = NUM_PIECE_TYPE_BITS * NUM_COLOR_BITS;
 
-pub const NUM_STATES_LOOKBACK: u8 = 0; // no lookback
+pub const NUM_STATES_LOOKBACK: u8 = 0; // number of previous boards encoded as extra planes, no lookback by default
 pub const NUM_STATES_TO_CONSIDER: u8 = NUM_STATES_LOOKBACK + 1;
 
 pub const NUM_BOARD_BITS: u8 = NUM_BITS_PER_BOARD * NUM_STATES_TO_CONSIDER; // 12 bits for board(s)
@@ -25,4 +25,5 @@ pub const NUM_UNDERPROMOTIONS: u8 = 3; // 3 underpromotions (knight, bishop, roo
 pub const NUM_WAYS_OF_UNDERPROMOTION: u8 = NUM_PAWN_MOVE_DIRECTIONS * NUM_UNDERPROMOTIONS; // 9 ways of underpromotion
 
 pub const NUM_TARGET_SQUARE_POSSIBILITIES: u8 = NUM_QUEEN_LIKE_MOVES + MAX_NUM_KNIGHT_MOVES + NUM_WAYS_OF_UNDERPROMOTION; // 73 of possible target squares for a move
-pub const NUM_OUTPUT_POLICY_MOVES: usize = 64 * NUM_TARGET_SQUARE_POSSIBILITIES as usize; // 4672 possible moves for policy head
\ No newline at end of file
+pub const NUM_OUTPUT_POLICY_MOVES: usize = 64 * NUM_TARGET_SQUARE_POSSIBILITIES as usize; // 4672 possible moves for policy head
+pub const NUM_ATTACKED_SQUARES_PLANES: u8 = 2; // squares attacked by the side to move and by its opponent
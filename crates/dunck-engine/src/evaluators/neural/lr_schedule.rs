@@ -0,0 +1,101 @@
+//! Learning rate schedules for training, as a function of the number of optimizer steps taken.
+
+use std::f64::consts::PI;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LrSchedule {
+    Constant,
+    /// Decays from the base rate to `min_fraction` of it along half a cosine over `total_steps`, and stays there.
+    Cosine { total_steps: usize, min_fraction: f64 },
+    /// Multiplies the rate by `gamma` every `step_size` steps.
+    Step { step_size: usize, gamma: f64 },
+}
+
+impl LrSchedule {
+    /// Returns the learning rate after `step` optimizer steps, starting from `base_learning_rate`.
+    pub fn get_learning_rate(&self, base_learning_rate: f64, step: usize) -> f64 {
+        match self {
+            LrSchedule::Constant => base_learning_rate,
+            LrSchedule::Cosine { total_steps, min_fraction } => {
+                let progress = step.min(*total_steps) as f64 / (*total_steps).max(1) as f64;
+                let fraction = min_fraction + (1. - min_fraction) * (1. + (PI * progress).cos()) / 2.;
+                base_learning_rate * fraction
+            },
+            LrSchedule::Step { step_size, gamma } => {
+                base_learning_rate * gamma.powi((step / (*step_size).max(1)) as i32)
+            },
+        }
+    }
+}
+
+impl Display for LrSchedule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LrSchedule::Constant => write!(f, "constant"),
+            LrSchedule::Cosine { total_steps, min_fraction } => write!(f, "cosine:{}:{}", total_steps, min_fraction),
+            LrSchedule::Step { step_size, gamma } => write!(f, "step:{}:{}", step_size, gamma),
+        }
+    }
+}
+
+impl FromStr for LrSchedule {
+    type Err = String;
+
+    /// Parses `constant`, `cosine:<total steps>[:<min fraction>]` or `step:<step size>:<gamma>`,
+    /// e.g. `cosine:10000:0.05` or `step:2000:0.5`.
+    fn from_str(s: &str) -> Result<LrSchedule, String> {
+        let fields: Vec<&str> = s.trim().split(':').collect();
+        let parse_steps = |field: &str| field.parse::<usize>()
+            .ok()
+            .filter(|steps| *steps > 0)
+            .ok_or_else(|| format!("Invalid number of steps {} in learning rate schedule {}", field, s));
+        let parse_factor = |field: &str| field.parse::<f64>()
+            .ok()
+            .filter(|factor| (0. ..=1.).contains(factor))
+            .ok_or_else(|| format!("Invalid factor {} in learning rate schedule {}, expected a number from 0 to 1", field, s));
+        match fields.as_slice() {
+            ["constant"] => Ok(LrSchedule::Constant),
+            ["cosine", total_steps] => Ok(LrSchedule::Cosine { total_steps: parse_steps(total_steps)?, min_fraction: 0. }),
+            ["cosine", total_steps, min_fraction] => {
+                Ok(LrSchedule::Cosine { total_steps: parse_steps(total_steps)?, min_fraction: parse_factor(min_fraction)? })
+            },
+            ["step", step_size, gamma] => Ok(LrSchedule::Step { step_size: parse_steps(step_size)?, gamma: parse_factor(gamma)? }),
+            _ => Err(format!("Invalid learning rate schedule {}, expected constant, cosine:<steps>[:<min fraction>] or step:<steps>:<gamma>", s))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_learning_rates() {
+        assert_eq!(LrSchedule::Constant.get_learning_rate(0.01, 1000), 0.01);
+
+        let cosine = LrSchedule::Cosine { total_steps: 100, min_fraction: 0.1 };
+        assert!((cosine.get_learning_rate(1., 0) - 1.).abs() < 1e-12);
+        assert!((cosine.get_learning_rate(1., 50) - 0.55).abs() < 1e-12);
+        assert!((cosine.get_learning_rate(1., 100) - 0.1).abs() < 1e-12);
+        assert!((cosine.get_learning_rate(1., 1000) - 0.1).abs() < 1e-12);
+
+        let step = LrSchedule::Step { step_size: 10, gamma: 0.5 };
+        assert_eq!(step.get_learning_rate(1., 9), 1.);
+        assert_eq!(step.get_learning_rate(1., 10), 0.5);
+        assert_eq!(step.get_learning_rate(1., 25), 0.25);
+    }
+
+    #[test]
+    fn test_parse() {
+        for schedule in [LrSchedule::Constant, LrSchedule::Cosine { total_steps: 5000, min_fraction: 0.05 }, LrSchedule::Step { step_size: 100, gamma: 0.5 }] {
+            assert_eq!(schedule.to_string().parse(), Ok(schedule));
+        }
+        assert_eq!("cosine:100".parse(), Ok(LrSchedule::Cosine { total_steps: 100, min_fraction: 0. }));
+        assert!("cosine".parse::<LrSchedule>().is_err());
+        assert!("step:0:0.5".parse::<LrSchedule>().is_err());
+        assert!("step:100:2".parse::<LrSchedule>().is_err());
+        assert!("linear".parse::<LrSchedule>().is_err());
+    }
+}
@@ -0,0 +1,8 @@
+//! The parts of the conv net that do not depend on `tch`: the network config, input constants, learning rate
+//! schedules and auxiliary targets, so that manifests and checkpoints can be inspected without libtorch.
+//! The network itself and its training are in `dunck_nn`.
+
+pub mod constants;
+pub mod net_config;
+pub mod lr_schedule;
+pub mod auxiliary_targets;
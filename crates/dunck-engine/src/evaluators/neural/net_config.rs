@@ -0,0 +1,197 @@
+//! Network configuration, saved next to each checkpoint so that weights are only loaded into a
+//! network with the same architecture and input encoding.
+
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+use crate::evaluators::neural::constants::{NUM_BITS_PER_BOARD, NUM_METADATA_BITS, NUM_STATES_LOOKBACK};
+
+/// The version of the checkpoint format. Bump this whenever the input encoding or the
+/// network layout changes in a way that makes old weights unusable.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// Describes the shape of a `ConvNet` and the input encoding it was trained on.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+pub struct NetConfig {
+    pub version: u32,
+    pub num_states_lookback: u8,
+    pub num_residual_blocks: usize,
+    pub num_filters: i64,
+    /// Whether the network has a head predicting the number of plies left in the game.
+    pub use_moves_left_head: bool,
+    /// Whether the network has an auxiliary head predicting the squares attacked by each side.
+    pub use_attacked_squares_head: bool,
+    /// Whether the network has an auxiliary head predicting the opponent's reply to the move played.
+    pub use_opponent_reply_head: bool,
+}
+
+impl NetConfig {
+    /// Creates a config for the current checkpoint version and input encoding.
+    pub fn new(num_residual_blocks: usize, num_filters: i64) -> NetConfig {
+        NetConfig {
+            version: CHECKPOINT_VERSION,
+            num_states_lookback: NUM_STATES_LOOKBACK,
+            num_residual_blocks,
+            num_filters,
+            use_moves_left_head: false,
+            use_attacked_squares_head: false,
+            use_opponent_reply_head: false,
+        }
+    }
+
+    /// Adds a moves-left head to the network.
+    pub fn with_moves_left_head(mut self) -> NetConfig {
+        self.use_moves_left_head = true;
+        self
+    }
+
+    /// Adds the auxiliary heads, which are only used during training.
+    pub fn with_auxiliary_heads(mut self, use_attacked_squares_head: bool, use_opponent_reply_head: bool) -> NetConfig {
+        self.use_attacked_squares_head = use_attacked_squares_head;
+        self.use_opponent_reply_head = use_opponent_reply_head;
+        self
+    }
+
+    /// Returns the number of 8x8 input planes the network expects.
+    pub const fn get_num_input_channels(&self) -> i64 {
+        (NUM_BITS_PER_BOARD as i64) * (self.num_states_lookback as i64 + 1) + NUM_METADATA_BITS as i64
+    }
+
+    /// Returns the path of the config file belonging to the checkpoint at `checkpoint_path`.
+    pub fn get_config_path(checkpoint_path: &str) -> String {
+        format!("{}.config", checkpoint_path)
+    }
+
+    /// Serializes the config as `key=value` lines.
+    pub fn to_config_string(&self) -> String {
+        format!(
+            "version={}\nnum_states_lookback={}\nnum_residual_blocks={}\nnum_filters={}\nuse_moves_left_head={}\nuse_attacked_squares_head={}\nuse_opponent_reply_head={}\n",
+            self.version, self.num_states_lookback, self.num_residual_blocks, self.num_filters,
+            self.use_moves_left_head, self.use_attacked_squares_head, self.use_opponent_reply_head
+        )
+    }
+
+    /// Parses a config written by `to_config_string`. Configs written before the optional heads
+    /// existed have no keys for them and are read as not using them.
+    pub fn from_config_string(config_string: &str) -> Result<NetConfig, Box<dyn Error>> {
+        let mut version = None;
+        let mut num_states_lookback = None;
+        let mut num_residual_blocks = None;
+        let mut num_filters = None;
+        let mut use_moves_left_head = false;
+        let mut use_attacked_squares_head = false;
+        let mut use_opponent_reply_head = false;
+
+        for line in config_string.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (key, value) = line.split_once('=').ok_or_else(|| format!("Malformed config line: {}", line))?;
+            match key.trim() {
+                "version" => version = Some(value.trim().parse()?),
+                "num_states_lookback" => num_states_lookback = Some(value.trim().parse()?),
+                "num_residual_blocks" => num_residual_blocks = Some(value.trim().parse()?),
+                "num_filters" => num_filters = Some(value.trim().parse()?),
+                "use_moves_left_head" => use_moves_left_head = value.trim().parse()?,
+                "use_attacked_squares_head" => use_attacked_squares_head = value.trim().parse()?,
+                "use_opponent_reply_head" => use_opponent_reply_head = value.trim().parse()?,
+                _ => return Err(format!("Unknown config key: {}", key).into())
+            }
+        }
+
+        Ok(NetConfig {
+            version: version.ok_or("Missing config key: version")?,
+            num_states_lookback: num_states_lookback.ok_or("Missing config key: num_states_lookback")?,
+            num_residual_blocks: num_residual_blocks.ok_or("Missing config key: num_residual_blocks")?,
+            num_filters: num_filters.ok_or("Missing config key: num_filters")?,
+            use_moves_left_head,
+            use_attacked_squares_head,
+            use_opponent_reply_head,
+        })
+    }
+
+    /// Writes the config next to the checkpoint at `checkpoint_path`.
+    pub fn save(&self, checkpoint_path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(Self::get_config_path(checkpoint_path), self.to_config_string())?;
+        Ok(())
+    }
+
+    /// Reads the config of the checkpoint at `checkpoint_path`.
+    /// Checkpoints saved before configs existed have no config file; they are assumed to be
+    /// version 1 with no lookback and no optional heads, using the architecture given by `fallback`.
+    pub fn load(checkpoint_path: &str, fallback: &NetConfig) -> Result<NetConfig, Box<dyn Error>> {
+        let config_path = Self::get_config_path(checkpoint_path);
+        if !Path::new(&config_path).exists() {
+            return Ok(NetConfig {
+                version: 1,
+                num_states_lookback: 0,
+                use_moves_left_head: false,
+                use_attacked_squares_head: false,
+                use_opponent_reply_head: false,
+                ..*fallback
+            });
+        }
+        Self::from_config_string(&fs::read_to_string(config_path)?)
+    }
+
+    /// Returns an error describing the first mismatch if a checkpoint with config `other`
+    /// cannot be loaded into a network with this config.
+    pub fn check_compatible(&self, other: &NetConfig) -> Result<(), Box<dyn Error>> {
+        if self.version != other.version {
+            return Err(format!("Checkpoint version {} does not match expected version {}", other.version, self.version).into());
+        }
+        if self.num_states_lookback != other.num_states_lookback {
+            return Err(format!("Checkpoint uses {} states of lookback, but {} are expected", other.num_states_lookback, self.num_states_lookback).into());
+        }
+        if self.num_residual_blocks != other.num_residual_blocks || self.num_filters != other.num_filters {
+            return Err(format!(
+                "Checkpoint has {} residual blocks with {} filters, but {} with {} are expected",
+                other.num_residual_blocks, other.num_filters, self.num_residual_blocks, self.num_filters
+            ).into());
+        }
+        if self.use_moves_left_head != other.use_moves_left_head {
+            return Err(format!(
+                "Checkpoint {} a moves-left head, but the network {}",
+                if other.use_moves_left_head { "has" } else { "does not have" },
+                if self.use_moves_left_head { "has one" } else { "does not" }
+            ).into());
+        }
+        if (self.use_attacked_squares_head, self.use_opponent_reply_head) != (other.use_attacked_squares_head, other.use_opponent_reply_head) {
+            return Err(format!(
+                "Checkpoint has auxiliary heads (attacked squares: {}, opponent reply: {}), but (attacked squares: {}, opponent reply: {}) are expected",
+                other.use_attacked_squares_head, other.use_opponent_reply_head, self.use_attacked_squares_head, self.use_opponent_reply_head
+            ).into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::neural::constants::NUM_POSITION_BITS;
+
+    #[test]
+    fn test_config_round_trip() {
+        let config = NetConfig::new(10, 256);
+        assert_eq!(config.get_num_input_channels(), NUM_POSITION_BITS as i64);
+        assert_eq!(NetConfig::from_config_string(&config.to_config_string()).unwrap(), config);
+        assert!(NetConfig::from_config_string("version=1\n").is_err());
+        assert!(NetConfig::from_config_string("version=one\n").is_err());
+
+        let config = config.with_moves_left_head().with_auxiliary_heads(true, false);
+        assert_eq!(NetConfig::from_config_string(&config.to_config_string()).unwrap(), config);
+        let old_config_string = "version=1\nnum_states_lookback=0\nnum_residual_blocks=10\nnum_filters=256\n";
+        let old_config = NetConfig::from_config_string(old_config_string).unwrap();
+        assert!(!old_config.use_moves_left_head && !old_config.use_attacked_squares_head && !old_config.use_opponent_reply_head);
+    }
+
+    #[test]
+    fn test_check_compatible() {
+        let config = NetConfig::new(10, 256);
+        assert!(config.check_compatible(&config).is_ok());
+        assert!(config.check_compatible(&NetConfig { version: CHECKPOINT_VERSION + 1, ..config }).is_err());
+        assert!(config.check_compatible(&NetConfig { num_states_lookback: NUM_STATES_LOOKBACK + 1, ..config }).is_err());
+        assert!(config.check_compatible(&NetConfig { num_filters: 128, ..config }).is_err());
+        assert!(config.check_compatible(&config.with_moves_left_head()).is_err());
+        assert!(config.check_compatible(&config.with_auxiliary_heads(false, true)).is_err());
+    }
+}
@@ -0,0 +1,396 @@
+//! An efficiently updatable neural network (NNUE) evaluator, running on integers in pure Rust, so without libtorch.
+//!
+//! The network has one input per colored piece and square, seen from each side's perspective, a hidden layer per
+//! perspective sharing its weights, and a single output. The hidden layers are kept in an `Accumulator`, which
+//! `AccumulatorStack` updates alongside `State::make_move` and `State::unmake_move` by adding and removing the
+//! inputs of the squares that changed, instead of recomputing them from every piece on the board.
+//! `AlphaBetaSearch::with_nnue` searches with such a stack.
+
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::Path;
+use crate::evaluation::{Evaluation, Evaluator};
+use dunck_core::r#move::Move;
+use dunck_core::state::{Board, State};
+use dunck_core::utils::{get_squares_from_mask_iter, Color, ColoredPiece, PieceType, Square};
+
+/// The first bytes of a network file.
+pub const NNUE_MAGIC: [u8; 4] = *b"DNUE";
+/// The version of the network file format written by `NnueNetwork::to_bytes`.
+pub const NNUE_VERSION: u32 = 1;
+/// The number of inputs of each perspective: one per piece type, color relative to the perspective, and square.
+pub const NUM_NNUE_FEATURES: usize = 2 * 6 * 64;
+/// Hidden activations are clipped to `[0, NNUE_QA]`, which is 1 in the unquantized network.
+pub const NNUE_QA: i32 = 255;
+/// The quantization factor of the output weights.
+pub const NNUE_QB: i32 = 64;
+/// The output of the unquantized network, scaled to centipawns.
+pub const NNUE_SCALE: i32 = 400;
+
+/// The error returned when a network file cannot be loaded.
+#[derive(Debug)]
+pub enum NnueLoadError {
+    Io(io::Error),
+    InvalidMagic,
+    UnsupportedVersion(u32),
+    /// The file is shorter or longer than its hidden size implies.
+    InvalidSize { expected: usize, actual: usize },
+}
+
+impl Display for NnueLoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NnueLoadError::Io(error) => write!(f, "I/O error: {}", error),
+            NnueLoadError::InvalidMagic => write!(f, "Not an NNUE network file"),
+            NnueLoadError::UnsupportedVersion(version) => write!(f, "Unsupported NNUE version: {}", version),
+            NnueLoadError::InvalidSize { expected, actual } => write!(f, "Invalid NNUE file size: expected {} bytes, got {}", expected, actual),
+        }
+    }
+}
+
+impl Error for NnueLoadError {}
+
+impl From<io::Error> for NnueLoadError {
+    fn from(error: io::Error) -> Self {
+        NnueLoadError::Io(error)
+    }
+}
+
+/// Returns the input of `colored_piece` on `square` from `perspective`'s point of view. Both perspectives see
+/// their own pieces first and their own side of the board at the bottom, so they share the weights.
+pub fn get_feature_index(perspective: Color, colored_piece: ColoredPiece, square: Square) -> usize {
+    let relative_color = (colored_piece.get_color() != perspective) as usize;
+    let piece_index = colored_piece.get_piece_type() as usize - 1;
+    let square_index = match perspective {
+        Color::White => square as usize,
+        Color::Black => square as usize ^ 56
+    };
+    (relative_color * 6 + piece_index) * 64 + square_index
+}
+
+/// A quantized network. The file format is the magic, the version and the hidden size as little-endian `u32`s,
+/// followed by the hidden weights (input-major), hidden biases and output weights (own perspective first)
+/// as little-endian `i16`s, and the output bias as a little-endian `i32`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NnueNetwork {
+    pub hidden_size: usize,
+    /// `NUM_NNUE_FEATURES` rows of `hidden_size` weights, quantized by `NNUE_QA`.
+    pub feature_weights: Vec<i16>,
+    /// Quantized by `NNUE_QA`.
+    pub feature_biases: Vec<i16>,
+    /// The weights of the side to move's hidden layer, then the opponent's, quantized by `NNUE_QB`.
+    pub output_weights: Vec<i16>,
+    /// Quantized by `NNUE_QA * NNUE_QB`.
+    pub output_bias: i32,
+}
+
+impl NnueNetwork {
+    /// Returns a network with every weight and bias zero, which evaluates every position as equal.
+    pub fn zeroed(hidden_size: usize) -> NnueNetwork {
+        NnueNetwork {
+            hidden_size,
+            feature_weights: vec![0; NUM_NNUE_FEATURES * hidden_size],
+            feature_biases: vec![0; hidden_size],
+            output_weights: vec![0; 2 * hidden_size],
+            output_bias: 0,
+        }
+    }
+
+    fn calc_file_size(hidden_size: usize) -> usize {
+        12 + 2 * (NUM_NNUE_FEATURES * hidden_size + hidden_size + 2 * hidden_size) + 4
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<NnueNetwork, NnueLoadError> {
+        let read_u32 = |offset: usize| bytes.get(offset..offset + 4).map(|b| u32::from_le_bytes(b.try_into().unwrap()));
+        if bytes.get(0..4) != Some(&NNUE_MAGIC[..]) {
+            return Err(NnueLoadError::InvalidMagic);
+        }
+        let version = read_u32(4).ok_or(NnueLoadError::InvalidSize { expected: 12, actual: bytes.len() })?;
+        if version != NNUE_VERSION {
+            return Err(NnueLoadError::UnsupportedVersion(version));
+        }
+        let hidden_size = read_u32(8).ok_or(NnueLoadError::InvalidSize { expected: 12, actual: bytes.len() })? as usize;
+        let expected = NnueNetwork::calc_file_size(hidden_size);
+        if bytes.len() != expected {
+            return Err(NnueLoadError::InvalidSize { expected, actual: bytes.len() });
+        }
+
+        let mut offset = 12;
+        let mut read_i16s = |count: usize| {
+            let values: Vec<i16> = bytes[offset..offset + 2 * count].chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+            offset += 2 * count;
+            values
+        };
+        let feature_weights = read_i16s(NUM_NNUE_FEATURES * hidden_size);
+        let feature_biases = read_i16s(hidden_size);
+        let output_weights = read_i16s(2 * hidden_size);
+        let output_bias = i32::from_le_bytes(bytes[expected - 4..].try_into().unwrap());
+        Ok(NnueNetwork { hidden_size, feature_weights, feature_biases, output_weights, output_bias })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(NnueNetwork::calc_file_size(self.hidden_size));
+        bytes.extend_from_slice(&NNUE_MAGIC);
+        bytes.extend_from_slice(&NNUE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.hidden_size as u32).to_le_bytes());
+        for value in self.feature_weights.iter().chain(self.feature_biases.iter()).chain(self.output_weights.iter()) {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.output_bias.to_le_bytes());
+        bytes
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<NnueNetwork, NnueLoadError> {
+        NnueNetwork::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_bytes())
+    }
+
+    fn get_feature_weights(&self, feature_index: usize) -> &[i16] {
+        &self.feature_weights[feature_index * self.hidden_size..(feature_index + 1) * self.hidden_size]
+    }
+
+    /// Returns the evaluation of the position whose hidden layers are in `accumulator`, in centipawns for `side_to_move`.
+    pub fn evaluate(&self, accumulator: &Accumulator, side_to_move: Color) -> i32 {
+        let (own_weights, other_weights) = self.output_weights.split_at(self.hidden_size);
+        let mut output = self.output_bias as i64;
+        for (values, weights) in [(&accumulator.values[side_to_move as usize], own_weights), (&accumulator.values[side_to_move.flip() as usize], other_weights)] {
+            for (value, weight) in values.iter().zip(weights.iter()) {
+                output += (*value as i32).clamp(0, NNUE_QA) as i64 * *weight as i64;
+            }
+        }
+        (output * NNUE_SCALE as i64 / (NNUE_QA * NNUE_QB) as i64) as i32
+    }
+}
+
+/// The hidden layer of each perspective, indexed by `Color`, before activation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Accumulator {
+    pub values: [Vec<i16>; 2],
+}
+
+impl Accumulator {
+    /// Computes the hidden layers from every piece on `board`.
+    pub fn from_board(network: &NnueNetwork, board: &Board) -> Accumulator {
+        let mut accumulator = Accumulator { values: [network.feature_biases.clone(), network.feature_biases.clone()] };
+        for square in Square::iter_all() {
+            let colored_piece = board.get_colored_piece_at(*square);
+            if colored_piece != ColoredPiece::NoPiece {
+                accumulator.add_piece(network, colored_piece, *square);
+            }
+        }
+        accumulator
+    }
+
+    pub fn add_piece(&mut self, network: &NnueNetwork, colored_piece: ColoredPiece, square: Square) {
+        for perspective in Color::ALL {
+            let weights = network.get_feature_weights(get_feature_index(perspective, colored_piece, square));
+            for (value, weight) in self.values[perspective as usize].iter_mut().zip(weights) {
+                *value = value.wrapping_add(*weight);
+            }
+        }
+    }
+
+    pub fn remove_piece(&mut self, network: &NnueNetwork, colored_piece: ColoredPiece, square: Square) {
+        for perspective in Color::ALL {
+            let weights = network.get_feature_weights(get_feature_index(perspective, colored_piece, square));
+            for (value, weight) in self.values[perspective as usize].iter_mut().zip(weights) {
+                *value = value.wrapping_sub(*weight);
+            }
+        }
+    }
+
+    /// Updates the hidden layers from the pieces of `before` to those of `after`, touching only the squares that changed,
+    /// so that captures, castling, en passant and promotions need no special cases.
+    pub fn apply_board_change(&mut self, network: &NnueNetwork, before: &Board, after: &Board) {
+        for color in Color::ALL {
+            for piece_type in PieceType::iter_pieces() {
+                let colored_piece = ColoredPiece::from(color, *piece_type);
+                let mask_before = before.color_masks[color as usize] & before.piece_type_masks[*piece_type as usize];
+                let mask_after = after.color_masks[color as usize] & after.piece_type_masks[*piece_type as usize];
+                for square in get_squares_from_mask_iter(mask_before & !mask_after) {
+                    self.remove_piece(network, colored_piece, square);
+                }
+                for square in get_squares_from_mask_iter(mask_after & !mask_before) {
+                    self.add_piece(network, colored_piece, square);
+                }
+            }
+        }
+    }
+}
+
+/// The accumulators of the positions along a line, for searches that make and unmake moves on a single state.
+pub struct AccumulatorStack {
+    stack: Vec<Accumulator>,
+}
+
+impl AccumulatorStack {
+    pub fn new(network: &NnueNetwork, state: &State) -> AccumulatorStack {
+        AccumulatorStack { stack: vec![Accumulator::from_board(network, &state.board)] }
+    }
+
+    /// Returns the accumulator of the current position.
+    pub fn get_current(&self) -> &Accumulator {
+        self.stack.last().expect("the stack always has the root's accumulator")
+    }
+
+    /// Makes `mv` on `state` and pushes the accumulator of the new position.
+    pub fn make_move(&mut self, network: &NnueNetwork, state: &mut State, mv: Move) {
+        let board_before = state.board.clone();
+        state.make_move(mv);
+        let mut accumulator = self.get_current().clone();
+        accumulator.apply_board_change(network, &board_before, &state.board);
+        self.stack.push(accumulator);
+    }
+
+    /// Unmakes `mv` on `state` and pops the accumulator of the position it led to.
+    pub fn unmake_move(&mut self, state: &mut State, mv: Move) {
+        assert!(self.stack.len() > 1, "unmake_move called without a matching make_move");
+        state.unmake_move(mv);
+        self.stack.pop();
+    }
+
+    /// Returns the evaluation of the current position, in centipawns for `side_to_move`.
+    pub fn evaluate(&self, network: &NnueNetwork, side_to_move: Color) -> i32 {
+        network.evaluate(self.get_current(), side_to_move)
+    }
+}
+
+/// Evaluates positions with an `NnueNetwork`, with a uniform policy. Each position is evaluated from scratch,
+/// since `Evaluator` sees positions one at a time; `AlphaBetaSearch::with_nnue` updates accumulators incrementally instead.
+#[derive(Clone)]
+pub struct NnueEvaluator {
+    pub network: NnueNetwork,
+}
+
+impl NnueEvaluator {
+    pub fn new(network: NnueNetwork) -> NnueEvaluator {
+        NnueEvaluator { network }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<NnueEvaluator, NnueLoadError> {
+        NnueNetwork::load(path).map(NnueEvaluator::new)
+    }
+
+    /// Returns the evaluation of `state`, in centipawns for the side to move.
+    pub fn calc_centipawns(&self, state: &State) -> i32 {
+        self.network.evaluate(&Accumulator::from_board(&self.network, &state.board), state.side_to_move)
+    }
+}
+
+impl Evaluator for NnueEvaluator {
+    fn evaluate(&self, state: &State) -> Evaluation {
+        let value = 2. * sigmoid(self.calc_centipawns(state) as f64 / 100., 0.5) - 1.; // Normalize to [-1, 1]
+
+        let legal_moves = state.calc_legal_moves();
+        let policy: Vec<(Move, f64)> = legal_moves.iter().map(|mv| (*mv, 1. / legal_moves.len() as f64)).collect();
+
+        Evaluation {
+            policy,
+            value,
+        }
+    }
+}
+
+fn sigmoid(x: f64, a: f64) -> f64 {
+    1.0 / (1.0 + (-a * x).exp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a network of hidden size 2 whose first unit sums the perspective's own material and whose second sums
+    /// the opponent's, in sixteenths of centipawns. Its output weights are zero.
+    fn material_network() -> NnueNetwork {
+        let mut network = NnueNetwork::zeroed(2);
+        let piece_values = [100, 300, 300, 500, 900, 0];
+        for color in Color::ALL {
+            for piece_type in PieceType::iter_pieces() {
+                for square in Square::iter_all() {
+                    // from white's perspective, white's pieces are its own
+                    let feature_index = get_feature_index(Color::White, ColoredPiece::from(color, *piece_type), *square);
+                    network.feature_weights[feature_index * 2 + color as usize] = piece_values[*piece_type as usize - 1] / 16;
+                }
+            }
+        }
+        network
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let network = material_network();
+        let bytes = network.to_bytes();
+        assert_eq!(NnueNetwork::from_bytes(&bytes).unwrap(), network);
+        assert!(matches!(NnueNetwork::from_bytes(&bytes[..bytes.len() - 1]), Err(NnueLoadError::InvalidSize { .. })));
+        assert!(matches!(NnueNetwork::from_bytes(b"NOPE"), Err(NnueLoadError::InvalidMagic)));
+        let mut future = bytes.clone();
+        future[4] = 2;
+        assert!(matches!(NnueNetwork::from_bytes(&future), Err(NnueLoadError::UnsupportedVersion(2))));
+        assert!(matches!(NnueNetwork::load("does/not/exist.nnue"), Err(NnueLoadError::Io(_))));
+    }
+
+    #[test]
+    fn test_feature_indices() {
+        let white_pawn_e2 = get_feature_index(Color::White, ColoredPiece::WhitePawn, Square::E2);
+        // black sees its own pawn on e7 where white sees its pawn on e2
+        assert_eq!(get_feature_index(Color::Black, ColoredPiece::BlackPawn, Square::E7), white_pawn_e2);
+        assert_ne!(get_feature_index(Color::Black, ColoredPiece::WhitePawn, Square::E2), white_pawn_e2);
+        let max_index = Color::ALL.iter()
+            .flat_map(|color| PieceType::iter_pieces().map(move |piece_type| ColoredPiece::from(*color, *piece_type)))
+            .map(|colored_piece| get_feature_index(Color::Black, colored_piece, Square::H1).max(get_feature_index(Color::White, colored_piece, Square::A1)))
+            .max()
+            .unwrap();
+        assert!(max_index < NUM_NNUE_FEATURES);
+    }
+
+    #[test]
+    fn test_incremental_updates_match_refresh() {
+        let mut network = NnueNetwork::zeroed(4);
+        // arbitrary but deterministic weights
+        for (i, weight) in network.feature_weights.iter_mut().enumerate() {
+            *weight = ((i * 7919) % 61) as i16 - 30;
+        }
+        network.output_weights = vec![3, -2, 5, 1, -4, 2, 0, 7];
+
+        // castling, en passant, promotion with capture
+        let mut state = State::from_fen("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 2").unwrap();
+        let mut stack = AccumulatorStack::new(&network, &state);
+        let mut made_moves = Vec::new();
+        for _ in 0..3 {
+            for mv in state.calc_legal_moves() {
+                stack.make_move(&network, &mut state, mv);
+                assert_eq!(*stack.get_current(), Accumulator::from_board(&network, &state.board), "{}", mv);
+                stack.unmake_move(&mut state, mv);
+            }
+            let mv = state.calc_legal_moves()[0];
+            stack.make_move(&network, &mut state, mv);
+            made_moves.push(mv);
+        }
+        for mv in made_moves.into_iter().rev() {
+            stack.unmake_move(&mut state, mv);
+        }
+        assert_eq!(*stack.get_current(), Accumulator::from_board(&network, &state.board));
+    }
+
+    #[test]
+    fn test_evaluation() {
+        let mut network = material_network();
+        // own material minus the opponent's, seen from the side to move
+        network.output_weights = vec![4 * NNUE_QB as i16, -4 * NNUE_QB as i16, 0, 0];
+        let evaluator = NnueEvaluator::new(network);
+        assert_eq!(evaluator.calc_centipawns(&State::initial()), 0);
+
+        let white_to_move = State::from_fen("4k3/8/8/8/8/8/8/3NK3 w - - 0 1").unwrap();
+        let black_to_move = State::from_fen("4k3/8/8/8/8/8/8/3NK3 b - - 0 1").unwrap();
+        assert!(evaluator.calc_centipawns(&white_to_move) > 0);
+        assert_eq!(evaluator.calc_centipawns(&black_to_move), -evaluator.calc_centipawns(&white_to_move));
+
+        let evaluation = evaluator.evaluate(&white_to_move);
+        assert!(evaluation.value > 0.);
+        assert_eq!(evaluation.policy.len(), white_to_move.calc_legal_moves().len());
+    }
+}
@@ -0,0 +1,308 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use dunck_core::attacks::multi_king_attacks;
+use crate::evaluation::{Evaluation, Evaluator};
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+use dunck_core::utils::{get_squares_from_mask_iter, Color, PieceType};
+
+/// How much each attacked square around a king is worth, in pawns, at a style weight of 1.
+pub const KING_ZONE_ATTACK_VALUE: f64 = 0.05;
+
+/// Weights for the parts of the evaluation that make up one color's playing style.
+/// At 0, the default, a part is left out, so that the evaluation is purely material and placement.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StyleWeights {
+    /// Scales the bonus for attacking the squares around the enemy king.
+    pub aggressiveness: f64,
+    /// Scales the penalty for enemy attacks on the squares around the own king.
+    pub king_safety: f64,
+}
+
+/// Asymmetric evaluation terms, to make the same evaluator play in distinct styles.
+/// Parsed from and displayed as comma-separated `key=value` pairs, e.g. `tempo=0.1,white.aggressiveness=2`,
+/// where the weight keys `aggressiveness` and `king_safety` set both colors unless prefixed with `white.` or `black.`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EvalStyle {
+    /// A bonus for the side to move, in pawns.
+    pub tempo: f64,
+    /// The style weights of each color, indexed by `Color`.
+    pub weights: [StyleWeights; 2],
+}
+
+impl EvalStyle {
+    pub fn with_tempo(mut self, tempo: f64) -> Self {
+        self.tempo = tempo;
+        self
+    }
+
+    pub fn with_weights(mut self, color: Color, weights: StyleWeights) -> Self {
+        self.weights[color as usize] = weights;
+        self
+    }
+}
+
+impl FromStr for EvalStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<EvalStyle, String> {
+        let mut style = EvalStyle::default();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').ok_or_else(|| format!("Missing value for style key: {}", pair))?;
+            let (key, value) = (key.trim().to_ascii_lowercase(), value.trim());
+            let value: f64 = value.parse().map_err(|e| format!("Invalid value for style key {}: {}", key, e))?;
+            if key == "tempo" {
+                style.tempo = value;
+                continue;
+            }
+
+            let (colors, name) = match key.split_once('.') {
+                Some(("white", name)) => (vec![Color::White], name),
+                Some(("black", name)) => (vec![Color::Black], name),
+                Some(_) => return Err(format!("Unknown style key: {}", key)),
+                None => (vec![Color::White, Color::Black], key.as_str())
+            };
+            for color in colors {
+                let weights = &mut style.weights[color as usize];
+                match name {
+                    "aggressiveness" => weights.aggressiveness = value,
+                    "king_safety" => weights.king_safety = value,
+                    _ => return Err(format!("Unknown style key: {}", key))
+                }
+            }
+        }
+        Ok(style)
+    }
+}
+
+impl Display for EvalStyle {
+    /// Writes only the terms that differ from the default, so that the default style is an empty string.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut pairs = Vec::new();
+        if self.tempo != 0. {
+            pairs.push(format!("tempo={}", self.tempo));
+        }
+        let [white, black] = self.weights;
+        let weights = [("aggressiveness", white.aggressiveness, black.aggressiveness), ("king_safety", white.king_safety, black.king_safety)];
+        for (name, white_value, black_value) in weights {
+            if white_value == black_value {
+                if white_value != 0. {
+                    pairs.push(format!("{}={}", name, white_value));
+                }
+                continue;
+            }
+            if white_value != 0. {
+                pairs.push(format!("white.{}={}", name, white_value));
+            }
+            if black_value != 0. {
+                pairs.push(format!("black.{}={}", name, black_value));
+            }
+        }
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+/// Evaluates positions by material and piece-square tables, with a uniform policy.
+/// An `EvalStyle` adds a tempo bonus and king attack and safety terms, weighted separately for each color.
+#[derive(Clone, Default)]
+pub struct PstEvaluator {
+    pub style: EvalStyle,
+}
+
+impl PstEvaluator {
+    pub fn new(style: EvalStyle) -> PstEvaluator {
+        PstEvaluator { style }
+    }
+
+    /// Returns the material and placement score of `color`'s pieces, in pawns.
+    pub fn calc_score(state: &State, color: Color) -> f64 {
+        let color_mask = state.board.color_masks[color as usize];
+        let mut score = 0.;
+        for piece_type in PieceType::iter_between(PieceType::Pawn, PieceType::King) {
+            let index = *piece_type as usize - 1;
+            let mask = color_mask & state.board.piece_type_masks[*piece_type as usize];
+            for square in get_squares_from_mask_iter(mask) {
+                // the tables are laid out from white's perspective, with a8 first, so black's squares are mirrored vertically
+                let square_index = match color {
+                    Color::White => square as usize,
+                    Color::Black => square as usize ^ 56
+                };
+                score += PIECE_VALUES[index] + PIECE_SQUARE_TABLES[index][square_index] as f64 / 100.;
+            }
+        }
+        score
+    }
+
+    /// Returns the king attack and safety score of `color`, in pawns, weighted by its style.
+    pub fn calc_style_score(&self, state: &State, color: Color) -> f64 {
+        let weights = self.style.weights[color as usize];
+        if weights.aggressiveness == 0. && weights.king_safety == 0. {
+            return 0.;
+        }
+        let attacks_on_king_zone = |attacking_color: Color| {
+            let king_mask = state.board.piece_type_masks[PieceType::King as usize] & state.board.color_masks[attacking_color.flip() as usize];
+            let king_zone = king_mask | multi_king_attacks(king_mask);
+            let occupied = state.board.piece_type_masks[PieceType::AllPieceTypes as usize];
+            (state.board.calc_attack_mask(attacking_color, occupied) & king_zone).count_ones() as f64
+        };
+        KING_ZONE_ATTACK_VALUE * (weights.aggressiveness * attacks_on_king_zone(color) - weights.king_safety * attacks_on_king_zone(color.flip()))
+    }
+}
+
+impl Evaluator for PstEvaluator {
+    fn evaluate(&self, state: &State) -> Evaluation {
+        let (us, them) = (state.side_to_move, state.side_to_move.flip());
+        let score_diff = PstEvaluator::calc_score(state, us) - PstEvaluator::calc_score(state, them)
+            + self.calc_style_score(state, us) - self.calc_style_score(state, them)
+            + self.style.tempo;
+
+        let value = 2. * sigmoid(score_diff, 0.5) - 1.; // Normalize to [-1, 1]
+
+        let legal_moves = state.calc_legal_moves();
+        let policy: Vec<(Move, f64)> = legal_moves.iter().map(|mv| (*mv, 1. / legal_moves.len() as f64)).collect();
+
+        Evaluation {
+            policy,
+            value,
+        }
+    }
+}
+
+fn sigmoid(x: f64, a: f64) -> f64 {
+    1.0 / (1.0 + (-a * x).exp())
+}
+
+const PIECE_VALUES: [f64; 6] = [
+    1.0,  // Pawn
+    3.2,  // Knight
+    3.3,  // Bishop
+    5.0,  // Rook
+    9.0,  // Queen
+    0.0   // King
+];
+
+/// Placement bonuses in centipawns, indexed by piece type and square from white's perspective.
+const PIECE_SQUARE_TABLES: [[i8; 64]; 6] = [
+    // Pawn
+    [
+         0,   0,   0,   0,   0,   0,   0,   0,
+        50,  50,  50,  50,  50,  50,  50,  50,
+        10,  10,  20,  30,  30,  20,  10,  10,
+         5,   5,  10,  25,  25,  10,   5,   5,
+         0,   0,   0,  20,  20,   0,   0,   0,
+         5,  -5, -10,   0,   0, -10,  -5,   5,
+         5,  10,  10, -20, -20,  10,  10,   5,
+         0,   0,   0,   0,   0,   0,   0,   0,
+    ],
+    // Knight
+    [
+       -50, -40, -30, -30, -30, -30, -40, -50,
+       -40, -20,   0,   0,   0,   0, -20, -40,
+       -30,   0,  10,  15,  15,  10,   0, -30,
+       -30,   5,  15,  20,  20,  15,   5, -30,
+       -30,   0,  15,  20,  20,  15,   0, -30,
+       -30,   5,  10,  15,  15,  10,   5, -30,
+       -40, -20,   0,   5,   5,   0, -20, -40,
+       -50, -40, -30, -30, -30, -30, -40, -50,
+    ],
+    // Bishop
+    [
+       -20, -10, -10, -10, -10, -10, -10, -20,
+       -10,   0,   0,   0,   0,   0,   0, -10,
+       -10,   0,   5,  10,  10,   5,   0, -10,
+       -10,   5,   5,  10,  10,   5,   5, -10,
+       -10,   0,  10,  10,  10,  10,   0, -10,
+       -10,  10,  10,  10,  10,  10,  10, -10,
+       -10,   5,   0,   0,   0,   0,   5, -10,
+       -20, -10, -10, -10, -10, -10, -10, -20,
+    ],
+    // Rook
+    [
+         0,   0,   0,   0,   0,   0,   0,   0,
+         5,  10,  10,  10,  10,  10,  10,   5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+        -5,   0,   0,   0,   0,   0,   0,  -5,
+         0,   0,   0,   5,   5,   0,   0,   0,
+    ],
+    // Queen
+    [
+       -20, -10, -10,  -5,  -5, -10, -10, -20,
+       -10,   0,   0,   0,   0,   0,   0, -10,
+       -10,   0,   5,   5,   5,   5,   0, -10,
+        -5,   0,   5,   5,   5,   5,   0,  -5,
+         0,   0,   5,   5,   5,   5,   0,  -5,
+       -10,   5,   5,   5,   5,   5,   0, -10,
+       -10,   0,   5,   0,   0,   0,   0, -10,
+       -20, -10, -10,  -5,  -5, -10, -10, -20,
+    ],
+    // King
+    [
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -30, -40, -40, -50, -50, -40, -40, -30,
+       -20, -30, -30, -40, -40, -30, -30, -20,
+       -10, -20, -20, -20, -20, -20, -20, -10,
+        20,  20,   0,   0,   0,   0,  20,  20,
+        20,  30,  10,   0,   0,  10,  30,  20,
+    ],
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pst_evaluator() {
+        let evaluator = PstEvaluator::default();
+        let evaluation = evaluator.evaluate(&State::initial());
+        assert!(evaluation.value.abs() < 1e-9);
+        assert_eq!(evaluation.policy.len(), 20);
+
+        // a centralized knight is worth more than one on the rim, for either color
+        let central = State::from_fen("4k3/8/8/8/4N3/8/8/4K3 w - - 0 1").unwrap();
+        let rim = State::from_fen("4k3/8/8/8/7N/8/8/4K3 w - - 0 1").unwrap();
+        assert!(evaluator.evaluate(&central).value > evaluator.evaluate(&rim).value);
+        let central = State::from_fen("4k3/8/8/4n3/8/8/8/4K3 b - - 0 1").unwrap();
+        let rim = State::from_fen("4k3/8/8/7n/8/8/8/4K3 b - - 0 1").unwrap();
+        assert!(evaluator.evaluate(&central).value > evaluator.evaluate(&rim).value);
+    }
+
+    #[test]
+    fn test_eval_style() {
+        let state = State::initial();
+        let evaluator = PstEvaluator::new(EvalStyle::default().with_tempo(0.2));
+        assert!(evaluator.evaluate(&state).value > 0.);
+        assert_eq!(evaluator.evaluate(&state).value, evaluator.evaluate(&State::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap()).value);
+
+        // white's queen and bishop bear down on black's king, which matters only to the styles weighting it
+        let state = State::from_fen("6k1/5ppp/8/8/8/1B6/5PPP/3Q2K1 w - - 0 1").unwrap();
+        let neutral = PstEvaluator::default().evaluate(&state).value;
+        let aggressive = StyleWeights { aggressiveness: 2., ..Default::default() };
+        let cautious = StyleWeights { king_safety: 2., ..Default::default() };
+        assert!(PstEvaluator::new(EvalStyle::default().with_weights(Color::White, aggressive)).evaluate(&state).value > neutral);
+        assert!(PstEvaluator::new(EvalStyle::default().with_weights(Color::Black, cautious)).evaluate(&state).value > neutral);
+        assert_eq!(PstEvaluator::new(EvalStyle::default().with_weights(Color::Black, aggressive)).evaluate(&state).value, neutral);
+    }
+
+    #[test]
+    fn test_eval_style_parsing() {
+        let style: EvalStyle = "tempo=0.1, aggressiveness=1.5, black.king_safety=2".parse().unwrap();
+        assert_eq!(style.tempo, 0.1);
+        assert_eq!(style.weights[Color::White as usize], StyleWeights { aggressiveness: 1.5, king_safety: 0. });
+        assert_eq!(style.weights[Color::Black as usize], StyleWeights { aggressiveness: 1.5, king_safety: 2. });
+        assert_eq!(style.to_string(), "tempo=0.1,aggressiveness=1.5,black.king_safety=2");
+        assert_eq!(style.to_string().parse(), Ok(style));
+
+        assert_eq!("".parse(), Ok(EvalStyle::default()));
+        assert_eq!(EvalStyle::default().to_string(), "");
+        assert!("tempo".parse::<EvalStyle>().is_err());
+        assert!("white.tempo=1".parse::<EvalStyle>().is_err());
+        assert!("green.aggressiveness=1".parse::<EvalStyle>().is_err());
+        assert!("aggressiveness=high".parse::<EvalStyle>().is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use rand::prelude::SliceRandom;
+use rand::Rng;
+use crate::evaluation::{Evaluation, Evaluator, OutcomeScores};
+use dunck_core::r#move::{Move, MoveFlag};
+use dunck_core::state::{get_see_value, State};
+use dunck_core::utils::PieceType;
+
+/// The material lead, in pawns, from which the side to move avoids stalemating the opponent by default.
+pub const DEFAULT_STALEMATE_AVOIDANCE_MARGIN: i32 = 5;
+
+#[derive(Clone)]
+pub struct RolloutEvaluator {
+    pub max_rollout_depth: u32,
+    pub outcome_scores: OutcomeScores,
+    /// If set, a side ahead by at least this many pawns does not play promotions and captures that stalemate
+    /// the opponent, which would otherwise turn many won rollouts in endgames into draws.
+    pub stalemate_avoidance_margin: Option<i32>,
+}
+
+impl RolloutEvaluator {
+    pub fn new(max_rollout_depth: u32) -> Self {
+        Self {
+            max_rollout_depth,
+            outcome_scores: OutcomeScores::default(),
+            stalemate_avoidance_margin: Some(DEFAULT_STALEMATE_AVOIDANCE_MARGIN),
+        }
+    }
+
+    pub fn with_outcome_scores(mut self, outcome_scores: OutcomeScores) -> Self {
+        self.outcome_scores = outcome_scores;
+        self
+    }
+
+    /// Sets the material lead from which stalemating promotions and captures are avoided, or None to play uniformly random moves.
+    pub fn with_stalemate_avoidance_margin(mut self, margin: Option<i32>) -> Self {
+        self.stalemate_avoidance_margin = margin;
+        self
+    }
+
+    /// Picks a random move of `moves`, the legal moves of `state`, redrawing among the others if it is a promotion
+    /// or capture that stalemates the opponent while the side to move is clearly winning.
+    fn choose_rollout_move<R: Rng>(&self, state: &State, moves: &[Move], rng: &mut R) -> Move {
+        let mv = *moves.choose(rng).unwrap();
+        let Some(margin) = self.stalemate_avoidance_margin else {
+            return mv;
+        };
+        // the material is only counted once a suspicious move was drawn, which keeps most rollout moves cheap
+        if !is_promotion_or_capture(state, mv) || calc_material_lead(state) < margin || !stalemates_opponent(state, mv) {
+            return mv;
+        }
+        let alternatives: Vec<Move> = moves.iter()
+            .filter(|other| !is_promotion_or_capture(state, **other) || !stalemates_opponent(state, **other))
+            .copied()
+            .collect();
+        alternatives.choose(rng).copied().unwrap_or(mv)
+    }
+}
+
+fn is_promotion_or_capture(state: &State, mv: Move) -> bool {
+    let opponent_mask = state.board.color_masks[state.side_to_move.flip() as usize];
+    matches!(mv.get_flag(), MoveFlag::Promotion | MoveFlag::EnPassant) || opponent_mask & mv.get_destination().get_mask() != 0
+}
+
+/// Returns the material of the side to move minus the opponent's, in pawns.
+fn calc_material_lead(state: &State) -> i32 {
+    let signature = state.get_material_signature();
+    let side_to_move = state.side_to_move;
+    PieceType::iter_non_king_pieces()
+        .map(|piece_type| {
+            let lead = signature.get_count(side_to_move, *piece_type) as i32 - signature.get_count(side_to_move.flip(), *piece_type) as i32;
+            get_see_value(*piece_type) * lead
+        })
+        .sum()
+}
+
+/// Returns whether playing `mv` leaves the opponent without legal moves while not in check.
+fn stalemates_opponent(state: &State, mv: Move) -> bool {
+    let mut next_state = state.clone();
+    next_state.make_move(mv);
+    !next_state.is_in_check() && next_state.calc_legal_moves().is_empty()
+}
+
+impl Evaluator for RolloutEvaluator {
+    fn evaluate(&self, state: &State) -> Evaluation {
+        let initial_moves = state.calc_legal_moves();
+        let side_to_move = state.side_to_move;
+        let mut state = state.clone();
+        let mut rng = rand::thread_rng();
+        let mut moves = Vec::new();
+        let mut i = 0;
+        let value;
+        loop {
+            state.calc_legal_moves_into(&mut moves);
+            if moves.is_empty() {
+                state.assume_and_update_termination();
+                value = self.outcome_scores.get_value_at_terminal_state(&state, side_to_move);
+                break;
+            } else {
+                let mv = self.choose_rollout_move(&state, &moves, &mut rng);
+                state.make_move(mv);
+                state.claim_any_draw();
+            }
+            i += 1;
+            
+            if i >= self.max_rollout_depth {
+                value = self.outcome_scores.max_depth_reached;
+                break;
+            }
+        }
+        
+        let mut policy = Vec::with_capacity(initial_moves.len());
+        for mv in initial_moves.iter() {
+            policy.push((*mv, 1. / initial_moves.len() as f64));
+        }
+        
+        Evaluation {
+            policy,
+            value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use super::*;
+
+    #[test]
+    fn test_stalemate_avoidance() {
+        // Qxh5 leaves black with a blocked pawn and a king with no squares
+        let state = State::from_fen("k7/p1K5/P7/7r/8/7Q/8/4N3 w - - 0 1").unwrap();
+        let stalemating_capture = Move::from_uci(&state, "h3h5").unwrap();
+        let other_move = Move::from_uci(&state, "h3h4").unwrap();
+        assert!(stalemates_opponent(&state, stalemating_capture));
+        assert!(!stalemates_opponent(&state, other_move));
+        assert_eq!(calc_material_lead(&state), 7);
+
+        let mut rng = StdRng::seed_from_u64(0);
+        let moves = [stalemating_capture, other_move];
+        let evaluator = RolloutEvaluator::new(100);
+        assert!((0..50).all(|_| evaluator.choose_rollout_move(&state, &moves, &mut rng) == other_move));
+        // the only move is played even if it stalemates
+        assert_eq!(evaluator.choose_rollout_move(&state, &moves[..1], &mut rng), stalemating_capture);
+
+        let evaluator = evaluator.with_stalemate_avoidance_margin(None);
+        assert!((0..50).any(|_| evaluator.choose_rollout_move(&state, &moves, &mut rng) == stalemating_capture));
+        let evaluator = evaluator.with_stalemate_avoidance_margin(Some(8));
+        assert!((0..50).any(|_| evaluator.choose_rollout_move(&state, &moves, &mut rng) == stalemating_capture));
+    }
+}
@@ -0,0 +1,149 @@
+use crate::evaluation::{Evaluation, Evaluator, OutcomeScores};
+use crate::tablebase::{TablebaseProber, TbScore};
+use dunck_core::state::State;
+
+/// The most pieces, including kings, in a position probed by default.
+pub const DEFAULT_TB_MAX_PIECES: u32 = 5;
+
+/// Wraps another evaluator, replacing its value with the tablebase score in positions with at most `max_pieces` pieces.
+/// The policy still comes from the wrapped evaluator, so the search keeps its move ordering.
+pub struct TablebaseEvaluator {
+    pub evaluator: Box<dyn Evaluator>,
+    pub prober: Box<dyn TablebaseProber>,
+    pub max_pieces: u32,
+    pub outcome_scores: OutcomeScores,
+}
+
+impl TablebaseEvaluator {
+    pub fn new(evaluator: Box<dyn Evaluator>, prober: Box<dyn TablebaseProber>) -> TablebaseEvaluator {
+        TablebaseEvaluator {
+            evaluator,
+            prober,
+            max_pieces: DEFAULT_TB_MAX_PIECES,
+            outcome_scores: OutcomeScores::default(),
+        }
+    }
+
+    pub fn with_max_pieces(mut self, max_pieces: u32) -> Self {
+        self.max_pieces = max_pieces;
+        self
+    }
+
+    pub fn with_outcome_scores(mut self, outcome_scores: OutcomeScores) -> Self {
+        self.outcome_scores = outcome_scores;
+        self
+    }
+
+    /// Returns the tablebase score of `state`, or None if it has too many pieces or is not covered.
+    pub fn probe(&self, state: &State) -> Option<TbScore> {
+        if state.board.count_all() > self.max_pieces {
+            return None;
+        }
+        TbScore::probe(state, self.prober.as_ref())
+    }
+}
+
+impl Evaluator for TablebaseEvaluator {
+    fn evaluate(&self, state: &State) -> Evaluation {
+        let mut evaluation = self.evaluator.evaluate(state);
+        if let Some(score) = self.probe(state) {
+            evaluation.value = score.get_value(&self.outcome_scores);
+        }
+        evaluation
+    }
+
+    fn evaluate_batch(&self, states: &[State]) -> Vec<Evaluation> {
+        let mut evaluations = self.evaluator.evaluate_batch(states);
+        for (state, evaluation) in states.iter().zip(evaluations.iter_mut()) {
+            if let Some(score) = self.probe(state) {
+                evaluation.value = score.get_value(&self.outcome_scores);
+            }
+        }
+        evaluations
+    }
+
+    fn estimate_moves_left(&self, state: &State) -> Option<f64> {
+        self.evaluator.estimate_moves_left(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::mcts::mcts::{calc_puct_score, MCTS};
+    use crate::tablebase::{Wdl, TB_WIN_VALUE};
+    use dunck_core::utils::{Color, PieceType};
+
+    /// Treats KQvK as won for the side with the queen and anything else with at most three pieces as drawn.
+    struct KqkTablebase {}
+
+    impl TablebaseProber for KqkTablebase {
+        fn probe_wdl(&self, state: &State) -> Option<Wdl> {
+            if state.board.count_all() > 3 {
+                return None;
+            }
+            let queens = state.board.piece_type_masks[PieceType::Queen as usize];
+            Some(if queens & state.board.color_masks[state.side_to_move as usize] != 0 {
+                Wdl::Win
+            } else if queens != 0 {
+                Wdl::Loss
+            } else {
+                Wdl::Draw
+            })
+        }
+
+        fn probe_dtz(&self, state: &State) -> Option<i32> {
+            self.probe_wdl(state).map(|wdl| if wdl == Wdl::Draw { 0 } else { 10 })
+        }
+    }
+
+    fn new_evaluator() -> TablebaseEvaluator {
+        TablebaseEvaluator::new(Box::new(MaterialEvaluator {}), Box::new(KqkTablebase {}))
+    }
+
+    #[test]
+    fn test_covered_position_uses_tablebase_value() {
+        let evaluator = new_evaluator();
+        let state = State::from_fen("8/8/6k1/8/3Q4/8/8/K7 w - - 0 1").unwrap();
+        let evaluation = evaluator.evaluate(&state);
+        let inner_evaluation = MaterialEvaluator {}.evaluate(&state);
+        assert_ne!(evaluation.value, inner_evaluation.value);
+        assert!(evaluation.value > 0.9 && evaluation.value <= TB_WIN_VALUE);
+        assert_eq!(evaluation.policy, inner_evaluation.policy);
+
+        let state = State::from_fen("8/8/6k1/8/3Q4/8/8/K7 b - - 0 1").unwrap();
+        assert!(evaluator.evaluate(&state).value < -0.9);
+        assert!(state.termination.is_none());
+
+        let state = State::from_fen("8/8/6k1/8/3N4/8/8/K7 w - - 0 1").unwrap();
+        let evaluator = evaluator.with_outcome_scores(OutcomeScores::with_draw_score(-0.2));
+        assert_eq!(evaluator.evaluate(&state).value, -0.2);
+    }
+
+    #[test]
+    fn test_uncovered_position_uses_inner_value() {
+        let evaluator = new_evaluator();
+        let state = State::initial();
+        assert_eq!(evaluator.probe(&state), None);
+        assert_eq!(evaluator.evaluate(&state).value, MaterialEvaluator {}.evaluate(&state).value);
+
+        // covered by the prober, but over the piece limit
+        let state = State::from_fen("8/8/6k1/8/3Q4/8/8/K7 w - - 0 1").unwrap();
+        assert!(evaluator.probe(&state).is_some());
+        assert_eq!(new_evaluator().with_max_pieces(2).probe(&state), None);
+    }
+
+    #[test]
+    fn test_search_sees_tablebase_win() {
+        // capturing the rook reaches KQvK, which the tablebase scores as won
+        let state = State::from_fen("8/8/6k1/8/3r4/8/3Q4/K7 w - - 0 1").unwrap();
+        let evaluator = new_evaluator();
+        let mut mcts = MCTS::new(state, 1.5, &evaluator, &calc_puct_score, false);
+        mcts.run(200);
+        let best_child = mcts.get_best_child_by_visits().unwrap();
+        let best_child = best_child.borrow();
+        assert_eq!(best_child.mv.unwrap().to_string(), "d2d4");
+        assert_eq!(best_child.state_after_move.board.count_color(Color::Black), 1);
+    }
+}
@@ -0,0 +1,165 @@
+//! Heuristic detection of fortresses and stalemate traps: positions where one side looks clearly better,
+//! but neither the board nor the search makes any progress towards converting the advantage.
+
+use dunck_core::state::State;
+use dunck_core::utils::Color;
+
+/// Flags a likely fortress once a game has gone `min_plies` plies without a capture or pawn move,
+/// while the static evaluation kept favoring the same side by at least `min_advantage`
+/// and that side's search score improved by no more than `max_score_change`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FortressDetector {
+    pub min_plies: u32,
+    pub min_advantage: f64,
+    pub max_score_change: f64,
+    /// How far reported scores are pulled towards a draw while a fortress is suspected,
+    /// from 0 (not at all) to 1 (scored as a draw).
+    pub draw_blend: f64,
+    observations: Vec<FortressObservation>,
+}
+
+/// The evaluations of a position, from white's perspective so that they can be compared across plies.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FortressObservation {
+    halfmove: u16,
+    halfmove_clock: u8,
+    static_value: f64,
+    search_score: f64,
+}
+
+impl Default for FortressDetector {
+    fn default() -> Self {
+        FortressDetector {
+            min_plies: 20,
+            min_advantage: 0.5,
+            max_score_change: 0.05,
+            draw_blend: 0.,
+            observations: Vec::new(),
+        }
+    }
+}
+
+impl FortressDetector {
+    pub fn with_draw_blend(mut self, draw_blend: f64) -> Self {
+        self.draw_blend = draw_blend;
+        self
+    }
+
+    /// Records the evaluations of `state`, both from the side to move's perspective.
+    /// Observing a ply again, e.g. after searching it further or taking moves back, forgets it and any later plies.
+    pub fn observe(&mut self, state: &State, static_value: f64, search_score: f64) {
+        let sign = if state.side_to_move == Color::White { 1. } else { -1. };
+        self.observations.retain(|observation| observation.halfmove < state.halfmove);
+        self.observations.push(FortressObservation {
+            halfmove: state.halfmove,
+            halfmove_clock: state.context.borrow().halfmove_clock,
+            static_value: sign * static_value,
+            search_score: sign * search_score,
+        });
+    }
+
+    /// Forgets all observations, e.g. when a new game starts.
+    pub fn reset(&mut self) {
+        self.observations.clear();
+    }
+
+    pub fn is_fortress_suspected(&self) -> bool {
+        let Some(latest) = self.observations.last() else {
+            return false;
+        };
+        if (latest.halfmove_clock as u32) < self.min_plies {
+            return false;
+        }
+
+        // no capture or pawn move was played in the window, so it only covers the current, unchanged material
+        let window_start = latest.halfmove.saturating_sub(self.min_plies as u16);
+        let window: Vec<&FortressObservation> = self.observations.iter()
+            .filter(|observation| observation.halfmove >= window_start)
+            .collect();
+        // a single observation says nothing about progress
+        if window.len() < 2 {
+            return false;
+        }
+
+        let sign = latest.static_value.signum();
+        let keeps_advantage = window.iter().all(|observation| sign * observation.static_value >= self.min_advantage);
+        let score_change = sign * (latest.search_score - window[0].search_score);
+        keeps_advantage && score_change <= self.max_score_change
+    }
+
+    /// Pulls `score` towards `draw_score` by `draw_blend` if a fortress is suspected.
+    pub fn adjust_score(&self, score: f64, draw_score: f64) -> f64 {
+        if self.is_fortress_suspected() {
+            score + self.draw_blend * (draw_score - score)
+        } else {
+            score
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dunck_core::r#move::Move;
+
+    const WRONG_BISHOP_FEN: &str = "7k/8/5K2/7P/8/8/8/B7 w - - 30 60";
+
+    /// Moves the bishop along the first two ranks and shuffles the black king back and forth, without repeating a position.
+    const SHUFFLING_MOVES: [&str; 10] = ["a1b2", "h8g8", "b2c1", "g8h8", "c1d2", "h8g8", "d2e1", "g8h8", "e1f2", "h8g8"];
+
+    /// Plays `SHUFFLING_MOVES`, observing each position with the given evaluations for white.
+    fn observe_shuffling(detector: &mut FortressDetector, state: &mut State, evaluations: &[(f64, f64)]) {
+        for (uci, (static_value, search_score)) in SHUFFLING_MOVES.iter().zip(evaluations) {
+            let sign = if state.side_to_move == Color::White { 1. } else { -1. };
+            detector.observe(state, sign * static_value, sign * search_score);
+            let mv = Move::from_uci(state, uci).unwrap();
+            state.make_move(mv);
+        }
+    }
+
+    #[test]
+    fn test_detects_stuck_advantage() {
+        // a rook pawn with the wrong bishop, which is a draw despite white's extra material
+        let mut state = State::from_fen(WRONG_BISHOP_FEN).unwrap();
+        let mut detector = FortressDetector::default();
+        observe_shuffling(&mut detector, &mut state, &[(0.8, 0.6); 10]);
+        assert!(detector.is_fortress_suspected());
+        assert_eq!(detector.adjust_score(0.6, 0.), 0.6);
+        assert!((detector.clone().with_draw_blend(0.5).adjust_score(0.6, 0.) - 0.3).abs() < 1e-9);
+
+        detector.reset();
+        assert!(!detector.is_fortress_suspected());
+    }
+
+    #[test]
+    fn test_progress_is_not_a_fortress() {
+        let start = State::from_fen(WRONG_BISHOP_FEN).unwrap();
+
+        // the search score keeps improving
+        let improving: Vec<(f64, f64)> = (0..10).map(|i| (0.8, 0.3 + 0.05 * i as f64)).collect();
+        let mut detector = FortressDetector::default();
+        observe_shuffling(&mut detector, &mut start.clone(), &improving);
+        assert!(!detector.is_fortress_suspected());
+
+        // the advantage is too small
+        let mut detector = FortressDetector::default();
+        observe_shuffling(&mut detector, &mut start.clone(), &[(0.1, 0.1); 10]);
+        assert!(!detector.is_fortress_suspected());
+
+        // too few plies without a capture or pawn move
+        let mut detector = FortressDetector { min_plies: 40, ..Default::default() };
+        observe_shuffling(&mut detector, &mut start.clone(), &[(0.8, 0.6); 10]);
+        assert!(!detector.is_fortress_suspected());
+    }
+
+    #[test]
+    fn test_observing_a_ply_again_replaces_it() {
+        let mut state = State::from_fen(WRONG_BISHOP_FEN).unwrap();
+        let mut detector = FortressDetector::default();
+        observe_shuffling(&mut detector, &mut state, &[(0.8, 0.6); 10]);
+        detector.observe(&state, 0.8, 0.6);
+        detector.observe(&state, 0.8, 0.9);
+        assert_eq!(detector.observations.len(), 11);
+        assert!(!detector.is_fortress_suspected());
+    }
+}
@@ -0,0 +1,459 @@
+//! A game in progress: the position, the clocks and the result, kept as a log of events that observers are told about.
+//! Everything that happens in a game goes through `Game::apply`, so a game can be rebuilt from its events
+//! and every frontend (UCI, referee, arena, recorder) sees the same bookkeeping.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+use crate::evaluation::get_value_at_terminal_state;
+use crate::time_manager::Clock;
+use dunck_core::game_record::GameRecord;
+use dunck_core::r#move::Move;
+use dunck_core::state::{State, Termination};
+use crate::time_control::TimeControl;
+use dunck_core::utils::Color;
+
+/// Why a game ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GameOverReason {
+    /// The game was played out to the end.
+    Termination(Termination),
+    Resignation(Color),
+    /// `Color` ran out of time.
+    FlagFell(Color),
+    DrawAgreed,
+}
+
+impl Display for GameOverReason {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GameOverReason::Termination(termination) => match termination {
+                Termination::Checkmate => write!(f, "Checkmate"),
+                Termination::Stalemate => write!(f, "Stalemate"),
+                Termination::InsufficientMaterial => write!(f, "Insufficient material"),
+                Termination::ThreefoldRepetition => write!(f, "Threefold repetition"),
+                Termination::FiftyMoveRule => write!(f, "Fifty-move rule"),
+                Termination::FivefoldRepetition => write!(f, "Fivefold repetition"),
+                Termination::SeventyFiveMoveRule => write!(f, "Seventy-five-move rule"),
+            },
+            GameOverReason::Resignation(color) => write!(f, "{} resigned", color),
+            GameOverReason::FlagFell(color) => write!(f, "{} ran out of time", color),
+            GameOverReason::DrawAgreed => write!(f, "Draw agreed"),
+        }
+    }
+}
+
+/// How a game ended, with `result` from white's point of view: 1, 0, or -1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GameResult {
+    pub result: i8,
+    pub reason: GameOverReason,
+}
+
+/// Something that happened in a game.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEvent {
+    /// `color` played `mv`, written as `san`, after thinking for `elapsed`.
+    MoveMade { color: Color, mv: Move, san: String, elapsed: Duration },
+    /// `Color` offered a draw, which stands until accepted or until the opponent moves.
+    DrawOffered(Color),
+    /// `Color` accepted the opponent's draw offer.
+    DrawAccepted(Color),
+    Resigned(Color),
+    /// `Color` ran out of time.
+    FlagFell(Color),
+    /// The game ended. Follows the event that ended it, and is not applied itself.
+    GameOver(GameResult),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameError {
+    /// The game has already ended.
+    GameOver,
+    /// It is the other side's turn.
+    NotYourTurn(Color),
+    /// The move, in UCI notation, is not legal.
+    IllegalMove(String),
+    /// A draw was accepted without the opponent having offered one.
+    NoDrawOffer,
+}
+
+impl Display for GameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GameError::GameOver => write!(f, "The game is over"),
+            GameError::NotYourTurn(color) => write!(f, "It is not {}'s turn", color),
+            GameError::IllegalMove(mv) => write!(f, "Illegal move: {}", mv),
+            GameError::NoDrawOffer => write!(f, "No draw was offered"),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
+/// Receives the events of a game as they are applied.
+pub trait GameObserver {
+    fn on_event(&self, event: &GameEvent);
+}
+
+impl<F: Fn(&GameEvent)> GameObserver for F {
+    fn on_event(&self, event: &GameEvent) {
+        self(event)
+    }
+}
+
+pub struct Game {
+    initial_state: State,
+    state: State,
+    moves: Vec<Move>,
+    /// The time control of each side, indexed by `Color`, if the game is timed.
+    time_controls: Option<[TimeControl; 2]>,
+    /// The remaining time of each side, indexed by `Color`, if the game is timed.
+    clocks: [Duration; 2],
+    /// The number of moves each side has made, indexed by `Color`, which decides the stage of its time control.
+    num_moves: [u32; 2],
+    draw_offer: Option<Color>,
+    result: Option<GameResult>,
+    events: Vec<GameEvent>,
+    observers: Vec<Box<dyn GameObserver>>,
+}
+
+impl Game {
+    /// Starts an untimed game from `initial_state`, which may already be finished, e.g. if it is checkmate.
+    pub fn new(initial_state: State) -> Game {
+        let mut state = initial_state.clone();
+        state.check_and_update_termination();
+        let mut game = Game {
+            state,
+            initial_state,
+            moves: Vec::new(),
+            time_controls: None,
+            clocks: [Duration::ZERO; 2],
+            num_moves: [0; 2],
+            draw_offer: None,
+            result: None,
+            events: Vec::new(),
+            observers: Vec::new(),
+        };
+        if let Some(termination) = game.state.termination {
+            let reason = GameOverReason::Termination(termination);
+            game.result = Some(GameResult { result: game.calc_result(reason), reason });
+        }
+        game
+    }
+
+    /// Times the game with `time_control` for both sides. Must be called before any event is applied.
+    pub fn with_time_control(self, time_control: TimeControl) -> Self {
+        self.with_time_controls([time_control.clone(), time_control])
+    }
+
+    /// Times the game with a separate time control for each side, indexed by `Color`, e.g. for time odds.
+    /// Must be called before any event is applied.
+    pub fn with_time_controls(mut self, time_controls: [TimeControl; 2]) -> Self {
+        self.clocks = [time_controls[0].get_initial_time(), time_controls[1].get_initial_time()];
+        self.time_controls = Some(time_controls);
+        self
+    }
+
+    /// Tells `observer` about every event applied from now on.
+    pub fn add_observer(&mut self, observer: Box<dyn GameObserver>) {
+        self.observers.push(observer);
+    }
+
+    /// Rebuilds a game by applying `events` from `initial_state`, skipping `GameOver` events since they follow
+    /// from the others.
+    pub fn from_events(initial_state: State, time_controls: Option<[TimeControl; 2]>, events: &[GameEvent]) -> Result<Game, GameError> {
+        let mut game = Game::new(initial_state);
+        if let Some(time_controls) = time_controls {
+            game = game.with_time_controls(time_controls);
+        }
+        for event in events {
+            if !matches!(event, GameEvent::GameOver(_)) {
+                game.apply(event.clone())?;
+            }
+        }
+        Ok(game)
+    }
+
+    pub fn get_state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn get_initial_state(&self) -> &State {
+        &self.initial_state
+    }
+
+    pub fn get_moves(&self) -> &[Move] {
+        &self.moves
+    }
+
+    pub fn get_events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    pub fn get_result(&self) -> Option<GameResult> {
+        self.result
+    }
+
+    /// Returns the time control of `color`, or None if the game is untimed.
+    pub fn get_time_control(&self, color: Color) -> Option<&TimeControl> {
+        self.time_controls.as_ref().map(|time_controls| &time_controls[color as usize])
+    }
+
+    /// Returns the remaining time of `color`, or None if the game is untimed.
+    pub fn get_remaining_time(&self, color: Color) -> Option<Duration> {
+        self.time_controls.as_ref().map(|_| self.clocks[color as usize])
+    }
+
+    /// Returns the clock of `color` before its next move, e.g. for a `TimeManager`, or None if the game is untimed.
+    pub fn get_clock(&self, color: Color) -> Option<Clock> {
+        self.get_time_control(color)
+            .map(|time_control| time_control.get_clock(self.clocks[color as usize], self.num_moves[color as usize]))
+    }
+
+    pub fn get_draw_offer(&self) -> Option<Color> {
+        self.draw_offer
+    }
+
+    /// Plays `mv` for the side to move, without using any time.
+    pub fn make_move(&mut self, mv: Move) -> Result<(), GameError> {
+        self.make_timed_move(mv, Duration::ZERO)
+    }
+
+    /// Plays `mv` for the side to move after thinking for `elapsed`.
+    /// In a timed game, a move that took longer than the remaining time is not played, and the flag falls instead.
+    pub fn make_timed_move(&mut self, mv: Move, elapsed: Duration) -> Result<(), GameError> {
+        let color = self.state.side_to_move;
+        if self.result.is_none() && self.time_controls.is_some() && elapsed > self.clocks[color as usize] {
+            return self.apply(GameEvent::FlagFell(color));
+        }
+        self.apply(GameEvent::MoveMade { color, mv, san: String::new(), elapsed })
+    }
+
+    pub fn offer_draw(&mut self, color: Color) -> Result<(), GameError> {
+        self.apply(GameEvent::DrawOffered(color))
+    }
+
+    pub fn accept_draw(&mut self, color: Color) -> Result<(), GameError> {
+        self.apply(GameEvent::DrawAccepted(color))
+    }
+
+    pub fn resign(&mut self, color: Color) -> Result<(), GameError> {
+        self.apply(GameEvent::Resigned(color))
+    }
+
+    /// Records that `color` ran out of time, e.g. as decided by an external clock.
+    pub fn flag(&mut self, color: Color) -> Result<(), GameError> {
+        self.apply(GameEvent::FlagFell(color))
+    }
+
+    /// Validates `event`, updates the game with it, records it and tells the observers,
+    /// followed by a `GameOver` event if it ended the game. The SAN of moves is filled in here.
+    pub fn apply(&mut self, mut event: GameEvent) -> Result<(), GameError> {
+        if self.result.is_some() {
+            return Err(GameError::GameOver);
+        }
+
+        let reason = match &mut event {
+            GameEvent::MoveMade { color, mv, san, elapsed } => {
+                if *color != self.state.side_to_move {
+                    return Err(GameError::NotYourTurn(*color));
+                }
+                let legal_moves = self.state.calc_legal_moves();
+                if !legal_moves.contains(mv) {
+                    return Err(GameError::IllegalMove(mv.to_uci()));
+                }
+
+                let mut next_state = self.state.clone();
+                next_state.make_move(*mv);
+                // draws are claimed on the players' behalf, as arbiters of engine games do
+                next_state.claim_any_draw();
+                next_state.check_and_update_termination();
+                *san = mv.to_san(&self.state, &next_state, &legal_moves);
+                self.state = next_state;
+                self.moves.push(*mv);
+
+                if let Some(time_controls) = &self.time_controls {
+                    let time_added = time_controls[*color as usize].get_time_added(self.num_moves[*color as usize]);
+                    let clock = &mut self.clocks[*color as usize];
+                    *clock = clock.saturating_sub(*elapsed) + time_added;
+                }
+                self.num_moves[*color as usize] += 1;
+                // moving declines the opponent's offer
+                if self.draw_offer == Some(color.flip()) {
+                    self.draw_offer = None;
+                }
+                self.state.termination.map(GameOverReason::Termination)
+            },
+            GameEvent::DrawOffered(color) => {
+                self.draw_offer = Some(*color);
+                None
+            },
+            GameEvent::DrawAccepted(color) => {
+                if self.draw_offer != Some(color.flip()) {
+                    return Err(GameError::NoDrawOffer);
+                }
+                Some(GameOverReason::DrawAgreed)
+            },
+            GameEvent::Resigned(color) => Some(GameOverReason::Resignation(*color)),
+            GameEvent::FlagFell(color) => Some(GameOverReason::FlagFell(*color)),
+            GameEvent::GameOver(_) => return Err(GameError::GameOver)
+        };
+
+        self.record_event(event);
+        if let Some(reason) = reason {
+            let result = GameResult { result: self.calc_result(reason), reason };
+            self.result = Some(result);
+            self.record_event(GameEvent::GameOver(result));
+        }
+        Ok(())
+    }
+
+    fn record_event(&mut self, event: GameEvent) {
+        for observer in &self.observers {
+            observer.on_event(&event);
+        }
+        self.events.push(event);
+    }
+
+    /// Returns the result, from white's point of view, of the game ending for `reason` in the current position.
+    fn calc_result(&self, reason: GameOverReason) -> i8 {
+        let loss_for = |color: Color| if color == Color::White { -1 } else { 1 };
+        match reason {
+            GameOverReason::Termination(_) => get_value_at_terminal_state(&self.state, Color::White) as i8,
+            GameOverReason::Resignation(color) => loss_for(color),
+            // running out of time against a lone king is a draw, since it could never be checkmated
+            GameOverReason::FlagFell(color) if self.state.board.count_color(color.flip()) == 1 => 0,
+            GameOverReason::FlagFell(color) => loss_for(color),
+            GameOverReason::DrawAgreed => 0,
+        }
+    }
+
+    /// Returns the game as a record, with a draw as the result if it has not ended.
+    pub fn to_record(&self) -> GameRecord {
+        GameRecord {
+            initial_fen: self.initial_state.to_fen(),
+            moves: self.moves.clone(),
+            result: self.result.map_or(0, |result| result.result),
+            metadata: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+    use super::*;
+
+    fn find_move(game: &Game, uci: &str) -> Move {
+        Move::from_uci(game.get_state(), uci).unwrap()
+    }
+
+    fn play(game: &mut Game, ucis: &[&str]) {
+        for uci in ucis {
+            let mv = find_move(game, uci);
+            game.make_move(mv).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_checkmate_notifies_observers() {
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let mut game = Game::new(State::initial());
+        let observed = events.clone();
+        game.add_observer(Box::new(move |event: &GameEvent| observed.borrow_mut().push(event.clone())));
+
+        play(&mut game, &["f2f3", "e7e5", "g2g4", "d8h4"]);
+        let result = GameResult { result: -1, reason: GameOverReason::Termination(Termination::Checkmate) };
+        assert_eq!(game.get_result(), Some(result));
+        assert_eq!(*events.borrow(), game.get_events());
+        assert_eq!(game.get_events().len(), 5);
+        assert!(matches!(&game.get_events()[3], GameEvent::MoveMade { color: Color::Black, san, .. } if san == "Qh4#"));
+        assert_eq!(game.get_events()[4], GameEvent::GameOver(result));
+
+        let mv = game.get_state().calc_legal_moves().first().copied();
+        assert_eq!(mv, None);
+        assert_eq!(game.resign(Color::White), Err(GameError::GameOver));
+        assert_eq!(game.to_record().result, -1);
+        assert_eq!(game.to_record().moves, game.get_moves());
+
+        let game = Game::new(game.get_state().clone());
+        assert_eq!(game.get_result(), Some(result));
+        assert!(game.get_events().is_empty());
+    }
+
+    #[test]
+    fn test_draw_offers_and_resignation() {
+        let mut game = Game::new(State::initial());
+        assert_eq!(game.accept_draw(Color::Black), Err(GameError::NoDrawOffer));
+        game.offer_draw(Color::White).unwrap();
+        play(&mut game, &["e2e4"]);
+        assert_eq!(game.get_draw_offer(), Some(Color::White));
+
+        // black moving declines the offer
+        play(&mut game, &["e7e5"]);
+        assert_eq!(game.get_draw_offer(), None);
+        assert_eq!(game.accept_draw(Color::Black), Err(GameError::NoDrawOffer));
+
+        game.offer_draw(Color::White).unwrap();
+        game.accept_draw(Color::Black).unwrap();
+        assert_eq!(game.get_result(), Some(GameResult { result: 0, reason: GameOverReason::DrawAgreed }));
+
+        let mut game = Game::new(State::initial());
+        let mut other_game = Game::new(State::initial());
+        play(&mut other_game, &["e2e4"]);
+        let mv = find_move(&other_game, "e7e5");
+        assert_eq!(game.apply(GameEvent::MoveMade { color: Color::Black, mv, san: String::new(), elapsed: Duration::ZERO }), Err(GameError::NotYourTurn(Color::Black)));
+        assert_eq!(game.make_move(mv), Err(GameError::IllegalMove("e7e5".to_string())));
+        game.resign(Color::White).unwrap();
+        assert_eq!(game.get_result(), Some(GameResult { result: -1, reason: GameOverReason::Resignation(Color::White) }));
+    }
+
+    #[test]
+    fn test_clocks() {
+        let time_control = TimeControl::new(Duration::from_secs(10), Duration::from_secs(1));
+        let mut game = Game::new(State::initial()).with_time_control(time_control.clone());
+        let mv = find_move(&game, "e2e4");
+        game.make_timed_move(mv, Duration::from_secs(3)).unwrap();
+        assert_eq!(game.get_remaining_time(Color::White), Some(Duration::from_secs(8)));
+        assert_eq!(game.get_remaining_time(Color::Black), Some(Duration::from_secs(10)));
+
+        let mv = find_move(&game, "e7e5");
+        game.make_timed_move(mv, Duration::from_secs(11)).unwrap();
+        assert_eq!(game.get_moves().len(), 1);
+        assert_eq!(game.get_result(), Some(GameResult { result: 1, reason: GameOverReason::FlagFell(Color::Black) }));
+        assert_eq!(Game::new(State::initial()).get_remaining_time(Color::White), None);
+
+        // running out of time against a lone king is a draw
+        let mut game = Game::new(State::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap()).with_time_control(time_control);
+        game.flag(Color::White).unwrap();
+        assert_eq!(game.get_result().unwrap().result, 0);
+
+        // the next stage's base time is added once a side completes the moves of the current one
+        let mut game = Game::new(State::initial()).with_time_control(TimeControl::from_pgn_tag("1/10:5").unwrap());
+        assert_eq!(game.get_clock(Color::White).unwrap().moves_to_go, Some(1));
+        play(&mut game, &["e2e4"]);
+        assert_eq!(game.get_remaining_time(Color::White), Some(Duration::from_secs(15)));
+        assert_eq!(game.get_clock(Color::White).unwrap().moves_to_go, None);
+        assert_eq!(game.get_clock(Color::Black).unwrap().moves_to_go, Some(1));
+    }
+
+    #[test]
+    fn test_from_events() {
+        let time_control = TimeControl::new(Duration::from_secs(60), Duration::ZERO);
+        let mut game = Game::new(State::initial()).with_time_control(time_control.clone());
+        for (uci, seconds) in [("d2d4", 2), ("d7d5", 5), ("c2c4", 1)] {
+            let mv = find_move(&game, uci);
+            game.make_timed_move(mv, Duration::from_secs(seconds)).unwrap();
+        }
+        game.offer_draw(Color::Black).unwrap();
+        game.resign(Color::Black).unwrap();
+
+        let replayed = Game::from_events(State::initial(), Some([time_control.clone(), time_control]), game.get_events()).unwrap();
+        assert_eq!(replayed.get_events(), game.get_events());
+        assert_eq!(replayed.get_state(), game.get_state());
+        assert_eq!(replayed.get_remaining_time(Color::Black), Some(Duration::from_secs(55)));
+        assert_eq!(replayed.get_result(), game.get_result());
+    }
+}
@@ -0,0 +1,623 @@
+//! Gating of newly trained checkpoints: a candidate must beat the current best network in an arena match
+//! by a configurable margin before it replaces the best checkpoint used for self-play.
+//! Every gating match is appended to a league history file, one tab-separated line per match.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs;
+use std::fs::OpenOptions;
+use std::io;
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use crate::arena_events::{ArenaEvent, ArenaMoveEvent, ArenaObserver, NoArenaObserver};
+use crate::evaluation::{get_value_at_terminal_state, Evaluator};
+use crate::evaluators::neural::net_config::NetConfig;
+use crate::mcts::mcts::MCTS;
+use crate::mcts::mcts_node::MCTSNode;
+use crate::tablebase::{TablebaseProber, Wdl};
+use crate::time_manager::TimeManager;
+use dunck_core::game_record::{GameRecord, MoveMetadata};
+use crate::handicap::Handicap;
+use dunck_core::state::State;
+use crate::time_control::TimeControl;
+use dunck_core::utils::Color;
+
+/// Settings for the games played in an arena match.
+pub struct ArenaConfig {
+    pub num_games: usize,
+    pub iterations_per_move: usize,
+    pub max_game_depth: usize,
+    pub exploration_param: f64,
+    pub calc_node_score: &'static dyn Fn(&MCTSNode, u32, f64) -> f64,
+    /// If set, games are adjudicated as soon as they reach a position the tablebase covers.
+    pub tablebase: Option<&'static dyn TablebaseProber>,
+    /// If set, `handicap.giver` searches only its share of `iterations_per_move`. `run_arena` also starts every game
+    /// from the handicapped position, with the candidate giving the odds whichever color it plays.
+    pub handicap: Option<Handicap>,
+    /// If set, both sides play on simulated clocks, with the time odds of `handicap` if any, and every move is
+    /// searched for the time the default `TimeManager` allocates instead of `iterations_per_move` iterations.
+    /// A side whose search overruns its clock loses on time.
+    pub time_control: Option<TimeControl>,
+}
+
+/// Why an arena game was ended before being played out.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Adjudication {
+    /// The tablebase covered the position, with `wdl` and `dtz` from `side_to_move`'s point of view.
+    /// Cursed wins and blessed losses are adjudicated as draws.
+    Tablebase { side_to_move: Color, wdl: Wdl, dtz: Option<i32> },
+    /// `color` ran out of time on its simulated clock. This loses, unless the opponent only has its king left.
+    FlagFell { color: Color, opponent_has_lone_king: bool },
+}
+
+impl Adjudication {
+    /// Returns the adjudicated result from white's point of view: 1, 0, or -1.
+    pub fn get_result(&self) -> i8 {
+        match self {
+            Adjudication::Tablebase { side_to_move, wdl, .. } => {
+                let result = match wdl {
+                    Wdl::Win => 1,
+                    Wdl::Loss => -1,
+                    Wdl::CursedWin | Wdl::Draw | Wdl::BlessedLoss => 0
+                };
+                if *side_to_move == Color::White { result } else { -result }
+            },
+            Adjudication::FlagFell { opponent_has_lone_king: true, .. } => 0,
+            Adjudication::FlagFell { color, .. } => if *color == Color::White { -1 } else { 1 },
+        }
+    }
+}
+
+impl Display for Adjudication {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let outcome = match self.get_result() {
+            1 => "white wins",
+            -1 => "black wins",
+            _ => "draw"
+        };
+        match self {
+            Adjudication::Tablebase { side_to_move, wdl, dtz } => {
+                write!(f, "Tablebase adjudication: {} ({:?} for {}", outcome, wdl, side_to_move)?;
+                if let Some(dtz) = dtz {
+                    write!(f, ", DTZ {}", dtz)?;
+                }
+                write!(f, ")")
+            },
+            Adjudication::FlagFell { color, opponent_has_lone_king } => {
+                write!(f, "Time forfeit: {} ({} ran out of time", outcome, color)?;
+                if *opponent_has_lone_king {
+                    write!(f, " against a lone king")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// A finished arena game, along with why it was ended early, if it was.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArenaGame {
+    pub record: GameRecord,
+    pub adjudication: Option<Adjudication>,
+}
+
+impl ArenaGame {
+    /// Renders the game as PGN, with the adjudication reason, if any, as the result comment.
+    pub fn to_pgn(&self) -> Result<String, String> {
+        let comment = self.adjudication.map(|adjudication| adjudication.to_string());
+        self.record.to_pgn(comment.as_deref())
+    }
+}
+
+/// The outcome of an arena match, from the candidate's point of view.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct ArenaResult {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32,
+}
+
+impl ArenaResult {
+    pub fn get_num_games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Returns the fraction of points scored, counting draws as half a point.
+    pub fn get_score(&self) -> f64 {
+        if self.get_num_games() == 0 {
+            return 0.;
+        }
+        (self.wins as f64 + 0.5 * self.draws as f64) / self.get_num_games() as f64
+    }
+}
+
+/// Plays a game from `initial_state`, searching each move from scratch with the evaluator of the side to move,
+/// and returns its record, with the search statistics of every move as metadata.
+/// Games that reach `max_game_depth` halfmoves are recorded as draws.
+/// If `config.tablebase` is set, games reaching a covered position are adjudicated instead.
+pub fn play_arena_game(initial_state: State, white: &dyn Evaluator, black: &dyn Evaluator, config: &ArenaConfig) -> GameRecord {
+    play_observed_arena_game(initial_state, white, black, config, 0, &NoArenaObserver).record
+}
+
+/// Like `play_arena_game`, but reports the start, every move, and the end of the game to `observer`,
+/// tagged with `game_index`, and returns the adjudication along with the record.
+pub fn play_observed_arena_game(
+    initial_state: State,
+    white: &dyn Evaluator,
+    black: &dyn Evaluator,
+    config: &ArenaConfig,
+    game_index: usize,
+    observer: &dyn ArenaObserver
+) -> ArenaGame {
+    let mut record = GameRecord {
+        initial_fen: initial_state.to_fen(),
+        moves: Vec::new(),
+        result: 0,
+        metadata: Some(Vec::new()),
+    };
+    observer.on_event(ArenaEvent::GameStarted { game_index, initial_fen: record.initial_fen.clone() });
+    let finish = |record: GameRecord, adjudication: Option<Adjudication>| {
+        observer.on_event(ArenaEvent::GameFinished { game_index, result: record.result, num_moves: record.moves.len(), adjudication });
+        ArenaGame { record, adjudication }
+    };
+
+    let mut clocks = [Duration::ZERO; 2];
+    let time_controls = config.time_control.as_ref().map(|time_control| match &config.handicap {
+        Some(handicap) => handicap.get_time_controls(time_control),
+        None => [time_control.clone(), time_control.clone()]
+    });
+    let mut remaining = time_controls.as_ref().map_or([Duration::ZERO; 2], |time_controls| time_controls.each_ref().map(TimeControl::get_initial_time));
+    let mut num_moves = [0; 2];
+    let mut state = initial_state;
+    for _ in 0..config.max_game_depth {
+        if state.termination.is_none() && state.calc_legal_moves().is_empty() {
+            state.assume_and_update_termination();
+        }
+        if state.termination.is_some() {
+            record.result = get_value_at_terminal_state(&state, Color::White) as i8;
+            return finish(record, None);
+        }
+        if let Some(adjudication) = config.tablebase.and_then(|tablebase| adjudicate_by_tablebase(&state, tablebase)) {
+            record.result = adjudication.get_result();
+            return finish(record, Some(adjudication));
+        }
+
+        let evaluator = if state.side_to_move == Color::White { white } else { black };
+        let side = state.side_to_move as usize;
+        let start_time = Instant::now();
+        let mut mcts = MCTS::new(state.clone(), config.exploration_param, evaluator, config.calc_node_score, false);
+        match &time_controls {
+            Some(time_controls) => {
+                let clock = time_controls[side].get_clock(remaining[side], num_moves[side]);
+                mcts.run_with_budget(&TimeManager::default().calc_budget(evaluator, &state, &clock));
+            },
+            None => {
+                let iterations = config.handicap.as_ref()
+                    .map_or(config.iterations_per_move, |handicap| handicap.get_iterations_per_move(state.side_to_move, config.iterations_per_move));
+                mcts.run(iterations);
+            }
+        }
+        let (mv, visits, value) = match mcts.get_best_child_by_visits() {
+            Some(child) => {
+                let child = child.borrow();
+                (child.mv.unwrap(), child.visits, child.value)
+            },
+            None => return finish(record, None)
+        };
+        let move_time = start_time.elapsed();
+        let (nodes, depth) = {
+            let root = mcts.root.borrow();
+            (root.visits, root.get_principal_variation().len())
+        };
+        clocks[side] += move_time;
+        if let Some(time_controls) = &time_controls {
+            if move_time > remaining[side] {
+                let opponent_has_lone_king = state.board.count_color(state.side_to_move.flip()) == 1;
+                let adjudication = Adjudication::FlagFell { color: state.side_to_move, opponent_has_lone_king };
+                record.result = adjudication.get_result();
+                return finish(record, Some(adjudication));
+            }
+            remaining[side] = remaining[side] - move_time + time_controls[side].get_time_added(num_moves[side]);
+            num_moves[side] += 1;
+        }
+
+        let legal_moves = state.calc_legal_moves();
+        let mut next_state = state.clone();
+        next_state.make_move(mv);
+        next_state.claim_any_draw();
+        // the child's value is from the perspective of the side that played the move
+        let eval = (visits > 0).then(|| {
+            let q = value / visits as f64;
+            if state.side_to_move == Color::White { q } else { -q }
+        });
+        observer.on_event(ArenaEvent::MovePlayed(ArenaMoveEvent {
+            game_index,
+            ply: record.moves.len() + 1,
+            mv,
+            san: mv.to_san(&state, &next_state, &legal_moves),
+            fen: next_state.to_fen(),
+            eval,
+            visits,
+            move_time,
+            clocks,
+        }));
+
+        state = next_state;
+        record.moves.push(mv);
+        if let Some(metadata) = &mut record.metadata {
+            metadata.push(MoveMetadata {
+                value: if visits > 0 { (value / visits as f64) as f32 } else { 0. },
+                visits,
+                nodes,
+                depth: depth.min(u16::MAX as usize) as u16,
+                elapsed_ms: move_time.as_millis().min(u32::MAX as u128) as u32,
+            });
+        }
+    }
+    finish(record, None)
+}
+
+/// Returns the tablebase adjudication of `state`, or None if the tablebase does not cover it.
+pub fn adjudicate_by_tablebase(state: &State, tablebase: &dyn TablebaseProber) -> Option<Adjudication> {
+    let wdl = tablebase.probe_wdl(state)?;
+    Some(Adjudication::Tablebase { side_to_move: state.side_to_move, wdl, dtz: tablebase.probe_dtz(state) })
+}
+
+/// Plays `config.num_games` games between `candidate` and `best`, alternating colors, starting with the candidate as white.
+pub fn run_arena(candidate: &dyn Evaluator, best: &dyn Evaluator, config: &ArenaConfig) -> ArenaResult {
+    run_observed_arena(candidate, best, config, &NoArenaObserver)
+}
+
+/// Like `run_arena`, but reports the events of every game to `observer`.
+/// The candidate plays white in the games with an even index.
+/// Panics if `config.handicap` cannot be applied to the initial position, e.g. because its opening is illegal.
+pub fn run_observed_arena(candidate: &dyn Evaluator, best: &dyn Evaluator, config: &ArenaConfig, observer: &dyn ArenaObserver) -> ArenaResult {
+    let mut result = ArenaResult::default();
+    for game_index in 0..config.num_games {
+        let candidate_is_white = game_index % 2 == 0;
+        let candidate_color = if candidate_is_white { Color::White } else { Color::Black };
+        let handicap = config.handicap.clone().map(|handicap| handicap.with_giver(candidate_color));
+        let initial_state = match &handicap {
+            Some(handicap) => handicap.apply(&State::initial()).unwrap_or_else(|e| panic!("Invalid arena handicap: {}", e)),
+            None => State::initial()
+        };
+        let game_config = ArenaConfig { handicap, time_control: config.time_control.clone(), ..*config };
+        let record = if candidate_is_white {
+            play_observed_arena_game(initial_state, candidate, best, &game_config, game_index, observer).record
+        } else {
+            play_observed_arena_game(initial_state, best, candidate, &game_config, game_index, observer).record
+        };
+        let candidate_result = if candidate_is_white { record.result } else { -record.result };
+
+        if candidate_result > 0 {
+            result.wins += 1;
+        } else if candidate_result < 0 {
+            result.losses += 1;
+        } else {
+            result.draws += 1;
+        }
+    }
+    result
+}
+
+/// Settings for promoting checkpoints.
+pub struct GatingConfig {
+    pub arena: ArenaConfig,
+    /// The minimum score, as a fraction of points, the candidate needs to be promoted.
+    pub promotion_threshold: f64,
+    pub best_model_path: String,
+    pub history_path: String,
+}
+
+/// A single gating match in the league history.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LeagueEntry {
+    pub timestamp: u64,
+    pub candidate_path: String,
+    pub result: ArenaResult,
+    pub promoted: bool,
+}
+
+impl LeagueEntry {
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            self.timestamp, self.candidate_path, self.result.wins, self.result.draws, self.result.losses, self.promoted
+        )
+    }
+
+    pub fn from_line(line: &str) -> Result<LeagueEntry, String> {
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 6 {
+            return Err(format!("Expected 6 fields, found {}: {}", fields.len(), line));
+        }
+        let parse_count = |field: &str| field.parse::<u32>().map_err(|e| format!("Invalid count {}: {}", field, e));
+        Ok(LeagueEntry {
+            timestamp: fields[0].parse().map_err(|e| format!("Invalid timestamp {}: {}", fields[0], e))?,
+            candidate_path: fields[1].to_string(),
+            result: ArenaResult {
+                wins: parse_count(fields[2])?,
+                draws: parse_count(fields[3])?,
+                losses: parse_count(fields[4])?,
+            },
+            promoted: fields[5].parse().map_err(|e| format!("Invalid promotion flag {}: {}", fields[5], e))?,
+        })
+    }
+}
+
+/// Pits the candidate checkpoint at `candidate_path`, loaded as `candidate`, against the current `best`.
+/// If the candidate scores at least `config.promotion_threshold`, its checkpoint (and config, if any)
+/// is copied over `config.best_model_path`. Either way, the match is appended to the league history.
+pub fn run_gating(candidate_path: &str, candidate: &dyn Evaluator, best: &dyn Evaluator, config: &GatingConfig) -> io::Result<LeagueEntry> {
+    let result = run_arena(candidate, best, &config.arena);
+    let promoted = result.get_score() >= config.promotion_threshold;
+
+    if promoted {
+        fs::copy(candidate_path, &config.best_model_path)?;
+        let candidate_config_path = NetConfig::get_config_path(candidate_path);
+        if Path::new(&candidate_config_path).exists() {
+            fs::copy(candidate_config_path, NetConfig::get_config_path(&config.best_model_path))?;
+        }
+    }
+
+    let entry = LeagueEntry {
+        timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|duration| duration.as_secs()).unwrap_or(0),
+        candidate_path: candidate_path.to_string(),
+        result,
+        promoted,
+    };
+    let mut history_file = OpenOptions::new().create(true).append(true).open(&config.history_path)?;
+    writeln!(history_file, "{}", entry.to_line())?;
+
+    Ok(entry)
+}
+
+/// Reads every match recorded in the league history at `path`, oldest first.
+/// A missing file is treated as an empty history.
+pub fn read_league_history(path: &str) -> io::Result<Vec<LeagueEntry>> {
+    if !Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+    fs::read_to_string(path)?
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| LeagueEntry::from_line(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use super::*;
+    use crate::arena_events::ChannelArenaObserver;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::mcts::mcts::calc_uct_score;
+    use crate::handicap::MaterialOdds;
+    use dunck_core::utils::PieceType;
+
+    fn make_arena_config(num_games: usize) -> ArenaConfig {
+        ArenaConfig {
+            num_games,
+            iterations_per_move: 20,
+            max_game_depth: 10,
+            exploration_param: 1.5,
+            calc_node_score: &calc_uct_score,
+            tablebase: None,
+            handicap: None,
+            time_control: None,
+        }
+    }
+
+    #[test]
+    fn test_arena_result_score() {
+        let result = ArenaResult { wins: 5, draws: 2, losses: 3 };
+        assert_eq!(result.get_num_games(), 10);
+        assert_eq!(result.get_score(), 0.6);
+        assert_eq!(ArenaResult::default().get_score(), 0.);
+    }
+
+    #[test]
+    fn test_play_arena_game_finds_mate() {
+        let evaluator = MaterialEvaluator {};
+        let config = ArenaConfig { iterations_per_move: 200, ..make_arena_config(1) };
+        let state = State::from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        let record = play_arena_game(state, &evaluator, &evaluator, &config);
+        assert_eq!(record.result, 1);
+        assert_eq!(record.moves.len(), 1);
+        assert!(record.replay().unwrap().calc_legal_moves().is_empty());
+    }
+
+    #[test]
+    fn test_move_metadata() {
+        let evaluator = MaterialEvaluator {};
+        let record = play_arena_game(State::initial(), &evaluator, &evaluator, &make_arena_config(1));
+        let metadata = record.metadata.as_ref().unwrap();
+        assert_eq!(metadata.len(), 10);
+        for entry in metadata {
+            assert_eq!(entry.nodes, 20);
+            assert!(entry.visits > 0 && entry.visits < entry.nodes);
+            assert!(entry.depth >= 1);
+            assert!(entry.value.abs() <= 1.);
+        }
+        assert!(record.to_pgn(None).unwrap().contains("[%nodes 20]"));
+    }
+
+    #[test]
+    fn test_observed_arena_events() {
+        let evaluator = MaterialEvaluator {};
+        let (observer, receiver) = ChannelArenaObserver::new(64);
+        let result = run_observed_arena(&evaluator, &evaluator, &make_arena_config(2), &observer);
+        assert_eq!(result.get_num_games(), 2);
+        assert_eq!(observer.get_num_dropped(), 0);
+
+        let events: Vec<ArenaEvent> = receiver.try_iter().collect();
+        assert_eq!(events.len(), 2 * (10 + 2));
+        assert_eq!(events[0], ArenaEvent::GameStarted { game_index: 0, initial_fen: State::initial().to_fen() });
+        match &events[1] {
+            ArenaEvent::MovePlayed(event) => {
+                assert_eq!(event.game_index, 0);
+                assert_eq!(event.ply, 1);
+                let mut state = State::initial();
+                state.make_move(event.mv);
+                assert_eq!(event.fen, state.to_fen());
+                assert!(event.eval.is_some());
+                assert_eq!(event.clocks[Color::White as usize], event.move_time);
+                assert_eq!(event.clocks[Color::Black as usize], Duration::ZERO);
+            },
+            event => panic!("Expected a move, found {:?}", event)
+        }
+        assert_eq!(events[11], ArenaEvent::GameFinished { game_index: 0, result: 0, num_moves: 10, adjudication: None });
+        assert!(matches!(events[12], ArenaEvent::GameStarted { game_index: 1, .. }));
+    }
+
+    #[test]
+    fn test_simulated_clocks() {
+        let evaluator = MaterialEvaluator {};
+        let time_control = TimeControl::new(Duration::from_secs(3), Duration::ZERO);
+        let config = ArenaConfig { time_control: Some(time_control), ..make_arena_config(1) };
+        let (observer, receiver) = ChannelArenaObserver::new(64);
+        let game = play_observed_arena_game(State::initial(), &evaluator, &evaluator, &config, 0, &observer);
+        assert_eq!(game.adjudication, None);
+        assert_eq!(game.record.moves.len(), 10);
+        // the time manager spends a fraction of the clock on every move
+        for event in receiver.try_iter() {
+            if let ArenaEvent::MovePlayed(event) = event {
+                assert!(event.move_time < Duration::from_secs(1), "{:?}", event.move_time);
+            }
+        }
+
+        let flag_config = ArenaConfig { time_control: Some(TimeControl::new(Duration::ZERO, Duration::ZERO)), ..make_arena_config(1) };
+        let game = play_observed_arena_game(State::initial(), &evaluator, &evaluator, &flag_config, 0, &NoArenaObserver);
+        assert_eq!(game.adjudication, Some(Adjudication::FlagFell { color: Color::White, opponent_has_lone_king: false }));
+        assert_eq!((game.record.result, game.record.moves.len()), (-1, 0));
+
+        let state = State::from_fen("8/8/8/3k4/8/8/8/Q3K3 w - - 0 1").unwrap();
+        let game = play_observed_arena_game(state, &evaluator, &evaluator, &flag_config, 0, &NoArenaObserver);
+        assert_eq!(game.adjudication, Some(Adjudication::FlagFell { color: Color::White, opponent_has_lone_king: true }));
+        assert_eq!(game.record.result, 0);
+    }
+
+    #[test]
+    fn test_handicap_arena() {
+        let evaluator = MaterialEvaluator {};
+        let (observer, receiver) = ChannelArenaObserver::new(64);
+        let handicap = Handicap { material: vec![MaterialOdds::Knight], time_factor: 0.005, ..Default::default() };
+        let config = ArenaConfig { iterations_per_move: 200, handicap: Some(handicap), ..make_arena_config(2) };
+        run_observed_arena(&evaluator, &evaluator, &config, &observer);
+
+        let events: Vec<ArenaEvent> = receiver.try_iter().collect();
+        let initial_fens: Vec<String> = events.iter().filter_map(|event| match event {
+            ArenaEvent::GameStarted { initial_fen, .. } => Some(initial_fen.clone()),
+            _ => None
+        }).collect();
+        assert_eq!(initial_fens, [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/R1BQKBNR w KQkq - 0 1",
+            "r1bqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        ]);
+        // the candidate, which gives the odds, searches a single iteration per move
+        for event in &events {
+            if let ArenaEvent::MovePlayed(event) = event {
+                let candidate_moved = (event.ply % 2 == 1) == (event.game_index == 0);
+                assert_eq!(event.visits <= 1, candidate_moved, "{:?}", event);
+            }
+        }
+    }
+
+    /// Covers positions without pawns or minor pieces: whoever has more rooks and queens wins, with DTZ 10.
+    struct MajorPieceTablebase {}
+
+    impl TablebaseProber for MajorPieceTablebase {
+        fn probe_wdl(&self, state: &State) -> Option<Wdl> {
+            let board = &state.board;
+            let minor_pieces = board.piece_type_masks[PieceType::Pawn as usize]
+                | board.piece_type_masks[PieceType::Knight as usize]
+                | board.piece_type_masks[PieceType::Bishop as usize];
+            if minor_pieces != 0 {
+                return None;
+            }
+            let majors = board.piece_type_masks[PieceType::Rook as usize] | board.piece_type_masks[PieceType::Queen as usize];
+            let count = |color: Color| (majors & board.color_masks[color as usize]).count_ones();
+            Some(match count(state.side_to_move).cmp(&count(state.side_to_move.flip())) {
+                std::cmp::Ordering::Greater => Wdl::Win,
+                std::cmp::Ordering::Equal => Wdl::Draw,
+                std::cmp::Ordering::Less => Wdl::Loss
+            })
+        }
+
+        fn probe_dtz(&self, state: &State) -> Option<i32> {
+            Some(match self.probe_wdl(state)? {
+                Wdl::Win | Wdl::CursedWin => 10,
+                Wdl::Draw => 0,
+                _ => -10
+            })
+        }
+    }
+
+    static MAJOR_PIECE_TABLEBASE: MajorPieceTablebase = MajorPieceTablebase {};
+
+    #[test]
+    fn test_tablebase_adjudication() {
+        let evaluator = MaterialEvaluator {};
+        let config = ArenaConfig { tablebase: Some(&MAJOR_PIECE_TABLEBASE), ..make_arena_config(1) };
+
+        let state = State::from_fen("4k3/8/8/8/8/8/8/R3K3 b - - 0 1").unwrap();
+        let (observer, receiver) = ChannelArenaObserver::new(16);
+        let game = play_observed_arena_game(state, &evaluator, &evaluator, &config, 0, &observer);
+        let adjudication = Adjudication::Tablebase { side_to_move: Color::Black, wdl: Wdl::Loss, dtz: Some(-10) };
+        assert_eq!(game.record.result, 1);
+        assert!(game.record.moves.is_empty());
+        assert_eq!(game.adjudication, Some(adjudication));
+        assert_eq!(
+            receiver.try_iter().last().unwrap(),
+            ArenaEvent::GameFinished { game_index: 0, result: 1, num_moves: 0, adjudication: Some(adjudication) }
+        );
+        assert_eq!(
+            game.to_pgn().unwrap(),
+            "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/8/R3K3 b - - 0 1\"]\n[Result \"1-0\"]\n\n\
+             {Tablebase adjudication: white wins (Loss for black, DTZ -10)} 1-0"
+        );
+
+        // positions with minor pieces are not covered, so the game is played out
+        let state = State::from_fen("4k3/8/8/8/8/8/8/R1B1K3 w - - 0 1").unwrap();
+        let game = play_observed_arena_game(state, &evaluator, &evaluator, &config, 0, &NoArenaObserver);
+        assert_eq!(game.adjudication, None);
+        assert!(!game.record.moves.is_empty());
+    }
+
+    #[test]
+    fn test_run_gating() {
+        let dir = env::temp_dir().join(format!("dunck_gating_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let candidate_path = dir.join("candidate.safetensors").to_str().unwrap().to_string();
+        let best_model_path = dir.join("best.safetensors").to_str().unwrap().to_string();
+        let history_path = dir.join("league.tsv").to_str().unwrap().to_string();
+        fs::write(&candidate_path, "candidate weights").unwrap();
+        fs::write(&best_model_path, "best weights").unwrap();
+
+        let evaluator = MaterialEvaluator {};
+        let mut config = GatingConfig {
+            arena: make_arena_config(2),
+            promotion_threshold: 1.1,
+            best_model_path: best_model_path.clone(),
+            history_path: history_path.clone(),
+        };
+
+        let entry = run_gating(&candidate_path, &evaluator, &evaluator, &config).unwrap();
+        assert!(!entry.promoted);
+        assert_eq!(entry.result.get_num_games(), 2);
+        assert_eq!(fs::read_to_string(&best_model_path).unwrap(), "best weights");
+
+        config.promotion_threshold = 0.;
+        let entry = run_gating(&candidate_path, &evaluator, &evaluator, &config).unwrap();
+        assert!(entry.promoted);
+        assert_eq!(fs::read_to_string(&best_model_path).unwrap(), "candidate weights");
+
+        let history = read_league_history(&history_path).unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1], entry);
+        assert!(!history[0].promoted);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,289 @@
+//! Handicap games, for calibrating the engine against weaker or stronger opponents and for generating
+//! handicapped training data. One side gives odds: pieces removed from its starting position, less time
+//! (or fewer search iterations in the arena), and a fixed opening played before the game starts.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::game::Game;
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+use crate::time_control::TimeControl;
+use dunck_core::utils::{Color, ColoredPiece, PieceType, Square};
+
+/// A piece removed from the starting position of the side giving odds, by its traditional square.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaterialOdds {
+    /// The f-pawn.
+    Pawn,
+    /// The queen's knight.
+    Knight,
+    /// The queen's rook, which also gives up castling queenside.
+    Rook,
+    Queen,
+}
+
+impl MaterialOdds {
+    pub const fn get_piece_type(&self) -> PieceType {
+        match self {
+            MaterialOdds::Pawn => PieceType::Pawn,
+            MaterialOdds::Knight => PieceType::Knight,
+            MaterialOdds::Rook => PieceType::Rook,
+            MaterialOdds::Queen => PieceType::Queen,
+        }
+    }
+
+    /// Returns the square the piece is removed from when `giver` gives the odds.
+    pub const fn get_square(&self, giver: Color) -> Square {
+        let white_square = match self {
+            MaterialOdds::Pawn => Square::F2,
+            MaterialOdds::Knight => Square::B1,
+            MaterialOdds::Rook => Square::A1,
+            MaterialOdds::Queen => Square::D1,
+        };
+        match giver {
+            Color::White => white_square,
+            Color::Black => white_square.reflect_rank()
+        }
+    }
+}
+
+impl FromStr for MaterialOdds {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<MaterialOdds, String> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "pawn" => Ok(MaterialOdds::Pawn),
+            "knight" => Ok(MaterialOdds::Knight),
+            "rook" => Ok(MaterialOdds::Rook),
+            "queen" => Ok(MaterialOdds::Queen),
+            _ => Err(format!("Unknown material odds: {}", s))
+        }
+    }
+}
+
+impl Display for MaterialOdds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MaterialOdds::Pawn => write!(f, "pawn"),
+            MaterialOdds::Knight => write!(f, "knight"),
+            MaterialOdds::Rook => write!(f, "rook"),
+            MaterialOdds::Queen => write!(f, "queen"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandicapError {
+    /// The piece to remove is not on its square.
+    MissingPiece { odds: MaterialOdds, square: Square },
+    /// A move of the opening, in UCI notation, is not legal.
+    IllegalOpeningMove(String),
+}
+
+impl Display for HandicapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            HandicapError::MissingPiece { odds, square } => write!(f, "No {} on {} to give as odds", odds, square),
+            HandicapError::IllegalOpeningMove(mv) => write!(f, "Illegal opening move: {}", mv),
+        }
+    }
+}
+
+impl std::error::Error for HandicapError {}
+
+/// The odds `giver` gives its opponent. The default gives no odds.
+///
+/// Parsed from and displayed as comma-separated `key=value` pairs, with lists joined by `+`:
+/// `giver` (`white` or `black`), `material` (e.g. `knight+pawn`), `opening` (UCI moves, e.g. `f2f3+e7e5`),
+/// and `time` (the fraction of the time control `giver` gets, e.g. `0.5`). Omitted keys keep their defaults.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Handicap {
+    pub giver: Color,
+    pub material: Vec<MaterialOdds>,
+    /// Moves played by both sides from the handicapped position before the game starts, in UCI notation.
+    pub opening: Vec<String>,
+    /// The fraction of the base time and increment, or of the search iterations per move, `giver` gets.
+    pub time_factor: f64,
+}
+
+impl Default for Handicap {
+    fn default() -> Self {
+        Handicap {
+            giver: Color::White,
+            material: Vec::new(),
+            opening: Vec::new(),
+            time_factor: 1.,
+        }
+    }
+}
+
+impl Handicap {
+    pub fn with_giver(mut self, giver: Color) -> Self {
+        self.giver = giver;
+        self
+    }
+
+    /// Returns `state` with the material odds removed and the opening played.
+    pub fn apply(&self, state: &State) -> Result<State, HandicapError> {
+        let mut state = self.remove_material(state)?;
+        for uci in &self.opening {
+            let mv = Move::from_uci(&state, uci).map_err(|_| HandicapError::IllegalOpeningMove(uci.clone()))?;
+            state.make_move(mv);
+        }
+        Ok(state)
+    }
+
+    /// Returns `state` with the material odds removed, and any castling rights they give up cleared.
+    pub fn remove_material(&self, state: &State) -> Result<State, HandicapError> {
+        let mut state = state.clone();
+        for odds in &self.material {
+            let square = odds.get_square(self.giver);
+            let colored_piece = ColoredPiece::from(self.giver, odds.get_piece_type());
+            if state.board.get_colored_piece_at(square) != colored_piece {
+                return Err(HandicapError::MissingPiece { odds: *odds, square });
+            }
+            state.board.remove_colored_piece_at(colored_piece, square);
+        }
+        // rebuilding the state recomputes its hashes
+        Ok(State::from_fen_with_castling_repair(&state.to_fen()).expect("removing pieces keeps the position valid"))
+    }
+
+    /// Returns the time control of each side, indexed by `Color`, with `giver`'s scaled by the time factor.
+    pub fn get_time_controls(&self, time_control: &TimeControl) -> [TimeControl; 2] {
+        let mut time_controls = [time_control.clone(), time_control.clone()];
+        time_controls[self.giver as usize] = time_control.scaled(self.time_factor);
+        time_controls
+    }
+
+    /// Returns how many search iterations per move `color` gets, with `giver`'s scaled by the time factor.
+    /// Always at least one.
+    pub fn get_iterations_per_move(&self, color: Color, iterations_per_move: usize) -> usize {
+        if color == self.giver {
+            ((iterations_per_move as f64 * self.time_factor).round() as usize).max(1)
+        } else {
+            iterations_per_move
+        }
+    }
+
+    /// Starts a game from `state` with the material odds removed, timed with `time_control` and the time odds
+    /// if given, and plays the opening in it so that the opening is part of the game's record.
+    pub fn create_game(&self, state: &State, time_control: Option<&TimeControl>) -> Result<Game, HandicapError> {
+        let mut game = Game::new(self.remove_material(state)?);
+        if let Some(time_control) = time_control {
+            game = game.with_time_controls(self.get_time_controls(time_control));
+        }
+        for uci in &self.opening {
+            Move::from_uci(game.get_state(), uci).ok()
+                .and_then(|mv| game.make_move(mv).ok())
+                .ok_or_else(|| HandicapError::IllegalOpeningMove(uci.clone()))?;
+        }
+        Ok(game)
+    }
+}
+
+impl FromStr for Handicap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Handicap, String> {
+        let mut handicap = Handicap::default();
+        for pair in s.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').ok_or_else(|| format!("Missing value for handicap key: {}", pair))?;
+            let (key, value) = (key.trim().to_ascii_lowercase(), value.trim());
+            let list = || value.split('+').map(str::trim).filter(|item| !item.is_empty());
+            match key.as_str() {
+                "giver" => handicap.giver = value.parse().map_err(|e| format!("Invalid handicap giver: {}", e))?,
+                "material" => handicap.material = list().map(str::parse).collect::<Result<_, _>>()?,
+                "opening" => handicap.opening = list().map(str::to_string).collect(),
+                "time" => {
+                    handicap.time_factor = value.parse().ok()
+                        .filter(|time_factor: &f64| *time_factor > 0. && time_factor.is_finite())
+                        .ok_or_else(|| format!("Invalid handicap time factor: {}", value))?;
+                },
+                _ => return Err(format!("Unknown handicap key: {}", key))
+            }
+        }
+        Ok(handicap)
+    }
+}
+
+impl Display for Handicap {
+    /// Writes only the terms that differ from the default, so that giving no odds is an empty string.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut pairs = Vec::new();
+        if self.giver != Color::White {
+            pairs.push(format!("giver={}", self.giver));
+        }
+        if !self.material.is_empty() {
+            let material: Vec<String> = self.material.iter().map(MaterialOdds::to_string).collect();
+            pairs.push(format!("material={}", material.join("+")));
+        }
+        if !self.opening.is_empty() {
+            pairs.push(format!("opening={}", self.opening.join("+")));
+        }
+        if self.time_factor != 1. {
+            pairs.push(format!("time={}", self.time_factor));
+        }
+        write!(f, "{}", pairs.join(","))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+
+    #[test]
+    fn test_parsing() {
+        let handicap: Handicap = "giver=black, material=knight+pawn, opening=e2e4+e7e5, time=0.5".parse().unwrap();
+        assert_eq!(handicap, Handicap {
+            giver: Color::Black,
+            material: vec![MaterialOdds::Knight, MaterialOdds::Pawn],
+            opening: vec!["e2e4".to_string(), "e7e5".to_string()],
+            time_factor: 0.5,
+        });
+        assert_eq!(handicap.to_string(), "giver=black,material=knight+pawn,opening=e2e4+e7e5,time=0.5");
+        assert_eq!(handicap.to_string().parse(), Ok(handicap));
+        assert_eq!("".parse(), Ok(Handicap::default()));
+        assert_eq!(Handicap::default().to_string(), "");
+
+        for invalid in ["material=bishop", "giver=red", "time=0", "time=-1", "handicap=1", "material"] {
+            assert!(invalid.parse::<Handicap>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_material_odds() {
+        let handicap = Handicap { material: vec![MaterialOdds::Rook, MaterialOdds::Pawn], ..Default::default() };
+        let state = handicap.apply(&State::initial()).unwrap();
+        assert_eq!(state.to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPP1PP/1NBQKBNR w Kkq - 0 1");
+        assert!(state.is_zobrist_consistent());
+
+        let state = handicap.with_giver(Color::Black).apply(&State::initial()).unwrap();
+        assert_eq!(state.to_fen(), "1nbqkbnr/ppppp1pp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQk - 0 1");
+
+        let handicap = Handicap { material: vec![MaterialOdds::Knight, MaterialOdds::Knight], ..Default::default() };
+        assert_eq!(handicap.apply(&State::initial()), Err(HandicapError::MissingPiece { odds: MaterialOdds::Knight, square: Square::B1 }));
+    }
+
+    #[test]
+    fn test_opening_and_time_odds() {
+        let handicap: Handicap = "material=queen,opening=f2f3+e7e5+g2g4,time=0.25".parse().unwrap();
+        let time_control = TimeControl::new(Duration::from_secs(60), Duration::from_secs(1));
+        let game = handicap.create_game(&State::initial(), Some(&time_control)).unwrap();
+        assert_eq!(game.get_initial_state().to_fen(), "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNB1KBNR w KQkq - 0 1");
+        assert_eq!(game.get_moves().len(), 3);
+        assert_eq!(game.get_state(), &handicap.apply(&State::initial()).unwrap());
+        assert_eq!(game.get_time_control(Color::White), Some(&TimeControl::new(Duration::from_secs(15), Duration::from_millis(250))));
+        assert_eq!(game.get_time_control(Color::Black), Some(&time_control));
+        assert_eq!(game.get_remaining_time(Color::White), Some(Duration::from_millis(15_500)));
+
+        assert_eq!(handicap.get_iterations_per_move(Color::White, 100), 25);
+        assert_eq!(handicap.get_iterations_per_move(Color::Black, 100), 100);
+        assert_eq!(handicap.get_iterations_per_move(Color::White, 1), 1);
+
+        let handicap: Handicap = "opening=e2e4+e2e4".parse().unwrap();
+        assert_eq!(handicap.apply(&State::initial()), Err(HandicapError::IllegalOpeningMove("e2e4".to_string())));
+        assert!(handicap.create_game(&State::initial(), None).is_err());
+    }
+}
@@ -0,0 +1,456 @@
+//! An HTTP REST front end for analysis, enabled by the `http` feature.
+//!
+//! Endpoints:
+//! - `POST /analyze` with a body like `{"fen": ..., "iterations": ...}`, returning the result of the
+//!   JSON-RPC `analyze` method (see `engine::server`)
+//! - `POST /bestmove` with the same body, returning `{"best_move": ...}`
+//! - `GET /legal_moves?fen=...`, returning the legal moves in UCI notation
+//!
+//! Requests are handled concurrently by a fixed number of worker threads. The workers share a single
+//! evaluator, run on the calling thread, which evaluates the positions requested by all workers in batches.
+//! Every request is independent: no search tree is kept between requests.
+//! A worker that panics drops its connection and is restarted, so one bad request does not stop the server.
+//! Connections that stall for longer than `CONNECTION_TIMEOUT` are dropped.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use serde_json::{json, Value};
+use crate::evaluation::{Evaluation, Evaluator};
+use crate::mcts::evaluation_queue::EvaluationQueue;
+use crate::mcts::mcts_node::MCTSNode;
+use crate::server::{EngineServer, ServerError, INVALID_PARAMS, METHOD_NOT_FOUND, PARSE_ERROR};
+use crate::worker::catch_worker_panic;
+use dunck_core::state::State;
+
+/// The largest request body that will be read, in bytes.
+pub const MAX_BODY_LENGTH: usize = 1 << 16;
+
+/// How long reading a request or writing a response may block before the connection is dropped.
+pub const CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A parsed HTTP request.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub query: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl HttpRequest {
+    /// Returns the value of the query parameter `key`, if present.
+    pub fn get_query_param(&self, key: &str) -> Option<&str> {
+        self.query.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+}
+
+/// Reads a single HTTP/1.1 request from `reader`.
+pub fn read_http_request<R: BufRead>(reader: &mut R) -> Result<HttpRequest, String> {
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).map_err(|e| e.to_string())?;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = match (parts.next(), parts.next()) {
+        (Some(method), Some(target)) => (method.to_string(), target),
+        _ => return Err(format!("Malformed request line: {}", request_line.trim()))
+    };
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path.to_string(), parse_query(query)?),
+        None => (target.to_string(), Vec::new())
+    };
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).map_err(|e| e.to_string())? == 0 {
+            break;
+        }
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                content_length = value.trim().parse().map_err(|_| format!("Invalid Content-Length: {}", value.trim()))?;
+            }
+        }
+    }
+    if content_length > MAX_BODY_LENGTH {
+        return Err(format!("Body too large: {} bytes", content_length));
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(|e| e.to_string())?;
+    let body = String::from_utf8(body).map_err(|e| e.to_string())?;
+
+    Ok(HttpRequest { method, path, query, body })
+}
+
+/// Parses a query string like `fen=8%2F8...&depth=3` into decoded key-value pairs.
+fn parse_query(query: &str) -> Result<Vec<(String, String)>, String> {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            Ok((percent_decode(key)?, percent_decode(value)?))
+        })
+        .collect()
+}
+
+/// Decodes `%XX` escapes, and `+` as a space.
+fn percent_decode(s: &str) -> Result<String, String> {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = s.get(i + 1..i + 3).ok_or_else(|| format!("Truncated escape in: {}", s))?;
+                decoded.push(u8::from_str_radix(hex, 16).map_err(|_| format!("Invalid escape in: {}", s))?);
+                i += 2;
+            },
+            byte => decoded.push(byte)
+        }
+        i += 1;
+    }
+    String::from_utf8(decoded).map_err(|e| e.to_string())
+}
+
+/// Handles a request with `server`, returning the HTTP status code and the JSON response body.
+pub fn handle_http_request(server: &mut EngineServer, request: &HttpRequest) -> (u16, Value) {
+    let result = match (request.method.as_str(), request.path.as_str()) {
+        ("POST", "/analyze") => parse_body(&request.body).and_then(|params| analyze(server, &params)),
+        ("POST", "/bestmove") => parse_body(&request.body)
+            .and_then(|params| analyze(server, &params))
+            .map(|result| json!({ "best_move": result["best_move"].clone() })),
+        ("GET", "/legal_moves") => {
+            let params = json!({ "fen": request.get_query_param("fen") });
+            server.handle_request("set_position", &params)
+                .and_then(|_| server.handle_request("legal_moves", &Value::Null))
+        },
+        _ => Err(ServerError { code: METHOD_NOT_FOUND, message: format!("No route for {} {}", request.method, request.path) })
+    };
+
+    match result {
+        Ok(result) => (200, result),
+        Err(e) => {
+            let status = if e.code == METHOD_NOT_FOUND { 404 } else { 400 };
+            (status, json!({ "error": e.message }))
+        }
+    }
+}
+
+fn parse_body(body: &str) -> Result<Value, ServerError> {
+    if body.trim().is_empty() {
+        return Ok(Value::Null);
+    }
+    serde_json::from_str::<Value>(body).map_err(|e| ServerError { code: PARSE_ERROR, message: e.to_string() })
+}
+
+/// Sets up the position given by `params` and analyzes it.
+fn analyze(server: &mut EngineServer, params: &Value) -> Result<Value, ServerError> {
+    if !params.is_null() && params.as_object().is_none() {
+        return Err(ServerError { code: INVALID_PARAMS, message: "Body must be a JSON object".to_string() });
+    }
+    server.handle_request("set_position", params)?;
+    server.handle_request("analyze", params)
+}
+
+fn write_http_response<W: Write>(writer: &mut W, status: u16, body: &Value) -> std::io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        _ => "Bad Request"
+    };
+    let body = body.to_string();
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status, reason, body.len(), body
+    )?;
+    writer.flush()
+}
+
+fn handle_connection(server: &mut EngineServer, stream: TcpStream) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(CONNECTION_TIMEOUT))?;
+    stream.set_write_timeout(Some(CONNECTION_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    match read_http_request(&mut reader) {
+        Ok(request) => {
+            let (status, body) = handle_http_request(server, &request);
+            write_http_response(&mut writer, status, &body)
+        },
+        Err(message) => write_http_response(&mut writer, 400, &json!({ "error": message }))
+    }
+}
+
+/// A position to evaluate, given by its FEN since `State` cannot be sent across threads,
+/// along with its index in the batch it was requested in and where to send its evaluation.
+struct EvaluationRequest {
+    fen: String,
+    index: usize,
+    reply: mpsc::Sender<(usize, Evaluation)>,
+}
+
+/// An evaluator that sends the states it is given to a shared evaluator running `run_evaluation_queue`
+/// and waits for their evaluations, so that the positions requested by several workers are evaluated together.
+/// Positions are sent as FENs, so the shared evaluator does not see the boards played before them.
+#[derive(Clone)]
+pub struct BatchingEvaluator {
+    sender: mpsc::Sender<EvaluationRequest>,
+}
+
+impl Evaluator for BatchingEvaluator {
+    fn evaluate(&self, state: &State) -> Evaluation {
+        self.evaluate_batch(std::slice::from_ref(state)).pop().unwrap()
+    }
+
+    fn evaluate_batch(&self, states: &[State]) -> Vec<Evaluation> {
+        let (reply, replies) = mpsc::channel();
+        for (index, state) in states.iter().enumerate() {
+            let request = EvaluationRequest { fen: state.to_fen(), index, reply: reply.clone() };
+            self.sender.send(request).expect("The shared evaluator has stopped");
+        }
+        drop(reply);
+
+        let mut evaluations: Vec<Option<Evaluation>> = vec![None; states.len()];
+        for (index, evaluation) in replies.iter().take(states.len()) {
+            evaluations[index] = Some(evaluation);
+        }
+        // a batch dropped by a panicking evaluator closes the reply channel, so the worker is restarted
+        evaluations.into_iter()
+            .map(|evaluation| evaluation.expect("The shared evaluator dropped a request"))
+            .collect()
+    }
+}
+
+/// Returns a channel on which `run_evaluation_queue` receives requests,
+/// and a `BatchingEvaluator` sending to it, to be cloned for each worker.
+fn evaluation_channel() -> (BatchingEvaluator, mpsc::Receiver<EvaluationRequest>) {
+    let (sender, receiver) = mpsc::channel();
+    (BatchingEvaluator { sender }, receiver)
+}
+
+/// Evaluates the positions requested on `receiver` with an evaluator built by `make_evaluator`,
+/// until every `BatchingEvaluator` sending to it is dropped.
+/// Whatever requests are already waiting are evaluated together, up to `batch_size` at a time,
+/// so a lone request is never held back waiting for a fuller batch.
+/// If the evaluator panics, the requests in its batch are dropped and it is rebuilt.
+fn run_evaluation_queue<E, F>(receiver: &mpsc::Receiver<EvaluationRequest>, batch_size: usize, make_evaluator: F)
+where
+    E: Evaluator,
+    F: Fn() -> E
+{
+    let mut evaluator = make_evaluator();
+    let mut queue = EvaluationQueue::new(batch_size);
+    while let Ok(request) = receiver.recv() {
+        let mut request = Some(request);
+        while let Some(EvaluationRequest { fen, index, reply }) = request.take() {
+            let state = State::from_fen(&fen).expect("Requested positions come from valid states");
+            if queue.push((index, reply), state) {
+                break;
+            }
+            request = receiver.try_recv().ok();
+        }
+
+        match catch_worker_panic(|| queue.flush(&evaluator)) {
+            Ok(evaluations) => {
+                for ((index, reply), evaluation) in evaluations {
+                    // the worker may have given up on the request after panicking, which is fine
+                    let _ = reply.send((index, evaluation));
+                }
+            },
+            Err(e) => {
+                eprintln!("{}, dropping the batch and rebuilding the evaluator", e);
+                queue = EvaluationQueue::new(batch_size);
+                evaluator = make_evaluator();
+            }
+        }
+    }
+}
+
+/// Listens on `addr` and handles requests on `num_workers` threads.
+/// The workers share one evaluator, built by `make_evaluator` and run on the calling thread,
+/// which evaluates up to `batch_size` of their positions at a time.
+/// Connections that fail to be accepted are logged and skipped, so this only returns if binding `addr` fails.
+pub fn serve_http<A, E, F>(
+    addr: A,
+    num_workers: usize,
+    batch_size: usize,
+    exploration_param: f64,
+    calc_node_score: &'static (dyn Fn(&MCTSNode, u32, f64) -> f64 + Sync),
+    make_evaluator: F
+) -> std::io::Result<()>
+where
+    A: ToSocketAddrs,
+    E: Evaluator,
+    F: Fn() -> E
+{
+    assert!(num_workers > 0);
+    let listener = TcpListener::bind(addr)?;
+    let (sender, receiver) = mpsc::channel::<TcpStream>();
+    let receiver = Arc::new(Mutex::new(receiver));
+    let (batching_evaluator, evaluation_receiver) = evaluation_channel();
+
+    thread::scope(|scope| {
+        for worker_index in 0..num_workers {
+            let receiver = Arc::clone(&receiver);
+            let evaluator = batching_evaluator.clone();
+            scope.spawn(move || {
+                // a worker that panics while handling a connection is restarted with a fresh server
+                'restart: loop {
+                    let mut server = EngineServer::new(exploration_param, &evaluator, calc_node_score);
+                    loop {
+                        let stream = match receiver.lock().unwrap().recv() {
+                            Ok(stream) => stream,
+                            Err(_) => break 'restart
+                        };
+                        match catch_worker_panic(|| handle_connection(&mut server, stream)) {
+                            Ok(Ok(())) => {},
+                            Ok(Err(e)) => eprintln!("Connection closed with error: {}", e),
+                            Err(e) => {
+                                eprintln!("{}, restarting worker {}", e, worker_index);
+                                continue 'restart;
+                            }
+                        }
+                    }
+                }
+            });
+        }
+        // the shared evaluator stops once every worker, and so every clone of the sender, is gone
+        drop(batching_evaluator);
+
+        scope.spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => sender.send(stream).expect("All workers have stopped"),
+                    Err(e) => eprintln!("Failed to accept a connection: {}", e)
+                }
+            }
+        });
+
+        run_evaluation_queue(&evaluation_receiver, batch_size, make_evaluator);
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::mcts::mcts::calc_uct_score;
+    use crate::server::MAX_ANALYSIS_ITERATIONS;
+    use dunck_core::utils::Color;
+
+    /// Evaluates with material, but panics on positions with black to move.
+    struct WhiteOnlyEvaluator;
+
+    impl Evaluator for WhiteOnlyEvaluator {
+        fn evaluate(&self, state: &State) -> Evaluation {
+            assert_eq!(state.side_to_move, Color::White, "Black to move");
+            MaterialEvaluator {}.evaluate(state)
+        }
+    }
+
+    #[test]
+    fn test_read_http_request() {
+        let raw = "GET /legal_moves?fen=8%2F8%2F8%2F8%2F8%2F8%2F8%2FK1k5+w+-+-+0+1 HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let request = read_http_request(&mut raw.as_bytes()).unwrap();
+        assert_eq!(request.method, "GET");
+        assert_eq!(request.path, "/legal_moves");
+        assert_eq!(request.get_query_param("fen"), Some("8/8/8/8/8/8/8/K1k5 w - - 0 1"));
+
+        let raw = "POST /analyze HTTP/1.1\r\nContent-Length: 17\r\n\r\n{\"iterations\": 5}";
+        let request = read_http_request(&mut raw.as_bytes()).unwrap();
+        assert_eq!(request.body, "{\"iterations\": 5}");
+
+        assert!(read_http_request(&mut "\r\n".as_bytes()).is_err());
+        assert!(read_http_request(&mut "GET /x?fen=%G1 HTTP/1.1\r\n\r\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_handle_http_request() {
+        let evaluator = MaterialEvaluator {};
+        let mut server = EngineServer::new(1.5, &evaluator, &calc_uct_score);
+        let make_request = |method: &str, path: &str, query: Vec<(String, String)>, body: &str| HttpRequest {
+            method: method.to_string(),
+            path: path.to_string(),
+            query,
+            body: body.to_string(),
+        };
+
+        let request = make_request("GET", "/legal_moves", vec![("fen".to_string(), "8/8/8/8/8/8/8/K6k w - - 0 1".to_string())], "");
+        let (status, body) = handle_http_request(&mut server, &request);
+        assert_eq!(status, 200);
+        assert_eq!(body.as_array().unwrap().len(), 3);
+
+        let request = make_request("POST", "/bestmove", vec![], r#"{"fen": "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "iterations": 200}"#);
+        let (status, body) = handle_http_request(&mut server, &request);
+        assert_eq!(status, 200);
+        assert!(body["best_move"].as_str().is_some());
+
+        let request = make_request("POST", "/analyze", vec![], r#"{"fen": "not a fen"}"#);
+        assert_eq!(handle_http_request(&mut server, &request).0, 400);
+
+        let body = json!({ "iterations": MAX_ANALYSIS_ITERATIONS + 1 }).to_string();
+        let request = make_request("POST", "/bestmove", vec![], &body);
+        assert_eq!(handle_http_request(&mut server, &request).0, 400);
+
+        let request = make_request("GET", "/unknown", vec![], "");
+        assert_eq!(handle_http_request(&mut server, &request).0, 404);
+    }
+
+    #[test]
+    fn test_batching_evaluator() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+        ];
+        let states: Vec<State> = fens.iter().map(|fen| State::from_fen(fen).unwrap()).collect();
+        let expected = &MaterialEvaluator {}.evaluate_batch(&states);
+
+        let (batching_evaluator, receiver) = evaluation_channel();
+        thread::scope(|scope| {
+            for _ in 0..4 {
+                let evaluator = batching_evaluator.clone();
+                scope.spawn(move || {
+                    let states: Vec<State> = fens.iter().map(|fen| State::from_fen(fen).unwrap()).collect();
+                    let evaluations = evaluator.evaluate_batch(&states);
+                    assert_eq!(evaluations.len(), expected.len());
+                    for (evaluation, expected) in evaluations.iter().zip(expected) {
+                        assert_eq!(evaluation.value, expected.value);
+                        assert_eq!(evaluation.policy, expected.policy);
+                    }
+                    assert_eq!(evaluator.evaluate(&states[1]).value, expected[1].value);
+                });
+            }
+            drop(batching_evaluator);
+            run_evaluation_queue(&receiver, 2, || MaterialEvaluator {});
+        });
+    }
+
+    #[test]
+    fn test_batching_evaluator_survives_evaluator_panic() {
+        let white_fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1";
+        let black_fen = "6k1/5ppp/8/8/8/8/8/R5K1 b - - 0 1";
+        let expected = MaterialEvaluator {}.evaluate(&State::from_fen(white_fen).unwrap()).value;
+
+        let (batching_evaluator, receiver) = evaluation_channel();
+        thread::scope(|scope| {
+            scope.spawn(move || {
+                let white_to_move = State::from_fen(white_fen).unwrap();
+                let black_to_move = State::from_fen(black_fen).unwrap();
+                assert!(catch_worker_panic(|| batching_evaluator.evaluate(&black_to_move)).is_err());
+                assert_eq!(batching_evaluator.evaluate(&white_to_move).value, expected);
+            });
+            run_evaluation_queue(&receiver, 4, || WhiteOnlyEvaluator);
+        });
+    }
+}
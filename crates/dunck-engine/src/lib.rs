@@ -0,0 +1,36 @@
+//! Search, evaluators and game play on top of `dunck_core`. The conv net evaluator lives in `dunck_nn`,
+//! which plugs into `evaluators::factory` with `dunck_nn::register`.
+
+pub mod mcts;
+pub mod alpha_beta;
+pub mod evaluation;
+pub mod dataset;
+pub mod policy_evaluation;
+pub mod evaluators;
+pub mod uci;
+pub mod analysis_session;
+pub mod analysis_checkpoint;
+pub mod analysis_db;
+pub mod engine_context;
+pub mod engine_options;
+pub mod time_manager;
+pub mod arena_events;
+pub mod gating;
+pub mod distributed_selfplay;
+pub mod selfplay;
+pub mod server;
+pub mod tablebase;
+pub mod fortress;
+pub mod tuning;
+pub mod manifest;
+pub mod worker;
+pub mod self_check;
+#[cfg(feature = "http")]
+pub mod http_server;
+pub mod game;
+pub mod handicap;
+pub mod referee;
+pub mod time_control;
+pub mod warmup;
+
+pub use warmup::{init, InitOptions};
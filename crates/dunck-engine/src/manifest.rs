@@ -0,0 +1,165 @@
+//! Fingerprints of the engine build, model and settings behind a result. A manifest is attached to exported games,
+//! analysis checkpoints and trained models, so that the result can be reproduced and attributed precisely.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use dunck_core::attacks::calc_magic_checksum;
+use crate::evaluators::neural::net_config::NetConfig;
+use dunck_core::state::ZOBRIST_KEYS_VERSION;
+use dunck_core::utils::Checksum;
+
+/// The PGN tag a manifest is written to.
+const PGN_TAG: &str = "EngineManifest";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EngineManifest {
+    pub crate_version: String,
+    /// The checksum of the model file, as 16 hexadecimal digits, if a model was used.
+    pub model_hash: Option<String>,
+    pub net_config: Option<NetConfig>,
+    /// Search or training parameters by name, e.g. `exploration_param` or `learning_rate`.
+    pub parameters: BTreeMap<String, String>,
+    /// The checksum of the magic bitboard tables, as 16 hexadecimal digits.
+    pub magic_checksum: String,
+    pub zobrist_keys_version: u32,
+}
+
+impl EngineManifest {
+    /// Returns the manifest of this build, without a model or parameters.
+    pub fn new() -> EngineManifest {
+        EngineManifest {
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            model_hash: None,
+            net_config: None,
+            parameters: BTreeMap::new(),
+            magic_checksum: format!("{:016x}", calc_magic_checksum()),
+            zobrist_keys_version: ZOBRIST_KEYS_VERSION,
+        }
+    }
+
+    /// Records the model at `model_path` by the checksum of its contents, and its config if it has one.
+    pub fn with_model(mut self, model_path: &str) -> io::Result<Self> {
+        let mut file = File::open(model_path)?;
+        let mut checksum = Checksum::default();
+        let mut buffer = vec![0; 1 << 16];
+        loop {
+            let num_read = file.read(&mut buffer)?;
+            if num_read == 0 {
+                break;
+            }
+            checksum.update(&buffer[..num_read]);
+        }
+        self.model_hash = Some(checksum.to_hex());
+        if Path::new(&NetConfig::get_config_path(model_path)).exists() {
+            let config = NetConfig::load(model_path, &NetConfig::new(0, 0)).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            self.net_config = Some(config);
+        }
+        Ok(self)
+    }
+
+    pub fn with_net_config(mut self, net_config: NetConfig) -> Self {
+        self.net_config = Some(net_config);
+        self
+    }
+
+    /// Records a parameter, e.g. `with_parameter("iterations_per_move", 800)`.
+    pub fn with_parameter(mut self, name: &str, value: impl Display) -> Self {
+        self.parameters.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a manifest is always serializable")
+    }
+
+    pub fn from_json(json: &str) -> Result<EngineManifest, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+
+    /// Writes the manifest to `path` as JSON.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        std::fs::write(path, self.to_json())
+    }
+
+    /// Adds the manifest to the tags of a PGN game, as compact JSON in an `EngineManifest` tag.
+    pub fn add_to_pgn_tags(&self, tags: &mut IndexMap<String, String>) {
+        let json = serde_json::to_string(self).expect("a manifest is always serializable");
+        tags.insert(PGN_TAG.to_string(), json);
+    }
+
+    /// Reads a manifest written by `add_to_pgn_tags`.
+    pub fn from_pgn_tags(tags: &IndexMap<String, String>) -> Result<EngineManifest, String> {
+        let json = tags.get(PGN_TAG).ok_or(format!("Missing {} tag", PGN_TAG))?;
+        EngineManifest::from_json(json).map_err(|e| format!("Invalid {} tag: {}", PGN_TAG, e))
+    }
+}
+
+impl Default for EngineManifest {
+    fn default() -> Self {
+        EngineManifest::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use super::*;
+    use dunck_core::game_record::GameRecord;
+    use dunck_core::pgn::{extract_tags, tokenize_pgn};
+
+    #[test]
+    fn test_manifest_of_build() {
+        let manifest = EngineManifest::new();
+        assert_eq!(manifest.crate_version, env!("CARGO_PKG_VERSION"));
+        assert_eq!(manifest.magic_checksum.len(), 16);
+        // the magic tables are generated deterministically
+        assert_eq!(manifest, EngineManifest::new());
+        assert_ne!(manifest, EngineManifest::new().with_parameter("exploration_param", 2.));
+    }
+
+    #[test]
+    fn test_serialization() {
+        let manifest = EngineManifest::new()
+            .with_net_config(NetConfig::new(6, 64).with_moves_left_head())
+            .with_parameter("exploration_param", 2.)
+            .with_parameter("iterations_per_move", 800);
+        assert_eq!(EngineManifest::from_json(&manifest.to_json()).unwrap(), manifest);
+
+        let mut tags = IndexMap::new();
+        tags.insert("Event".to_string(), "Self-play".to_string());
+        manifest.add_to_pgn_tags(&mut tags);
+        assert!(tags["EngineManifest"].contains(r#""parameters":{"exploration_param":"2","iterations_per_move":"800"}"#));
+        assert_eq!(EngineManifest::from_pgn_tags(&tags), Ok(manifest));
+
+        // the tag survives writing and parsing the game
+        let pgn = GameRecord::new(Vec::new(), 0).to_pgn_with_tags(&tags, None).unwrap();
+        let parsed_tags = extract_tags(&tokenize_pgn(&pgn).unwrap()).unwrap();
+        assert_eq!(EngineManifest::from_pgn_tags(&parsed_tags), EngineManifest::from_pgn_tags(&tags));
+        tags.shift_remove("EngineManifest");
+        assert!(EngineManifest::from_pgn_tags(&tags).is_err());
+    }
+
+    #[test]
+    fn test_model_hash() {
+        let model_path = env::temp_dir().join(format!("dunck_manifest_test_{}.safetensors", std::process::id()));
+        let model_path = model_path.to_str().unwrap();
+        fs::write(model_path, b"foobar").unwrap();
+        let manifest = EngineManifest::new().with_model(model_path).unwrap();
+        assert_eq!(manifest.model_hash.as_deref(), Some("85944171f73967e8"));
+        assert_eq!(manifest.net_config, None);
+
+        NetConfig::new(2, 32).save(model_path).unwrap();
+        let manifest = EngineManifest::new().with_model(model_path).unwrap();
+        assert_eq!(manifest.net_config, Some(NetConfig::new(2, 32)));
+        fs::remove_file(model_path).unwrap();
+        fs::remove_file(NetConfig::get_config_path(model_path)).unwrap();
+        assert!(EngineManifest::new().with_model(model_path).is_err());
+    }
+}
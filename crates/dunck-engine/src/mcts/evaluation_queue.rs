@@ -0,0 +1,145 @@
+//! Batched leaf evaluation: leaves selected over several simulations are queued, evaluated in a single
+//! `Evaluator::evaluate_batch` call, and their evaluations handed back to whatever is waiting on them.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::evaluation::{Evaluation, Evaluator};
+use crate::mcts::mcts_node::MCTSNode;
+use dunck_core::state::State;
+
+/// The value backed up along the path to each queued leaf while it waits for its evaluation,
+/// as a loss for the side choosing each move, so that the next simulations pick other leaves.
+pub const VIRTUAL_LOSS: f64 = 1.;
+
+/// States waiting to be evaluated together, each with a ticket identifying who is waiting on it,
+/// e.g. the leaf to expand, or the tree and the leaf when several trees share a queue.
+pub struct EvaluationQueue<T> {
+    capacity: usize,
+    tickets: Vec<T>,
+    states: Vec<State>,
+}
+
+impl<T> EvaluationQueue<T> {
+    /// Returns an empty queue that is full once `capacity` states are queued. A capacity of 0 is treated as 1.
+    pub fn new(capacity: usize) -> EvaluationQueue<T> {
+        EvaluationQueue {
+            capacity: capacity.max(1),
+            tickets: Vec::with_capacity(capacity),
+            states: Vec::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.states.len() >= self.capacity
+    }
+
+    /// Queues `state` for evaluation on behalf of `ticket`, and returns whether the queue is now full.
+    pub fn push(&mut self, ticket: T, state: State) -> bool {
+        self.tickets.push(ticket);
+        self.states.push(state);
+        self.is_full()
+    }
+
+    /// Evaluates every queued state with one call to `evaluator.evaluate_batch` and empties the queue,
+    /// returning each ticket with the evaluation of its state, in the order they were pushed.
+    pub fn flush(&mut self, evaluator: &dyn Evaluator) -> Vec<(T, Evaluation)> {
+        if self.states.is_empty() {
+            return Vec::new();
+        }
+        let evaluations = evaluator.evaluate_batch(&self.states);
+        assert_eq!(evaluations.len(), self.states.len(), "The evaluator returned the wrong number of evaluations");
+        self.states.clear();
+        self.tickets.drain(..).zip(evaluations).collect()
+    }
+}
+
+/// The virtual losses applied to the paths of queued leaves, recorded so that they can be undone exactly.
+#[derive(Default)]
+pub struct VirtualLosses {
+    /// Every node changed, with its visits and value from before the change, oldest first.
+    applied: Vec<(Rc<RefCell<MCTSNode>>, u32, f64)>,
+}
+
+impl VirtualLosses {
+    /// Adds a visit and a `VIRTUAL_LOSS` to `leaf` and every node above it.
+    pub fn apply(&mut self, leaf: &Rc<RefCell<MCTSNode>>) {
+        let mut node = Some(leaf.clone());
+        while let Some(current) = node {
+            let mut current_mut = current.borrow_mut();
+            self.applied.push((current.clone(), current_mut.visits, current_mut.value));
+            current_mut.visits += 1;
+            current_mut.value -= VIRTUAL_LOSS;
+            node = current_mut.previous_node.clone();
+        }
+    }
+
+    /// Restores every node to its visits and value from before the first `apply`.
+    pub fn revert(&mut self) {
+        while let Some((node, visits, value)) = self.applied.pop() {
+            let mut node = node.borrow_mut();
+            node.visits = visits;
+            node.value = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::mcts::mcts::{calc_puct_score, MCTS};
+
+    #[test]
+    fn test_queue_returns_evaluations_in_order() {
+        let states = [
+            State::initial(),
+            State::from_fen("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1").unwrap(),
+            State::from_fen("4k3/8/8/8/8/8/8/q3K3 w - - 0 1").unwrap(),
+        ];
+        let mut queue = EvaluationQueue::new(2);
+        assert!(!queue.push(0, states[0].clone()));
+        assert!(queue.push(1, states[1].clone()));
+        queue.push(2, states[2].clone());
+        assert_eq!(queue.len(), 3);
+
+        let evaluator = MaterialEvaluator {};
+        let evaluations = queue.flush(&evaluator);
+        assert!(queue.is_empty());
+        assert_eq!(evaluations.iter().map(|(ticket, _)| *ticket).collect::<Vec<_>>(), vec![0, 1, 2]);
+        for ((_, evaluation), state) in evaluations.iter().zip(states.iter()) {
+            assert_eq!(evaluation.value, evaluator.evaluate(state).value);
+        }
+        assert!(queue.flush(&evaluator).is_empty());
+    }
+
+    #[test]
+    fn test_virtual_losses_are_reverted_exactly() {
+        let evaluator = MaterialEvaluator {};
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_puct_score, false);
+        mcts.run(50);
+        let child = mcts.root.borrow().children[0].clone();
+        child.borrow_mut().value = 0.3;
+        let root_visits = mcts.root.borrow().visits;
+        let child_visits = child.borrow().visits;
+
+        let mut virtual_losses = VirtualLosses::default();
+        virtual_losses.apply(&child);
+        virtual_losses.apply(&child);
+        assert_eq!(mcts.root.borrow().visits, root_visits + 2);
+        assert_eq!(child.borrow().visits, child_visits + 2);
+        assert!((child.borrow().value - (0.3 - 2. * VIRTUAL_LOSS)).abs() < 1e-12);
+
+        virtual_losses.revert();
+        assert_eq!(mcts.root.borrow().visits, root_visits);
+        assert_eq!(child.borrow().visits, child_visits);
+        assert_eq!(child.borrow().value, 0.3);
+    }
+}
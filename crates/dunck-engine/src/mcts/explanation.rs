@@ -0,0 +1,187 @@
+use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use crate::mcts::mcts::MCTS;
+use crate::mcts::mcts_node::MCTSNode;
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+
+/// The number of counter-lines included in a `MoveExplanation`.
+pub const NUM_COUNTER_LINES: usize = 3;
+
+/// One of the opponent's most visited replies to an explained move.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CounterLine {
+    pub visits: u32,
+    /// The mean value of the reply for the opponent.
+    pub q: f64,
+    /// The reply followed by its principal variation.
+    pub line: Vec<Move>,
+}
+
+/// What the search found out about one of the root moves, to show why it was or was not chosen.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveExplanation {
+    pub root_state: State,
+    pub mv: Move,
+    pub visits: u32,
+    pub root_visits: u32,
+    /// The mean value of the move for the side to move at the root, or `None` if it was never visited.
+    pub q: Option<f64>,
+    pub prior: f64,
+    /// The move's position among the root moves when ordered by visits, starting at 1.
+    pub rank: usize,
+    /// The most visited root move and its mean value.
+    pub best_move: Option<(Move, Option<f64>)>,
+    /// The move followed by its principal variation.
+    pub principal_variation: Vec<Move>,
+    /// The opponent's most visited replies, most visited first.
+    pub counter_lines: Vec<CounterLine>,
+}
+
+fn get_q(node: &MCTSNode) -> Option<f64> {
+    if node.visits == 0 {
+        None
+    } else {
+        Some(node.value / node.visits as f64)
+    }
+}
+
+fn get_children_by_visits(node: &MCTSNode) -> Vec<Rc<RefCell<MCTSNode>>> {
+    let mut children = node.children.clone();
+    children.sort_by_key(|child| Reverse(child.borrow().visits));
+    children
+}
+
+/// Formats `line` in SAN, played from `state`.
+pub fn format_line(state: &State, line: &[Move]) -> String {
+    let mut state = state.clone();
+    let mut sans = Vec::with_capacity(line.len());
+    for mv in line {
+        let legal_moves = state.calc_legal_moves();
+        let mut next_state = state.clone();
+        next_state.make_move(*mv);
+        sans.push(mv.to_san(&state, &next_state, &legal_moves));
+        state = next_state;
+    }
+    sans.join(" ")
+}
+
+impl MCTSNode {
+    /// Returns the line of most visited moves after this node.
+    pub fn get_principal_variation(&self) -> Vec<Move> {
+        let mut line = Vec::new();
+        let mut best_child = get_children_by_visits(self).into_iter().next();
+        while let Some(child) = best_child.filter(|child| child.borrow().visits > 0) {
+            line.push(child.borrow().mv.unwrap());
+            best_child = get_children_by_visits(&child.borrow()).into_iter().next();
+        }
+        line
+    }
+}
+
+impl<'a> MCTS<'a> {
+    /// Explains the search's opinion of the root move `mv`: its statistics, its principal variation,
+    /// and the opponent replies that refute it.
+    pub fn explain_move(&self, mv: Move) -> Result<MoveExplanation, String> {
+        let root = self.root.borrow();
+        let children = get_children_by_visits(&root);
+        let index = children.iter()
+            .position(|child| child.borrow().mv == Some(mv))
+            .ok_or_else(|| format!("{} is not a root move", mv))?;
+        let child = children[index].borrow();
+
+        let mut principal_variation = vec![mv];
+        principal_variation.extend(child.get_principal_variation());
+
+        let counter_lines = get_children_by_visits(&child).into_iter()
+            .filter(|reply| reply.borrow().visits > 0)
+            .take(NUM_COUNTER_LINES)
+            .map(|reply| {
+                let reply = reply.borrow();
+                let mut line = vec![reply.mv.unwrap()];
+                line.extend(reply.get_principal_variation());
+                CounterLine { visits: reply.visits, q: get_q(&reply).unwrap(), line }
+            })
+            .collect();
+
+        Ok(MoveExplanation {
+            root_state: root.state_after_move.clone(),
+            mv,
+            visits: child.visits,
+            root_visits: root.visits,
+            q: get_q(&child),
+            prior: child.prior,
+            rank: index + 1,
+            best_move: children.first().map(|best| (best.borrow().mv.unwrap(), get_q(&best.borrow()))),
+            principal_variation,
+            counter_lines,
+        })
+    }
+}
+
+impl Display for MoveExplanation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let format_q = |q: Option<f64>| q.map_or("n/a".to_string(), |q| format!("{:+.3}", q));
+        let share = if self.root_visits == 0 { 0. } else { self.visits as f64 / self.root_visits as f64 * 100. };
+
+        writeln!(
+            f, "{}: {} visits ({:.1}%), rank {}, Q {}, prior {:.3}",
+            format_line(&self.root_state, &[self.mv]), self.visits, share, self.rank, format_q(self.q), self.prior
+        )?;
+        if let Some((best_move, best_q)) = self.best_move.filter(|(best_move, _)| *best_move != self.mv) {
+            write!(f, "Best move: {} with Q {}", format_line(&self.root_state, &[best_move]), format_q(best_q))?;
+            if let (Some(q), Some(best_q)) = (self.q, best_q) {
+                write!(f, " ({:+.3} better)", best_q - q)?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "PV: {}", format_line(&self.root_state, &self.principal_variation))?;
+
+        let mut state_after_move = self.root_state.clone();
+        state_after_move.make_move(self.mv);
+        for counter_line in &self.counter_lines {
+            writeln!(
+                f, "Reply {} visits, Q {} for the opponent: {}",
+                counter_line.visits, format_q(Some(counter_line.q)), format_line(&state_after_move, &counter_line.line)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::mcts::mcts::calc_uct_score;
+    use dunck_core::r#move::MoveFlag;
+    use dunck_core::utils::Square;
+
+    #[test]
+    fn test_explain_move() {
+        // Qxd5 loses the queen to exd5
+        let state = State::from_fen("4k3/8/4p3/3p4/8/8/8/3QK3 w - - 0 1").unwrap();
+        let evaluator = MaterialEvaluator {};
+        let mut mcts = MCTS::new(state, 1.5, &evaluator, &calc_uct_score, false);
+        mcts.run(500);
+
+        let queen_capture = Move::new_non_promotion(Square::D5, Square::D1, MoveFlag::NormalMove);
+        let explanation = mcts.explain_move(queen_capture).unwrap();
+        assert_eq!(explanation.principal_variation[0], queen_capture);
+        assert!(explanation.rank > 1);
+        assert!(explanation.q.unwrap() < explanation.best_move.unwrap().1.unwrap());
+
+        let refutation = Move::new_non_promotion(Square::D5, Square::E6, MoveFlag::NormalMove);
+        assert_eq!(explanation.counter_lines[0].line[0], refutation);
+        assert!(explanation.counter_lines.windows(2).all(|pair| pair[0].visits >= pair[1].visits));
+
+        let display = explanation.to_string();
+        assert!(display.starts_with("Qxd5"));
+        assert!(display.contains("exd5"));
+
+        assert!(mcts.explain_move(Move::new_non_promotion(Square::A8, Square::D1, MoveFlag::NormalMove)).is_err());
+    }
+}
@@ -0,0 +1,577 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use std::time::Instant;
+use crate::evaluation::{Evaluation, Evaluator, MovesLeftUtility, OutcomeScores};
+use crate::mcts::evaluation_queue::{EvaluationQueue, VirtualLosses};
+use crate::mcts::mcts_node::MCTSNode;
+use crate::mcts::multi_pv::MultiPv;
+use crate::mcts::progressive_widening::{ExpansionStats, ProgressiveWidening};
+use crate::time_manager::SearchBudget;
+use dunck_core::r#move::Move;
+use dunck_core::state::{State};
+
+/// The number of iterations run between checks of the budget and the stop condition in `MCTS::run_until`.
+pub const ITERATIONS_PER_BUDGET_CHECK: u32 = 16;
+
+pub fn calc_uct_score(node: &MCTSNode, parent_visits: u32, exploration_constant: f64) -> f64 {
+    if node.visits == 0 {
+        f64::INFINITY
+    } else {
+        let exploitation = node.value / node.visits as f64;
+        let exploration = exploration_constant * ((parent_visits as f64).ln() / node.visits as f64).sqrt();
+        exploitation + exploration
+    }
+}
+
+pub fn calc_puct_score(node: &MCTSNode, parent_visits: u32, exploration_constant: f64) -> f64 {
+    let exploration = exploration_constant * node.prior * (parent_visits as f64).sqrt() / (1.0 + node.visits as f64);
+
+    if node.visits == 0 {
+        exploration  // Prior-driven exploration for unvisited nodes
+    } else {
+        let exploitation = node.value / node.visits as f64;
+        exploitation + exploration
+    }
+}
+
+/// A Monte Carlo tree search over `Rc<RefCell<MCTSNode>>` nodes.
+///
+/// Operations that only read the tree (`Display`, `get_best_child_by_visits`, `get_best_child_by_score`,
+/// `get_expansion_stats`, `explain_move`, and `MCTSNode::get_principal_variation`) take only shared borrows
+/// of nodes, so they may be called between iterations or while the caller holds shared borrows of any nodes.
+/// Holding a mutable borrow of a node while calling any operation, or any borrow while calling `run`, panics.
+pub struct MCTS<'a> {
+    pub root: Rc<RefCell<MCTSNode>>,
+    pub exploration_param: f64,
+    pub evaluator: &'a dyn Evaluator,
+    pub calc_node_score: &'static dyn Fn(&MCTSNode, u32, f64) -> f64,
+    pub save_data: bool,
+    pub state_evaluations: Vec<(State, Evaluation)>,
+    /// If set, nodes only expand their highest-prior children, adding more as they are visited.
+    pub progressive_widening: Option<ProgressiveWidening>,
+    /// The values backed up from terminal nodes and returned by `play_game`.
+    pub outcome_scores: OutcomeScores,
+    /// If set, leaf values are adjusted by the evaluator's moves-left estimate before being backed up.
+    pub moves_left_utility: Option<MovesLeftUtility>,
+    /// If set, several root moves are guaranteed a share of the search, to report them as separate lines.
+    pub multi_pv: Option<MultiPv>,
+    /// Whether positions below the root that occurred before are scored as draws instead of being searched,
+    /// since either side can claim the draw by repeating them again.
+    pub score_repetitions_as_draws: bool,
+    /// The number of leaves selected, using virtual losses, and evaluated together with `Evaluator::evaluate_batch`
+    /// in each step of `run`. 1 evaluates every leaf on its own.
+    pub evaluation_batch_size: usize
+}
+
+impl<'a> MCTS<'a> {
+    pub fn new(
+        state: State,
+        exploration_param: f64,
+        evaluator: &'a dyn Evaluator,
+        calc_node_score: &'static dyn Fn(&MCTSNode, u32, f64) -> f64,
+        save_data: bool
+    ) -> Self {
+        Self {
+            root: Rc::new(RefCell::new(MCTSNode::new(None, None, state))),
+            exploration_param,
+            evaluator,
+            calc_node_score,
+            save_data,
+            state_evaluations: Vec::new(),
+            progressive_widening: None,
+            outcome_scores: OutcomeScores::default(),
+            moves_left_utility: None,
+            multi_pv: None,
+            score_repetitions_as_draws: false,
+            evaluation_batch_size: 1
+        }
+    }
+
+    pub fn with_outcome_scores(mut self, outcome_scores: OutcomeScores) -> Self {
+        self.outcome_scores = outcome_scores;
+        self
+    }
+
+    /// Makes the search prefer faster wins and slower losses, if the evaluator estimates moves left.
+    pub fn with_moves_left_utility(mut self, moves_left_utility: MovesLeftUtility) -> Self {
+        self.moves_left_utility = Some(moves_left_utility);
+        self
+    }
+
+    /// Enables progressive widening with the given schedule.
+    pub fn with_progressive_widening(mut self, progressive_widening: ProgressiveWidening) -> Self {
+        self.progressive_widening = Some(progressive_widening);
+        self
+    }
+
+    /// Scores repeated positions as draws, see `score_repetitions_as_draws`.
+    pub fn with_repetition_draws(mut self) -> Self {
+        self.score_repetitions_as_draws = true;
+        self
+    }
+
+    /// Shares the search between the `multi_pv.num_lines` best root moves, see `MultiPv`.
+    pub fn with_multi_pv(mut self, multi_pv: MultiPv) -> Self {
+        self.multi_pv = Some(multi_pv);
+        self
+    }
+
+    /// Evaluates leaves in batches of up to `evaluation_batch_size`, see `evaluation_batch_size`.
+    pub fn with_evaluation_batch_size(mut self, evaluation_batch_size: usize) -> Self {
+        self.evaluation_batch_size = evaluation_batch_size.max(1);
+        self
+    }
+
+    fn select_best_leaf(&self) -> Rc<RefCell<MCTSNode>> {
+        let mut leaf = self.root.clone();
+        loop {
+            if let Some(widening) = &self.progressive_widening {
+                leaf.borrow_mut().widen(&leaf, widening);
+            }
+            let starved_candidate = match &self.multi_pv {
+                Some(multi_pv) if Rc::ptr_eq(&leaf, &self.root) => multi_pv.select_starved_candidate(&leaf.borrow()),
+                _ => None
+            };
+            let option_best_child = starved_candidate
+                .or_else(|| leaf.borrow().select_best_child(self.calc_node_score, self.exploration_param));
+            match option_best_child {
+                Some(best_child) => {
+                    leaf = best_child;
+                }
+                None => {
+                    return leaf;
+                }
+            }
+        }
+    }
+
+    pub fn run(&mut self, iterations: usize) {
+        let mut num_iterations_run = 0;
+        while num_iterations_run < iterations {
+            num_iterations_run += if self.evaluation_batch_size > 1 {
+                self.run_batch(self.evaluation_batch_size.min(iterations - num_iterations_run))
+            } else {
+                self.run_iteration();
+                1
+            };
+        }
+    }
+
+    fn run_iteration(&mut self) {
+        let leaf = self.select_best_leaf();
+        if self.is_repetition_leaf(&leaf) {
+            // the leaf is left unexpanded, so that it is scored the same way on every visit
+            leaf.borrow_mut().backup(self.outcome_scores.draw);
+            return;
+        }
+        let state_after_move = leaf.borrow().state_after_move.clone();
+        let is_terminal = leaf.borrow().is_expanded;
+        let evaluation = if is_terminal {
+            self.evaluate_terminal(&state_after_move)
+        } else {
+            self.evaluator.evaluate(&state_after_move)
+        };
+        self.expand_and_backup(&leaf, state_after_move, evaluation, is_terminal);
+    }
+
+    /// Selects up to `batch_size` distinct leaves, applying a virtual loss to the path to each so that the next
+    /// selection avoids it, then evaluates them with one `Evaluator::evaluate_batch` call and backs them all up.
+    /// Returns the number of leaves, which is smaller than `batch_size` when a leaf is selected twice.
+    fn run_batch(&mut self, batch_size: usize) -> usize {
+        let mut leaves: Vec<Rc<RefCell<MCTSNode>>> = Vec::with_capacity(batch_size);
+        let mut virtual_losses = VirtualLosses::default();
+        while leaves.len() < batch_size {
+            let leaf = self.select_best_leaf();
+            if leaves.iter().any(|selected_leaf| Rc::ptr_eq(selected_leaf, &leaf)) {
+                // even with the virtual losses, no other leaf is worth exploring yet
+                break;
+            }
+            virtual_losses.apply(&leaf);
+            leaves.push(leaf);
+        }
+        virtual_losses.revert();
+
+        let num_leaves = leaves.len();
+        let mut queue = EvaluationQueue::new(num_leaves);
+        for leaf in leaves {
+            let state_after_move = leaf.borrow().state_after_move.clone();
+            if self.is_repetition_leaf(&leaf) {
+                leaf.borrow_mut().backup(self.outcome_scores.draw);
+            } else if leaf.borrow().is_expanded {
+                let evaluation = self.evaluate_terminal(&state_after_move);
+                self.expand_and_backup(&leaf, state_after_move, evaluation, true);
+            } else {
+                queue.push(leaf, state_after_move);
+            }
+        }
+        for (leaf, evaluation) in queue.flush(self.evaluator) {
+            let state_after_move = leaf.borrow().state_after_move.clone();
+            self.expand_and_backup(&leaf, state_after_move, evaluation, false);
+        }
+        num_leaves
+    }
+
+    /// Whether `leaf` is scored as a draw by repetition instead of being searched, see `score_repetitions_as_draws`.
+    fn is_repetition_leaf(&self, leaf: &Rc<RefCell<MCTSNode>>) -> bool {
+        let leaf_ref = leaf.borrow();
+        self.score_repetitions_as_draws && !leaf_ref.is_expanded && !Rc::ptr_eq(leaf, &self.root)
+            && leaf_ref.state_after_move.repetition_count() > 0
+    }
+
+    fn evaluate_terminal(&self, state_after_move: &State) -> Evaluation {
+        Evaluation {
+            policy: Vec::with_capacity(0),
+            value: self.outcome_scores.get_value_at_terminal_state(state_after_move, state_after_move.side_to_move),
+        }
+    }
+
+    fn expand_and_backup(&mut self, leaf: &Rc<RefCell<MCTSNode>>, state_after_move: State, evaluation: Evaluation, is_terminal: bool) {
+        // terminal values need no adjustment, since no moves are left
+        let value = match &self.moves_left_utility {
+            Some(utility) if !is_terminal => match self.evaluator.estimate_moves_left(&state_after_move) {
+                Some(moves_left) => utility.adjust_value(evaluation.value, moves_left),
+                None => evaluation.value
+            },
+            _ => evaluation.value
+        };
+
+        if self.save_data {
+            self.state_evaluations.push((state_after_move, evaluation.clone()));
+        }
+
+        match &self.progressive_widening {
+            Some(widening) => leaf.borrow_mut().expand_progressively(evaluation.policy, leaf, widening),
+            None => leaf.borrow_mut().expand(evaluation.policy, leaf)
+        }
+        leaf.borrow_mut().backup(value);
+    }
+
+    /// Runs iterations until `budget` is used up, and returns how many were run.
+    /// At least one iteration is run, so that the root has a best child if it has any moves.
+    pub fn run_with_budget(&mut self, budget: &SearchBudget) -> u32 {
+        self.run_until(budget, |_| false)
+    }
+
+    /// Like `run_with_budget`, but also stops once `should_stop` returns true.
+    /// `should_stop` is called every `ITERATIONS_PER_BUDGET_CHECK` iterations,
+    /// e.g. to check a stop flag or report progress.
+    pub fn run_until(&mut self, budget: &SearchBudget, mut should_stop: impl FnMut(&MCTS) -> bool) -> u32 {
+        let start_time = Instant::now();
+        let mut iterations = 0;
+        loop {
+            let chunk_size = match budget.nodes {
+                Some(nodes) => ITERATIONS_PER_BUDGET_CHECK.min(nodes.saturating_sub(iterations).max(1)),
+                None => ITERATIONS_PER_BUDGET_CHECK
+            };
+            self.run(chunk_size as usize);
+            iterations += chunk_size;
+            if budget.is_exhausted(iterations, start_time) || should_stop(self) {
+                return iterations;
+            }
+        }
+    }
+
+    /// Restricts the root to `allowed_moves`, in that order, expanding it first if needed.
+    /// Intended to be called before searching, e.g. with the result of a tablebase root filter,
+    /// so that the search never explores or plays any other move.
+    pub fn restrict_root_moves(&mut self, allowed_moves: &[Move]) {
+        if !self.root.borrow().is_expanded {
+            let evaluation = self.evaluator.evaluate(&self.root.borrow().state_after_move);
+            match &self.progressive_widening {
+                Some(widening) => self.root.borrow_mut().expand_progressively(evaluation.policy, &self.root, widening),
+                None => self.root.borrow_mut().expand(evaluation.policy, &self.root)
+            }
+        }
+
+        let mut root = self.root.borrow_mut();
+        let mut children = std::mem::take(&mut root.children);
+        root.children = allowed_moves.iter().filter_map(|mv| {
+            let index = children.iter().position(|child| child.borrow().mv == Some(*mv))?;
+            Some(children.remove(index))
+        }).collect();
+        root.pending_children.retain(|(mv, _)| allowed_moves.contains(mv));
+        root.pending_children.sort_by_key(|(mv, _)| allowed_moves.iter().position(|allowed| allowed == mv));
+    }
+
+    /// Returns how many of the legal moves seen by the search were expanded into nodes.
+    pub fn get_expansion_stats(&self) -> ExpansionStats {
+        self.root.borrow().get_expansion_stats()
+    }
+
+    pub fn get_best_child_by_score(&self) -> Option<Rc<RefCell<MCTSNode>>> {
+        self.root.borrow().select_best_child(self.calc_node_score, 0.)
+    }
+
+    pub fn get_best_child_by_visits(&self) -> Option<Rc<RefCell<MCTSNode>>> {
+        self.root.borrow().children.iter().max_by(|a, b| {
+            let a_score = a.borrow().visits;
+            let b_score = b.borrow().visits;
+            a_score.cmp(&b_score)
+        }).cloned()
+    }
+    
+    pub fn take_child_with_move(&mut self, mv: Move, expand_if_unexpanded: bool) -> Result<(), String> {
+        if !self.root.borrow().is_expanded {
+            if expand_if_unexpanded {
+                let evaluation = self.evaluator.evaluate(&self.root.borrow().state_after_move);
+                self.root.borrow_mut().expand(evaluation.policy, &self.root);
+            } else {
+                return Err("Root node is not expanded".to_string());
+            }
+        }
+        
+        let mut new_root = None;
+        {
+            let root = self.root.borrow();
+            let children_iter = root.children.iter();
+            for child in children_iter {
+                if child.borrow().mv == Some(mv) {
+                    new_root = Some(Rc::clone(child));
+                    break;
+                }
+            }
+        }
+        if let Some(new_root) = new_root {
+            self.root = new_root;
+            self.root.borrow_mut().previous_node = None;
+            self.root.borrow_mut().flip_values();
+            Ok(())
+        } else {
+            Err("No child found".to_string())
+        }
+    }
+
+    pub fn take_best_child(&mut self) -> Result<(State, Move), String> {
+        if let Some(best_child) = self.get_best_child_by_visits() {
+            let best_move = best_child.borrow().mv.clone();
+            let next_state = best_child.borrow().state_after_move.clone();
+            self.root = best_child;
+            self.root.borrow_mut().previous_node = None;
+            self.root.borrow_mut().flip_values();
+
+            Ok((next_state, best_move.unwrap()))
+        } else {
+            Err("No best child found".to_string())
+        }
+    }
+
+    pub fn play_game(&mut self, num_iterations_per_move: usize, max_depth: usize) -> f64 {
+        let initial_side_to_move = self.root.borrow().state_after_move.side_to_move;
+        for _ in 0..max_depth {
+            self.run(num_iterations_per_move);
+            match self.take_best_child() {
+                Ok(_) => {}
+                Err(_) => {
+                    let final_state = self.root.borrow().state_after_move.clone();
+                    assert!(final_state.termination.is_some());
+                    assert!(final_state.is_unequivocally_valid());
+                    return self.outcome_scores.get_value_at_terminal_state(&final_state, initial_side_to_move);
+                }
+            }
+        }
+        self.outcome_scores.max_depth_reached
+    }
+}
+
+impl<'a> Display for MCTS<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.root.borrow())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluators::random_rollout::RolloutEvaluator;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use super::*;
+
+    #[test]
+    fn test_mcts() {
+        // let evaluator = ConvNetEvaluator::new(4, 8, true);
+        let evaluator = RolloutEvaluator::new(300);
+        let exploration_param = 1.5;
+        let mut mcts = MCTS::new(
+            State::from_fen("r1n1k3/p2p1pbr/B1p1pnp1/2qPN3/4P3/R1N1BQ1P/1PP2P1P/4K2R w Kq - 5 6").unwrap(),
+            // State::initial(),
+            exploration_param,
+            &evaluator,
+            &calc_uct_score,
+            true
+        );
+        for i in 0..1 {
+            println!("Move: {}", i);
+            mcts.run(1000);
+            println!("{}", mcts);
+            let initial_state = mcts.root.borrow().state_after_move.clone();
+            match mcts.take_best_child() {
+                Ok((next_state, mv)) => {
+                    println!("Playing best move: {:?}", mv.to_san(&initial_state, &next_state, &next_state.calc_legal_moves()));
+                    next_state.board.print();
+                }
+                Err(e) => {
+                    println!("Error: {}", e);
+                    break;
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn test_progressive_widening() {
+        let evaluator = MaterialEvaluator {};
+        let widening = ProgressiveWidening { min_children: 3, coefficient: 1., exponent: 0.5 };
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_uct_score, false)
+            .with_progressive_widening(widening);
+
+        mcts.run(1);
+        assert_eq!(mcts.root.borrow().children.len(), 3);
+        assert_eq!(mcts.root.borrow().pending_children.len(), 17);
+        assert_eq!(mcts.get_expansion_stats(), ExpansionStats { nodes_expanded: 1, moves_considered: 20, children_created: 3 });
+
+        mcts.run(99);
+        let root_visits = mcts.root.borrow().visits;
+        assert_eq!(mcts.root.borrow().children.len(), widening.get_max_children(root_visits - 1));
+
+        let stats = mcts.get_expansion_stats();
+        assert!(stats.get_num_deferred() > 0);
+        assert_eq!(stats.moves_considered, stats.children_created + stats.get_num_deferred());
+    }
+
+    #[test]
+    fn test_repetition_draws() {
+        let evaluator = MaterialEvaluator {};
+        let mut state = State::initial();
+        for uci in ["g1f3", "g8f6", "f3g1"] {
+            state.make_move(Move::from_uci(&state, uci).unwrap());
+        }
+        let get_repetition_child = |mcts: &MCTS| mcts.root.borrow().children.iter()
+            .find(|child| child.borrow().mv.unwrap().to_uci() == "f6g8")
+            .cloned()
+            .unwrap();
+
+        let mut mcts = MCTS::new(state.clone(), 1.5, &evaluator, &calc_uct_score, false).with_repetition_draws();
+        mcts.run(200);
+        let repetition_child = get_repetition_child(&mcts);
+        assert!(repetition_child.borrow().visits > 1);
+        assert!(!repetition_child.borrow().is_expanded);
+
+        let mut mcts = MCTS::new(state, 1.5, &evaluator, &calc_uct_score, false);
+        mcts.run(200);
+        assert!(get_repetition_child(&mcts).borrow().is_expanded);
+    }
+
+    #[test]
+    fn test_moves_left_utility() {
+        struct DecisiveEvaluator;
+        impl Evaluator for DecisiveEvaluator {
+            fn evaluate(&self, state: &State) -> Evaluation {
+                let mut evaluation = MaterialEvaluator {}.evaluate(state);
+                evaluation.value = 0.9;
+                evaluation
+            }
+
+            fn estimate_moves_left(&self, _state: &State) -> Option<f64> {
+                Some(10.)
+            }
+        }
+
+        let evaluator = DecisiveEvaluator;
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_uct_score, false);
+        mcts.run(1);
+        assert_eq!(mcts.root.borrow().value.abs(), 0.9);
+
+        let utility = MovesLeftUtility::default();
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_uct_score, false)
+            .with_moves_left_utility(utility);
+        mcts.run(1);
+        assert_eq!(mcts.root.borrow().value.abs(), utility.adjust_value(0.9, 10.));
+    }
+
+    #[test]
+    fn test_batched_evaluation() {
+        struct BatchRecordingEvaluator {
+            batch_sizes: RefCell<Vec<usize>>,
+        }
+        impl Evaluator for BatchRecordingEvaluator {
+            fn evaluate(&self, state: &State) -> Evaluation {
+                self.batch_sizes.borrow_mut().push(1);
+                MaterialEvaluator {}.evaluate(state)
+            }
+
+            fn evaluate_batch(&self, states: &[State]) -> Vec<Evaluation> {
+                self.batch_sizes.borrow_mut().push(states.len());
+                states.iter().map(|state| MaterialEvaluator {}.evaluate(state)).collect()
+            }
+        }
+
+        let evaluator = BatchRecordingEvaluator { batch_sizes: RefCell::new(Vec::new()) };
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_puct_score, false)
+            .with_evaluation_batch_size(8);
+        mcts.run(100);
+        assert_eq!(mcts.root.borrow().visits, 100);
+        let batch_sizes = evaluator.batch_sizes.borrow();
+        assert_eq!(batch_sizes.iter().sum::<usize>(), 100);
+        // the root is expanded on its own, and every leaf after it is a distinct child
+        assert_eq!(batch_sizes[0], 1);
+        assert!(batch_sizes.iter().all(|batch_size| *batch_size <= 8));
+        assert!(batch_sizes.len() < 20);
+        let num_visited_children = mcts.root.borrow().children.iter().filter(|child| child.borrow().visits > 0).count();
+        assert!(num_visited_children >= 8);
+    }
+
+    #[test]
+    fn test_run_with_budget() {
+        let evaluator = MaterialEvaluator {};
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_uct_score, false);
+        assert_eq!(mcts.run_with_budget(&SearchBudget::from_nodes(40)), 40);
+        assert_eq!(mcts.root.borrow().visits, 40);
+
+        // a budget that is used up before the search starts still searches once
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_uct_score, false);
+        let iterations = mcts.run_with_budget(&SearchBudget::from_movetime(std::time::Duration::ZERO));
+        assert_eq!(iterations, ITERATIONS_PER_BUDGET_CHECK);
+        assert!(mcts.get_best_child_by_visits().is_some());
+
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_uct_score, false);
+        let mut num_checks = 0;
+        let iterations = mcts.run_until(&SearchBudget::default(), |_| {
+            num_checks += 1;
+            num_checks == 3
+        });
+        assert_eq!(iterations, 3 * ITERATIONS_PER_BUDGET_CHECK);
+    }
+
+    #[test]
+    fn test_read_operations_while_nodes_are_borrowed() {
+        let evaluator = MaterialEvaluator {};
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_uct_score, false);
+        mcts.run(100);
+
+        let root = mcts.root.borrow();
+        let _child = root.children[0].borrow();
+        assert!(!format!("{}", mcts).is_empty());
+        let best_child = mcts.get_best_child_by_visits().unwrap();
+        assert!(mcts.get_best_child_by_score().is_some());
+        assert!(!root.get_principal_variation().is_empty());
+        assert_eq!(mcts.get_expansion_stats(), root.get_expansion_stats());
+        let best_move = best_child.borrow().mv.unwrap();
+        assert_eq!(mcts.explain_move(best_move).unwrap().mv, best_move);
+    }
+
+    #[test]
+    fn test_select_best_child_ignores_nan_scores() {
+        fn calc_nan_for_first_child(node: &MCTSNode, _parent_visits: u32, _exploration_param: f64) -> f64 {
+            if node.prior == 0.5 { f64::NAN } else { node.prior }
+        }
+        let evaluator = MaterialEvaluator {};
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_nan_for_first_child, false);
+        mcts.run(1);
+        let priors = [0.5, 0.1, 0.2];
+        for (child, prior) in mcts.root.borrow().children.iter().zip(priors.iter().chain(std::iter::repeat(&0.))) {
+            child.borrow_mut().prior = *prior;
+        }
+        assert_eq!(mcts.get_best_child_by_score().unwrap().borrow().prior, 0.2);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,198 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+use crate::mcts::progressive_widening::{ExpansionStats, ProgressiveWidening};
+use dunck_core::r#move::{Move, MoveFlag};
+use dunck_core::state::State;
+use dunck_core::utils::PieceType;
+
+/// Orders `policy` by prior, highest first, except that captures losing material by static exchange evaluation
+/// come after all other moves, so that the first children expanded are the most promising.
+pub fn order_policy(state: &State, policy: &mut [(Move, f64)]) {
+    let is_losing_capture = |mv: Move| {
+        let is_capture = mv.get_flag() == MoveFlag::EnPassant
+            || state.board.get_piece_type_at(mv.get_destination()) != PieceType::NoPieceType;
+        is_capture && state.calc_see(mv) < 0
+    };
+    let mut keyed_policy: Vec<(bool, (Move, f64))> = policy.iter().map(|entry| (is_losing_capture(entry.0), *entry)).collect();
+    keyed_policy.sort_by(|(a_is_losing, a), (b_is_losing, b)| a_is_losing.cmp(b_is_losing).then(b.1.partial_cmp(&a.1).unwrap()));
+    for (entry, (_, ordered_entry)) in policy.iter_mut().zip(keyed_policy) {
+        *entry = ordered_entry;
+    }
+}
+
+#[derive(Debug)]
+pub struct MCTSNode {
+    pub state_after_move: State,
+    pub mv: Option<Move>,
+    pub visits: u32,
+    pub value: f64,
+    pub prior: f64,
+    pub children: Vec<Rc<RefCell<MCTSNode>>>,
+    pub previous_node: Option<Rc<RefCell<MCTSNode>>>,
+    pub is_expanded: bool,
+    /// Moves not yet expanded into children because of progressive widening, in the order given by `order_policy`.
+    pub pending_children: Vec<(Move, f64)>,
+}
+
+impl MCTSNode {
+    pub fn new(mv: Option<Move>, previous_node: Option<Rc<RefCell<MCTSNode>>>, state_after_move: State) -> Self {
+        Self {
+            state_after_move,
+            mv,
+            visits: 0,
+            value: 0.,
+            prior: 0.,
+            children: Vec::new(),
+            previous_node,
+            is_expanded: false,
+            pending_children: Vec::new(),
+        }
+    }
+
+    pub fn flip_values(&mut self) {
+        self.value = -self.value;
+        for child in &self.children {
+            child.borrow_mut().flip_values();
+        }
+    }
+
+    /// Expands a child for every move in `policy`, in the order given by `order_policy`.
+    pub fn expand(&mut self, mut policy: Vec<(Move, f64)>, self_ptr: &Rc<RefCell<MCTSNode>>) {
+        self.is_expanded = true;
+        if policy.is_empty() {
+            self.state_after_move.assume_and_update_termination();
+        } else {
+            order_policy(&self.state_after_move, &mut policy);
+            for (legal_move, prior) in policy {
+                self.add_child(legal_move, prior, self_ptr);
+            }
+        }
+    }
+
+    /// Like `expand`, but only expands the first children in the order given by `order_policy`, as allowed by `widening`.
+    /// The rest are expanded later by `widen`.
+    pub fn expand_progressively(&mut self, mut policy: Vec<(Move, f64)>, self_ptr: &Rc<RefCell<MCTSNode>>, widening: &ProgressiveWidening) {
+        if policy.is_empty() {
+            self.expand(policy, self_ptr);
+            return;
+        }
+        self.is_expanded = true;
+        order_policy(&self.state_after_move, &mut policy);
+        self.pending_children = policy;
+        self.widen(self_ptr, widening);
+    }
+
+    /// Expands pending children, highest prior first, until the number of children reaches the limit
+    /// `widening` allows for the current visit count.
+    pub fn widen(&mut self, self_ptr: &Rc<RefCell<MCTSNode>>, widening: &ProgressiveWidening) {
+        let max_children = widening.get_max_children(self.visits);
+        let num_new_children = max_children.saturating_sub(self.children.len()).min(self.pending_children.len());
+        let new_children: Vec<(Move, f64)> = self.pending_children.drain(..num_new_children).collect();
+        for (legal_move, prior) in new_children {
+            self.add_child(legal_move, prior, self_ptr);
+        }
+    }
+
+    fn add_child(&mut self, legal_move: Move, prior: f64, self_ptr: &Rc<RefCell<MCTSNode>>) {
+        let mut new_state = self.state_after_move.clone();
+        new_state.make_move(legal_move);
+        new_state.claim_any_draw();
+        let new_node = MCTSNode {
+            state_after_move: new_state,
+            mv: Some(legal_move),
+            visits: 0,
+            value: 0.0,
+            prior,
+            children: Vec::new(),
+            previous_node: Some(self_ptr.clone()),
+            is_expanded: false,
+            pending_children: Vec::new(),
+        };
+        self.children.push(Rc::new(RefCell::new(new_node)));
+    }
+
+    /// Sums the expansion statistics of this node and all of its descendants.
+    pub fn get_expansion_stats(&self) -> ExpansionStats {
+        let mut stats = ExpansionStats::default();
+        if !self.children.is_empty() {
+            stats.nodes_expanded = 1;
+            stats.children_created = self.children.len();
+            stats.moves_considered = self.children.len() + self.pending_children.len();
+        }
+        for child in &self.children {
+            let child_stats = child.borrow().get_expansion_stats();
+            stats.nodes_expanded += child_stats.nodes_expanded;
+            stats.moves_considered += child_stats.moves_considered;
+            stats.children_created += child_stats.children_created;
+        }
+        stats
+    }
+
+    /// Returns the child with the highest score. Scores that are NaN rank below all others.
+    pub fn select_best_child(&self, calc_score: &'static dyn Fn(&MCTSNode, u32, f64) -> f64,  exploration_param: f64) -> Option<Rc<RefCell<MCTSNode>>> {
+        self.children.iter().max_by(|a, b| {
+            let a_score = calc_score(&*a.borrow(), self.visits, exploration_param);
+            let b_score = calc_score(&*b.borrow(), self.visits, exploration_param);
+            match (a_score.is_nan(), b_score.is_nan()) {
+                (false, false) => a_score.total_cmp(&b_score),
+                (a_is_nan, b_is_nan) => b_is_nan.cmp(&a_is_nan)
+            }
+        }).cloned()
+    }
+
+    pub fn backup(&mut self, value: f64) {
+        self.visits += 1;
+        self.value -= value;
+        if let Some(previous_node) = &self.previous_node {
+            previous_node.borrow_mut().backup(-1. * value);
+        }
+    }
+
+    fn metadata(&self) -> String {
+        format!("MCTSNode(move: {:?}, prior: {}, visits: {}, value: {})", self.mv, self.prior, self.visits, self.value)
+    }
+
+    fn fmt_helper(&self, depth: usize, depth_limit: usize) -> String {
+        let mut s = format!("{}{}\n", "| ".repeat(depth), self.metadata());
+        if depth < depth_limit {
+            for child in &self.children {
+                s += &child.borrow().fmt_helper(depth + 1, depth_limit);
+            }
+        }
+        s
+    }
+}
+
+impl Display for MCTSNode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.fmt_helper(0, 1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::progressive_widening::ProgressiveWidening;
+    use dunck_core::utils::Square;
+
+    #[test]
+    fn test_losing_captures_expanded_last() {
+        // Qxd5 loses the queen to exd5, Nxa5 wins a pawn
+        let state = State::from_fen("4k3/8/4p3/p2p4/8/1N6/8/3QK3 w - - 0 1").unwrap();
+        let queen_capture = Move::new_non_promotion(Square::D5, Square::D1, MoveFlag::NormalMove);
+        let knight_capture = Move::new_non_promotion(Square::A5, Square::B3, MoveFlag::NormalMove);
+        let policy: Vec<(Move, f64)> = state.calc_legal_moves().into_iter()
+            .map(|mv| (mv, if mv == queen_capture { 0.5 } else if mv == knight_capture { 0.1 } else { 0.01 }))
+            .collect();
+
+        let node = Rc::new(RefCell::new(MCTSNode::new(None, None, state)));
+        let widening = ProgressiveWidening { min_children: 1, coefficient: 0., exponent: 0. };
+        node.borrow_mut().expand_progressively(policy, &node, &widening);
+
+        let node = node.borrow();
+        assert_eq!(node.children[0].borrow().mv, Some(knight_capture));
+        assert_eq!(node.pending_children.last().unwrap().0, queen_capture);
+    }
+}
@@ -0,0 +1,7 @@
+pub mod mcts;
+pub mod mcts_node;
+pub mod progressive_widening;
+pub mod root_parallel;
+pub mod explanation;
+pub mod multi_pv;
+pub mod evaluation_queue;
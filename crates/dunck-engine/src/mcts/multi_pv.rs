@@ -0,0 +1,120 @@
+//! MultiPV: analysing several candidate root moves in a single search, sharing one node budget between them.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use crate::mcts::mcts::MCTS;
+use crate::mcts::mcts_node::MCTSNode;
+use dunck_core::r#move::Move;
+
+/// Guarantees the `num_lines` most visited root moves a share of the search,
+/// so that every reported line is searched deeply enough to be meaningful.
+/// Each candidate is searched at least until it has `min_visit_fraction / num_lines` of the root's visits;
+/// the rest of the budget goes to whichever moves the usual selection prefers, in proportion to how promising they are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MultiPv {
+    pub num_lines: usize,
+    pub min_visit_fraction: f64,
+}
+
+impl MultiPv {
+    pub fn new(num_lines: usize) -> MultiPv {
+        MultiPv { num_lines, ..Default::default() }
+    }
+
+    /// Returns the least visited candidate below its minimum share of `root`'s visits,
+    /// or None if every candidate has its share and the usual selection should be used.
+    pub fn select_starved_candidate(&self, root: &MCTSNode) -> Option<Rc<RefCell<MCTSNode>>> {
+        if self.num_lines <= 1 {
+            return None;
+        }
+        let min_visits = self.min_visit_fraction * root.visits as f64 / self.num_lines as f64;
+        get_candidates(root, self.num_lines).into_iter()
+            .filter(|child| (child.borrow().visits as f64) < min_visits)
+            .min_by_key(|child| child.borrow().visits)
+    }
+}
+
+impl Default for MultiPv {
+    fn default() -> Self {
+        MultiPv {
+            num_lines: 1,
+            min_visit_fraction: 0.5,
+        }
+    }
+}
+
+/// Returns up to `num_lines` children of `root`, most visited first, with ties broken by prior.
+fn get_candidates(root: &MCTSNode, num_lines: usize) -> Vec<Rc<RefCell<MCTSNode>>> {
+    let mut children = root.children.clone();
+    children.sort_by(|a, b| {
+        let (a, b) = (a.borrow(), b.borrow());
+        b.visits.cmp(&a.visits).then(b.prior.total_cmp(&a.prior))
+    });
+    children.truncate(num_lines);
+    children
+}
+
+/// One of the lines reported by a MultiPV search.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PvLine {
+    pub visits: u32,
+    /// The mean value of the line for the side to move at the root, or `None` if it was never visited.
+    pub q: Option<f64>,
+    /// The root move followed by its principal variation.
+    pub moves: Vec<Move>,
+}
+
+impl<'a> MCTS<'a> {
+    /// Returns the `num_lines` most visited root moves with their principal variations, best first,
+    /// all read from the same tree.
+    pub fn get_pv_lines(&self, num_lines: usize) -> Vec<PvLine> {
+        get_candidates(&self.root.borrow(), num_lines).iter().map(|child| {
+            let child = child.borrow();
+            let mut moves = vec![child.mv.unwrap()];
+            moves.extend(child.get_principal_variation());
+            PvLine {
+                visits: child.visits,
+                q: (child.visits > 0).then(|| child.value / child.visits as f64),
+                moves,
+            }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::mcts::mcts::calc_puct_score;
+    use dunck_core::state::State;
+
+    #[test]
+    fn test_multi_pv_shares_visits() {
+        let evaluator = MaterialEvaluator {};
+        let multi_pv = MultiPv::new(4);
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_puct_score, false)
+            .with_multi_pv(multi_pv);
+        mcts.run(400);
+
+        let lines = mcts.get_pv_lines(multi_pv.num_lines);
+        assert_eq!(lines.len(), 4);
+        // the candidates can change during the search, so allow some slack
+        let min_visits = 0.9 * multi_pv.min_visit_fraction * 400. / 4.;
+        for line in &lines {
+            assert!(line.visits as f64 >= min_visits, "{:?}", line);
+            assert!(line.q.is_some() && line.moves.len() > 1);
+        }
+        assert!(lines.windows(2).all(|pair| pair[0].visits >= pair[1].visits));
+        assert_eq!(lines[0].visits, mcts.get_best_child_by_visits().unwrap().borrow().visits);
+    }
+
+    #[test]
+    fn test_single_line_uses_usual_selection() {
+        let evaluator = MaterialEvaluator {};
+        let mut mcts = MCTS::new(State::initial(), 1.5, &evaluator, &calc_puct_score, false);
+        mcts.run(50);
+        assert!(MultiPv::new(1).select_starved_candidate(&mcts.root.borrow()).is_none());
+        assert_eq!(mcts.get_pv_lines(1).len(), 1);
+        assert_eq!(mcts.get_pv_lines(100).len(), 20);
+    }
+}
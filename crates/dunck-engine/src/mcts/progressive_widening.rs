@@ -0,0 +1,64 @@
+//! Progressive widening: expanding only the children with the highest priors, adding more as a node is visited.
+
+/// Limits the number of expanded children of a node to
+/// `max(min_children, floor(coefficient * visits ^ exponent))`.
+/// The remaining moves are kept, sorted by prior, and expanded as the limit grows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProgressiveWidening {
+    pub min_children: usize,
+    pub coefficient: f64,
+    pub exponent: f64,
+}
+
+impl ProgressiveWidening {
+    /// Returns the maximum number of children a node with `visits` visits may have expanded. Always at least 1.
+    pub fn get_max_children(&self, visits: u32) -> usize {
+        let grown = (self.coefficient * (visits as f64).powf(self.exponent)).floor() as usize;
+        grown.max(self.min_children).max(1)
+    }
+}
+
+impl Default for ProgressiveWidening {
+    fn default() -> Self {
+        ProgressiveWidening {
+            min_children: 4,
+            coefficient: 2.,
+            exponent: 0.5,
+        }
+    }
+}
+
+/// How much of the legal move lists seen by a search was actually expanded into nodes.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub struct ExpansionStats {
+    /// Number of expanded nodes with at least one legal move.
+    pub nodes_expanded: usize,
+    /// Number of legal moves in those nodes.
+    pub moves_considered: usize,
+    /// Number of those moves that were expanded into child nodes.
+    pub children_created: usize,
+}
+
+impl ExpansionStats {
+    /// Returns the number of moves that are still waiting to be expanded.
+    pub const fn get_num_deferred(&self) -> usize {
+        self.moves_considered - self.children_created
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_max_children() {
+        let widening = ProgressiveWidening { min_children: 3, coefficient: 1., exponent: 0.5 };
+        assert_eq!(widening.get_max_children(0), 3);
+        assert_eq!(widening.get_max_children(9), 3);
+        assert_eq!(widening.get_max_children(16), 4);
+        assert_eq!(widening.get_max_children(100), 10);
+
+        let widening = ProgressiveWidening { min_children: 0, coefficient: 0., exponent: 1. };
+        assert_eq!(widening.get_max_children(1000), 1);
+    }
+}
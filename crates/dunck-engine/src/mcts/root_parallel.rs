@@ -0,0 +1,119 @@
+//! Root-parallel MCTS: independent trees searched on separate threads, with root visit counts merged at the end.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::thread;
+use crate::evaluation::Evaluator;
+use crate::mcts::mcts::MCTS;
+use crate::mcts::mcts_node::MCTSNode;
+use crate::worker::EngineError;
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+
+/// Runs `num_trees` independent searches of `iterations_per_tree` iterations each from `state`,
+/// one per thread, and returns the root visit counts summed over all trees, sorted by most visits first.
+/// Each thread builds its own evaluator via `make_evaluator`, which receives the index of its tree to use as a seed.
+/// Since `State` cannot be shared across threads, each tree starts from the FEN of `state`,
+/// so repetitions of positions played before `state` are not seen by the search.
+/// If any thread panics, the other trees are still finished, and the first panic is returned as an error.
+pub fn run_root_parallel<E, F>(
+    state: &State,
+    num_trees: usize,
+    iterations_per_tree: usize,
+    exploration_param: f64,
+    calc_node_score: &'static (dyn Fn(&MCTSNode, u32, f64) -> f64 + Sync),
+    make_evaluator: F
+) -> Result<Vec<(Move, u32)>, EngineError>
+where
+    E: Evaluator,
+    F: Fn(u64) -> E + Sync
+{
+    let fen = state.to_fen();
+    let make_evaluator = &make_evaluator;
+
+    let tree_visit_counts: Result<Vec<Vec<(Move, u32)>>, EngineError> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..num_trees).map(|tree_index| {
+            let fen = fen.clone();
+            scope.spawn(move || {
+                let evaluator = make_evaluator(tree_index as u64);
+                let state = State::from_fen(&fen).unwrap();
+                let mut mcts = MCTS::new(state, exploration_param, &evaluator, calc_node_score, false);
+                mcts.run(iterations_per_tree);
+                let root = mcts.root.borrow();
+                root.children.iter().map(|child| {
+                    let child = child.borrow();
+                    (child.mv.unwrap(), child.visits)
+                }).collect()
+            })
+        }).collect();
+        // every handle is joined before the first error is returned, so the scope does not panic again
+        let results: Vec<_> = handles.into_iter().map(|handle| handle.join().map_err(EngineError::from_panic)).collect();
+        results.into_iter().collect()
+    });
+
+    let mut merged_visit_counts: HashMap<Move, u32> = HashMap::new();
+    for visit_counts in tree_visit_counts? {
+        for (mv, visits) in visit_counts {
+            *merged_visit_counts.entry(mv).or_insert(0) += visits;
+        }
+    }
+
+    let mut merged_visit_counts: Vec<(Move, u32)> = merged_visit_counts.into_iter().collect();
+    merged_visit_counts.sort_by_key(|&(_, visits)| Reverse(visits));
+    Ok(merged_visit_counts)
+}
+
+/// Runs a root-parallel search (see `run_root_parallel`) and returns the move with the most merged visits,
+/// or None if `state` has no legal moves.
+pub fn get_best_move_root_parallel<E, F>(
+    state: &State,
+    num_trees: usize,
+    iterations_per_tree: usize,
+    exploration_param: f64,
+    calc_node_score: &'static (dyn Fn(&MCTSNode, u32, f64) -> f64 + Sync),
+    make_evaluator: F
+) -> Result<Option<Move>, EngineError>
+where
+    E: Evaluator,
+    F: Fn(u64) -> E + Sync
+{
+    let visit_counts = run_root_parallel(state, num_trees, iterations_per_tree, exploration_param, calc_node_score, make_evaluator)?;
+    Ok(visit_counts.first().map(|(mv, _)| *mv))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::mcts::mcts::calc_uct_score;
+
+    #[test]
+    fn test_run_root_parallel() {
+        let state = State::initial();
+        let num_trees = 3;
+        let iterations_per_tree = 50;
+        let visit_counts = run_root_parallel(
+            &state, num_trees, iterations_per_tree, 1.5, &calc_uct_score, |_| MaterialEvaluator {}
+        ).unwrap();
+
+        let legal_moves = state.calc_legal_moves();
+        assert_eq!(visit_counts.len(), legal_moves.len());
+        assert!(visit_counts.iter().all(|(mv, _)| legal_moves.contains(mv)));
+        assert!(visit_counts.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+
+        // the first iteration of each tree only expands the root
+        let total_visits: u32 = visit_counts.iter().map(|(_, visits)| visits).sum();
+        assert_eq!(total_visits as usize, num_trees * (iterations_per_tree - 1));
+    }
+
+    #[test]
+    fn test_panicking_tree() {
+        let result = get_best_move_root_parallel(&State::initial(), 3, 20, 1.5, &calc_uct_score, |tree_index| {
+            if tree_index == 1 {
+                panic!("could not load weights");
+            }
+            MaterialEvaluator {}
+        });
+        assert_eq!(result, Err(EngineError::WorkerPanicked("could not load weights".to_string())));
+    }
+}
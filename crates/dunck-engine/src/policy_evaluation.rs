@@ -0,0 +1,183 @@
+//! Measures how often a model's policy agrees with the moves humans played, as a quick proxy of
+//! model quality that needs no matches to be played.
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use crate::dataset::{CurriculumConfig, GamePhase};
+use crate::evaluation::Evaluator;
+use dunck_core::pgn::PgnStateTree;
+use dunck_core::utils::Color;
+
+/// How many human moves were among the model's most likely moves.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PolicyMatchStats {
+    pub num_positions: u64,
+    pub num_top_1: u64,
+    pub num_top_3: u64,
+}
+
+impl PolicyMatchStats {
+    pub fn get_top_1_rate(&self) -> f64 {
+        if self.num_positions == 0 { 0. } else { self.num_top_1 as f64 / self.num_positions as f64 }
+    }
+
+    pub fn get_top_3_rate(&self) -> f64 {
+        if self.num_positions == 0 { 0. } else { self.num_top_3 as f64 / self.num_positions as f64 }
+    }
+
+    fn add(&mut self, other: &PolicyMatchStats) {
+        self.num_positions += other.num_positions;
+        self.num_top_1 += other.num_top_1;
+        self.num_top_3 += other.num_top_3;
+    }
+}
+
+/// Policy match statistics over a corpus of games, broken down by the Elo of the player to move
+/// and by game phase. Positions whose player has no Elo tag are in the `None` bucket.
+#[derive(Debug, Clone)]
+pub struct PolicyMatchReport {
+    pub elo_bucket_width: u32,
+    /// Decides which game phase each position belongs to.
+    pub phases: CurriculumConfig,
+    pub stats: BTreeMap<(Option<u32>, GamePhase), PolicyMatchStats>,
+}
+
+impl PolicyMatchReport {
+    pub fn new(elo_bucket_width: u32) -> PolicyMatchReport {
+        PolicyMatchReport {
+            elo_bucket_width,
+            phases: CurriculumConfig::default(),
+            stats: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the lowest Elo of the bucket `elo` falls into.
+    pub fn get_elo_bucket(&self, elo: u32) -> u32 {
+        elo - elo % self.elo_bucket_width
+    }
+
+    /// Evaluates every position on the main line of `tree` that has a move played from it.
+    /// The human move only counts as top-k if fewer than k other moves have at least its prior,
+    /// so ties count against the model.
+    pub fn add_game(&mut self, evaluator: &dyn Evaluator, tree: &PgnStateTree) {
+        let get_elo = |tag: &str| tree.get_tag(tag).and_then(|elo| elo.parse::<u32>().ok());
+        let elos = [get_elo("WhiteElo"), get_elo("BlackElo")];
+
+        let mut node = tree.head.clone();
+        loop {
+            let next_node = node.borrow().next_main_node();
+            let Some(next_node) = next_node else { break };
+            let state = node.borrow().state_after_move.clone();
+            let human_move = next_node.borrow().move_and_san_and_previous_node.as_ref().unwrap().0;
+
+            let policy = evaluator.evaluate(&state).policy;
+            let human_prior = policy.iter().find(|(mv, _)| *mv == human_move).map_or(0., |(_, prior)| *prior);
+            let rank = policy.iter().filter(|(mv, prior)| *mv != human_move && *prior >= human_prior).count();
+
+            let elo_bucket = elos[(state.side_to_move == Color::Black) as usize].map(|elo| self.get_elo_bucket(elo));
+            let stats = self.stats.entry((elo_bucket, self.phases.get_game_phase(&state))).or_default();
+            stats.num_positions += 1;
+            stats.num_top_1 += (rank < 1) as u64;
+            stats.num_top_3 += (rank < 3) as u64;
+
+            node = next_node;
+        }
+    }
+
+    pub fn get_overall_stats(&self) -> PolicyMatchStats {
+        let mut overall = PolicyMatchStats::default();
+        for stats in self.stats.values() {
+            overall.add(stats);
+        }
+        overall
+    }
+
+    pub fn get_stats_by_elo_bucket(&self) -> BTreeMap<Option<u32>, PolicyMatchStats> {
+        let mut by_elo_bucket = BTreeMap::new();
+        for ((elo_bucket, _), stats) in self.stats.iter() {
+            by_elo_bucket.entry(*elo_bucket).or_insert_with(PolicyMatchStats::default).add(stats);
+        }
+        by_elo_bucket
+    }
+
+    pub fn get_stats_by_phase(&self) -> BTreeMap<GamePhase, PolicyMatchStats> {
+        let mut by_phase = BTreeMap::new();
+        for ((_, phase), stats) in self.stats.iter() {
+            by_phase.entry(*phase).or_insert_with(PolicyMatchStats::default).add(stats);
+        }
+        by_phase
+    }
+}
+
+impl Display for PolicyMatchReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let write_row = |f: &mut Formatter<'_>, label: &str, stats: &PolicyMatchStats| {
+            writeln!(
+                f, "{:<16}{:>10}{:>9.1}%{:>9.1}%",
+                label, stats.num_positions, stats.get_top_1_rate() * 100., stats.get_top_3_rate() * 100.
+            )
+        };
+
+        writeln!(f, "{:<16}{:>10}{:>10}{:>10}", "", "Positions", "Top-1", "Top-3")?;
+        write_row(f, "Overall", &self.get_overall_stats())?;
+        for (elo_bucket, stats) in self.get_stats_by_elo_bucket() {
+            let label = match elo_bucket {
+                Some(elo_bucket) => format!("Elo {}-{}", elo_bucket, elo_bucket + self.elo_bucket_width - 1),
+                None => "Elo unknown".to_string(),
+            };
+            write_row(f, &label, &stats)?;
+        }
+        for (phase, stats) in self.get_stats_by_phase() {
+            write_row(f, &format!("{:?}", phase), &stats)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use super::*;
+    use crate::evaluation::Evaluation;
+    use dunck_core::pgn::{extract_tags, tokenize_pgn};
+    use dunck_core::state::State;
+    use dunck_core::utils::Square;
+
+    /// Only likes moves to e4 and e5.
+    struct CenterEvaluator;
+
+    impl Evaluator for CenterEvaluator {
+        fn evaluate(&self, state: &State) -> Evaluation {
+            let policy = state.calc_legal_moves().into_iter().map(|mv| {
+                let destination = mv.get_destination();
+                (mv, if destination == Square::E4 || destination == Square::E5 { 1. } else { 0. })
+            }).collect();
+            Evaluation { policy, value: 0. }
+        }
+    }
+
+    fn parse_game(pgn: &str) -> PgnStateTree {
+        let mut tree = PgnStateTree::from_str(pgn).unwrap();
+        tree.tags = extract_tags(&tokenize_pgn(pgn).unwrap()).unwrap();
+        tree
+    }
+
+    #[test]
+    fn test_policy_match_report() {
+        let mut report = PolicyMatchReport::new(200);
+        report.add_game(&CenterEvaluator, &parse_game("[WhiteElo \"2450\"]\n[BlackElo \"1800\"]\n1. e4 e5 2. Nf3 Nc6"));
+        report.add_game(&CenterEvaluator, &parse_game("1. d4"));
+
+        assert_eq!(report.get_overall_stats(), PolicyMatchStats { num_positions: 5, num_top_1: 2, num_top_3: 2 });
+
+        let by_elo_bucket = report.get_stats_by_elo_bucket();
+        assert_eq!(by_elo_bucket[&Some(2400)], PolicyMatchStats { num_positions: 2, num_top_1: 1, num_top_3: 1 });
+        assert_eq!(by_elo_bucket[&Some(1800)], PolicyMatchStats { num_positions: 2, num_top_1: 1, num_top_3: 1 });
+        assert_eq!(by_elo_bucket[&None], PolicyMatchStats { num_positions: 1, num_top_1: 0, num_top_3: 0 });
+        assert_eq!(report.get_stats_by_phase().keys().collect::<Vec<_>>(), [&GamePhase::Opening]);
+
+        let display = report.to_string();
+        assert!(display.contains("Elo 2400-2599"));
+        assert!(display.contains("Elo unknown"));
+    }
+}
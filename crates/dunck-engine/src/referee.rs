@@ -0,0 +1,642 @@
+//! A referee for matches between external UCI engines. The engines only ever see the moves played so far;
+//! legality, game endings, clocks and adjudication are all decided here, by dunck's own rules.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use crate::gating::ArenaResult;
+use crate::time_manager::Clock;
+use crate::game::{Game, GameOverReason};
+use dunck_core::game_record::GameRecord;
+use dunck_core::r#move::Move;
+use dunck_core::state::{State, INITIAL_FEN};
+use crate::time_control::TimeControl;
+use dunck_core::utils::Color;
+
+/// How long an engine may take to answer `uci` or `isready`.
+pub const ENGINE_READY_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long an engine that ran out of time is given to answer `stop`, before the next game.
+pub const ENGINE_STOP_TIMEOUT: Duration = Duration::from_secs(1);
+/// The score reported for a forced mate, in centipawns, less the number of moves to mate.
+pub const MATE_SCORE: i32 = 100_000;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RefereeError {
+    /// The engine process could not be started or written to, or it exited.
+    Io(String),
+    /// The engine did not answer in time.
+    Timeout(String),
+    /// The engine answered with something the referee does not understand.
+    Protocol(String),
+}
+
+impl Display for RefereeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            RefereeError::Io(message) => write!(f, "Engine I/O error: {}", message),
+            RefereeError::Timeout(message) => write!(f, "Engine timed out: {}", message),
+            RefereeError::Protocol(message) => write!(f, "Engine protocol error: {}", message),
+        }
+    }
+}
+
+impl std::error::Error for RefereeError {}
+
+/// A move chosen by an engine, in UCI notation, with the last score it reported while searching,
+/// in centipawns from its own point of view.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EngineMove {
+    pub mv: String,
+    pub score: Option<i32>,
+}
+
+/// One side of a refereed game, e.g. an external engine.
+pub trait MatchPlayer {
+    fn get_name(&self) -> &str;
+
+    /// Prepares for a new game.
+    fn new_game(&mut self) -> Result<(), RefereeError>;
+
+    /// Returns the move to play after `moves`, in UCI notation, from `initial_fen`,
+    /// with `clocks` holding each side's clock, indexed by `Color`.
+    /// Gives up with `RefereeError::Timeout` if no move is chosen within `timeout`.
+    fn go(
+        &mut self,
+        initial_fen: &str,
+        moves: &[String],
+        clocks: &[Clock; 2],
+        timeout: Duration
+    ) -> Result<EngineMove, RefereeError>;
+}
+
+/// An external engine, run as a child process speaking UCI on its standard input and output.
+pub struct UciProcess {
+    name: String,
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+}
+
+impl UciProcess {
+    /// Starts `command`, split on whitespace into the program and its arguments, and waits for it to be ready.
+    /// The engine is named after its `id name`, or after the command if it does not give one.
+    pub fn start(command: &str) -> Result<UciProcess, RefereeError> {
+        let mut parts = command.split_whitespace();
+        let program = parts.next().ok_or_else(|| RefereeError::Io("Empty engine command".to_string()))?;
+        let mut child = Command::new(program)
+            .args(parts)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| RefereeError::Io(format!("Failed to start {}: {}", command, e)))?;
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+
+        // a reader thread lets answers be awaited with a timeout
+        let (sender, lines) = channel();
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut process = UciProcess { name: command.to_string(), child, stdin, lines };
+        process.send("uci")?;
+        let mut name = None;
+        process.read_until(ENGINE_READY_TIMEOUT, |line| {
+            if let Some(id_name) = line.strip_prefix("id name ") {
+                name = Some(id_name.trim().to_string());
+            }
+            line.trim() == "uciok"
+        })?;
+        if let Some(name) = name {
+            process.name = name;
+        }
+        process.wait_until_ready()?;
+        Ok(process)
+    }
+
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), RefereeError> {
+        self.send(&format!("setoption name {} value {}", name, value))?;
+        self.wait_until_ready()
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), RefereeError> {
+        writeln!(self.stdin, "{}", line)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|e| RefereeError::Io(format!("Failed to write to {}: {}", self.name, e)))
+    }
+
+    fn wait_until_ready(&mut self) -> Result<(), RefereeError> {
+        self.send("isready")?;
+        self.read_until(ENGINE_READY_TIMEOUT, |line| line.trim() == "readyok")?;
+        Ok(())
+    }
+
+    /// Reads lines until `is_done` accepts one, which is returned, or until `timeout` passes.
+    fn read_until(&mut self, timeout: Duration, mut is_done: impl FnMut(&str) -> bool) -> Result<String, RefereeError> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            match self.lines.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+                Ok(line) if is_done(&line) => return Ok(line),
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => return Err(RefereeError::Timeout(format!("{} did not answer within {:?}", self.name, timeout))),
+                Err(RecvTimeoutError::Disconnected) => return Err(RefereeError::Io(format!("{} exited", self.name)))
+            }
+        }
+    }
+}
+
+impl MatchPlayer for UciProcess {
+    fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    fn new_game(&mut self) -> Result<(), RefereeError> {
+        self.send("ucinewgame")?;
+        self.wait_until_ready()
+    }
+
+    fn go(
+        &mut self,
+        initial_fen: &str,
+        moves: &[String],
+        clocks: &[Clock; 2],
+        timeout: Duration
+    ) -> Result<EngineMove, RefereeError> {
+        let mut position = if initial_fen == INITIAL_FEN { "position startpos".to_string() } else { format!("position fen {}", initial_fen) };
+        if !moves.is_empty() {
+            position += &format!(" moves {}", moves.join(" "));
+        }
+        self.send(&position)?;
+        let [white_clock, black_clock] = clocks;
+        let mut go = format!(
+            "go wtime {} btime {} winc {} binc {}",
+            white_clock.remaining.as_millis(), black_clock.remaining.as_millis(), white_clock.increment.as_millis(), black_clock.increment.as_millis()
+        );
+        let black_started = initial_fen.split_whitespace().nth(1) == Some("b");
+        let side_to_move = if black_started == (moves.len() % 2 == 1) { Color::White } else { Color::Black };
+        if let Some(moves_to_go) = clocks[side_to_move as usize].moves_to_go {
+            go += &format!(" movestogo {}", moves_to_go);
+        }
+        self.send(&go)?;
+
+        let mut score = None;
+        let answer = self.read_until(timeout, |line| {
+            if line.starts_with("info") {
+                score = parse_info_score(line).or(score);
+            }
+            line.starts_with("bestmove")
+        });
+        let line = match answer {
+            Ok(line) => line,
+            Err(RefereeError::Timeout(message)) => {
+                // keep the engine in sync for the next game, if it is still responsive
+                let _ = self.send("stop").and_then(|_| self.read_until(ENGINE_STOP_TIMEOUT, |line| line.starts_with("bestmove")));
+                return Err(RefereeError::Timeout(message));
+            },
+            Err(e) => return Err(e)
+        };
+        let mv = line.split_whitespace().nth(1)
+            .ok_or_else(|| RefereeError::Protocol(format!("{} sent bestmove without a move", self.name)))?;
+        Ok(EngineMove { mv: mv.to_string(), score })
+    }
+}
+
+impl Drop for UciProcess {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let deadline = Instant::now() + ENGINE_STOP_TIMEOUT;
+        while Instant::now() < deadline {
+            if !matches!(self.child.try_wait(), Ok(None)) {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Returns the score of a UCI `info` line in centipawns, with mates as `MATE_SCORE` less the number of moves,
+/// or None if it has no score.
+pub fn parse_info_score(line: &str) -> Option<i32> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let index = tokens.iter().position(|token| *token == "score")?;
+    let value: i32 = tokens.get(index + 2)?.parse().ok()?;
+    match *tokens.get(index + 1)? {
+        "cp" => Some(value),
+        "mate" if value >= 0 => Some(MATE_SCORE - value),
+        "mate" => Some(-MATE_SCORE - value),
+        _ => None
+    }
+}
+
+/// Adjudicates a game as won once, for `num_moves` moves in a row, both engines have reported scores
+/// at least `score` centipawns in favor of the same side.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResignRule {
+    pub score: i32,
+    pub num_moves: usize,
+}
+
+impl Default for ResignRule {
+    fn default() -> Self {
+        ResignRule { score: 600, num_moves: 4 }
+    }
+}
+
+/// Adjudicates a game as drawn once it has reached `min_plies` halfmoves and, for `num_moves` moves in a row,
+/// both engines have reported scores within `score` centipawns of zero.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawRule {
+    pub min_plies: usize,
+    pub score: i32,
+    pub num_moves: usize,
+}
+
+impl Default for DrawRule {
+    fn default() -> Self {
+        DrawRule { min_plies: 80, score: 10, num_moves: 8 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefereeConfig {
+    pub time_control: TimeControl,
+    /// How far past its remaining time an engine may go before losing on time, to allow for process overhead.
+    pub time_margin: Duration,
+    /// Games that reach this many halfmoves are adjudicated as draws.
+    pub max_plies: usize,
+    pub resign_rule: Option<ResignRule>,
+    pub draw_rule: Option<DrawRule>,
+}
+
+impl Default for RefereeConfig {
+    fn default() -> Self {
+        RefereeConfig {
+            time_control: TimeControl::new(Duration::from_secs(10), Duration::from_millis(100)),
+            time_margin: Duration::from_millis(50),
+            max_plies: 400,
+            resign_rule: None,
+            draw_rule: None,
+        }
+    }
+}
+
+/// How a refereed game ended.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GameEnd {
+    /// The game ended by the rules, including a loss on time.
+    GameOver(GameOverReason),
+    /// `color` tried to play `mv`, which is not legal or not a move at all.
+    IllegalMove { color: Color, mv: String },
+    /// The engine playing `color` stopped working, e.g. by crashing or by not following the protocol.
+    EngineFailure { color: Color, error: String },
+    /// Both engines agreed, by the resign rule, that `Color` is winning.
+    ResignAdjudication(Color),
+    /// Both engines agreed, by the draw rule, that the game is drawn.
+    DrawAdjudication,
+    /// The game reached the maximum number of halfmoves.
+    MaxPlies,
+}
+
+impl Display for GameEnd {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            GameEnd::GameOver(reason) => write!(f, "{}", reason),
+            GameEnd::IllegalMove { color, mv } => write!(f, "{} played an illegal move: {}", color, mv),
+            GameEnd::EngineFailure { color, error } => write!(f, "{} forfeits: {}", color, error),
+            GameEnd::ResignAdjudication(winner) => write!(f, "Adjudicated as a win for {}", winner),
+            GameEnd::DrawAdjudication => write!(f, "Adjudicated as a draw"),
+            GameEnd::MaxPlies => write!(f, "Adjudicated as a draw after the maximum number of moves"),
+        }
+    }
+}
+
+/// A finished refereed game.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RefereeGame {
+    pub white: String,
+    pub black: String,
+    pub record: GameRecord,
+    pub time_control: TimeControl,
+    pub end: GameEnd,
+}
+
+impl RefereeGame {
+    /// Renders the game as PGN, with tags for the event, round, players and time control,
+    /// and the reason it ended as the result comment.
+    pub fn to_pgn(&self, event: &str, round: usize) -> Result<String, String> {
+        let tags = format!(
+            "[Event \"{}\"]\n[Round \"{}\"]\n[White \"{}\"]\n[Black \"{}\"]\n[TimeControl \"{}\"]\n",
+            event, round, self.white, self.black, self.time_control.to_pgn_tag()
+        );
+        Ok(tags + &self.record.to_pgn(Some(&self.end.to_string()))?)
+    }
+}
+
+/// Plays a game from `initial_state` between `white` and `black`, enforcing the clocks and adjudication rules of `config`.
+pub fn play_refereed_game(initial_state: State, white: &mut dyn MatchPlayer, black: &mut dyn MatchPlayer, config: &RefereeConfig) -> RefereeGame {
+    let white_name = white.get_name().to_string();
+    let black_name = black.get_name().to_string();
+    let initial_fen = initial_state.to_fen();
+    let mut game = Game::new(initial_state).with_time_control(config.time_control.clone());
+    let finish = |game: &Game, end: GameEnd, result: i8| {
+        let mut record = game.to_record();
+        record.result = result;
+        RefereeGame { white: white_name.clone(), black: black_name.clone(), record, time_control: config.time_control.clone(), end }
+    };
+    // forfeits are draws if the opponent has only its king left
+    let forfeit = |game: &Game, end: GameEnd, color: Color| {
+        let result = if game.get_state().board.count_color(color.flip()) == 1 { 0 } else if color == Color::White { -1 } else { 1 };
+        finish(game, end, result)
+    };
+
+    for color in [Color::White, Color::Black] {
+        let player: &mut dyn MatchPlayer = if color == Color::White { white } else { black };
+        if let Err(e) = player.new_game() {
+            return forfeit(&game, GameEnd::EngineFailure { color, error: e.to_string() }, color);
+        }
+    }
+
+    let mut ucis: Vec<String> = Vec::new();
+    // the score each engine reported for its moves, from white's point of view
+    let mut scores: Vec<Option<i32>> = Vec::new();
+    loop {
+        if let Some(result) = game.get_result() {
+            return finish(&game, GameEnd::GameOver(result.reason), result.result);
+        }
+        if let Some((end, result)) = adjudicate_by_scores(&scores, config) {
+            return finish(&game, end, result);
+        }
+        if ucis.len() >= config.max_plies {
+            return finish(&game, GameEnd::MaxPlies, 0);
+        }
+
+        let color = game.get_state().side_to_move;
+        let remaining = game.get_remaining_time(color).unwrap_or_default();
+        let clocks = [Color::White, Color::Black].map(|color| game.get_clock(color).expect("the game is timed"));
+        let timeout = remaining + config.time_margin;
+        let start_time = Instant::now();
+        let player: &mut dyn MatchPlayer = if color == Color::White { white } else { black };
+        let response = player.go(&initial_fen, &ucis, &clocks, timeout);
+        let elapsed = start_time.elapsed();
+        let engine_move = match response {
+            Ok(engine_move) if elapsed <= timeout => engine_move,
+            Ok(_) | Err(RefereeError::Timeout(_)) => {
+                game.flag(color).expect("the game is not over");
+                continue;
+            },
+            Err(e) => return forfeit(&game, GameEnd::EngineFailure { color, error: e.to_string() }, color)
+        };
+
+        let Ok(mv) = Move::from_uci(game.get_state(), &engine_move.mv) else {
+            return forfeit(&game, GameEnd::IllegalMove { color, mv: engine_move.mv }, color);
+        };
+        // the margin is only there for process overhead, so it is never charged against the clock
+        game.make_timed_move(mv, elapsed.min(remaining)).expect("the move is legal");
+        ucis.push(engine_move.mv);
+        scores.push(engine_move.score.map(|score| if color == Color::White { score } else { -score }));
+    }
+}
+
+/// Applies the resign and draw rules to the scores reported so far, from white's point of view,
+/// returning how the game ends and its result if either rule applies.
+fn adjudicate_by_scores(scores: &[Option<i32>], config: &RefereeConfig) -> Option<(GameEnd, i8)> {
+    let get_last_scores = |num_moves: usize| -> Option<Vec<i32>> {
+        let num_plies = 2 * num_moves;
+        if num_moves == 0 || scores.len() < num_plies {
+            return None;
+        }
+        scores[scores.len() - num_plies..].iter().copied().collect()
+    };
+
+    if let Some(rule) = config.resign_rule {
+        if let Some(last_scores) = get_last_scores(rule.num_moves) {
+            if last_scores.iter().all(|score| *score >= rule.score) {
+                return Some((GameEnd::ResignAdjudication(Color::White), 1));
+            }
+            if last_scores.iter().all(|score| *score <= -rule.score) {
+                return Some((GameEnd::ResignAdjudication(Color::Black), -1));
+            }
+        }
+    }
+    if let Some(rule) = config.draw_rule {
+        if scores.len() >= rule.min_plies {
+            if let Some(last_scores) = get_last_scores(rule.num_moves) {
+                if last_scores.iter().all(|score| score.abs() <= rule.score) {
+                    return Some((GameEnd::DrawAdjudication, 0));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Plays `num_games` games between `first` and `second`, alternating colors, with each opening in `openings`
+/// played once with either color, in order. Calls `on_game` with the index of each finished game.
+/// Returns the match result from `first`'s point of view.
+pub fn run_refereed_match(
+    first: &mut dyn MatchPlayer,
+    second: &mut dyn MatchPlayer,
+    openings: &[State],
+    num_games: usize,
+    config: &RefereeConfig,
+    mut on_game: impl FnMut(usize, &RefereeGame)
+) -> ArenaResult {
+    let mut result = ArenaResult::default();
+    for game_index in 0..num_games {
+        let initial_state = openings.get((game_index / 2) % openings.len().max(1)).cloned().unwrap_or_else(State::initial);
+        let first_is_white = game_index % 2 == 0;
+        let game = if first_is_white {
+            play_refereed_game(initial_state, first, second, config)
+        } else {
+            play_refereed_game(initial_state, second, first, config)
+        };
+
+        let first_result = if first_is_white { game.record.result } else { -game.record.result };
+        match first_result {
+            1 => result.wins += 1,
+            -1 => result.losses += 1,
+            _ => result.draws += 1
+        }
+        on_game(game_index, &game);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dunck_core::state::Termination;
+
+    /// Plays the given moves in order, then the first legal move, always reporting `score`.
+    struct ScriptedPlayer {
+        name: String,
+        moves: Vec<String>,
+        score: Option<i32>,
+        move_time: Duration,
+    }
+
+    impl ScriptedPlayer {
+        fn new(name: &str, moves: &[&str]) -> ScriptedPlayer {
+            ScriptedPlayer { name: name.to_string(), moves: moves.iter().map(|mv| mv.to_string()).collect(), score: None, move_time: Duration::ZERO }
+        }
+    }
+
+    impl MatchPlayer for ScriptedPlayer {
+        fn get_name(&self) -> &str {
+            &self.name
+        }
+
+        fn new_game(&mut self) -> Result<(), RefereeError> {
+            Ok(())
+        }
+
+        fn go(&mut self, initial_fen: &str, moves: &[String], _clocks: &[Clock; 2], _timeout: Duration) -> Result<EngineMove, RefereeError> {
+            thread::sleep(self.move_time);
+            let mv = if self.moves.is_empty() {
+                let mut state = State::from_fen(initial_fen).unwrap();
+                for uci in moves {
+                    let mv = Move::from_uci(&state, uci).unwrap();
+                    state.make_move(mv);
+                }
+                state.calc_legal_moves()[0].to_uci()
+            } else {
+                self.moves.remove(0)
+            };
+            Ok(EngineMove { mv, score: self.score })
+        }
+    }
+
+    #[test]
+    fn test_parse_info_score() {
+        assert_eq!(parse_info_score("info depth 12 score cp -35 nodes 1000 pv e2e4"), Some(-35));
+        assert_eq!(parse_info_score("info depth 20 score mate 3 pv d8h4"), Some(MATE_SCORE - 3));
+        assert_eq!(parse_info_score("info depth 20 score mate -2"), Some(-MATE_SCORE + 2));
+        assert_eq!(parse_info_score("info depth 20 nodes 5"), None);
+    }
+
+    #[test]
+    fn test_checkmate_is_recorded_in_pgn() {
+        let mut white = ScriptedPlayer::new("Fool", &["f2f3", "g2g4"]);
+        let mut black = ScriptedPlayer::new("Mater", &["e7e5", "d8h4"]);
+        let game = play_refereed_game(State::initial(), &mut white, &mut black, &RefereeConfig::default());
+        assert_eq!(game.end, GameEnd::GameOver(GameOverReason::Termination(Termination::Checkmate)));
+        assert_eq!(game.record.result, -1);
+        assert_eq!(game.record.moves.len(), 4);
+
+        let pgn = game.to_pgn("Test match", 1).unwrap();
+        assert!(pgn.starts_with("[Event \"Test match\"]\n[Round \"1\"]\n[White \"Fool\"]\n[Black \"Mater\"]\n[TimeControl \"10+0.1\"]\n[Result \"0-1\"]"));
+        assert!(pgn.ends_with("2. g4 Qh4# {Checkmate} 0-1"));
+    }
+
+    #[test]
+    fn test_forfeits() {
+        let mut white = ScriptedPlayer::new("white", &["e2e4"]);
+        let mut black = ScriptedPlayer::new("black", &["e7e4"]);
+        let game = play_refereed_game(State::initial(), &mut white, &mut black, &RefereeConfig::default());
+        assert_eq!(game.end, GameEnd::IllegalMove { color: Color::Black, mv: "e7e4".to_string() });
+        assert_eq!(game.record.result, 1);
+
+        let config = RefereeConfig {
+            time_control: TimeControl::new(Duration::from_millis(20), Duration::ZERO),
+            time_margin: Duration::ZERO,
+            ..Default::default()
+        };
+        let mut white = ScriptedPlayer::new("white", &[]);
+        white.move_time = Duration::from_millis(50);
+        let game = play_refereed_game(State::initial(), &mut white, &mut ScriptedPlayer::new("black", &[]), &config);
+        assert_eq!(game.end, GameEnd::GameOver(GameOverReason::FlagFell(Color::White)));
+        assert_eq!(game.record.result, -1);
+
+        // a flag against a lone king is a draw
+        let state = State::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        let mut white = ScriptedPlayer::new("white", &[]);
+        white.move_time = Duration::from_millis(50);
+        let game = play_refereed_game(state, &mut white, &mut ScriptedPlayer::new("black", &[]), &config);
+        assert_eq!(game.end, GameEnd::GameOver(GameOverReason::FlagFell(Color::White)));
+        assert_eq!(game.record.result, 0);
+    }
+
+    #[test]
+    fn test_adjudication() {
+        let config = RefereeConfig { resign_rule: Some(ResignRule::default()), ..Default::default() };
+        let mut white = ScriptedPlayer::new("white", &[]);
+        let mut black = ScriptedPlayer::new("black", &[]);
+        white.score = Some(700);
+        black.score = Some(-650);
+        let game = play_refereed_game(State::initial(), &mut white, &mut black, &config);
+        assert_eq!(game.end, GameEnd::ResignAdjudication(Color::White));
+        assert_eq!(game.record.result, 1);
+        assert_eq!(game.record.moves.len(), 8);
+
+        let config = RefereeConfig { draw_rule: Some(DrawRule { min_plies: 10, ..Default::default() }), ..Default::default() };
+        white.score = Some(5);
+        black.score = Some(-3);
+        let game = play_refereed_game(State::initial(), &mut white, &mut black, &config);
+        assert_eq!(game.end, GameEnd::DrawAdjudication);
+        assert_eq!(game.record.moves.len(), 16);
+
+        // the engines disagree, so the game goes on until the maximum number of moves
+        let config = RefereeConfig { resign_rule: Some(ResignRule::default()), max_plies: 30, ..Default::default() };
+        black.score = Some(650);
+        let game = play_refereed_game(State::initial(), &mut white, &mut black, &config);
+        assert_eq!(game.end, GameEnd::MaxPlies);
+        assert_eq!(game.record.result, 0);
+    }
+
+    #[test]
+    fn test_match_alternates_colors() {
+        let mut first = ScriptedPlayer::new("first", &[]);
+        let mut second = ScriptedPlayer::new("second", &[]);
+        let openings = [State::initial(), State::from_fen("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap()];
+        let config = RefereeConfig { max_plies: 6, ..Default::default() };
+
+        let mut games = Vec::new();
+        let result = run_refereed_match(&mut first, &mut second, &openings, 4, &config, |game_index, game| games.push((game_index, game.clone())));
+        assert_eq!(result, ArenaResult { wins: 0, draws: 4, losses: 0 });
+        let whites: Vec<&str> = games.iter().map(|(_, game)| game.white.as_str()).collect();
+        assert_eq!(whites, ["first", "second", "first", "second"]);
+        assert_eq!(games[2].1.record.initial_fen, openings[1].to_fen());
+        assert_eq!(games[3].1.record.initial_fen, openings[1].to_fen());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_uci_process() {
+        // a stand-in engine that always answers with a null move
+        let script = "while read line; do case $line in \
+            uci) echo 'id name Nullmover'; echo uciok;; \
+            isready) echo readyok;; \
+            go*) echo 'info depth 1 score cp 12'; echo 'bestmove 0000';; \
+            quit) exit;; \
+            esac; done";
+        let path = std::env::temp_dir().join(format!("dunck_referee_engine_{}.sh", std::process::id()));
+        std::fs::write(&path, script).unwrap();
+        let command = format!("sh {}", path.display());
+
+        let mut engine = UciProcess::start(&command).unwrap();
+        assert_eq!(engine.get_name(), "Nullmover");
+        engine.new_game().unwrap();
+        let clock = Clock { remaining: Duration::from_secs(1), increment: Duration::ZERO, moves_to_go: Some(40) };
+        let engine_move = engine.go(INITIAL_FEN, &[], &[clock; 2], Duration::from_secs(5)).unwrap();
+        assert_eq!(engine_move, EngineMove { mv: "0000".to_string(), score: Some(12) });
+
+        let mut opponent = ScriptedPlayer::new("opponent", &[]);
+        let game = play_refereed_game(State::initial(), &mut opponent, &mut engine, &RefereeConfig::default());
+        assert_eq!(game.end, GameEnd::IllegalMove { color: Color::Black, mv: "0000".to_string() });
+        drop(engine);
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(matches!(UciProcess::start("dunck-no-such-engine").err(), Some(RefereeError::Io(_))));
+    }
+}
@@ -0,0 +1,316 @@
+//! Diagnostics of the engine's internals: move generation, attack tables, hashing, the evaluator and the tablebase.
+//! The report is meant to be attached to bug reports, to rule out a broken build or model before digging into a search.
+
+use std::fmt::{Display, Formatter};
+use std::time::Instant;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use dunck_core::attacks::{calc_magic_checksum, find_magic_mismatch};
+use crate::evaluation::Evaluator;
+use crate::manifest::EngineManifest;
+use crate::tablebase::{TablebaseProber, Wdl};
+use dunck_core::perft::{perft, PERFT_POSITIONS};
+use dunck_core::state::State;
+
+/// Perft counts up to this many nodes are checked by default, which takes well under a second.
+pub const DEFAULT_SELF_CHECK_PERFT_NODES: u64 = 100_000;
+/// The number of random moves made and unmade from each perft position to check the Zobrist hashes.
+const NUM_ZOBRIST_ROUND_TRIP_PLIES: usize = 200;
+/// How far the policy of the evaluator may sum from 1.
+const POLICY_SUM_TOLERANCE: f64 = 1e-3;
+/// Positions with a known tablebase result, from the side to move's point of view.
+const TABLEBASE_POSITIONS: [(&str, Wdl); 3] = [
+    ("4k3/8/8/8/8/8/8/3QK3 w - - 0 1", Wdl::Win),
+    ("4k3/8/8/8/8/8/8/3QK3 b - - 0 1", Wdl::Loss),
+    ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", Wdl::Draw),
+];
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    Passed,
+    Failed,
+    /// The check needs something that was not given, e.g. a tablebase.
+    Skipped,
+}
+
+impl Display for CheckStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            CheckStatus::Passed => "PASS",
+            CheckStatus::Failed => "FAIL",
+            CheckStatus::Skipped => "SKIP",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct CheckResult {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    /// What was checked if the check passed, or what went wrong if it failed.
+    pub details: String,
+    pub elapsed_ms: u128,
+}
+
+/// The results of every check, with the manifest of the build they ran on.
+#[derive(Serialize, Debug, Clone)]
+pub struct SelfCheckReport {
+    pub manifest: EngineManifest,
+    pub checks: Vec<CheckResult>,
+}
+
+impl SelfCheckReport {
+    /// Returns whether no check failed. Skipped checks do not count as failures.
+    pub fn is_ok(&self) -> bool {
+        self.get_failures().next().is_none()
+    }
+
+    pub fn get_failures(&self) -> impl Iterator<Item = &CheckResult> {
+        self.checks.iter().filter(|check| check.status == CheckStatus::Failed)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("a self-check report is always serializable")
+    }
+}
+
+impl Display for SelfCheckReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "dunck {}, magic checksum {}, Zobrist keys version {}", self.manifest.crate_version, self.manifest.magic_checksum, self.manifest.zobrist_keys_version)?;
+        for check in self.checks.iter() {
+            writeln!(f, "[{}] {} ({} ms): {}", check.status, check.name, check.elapsed_ms, check.details)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs the self-check with what it is given. Checks of the evaluator and tablebase are skipped without them.
+pub struct SelfCheck<'a> {
+    pub evaluator: Option<&'a dyn Evaluator>,
+    pub tablebase: Option<&'a dyn TablebaseProber>,
+    /// Perft counts above this many nodes are not checked.
+    pub max_perft_nodes: u64,
+    /// The seed of the random moves of the Zobrist round trips.
+    pub seed: u64,
+    pub manifest: EngineManifest,
+}
+
+impl<'a> Default for SelfCheck<'a> {
+    fn default() -> Self {
+        SelfCheck {
+            evaluator: None,
+            tablebase: None,
+            max_perft_nodes: DEFAULT_SELF_CHECK_PERFT_NODES,
+            seed: 0,
+            manifest: EngineManifest::new(),
+        }
+    }
+}
+
+impl<'a> SelfCheck<'a> {
+    pub fn with_evaluator(mut self, evaluator: &'a dyn Evaluator) -> Self {
+        self.evaluator = Some(evaluator);
+        self
+    }
+
+    pub fn with_tablebase(mut self, tablebase: &'a dyn TablebaseProber) -> Self {
+        self.tablebase = Some(tablebase);
+        self
+    }
+
+    pub fn with_max_perft_nodes(mut self, max_perft_nodes: u64) -> Self {
+        self.max_perft_nodes = max_perft_nodes;
+        self
+    }
+
+    /// Records e.g. the model in the report, to identify what the checks ran on.
+    pub fn with_manifest(mut self, manifest: EngineManifest) -> Self {
+        self.manifest = manifest;
+        self
+    }
+
+    pub fn run(self) -> SelfCheckReport {
+        let checks = vec![
+            run_check("perft", || check_perft(self.max_perft_nodes)),
+            run_check("magic tables", check_magic_tables),
+            run_check("zobrist round trips", || check_zobrist_round_trips(self.seed)),
+            run_check("evaluator", || match self.evaluator {
+                Some(evaluator) => check_evaluator(evaluator),
+                None => (CheckStatus::Skipped, "No evaluator given".to_string()),
+            }),
+            run_check("tablebase", || match self.tablebase {
+                Some(tablebase) => check_tablebase(tablebase),
+                None => (CheckStatus::Skipped, "No tablebase given".to_string()),
+            }),
+        ];
+        SelfCheckReport { manifest: self.manifest, checks }
+    }
+}
+
+fn run_check(name: &'static str, check: impl FnOnce() -> (CheckStatus, String)) -> CheckResult {
+    let start_time = Instant::now();
+    let (status, details) = check();
+    CheckResult { name, status, details, elapsed_ms: start_time.elapsed().as_millis() }
+}
+
+fn to_result(error: Option<String>, passed_details: String) -> (CheckStatus, String) {
+    match error {
+        Some(error) => (CheckStatus::Failed, error),
+        None => (CheckStatus::Passed, passed_details),
+    }
+}
+
+/// Checks the perft counts of the well-known positions, up to `max_nodes` nodes each.
+fn check_perft(max_nodes: u64) -> (CheckStatus, String) {
+    let mut num_counts = 0;
+    for position in PERFT_POSITIONS.iter() {
+        let state = State::from_fen(position.fen).unwrap();
+        for (depth, expected) in (1..).zip(position.counts.iter()).take_while(|(_, expected)| **expected <= max_nodes) {
+            let actual = perft(&state, depth);
+            if actual != *expected {
+                let error = format!("{} at depth {}: {} nodes instead of {}", position.name, depth, actual, expected);
+                return (CheckStatus::Failed, error);
+            }
+            num_counts += 1;
+        }
+    }
+    (CheckStatus::Passed, format!("{} counts of {} positions match", num_counts, PERFT_POSITIONS.len()))
+}
+
+/// Checks the magic attack tables against attacks calculated ray by ray.
+fn check_magic_tables() -> (CheckStatus, String) {
+    let error = find_magic_mismatch()
+        .map(|(square, occupied_mask)| format!("Wrong attacks from {} with occupied mask {:016x}", square, occupied_mask));
+    to_result(error, format!("Checksum {:016x}", calc_magic_checksum()))
+}
+
+/// Makes and unmakes random moves from the perft positions, checking that the incrementally updated hashes
+/// match hashes calculated from scratch, and that unmaking a move restores the hashes from before it.
+fn check_zobrist_round_trips(seed: u64) -> (CheckStatus, String) {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut num_moves = 0;
+    for position in PERFT_POSITIONS.iter() {
+        let mut state = State::from_fen(position.fen).unwrap();
+        let initial_fen = state.to_fen();
+        let mut history = Vec::new();
+        for _ in 0..NUM_ZOBRIST_ROUND_TRIP_PLIES {
+            let moves = state.calc_legal_moves();
+            let should_unmake = !history.is_empty() && (moves.is_empty() || rng.gen_bool(0.3));
+            if should_unmake {
+                let (mv, hash_before) = history.pop().unwrap();
+                state.unmake_move(mv);
+                if state.get_zobrist_hash() != hash_before {
+                    return (CheckStatus::Failed, format!("Unmaking {} does not restore the hash of {}", mv.to_uci(), state.to_fen()));
+                }
+            } else if let Some(mv) = moves.choose(&mut rng) {
+                history.push((*mv, state.get_zobrist_hash()));
+                state.make_move(*mv);
+                num_moves += 1;
+            } else {
+                break;
+            }
+            if state.get_zobrist_hash() != state.calc_zobrist_hash() || !state.is_zobrist_consistent() {
+                return (CheckStatus::Failed, format!("The incremental hash of {} is wrong", state.to_fen()));
+            }
+        }
+        while let Some((mv, _)) = history.pop() {
+            state.unmake_move(mv);
+        }
+        if state.to_fen() != initial_fen {
+            return (CheckStatus::Failed, format!("Unmaking every move from {} ends at {}", initial_fen, state.to_fen()));
+        }
+    }
+    (CheckStatus::Passed, format!("{} moves made and unmade", num_moves))
+}
+
+/// Evaluates the perft positions, checking that the values are within [-1, 1]
+/// and that the policies are distributions over exactly the legal moves.
+fn check_evaluator(evaluator: &dyn Evaluator) -> (CheckStatus, String) {
+    for position in PERFT_POSITIONS.iter() {
+        let state = State::from_fen(position.fen).unwrap();
+        let legal_moves = state.calc_legal_moves();
+        let evaluation = evaluator.evaluate(&state);
+        let error = if !evaluation.value.is_finite() || evaluation.value.abs() > 1. {
+            Some(format!("Value {} is out of range", evaluation.value))
+        } else if evaluation.policy.len() != legal_moves.len() || evaluation.policy.iter().any(|(mv, _)| !legal_moves.contains(mv)) {
+            Some(format!("The policy covers {} moves, not the {} legal moves", evaluation.policy.len(), legal_moves.len()))
+        } else if evaluation.policy.iter().any(|(_, probability)| !probability.is_finite() || *probability < 0.) {
+            Some("The policy has a negative or non-finite probability".to_string())
+        } else if !legal_moves.is_empty() && (evaluation.policy.iter().map(|(_, probability)| probability).sum::<f64>() - 1.).abs() > POLICY_SUM_TOLERANCE {
+            Some("The policy does not sum to 1".to_string())
+        } else {
+            None
+        };
+        if let Some(error) = error {
+            return (CheckStatus::Failed, format!("{}: {}", position.name, error));
+        }
+    }
+    (CheckStatus::Passed, format!("{} positions evaluated", PERFT_POSITIONS.len()))
+}
+
+/// Probes positions with a known result.
+fn check_tablebase(tablebase: &dyn TablebaseProber) -> (CheckStatus, String) {
+    for (fen, expected) in TABLEBASE_POSITIONS {
+        let actual = tablebase.probe_wdl(&State::from_fen(fen).unwrap());
+        if actual != Some(expected) {
+            return (CheckStatus::Failed, format!("{} probes as {:?} instead of {:?}", fen, actual, expected));
+        }
+    }
+    (CheckStatus::Passed, format!("{} positions probed", TABLEBASE_POSITIONS.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluation::Evaluation;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use dunck_core::utils::Color;
+    use super::*;
+
+    struct KqkTablebase;
+
+    impl TablebaseProber for KqkTablebase {
+        fn probe_wdl(&self, state: &State) -> Option<Wdl> {
+            match state.get_material_signature().get_num_pieces() {
+                2 => Some(Wdl::Draw),
+                3 if state.side_to_move == Color::White => Some(Wdl::Win),
+                3 => Some(Wdl::Loss),
+                _ => None,
+            }
+        }
+
+        fn probe_dtz(&self, _state: &State) -> Option<i32> {
+            None
+        }
+    }
+
+    struct OutOfRangeEvaluator;
+
+    impl Evaluator for OutOfRangeEvaluator {
+        fn evaluate(&self, _state: &State) -> Evaluation {
+            Evaluation { policy: Vec::new(), value: 2. }
+        }
+    }
+
+    #[test]
+    fn test_self_check() {
+        let report = SelfCheck::default().with_max_perft_nodes(2000).run();
+        assert!(report.is_ok(), "{}", report);
+        let statuses: Vec<CheckStatus> = report.checks.iter().map(|check| check.status).collect();
+        assert_eq!(statuses, [CheckStatus::Passed, CheckStatus::Passed, CheckStatus::Passed, CheckStatus::Skipped, CheckStatus::Skipped]);
+
+        let evaluator = MaterialEvaluator {};
+        let report = SelfCheck::default().with_max_perft_nodes(0).with_evaluator(&evaluator).with_tablebase(&KqkTablebase).run();
+        assert!(report.checks.iter().all(|check| check.status == CheckStatus::Passed), "{}", report);
+
+        let report = SelfCheck::default().with_max_perft_nodes(0).with_evaluator(&OutOfRangeEvaluator).run();
+        assert!(!report.is_ok());
+        assert_eq!(report.get_failures().map(|check| check.name).collect::<Vec<_>>(), ["evaluator"]);
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json()).unwrap();
+        assert_eq!(json["checks"][3]["status"], "failed");
+    }
+}
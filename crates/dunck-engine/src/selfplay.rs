@@ -0,0 +1,332 @@
+//! Self-play for AlphaZero-style training: games of MCTS against itself, with Dirichlet noise mixed into the root
+//! priors and early moves sampled by visit count, so that the games explore. Every position is recorded with the
+//! visit distribution of its search and the outcome of the game, which are the policy and value targets of training.
+//!
+//! Each game is written as a `GameRecord` in its binary format, followed by, for each move,
+//! the number of root moves (u8) and, for each of them, the move (u16) and its share of the visits (u16),
+//! where 65535 stands for all of them. All integers are big-endian.
+
+use std::io;
+use std::io::{Read, Write};
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::Rng;
+use rand_distr::Gamma;
+use crate::evaluation::{get_value_at_terminal_state, Evaluation, Evaluator};
+use crate::mcts::mcts::{calc_puct_score, MCTS};
+use crate::mcts::mcts_node::MCTSNode;
+use dunck_core::game_record::GameRecord;
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+use dunck_core::utils::Color;
+
+/// The scale of the visit shares in the binary format.
+const VISIT_SHARE_SCALE: f64 = u16::MAX as f64;
+
+#[derive(Clone)]
+pub struct SelfPlayConfig {
+    pub num_games: usize,
+    pub iterations_per_move: usize,
+    /// Games that reach this many halfmoves are recorded as draws.
+    pub max_game_depth: usize,
+    pub exploration_param: f64,
+    pub calc_node_score: &'static dyn Fn(&MCTSNode, u32, f64) -> f64,
+    /// The concentration of the Dirichlet noise. Smaller values concentrate the noise on fewer moves.
+    pub dirichlet_alpha: f64,
+    /// The weight of the noise in the root priors, from 0 for none to 1 for only noise.
+    pub dirichlet_epsilon: f64,
+    /// Moves are sampled with probabilities proportional to their visits raised to `1 / temperature`.
+    pub temperature: f64,
+    /// After this many halfmoves, the most visited move is always played.
+    pub temperature_plies: usize,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> Self {
+        SelfPlayConfig {
+            num_games: 1,
+            iterations_per_move: 800,
+            max_game_depth: 512,
+            exploration_param: 2.0,
+            calc_node_score: &calc_puct_score,
+            dirichlet_alpha: 0.3,
+            dirichlet_epsilon: 0.25,
+            temperature: 1.0,
+            temperature_plies: 30,
+        }
+    }
+}
+
+/// A self-play game with the visit distribution of the search behind every move.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelfPlayGame {
+    pub record: GameRecord,
+    /// For each move, the share of the root visits of every root move, summing to 1.
+    pub policies: Vec<Vec<(Move, f64)>>,
+}
+
+impl SelfPlayGame {
+    /// Writes the game in the binary format.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.policies.len() != self.record.moves.len() {
+            return Err(invalid_data(format!("{} policies for {} moves", self.policies.len(), self.record.moves.len())));
+        }
+        self.record.write_to(writer)?;
+        for policy in self.policies.iter() {
+            if policy.len() > u8::MAX as usize {
+                return Err(invalid_data(format!("Too many root moves: {}", policy.len())));
+            }
+            writer.write_all(&[policy.len() as u8])?;
+            for (mv, share) in policy {
+                writer.write_all(&mv.to_u16().to_be_bytes())?;
+                writer.write_all(&((share.clamp(0., 1.) * VISIT_SHARE_SCALE).round() as u16).to_be_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads a game in the binary format, or returns None if `reader` is already at its end.
+    /// The shares of each policy are normalized to sum to 1 again after rounding.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<SelfPlayGame>> {
+        let Some(record) = GameRecord::read_from(reader)? else {
+            return Ok(None);
+        };
+        let mut policies = Vec::with_capacity(record.moves.len());
+        for _ in 0..record.moves.len() {
+            let mut num_moves = [0; 1];
+            reader.read_exact(&mut num_moves)?;
+            let mut policy = Vec::with_capacity(num_moves[0] as usize);
+            for _ in 0..num_moves[0] {
+                let mut entry = [0; 4];
+                reader.read_exact(&mut entry)?;
+                let mv = Move::from_u16(u16::from_be_bytes([entry[0], entry[1]])).map_err(|e| invalid_data(e.to_string()))?;
+                policy.push((mv, u16::from_be_bytes([entry[2], entry[3]]) as f64));
+            }
+            let total: f64 = policy.iter().map(|(_, share)| share).sum();
+            if total > 0. {
+                policy.iter_mut().for_each(|(_, share)| *share /= total);
+            }
+            policies.push(policy);
+        }
+        Ok(Some(SelfPlayGame { record, policies }))
+    }
+
+    /// Replays the game and returns its positions, each labeled with the visit distribution of its search and
+    /// the outcome of the game from the side to move's point of view.
+    pub fn to_training_samples(&self) -> Result<Vec<(State, Evaluation)>, String> {
+        let mut state = State::from_fen(&self.record.initial_fen).map_err(|e| format!("Invalid initial FEN: {}", e))?;
+        let mut samples = Vec::with_capacity(self.record.moves.len());
+        for (i, (mv, policy)) in self.record.moves.iter().zip(self.policies.iter()).enumerate() {
+            if !state.calc_legal_moves().contains(mv) {
+                return Err(format!("Illegal move {} at halfmove {}", mv.to_uci(), i));
+            }
+            let value = match state.side_to_move {
+                Color::White => self.record.result as f64,
+                Color::Black => -self.record.result as f64,
+            };
+            samples.push((state.clone(), Evaluation { policy: policy.clone(), value }));
+            state.make_move(*mv);
+        }
+        Ok(samples)
+    }
+}
+
+/// Writes games back to back.
+pub fn write_selfplay_games<W: Write>(writer: &mut W, games: &[SelfPlayGame]) -> io::Result<()> {
+    games.iter().try_for_each(|game| game.write_to(writer))
+}
+
+/// Reads games written by `write_selfplay_games` until the end of `reader`.
+pub fn read_selfplay_games<R: Read>(reader: &mut R) -> io::Result<Vec<SelfPlayGame>> {
+    let mut games = Vec::new();
+    while let Some(game) = SelfPlayGame::read_from(reader)? {
+        games.push(game);
+    }
+    Ok(games)
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Samples a point of the symmetric Dirichlet distribution with `alpha` over `num_moves` moves.
+pub fn generate_dirichlet_noise<R: Rng>(num_moves: usize, alpha: f64, rng: &mut R) -> Vec<f64> {
+    let gamma = Gamma::new(alpha, 1.0).expect("Invalid alpha for Dirichlet");
+    let mut noise: Vec<f64> = (0..num_moves).map(|_| gamma.sample(rng)).collect();
+    let sum: f64 = noise.iter().sum();
+    if sum > 0. {
+        noise.iter_mut().for_each(|n| *n /= sum);
+    } else {
+        // every sample underflowed, which happens with a tiny alpha
+        noise.iter_mut().for_each(|n| *n = 1. / num_moves as f64);
+    }
+    noise
+}
+
+/// Mixes Dirichlet noise into the priors of the children of the root, which must already be expanded.
+pub fn add_root_noise<R: Rng>(mcts: &MCTS, alpha: f64, epsilon: f64, rng: &mut R) {
+    let root = mcts.root.borrow();
+    let noise = generate_dirichlet_noise(root.children.len(), alpha, rng);
+    for (child, noise) in root.children.iter().zip(noise) {
+        let mut child = child.borrow_mut();
+        child.prior = (1. - epsilon) * child.prior + epsilon * noise;
+    }
+}
+
+/// Picks a move with probability proportional to its visits raised to `1 / temperature`,
+/// or the most visited one if `temperature` is 0. Returns None if no move was visited.
+pub fn select_move_by_temperature<R: Rng>(visits: &[(Move, u32)], temperature: f64, rng: &mut R) -> Option<Move> {
+    let max_visits = visits.iter().map(|(_, visits)| *visits).max().filter(|max_visits| *max_visits > 0)?;
+    if temperature <= 0. {
+        return visits.iter().find(|(_, visits)| *visits == max_visits).map(|(mv, _)| *mv);
+    }
+    // relative to the most visited move, so that small temperatures do not overflow
+    let weights = visits.iter().map(|(_, visits)| (*visits as f64 / max_visits as f64).powf(1. / temperature));
+    let index = WeightedIndex::new(weights).ok()?.sample(rng);
+    Some(visits[index].0)
+}
+
+/// Plays a game of `evaluator` against itself from `initial_state`, searching each move from scratch.
+pub fn play_selfplay_game<R: Rng>(initial_state: State, evaluator: &dyn Evaluator, config: &SelfPlayConfig, rng: &mut R) -> SelfPlayGame {
+    let mut game = SelfPlayGame {
+        record: GameRecord {
+            initial_fen: initial_state.to_fen(),
+            moves: Vec::new(),
+            result: 0,
+            metadata: None,
+        },
+        policies: Vec::new(),
+    };
+    let mut state = initial_state;
+    for ply in 0..config.max_game_depth {
+        if state.termination.is_none() && state.calc_legal_moves().is_empty() {
+            state.assume_and_update_termination();
+        }
+        if state.termination.is_some() {
+            game.record.result = get_value_at_terminal_state(&state, Color::White) as i8;
+            return game;
+        }
+
+        let mut mcts = MCTS::new(state.clone(), config.exploration_param, evaluator, config.calc_node_score, false);
+        // the first iteration expands the root, so that noise can be mixed into the priors of its children
+        mcts.run(1);
+        add_root_noise(&mcts, config.dirichlet_alpha, config.dirichlet_epsilon, rng);
+        mcts.run(config.iterations_per_move.saturating_sub(1));
+
+        let visits: Vec<(Move, u32)> = mcts.root.borrow().children.iter()
+            .map(|child| {
+                let child = child.borrow();
+                (child.mv.unwrap(), child.visits)
+            })
+            .collect();
+        let temperature = if ply < config.temperature_plies { config.temperature } else { 0. };
+        let Some(mv) = select_move_by_temperature(&visits, temperature, rng) else {
+            return game;
+        };
+        let total_visits: u32 = visits.iter().map(|(_, visits)| visits).sum();
+        game.policies.push(visits.iter().map(|(mv, visits)| (*mv, *visits as f64 / total_visits as f64)).collect());
+        game.record.moves.push(mv);
+        state.make_move(mv);
+        // the search scores claimable draws as draws, so the game ends at them too
+        state.claim_any_draw();
+    }
+    game
+}
+
+/// Plays `config.num_games` games from the initial position, writing each to `writer` as soon as it is finished,
+/// and returns the number of positions written.
+pub fn run_selfplay<R: Rng, W: Write>(evaluator: &dyn Evaluator, config: &SelfPlayConfig, rng: &mut R, writer: &mut W) -> io::Result<usize> {
+    let mut num_positions = 0;
+    for _ in 0..config.num_games {
+        let game = play_selfplay_game(State::initial(), evaluator, config, rng);
+        game.write_to(writer)?;
+        num_positions += game.record.moves.len();
+    }
+    writer.flush()?;
+    Ok(num_positions)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use dunck_core::utils::Square;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use super::*;
+
+    /// Puts all of the prior on the g-file knights moving between their starting squares and f3 or f6.
+    struct KnightShuffleEvaluator;
+
+    impl Evaluator for KnightShuffleEvaluator {
+        fn evaluate(&self, state: &State) -> Evaluation {
+            let shuffles = [(Square::G1, Square::F3), (Square::F3, Square::G1), (Square::G8, Square::F6), (Square::F6, Square::G8)];
+            let policy = state.calc_legal_moves().into_iter()
+                .map(|mv| (mv, if shuffles.contains(&(mv.get_source(), mv.get_destination())) { 1. } else { 0. }))
+                .collect();
+            Evaluation { policy, value: 0. }
+        }
+    }
+
+    #[test]
+    fn test_dirichlet_noise() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let noise = generate_dirichlet_noise(20, 0.3, &mut rng);
+        assert_eq!(noise.len(), 20);
+        assert!(noise.iter().all(|n| *n >= 0.));
+        assert!((noise.iter().sum::<f64>() - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_select_move_by_temperature() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let moves = State::initial().calc_legal_moves();
+        let visits = [(moves[0], 10), (moves[1], 30), (moves[2], 0)];
+        assert_eq!(select_move_by_temperature(&visits, 0., &mut rng), Some(moves[1]));
+        assert_eq!(select_move_by_temperature(&visits[2..], 1., &mut rng), None);
+
+        let num_first = (0..1000).filter(|_| select_move_by_temperature(&visits, 1., &mut rng) == Some(moves[0])).count();
+        assert!((150..350).contains(&num_first), "{}", num_first);
+        assert!((0..100).all(|_| select_move_by_temperature(&visits, 1., &mut rng) != Some(moves[2])));
+    }
+
+    #[test]
+    fn test_selfplay() {
+        let config = SelfPlayConfig {
+            num_games: 2,
+            iterations_per_move: 20,
+            max_game_depth: 12,
+            ..SelfPlayConfig::default()
+        };
+        let mut bytes = Vec::new();
+        let num_positions = run_selfplay(&MaterialEvaluator {}, &config, &mut StdRng::seed_from_u64(0), &mut bytes).unwrap();
+
+        let games = read_selfplay_games(&mut Cursor::new(bytes)).unwrap();
+        assert_eq!(games.len(), 2);
+        assert_eq!(games.iter().map(|game| game.record.moves.len()).sum::<usize>(), num_positions);
+        for game in games {
+            let samples = game.to_training_samples().unwrap();
+            assert_eq!(samples.len(), game.record.moves.len());
+            assert!(samples.len() <= 12);
+            for (state, evaluation) in samples {
+                assert_eq!(evaluation.value.abs(), game.record.result.abs() as f64);
+                assert_eq!(evaluation.policy.len(), state.calc_legal_moves().len());
+                assert!((evaluation.policy.iter().map(|(_, share)| share).sum::<f64>() - 1.).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn test_selfplay_game_ends_at_threefold_repetition() {
+        let config = SelfPlayConfig {
+            iterations_per_move: 20,
+            max_game_depth: 40,
+            dirichlet_epsilon: 0.,
+            temperature: 0.,
+            ..SelfPlayConfig::default()
+        };
+        let game = play_selfplay_game(State::initial(), &KnightShuffleEvaluator, &config, &mut StdRng::seed_from_u64(0));
+        // the initial position occurs for the third time after two rounds of knight moves
+        assert_eq!(game.record.moves.len(), 8);
+        assert_eq!(game.record.result, 0);
+    }
+}
@@ -0,0 +1,342 @@
+//! A lightweight JSON-RPC server, so that frontends in other languages can use the engine over a socket.
+//!
+//! Each request is a single line of JSON such as `{"id": 1, "method": "analyze", "params": {"iterations": 800}}`,
+//! and is answered by a single line, either `{"id": 1, "result": ...}` or
+//! `{"id": 1, "error": {"code": ..., "message": ...}}`, using the JSON-RPC 2.0 error codes.
+//!
+//! Supported methods:
+//! - `set_position`: `{"fen": ..., "moves": [...]}`, both optional, moves in UCI notation
+//! - `get_position`: returns the FEN, side to move, and termination, if any
+//! - `legal_moves`: returns the legal moves in UCI notation
+//! - `make_move`: `{"move": ...}`, in UCI notation
+//! - `analyze`: `{"iterations": ...}`, optional and at most `MAX_ANALYSIS_ITERATIONS`, continuing the search kept from previous calls;
+//!   the result flags positions where the game seems stuck in a fortress
+//! - `engine_info`: returns the name of the active evaluator and the `EngineManifest` of the build
+
+use std::cmp::Reverse;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, ToSocketAddrs};
+use serde_json::{json, Value};
+use crate::analysis_session::{AnalysisSession, TreeReuse};
+use crate::manifest::EngineManifest;
+use crate::evaluation::Evaluator;
+use crate::mcts::mcts_node::MCTSNode;
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+use dunck_core::utils::Color;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+
+/// The number of search iterations used by `analyze` when none are given.
+pub const DEFAULT_ANALYSIS_ITERATIONS: usize = 800;
+
+/// The largest number of search iterations a single `analyze` request may ask for,
+/// since the search tree of a session grows with every iteration.
+pub const MAX_ANALYSIS_ITERATIONS: usize = 100_000;
+
+/// An error to be sent back to the client, with a JSON-RPC error code.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl ServerError {
+    fn invalid_params(message: String) -> ServerError {
+        ServerError { code: INVALID_PARAMS, message }
+    }
+}
+
+/// Serves requests for a single game at a time, keeping the search tree between requests.
+pub struct EngineServer<'a> {
+    pub session: AnalysisSession<'a>,
+    pub evaluator_name: String,
+    exploration_param: f64,
+    evaluator: &'a dyn Evaluator,
+    calc_node_score: &'static dyn Fn(&MCTSNode, u32, f64) -> f64,
+}
+
+impl<'a> EngineServer<'a> {
+    pub fn new(
+        exploration_param: f64,
+        evaluator: &'a dyn Evaluator,
+        calc_node_score: &'static dyn Fn(&MCTSNode, u32, f64) -> f64
+    ) -> Self {
+        EngineServer {
+            session: AnalysisSession::new(State::initial(), exploration_param, evaluator, calc_node_score),
+            evaluator_name: "unknown".to_string(),
+            exploration_param,
+            evaluator,
+            calc_node_score,
+        }
+    }
+
+    /// Sets the evaluator name reported by `engine_info`.
+    pub fn with_evaluator_name(mut self, evaluator_name: &str) -> Self {
+        self.evaluator_name = evaluator_name.to_string();
+        self
+    }
+
+    /// Starts over from `state`, discarding the search tree.
+    pub fn reset(&mut self, state: State) {
+        self.session = AnalysisSession::new(state, self.exploration_param, self.evaluator, self.calc_node_score);
+    }
+
+    /// Handles one request line and returns the response line, without a trailing newline.
+    pub fn handle_line(&mut self, line: &str) -> String {
+        let request = match serde_json::from_str::<Value>(line) {
+            Ok(request) => request,
+            Err(e) => return make_error_response(&Value::Null, PARSE_ERROR, &e.to_string()).to_string()
+        };
+        let id = request.get("id").cloned().unwrap_or(Value::Null);
+        let method = match request.get("method").and_then(Value::as_str) {
+            Some(method) => method,
+            None => return make_error_response(&id, INVALID_REQUEST, "Missing method").to_string()
+        };
+        let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+        match self.handle_request(method, &params) {
+            Ok(result) => json!({ "id": id, "result": result }).to_string(),
+            Err(e) => make_error_response(&id, e.code, &e.message).to_string()
+        }
+    }
+
+    /// Dispatches a request to the method it names.
+    pub fn handle_request(&mut self, method: &str, params: &Value) -> Result<Value, ServerError> {
+        match method {
+            "set_position" => self.set_position(params),
+            "get_position" => Ok(self.get_position()),
+            "legal_moves" => Ok(self.get_legal_moves()),
+            "make_move" => self.make_move(params),
+            "analyze" => self.analyze(params),
+            "engine_info" => Ok(json!({ "evaluator": self.evaluator_name.clone(), "manifest": EngineManifest::new() })),
+            _ => Err(ServerError { code: METHOD_NOT_FOUND, message: format!("Unknown method: {}", method) })
+        }
+    }
+
+    fn set_position(&mut self, params: &Value) -> Result<Value, ServerError> {
+        let state = match params.get("fen").and_then(Value::as_str) {
+            Some(fen) => State::from_fen(fen).map_err(|e| ServerError::invalid_params(format!("Invalid FEN: {}", e)))?,
+            None => State::initial()
+        };
+        self.reset(state);
+
+        if let Some(moves) = params.get("moves").and_then(Value::as_array) {
+            for uci in moves {
+                let uci = uci.as_str().ok_or_else(|| ServerError::invalid_params("Moves must be strings".to_string()))?;
+                let mv = self.parse_move(uci)?;
+                self.session.make_move(mv).map_err(ServerError::invalid_params)?;
+            }
+        }
+
+        Ok(self.get_position())
+    }
+
+    fn get_position(&self) -> Value {
+        let state = self.session.get_state();
+        json!({
+            "fen": state.to_fen(),
+            "side_to_move": if state.side_to_move == Color::White { "white" } else { "black" },
+            "termination": state.termination.map(|termination| format!("{:?}", termination))
+        })
+    }
+
+    fn get_legal_moves(&self) -> Value {
+        let moves: Vec<String> = self.session.get_state().calc_legal_moves().iter().map(Move::to_uci).collect();
+        json!(moves)
+    }
+
+    fn make_move(&mut self, params: &Value) -> Result<Value, ServerError> {
+        let uci = params.get("move").and_then(Value::as_str)
+            .ok_or_else(|| ServerError::invalid_params("Missing move".to_string()))?;
+        let mv = self.parse_move(uci)?;
+        let tree_reuse = self.session.make_move(mv).map_err(ServerError::invalid_params)?;
+        let tree_reuse = match tree_reuse {
+            TreeReuse::Expected => "expected",
+            TreeReuse::Salvaged => "salvaged",
+            TreeReuse::Discarded => "discarded"
+        };
+        let state = self.session.get_state();
+        Ok(json!({
+            "fen": state.to_fen(),
+            "tree_reuse": tree_reuse,
+            "termination": state.termination.map(|termination| format!("{:?}", termination))
+        }))
+    }
+
+    fn analyze(&mut self, params: &Value) -> Result<Value, ServerError> {
+        let iterations = match params.get("iterations") {
+            Some(iterations) => iterations.as_u64()
+                .ok_or_else(|| ServerError::invalid_params("Iterations must be a non-negative integer".to_string()))? as usize,
+            None => DEFAULT_ANALYSIS_ITERATIONS
+        };
+        if iterations > MAX_ANALYSIS_ITERATIONS {
+            return Err(ServerError::invalid_params(format!("Iterations must be at most {}", MAX_ANALYSIS_ITERATIONS)));
+        }
+        let state = self.session.get_state();
+        if state.termination.is_none() && !state.calc_legal_moves().is_empty() {
+            self.session.analyze(iterations);
+        }
+
+        let root = self.session.mcts.root.borrow();
+        let mut children: Vec<(Move, u32, f64)> = root.children.iter().map(|child| {
+            let child = child.borrow();
+            let score = if child.visits == 0 { 0. } else { child.value / child.visits as f64 };
+            (child.mv.unwrap(), child.visits, score)
+        }).collect();
+        children.sort_by_key(|&(_, visits, _)| Reverse(visits));
+
+        let best_move = children.first().map(|(mv, _, _)| mv.to_uci());
+        let moves: Vec<Value> = children.iter()
+            .map(|(mv, visits, score)| json!({ "move": mv.to_uci(), "visits": *visits, "score": *score }))
+            .collect();
+        Ok(json!({
+            "best_move": best_move,
+            "visits": root.visits,
+            "moves": moves,
+            "fortress_suspected": self.session.is_fortress_suspected()
+        }))
+    }
+
+    /// Finds the legal move in the current position with the given UCI notation.
+    fn parse_move(&self, uci: &str) -> Result<Move, ServerError> {
+        Move::from_uci(&self.session.get_state(), uci).map_err(|e| ServerError::invalid_params(e.to_string()))
+    }
+
+    /// Answers requests read line by line from `reader` until it is exhausted.
+    /// Blank lines are ignored.
+    pub fn serve_stream<R: BufRead, W: Write>(&mut self, reader: R, mut writer: W) -> std::io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            writeln!(writer, "{}", self.handle_line(&line))?;
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Listens on `addr` and serves one connection at a time.
+    /// Each connection starts from the initial position.
+    pub fn serve<A: ToSocketAddrs>(&mut self, addr: A) -> std::io::Result<()> {
+        let listener = TcpListener::bind(addr)?;
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("Failed to accept a connection: {}", e);
+                    continue;
+                }
+            };
+            self.reset(State::initial());
+            let reader = BufReader::new(stream.try_clone()?);
+            if let Err(e) = self.serve_stream(reader, stream) {
+                eprintln!("Connection closed with error: {}", e);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn make_error_response(id: &Value, code: i64, message: &str) -> Value {
+    json!({ "id": id.clone(), "error": json!({ "code": code, "message": message }) })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::mcts::mcts::calc_uct_score;
+    use dunck_core::state::ZOBRIST_KEYS_VERSION;
+
+    fn parse_response(response: &str) -> Value {
+        serde_json::from_str::<Value>(response).unwrap()
+    }
+
+    #[test]
+    fn test_position_and_moves() {
+        let evaluator = MaterialEvaluator {};
+        let mut server = EngineServer::new(1.5, &evaluator, &calc_uct_score);
+
+        let response = parse_response(&server.handle_line(r#"{"id": 1, "method": "legal_moves"}"#));
+        assert_eq!(response["id"], 1);
+        assert_eq!(response["result"].as_array().unwrap().len(), 20);
+
+        let response = parse_response(&server.handle_line(r#"{"id": 2, "method": "make_move", "params": {"move": "e2e4"}}"#));
+        assert_eq!(response["result"]["fen"], "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1");
+
+        let response = parse_response(&server.handle_line(
+            r#"{"id": 3, "method": "set_position", "params": {"fen": "4k3/8/8/8/8/8/8/4K2R w K - 0 1", "moves": ["h1h2"]}}"#
+        ));
+        assert_eq!(response["result"]["fen"], "4k3/8/8/8/8/8/7R/4K3 b - - 1 1");
+        assert_eq!(response["result"]["side_to_move"], "black");
+    }
+
+    #[test]
+    fn test_analyze() {
+        let evaluator = MaterialEvaluator {};
+        let mut server = EngineServer::new(1.5, &evaluator, &calc_uct_score);
+
+        let response = parse_response(&server.handle_line(r#"{"id": "a", "method": "analyze", "params": {"iterations": 100}}"#));
+        assert_eq!(response["id"], "a");
+        let result = &response["result"];
+        let legal_moves = server.session.get_state().calc_legal_moves();
+        assert!(legal_moves.iter().any(|mv| result["best_move"] == mv.to_uci().as_str()));
+        assert_eq!(result["moves"].as_array().unwrap().len(), legal_moves.len());
+        assert_eq!(result["moves"][0]["move"], result["best_move"].clone());
+        assert_eq!(result["fortress_suspected"], false);
+    }
+
+    #[test]
+    fn test_analyze_iteration_limit() {
+        let evaluator = MaterialEvaluator {};
+        let mut server = EngineServer::new(1.5, &evaluator, &calc_uct_score);
+
+        let request = json!({ "id": 1, "method": "analyze", "params": { "iterations": MAX_ANALYSIS_ITERATIONS + 1 } });
+        let response = parse_response(&server.handle_line(&request.to_string()));
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+        assert_eq!(server.session.mcts.root.borrow().visits, 0);
+    }
+
+    #[test]
+    fn test_errors() {
+        let evaluator = MaterialEvaluator {};
+        let mut server = EngineServer::new(1.5, &evaluator, &calc_uct_score);
+
+        let response = parse_response(&server.handle_line("not json"));
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+
+        let response = parse_response(&server.handle_line(r#"{"id": 1}"#));
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+
+        let response = parse_response(&server.handle_line(r#"{"id": 1, "method": "engine_info"}"#));
+        assert_eq!(response["result"]["evaluator"], "unknown");
+        assert_eq!(response["result"]["manifest"]["zobrist_keys_version"], ZOBRIST_KEYS_VERSION);
+
+        let response = parse_response(&server.handle_line(r#"{"id": 1, "method": "resign"}"#));
+        assert_eq!(response["error"]["code"], METHOD_NOT_FOUND);
+
+        let response = parse_response(&server.handle_line(r#"{"id": 1, "method": "make_move", "params": {"move": "e2e5"}}"#));
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_serve_stream() {
+        let evaluator = MaterialEvaluator {};
+        let mut server = EngineServer::new(1.5, &evaluator, &calc_uct_score);
+
+        let input = "{\"id\": 1, \"method\": \"get_position\"}\n\n{\"id\": 2, \"method\": \"legal_moves\"}\n";
+        let mut output = Vec::new();
+        server.serve_stream(input.as_bytes(), &mut output).unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        let responses: Vec<Value> = output.lines().map(parse_response).collect();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["result"]["fen"], dunck_core::state::INITIAL_FEN);
+        assert_eq!(responses[1]["id"], 2);
+    }
+}
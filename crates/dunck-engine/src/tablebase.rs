@@ -0,0 +1,235 @@
+//! Endgame tablebase probing interface and root move filtering.
+//! Probing itself is left to implementors of `TablebaseProber` (e.g. a Syzygy binding),
+//! so the engine does not depend on any particular tablebase format.
+
+use crate::evaluation::OutcomeScores;
+use crate::mcts::mcts::MCTS;
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+
+/// A win/draw/loss tablebase result, from the side to move's point of view.
+/// Cursed wins and blessed losses are wins and losses that the fifty-move rule turns into draws.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+pub enum Wdl {
+    Loss,
+    BlessedLoss,
+    Draw,
+    CursedWin,
+    Win
+}
+
+impl Wdl {
+    /// Returns the same result from the other side's point of view.
+    pub const fn flip(&self) -> Wdl {
+        match self {
+            Wdl::Loss => Wdl::Win,
+            Wdl::BlessedLoss => Wdl::CursedWin,
+            Wdl::Draw => Wdl::Draw,
+            Wdl::CursedWin => Wdl::BlessedLoss,
+            Wdl::Win => Wdl::Loss
+        }
+    }
+}
+
+pub trait TablebaseProber {
+    /// Returns the result of `state` with perfect play, or None if it is not covered by the tablebase.
+    fn probe_wdl(&self, state: &State) -> Option<Wdl>;
+
+    /// Returns the distance to the next zeroing move (capture or pawn move) with perfect play,
+    /// positive if the side to move wins and negative if it loses, or None if it is not covered by the tablebase.
+    fn probe_dtz(&self, state: &State) -> Option<i32>;
+}
+
+/// The value of a won tablebase position, before the distance to zeroing is taken into account.
+/// It is kept below checkmate so that the search still prefers actually delivering mate.
+pub const TB_WIN_VALUE: f64 = 0.98;
+/// How much each ply of distance to zeroing shrinks the value of a won position, so that the search makes progress.
+pub const TB_DTZ_PENALTY: f64 = 0.0002;
+/// How far a cursed win or blessed loss is valued from a draw.
+pub const TB_CURSED_VALUE: f64 = 0.02;
+
+/// A tablebase result together with its distance to zeroing, if known, from the side to move's point of view.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct TbScore {
+    pub wdl: Wdl,
+    pub dtz: Option<i32>,
+}
+
+impl TbScore {
+    /// Probes `state`, returning None if it is not covered by the tablebase.
+    pub fn probe(state: &State, prober: &dyn TablebaseProber) -> Option<TbScore> {
+        let wdl = prober.probe_wdl(state)?;
+        Some(TbScore { wdl, dtz: prober.probe_dtz(state) })
+    }
+
+    /// Returns the value of the score for the side to move, with draws valued as in `outcome_scores`.
+    pub fn get_value(&self, outcome_scores: &OutcomeScores) -> f64 {
+        let win_value = TB_WIN_VALUE - TB_DTZ_PENALTY * self.dtz.map_or(0, |dtz| dtz.abs().min(100)) as f64;
+        match self.wdl {
+            Wdl::Win => win_value,
+            Wdl::CursedWin => outcome_scores.draw + TB_CURSED_VALUE,
+            Wdl::Draw => outcome_scores.draw,
+            Wdl::BlessedLoss => outcome_scores.draw - TB_CURSED_VALUE,
+            Wdl::Loss => -win_value
+        }
+    }
+}
+
+/// Restricts the root moves of `state` to those that keep its tablebase result:
+/// if the position is won, only winning moves are kept, ordered so that moves reaching a zeroing move
+/// soonest come first; if it is drawn, losing moves are removed.
+/// Returns None, leaving the search unrestricted, if `state` or any position after a legal move is not covered.
+pub fn filter_root_moves_by_tablebase(state: &State, prober: &dyn TablebaseProber) -> Option<Vec<Move>> {
+    let root_wdl = prober.probe_wdl(state)?;
+
+    let mut results = Vec::new();
+    for mv in state.calc_legal_moves() {
+        let mut next_state = state.clone();
+        next_state.make_move(mv);
+        let wdl = prober.probe_wdl(&next_state)?.flip();
+        let is_zeroing = next_state.context.borrow().halfmove_clock == 0;
+        let dtz = if is_zeroing { 0 } else { prober.probe_dtz(&next_state)?.abs() };
+        results.push((mv, wdl, dtz));
+    }
+
+    results.retain(|(_, wdl, _)| *wdl >= root_wdl);
+    if root_wdl > Wdl::Draw {
+        results.sort_by_key(|(_, _, dtz)| *dtz);
+    }
+    Some(results.into_iter().map(|(mv, _, _)| mv).collect())
+}
+
+/// Applies `filter_root_moves_by_tablebase` to the root of `mcts`, before searching it.
+/// Returns whether the root was covered by the tablebase and thus restricted.
+pub fn apply_tablebase_root_filter(mcts: &mut MCTS, prober: &dyn TablebaseProber) -> bool {
+    let state = mcts.root.borrow().state_after_move.clone();
+    match filter_root_moves_by_tablebase(&state, prober) {
+        Some(allowed_moves) if !allowed_moves.is_empty() => {
+            mcts.restrict_root_moves(&allowed_moves);
+            true
+        },
+        _ => false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+    use crate::mcts::mcts::calc_uct_score;
+    use dunck_core::utils::{Color, PieceType, Square};
+
+    /// Treats any position where only one side has a queen as won for that side, and anything else as drawn.
+    /// A queen the side to move can capture does not count. DTZ is the distance between the kings.
+    struct QueenTablebase {}
+
+    impl TablebaseProber for QueenTablebase {
+        fn probe_wdl(&self, state: &State) -> Option<Wdl> {
+            let board = &state.board;
+            let occupied = board.piece_type_masks[PieceType::AllPieceTypes as usize];
+            let queens = board.piece_type_masks[PieceType::Queen as usize];
+            let has_queen = |color: Color| Square::ALL.into_iter().any(|square| {
+                let is_queen = queens & board.color_masks[color as usize] & square.get_mask() != 0;
+                let is_hanging = color != state.side_to_move &&
+                    board.calc_attackers_to(square, occupied) & board.color_masks[state.side_to_move as usize] != 0;
+                is_queen && !is_hanging
+            });
+            Some(match (has_queen(state.side_to_move), has_queen(state.side_to_move.flip())) {
+                (true, false) => Wdl::Win,
+                (false, true) => Wdl::Loss,
+                _ => Wdl::Draw
+            })
+        }
+
+        fn probe_dtz(&self, state: &State) -> Option<i32> {
+            let kings = state.board.piece_type_masks[PieceType::King as usize];
+            let find_king = |color: Color| Square::ALL.into_iter()
+                .find(|square| kings & state.board.color_masks[color as usize] & square.get_mask() != 0)
+                .unwrap();
+            let distance = find_king(Color::White).distance(find_king(Color::Black)) as i32;
+            Some(match self.probe_wdl(state)? {
+                Wdl::Win | Wdl::CursedWin => distance,
+                Wdl::Draw => 0,
+                _ => -distance
+            })
+        }
+    }
+
+    #[test]
+    fn test_winning_root_keeps_winning_moves() {
+        // queen moves next to the king on g6, like Qf5+, hang the queen and lose the win
+        let state = State::from_fen("8/8/6k1/8/3Q4/8/8/K7 w - - 0 1").unwrap();
+        let moves = filter_root_moves_by_tablebase(&state, &QueenTablebase {}).unwrap();
+        assert!(!moves.is_empty());
+        assert!(moves.len() < state.calc_legal_moves().len());
+
+        let prober = QueenTablebase {};
+        let mut dtzs = Vec::new();
+        for mv in moves {
+            let mut next_state = state.clone();
+            next_state.make_move(mv);
+            assert_eq!(prober.probe_wdl(&next_state), Some(Wdl::Loss));
+            dtzs.push(prober.probe_dtz(&next_state).unwrap().abs());
+        }
+        assert!(dtzs.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn test_drawn_root_excludes_losing_moves() {
+        // both sides have a queen, and e.g. Qd4 hangs the white queen to the black one
+        let state = State::from_fen("7k/8/8/3q4/8/8/5K2/6Q1 w - - 0 1").unwrap();
+        let prober = QueenTablebase {};
+        assert_eq!(prober.probe_wdl(&state), Some(Wdl::Draw));
+
+        let moves = filter_root_moves_by_tablebase(&state, &prober).unwrap();
+        assert!(moves.len() < state.calc_legal_moves().len());
+        for mv in moves {
+            let mut next_state = state.clone();
+            next_state.make_move(mv);
+            assert_ne!(prober.probe_wdl(&next_state), Some(Wdl::Win));
+        }
+    }
+
+    #[test]
+    fn test_apply_tablebase_root_filter() {
+        let state = State::from_fen("8/8/6k1/8/3Q4/8/8/K7 w - - 0 1").unwrap();
+        let prober = QueenTablebase {};
+        let allowed_moves = filter_root_moves_by_tablebase(&state, &prober).unwrap();
+
+        let evaluator = MaterialEvaluator {};
+        let mut mcts = MCTS::new(state, 1.5, &evaluator, &calc_uct_score, false);
+        assert!(apply_tablebase_root_filter(&mut mcts, &prober));
+        mcts.run(100);
+
+        let root_moves: Vec<Move> = mcts.root.borrow().children.iter().map(|child| child.borrow().mv.unwrap()).collect();
+        assert_eq!(root_moves, allowed_moves);
+        assert!(allowed_moves.contains(&mcts.get_best_child_by_visits().unwrap().borrow().mv.unwrap()));
+    }
+
+    #[test]
+    fn test_tb_score_values() {
+        let outcome_scores = OutcomeScores::default();
+        let get_value = |wdl, dtz| TbScore { wdl, dtz }.get_value(&outcome_scores);
+        let values: Vec<f64> = [Wdl::Loss, Wdl::BlessedLoss, Wdl::Draw, Wdl::CursedWin, Wdl::Win].into_iter()
+            .map(|wdl| get_value(wdl, None))
+            .collect();
+        assert!(values.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(values[4] < 1. && values[0] > -1.);
+        assert!(get_value(Wdl::Win, Some(3)) > get_value(Wdl::Win, Some(30)));
+        assert!(get_value(Wdl::Loss, Some(-3)) < get_value(Wdl::Loss, Some(-30)));
+        assert_eq!(TbScore { wdl: Wdl::Draw, dtz: None }.get_value(&OutcomeScores::with_draw_score(-0.1)), -0.1);
+
+        let state = State::from_fen("8/8/6k1/8/3Q4/8/8/K7 w - - 0 1").unwrap();
+        assert_eq!(TbScore::probe(&state, &QueenTablebase {}), Some(TbScore { wdl: Wdl::Win, dtz: Some(6) }));
+    }
+
+    #[test]
+    fn test_uncovered_position_is_not_filtered() {
+        struct EmptyTablebase {}
+        impl TablebaseProber for EmptyTablebase {
+            fn probe_wdl(&self, _state: &State) -> Option<Wdl> { None }
+            fn probe_dtz(&self, _state: &State) -> Option<i32> { None }
+        }
+        assert_eq!(filter_root_moves_by_tablebase(&State::initial(), &EmptyTablebase {}), None);
+    }
+}
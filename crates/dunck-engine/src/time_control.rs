@@ -0,0 +1,236 @@
+//! Time controls, from sudden death and Fischer increments to multi-stage classical controls,
+//! in the notations players write them in and in the seconds-based format of PGN `TimeControl` tags.
+
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+use crate::time_manager::Clock;
+
+/// One stage of a time control: `base` time for `moves` moves, or for the rest of the game if `moves` is None,
+/// plus `increment` after every move.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeControlStage {
+    pub moves: Option<u32>,
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+/// Stages played one after another, the same for both sides. Each stage's base time is added to a side's clock
+/// once it completes the moves of the previous stage. If the last stage has a number of moves, it repeats.
+///
+/// Parsed from and displayed as stages separated by `:`, each written `[<moves>/]<minutes>[+<seconds>]`,
+/// where `|` may stand for `+`: e.g. `5+3`, `15|10`, or `40/90+30:30+30`.
+/// `from_pgn_tag` and `to_pgn_tag` use the PGN format instead, with every time in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeControl {
+    stages: Vec<TimeControlStage>,
+}
+
+impl TimeControl {
+    /// Returns a single-stage time control of `base` for the whole game plus `increment` after every move:
+    /// sudden death without an increment, a Fischer time control with one.
+    pub fn new(base: Duration, increment: Duration) -> TimeControl {
+        TimeControl { stages: vec![TimeControlStage { moves: None, base, increment }] }
+    }
+
+    /// Returns a time control with the given stages, checking that there is at least one,
+    /// that every stage has at least one move, and that only the last one lasts for the rest of the game.
+    pub fn from_stages(stages: Vec<TimeControlStage>) -> Result<TimeControl, String> {
+        let Some((_, previous_stages)) = stages.split_last() else {
+            return Err("A time control needs at least one stage".to_string());
+        };
+        if previous_stages.iter().any(|stage| stage.moves.is_none()) {
+            return Err("Only the last stage of a time control can last for the rest of the game".to_string());
+        }
+        if stages.iter().any(|stage| stage.moves == Some(0)) {
+            return Err("Every stage of a time control needs at least one move".to_string());
+        }
+        Ok(TimeControl { stages })
+    }
+
+    pub fn get_stages(&self) -> &[TimeControlStage] {
+        &self.stages
+    }
+
+    /// Returns the time on each clock at the start of the game.
+    pub fn get_initial_time(&self) -> Duration {
+        self.stages[0].base
+    }
+
+    /// Returns the index of the stage a side plays its next move in after playing `moves_played` moves,
+    /// and the number of moves it has left in that stage, including the next one, unless it is the last stage.
+    fn locate(&self, moves_played: u32) -> (usize, Option<u32>) {
+        let mut moves_played = moves_played;
+        for (index, stage) in self.stages.iter().enumerate() {
+            match stage.moves {
+                Some(moves) if moves_played >= moves => moves_played -= moves,
+                moves => return (index, moves.map(|moves| moves - moves_played))
+            }
+        }
+        // the last stage repeats
+        let index = self.stages.len() - 1;
+        let moves = self.stages[index].moves.expect("only a last stage with moves repeats");
+        (index, Some(moves - moves_played % moves))
+    }
+
+    /// Returns the increment a side gets for its next move after playing `moves_played` moves.
+    pub fn get_increment(&self, moves_played: u32) -> Duration {
+        self.stages[self.locate(moves_played).0].increment
+    }
+
+    /// Returns the number of moves a side has left until its next time control after playing `moves_played` moves,
+    /// or None if it is in the last stage without one.
+    pub fn get_moves_to_go(&self, moves_played: u32) -> Option<u32> {
+        self.locate(moves_played).1
+    }
+
+    /// Returns the time added to a side's clock after its next move, having played `moves_played` moves before it:
+    /// the increment, plus the base time of the next stage if the move completes the current one.
+    pub fn get_time_added(&self, moves_played: u32) -> Duration {
+        let (index, moves_to_go) = self.locate(moves_played);
+        let mut time_added = self.stages[index].increment;
+        if moves_to_go == Some(1) {
+            time_added += self.stages.get(index + 1).unwrap_or(&self.stages[index]).base;
+        }
+        time_added
+    }
+
+    /// Returns the clock of a side with `remaining` time after playing `moves_played` moves.
+    pub fn get_clock(&self, remaining: Duration, moves_played: u32) -> Clock {
+        Clock {
+            remaining,
+            increment: self.get_increment(moves_played),
+            moves_to_go: self.get_moves_to_go(moves_played),
+        }
+    }
+
+    /// Returns the time control with every base time and increment multiplied by `factor`, e.g. for time odds.
+    pub fn scaled(&self, factor: f64) -> TimeControl {
+        let stages = self.stages.iter()
+            .map(|stage| TimeControlStage { base: stage.base.mul_f64(factor), increment: stage.increment.mul_f64(factor), ..*stage })
+            .collect();
+        TimeControl { stages }
+    }
+
+    /// Parses the value of a PGN `TimeControl` tag, e.g. `40/5400+30:1800+30` or `300+3`, with times in seconds.
+    /// Unknown (`?`), untimed (`-`) and sandclock (`*`) time controls are not supported.
+    pub fn from_pgn_tag(tag: &str) -> Result<TimeControl, String> {
+        parse_stages(tag, 1., &['+'])
+    }
+
+    /// Renders the time control as the value of a PGN `TimeControl` tag, with times in seconds.
+    pub fn to_pgn_tag(&self) -> String {
+        format_stages(&self.stages, 1.)
+    }
+}
+
+/// Parses stages separated by `:`, with base times in units of `base_unit` seconds and increments in seconds.
+fn parse_stages(s: &str, base_unit: f64, increment_separators: &[char]) -> Result<TimeControl, String> {
+    let invalid = || format!("Invalid time control: {}", s);
+    let parse_seconds = |seconds: &str, unit: f64| seconds.trim().parse::<f64>().ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds * unit).ok())
+        .ok_or_else(invalid);
+
+    let mut stages = Vec::new();
+    for stage in s.trim().split(':') {
+        let (moves, time) = match stage.split_once('/') {
+            Some((moves, time)) => (Some(moves.trim().parse::<u32>().map_err(|_| invalid())?), time),
+            None => (None, stage)
+        };
+        let (base, increment) = time.split_once(increment_separators).unwrap_or((time, "0"));
+        stages.push(TimeControlStage { moves, base: parse_seconds(base, base_unit)?, increment: parse_seconds(increment, 1.)? });
+    }
+    TimeControl::from_stages(stages).map_err(|e| format!("Invalid time control {}: {}", s, e))
+}
+
+/// Formats stages separated by `:`, with base times in units of `base_unit` seconds, omitting zero increments.
+fn format_stages(stages: &[TimeControlStage], base_unit: f64) -> String {
+    let stages: Vec<String> = stages.iter().map(|stage| {
+        let mut formatted = match stage.moves {
+            Some(moves) => format!("{}/", moves),
+            None => String::new()
+        };
+        formatted += &(stage.base.as_secs_f64() / base_unit).to_string();
+        if !stage.increment.is_zero() {
+            formatted += &format!("+{}", stage.increment.as_secs_f64());
+        }
+        formatted
+    }).collect();
+    stages.join(":")
+}
+
+impl FromStr for TimeControl {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<TimeControl, String> {
+        parse_stages(s, 60., &['+', '|'])
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", format_stages(&self.stages, 60.))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(60 * minutes)
+    }
+
+    #[test]
+    fn test_parsing() {
+        assert_eq!("5+3".parse(), Ok(TimeControl::new(minutes(5), Duration::from_secs(3))));
+        assert_eq!("15|10".parse(), Ok(TimeControl::new(minutes(15), Duration::from_secs(10))));
+        assert_eq!("1".parse(), Ok(TimeControl::new(minutes(1), Duration::ZERO)));
+        assert_eq!("0.5+0.1".parse(), Ok(TimeControl::new(Duration::from_secs(30), Duration::from_millis(100))));
+
+        let classical: TimeControl = "40/90+30:30+30".parse().unwrap();
+        assert_eq!(classical.get_stages(), [
+            TimeControlStage { moves: Some(40), base: minutes(90), increment: Duration::from_secs(30) },
+            TimeControlStage { moves: None, base: minutes(30), increment: Duration::from_secs(30) },
+        ]);
+        assert_eq!(classical.to_string(), "40/90+30:30+30");
+        assert_eq!(classical.to_pgn_tag(), "40/5400+30:1800+30");
+        assert_eq!(TimeControl::from_pgn_tag("40/5400+30:1800+30"), Ok(classical));
+        assert_eq!(TimeControl::from_pgn_tag("300"), Ok(TimeControl::new(minutes(5), Duration::ZERO)));
+        assert_eq!("15|10".parse::<TimeControl>().unwrap().to_string(), "15+10");
+
+        for invalid in ["", "fast", "-1+1", "5+", "0/5", "5:40/90", "x/90", "40/90::5"] {
+            assert!(invalid.parse::<TimeControl>().is_err(), "{}", invalid);
+        }
+        for invalid in ["?", "-", "*180", "5|3"] {
+            assert!(TimeControl::from_pgn_tag(invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_stages() {
+        let classical: TimeControl = "40/90+30:20/60:15".parse().unwrap();
+        assert_eq!(classical.get_initial_time(), minutes(90));
+        assert_eq!(classical.get_moves_to_go(0), Some(40));
+        assert_eq!(classical.get_moves_to_go(39), Some(1));
+        assert_eq!(classical.get_time_added(38), Duration::from_secs(30));
+        assert_eq!(classical.get_time_added(39), minutes(60) + Duration::from_secs(30));
+        assert_eq!(classical.get_moves_to_go(40), Some(20));
+        assert_eq!(classical.get_increment(40), Duration::ZERO);
+        assert_eq!(classical.get_time_added(59), minutes(15));
+        assert_eq!(classical.get_moves_to_go(60), None);
+        assert_eq!(classical.get_time_added(100), Duration::ZERO);
+
+        // a last stage with moves repeats
+        let repeating: TimeControl = "40/120".parse().unwrap();
+        assert_eq!(repeating.get_moves_to_go(45), Some(35));
+        assert_eq!(repeating.get_time_added(79), minutes(120));
+        assert_eq!(repeating.get_clock(minutes(3), 79), Clock { remaining: minutes(3), increment: Duration::ZERO, moves_to_go: Some(1) });
+
+        let fischer = TimeControl::new(minutes(3), Duration::from_secs(2));
+        assert_eq!(fischer.get_moves_to_go(10), None);
+        assert_eq!(fischer.get_time_added(10), Duration::from_secs(2));
+        assert_eq!(fischer.scaled(0.5), TimeControl::new(Duration::from_secs(90), Duration::from_secs(1)));
+    }
+}
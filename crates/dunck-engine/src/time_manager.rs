@@ -0,0 +1,136 @@
+//! Splits the remaining clock time between moves, using the evaluator's moves-left estimate
+//! when there is one instead of assuming a fixed game length.
+//! The result is a `SearchBudget`, which `MCTS::run_with_budget` searches until it is used up.
+
+use std::time::{Duration, Instant};
+use crate::evaluation::Evaluator;
+use dunck_core::state::State;
+
+/// A player's clock before a move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    pub remaining: Duration,
+    pub increment: Duration,
+    /// The number of moves until the next time control, if the clock has one.
+    pub moves_to_go: Option<u32>,
+}
+
+/// The limits of a single search, which stops as soon as one of them is reached.
+/// Without any limits, the search only stops when told to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchBudget {
+    pub time_limit: Option<Duration>,
+    /// The number of search iterations.
+    pub nodes: Option<u32>,
+}
+
+impl SearchBudget {
+    /// Returns a budget of a fixed number of iterations.
+    pub fn from_nodes(nodes: u32) -> SearchBudget {
+        SearchBudget { nodes: Some(nodes), ..Default::default() }
+    }
+
+    /// Returns a budget of a fixed time per move.
+    pub fn from_movetime(movetime: Duration) -> SearchBudget {
+        SearchBudget { time_limit: Some(movetime), ..Default::default() }
+    }
+
+    pub fn with_nodes(mut self, nodes: u32) -> Self {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    /// Returns whether a search that started at `start_time` and has run `iterations` iterations should stop.
+    pub fn is_exhausted(&self, iterations: u32, start_time: Instant) -> bool {
+        self.nodes.is_some_and(|nodes| iterations >= nodes)
+            || self.time_limit.is_some_and(|time_limit| start_time.elapsed() >= time_limit)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeManager {
+    /// The number of own moves assumed to be left when there is no estimate.
+    pub default_moves_left: f64,
+    /// The fewest own moves budgeted for, so that short estimates never spend the whole clock.
+    pub min_moves_left: f64,
+    /// The fraction of the increment spent on each move.
+    pub increment_fraction: f64,
+    /// Time reserved for communication delays.
+    pub move_overhead: Duration,
+}
+
+impl Default for TimeManager {
+    fn default() -> Self {
+        TimeManager {
+            default_moves_left: 30.,
+            min_moves_left: 10.,
+            increment_fraction: 0.75,
+            move_overhead: Duration::from_millis(30),
+        }
+    }
+}
+
+impl TimeManager {
+    /// Returns the time to spend on the next move.
+    /// `moves_to_go` is the number of moves until the next time control, if the clock has one.
+    /// `estimated_plies_left` is the predicted remaining game length in plies, for both sides together.
+    pub fn allocate(&self, remaining: Duration, increment: Duration, moves_to_go: Option<u32>, estimated_plies_left: Option<f64>) -> Duration {
+        let available = remaining.saturating_sub(self.move_overhead);
+        let estimated_moves_left = match estimated_plies_left {
+            Some(plies_left) => (plies_left / 2.).max(self.min_moves_left),
+            None => self.default_moves_left
+        };
+        let moves_left = match moves_to_go {
+            Some(moves_to_go) => estimated_moves_left.min(moves_to_go.max(1) as f64),
+            None => estimated_moves_left
+        };
+        let allocated = available.as_secs_f64() / moves_left + increment.as_secs_f64() * self.increment_fraction;
+        Duration::from_secs_f64(allocated).min(available)
+    }
+
+    /// Like `allocate`, asking `evaluator` for the remaining game length from `state`.
+    pub fn allocate_for_state(&self, evaluator: &dyn Evaluator, state: &State, remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> Duration {
+        self.allocate(remaining, increment, moves_to_go, evaluator.estimate_moves_left(state))
+    }
+
+    /// Returns the budget for the next move from `state` when playing on `clock`.
+    pub fn calc_budget(&self, evaluator: &dyn Evaluator, state: &State, clock: &Clock) -> SearchBudget {
+        SearchBudget::from_movetime(self.allocate_for_state(evaluator, state, clock.remaining, clock.increment, clock.moves_to_go))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluators::material_simple::MaterialEvaluator;
+
+    #[test]
+    fn test_allocate() {
+        let time_manager = TimeManager { move_overhead: Duration::ZERO, ..Default::default() };
+        let minute = Duration::from_secs(60);
+
+        assert_eq!(time_manager.allocate(minute, Duration::ZERO, None, None), Duration::from_secs(2));
+        assert!(time_manager.allocate(minute, Duration::ZERO, None, Some(20.)) > time_manager.allocate(minute, Duration::ZERO, None, Some(80.)));
+        assert_eq!(time_manager.allocate(minute, Duration::ZERO, None, Some(2.)), Duration::from_secs(6));
+        assert_eq!(time_manager.allocate(minute, Duration::ZERO, Some(1), None), minute);
+        assert_eq!(time_manager.allocate(Duration::ZERO, Duration::from_secs(1), None, None), Duration::ZERO);
+
+        let allocated = time_manager.allocate_for_state(&MaterialEvaluator {}, &State::initial(), minute, Duration::ZERO, None);
+        assert_eq!(allocated, Duration::from_secs(2));
+
+        let clock = Clock { remaining: minute, increment: Duration::from_secs(2), moves_to_go: None };
+        let budget = time_manager.calc_budget(&MaterialEvaluator {}, &State::initial(), &clock);
+        assert_eq!(budget, SearchBudget::from_movetime(Duration::from_millis(3500)));
+    }
+
+    #[test]
+    fn test_search_budget() {
+        let start_time = Instant::now();
+        assert!(!SearchBudget::default().is_exhausted(u32::MAX, start_time));
+        assert!(!SearchBudget::from_nodes(10).is_exhausted(9, start_time));
+        assert!(SearchBudget::from_nodes(10).is_exhausted(10, start_time));
+        assert!(SearchBudget::from_movetime(Duration::ZERO).is_exhausted(0, start_time));
+        assert!(!SearchBudget::from_movetime(Duration::from_secs(60)).is_exhausted(0, start_time));
+        assert!(SearchBudget::from_movetime(Duration::from_secs(60)).with_nodes(1).is_exhausted(1, start_time));
+    }
+}
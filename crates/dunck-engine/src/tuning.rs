@@ -0,0 +1,28 @@
+//! A registry of named integer parameters, so that search settings can be tuned without knowing their types.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TunableParameter {
+    pub name: &'static str,
+    pub value: i32,
+    pub min: i32,
+    pub max: i32,
+}
+
+pub trait Tunable {
+    /// Returns every tunable parameter with its current value and allowed range.
+    fn get_tunable_parameters(&self) -> Vec<TunableParameter>;
+
+    /// Sets the parameter called `name`, failing if it is unknown or `value` is out of range.
+    fn set_tunable_parameter(&mut self, name: &str, value: i32) -> Result<(), String>;
+}
+
+/// Checks that `value` is in range for the parameter called `name` among `parameters`.
+pub fn check_tunable_parameter(parameters: &[TunableParameter], name: &str, value: i32) -> Result<(), String> {
+    match parameters.iter().find(|parameter| parameter.name == name) {
+        None => Err(format!("Unknown parameter: {}", name)),
+        Some(parameter) if value < parameter.min || value > parameter.max => {
+            Err(format!("Value {} for {} is outside [{}, {}]", value, name, parameter.min, parameter.max))
+        },
+        Some(_) => Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+//! Parsing of the commands a GUI sends to a UCI engine.
+
+use std::time::Duration;
+use crate::uci::parse_setoption;
+
+/// The limits of a search started by `go`. Any combination may be given; the search stops at the first one reached.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GoLimits {
+    /// The minimum length of the principal variation, in plies.
+    pub depth: Option<u32>,
+    /// The number of search iterations, i.e. visits of the root.
+    pub nodes: Option<u32>,
+    pub movetime: Option<Duration>,
+    /// The remaining clock times and increments of white and black, indexed by `Color`.
+    pub time: [Option<Duration>; 2],
+    pub increment: [Option<Duration>; 2],
+    pub moves_to_go: Option<u32>,
+    /// Search until `stop`, ignoring every other limit.
+    pub infinite: bool,
+}
+
+impl GoLimits {
+    /// Returns whether no limit was given, in which case the engine picks its own.
+    pub fn is_unlimited(&self) -> bool {
+        !self.infinite && self.depth.is_none() && self.nodes.is_none() && self.movetime.is_none()
+            && self.time.iter().all(Option::is_none)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciCommand {
+    Uci,
+    Debug(bool),
+    IsReady,
+    SetOption { name: String, value: String },
+    UciNewGame,
+    /// The position to search, as a FEN (the initial position if None) followed by moves in UCI notation.
+    Position { fen: Option<String>, moves: Vec<String> },
+    Go(GoLimits),
+    Stop,
+    PonderHit,
+    Quit,
+}
+
+impl UciCommand {
+    /// Parses a line sent by the GUI. Returns None for blank lines and unknown commands,
+    /// which the protocol says to ignore, and an error for known commands with invalid arguments.
+    pub fn parse(line: &str) -> Option<Result<UciCommand, String>> {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        let (&name, arguments) = tokens.split_first()?;
+        let command = match name {
+            "uci" => Ok(UciCommand::Uci),
+            "debug" => Ok(UciCommand::Debug(arguments.first() == Some(&"on"))),
+            "isready" => Ok(UciCommand::IsReady),
+            "setoption" => parse_setoption(line)
+                .map(|(name, value)| UciCommand::SetOption { name, value })
+                .ok_or_else(|| format!("Invalid setoption command: {}", line.trim())),
+            "ucinewgame" => Ok(UciCommand::UciNewGame),
+            "position" => parse_position(arguments),
+            "go" => parse_go(arguments).map(UciCommand::Go),
+            "stop" => Ok(UciCommand::Stop),
+            "ponderhit" => Ok(UciCommand::PonderHit),
+            "quit" => Ok(UciCommand::Quit),
+            _ => return None
+        };
+        Some(command)
+    }
+}
+
+fn parse_position(arguments: &[&str]) -> Result<UciCommand, String> {
+    let moves_index = arguments.iter().position(|token| *token == "moves").unwrap_or(arguments.len());
+    let fen = match arguments.first() {
+        Some(&"startpos") => None,
+        Some(&"fen") if moves_index > 1 => Some(arguments[1..moves_index].join(" ")),
+        _ => return Err(format!("Invalid position command: position {}", arguments.join(" ")))
+    };
+    let moves = arguments.iter().skip(moves_index + 1).map(|mv| mv.to_string()).collect();
+    Ok(UciCommand::Position { fen, moves })
+}
+
+fn parse_go(arguments: &[&str]) -> Result<GoLimits, String> {
+    let mut limits = GoLimits::default();
+    let mut tokens = arguments.iter();
+    while let Some(&token) = tokens.next() {
+        if token == "infinite" {
+            limits.infinite = true;
+            continue;
+        }
+        if token == "ponder" {
+            continue;
+        }
+        let value = tokens.next().ok_or_else(|| format!("Missing value for {}", token))?;
+        let parse_number = || value.parse::<u64>().map_err(|e| format!("Invalid value for {}: {} ({})", token, value, e));
+        let parse_millis = || parse_number().map(Duration::from_millis);
+        match token {
+            "depth" => limits.depth = Some(parse_number()? as u32),
+            "nodes" => limits.nodes = Some(parse_number()? as u32),
+            "movetime" => limits.movetime = Some(parse_millis()?),
+            "wtime" => limits.time[0] = Some(parse_millis()?),
+            "btime" => limits.time[1] = Some(parse_millis()?),
+            "winc" => limits.increment[0] = Some(parse_millis()?),
+            "binc" => limits.increment[1] = Some(parse_millis()?),
+            "movestogo" => limits.moves_to_go = Some(parse_number()? as u32),
+            // searchmoves and mate are not supported, and their arguments are skipped
+            "searchmoves" | "mate" => {},
+            _ => return Err(format!("Unknown go parameter: {}", token))
+        }
+    }
+    Ok(limits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_position() {
+        assert_eq!(UciCommand::parse("position startpos"), Some(Ok(UciCommand::Position { fen: None, moves: vec![] })));
+        assert_eq!(
+            UciCommand::parse("position startpos moves e2e4 e7e5"),
+            Some(Ok(UciCommand::Position { fen: None, moves: vec!["e2e4".to_string(), "e7e5".to_string()] }))
+        );
+        assert_eq!(
+            UciCommand::parse("position fen 4k3/8/8/8/8/8/8/4K2R w K - 0 1 moves e1g1"),
+            Some(Ok(UciCommand::Position { fen: Some("4k3/8/8/8/8/8/8/4K2R w K - 0 1".to_string()), moves: vec!["e1g1".to_string()] }))
+        );
+        assert!(matches!(UciCommand::parse("position fen"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_go() {
+        let limits = match UciCommand::parse("go wtime 60000 btime 50000 winc 1000 binc 1000 movestogo 20") {
+            Some(Ok(UciCommand::Go(limits))) => limits,
+            command => panic!("Expected go, found {:?}", command)
+        };
+        assert_eq!(limits.time, [Some(Duration::from_secs(60)), Some(Duration::from_secs(50))]);
+        assert_eq!(limits.increment, [Some(Duration::from_secs(1)); 2]);
+        assert_eq!(limits.moves_to_go, Some(20));
+        assert!(!limits.is_unlimited());
+
+        assert_eq!(UciCommand::parse("go depth 5 nodes 800"), Some(Ok(UciCommand::Go(GoLimits { depth: Some(5), nodes: Some(800), ..Default::default() }))));
+        assert_eq!(UciCommand::parse("go movetime 100"), Some(Ok(UciCommand::Go(GoLimits { movetime: Some(Duration::from_millis(100)), ..Default::default() }))));
+        assert!(matches!(UciCommand::parse("go infinite"), Some(Ok(UciCommand::Go(GoLimits { infinite: true, .. })))));
+        assert!(matches!(UciCommand::parse("go"), Some(Ok(UciCommand::Go(limits))) if limits.is_unlimited()));
+        assert!(matches!(UciCommand::parse("go depth"), Some(Err(_))));
+        assert!(matches!(UciCommand::parse("go nodes many"), Some(Err(_))));
+    }
+
+    #[test]
+    fn test_parse_other_commands() {
+        assert_eq!(UciCommand::parse("  isready "), Some(Ok(UciCommand::IsReady)));
+        assert_eq!(UciCommand::parse("setoption name Clear Hash"), Some(Ok(UciCommand::SetOption { name: "Clear Hash".to_string(), value: String::new() })));
+        assert_eq!(UciCommand::parse("debug on"), Some(Ok(UciCommand::Debug(true))));
+        assert_eq!(UciCommand::parse(""), None);
+        assert_eq!(UciCommand::parse("xyzzy"), None);
+    }
+}
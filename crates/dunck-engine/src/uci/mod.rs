@@ -0,0 +1,33 @@
+//! UCI (Universal Chess Interface) protocol helpers.
+
+pub mod command;
+pub mod uci_engine;
+
+/// Parses a `setoption name <name> [value <value>]` command into its name and value.
+/// Both may contain spaces; a missing value is returned as an empty string, as for UCI buttons.
+pub fn parse_setoption(line: &str) -> Option<(String, String)> {
+    let rest = line.trim().strip_prefix("setoption")?.trim_start().strip_prefix("name")?;
+    let tokens: Vec<&str> = rest.split_whitespace().collect();
+    let value_index = tokens.iter().position(|token| *token == "value");
+    let (name_tokens, value_tokens) = match value_index {
+        Some(index) => (&tokens[..index], &tokens[index + 1..]),
+        None => (&tokens[..], &[][..])
+    };
+    if name_tokens.is_empty() {
+        return None;
+    }
+    Some((name_tokens.join(" "), value_tokens.join(" ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_setoption() {
+        assert_eq!(parse_setoption("setoption name UCI_AnalyseMode value true"), Some(("UCI_AnalyseMode".to_string(), "true".to_string())));
+        assert_eq!(parse_setoption("setoption name Clear Hash"), Some(("Clear Hash".to_string(), "".to_string())));
+        assert_eq!(parse_setoption("setoption name"), None);
+        assert_eq!(parse_setoption("position startpos"), None);
+    }
+}
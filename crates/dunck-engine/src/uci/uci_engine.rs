@@ -0,0 +1,535 @@
+//! A UCI engine: commands are handled on the caller's thread, while searches run on a worker thread
+//! that owns the evaluator, so that `stop` and `isready` are answered while searching.
+
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use crate::engine_options::{EngineOptions, MAX_MULTI_PV, UCI_ANALYSE_MODE, UCI_MULTI_PV};
+use crate::evaluation::Evaluator;
+use crate::evaluators::factory::{create_evaluator_of_kind, get_eval_backend_uci_option, EvaluatorConfig, EvaluatorKind, UCI_EVAL_BACKEND};
+use crate::evaluators::material_simple::MaterialEvaluator;
+use crate::mcts::mcts::MCTS;
+use crate::mcts::mcts_node::MCTSNode;
+use crate::mcts::multi_pv::MultiPv;
+use crate::time_manager::{Clock, SearchBudget, TimeManager};
+use crate::uci::command::{GoLimits, UciCommand};
+use crate::worker::catch_worker_panic;
+pub use dunck_core::game_record::{centipawns_to_value, value_to_centipawns};
+use dunck_core::r#move::Move;
+use dunck_core::state::{State, INITIAL_FEN};
+
+pub const ENGINE_NAME: &str = "dunck";
+pub const ENGINE_AUTHOR: &str = "the dunck developers";
+
+/// The number of iterations searched by `go` without any limits.
+pub const DEFAULT_GO_NODES: u32 = 800;
+/// The most iterations searched by `go depth`, in case the principal variation never gets long enough.
+pub const MAX_DEPTH_SEARCH_NODES: u32 = 1_000_000;
+/// How often `info` lines are sent while searching.
+pub const INFO_INTERVAL: Duration = Duration::from_millis(1000);
+
+type Output = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// Writes `line` to `output` and flushes it. Errors are ignored, since the GUI may already be gone.
+fn send_line(output: &Output, line: &str) {
+    let mut output = output.lock().unwrap();
+    let _ = writeln!(output, "{}", line);
+    let _ = output.flush();
+}
+
+struct SearchRequest {
+    fen: String,
+    moves: Vec<Move>,
+    limits: GoLimits,
+    /// The number of lines to report.
+    multi_pv: usize,
+    stop: Arc<AtomicBool>,
+}
+
+enum WorkerMessage {
+    Search(SearchRequest),
+    SetEvaluator(EvaluatorKind),
+}
+
+pub struct UciEngine {
+    pub options: EngineOptions,
+    evaluator_kind: EvaluatorKind,
+    fen: String,
+    moves: Vec<Move>,
+    output: Output,
+    worker_sender: Option<Sender<WorkerMessage>>,
+    worker: Option<JoinHandle<()>>,
+    /// The stop flag of the latest search.
+    stop: Arc<AtomicBool>,
+    /// Whether the latest search was started by `go infinite`.
+    is_infinite: bool,
+}
+
+impl UciEngine {
+    /// Starts the search worker, which builds the evaluator for `evaluator_kind`,
+    /// and writes every response to `output`.
+    pub fn new(
+        evaluator_kind: EvaluatorKind,
+        exploration_param: f64,
+        calc_node_score: &'static (dyn Fn(&MCTSNode, u32, f64) -> f64 + Sync),
+        output: Box<dyn Write + Send>
+    ) -> UciEngine {
+        let output: Output = Arc::new(Mutex::new(output));
+        let (worker_sender, worker_receiver) = channel();
+        let worker = {
+            let evaluator_kind = evaluator_kind.clone();
+            let output = output.clone();
+            thread::spawn(move || run_worker(worker_receiver, evaluator_kind, exploration_param, calc_node_score, output))
+        };
+        UciEngine {
+            options: EngineOptions::default(),
+            evaluator_kind,
+            fen: INITIAL_FEN.to_string(),
+            moves: Vec::new(),
+            output,
+            worker_sender: Some(worker_sender),
+            worker: Some(worker),
+            stop: Arc::new(AtomicBool::new(true)),
+            is_infinite: false,
+        }
+    }
+
+    /// Handles one line from the GUI. Returns false once `quit` has been handled.
+    pub fn handle_line(&mut self, line: &str) -> bool {
+        match UciCommand::parse(line) {
+            Some(Ok(command)) => self.handle_command(command),
+            Some(Err(e)) => {
+                send_line(&self.output, &format!("info string {}", e));
+                true
+            },
+            None => true
+        }
+    }
+
+    pub fn handle_command(&mut self, command: UciCommand) -> bool {
+        match command {
+            UciCommand::Uci => {
+                send_line(&self.output, &format!("id name {}", ENGINE_NAME));
+                send_line(&self.output, &format!("id author {}", ENGINE_AUTHOR));
+                send_line(&self.output, &format!("option name {} type check default false", UCI_ANALYSE_MODE));
+                send_line(&self.output, &format!("option name {} type spin default 1 min 1 max {}", UCI_MULTI_PV, MAX_MULTI_PV));
+                send_line(&self.output, &get_eval_backend_uci_option(&self.evaluator_kind));
+                send_line(&self.output, "uciok");
+            },
+            UciCommand::Debug(_) | UciCommand::PonderHit => {},
+            UciCommand::IsReady => send_line(&self.output, "readyok"),
+            UciCommand::SetOption { name, value } => self.set_option(&name, &value),
+            UciCommand::UciNewGame => {
+                self.stop_search();
+                self.fen = INITIAL_FEN.to_string();
+                self.moves.clear();
+            },
+            UciCommand::Position { fen, moves } => {
+                if let Err(e) = self.set_position(fen, &moves) {
+                    send_line(&self.output, &format!("info string {}", e));
+                }
+            },
+            UciCommand::Go(limits) => {
+                self.stop_search();
+                self.stop = Arc::new(AtomicBool::new(false));
+                self.is_infinite = limits.infinite;
+                self.send_to_worker(WorkerMessage::Search(SearchRequest {
+                    fen: self.fen.clone(),
+                    moves: self.moves.clone(),
+                    limits,
+                    multi_pv: self.options.multi_pv,
+                    stop: self.stop.clone(),
+                }));
+            },
+            UciCommand::Stop => self.stop_search(),
+            UciCommand::Quit => {
+                self.shut_down();
+                return false;
+            }
+        }
+        true
+    }
+
+    fn set_option(&mut self, name: &str, value: &str) {
+        if name.eq_ignore_ascii_case(UCI_EVAL_BACKEND) {
+            match value.parse::<EvaluatorKind>() {
+                Ok(kind) => {
+                    self.evaluator_kind = kind.clone();
+                    self.send_to_worker(WorkerMessage::SetEvaluator(kind));
+                },
+                Err(e) => send_line(&self.output, &format!("info string {}", e))
+            }
+        } else if let Err(e) = self.options.set_uci_option(name, value) {
+            send_line(&self.output, &format!("info string {}", e));
+        }
+    }
+
+    /// Sets the position to search, checking that the FEN is valid and every move is legal.
+    /// The previous position is kept if not.
+    fn set_position(&mut self, fen: Option<String>, moves: &[String]) -> Result<(), String> {
+        let fen = fen.unwrap_or(INITIAL_FEN.to_string());
+        let mut state = State::from_fen(&fen).map_err(|e| format!("Invalid FEN {}: {}", fen, e))?;
+        let mut parsed_moves = Vec::with_capacity(moves.len());
+        for uci in moves {
+            let mv = Move::from_uci(&state, uci).map_err(|e| e.to_string())?;
+            state.make_move(mv);
+            parsed_moves.push(mv);
+        }
+        self.fen = fen;
+        self.moves = parsed_moves;
+        Ok(())
+    }
+
+    /// Stops the running search, if any. Its `bestmove` is still sent.
+    pub fn stop_search(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Stops the running search and waits for the worker to finish.
+    pub fn shut_down(&mut self) {
+        self.stop_search();
+        self.finish_searches();
+    }
+
+    /// Waits for the searches that were started to finish, after which no more can be started.
+    fn finish_searches(&mut self) {
+        self.worker_sender = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+
+    fn send_to_worker(&self, message: WorkerMessage) {
+        if let Some(sender) = &self.worker_sender {
+            let _ = sender.send(message);
+        }
+    }
+
+    /// Handles lines from `lines` until `quit` or the end of the input.
+    /// At the end of the input, the running search is finished, unless it was started by `go infinite`.
+    pub fn run<I: Iterator<Item = String>>(&mut self, lines: I) {
+        for line in lines {
+            if !self.handle_line(&line) {
+                return;
+            }
+        }
+        if self.is_infinite {
+            self.stop_search();
+        }
+        self.finish_searches();
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        self.shut_down();
+    }
+}
+
+fn create_worker_evaluator(kind: &EvaluatorKind, output: &Output) -> Box<dyn Evaluator> {
+    match create_evaluator_of_kind(kind, &EvaluatorConfig::default()) {
+        Ok(loaded) => {
+            send_line(output, &loaded.get_uci_info_string());
+            loaded.evaluator
+        },
+        Err(e) => {
+            send_line(output, &format!("info string {}, using material evaluator", e));
+            Box::new(MaterialEvaluator {})
+        }
+    }
+}
+
+fn run_worker(
+    receiver: Receiver<WorkerMessage>,
+    evaluator_kind: EvaluatorKind,
+    exploration_param: f64,
+    calc_node_score: &'static (dyn Fn(&MCTSNode, u32, f64) -> f64 + Sync),
+    output: Output
+) {
+    let mut evaluator_kind = evaluator_kind;
+    let mut evaluator = create_worker_evaluator(&evaluator_kind, &output);
+    for message in receiver {
+        match message {
+            WorkerMessage::SetEvaluator(kind) => {
+                evaluator = create_worker_evaluator(&kind, &output);
+                evaluator_kind = kind;
+            },
+            WorkerMessage::Search(request) => {
+                let result = catch_worker_panic(|| run_search(evaluator.as_ref(), request, exploration_param, calc_node_score, &output));
+                if let Err(e) = result {
+                    // the GUI is still waiting for a move, and the evaluator may have been left mid-update
+                    send_line(&output, &format!("info string {}, restarting the search worker", e));
+                    send_line(&output, "bestmove 0000");
+                    evaluator = create_worker_evaluator(&evaluator_kind, &output);
+                }
+            },
+        }
+    }
+}
+
+fn run_search(
+    evaluator: &dyn Evaluator,
+    request: SearchRequest,
+    exploration_param: f64,
+    calc_node_score: &'static (dyn Fn(&MCTSNode, u32, f64) -> f64 + Sync),
+    output: &Output
+) {
+    let start_time = Instant::now();
+    let mut state = State::from_fen(&request.fen).unwrap();
+    for mv in &request.moves {
+        state.make_move(*mv);
+    }
+    if state.calc_legal_moves().is_empty() {
+        send_line(output, "bestmove 0000");
+        return;
+    }
+
+    let limits = &request.limits;
+    let budget = calc_search_budget(limits, evaluator, &state);
+    let mut mcts = MCTS::new(state, exploration_param, evaluator, calc_node_score, false);
+    if request.multi_pv > 1 {
+        mcts = mcts.with_multi_pv(MultiPv::new(request.multi_pv));
+    }
+    let mut last_info_time = start_time;
+    mcts.run_until(&budget, |mcts| {
+        if last_info_time.elapsed() >= INFO_INTERVAL {
+            send_info_lines(output, mcts, start_time, request.multi_pv);
+            last_info_time = Instant::now();
+        }
+        let is_deep_enough = !limits.infinite && limits.depth.is_some_and(|depth| {
+            mcts.root.borrow().get_principal_variation().len() >= depth as usize
+        });
+        request.stop.load(Ordering::Relaxed) || is_deep_enough
+    });
+
+    send_info_lines(output, &mcts, start_time, request.multi_pv);
+    // the first reported line, so that the best move matches it even when visits are tied
+    let best_move = mcts.get_pv_lines(1).first().map(|line| line.moves[0]);
+    match best_move {
+        Some(best_move) => send_line(output, &format!("bestmove {}", best_move.to_uci())),
+        None => send_line(output, "bestmove 0000")
+    }
+}
+
+/// Returns the budget of a search from `state` with the given `go` limits.
+/// `movetime` takes precedence over the clock, and the depth limit is checked separately.
+fn calc_search_budget(limits: &GoLimits, evaluator: &dyn Evaluator, state: &State) -> SearchBudget {
+    if limits.infinite {
+        return SearchBudget::default();
+    }
+
+    let side_to_move = state.side_to_move as usize;
+    let mut budget = match (limits.movetime, limits.time[side_to_move]) {
+        (Some(movetime), _) => SearchBudget::from_movetime(movetime),
+        (None, Some(remaining)) => {
+            let clock = Clock {
+                remaining,
+                increment: limits.increment[side_to_move].unwrap_or(Duration::ZERO),
+                moves_to_go: limits.moves_to_go,
+            };
+            TimeManager::default().calc_budget(evaluator, state, &clock)
+        },
+        (None, None) => SearchBudget::default()
+    };
+    budget.nodes = match limits.nodes {
+        Some(nodes) => Some(nodes),
+        None if limits.depth.is_some() => Some(MAX_DEPTH_SEARCH_NODES),
+        None if limits.is_unlimited() => Some(DEFAULT_GO_NODES),
+        None => None
+    };
+    budget
+}
+
+/// Returns an `info` line for each of the `multi_pv` best root moves, with the search's depth, nodes, speed,
+/// and the line's score and principal variation. The depth is the length of the line's principal variation,
+/// and the `multipv` field is only included when more than one line is requested.
+fn get_info_lines(mcts: &MCTS, start_time: Instant, multi_pv: usize) -> Vec<String> {
+    let root_visits = mcts.root.borrow().visits;
+    let elapsed = start_time.elapsed();
+    let nps = (root_visits as f64 / elapsed.as_secs_f64().max(1e-3)) as u64;
+    let stats = format!("nodes {} nps {} time {}", root_visits, nps, elapsed.as_millis());
+
+    let info_lines: Vec<String> = mcts.get_pv_lines(multi_pv).iter().enumerate().filter_map(|(i, line)| {
+        // the line's value is from the perspective of the side to move at the root
+        let score = value_to_centipawns(line.q?);
+        let pv: Vec<String> = line.moves.iter().map(Move::to_uci).collect();
+        let multi_pv_field = if multi_pv > 1 { format!(" multipv {}", i + 1) } else { String::new() };
+        Some(format!("info depth {}{} {} score cp {} pv {}", pv.len(), multi_pv_field, stats, score, pv.join(" ")))
+    }).collect();
+    if info_lines.is_empty() {
+        return vec![format!("info {}", stats)];
+    }
+    info_lines
+}
+
+fn send_info_lines(output: &Output, mcts: &MCTS, start_time: Instant, multi_pv: usize) {
+    for info_line in get_info_lines(mcts, start_time, multi_pv) {
+        send_line(output, &info_line);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcts::mcts::calc_puct_score;
+
+    /// An output that can be read while the engine writes to it.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn get_lines(&self) -> Vec<String> {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap().lines().map(str::to_string).collect()
+        }
+
+        fn wait_for_line(&self, prefix: &str) -> String {
+            let start_time = Instant::now();
+            while start_time.elapsed() < Duration::from_secs(30) {
+                if let Some(line) = self.get_lines().into_iter().find(|line| line.starts_with(prefix)) {
+                    return line;
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+            panic!("No line starting with {} in {:?}", prefix, self.get_lines());
+        }
+    }
+
+    fn make_engine() -> (UciEngine, SharedBuffer) {
+        let buffer = SharedBuffer::default();
+        let engine = UciEngine::new(EvaluatorKind::Material, 1.5, &calc_puct_score, Box::new(buffer.clone()));
+        (engine, buffer)
+    }
+
+    #[test]
+    fn test_handshake() {
+        let (mut engine, buffer) = make_engine();
+        assert!(engine.handle_line("uci"));
+        assert!(engine.handle_line("isready"));
+        buffer.wait_for_line("readyok");
+        let lines = buffer.get_lines();
+        assert!(lines.contains(&"id name dunck".to_string()));
+        assert!(lines.contains(&"option name EvalBackend type string default material".to_string()));
+        assert!(lines.contains(&"uciok".to_string()));
+        assert!(!engine.handle_line("quit"));
+    }
+
+    #[test]
+    fn test_go_nodes() {
+        let (mut engine, buffer) = make_engine();
+        engine.handle_line("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        engine.handle_line("go nodes 400");
+        assert_eq!(buffer.wait_for_line("bestmove"), "bestmove a1a8");
+        let info = buffer.get_lines().into_iter().rev().find(|line| line.starts_with("info depth")).unwrap();
+        assert!(info.contains(" nodes 400 "), "{}", info);
+        assert!(info.contains(" pv a1a8"), "{}", info);
+    }
+
+    #[test]
+    fn test_go_multi_pv() {
+        let (mut engine, buffer) = make_engine();
+        engine.handle_line("setoption name MultiPV value 3");
+        engine.handle_line("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        engine.handle_line("go nodes 300");
+        assert_eq!(buffer.wait_for_line("bestmove"), "bestmove a1a8");
+        let lines = buffer.get_lines();
+        // the final lines are sent together, right before the best move
+        let info_lines = &lines[lines.len() - 4..lines.len() - 1];
+        for (i, info) in info_lines.iter().enumerate() {
+            assert!(info.contains(&format!(" multipv {} nodes 300 ", i + 1)), "{}", info);
+        }
+        assert!(info_lines[0].contains(" pv a1a8"), "{}", info_lines[0]);
+        assert!(info_lines[1..].iter().all(|info| !info.contains(" pv a1a8")));
+    }
+
+    #[test]
+    fn test_go_infinite_and_stop() {
+        let (mut engine, buffer) = make_engine();
+        engine.handle_line("position startpos moves e2e4 e7e5");
+        engine.handle_line("go infinite");
+        thread::sleep(Duration::from_millis(50));
+        assert!(buffer.get_lines().iter().all(|line| !line.starts_with("bestmove")));
+        engine.handle_line("stop");
+        let best_move = buffer.wait_for_line("bestmove");
+        let mut state = State::initial();
+        for uci in ["e2e4", "e7e5"] {
+            let mv = Move::from_uci(&state, uci).unwrap();
+            state.make_move(mv);
+        }
+        assert!(state.calc_legal_moves().iter().any(|mv| best_move == format!("bestmove {}", mv.to_uci())));
+    }
+
+    #[test]
+    fn test_invalid_commands_are_reported() {
+        let (mut engine, buffer) = make_engine();
+        engine.handle_line("position startpos moves e2e5");
+        engine.handle_line("setoption name EvalBackend value alphazero");
+        engine.handle_line("go depth 1");
+        buffer.wait_for_line("bestmove");
+        let lines = buffer.get_lines();
+        assert!(lines.contains(&"info string Illegal move: e2e5".to_string()));
+        assert!(lines.contains(&"info string Unknown evaluator: alphazero".to_string()));
+    }
+
+    #[test]
+    fn test_worker_survives_panic() {
+        static HAS_PANICKED: AtomicBool = AtomicBool::new(false);
+        fn panic_once(node: &MCTSNode, parent_visits: u32, exploration_param: f64) -> f64 {
+            if !HAS_PANICKED.swap(true, Ordering::Relaxed) {
+                panic!("corrupted node");
+            }
+            calc_puct_score(node, parent_visits, exploration_param)
+        }
+
+        let buffer = SharedBuffer::default();
+        let mut engine = UciEngine::new(EvaluatorKind::Material, 1.5, &panic_once, Box::new(buffer.clone()));
+        engine.handle_line("position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+        engine.handle_line("go nodes 400");
+        assert_eq!(buffer.wait_for_line("bestmove"), "bestmove 0000");
+        assert!(buffer.get_lines().contains(&"info string Worker panicked: corrupted node, restarting the search worker".to_string()));
+
+        buffer.0.lock().unwrap().clear();
+        engine.handle_line("go nodes 400");
+        assert_eq!(buffer.wait_for_line("bestmove"), "bestmove a1a8");
+    }
+
+    #[test]
+    fn test_value_to_centipawns() {
+        assert_eq!(value_to_centipawns(0.), 0);
+        assert!(value_to_centipawns(0.5) > 100);
+        assert_eq!(value_to_centipawns(-0.5), -value_to_centipawns(0.5));
+        assert!(value_to_centipawns(1.) > 10000);
+    }
+
+    #[test]
+    fn test_calc_search_budget() {
+        let state = State::initial();
+        let evaluator = MaterialEvaluator {};
+        let calc_budget = |line: &str| match UciCommand::parse(line) {
+            Some(Ok(UciCommand::Go(limits))) => calc_search_budget(&limits, &evaluator, &state),
+            _ => panic!("Invalid go command: {}", line)
+        };
+
+        assert_eq!(calc_budget("go"), SearchBudget::from_nodes(DEFAULT_GO_NODES));
+        assert_eq!(calc_budget("go infinite nodes 5"), SearchBudget::default());
+        assert_eq!(calc_budget("go depth 3"), SearchBudget::from_nodes(MAX_DEPTH_SEARCH_NODES));
+        assert_eq!(calc_budget("go movetime 500 wtime 1000 nodes 5"), SearchBudget::from_movetime(Duration::from_millis(500)).with_nodes(5));
+
+        let clock_budget = calc_budget("go wtime 60000 btime 1000 winc 1000");
+        assert!(clock_budget.time_limit.unwrap() > Duration::from_secs(1));
+        assert_eq!(clock_budget.nodes, None);
+    }
+}
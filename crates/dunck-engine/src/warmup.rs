@@ -0,0 +1,98 @@
+//! Eager initialization, so that the first real query does not pay for building tables, loading the model
+//! and warming caches, e.g. right after the UCI engine or the server starts.
+
+use std::time::{Duration, Instant};
+use crate::evaluation::Evaluator;
+use crate::evaluators::factory::{create_evaluator_of_kind, EvaluatorConfig, EvaluatorKind, LoadedEvaluator};
+use crate::mcts::mcts::{calc_puct_score, MCTS};
+use dunck_core::perft::{perft, PERFT_POSITIONS};
+use dunck_core::state::State;
+
+/// The exploration parameter of the warm-up search, which only needs to visit a few positions.
+const WARMUP_EXPLORATION_PARAM: f64 = 2.0;
+
+/// What `init` should prepare besides the move generation tables.
+#[derive(Clone, Debug, Default)]
+pub struct InitOptions {
+    /// The evaluator to build, if any.
+    pub evaluator: Option<EvaluatorKind>,
+    /// The model and fallback used by a conv net evaluator.
+    pub evaluator_config: EvaluatorConfig,
+    /// The number of iterations of a search from the initial position run with the evaluator, or 0 for none.
+    pub warmup_iterations: usize,
+}
+
+impl InitOptions {
+    pub fn with_evaluator(mut self, kind: EvaluatorKind) -> Self {
+        self.evaluator = Some(kind);
+        self
+    }
+
+    pub fn with_evaluator_config(mut self, config: EvaluatorConfig) -> Self {
+        self.evaluator_config = config;
+        self
+    }
+
+    /// Runs a search of `iterations` with the evaluator once it is built.
+    pub fn with_warmup_search(mut self, iterations: usize) -> Self {
+        self.warmup_iterations = iterations;
+        self
+    }
+}
+
+/// What `init` prepared.
+pub struct Initialized {
+    /// The evaluator built for `InitOptions::evaluator`, ready to use.
+    pub evaluator: Option<LoadedEvaluator>,
+    pub elapsed: Duration,
+}
+
+/// Builds the attack and Zobrist tables, loads the evaluator and warms it up, as given by `options`.
+/// Fails if the evaluator is not available in this build.
+pub fn init(options: &InitOptions) -> Result<Initialized, String> {
+    let start_time = Instant::now();
+    warm_up_move_generation();
+    let evaluator = options.evaluator.as_ref()
+        .map(|kind| create_evaluator_of_kind(kind, &options.evaluator_config))
+        .transpose()?;
+    if let Some(loaded) = &evaluator {
+        warm_up_evaluator(loaded.evaluator.as_ref(), options.warmup_iterations);
+    }
+    Ok(Initialized { evaluator, elapsed: start_time.elapsed() })
+}
+
+/// Generates the moves of a position with every kind of move two plies deep,
+/// which builds the tables that move generation and hashing use.
+pub fn warm_up_move_generation() {
+    let kiwipete = PERFT_POSITIONS.iter().find(|position| position.name == "kiwipete").unwrap();
+    perft(&State::from_fen(kiwipete.fen).unwrap(), 2);
+}
+
+/// Runs a search of `iterations` from the initial position with `evaluator`, so that its first real search
+/// does not include e.g. allocating the model's buffers.
+pub fn warm_up_evaluator(evaluator: &dyn Evaluator, iterations: usize) {
+    if iterations == 0 {
+        return;
+    }
+    let mut mcts = MCTS::new(State::initial(), WARMUP_EXPLORATION_PARAM, evaluator, &calc_puct_score, false);
+    mcts.run(iterations);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::evaluators::factory::ActiveEvaluator;
+    use super::*;
+
+    #[test]
+    fn test_init() {
+        let initialized = init(&InitOptions::default()).unwrap();
+        assert!(initialized.evaluator.is_none());
+
+        let options = InitOptions::default().with_evaluator(EvaluatorKind::Material).with_warmup_search(20);
+        let loaded = init(&options).unwrap().evaluator.unwrap();
+        assert_eq!(loaded.active, ActiveEvaluator::Material);
+
+        let options = InitOptions::default().with_evaluator(EvaluatorKind::Nnue { path: "net.nnue".to_string() });
+        assert!(init(&options).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+//! Panic boundaries for worker threads, so that a bug hit by one search or batch of games
+//! is reported as an error instead of taking down the whole process.
+
+use std::any::Any;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EngineError {
+    /// A worker thread panicked, with the panic message.
+    WorkerPanicked(String),
+}
+
+impl EngineError {
+    /// Converts the payload of a caught panic, as returned by `catch_unwind` or `JoinHandle::join`.
+    pub fn from_panic(payload: Box<dyn Any + Send>) -> EngineError {
+        let message = match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(payload) => match payload.downcast::<&'static str>() {
+                Ok(message) => message.to_string(),
+                Err(_) => "unknown panic".to_string()
+            }
+        };
+        EngineError::WorkerPanicked(message)
+    }
+}
+
+impl Display for EngineError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EngineError::WorkerPanicked(message) => write!(f, "Worker panicked: {}", message),
+        }
+    }
+}
+
+impl Error for EngineError {}
+
+/// Runs `f`, returning the panic it raises as `EngineError::WorkerPanicked`.
+/// Whatever `f` borrows mutably may be left half-updated by the panic, so callers should rebuild
+/// such state (e.g. the evaluator and search tree) before running more work on it.
+pub fn catch_worker_panic<T>(f: impl FnOnce() -> T) -> Result<T, EngineError> {
+    catch_unwind(AssertUnwindSafe(f)).map_err(EngineError::from_panic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catch_worker_panic() {
+        assert_eq!(catch_worker_panic(|| 1 + 1), Ok(2));
+        assert_eq!(catch_worker_panic(|| panic!("no legal moves")), Err::<(), _>(EngineError::WorkerPanicked("no legal moves".to_string())));
+        let square = 64;
+        assert_eq!(
+            catch_worker_panic(|| panic!("invalid square {}", square)).unwrap_err().to_string(),
+            "Worker panicked: invalid square 64"
+        );
+        assert_eq!(
+            EngineError::from_panic(Box::new(0)),
+            EngineError::WorkerPanicked("unknown panic".to_string())
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use tch::{nn, Tensor};
+use tch::nn::ModuleT;
+use dunck_engine::evaluators::neural::constants::NUM_ATTACKED_SQUARES_PLANES;
+use crate::training_utils::print_tensor_stats;
+
+/// Predicts which squares each side attacks, as logits of shape batch_size x 2 x 8 x 8.
+/// Only used as an auxiliary training target.
+#[derive(Debug)]
+pub struct AttackedSquaresHead {
+    conv1: nn::Conv2D,
+    bn: nn::BatchNorm,
+    conv2: nn::Conv2D,
+}
+
+impl AttackedSquaresHead {
+    pub fn new(vs: &nn::Path, num_filters: i64) -> Self {
+        AttackedSquaresHead {
+            conv1: nn::conv2d(vs, num_filters, 32, 3, nn::ConvConfig { padding: 1, ..Default::default() }),
+            bn: nn::batch_norm2d(vs, 32, Default::default()),
+            conv2: nn::conv2d(vs, 32, NUM_ATTACKED_SQUARES_PLANES as i64, 1, Default::default()),
+        }
+    }
+
+    pub fn forward_t(&self, x: &Tensor, train: bool) -> Tensor {
+        let mut out = self.conv1.forward_t(x, train);
+        out = self.bn.forward_t(&out, train).relu();
+        out = self.conv2.forward_t(&out, train);
+        print_tensor_stats(&out, "Attacked squares output");
+
+        out
+    }
+}
@@ -1,32 +1,54 @@
 use std::error::Error;
 use tch::{nn, Device, Kind, Tensor};
 use tch::nn::{ModuleT};
-use crate::engine::evaluators::neural::constants::*;
-use crate::engine::evaluators::neural::combined_policy_value_network::CombinedPolicyValueNetwork;
-use crate::engine::evaluators::neural::policy_head::PolicyHead;
-use crate::engine::evaluators::neural::residual_block::ResidualBlock;
-use crate::engine::evaluators::neural::training_utils::print_tensor_stats;
-use crate::engine::evaluators::neural::value_head::ValueHead;
+use dunck_engine::evaluators::neural::constants::*;
+use crate::attacked_squares_head::AttackedSquaresHead;
+use crate::combined_policy_value_network::CombinedPolicyValueNetwork;
+use crate::moves_left_head::MovesLeftHead;
+use dunck_engine::evaluators::neural::net_config::NetConfig;
+use crate::policy_head::PolicyHead;
+use crate::residual_block::ResidualBlock;
+use crate::training_utils::print_tensor_stats;
+use crate::value_head::ValueHead;
 
 // Define the main model structure
 #[derive(Debug)]
 pub struct ConvNet {
     pub vs: nn::VarStore,
+    pub config: NetConfig,
     pub num_filters: i64,
     pub conv1: nn::Conv2D,
     pub bn1: nn::BatchNorm,
     pub residual_blocks: Vec<ResidualBlock>,
     pub policy_head: PolicyHead,
     pub value_head: ValueHead,
+    pub moves_left_head: Option<MovesLeftHead>,
+    pub attacked_squares_head: Option<AttackedSquaresHead>,
+    /// Predicts the opponent's reply in the same encoding as the policy, from the opponent's perspective.
+    pub opponent_reply_head: Option<PolicyHead>,
+}
+
+/// The outputs of the auxiliary heads the model has.
+pub struct AuxiliaryOutputs {
+    /// Logits of shape batch_size x 2 x 8 x 8.
+    pub attacked_squares: Option<Tensor>,
+    /// Logits of shape batch_size x 8 x 8 x 73.
+    pub opponent_reply: Option<Tensor>,
 }
 
 impl ConvNet {
     pub fn new(device: Device, num_residual_blocks: usize, num_filters: i64) -> ConvNet {
+        Self::from_config(device, NetConfig::new(num_residual_blocks, num_filters))
+    }
+
+    /// Creates a model with the architecture described by `config`.
+    pub fn from_config(device: Device, config: NetConfig) -> ConvNet {
         let vs = nn::VarStore::new(device);
         let root = &vs.root();
+        let (num_residual_blocks, num_filters) = (config.num_residual_blocks, config.num_filters);
 
         // Initial convolutional layer
-        let conv1 = nn::conv2d(root, NUM_POSITION_BITS as i64, num_filters, 3, nn::ConvConfig { padding: 1, ..Default::default() }); // 17 input channels, num_filters output channels
+        let conv1 = nn::conv2d(root, config.get_num_input_channels(), num_filters, 3, nn::ConvConfig { padding: 1, ..Default::default() }); // NUM_POSITION_BITS input channels, num_filters output channels
 
         // Batch normalization for initial convolution layer
         let bn1 = nn::batch_norm2d(root, num_filters, Default::default());
@@ -39,26 +61,95 @@ impl ConvNet {
 
         let policy_head = PolicyHead::new(root, num_filters);
         let value_head = ValueHead::new(root, num_filters);
+        let moves_left_head = if config.use_moves_left_head {
+            Some(MovesLeftHead::new(&(root / "moves_left_head"), num_filters))
+        } else {
+            None
+        };
+        let attacked_squares_head = if config.use_attacked_squares_head {
+            Some(AttackedSquaresHead::new(&(root / "attacked_squares_head"), num_filters))
+        } else {
+            None
+        };
+        let opponent_reply_head = if config.use_opponent_reply_head {
+            Some(PolicyHead::new(&(root / "opponent_reply_head"), num_filters))
+        } else {
+            None
+        };
 
         ConvNet {
             vs,
+            config,
             num_filters,
             conv1,
             bn1,
             residual_blocks,
             policy_head,
             value_head,
+            moves_left_head,
+            attacked_squares_head,
+            opponent_reply_head,
         }
     }
 
-    /// Save model weights manually using read_safetensors
+    /// Passes the input through the initial convolution and the residual blocks, shared by all heads.
+    fn forward_body_t(&self, x: &Tensor, train: bool) -> Tensor {
+        assert_eq!(x.size().len(), 4);
+        assert_eq!(x.size()[1..4], [NUM_POSITION_BITS as i64, 8, 8]);
+        assert!(x.size()[0] > 0);
+
+        // Debug print initial tensor
+        print_tensor_stats(x, "Initial tensor");
+        
+        // Apply initial convolution, batch normalization, and ReLU activation
+        let mut x = self.conv1.forward_t(x, train);
+        print_tensor_stats(&x, "After conv1");
+        
+        x = self.bn1.forward_t(&x, train).relu();
+        print_tensor_stats(&x, "After bn1+relu");
+
+        // Pass through the residual blocks
+        for block in &self.residual_blocks {
+            x = block.forward_t(&x, train);
+        }
+        print_tensor_stats(&x, "After residual blocks");
+
+        x
+    }
+
+    /// Like `forward_t`, but also returns the predicted number of plies left (batch_size x 1)
+    /// if the model has a moves-left head.
+    pub fn forward_with_moves_left_t(&self, x: &Tensor, train: bool) -> (Tensor, Tensor, Option<Tensor>) {
+        let x = self.forward_body_t(x, train);
+        let policy = self.policy_head.forward_t(&x, train);
+        let value = self.value_head.forward_t(&x, train);
+        let moves_left = self.moves_left_head.as_ref().map(|head| head.forward_t(&x, train));
+        (policy, value, moves_left)
+    }
+
+    /// Like `forward_t`, but also returns the outputs of the auxiliary heads.
+    pub fn forward_with_auxiliary_t(&self, x: &Tensor, train: bool) -> (Tensor, Tensor, AuxiliaryOutputs) {
+        let x = self.forward_body_t(x, train);
+        let policy = self.policy_head.forward_t(&x, train);
+        let value = self.value_head.forward_t(&x, train);
+        let auxiliary = AuxiliaryOutputs {
+            attacked_squares: self.attacked_squares_head.as_ref().map(|head| head.forward_t(&x, train)),
+            opponent_reply: self.opponent_reply_head.as_ref().map(|head| head.forward_t(&x, train)),
+        };
+        (policy, value, auxiliary)
+    }
+
+    /// Save model weights manually using read_safetensors, along with the config next to them
     pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
         self.vs.save(path)?;
+        self.config.save(path)?;
         Ok(())
     }
 
-    /// Load model weights manually using fill_safetensors
+    /// Load model weights manually using fill_safetensors,
+    /// refusing checkpoints whose config does not match this model
     pub fn load(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        self.config.check_compatible(&NetConfig::load(path, &self.config)?)?;
         self.vs.load(path)?;
 
         // After network creation
@@ -78,25 +169,7 @@ impl ConvNet {
 impl CombinedPolicyValueNetwork for ConvNet {
     /// Forward pass through the model
     fn forward_t(&self, x: &Tensor, train: bool) -> (Tensor, Tensor) {
-        assert_eq!(x.size().len(), 4);
-        assert_eq!(x.size()[1..4], [NUM_POSITION_BITS as i64, 8, 8]);
-        assert!(x.size()[0] > 0);
-
-        // Debug print initial tensor
-        print_tensor_stats(x, "Initial tensor");
-        
-        // Apply initial convolution, batch normalization, and ReLU activation
-        let mut x = self.conv1.forward_t(x, train);
-        print_tensor_stats(&x, "After conv1");
-        
-        x = self.bn1.forward_t(&x, train).relu();
-        print_tensor_stats(&x, "After bn1+relu");
-
-        // Pass through the residual blocks
-        for block in &self.residual_blocks {
-            x = block.forward_t(&x, train);
-        }
-        print_tensor_stats(&x, "After residual blocks");
+        let x = self.forward_body_t(x, train);
 
         // Should be batch_size x 8 x 8 x 73
         let policy = self.policy_head.forward_t(&x, train);
@@ -113,8 +186,8 @@ impl CombinedPolicyValueNetwork for ConvNet {
 mod tests {
     use tch::Kind;
     use tch::nn::OptimizerConfig;
-    use crate::engine::evaluators::neural::utils::{state_to_tensor, DEVICE};
-    use crate::state::State;
+    use crate::utils::{state_to_tensor, DEVICE};
+    use dunck_core::state::State;
     use super::*;
 
     #[test]
@@ -126,6 +199,30 @@ mod tests {
 
         assert_eq!(policy.size(), [1, 8, 8, NUM_TARGET_SQUARE_POSSIBILITIES as i64]);
         assert_eq!(value.size(), [1, 1]);
+        assert!(model.forward_with_moves_left_t(&input_tensor, false).2.is_none());
+    }
+
+    #[test]
+    fn test_moves_left_head() {
+        let model = ConvNet::from_config(*DEVICE, NetConfig::new(2, 32).with_moves_left_head());
+
+        let input_tensor = state_to_tensor(&State::initial());
+        let (_, _, moves_left) = model.forward_with_moves_left_t(&input_tensor, false);
+
+        let moves_left = moves_left.unwrap();
+        assert_eq!(moves_left.size(), [1, 1]);
+        assert!(moves_left.double_value(&[0, 0]) >= 0.);
+    }
+
+    #[test]
+    fn test_auxiliary_heads() {
+        let model = ConvNet::from_config(*DEVICE, NetConfig::new(2, 32).with_auxiliary_heads(true, true));
+
+        let input_tensor = state_to_tensor(&State::initial());
+        let (_, _, auxiliary) = model.forward_with_auxiliary_t(&input_tensor, false);
+
+        assert_eq!(auxiliary.attacked_squares.unwrap().size(), [1, NUM_ATTACKED_SQUARES_PLANES as i64, 8, 8]);
+        assert_eq!(auxiliary.opponent_reply.unwrap().size(), [1, 8, 8, NUM_TARGET_SQUARE_POSSIBILITIES as i64]);
     }
 
     #[test]
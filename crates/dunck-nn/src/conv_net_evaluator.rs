@@ -0,0 +1,137 @@
+use std::iter::zip;
+use tch::{Kind, Tensor};
+use crate::utils::PolicyIndex;
+use crate::combined_policy_value_network::CombinedPolicyValueNetwork;
+use crate::conv_net::{ConvNet};
+use crate::utils::{state_to_tensor, DEVICE};
+use dunck_engine::evaluation::{Evaluation, Evaluator};
+use dunck_engine::evaluators::factory::register_conv_net_loader;
+use dunck_core::r#move::Move;
+use dunck_core::state::State;
+
+#[derive(Debug)]
+pub struct ConvNetEvaluator {
+    pub model: ConvNet,
+}
+
+impl ConvNetEvaluator {
+    pub fn new(num_residual_blocks: usize, num_filters: i64) -> ConvNetEvaluator {
+        let model = ConvNet::new(*DEVICE, num_residual_blocks, num_filters);
+
+        ConvNetEvaluator {
+            model,
+        }
+    }
+}
+
+/// Loads a `ConvNetEvaluator` from `model_path`, as the evaluator factory's `ConvNetLoader`.
+pub fn load_conv_net_evaluator(model_path: &str, num_residual_blocks: usize, num_filters: i64) -> Result<Box<dyn Evaluator>, String> {
+    let mut evaluator = ConvNetEvaluator::new(num_residual_blocks, num_filters);
+    evaluator.model.load(model_path).map_err(|e| e.to_string())?;
+    Ok(Box::new(evaluator))
+}
+
+/// Lets `dunck_engine`'s evaluator factory load conv nets, making them the default evaluator.
+/// Does nothing if called again.
+pub fn register() {
+    register_conv_net_loader(load_conv_net_evaluator);
+}
+
+/// Returns the priors of the legal moves of `state`, the softmax of their logits in row `batch_index` of `policy_logits`.
+fn calc_priors(state: &State, policy_logits: &Tensor, batch_index: i64) -> Vec<(Move, f64)> {
+    let legal_moves = state.calc_legal_moves();
+    let legal_moves_policy_logits = Tensor::zeros(&[legal_moves.len() as i64], (Kind::Float, *DEVICE));
+
+    for (i, mv) in legal_moves.iter().enumerate() {
+        let policy_index = PolicyIndex::calc(mv, state.side_to_move);
+
+        let policy_logit = policy_logits.double_value(&[
+            batch_index,
+            policy_index.source_rank_index as i64,
+            policy_index.source_file_index as i64,
+            policy_index.move_index as i64
+        ]);
+
+        let _ = legal_moves_policy_logits.get(i as i64).fill_(policy_logit);
+    }
+
+    let priors = legal_moves_policy_logits.softmax(-1, Kind::Float);
+    let priors_vec = Vec::<f32>::try_from(priors).unwrap();
+
+    zip(legal_moves, priors_vec)
+        .map(|(mv, prior)| (mv, prior as f64))
+        .collect()
+}
+
+impl Evaluator for ConvNetEvaluator {
+    fn evaluate(&self, state: &State) -> Evaluation {
+        let state_tensor = state_to_tensor(state);
+        let input_tensor = Tensor::stack(&[state_tensor], 0).to_device(*DEVICE); // No batch, so stack along the first dimension
+        let (policy_logits, value_tensor) = self.model.forward_t(&input_tensor, false);
+
+        Evaluation {
+            policy: calc_priors(state, &policy_logits, 0),
+            value: value_tensor.double_value(&[]),
+        }
+    }
+
+    /// Evaluates all of `states` in a single forward pass.
+    fn evaluate_batch(&self, states: &[State]) -> Vec<Evaluation> {
+        if states.is_empty() {
+            return Vec::new();
+        }
+        let state_tensors: Vec<Tensor> = states.iter().map(state_to_tensor).collect();
+        let input_tensor = Tensor::stack(&state_tensors, 0).to_device(*DEVICE);
+        let (policy_logits, value_tensor) = self.model.forward_t(&input_tensor, false);
+
+        states.iter().enumerate().map(|(i, state)| Evaluation {
+            policy: calc_priors(state, &policy_logits, i as i64),
+            value: value_tensor.double_value(&[i as i64, 0]),
+        }).collect()
+    }
+
+    fn estimate_moves_left(&self, state: &State) -> Option<f64> {
+        self.model.moves_left_head.as_ref()?;
+        let input_tensor = Tensor::stack(&[state_to_tensor(state)], 0).to_device(*DEVICE);
+        let (_, _, moves_left) = self.model.forward_with_moves_left_t(&input_tensor, false);
+        moves_left.map(|moves_left| moves_left.double_value(&[0, 0]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use dunck_engine::evaluators::factory::{create_evaluator, get_default_evaluator_kind, ActiveEvaluator, EvaluatorConfig};
+    use dunck_engine::mcts::mcts::{calc_uct_score, MCTS};
+
+    #[test]
+    fn test_register() {
+        register();
+        register();
+        assert_eq!(get_default_evaluator_kind().to_string(), "convnet:model.safetensors");
+
+        let config = EvaluatorConfig { model_path: "does/not/exist.safetensors".to_string(), ..Default::default() };
+        let loaded = create_evaluator(&config);
+        assert_eq!(loaded.active, ActiveEvaluator::Material);
+        assert_eq!(loaded.warning, Some("model file does/not/exist.safetensors not found".to_string()));
+    }
+
+    #[test]
+    fn test_play_game() {
+        let evaluator = ConvNetEvaluator::new(4, 8);
+        let exploration_param = 1.5;
+        let mut mcts = MCTS::new(
+            State::initial(),
+            exploration_param,
+            &evaluator,
+            &calc_uct_score,
+            true
+        );
+        let result = mcts.play_game(400, 300);
+        for (state, evaluation) in mcts.state_evaluations.iter() {
+            println!("State: {}", state.board);
+            println!("Evaluation: {:?}", evaluation);
+        }
+        println!("Simulation result: {}", result);
+    }
+}
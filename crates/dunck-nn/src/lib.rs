@@ -0,0 +1,19 @@
+//! The conv net evaluator and its training, the only part of dunck depending on `tch` and libtorch.
+//! Call `register` at startup so that `dunck_engine`'s evaluator factory can load conv nets.
+
+pub mod conv_net_evaluator;
+pub mod conv_net;
+pub mod utils;
+pub mod residual_block;
+pub mod se_layer;
+pub mod policy_head;
+pub mod value_head;
+pub mod moves_left_head;
+pub mod attacked_squares_head;
+pub mod combined_policy_value_network;
+pub mod training;
+pub mod training_utils;
+pub mod racist_dummy_net;
+pub mod racist_dummy_evaluator;
+
+pub use conv_net_evaluator::register;
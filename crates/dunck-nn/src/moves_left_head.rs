@@ -0,0 +1,36 @@
+use tch::{nn, Tensor};
+use tch::nn::ModuleT;
+use crate::training_utils::print_tensor_stats;
+
+/// Predicts the number of plies left in the game, as a non-negative scalar per position.
+#[derive(Debug)]
+pub struct MovesLeftHead {
+    conv: nn::Conv2D,
+    bn: nn::BatchNorm,
+    fc1: nn::Linear,
+    fc2: nn::Linear,
+}
+
+impl MovesLeftHead {
+    pub fn new(vs: &nn::Path, num_filters: i64) -> Self {
+        MovesLeftHead {
+            conv: nn::conv2d(vs, num_filters, 8, 1, Default::default()),
+            bn: nn::batch_norm2d(vs, 8, Default::default()),
+            fc1: nn::linear(vs, 8 * 8 * 8, 128, Default::default()),
+            fc2: nn::linear(vs, 128, 1, Default::default()),
+        }
+    }
+
+    pub fn forward_t(&self, x: &Tensor, train: bool) -> Tensor {
+        let mut out = self.conv.forward_t(x, train);
+        out = self.bn.forward_t(&out, train).relu();
+        out = out.flatten(1, -1);
+        out = self.fc1.forward_t(&out, train).relu();
+
+        // softplus keeps the prediction non-negative without cutting off gradients
+        out = self.fc2.forward_t(&out, train).softplus();
+        print_tensor_stats(&out, "Moves left output");
+
+        out
+    }
+}
@@ -1,7 +1,7 @@
 use tch::{nn, Kind, Tensor};
 use tch::nn::ModuleT;
-use crate::engine::evaluators::neural::constants::NUM_TARGET_SQUARE_POSSIBILITIES;
-use crate::engine::evaluators::neural::training_utils::print_tensor_stats;
+use dunck_engine::evaluators::neural::constants::NUM_TARGET_SQUARE_POSSIBILITIES;
+use crate::training_utils::print_tensor_stats;
 
 #[derive(Debug)]
 pub struct PolicyHead {
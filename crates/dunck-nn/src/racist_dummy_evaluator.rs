@@ -1,10 +1,10 @@
 use std::iter::zip;
 use tch::{Kind, Tensor};
-use crate::engine::evaluation::{Evaluation, Evaluator};
-use crate::engine::evaluators::neural::combined_policy_value_network::CombinedPolicyValueNetwork;
-use crate::engine::evaluators::neural::racist_dummy_net::RacistDummyNet;
-use crate::engine::evaluators::neural::utils::{state_to_tensor, PolicyIndex, DEVICE};
-use crate::state::State;
+use dunck_engine::evaluation::{Evaluation, Evaluator};
+use crate::combined_policy_value_network::CombinedPolicyValueNetwork;
+use crate::racist_dummy_net::RacistDummyNet;
+use crate::utils::{state_to_tensor, PolicyIndex, DEVICE};
+use dunck_core::state::State;
 
 pub struct RacistDummyEvaluator {
     pub model: RacistDummyNet
@@ -1,7 +1,7 @@
 use tch::{Kind, Tensor};
-use crate::engine::evaluators::neural::combined_policy_value_network::CombinedPolicyValueNetwork;
-use crate::engine::evaluators::neural::constants::{NUM_BITS_PER_BOARD, NUM_POSITION_BITS, NUM_TARGET_SQUARE_POSSIBILITIES};
-use crate::engine::evaluators::neural::utils::DEVICE;
+use crate::combined_policy_value_network::CombinedPolicyValueNetwork;
+use dunck_engine::evaluators::neural::constants::{NUM_BOARD_BITS, NUM_POSITION_BITS, NUM_TARGET_SQUARE_POSSIBILITIES};
+use crate::utils::DEVICE;
 
 pub struct RacistDummyNet {
     pub white_value_output: f64,
@@ -24,7 +24,7 @@ impl CombinedPolicyValueNetwork for RacistDummyNet {
         let value = Tensor::zeros(&[batch_size, 1], (Kind::Float, *DEVICE));
 
         // Get the side to move from the input tensor (channel 12)
-        let side_to_move_channel = input.narrow(1, NUM_BITS_PER_BOARD as i64, 1);  // Get the side-to-move channel
+        let side_to_move_channel = input.narrow(1, NUM_BOARD_BITS as i64, 1);  // Get the side-to-move channel
 
         for i in 0..batch_size {
             let side_to_move_values = side_to_move_channel.get(i);
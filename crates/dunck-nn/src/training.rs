@@ -1,10 +1,11 @@
 use tch::{nn, Kind, Tensor};
-use crate::engine::evaluation::Evaluation;
-use crate::engine::evaluators::neural::combined_policy_value_network::CombinedPolicyValueNetwork;
-use crate::engine::evaluators::neural::constants::{NUM_POSITION_BITS, NUM_TARGET_SQUARE_POSSIBILITIES};
-use crate::engine::evaluators::neural::conv_net::ConvNet;
-use crate::engine::evaluators::neural::utils::{state_to_tensor, PolicyIndex, DEVICE};
-use crate::state::State;
+use dunck_engine::evaluation::Evaluation;
+use dunck_engine::evaluators::neural::auxiliary_targets::{get_attacked_squares_target, AuxiliaryExample};
+use crate::combined_policy_value_network::CombinedPolicyValueNetwork;
+use dunck_engine::evaluators::neural::constants::{NUM_ATTACKED_SQUARES_PLANES, NUM_OUTPUT_POLICY_MOVES, NUM_POSITION_BITS, NUM_TARGET_SQUARE_POSSIBILITIES};
+use crate::conv_net::ConvNet;
+use crate::utils::{state_to_tensor, PolicyIndex, DEVICE};
+use dunck_core::state::State;
 
 pub struct LossMetrics {
     pub policy_loss: f64,
@@ -81,6 +82,127 @@ pub fn train_batch(
     run_model(model, Some(optimizer), batch_data)
 }
 
+/// Trains the moves-left head on positions labelled with the number of plies left in their game,
+/// returning the loss. Panics if the model has no moves-left head.
+pub fn train_moves_left_batch(
+    model: &ConvNet,
+    optimizer: &mut nn::Optimizer,
+    batch_data: &[(State, f64)],
+) -> f64 {
+    assert!(!batch_data.is_empty());
+
+    let batch_states: Vec<Tensor> = batch_data.iter().map(|(state, _)| state_to_tensor(state)).collect();
+    let input_states = Tensor::stack(&batch_states, 0).to_kind(Kind::Float).to_device(*DEVICE);
+    let plies_left: Vec<f32> = batch_data.iter().map(|(_, plies_left)| *plies_left as f32).collect();
+    let expected_moves_left = Tensor::from_slice(&plies_left).view([-1, 1]).to_device(*DEVICE);
+
+    let (_, _, predicted_moves_left) = model.forward_with_moves_left_t(&input_states, true);
+    let predicted_moves_left = predicted_moves_left.expect("Model has no moves-left head");
+    assert_eq!(predicted_moves_left.size(), expected_moves_left.size());
+
+    // Huber loss, since game lengths have a long tail that would dominate a squared error
+    let loss = predicted_moves_left.smooth_l1_loss(&expected_moves_left, tch::Reduction::Mean, 1.0);
+
+    optimizer.zero_grad();
+    loss.backward();
+    optimizer.step();
+
+    loss.double_value(&[])
+}
+
+/// How much each auxiliary loss adds to the total loss.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AuxiliaryLossWeights {
+    pub attacked_squares: f64,
+    pub opponent_reply: f64,
+}
+
+impl Default for AuxiliaryLossWeights {
+    fn default() -> Self {
+        AuxiliaryLossWeights {
+            attacked_squares: 0.1,
+            opponent_reply: 0.1,
+        }
+    }
+}
+
+/// The losses of a batch trained with auxiliary targets.
+/// Auxiliary losses are `None` if the model does not have the corresponding head.
+pub struct AuxiliaryLossMetrics {
+    pub policy_loss: f64,
+    pub value_loss: f64,
+    pub attacked_squares_loss: Option<f64>,
+    pub opponent_reply_loss: Option<f64>,
+    pub total_loss: f64,
+}
+
+/// Like `train_batch`, but also trains whichever auxiliary heads the model has,
+/// with `weights` scaling their losses.
+pub fn train_auxiliary_batch(
+    model: &ConvNet,
+    optimizer: &mut nn::Optimizer,
+    batch_data: &[(AuxiliaryExample, Evaluation)],
+    weights: &AuxiliaryLossWeights,
+) -> AuxiliaryLossMetrics {
+    assert!(!batch_data.is_empty());
+
+    let main_batch_data: Vec<(State, Evaluation)> = batch_data.iter()
+        .map(|(example, evaluation)| (example.state.clone(), evaluation.clone()))
+        .collect();
+    let (input_states, expected_policies, expected_values) = create_batch_tensors(&main_batch_data);
+
+    let (predicted_policies, predicted_values, auxiliary) = model.forward_with_auxiliary_t(&input_states, true);
+
+    let policy_loss = predicted_policies.cross_entropy_loss::<Tensor>(&expected_policies, None, tch::Reduction::Mean, -100, 0.);
+    let value_loss = predicted_values.mse_loss(&expected_values, tch::Reduction::Mean);
+    let mut total_loss = &policy_loss + &value_loss;
+
+    let attacked_squares_loss = auxiliary.attacked_squares.map(|predicted_attacked_squares| {
+        let targets: Vec<f32> = batch_data.iter()
+            .flat_map(|(example, _)| get_attacked_squares_target(&example.state).into_iter().flatten().flatten())
+            .collect();
+        let expected_attacked_squares = Tensor::from_slice(&targets)
+            .view([-1, NUM_ATTACKED_SQUARES_PLANES as i64, 8, 8])
+            .to_device(*DEVICE);
+        predicted_attacked_squares.binary_cross_entropy_with_logits::<Tensor>(&expected_attacked_squares, None, None, tch::Reduction::Mean)
+    });
+    if let Some(loss) = &attacked_squares_loss {
+        total_loss = total_loss + loss * weights.attacked_squares;
+    }
+
+    // positions whose game ended before the opponent replied are ignored
+    let has_replies = batch_data.iter().any(|(example, _)| example.opponent_reply.is_some());
+    let opponent_reply_loss = auxiliary.opponent_reply.filter(|_| has_replies).map(|predicted_replies| {
+        let targets: Vec<i64> = batch_data.iter().map(|(example, _)| match example.opponent_reply {
+            Some(reply) => {
+                let policy_index = PolicyIndex::calc(&reply, example.state.side_to_move.flip());
+                (policy_index.source_rank_index as i64 * 8 + policy_index.source_file_index as i64) * NUM_TARGET_SQUARE_POSSIBILITIES as i64
+                    + policy_index.move_index as i64
+            },
+            None => -100,
+        }).collect();
+        let expected_replies = Tensor::from_slice(&targets).to_device(*DEVICE);
+        predicted_replies
+            .view([-1, NUM_OUTPUT_POLICY_MOVES as i64])
+            .cross_entropy_loss::<Tensor>(&expected_replies, None, tch::Reduction::Mean, -100, 0.)
+    });
+    if let Some(loss) = &opponent_reply_loss {
+        total_loss = total_loss + loss * weights.opponent_reply;
+    }
+
+    optimizer.zero_grad();
+    total_loss.backward();
+    optimizer.step();
+
+    AuxiliaryLossMetrics {
+        policy_loss: policy_loss.double_value(&[]),
+        value_loss: value_loss.double_value(&[]),
+        attacked_squares_loss: attacked_squares_loss.map(|loss| loss.double_value(&[])),
+        opponent_reply_loss: opponent_reply_loss.map(|loss| loss.double_value(&[])),
+        total_loss: total_loss.double_value(&[]),
+    }
+}
+
 /// Create batch tensors for states, policies, and values
 pub fn create_batch_tensors(training_data: &[(State, Evaluation)]) -> (Tensor, Tensor, Tensor) {
     let mut batch_states = Vec::new();
@@ -136,22 +258,24 @@ pub fn create_batch_tensors(training_data: &[(State, Evaluation)]) -> (Tensor, T
 
 #[cfg(test)]
 mod tests {
-    use engine::evaluation::Evaluator;
+    use dunck_engine::evaluation::{Evaluation, Evaluator};
     use tch::{nn, Kind, Tensor};
     use tch::nn::OptimizerConfig;
-    use r#move::{Move, MoveFlag};
-    use state::State;
-    use utils::{Square};
-    use crate::*;
-    use crate::engine::evaluation::Evaluation;
-    use crate::engine::evaluators::neural::constants::NUM_TARGET_SQUARE_POSSIBILITIES;
-    use crate::engine::evaluators::neural::conv_net_evaluator::ConvNetEvaluator;
-    use crate::engine::evaluators::neural::racist_dummy_evaluator::RacistDummyEvaluator;
-    use crate::engine::evaluators::neural::racist_dummy_net::RacistDummyNet;
-    use crate::engine::evaluators::neural::training::{compute_loss, train_batch, LossMetrics};
-    use crate::engine::evaluators::neural::training_utils::{extract_pgns, get_labeled_random_batch_from_pgns};
-    use crate::engine::evaluators::neural::utils::{PolicyIndex, DEVICE};
-    use crate::utils::Color;
+    use dunck_core::r#move::{Move, MoveFlag};
+    use dunck_core::state::State;
+    use dunck_core::utils::Square;
+    use dunck_engine::evaluators::neural::constants::NUM_TARGET_SQUARE_POSSIBILITIES;
+    use crate::conv_net_evaluator::ConvNetEvaluator;
+    use crate::racist_dummy_evaluator::RacistDummyEvaluator;
+    use crate::racist_dummy_net::RacistDummyNet;
+    use crate::conv_net::ConvNet;
+    use dunck_engine::evaluators::neural::net_config::NetConfig;
+    use dunck_engine::evaluators::neural::auxiliary_targets::get_auxiliary_examples_from_state_tree;
+    use crate::training::{compute_loss, train_auxiliary_batch, train_batch, train_moves_left_batch, AuxiliaryLossWeights, LossMetrics};
+    use crate::training_utils::{extract_pgns, get_labeled_random_batch_from_pgns, get_moves_left_examples_from_state_tree};
+    use dunck_core::pgn::PgnStateTree;
+    use crate::utils::{PolicyIndex, DEVICE};
+    use dunck_core::utils::Color;
 
     const NUM_RESIDUAL_BLOCKS: usize = 10;
     const NUM_FILTERS: i64 = 256;
@@ -514,4 +638,50 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_training_moves_left() {
+        let model = ConvNet::from_config(*DEVICE, NetConfig::new(2, 32).with_moves_left_head());
+        let mut optimizer = nn::Adam::default().build(&model.vs, 0.005).unwrap();
+
+        let state_tree: PgnStateTree = "1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7#".parse().unwrap();
+        let examples = get_moves_left_examples_from_state_tree(&state_tree);
+        assert_eq!(examples.len(), 8);
+        assert_eq!(examples[0].1, 7.);
+        assert_eq!(examples[7].1, 0.);
+
+        let initial_loss = train_moves_left_batch(&model, &mut optimizer, &examples);
+        let mut loss = initial_loss;
+        for _ in 0..50 {
+            loss = train_moves_left_batch(&model, &mut optimizer, &examples);
+        }
+        assert!(loss < initial_loss);
+    }
+
+    #[test]
+    fn test_training_auxiliary_heads() {
+        let model = ConvNet::from_config(*DEVICE, NetConfig::new(2, 32).with_auxiliary_heads(true, true));
+        let mut optimizer = nn::Adam::default().build(&model.vs, 0.005).unwrap();
+
+        let state_tree: PgnStateTree = "1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7#".parse().unwrap();
+        let batch = get_auxiliary_examples_from_state_tree(&state_tree).into_iter().map(|example| {
+            let evaluation = Evaluation {
+                policy: example.state.calc_legal_moves().into_iter().take(1).map(|mv| (mv, 1.0)).collect(),
+                value: 0.0,
+            };
+            (example, evaluation)
+        }).collect::<Vec<_>>();
+
+        let weights = AuxiliaryLossWeights::default();
+        let initial_metrics = train_auxiliary_batch(&model, &mut optimizer, &batch, &weights);
+        assert!(initial_metrics.attacked_squares_loss.is_some());
+        assert!(initial_metrics.opponent_reply_loss.is_some());
+
+        let mut metrics = initial_metrics;
+        let initial_attacked_squares_loss = metrics.attacked_squares_loss.unwrap();
+        for _ in 0..50 {
+            metrics = train_auxiliary_batch(&model, &mut optimizer, &batch, &weights);
+        }
+        assert!(metrics.attacked_squares_loss.unwrap() < initial_attacked_squares_loss);
+    }
+}
@@ -1,12 +1,13 @@
+use std::io::BufRead;
 use std::str::FromStr;
 use rand::prelude::{SliceRandom, ThreadRng};
 use rand::Rng;
 use tch::{Kind, Tensor};
-use crate::engine::evaluation::Evaluation;
-use crate::pgn::PgnStateTree;
-use crate::r#move::Move;
-use crate::state::{State, Termination};
-use crate::utils::{Color, ColoredPiece, PieceType};
+use dunck_engine::evaluation::Evaluation;
+use dunck_core::pgn::{PgnParseError, PgnReader, PgnStateTree};
+use dunck_core::r#move::Move;
+use dunck_core::state::{State, Termination};
+use dunck_core::utils::{Color, ColoredPiece, PieceType};
 
 pub fn print_tensor_stats(tensor: &Tensor, message: &str) {
     println!("{}", message);
@@ -59,6 +60,28 @@ pub fn get_labeled_random_batch_from_pgns(
     data
 }
 
+/// Sample a batch of data from the next games of a streamed PGN database, one position per game.
+/// Games that fail to parse or yield no example are skipped; the batch is short if the stream ends or fails to read.
+pub fn get_labeled_batch_from_pgn_reader<R: BufRead>(
+    reader: &mut PgnReader<R>,
+    num_samples: usize,
+    random_state: &mut ThreadRng
+) -> Vec<(State, Evaluation)> {
+    let mut data = Vec::with_capacity(num_samples);
+    while data.len() < num_samples {
+        let state_tree = match reader.next() {
+            Some(Ok(state_tree)) => state_tree,
+            Some(Err(PgnParseError::Io(_))) | None => break,
+            Some(Err(_)) => continue,
+        };
+
+        if let Some(example) = get_random_example_from_state_tree(state_tree, random_state) {
+            data.push(example);
+        }
+    }
+    data
+}
+
 fn sigmoid(x: f64) -> f64 {
     2.0 / (1.0 + (-0.5 * x).exp()) - 1.0
 }
@@ -150,4 +173,25 @@ pub fn get_random_example_from_state_tree(state_tree: PgnStateTree, rng: &mut Th
     // println!("Value: {}", value);
 
     Some((initial_state, Evaluation { policy, value }))
-}
\ No newline at end of file
+}
+
+/// Labels every position on the main line of a game with the number of plies left until it ended,
+/// the target of the moves-left head.
+pub fn get_moves_left_examples_from_state_tree(state_tree: &PgnStateTree) -> Vec<(State, f64)> {
+    let mut states = Vec::new();
+    let mut current_node = state_tree.head.clone();
+    loop {
+        states.push(current_node.borrow().state_after_move.clone());
+        let next_node = current_node.borrow().next_main_node();
+        match next_node {
+            Some(next_node) => current_node = next_node,
+            None => break,
+        }
+    }
+
+    let num_states = states.len();
+    states.into_iter()
+        .enumerate()
+        .map(|(i, state)| (state, (num_states - 1 - i) as f64))
+        .collect()
+}
@@ -1,9 +1,9 @@
 use static_init::dynamic;
 use tch::{Device, Kind, Tensor};
-use crate::engine::evaluators::neural::constants::{MAX_RAY_LENGTH, NUM_BITS_PER_BOARD, NUM_PIECE_TYPE_BITS, NUM_POSITION_BITS, NUM_QUEEN_LIKE_MOVES, NUM_SIDE_TO_MOVE_BITS, NUM_UNDERPROMOTIONS, NUM_WAYS_OF_UNDERPROMOTION};
-use crate::r#move::{Move, MoveFlag};
-use crate::state::State;
-use crate::utils::{get_squares_from_mask_iter, Color, KnightMoveDirection, PieceType, QueenLikeMoveDirection, Square};
+use dunck_engine::evaluators::neural::constants::{MAX_RAY_LENGTH, NUM_BITS_PER_BOARD, NUM_BOARD_BITS, NUM_PIECE_TYPE_BITS, NUM_STATES_LOOKBACK, NUM_POSITION_BITS, NUM_QUEEN_LIKE_MOVES, NUM_SIDE_TO_MOVE_BITS, NUM_UNDERPROMOTIONS, NUM_WAYS_OF_UNDERPROMOTION};
+use dunck_core::r#move::{Move, MoveFlag};
+use dunck_core::state::{Board, State};
+use dunck_core::utils::{get_squares_from_mask_iter, Color, KnightMoveDirection, PieceType, QueenLikeMoveDirection, Square};
 
 #[dynamic(lazy)]
 pub static DEVICE: Device = Device::Cpu;
@@ -98,13 +98,13 @@ const fn calc_move_index(src_square_from_current_perspective: Square,
     }
 }
 
-/// Fills the tensor channels for a given color's pieces.
+/// Fills the tensor channels for a given color's pieces on `board`, from the perspective of `perspective`.
 /// `offset` determines the starting channel for this color's pieces in the tensor.
-fn fill_pieces_for_color(tensor: &mut Tensor, state: &State, color: Color, offset: i64) {
+fn fill_pieces_for_color(tensor: &mut Tensor, board: &Board, perspective: Color, color: Color, offset: i64) {
     for piece_type in PieceType::iter_pieces() {
-        let mask = state.board.color_masks[color as usize] & state.board.piece_type_masks[*piece_type as usize];
+        let mask = board.color_masks[color as usize] & board.piece_type_masks[*piece_type as usize];
         for square in get_squares_from_mask_iter(mask) {
-            let square_from_perspective = square.to_perspective_from_white(state.side_to_move);
+            let square_from_perspective = square.to_perspective_from_white(perspective);
             let unshifted_channel_index = *piece_type as i64 - PieceType::Pawn as i64;
             assert!(unshifted_channel_index >= 0 && unshifted_channel_index < NUM_PIECE_TYPE_BITS as i64);
             let channel_index = offset + unshifted_channel_index;
@@ -117,17 +117,29 @@ fn fill_pieces_for_color(tensor: &mut Tensor, state: &State, color: Color, offse
     }
 }
 
-fn fill_pieces(tensor: &mut Tensor, state: &State) {
+/// Fills the channels for one board, always from the perspective of the current side to move.
+fn fill_board(tensor: &mut Tensor, board: &Board, side_to_move: Color, offset: i64) {
     // Channels 0-5: Player's pieces
-    fill_pieces_for_color(tensor, state, state.side_to_move, 0);
+    fill_pieces_for_color(tensor, board, side_to_move, side_to_move, offset);
 
     // Channels 6-11: Opponent's pieces
-    fill_pieces_for_color(tensor, state, state.side_to_move.flip(), NUM_PIECE_TYPE_BITS as i64);
+    fill_pieces_for_color(tensor, board, side_to_move, side_to_move.flip(), offset + NUM_PIECE_TYPE_BITS as i64);
+}
+
+/// Fills the channels for the current board, followed by the channels for each of the previous
+/// NUM_STATES_LOOKBACK boards. Boards from before the start of the game history are left empty.
+fn fill_pieces(tensor: &mut Tensor, state: &State) {
+    fill_board(tensor, &state.board, state.side_to_move, 0);
+
+    let recent_boards = state.get_recent_boards(NUM_STATES_LOOKBACK as usize);
+    for (i, board) in recent_boards.iter().enumerate() {
+        fill_board(tensor, board, state.side_to_move, (i + 1) as i64 * NUM_BITS_PER_BOARD as i64);
+    }
 }
 
 fn fill_side_to_move(tensor: &mut Tensor, side_to_move: Color) {
     let val = if side_to_move == Color::White { 1. } else { 0. };
-    let _ = tensor.get(NUM_BITS_PER_BOARD as i64).fill_(
+    let _ = tensor.get(NUM_BOARD_BITS as i64).fill_(
         val
     );
 }
@@ -135,25 +147,25 @@ fn fill_side_to_move(tensor: &mut Tensor, side_to_move: Color) {
 fn fill_castling_rights(tensor: &mut Tensor, castling_rights: u8) { // todo: account for perspective
     for (i, bit) in [0b1000, 0b0100, 0b0010, 0b0001].iter().enumerate() {
         let val = if castling_rights & bit != 0 { 1. } else { 0. };
-        let _ = tensor.get((NUM_BITS_PER_BOARD + NUM_SIDE_TO_MOVE_BITS + i as u8) as i64).fill_(
+        let _ = tensor.get((NUM_BOARD_BITS + NUM_SIDE_TO_MOVE_BITS + i as u8) as i64).fill_(
             val
         );
     }
 }
 
 pub fn state_to_tensor(state: &State) -> Tensor {
-    // Initialize a tensor with shape [17, 8, 8], where:
-    // - 17 is the number of channels
+    // Initialize a tensor with shape [NUM_POSITION_BITS, 8, 8], where:
+    // - NUM_POSITION_BITS is the number of channels (17 with no lookback)
     // - 8x8 is the board size
     let mut tensor = Tensor::zeros(&[NUM_POSITION_BITS as i64, 8, 8], (Kind::Float, *DEVICE));
     
-    // Channels 0-11: Pieces
+    // Channels 0-11: Pieces, then 12 more channels per previous board
     fill_pieces(&mut tensor, state);
 
-    // Channel 12: Side to move (1 if white to move, 0 if black to move)
+    // Channel NUM_BOARD_BITS: Side to move (1 if white to move, 0 if black to move)
     fill_side_to_move(&mut tensor, state.side_to_move);
 
-    // Channel 13-16: Castling rights
+    // Next 4 channels: Castling rights
     fill_castling_rights(&mut tensor, state.context.borrow().castling_rights);
 
     tensor
@@ -162,8 +174,8 @@ pub fn state_to_tensor(state: &State) -> Tensor {
 #[cfg(test)]
 mod tests {
     use std::collections::HashSet;
-    use crate::attacks::{single_bishop_attacks, single_knight_attacks, single_rook_attacks};
-    use crate::engine::evaluators::neural::constants::{MAX_NUM_KNIGHT_MOVES, NUM_PAWN_MOVE_DIRECTIONS, NUM_TARGET_SQUARE_POSSIBILITIES};
+    use dunck_core::attacks::{single_bishop_attacks, single_knight_attacks, single_rook_attacks};
+    use dunck_engine::evaluators::neural::constants::{MAX_NUM_KNIGHT_MOVES, NUM_PAWN_MOVE_DIRECTIONS, NUM_TARGET_SQUARE_POSSIBILITIES};
     use super::*;
 
     #[test]
@@ -1,6 +1,6 @@
 use tch::{nn, Kind, Tensor};
 use tch::nn::ModuleT;
-use crate::engine::evaluators::neural::training_utils::print_tensor_stats;
+use crate::training_utils::print_tensor_stats;
 
 #[derive(Debug)]
 pub struct ValueHead {
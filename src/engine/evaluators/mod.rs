@@ -1,3 +0,0 @@
-pub mod material_simple;
-pub mod random_rollout;
-pub mod neural;
\ No newline at end of file
@@ -1,2 +0,0 @@
-pub mod mcts;
-pub mod mcts_node;
\ No newline at end of file
@@ -1,4 +0,0 @@
-pub mod mcts;
-pub mod evaluation;
-pub mod evaluators;
-pub mod uci;
\ No newline at end of file
@@ -1,6 +0,0 @@
-pub mod attacks;
-pub mod engine;
-pub mod r#move;
-pub mod pgn;
-pub mod state;
-pub mod utils;
\ No newline at end of file
@@ -1,14 +0,0 @@
-mod state_tree_node;
-mod state_tree_traverser;
-mod render;
-mod parse;
-mod tokenize;
-mod error;
-mod state_tree;
-
-pub use render::*;
-pub use parse::*;
-pub use tokenize::*;
-pub use error::*;
-pub use state_tree::*;
-pub use state_tree_traverser::*;
@@ -1,21 +0,0 @@
-//! This module contains game state related code.
-
-mod board;
-mod context;
-mod termination;
-mod make_move;
-mod movegen;
-mod unmake_move;
-mod zobrist;
-mod fen;
-mod state;
-
-pub use state::*;
-pub use board::*;
-pub use context::*;
-pub use termination::*;
-pub use make_move::*;
-pub use movegen::*;
-pub use unmake_move::*;
-pub use zobrist::*;
-pub use fen::*;